@@ -0,0 +1,143 @@
+//! Account decoding
+//!
+//! Mailer accounts are an 8-byte discriminator followed by the Borsh-encoded state.
+//! Decoding checks the owner and discriminator against [`mailer::discriminator`] so a
+//! mismatched account is reported instead of silently misread.
+
+use borsh::BorshDeserialize;
+use mailer::state_versions::VersionedMailerState;
+use mailer::{
+    layout, ClaimRentPayer, ClosureLog, DelegateIndex, Delegation, DiscountSchedule, EvmMapping,
+    FeeDiscount, FeeMint, InboxPolicy, MailerState, MailerStats, MessageNonce, PayoutConfig,
+    Preauthorization, Promo, RateLimit, RecipientClaim, RecipientInbox, Sanctioned, SendNonce,
+    SenderStats, Webhook,
+};
+use solana_sdk::{account::Account, pubkey::Pubkey};
+
+use crate::ClientError;
+
+/// A Mailer account type with a known layout name
+pub trait MailerAccount: BorshDeserialize {
+    /// Account name used for the discriminator, as listed in [`mailer::layout::accounts`]
+    const NAME: &'static str;
+
+    /// Decode the data following the discriminator
+    fn decode_data(data: &[u8]) -> std::io::Result<Self> {
+        Self::deserialize(&mut &data[..])
+    }
+}
+
+macro_rules! mailer_account {
+    ($($ty:ident),*) => {
+        $(impl MailerAccount for $ty {
+            const NAME: &'static str = stringify!($ty);
+        })*
+    };
+}
+
+/// Claims not yet upgraded to the current layout decode with zeroed counters
+impl MailerAccount for RecipientClaim {
+    const NAME: &'static str = "RecipientClaim";
+
+    fn decode_data(data: &[u8]) -> std::io::Result<Self> {
+        RecipientClaim::from_account_data(data)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))
+    }
+}
+
+/// Policies created before minimum fees decode with no minimum
+impl MailerAccount for InboxPolicy {
+    const NAME: &'static str = "InboxPolicy";
+
+    fn decode_data(data: &[u8]) -> std::io::Result<Self> {
+        InboxPolicy::from_account_data(data)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))
+    }
+}
+
+mailer_account!(
+    MailerState,
+    Delegation,
+    FeeDiscount,
+    PayoutConfig,
+    Preauthorization,
+    RateLimit,
+    Promo,
+    FeeMint,
+    RecipientInbox,
+    ClaimRentPayer,
+    Webhook,
+    ClosureLog,
+    Sanctioned,
+    DelegateIndex,
+    MailerStats,
+    EvmMapping,
+    MessageNonce,
+    SendNonce,
+    DiscountSchedule,
+    SenderStats
+);
+
+/// Current discriminator for the account type named `name`
+pub fn discriminator(name: &str) -> Option<[u8; 8]> {
+    layout::accounts()
+        .into_iter()
+        .find(|l| l.name == name)
+        .map(|l| l.discriminator)
+}
+
+/// Decode raw account data, checking the discriminator
+/// Legacy discriminators of accounts not yet migrated are accepted as well.
+pub fn decode<T: MailerAccount>(address: &Pubkey, data: &[u8]) -> Result<T, ClientError> {
+    let invalid = || ClientError::InvalidDiscriminator {
+        address: *address,
+        expected: T::NAME,
+    };
+    match data.get(..8).and_then(mailer::discriminator::identify) {
+        Some((name, _)) if name == T::NAME => {}
+        _ => return Err(invalid()),
+    }
+    // Accounts are allocated at their maximum size, so trailing bytes are expected
+    Ok(T::decode_data(&data[8..])?)
+}
+
+/// Decode a fetched account, checking the owner and discriminator
+pub fn decode_account<T: MailerAccount>(
+    program_id: &Pubkey,
+    address: &Pubkey,
+    account: &Account,
+) -> Result<T, ClientError> {
+    if account.owner != *program_id {
+        return Err(ClientError::InvalidOwner { address: *address });
+    }
+    decode(address, &account.data)
+}
+
+/// Decode a fetched mailer state account in any layout version, checking the owner and
+/// discriminator
+/// A deployment still in an earlier layout decodes as `MigrateState` would upgrade it.
+pub fn decode_mailer_state_account(
+    program_id: &Pubkey,
+    address: &Pubkey,
+    account: &Account,
+) -> Result<MailerState, ClientError> {
+    if account.owner != *program_id {
+        return Err(ClientError::InvalidOwner { address: *address });
+    }
+    match account
+        .data
+        .get(..8)
+        .and_then(mailer::discriminator::identify)
+    {
+        Some((name, _)) if name == MailerState::NAME => {}
+        _ => {
+            return Err(ClientError::InvalidDiscriminator {
+                address: *address,
+                expected: MailerState::NAME,
+            })
+        }
+    }
+    VersionedMailerState::deserialize(&account.data[8..])
+        .map(VersionedMailerState::into_current)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()).into())
+}
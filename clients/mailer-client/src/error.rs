@@ -0,0 +1,51 @@
+use mailer::MailerError;
+use solana_sdk::{instruction::InstructionError, pubkey::Pubkey, transaction::TransactionError};
+use thiserror::Error;
+
+/// Errors returned by the Mailer client
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error("RPC request failed: {0}")]
+    Rpc(Box<solana_client::client_error::ClientError>),
+    #[error("Account {0} not found")]
+    AccountNotFound(Pubkey),
+    #[error("Account {address} is not owned by the Mailer program")]
+    InvalidOwner { address: Pubkey },
+    #[error("Account {address} is not a {expected} account")]
+    InvalidDiscriminator {
+        address: Pubkey,
+        expected: &'static str,
+    },
+    #[error("Failed to decode account data: {0}")]
+    Decode(#[from] std::io::Error),
+    #[error("Sender is not allowed by the recipient's inbox policy")]
+    SenderNotAllowed,
+    #[error("Recipient has blocked the sender")]
+    RecipientBlocked,
+    #[error("Priority send pays less than the recipient's minimum fee")]
+    RecipientFeeTooLow,
+    #[error("Math overflow")]
+    MathOverflow,
+}
+
+impl ClientError {
+    /// Mailer error that failed the transaction behind this error, if any
+    pub fn mailer_error(&self) -> Option<MailerError> {
+        let transaction_error = match self {
+            ClientError::Rpc(error) => error.get_transaction_error()?,
+            _ => return None,
+        };
+        match transaction_error {
+            TransactionError::InstructionError(_, InstructionError::Custom(code)) => {
+                MailerError::from_code(code)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl From<solana_client::client_error::ClientError> for ClientError {
+    fn from(e: solana_client::client_error::ClientError) -> Self {
+        ClientError::Rpc(Box::new(e))
+    }
+}
@@ -0,0 +1,83 @@
+//! Fee estimation
+//!
+//! Mirrors the program's fee path so a service can check USDC balances and approvals
+//! before sending: fee pause, matured pending fee, custom discount, inbox policy
+//! surcharge, then the 10% owner share for standard and email sends.
+
+use mailer::{FeeDiscount, InboxMode, InboxPolicy, MailerState};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::ClientError;
+
+/// Send fee before the standard/priority split: fee pause, matured pending fee and
+/// the sender's custom discount
+pub fn effective_send_fee(state: &MailerState, now: i64, discount: Option<&FeeDiscount>) -> u64 {
+    if state.fee_paused {
+        return 0;
+    }
+    let base_fee = state.effective_send_fee(now);
+    match discount.map(|d| d.discount) {
+        None | Some(0) => base_fee,
+        Some(100) => 0,
+        Some(discount) => (base_fee * (100 - discount as u64)) / 100,
+    }
+}
+
+/// Apply the recipient's inbox policy to `fee`
+pub fn apply_inbox_policy(
+    fee: u64,
+    sender: &Pubkey,
+    policy: Option<&InboxPolicy>,
+) -> Result<u64, ClientError> {
+    match policy {
+        Some(policy) if !policy.allows(sender) => {
+            if policy.penalty_multiplier == 0 {
+                return Err(match policy.mode {
+                    InboxMode::Blocklist => ClientError::RecipientBlocked,
+                    _ => ClientError::SenderNotAllowed,
+                });
+            }
+            fee.checked_mul(policy.penalty_multiplier as u64)
+                .ok_or(ClientError::MathOverflow)
+        }
+        _ => Ok(fee),
+    }
+}
+
+/// USDC charged by `Send`/`SendPrepared`/`SendThroughWebhook`, including the `tip` of a
+/// priority send
+/// Fails with `RecipientFeeTooLow` when a priority send pays less than the policy's minimum.
+pub fn send_cost(
+    state: &MailerState,
+    now: i64,
+    sender: &Pubkey,
+    discount: Option<&FeeDiscount>,
+    policy: Option<&InboxPolicy>,
+    revenue_share_to_receiver: bool,
+    tip: u64,
+) -> Result<u64, ClientError> {
+    let fee = apply_inbox_policy(effective_send_fee(state, now, discount), sender, policy)?;
+    if !revenue_share_to_receiver {
+        return Ok((fee * 10) / 100);
+    }
+    let cost = fee.checked_add(tip).ok_or(ClientError::MathOverflow)?;
+    if policy.is_some_and(|policy| cost < policy.min_fee) {
+        return Err(ClientError::RecipientFeeTooLow);
+    }
+    Ok(cost)
+}
+
+/// USDC charged by `SendToEmail`/`SendPreparedToEmail` (owner share only)
+pub fn email_send_cost(state: &MailerState, now: i64, discount: Option<&FeeDiscount>) -> u64 {
+    (effective_send_fee(state, now, discount) * 10) / 100
+}
+
+/// USDC charged by `DelegateTo`; clearing a delegation is free
+pub fn delegation_cost(state: &MailerState, delegate: Option<&Pubkey>) -> u64 {
+    match delegate {
+        Some(delegate) if *delegate != Pubkey::default() && !state.fee_paused => {
+            state.delegation_fee
+        }
+        _ => 0,
+    }
+}
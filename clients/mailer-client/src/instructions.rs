@@ -0,0 +1,1685 @@
+//! Instruction builders
+//!
+//! [`MailerAccounts`] holds the deployment-wide accounts (program id, mailer state PDA,
+//! USDC mint and the mailer's USDC vault) and builds instructions with the account
+//! metas each handler expects. User token accounts default to associated token
+//! accounts of the signer.
+
+use mailer::{
+    crypto::Envelope, hash_email, instruction_versions::Reserved, pda, sns, wormhole, ClaimSplit,
+    MailerInstruction, VolumeTier,
+};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program, sysvar,
+};
+
+/// SPL associated token account program
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+/// SPL token program
+pub const TOKEN_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+/// Associated token account of `owner` for `mint`
+pub fn associated_token_address(owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[owner.as_ref(), TOKEN_PROGRAM_ID.as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Arguments of a `Send` instruction
+#[derive(Debug, Clone)]
+pub struct SendArgs {
+    pub to: Pubkey,
+    pub subject: String,
+    pub body: String,
+    pub revenue_share_to_receiver: bool,
+    pub resolve_sender_to_name: bool,
+    pub request_read_receipt: bool,
+    pub receipt_nonce: Option<u64>,
+    pub memo: Option<String>,
+    pub strict: bool,
+    /// Conversation id logged with the send; not carried by `ClaimAndSend`
+    pub thread_id: Option<[u8; 32]>,
+    /// Message id of the message this one replies to; not carried by `ClaimAndSend`
+    pub in_reply_to: Option<[u8; 32]>,
+    /// Send a priority send as standard when the recipient's claim is frozen or capped;
+    /// not carried by `ClaimAndSend`
+    pub allow_downgrade: bool,
+    /// Amount added on top of a priority fee and credited in full to the recipient's claim;
+    /// not carried by `ClaimAndSend`
+    pub tip: u64,
+    /// Pass the sender's Subscription PDA so an active subscription covers the fee
+    pub use_subscription: bool,
+    /// Signer paying the recipient claim's rent instead of the sender; carried by `Send` and
+    /// `ClaimAndSend`
+    pub rent_payer: Option<Pubkey>,
+    /// Mirror the send through the Wormhole bridge as this message, the wormhole config's
+    /// current message count; needs the bridge set with
+    /// [`MailerAccounts::with_wormhole_bridge`]. Carried by `Send` only
+    pub bridge_message: Option<u64>,
+    /// Nonce making a retry of this send a no-op once it has landed, recorded in the sender's
+    /// SendNonce PDA. Carried by `Send` only
+    pub idempotency_nonce: Option<u64>,
+}
+
+impl SendArgs {
+    /// Standard send (owner fee only) without receipts or memo
+    pub fn new(to: Pubkey, subject: impl Into<String>, body: impl Into<String>) -> Self {
+        Self {
+            to,
+            subject: subject.into(),
+            body: body.into(),
+            revenue_share_to_receiver: false,
+            resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            use_subscription: false,
+            rent_payer: None,
+            bridge_message: None,
+            idempotency_nonce: None,
+        }
+    }
+
+    /// Priority send: full fee with the recipient's revenue share
+    pub fn priority(mut self) -> Self {
+        self.revenue_share_to_receiver = true;
+        self
+    }
+
+    /// Fail the send when the fee cannot be paid, instead of sending it unpaid
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    /// Log the send as part of the conversation `thread_id`
+    pub fn in_thread(mut self, thread_id: [u8; 32]) -> Self {
+        self.thread_id = Some(thread_id);
+        self
+    }
+
+    /// Log the send as a reply to the message with id `message_id`
+    pub fn in_reply_to(mut self, message_id: [u8; 32]) -> Self {
+        self.in_reply_to = Some(message_id);
+        self
+    }
+
+    /// Let a priority send go out as a standard send if the recipient's claim cannot take it
+    pub fn allow_downgrade(mut self) -> Self {
+        self.allow_downgrade = true;
+        self
+    }
+
+    /// Tip the recipient `tip` on top of a priority send's fee
+    pub fn tip(mut self, tip: u64) -> Self {
+        self.tip = tip;
+        self
+    }
+
+    /// Cover the fee with the sender's subscription while it is active
+    pub fn with_subscription(mut self) -> Self {
+        self.use_subscription = true;
+        self
+    }
+
+    /// Have `rent_payer`, which must also sign, pay the recipient claim's rent
+    pub fn with_rent_payer(mut self, rent_payer: Pubkey) -> Self {
+        self.rent_payer = Some(rent_payer);
+        self
+    }
+
+    /// Post the send as Wormhole message number `message` for the EVM deployments to mirror
+    pub fn bridged(mut self, message: u64) -> Self {
+        self.bridge_message = Some(message);
+        self
+    }
+
+    /// Make the send idempotent under `nonce`: a retry with the same nonce succeeds without
+    /// sending or charging again
+    pub fn idempotent(mut self, nonce: u64) -> Self {
+        self.idempotency_nonce = Some(nonce);
+        self
+    }
+}
+
+/// Deployment-wide accounts shared by every instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MailerAccounts {
+    pub program_id: Pubkey,
+    pub mailer: Pubkey,
+    pub usdc_mint: Pubkey,
+    pub mailer_usdc: Pubkey,
+    /// Spam oracle registered in the mailer state, passed to sends to a wallet
+    pub spam_oracle: Option<Pubkey>,
+    /// Whether the mailer state has a rate limit, so sends pass the sender's rate limit PDA
+    pub rate_limited: bool,
+    /// Price account of the fee oracle registered in the mailer state, passed to lamport sends
+    pub fee_oracle: Option<Pubkey>,
+    /// Whether the mailer state uses the RefundPayer claim creation policy, so priority sends
+    /// pass the claim's rent payer record
+    pub claim_rent_refunds: bool,
+    /// Whether the mailer state has sanctions enabled, so sends and claims pass the sanctions
+    /// registry entry of each acting address
+    pub sanctions_enabled: bool,
+    /// Whether the mailer state has stats enabled, so sends and claims pass the stats account
+    pub stats_enabled: bool,
+    /// Treasury USDC account registered in the mailer state, passed to sends and owner claims
+    pub treasury: Option<Pubkey>,
+    /// Wormhole core bridge set with `SetWormholeBridge`, whose accounts bridged sends pass
+    pub wormhole_bridge: Option<Pubkey>,
+    /// Whether a discount schedule is set, so sends pass it and the sender's stats account
+    pub volume_discounts: bool,
+}
+
+impl MailerAccounts {
+    /// Accounts for a deployment whose vault is the mailer PDA's associated token account
+    pub fn new(program_id: Pubkey, usdc_mint: Pubkey) -> Self {
+        let (mailer, _) = pda::mailer_state(&program_id);
+        Self {
+            program_id,
+            mailer,
+            usdc_mint,
+            mailer_usdc: associated_token_address(&mailer, &usdc_mint),
+            spam_oracle: None,
+            rate_limited: false,
+            fee_oracle: None,
+            claim_rent_refunds: false,
+            sanctions_enabled: false,
+            stats_enabled: false,
+            treasury: None,
+            wormhole_bridge: None,
+            volume_discounts: false,
+        }
+    }
+
+    /// Use an explicit mailer USDC vault instead of the associated token account
+    pub fn with_mailer_usdc(mut self, mailer_usdc: Pubkey) -> Self {
+        self.mailer_usdc = mailer_usdc;
+        self
+    }
+
+    /// Pass the registered spam oracle program with sends to a wallet
+    pub fn with_spam_oracle(mut self, spam_oracle: Pubkey) -> Self {
+        self.spam_oracle = Some(spam_oracle);
+        self
+    }
+
+    /// Pass the sender's rate limit account with sends, as required while a rate limit is set
+    pub fn with_rate_limit(mut self) -> Self {
+        self.rate_limited = true;
+        self
+    }
+
+    /// Pass the registered fee oracle's price account with sends paying in lamports
+    pub fn with_fee_oracle(mut self, fee_oracle: Pubkey) -> Self {
+        self.fee_oracle = Some(fee_oracle);
+        self
+    }
+
+    /// Pass the claim rent payer record with priority sends, as required under RefundPayer
+    pub fn with_claim_rent_refunds(mut self) -> Self {
+        self.claim_rent_refunds = true;
+        self
+    }
+
+    /// Pass sanctions registry entries with sends and claims, as required while sanctions are
+    /// enabled
+    pub fn with_sanctions(mut self) -> Self {
+        self.sanctions_enabled = true;
+        self
+    }
+
+    /// Pass the stats account with sends and claims, as required once it is initialized
+    pub fn with_stats(mut self) -> Self {
+        self.stats_enabled = true;
+        self
+    }
+
+    /// Pass the registered treasury USDC account with sends and owner claims
+    pub fn with_treasury(mut self, treasury: Pubkey) -> Self {
+        self.treasury = Some(treasury);
+        self
+    }
+
+    /// Pass the accounts of the Wormhole core bridge `core_bridge` with bridged sends
+    pub fn with_wormhole_bridge(mut self, core_bridge: Pubkey) -> Self {
+        self.wormhole_bridge = Some(core_bridge);
+        self
+    }
+
+    /// Pass the discount schedule and the sender's stats account with sends, so they earn and
+    /// count toward volume discounts
+    pub fn with_volume_discounts(mut self) -> Self {
+        self.volume_discounts = true;
+        self
+    }
+
+    /// Associated USDC account of `owner`
+    pub fn usdc_account(&self, owner: &Pubkey) -> Pubkey {
+        associated_token_address(owner, &self.usdc_mint)
+    }
+
+    fn instruction(&self, ix: MailerInstruction, accounts: Vec<AccountMeta>) -> Instruction {
+        Instruction::new_with_borsh(self.program_id, &ix, accounts)
+    }
+
+    /// Optional accounts read by the fee path: the sender's discount and the
+    /// recipient's inbox policy. Both are ignored by the program when uninitialized.
+    /// Sends to a wallet also pass the recipient's inbox, numbering the send, and the spam
+    /// oracle when one is registered; every send passes the sender's rate limit account
+    /// while a rate limit is set, its sanctions registry entry while sanctions are enabled,
+    /// the stats account while stats are enabled, the treasury once one is registered,
+    /// its message nonce account, numbering its message ids, and the discount schedule and its
+    /// sender stats while volume discounts are enabled.
+    fn fee_accounts(&self, sender: &Pubkey, to: Option<&Pubkey>) -> Vec<AccountMeta> {
+        let mut metas = vec![AccountMeta::new_readonly(
+            pda::fee_discount(&self.program_id, sender).0,
+            false,
+        )];
+        if self.rate_limited {
+            metas.push(AccountMeta::new(
+                pda::rate_limit(&self.program_id, sender).0,
+                false,
+            ));
+        }
+        metas.extend(self.sanctions_accounts(sender));
+        metas.extend(self.stats_accounts());
+        metas.extend(
+            self.treasury
+                .map(|treasury| AccountMeta::new(treasury, false)),
+        );
+        metas.push(AccountMeta::new(
+            pda::message_nonce(&self.program_id, sender).0,
+            false,
+        ));
+        if self.volume_discounts {
+            metas.push(AccountMeta::new_readonly(
+                pda::discount_schedule(&self.program_id).0,
+                false,
+            ));
+            metas.push(AccountMeta::new(
+                pda::sender_stats(&self.program_id, sender).0,
+                false,
+            ));
+        }
+        if let Some(to) = to {
+            metas.extend(self.sanctions_accounts(to));
+            metas.push(AccountMeta::new_readonly(
+                pda::inbox_policy(&self.program_id, to).0,
+                false,
+            ));
+            metas.push(AccountMeta::new(
+                pda::recipient_inbox(&self.program_id, to).0,
+                false,
+            ));
+            if let Some(oracle) = self.spam_oracle {
+                metas.push(AccountMeta::new_readonly(oracle, false));
+            }
+        }
+        metas
+    }
+
+    /// Optional trailing accounts of `Send` for the receipt, memo, read receipt,
+    /// subscription, bridging and idempotency nonce requested in `args`, followed by the fee
+    /// accounts
+    fn send_optional_accounts(&self, sender: &Pubkey, args: &SendArgs) -> Vec<AccountMeta> {
+        let mut metas = Vec::new();
+        if let Some(nonce) = args.receipt_nonce {
+            let (receipt, _) = pda::message_receipt(&self.program_id, &args.to, nonce);
+            metas.push(AccountMeta::new(receipt, false));
+        }
+        if args.memo.is_some() {
+            let (history, _) = pda::claim_history(&self.program_id, &args.to, sender);
+            metas.push(AccountMeta::new(history, false));
+        }
+        if args.request_read_receipt {
+            let (prefs, _) = pda::recipient_prefs(&self.program_id, &args.to);
+            metas.push(AccountMeta::new_readonly(prefs, false));
+        }
+        if args.use_subscription {
+            metas.push(AccountMeta::new(
+                pda::subscription(&self.program_id, sender).0,
+                false,
+            ));
+        }
+        if let Some(message) = args.bridge_message {
+            metas.extend(self.wormhole_accounts(message));
+        }
+        if args.idempotency_nonce.is_some() {
+            metas.push(AccountMeta::new(
+                pda::send_nonce(&self.program_id, sender).0,
+                false,
+            ));
+        }
+        metas.extend(self.fee_accounts(sender, Some(&args.to)));
+        metas
+    }
+
+    /// Accounts posting Wormhole message number `message` through the configured bridge
+    fn wormhole_accounts(&self, message: u64) -> Vec<AccountMeta> {
+        let core_bridge = match self.wormhole_bridge {
+            Some(core_bridge) => core_bridge,
+            None => return Vec::new(),
+        };
+        let (emitter, _) = pda::wormhole_emitter(&self.program_id);
+        let addresses = wormhole::BridgeAddresses::derive(&core_bridge, &emitter);
+        vec![
+            AccountMeta::new(pda::wormhole_config(&self.program_id).0, false),
+            AccountMeta::new_readonly(core_bridge, false),
+            AccountMeta::new(addresses.bridge, false),
+            AccountMeta::new(pda::wormhole_message(&self.program_id, message).0, false),
+            AccountMeta::new_readonly(emitter, false),
+            AccountMeta::new(addresses.sequence, false),
+            AccountMeta::new(addresses.fee_collector, false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ]
+    }
+
+    /// Sanctions registry entry of `address`, passed with sends and claims while sanctions
+    /// are enabled (for sends to a wallet, the recipient's too, so frozen claims are seen)
+    fn sanctions_accounts(&self, address: &Pubkey) -> Vec<AccountMeta> {
+        if !self.sanctions_enabled {
+            return Vec::new();
+        }
+        vec![AccountMeta::new_readonly(
+            pda::sanctioned(&self.program_id, address).0,
+            false,
+        )]
+    }
+
+    /// Stats account, passed with sends and claims while stats are enabled
+    fn stats_accounts(&self) -> Vec<AccountMeta> {
+        if !self.stats_enabled {
+            return Vec::new();
+        }
+        vec![AccountMeta::new(pda::stats(&self.program_id).0, false)]
+    }
+
+    /// Rent payer of a wallet send's recipient claim, following its fixed accounts: the
+    /// sponsor when one is given, otherwise the sender
+    fn rent_payer_account(&self, sender: &Pubkey, rent_payer: Option<Pubkey>) -> AccountMeta {
+        AccountMeta::new(rent_payer.unwrap_or(*sender), true)
+    }
+
+    /// Rent payer record of `claim`, passed with priority sends under RefundPayer
+    fn claim_rent_payer_accounts(&self, claim: &Pubkey, revenue_share: bool) -> Vec<AccountMeta> {
+        if !(revenue_share && self.claim_rent_refunds) {
+            return Vec::new();
+        }
+        vec![AccountMeta::new(
+            pda::claim_rent_payer(&self.program_id, claim).0,
+            false,
+        )]
+    }
+
+    /// `Send` paid from the sender's associated USDC account
+    pub fn send(&self, sender: &Pubkey, args: SendArgs) -> Instruction {
+        let (claim, _) = pda::recipient_claim(&self.program_id, &args.to);
+        let mut accounts = vec![
+            AccountMeta::new(*sender, true),
+            AccountMeta::new(claim, false),
+            AccountMeta::new(self.mailer, false),
+            AccountMeta::new(self.usdc_account(sender), false),
+            AccountMeta::new(self.mailer_usdc, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ];
+        accounts.push(self.rent_payer_account(sender, args.rent_payer));
+        accounts.extend(self.send_optional_accounts(sender, &args));
+        accounts.extend(self.claim_rent_payer_accounts(&claim, args.revenue_share_to_receiver));
+
+        self.instruction(
+            MailerInstruction::Send {
+                to: args.to,
+                subject: args.subject,
+                _body: args.body,
+                revenue_share_to_receiver: args.revenue_share_to_receiver,
+                resolve_sender_to_name: args.resolve_sender_to_name,
+                request_read_receipt: args.request_read_receipt,
+                receipt_nonce: args.receipt_nonce,
+                memo: args.memo,
+                strict: args.strict,
+                thread_id: args.thread_id,
+                in_reply_to: args.in_reply_to,
+                allow_downgrade: args.allow_downgrade,
+                tip: args.tip,
+                bridge: args.bridge_message.is_some(),
+                idempotency_nonce: args.idempotency_nonce,
+            },
+            accounts,
+        )
+    }
+
+    /// `SendPrepared` paid from the sender's associated USDC account
+    pub fn send_prepared(
+        &self,
+        sender: &Pubkey,
+        to: Pubkey,
+        mail_id: impl Into<String>,
+        revenue_share_to_receiver: bool,
+    ) -> Instruction {
+        let (claim, _) = pda::recipient_claim(&self.program_id, &to);
+        let mut accounts = vec![
+            AccountMeta::new(*sender, true),
+            AccountMeta::new(claim, false),
+            AccountMeta::new(self.mailer, false),
+            AccountMeta::new(self.usdc_account(sender), false),
+            AccountMeta::new(self.mailer_usdc, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ];
+        accounts.push(self.rent_payer_account(sender, None));
+        accounts.extend(self.fee_accounts(sender, Some(&to)));
+        accounts.extend(self.claim_rent_payer_accounts(&claim, revenue_share_to_receiver));
+
+        self.instruction(
+            MailerInstruction::SendPrepared {
+                to,
+                mail_id: mail_id.into(),
+                revenue_share_to_receiver,
+                resolve_sender_to_name: false,
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+            },
+            accounts,
+        )
+    }
+
+    /// `SendEncrypted` of an envelope sealed to `to`, paid from the sender's associated
+    /// USDC account
+    pub fn send_encrypted(
+        &self,
+        sender: &Pubkey,
+        to: Pubkey,
+        envelope: &Envelope,
+        revenue_share_to_receiver: bool,
+    ) -> Instruction {
+        let (claim, _) = pda::recipient_claim(&self.program_id, &to);
+        let mut accounts = vec![
+            AccountMeta::new(*sender, true),
+            AccountMeta::new(claim, false),
+            AccountMeta::new(self.mailer, false),
+            AccountMeta::new(self.usdc_account(sender), false),
+            AccountMeta::new(self.mailer_usdc, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ];
+        accounts.push(self.rent_payer_account(sender, None));
+        accounts.extend(self.fee_accounts(sender, Some(&to)));
+        accounts.extend(self.claim_rent_payer_accounts(&claim, revenue_share_to_receiver));
+
+        self.instruction(
+            MailerInstruction::SendEncrypted {
+                to,
+                ephemeral_key: envelope.ephemeral_key,
+                nonce: envelope.nonce,
+                ciphertext: envelope.ciphertext.clone(),
+                revenue_share_to_receiver,
+                strict: false,
+            },
+            accounts,
+        )
+    }
+
+    /// `SendToEmail` paid from the sender's associated USDC account
+    pub fn send_to_email(
+        &self,
+        sender: &Pubkey,
+        to_email: impl Into<String>,
+        subject: impl Into<String>,
+        body: impl Into<String>,
+    ) -> Instruction {
+        // The sender pays for their message nonce and rate limit accounts
+        let mut accounts = vec![
+            AccountMeta::new(*sender, true),
+            AccountMeta::new(self.mailer, false),
+            AccountMeta::new(self.usdc_account(sender), false),
+            AccountMeta::new(self.mailer_usdc, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        ];
+        accounts.extend(self.fee_accounts(sender, None));
+        accounts.push(AccountMeta::new_readonly(system_program::id(), false));
+
+        self.instruction(
+            MailerInstruction::SendToEmail {
+                to_email: to_email.into(),
+                subject: subject.into(),
+                _body: body.into(),
+                strict: false,
+            },
+            accounts,
+        )
+    }
+
+    /// `SendToEmailHash` to the address hashed with `salt` (see [`hash_email`]), paid
+    /// from the sender's associated USDC account; `hint_id` lets the delivery backend find the
+    /// salt and address
+    pub fn send_to_email_hash(
+        &self,
+        sender: &Pubkey,
+        email: &str,
+        salt: &[u8],
+        hint_id: u64,
+        subject: impl Into<String>,
+        body: impl Into<String>,
+    ) -> Instruction {
+        // The sender pays for their message nonce and rate limit accounts
+        let mut accounts = vec![
+            AccountMeta::new(*sender, true),
+            AccountMeta::new(self.mailer, false),
+            AccountMeta::new(self.usdc_account(sender), false),
+            AccountMeta::new(self.mailer_usdc, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        ];
+        accounts.extend(self.fee_accounts(sender, None));
+        accounts.push(AccountMeta::new_readonly(system_program::id(), false));
+
+        self.instruction(
+            MailerInstruction::SendToEmailHash {
+                email_hash: hash_email(salt, email).to_vec(),
+                hint_id,
+                subject: subject.into(),
+                _body: body.into(),
+                strict: false,
+            },
+            accounts,
+        )
+    }
+
+    /// `ClaimRecipientShare` into `recipient_usdc`
+    ///
+    /// Pass `with_payout_config` when the recipient has a payout config, so the
+    /// program can enforce its destination; `recipient_usdc` must then be that account.
+    /// The mailer account is writable so no-expiry claims can settle their surcharge.
+    pub fn claim_recipient_share(
+        &self,
+        recipient: &Pubkey,
+        recipient_usdc: &Pubkey,
+        amount: Option<u64>,
+        with_payout_config: bool,
+    ) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new_readonly(*recipient, true),
+            AccountMeta::new(pda::recipient_claim(&self.program_id, recipient).0, false),
+            AccountMeta::new(self.mailer, false),
+            AccountMeta::new(*recipient_usdc, false),
+            AccountMeta::new(self.mailer_usdc, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        ];
+        if with_payout_config {
+            let (payout, _) = pda::payout_config(&self.program_id, recipient);
+            accounts.push(AccountMeta::new_readonly(payout, false));
+        }
+        accounts.extend(self.sanctions_accounts(recipient));
+        accounts.extend(self.stats_accounts());
+
+        self.instruction(
+            MailerInstruction::ClaimRecipientShare {
+                amount,
+                split: None,
+                close_to: None,
+            },
+            accounts,
+        )
+    }
+
+    /// `ClaimRecipientShare` paid out across the destinations of `split`
+    ///
+    /// `recipient_usdc` is checked like the destination of a plain claim but receives
+    /// nothing unless it is also listed in `split`.
+    pub fn claim_recipient_share_split(
+        &self,
+        recipient: &Pubkey,
+        recipient_usdc: &Pubkey,
+        amount: Option<u64>,
+        split: Vec<ClaimSplit>,
+    ) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new_readonly(*recipient, true),
+            AccountMeta::new(pda::recipient_claim(&self.program_id, recipient).0, false),
+            AccountMeta::new(self.mailer, false),
+            AccountMeta::new(*recipient_usdc, false),
+            AccountMeta::new(self.mailer_usdc, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        ];
+        accounts.extend(
+            split
+                .iter()
+                .map(|entry| AccountMeta::new(entry.destination, false)),
+        );
+        accounts.extend(self.sanctions_accounts(recipient));
+        accounts.extend(self.stats_accounts());
+
+        self.instruction(
+            MailerInstruction::ClaimRecipientShare {
+                amount,
+                split: Some(split),
+                close_to: None,
+            },
+            accounts,
+        )
+    }
+
+    /// `ClaimRecipientShare` of the whole balance into `recipient_usdc`, closing the emptied
+    /// claim account and refunding its rent to `refund_to`
+    ///
+    /// The closure log must be initialized. The next priority send recreates the claim.
+    pub fn claim_recipient_share_and_close(
+        &self,
+        recipient: &Pubkey,
+        recipient_usdc: &Pubkey,
+        refund_to: &Pubkey,
+        with_payout_config: bool,
+    ) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new_readonly(*recipient, true),
+            AccountMeta::new(pda::recipient_claim(&self.program_id, recipient).0, false),
+            AccountMeta::new(self.mailer, false),
+            AccountMeta::new(*recipient_usdc, false),
+            AccountMeta::new(self.mailer_usdc, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        ];
+        if with_payout_config {
+            let (payout, _) = pda::payout_config(&self.program_id, recipient);
+            accounts.push(AccountMeta::new_readonly(payout, false));
+        }
+        accounts.push(AccountMeta::new(*refund_to, false));
+        accounts.push(AccountMeta::new(
+            pda::closure_log(&self.program_id).0,
+            false,
+        ));
+        accounts.extend(self.sanctions_accounts(recipient));
+        accounts.extend(self.stats_accounts());
+
+        self.instruction(
+            MailerInstruction::ClaimRecipientShare {
+                amount: None,
+                split: None,
+                close_to: Some(*refund_to),
+            },
+            accounts,
+        )
+    }
+
+    /// `ClaimAndSend`: claim the sender's share into their associated USDC account, then
+    /// send paid from it
+    pub fn claim_and_send(
+        &self,
+        sender: &Pubkey,
+        amount: Option<u64>,
+        args: SendArgs,
+    ) -> Instruction {
+        let (claim, _) = pda::recipient_claim(&self.program_id, &args.to);
+        let mut accounts = vec![
+            AccountMeta::new(*sender, true),
+            AccountMeta::new(pda::recipient_claim(&self.program_id, sender).0, false),
+            AccountMeta::new(claim, false),
+            AccountMeta::new(self.mailer, false),
+            AccountMeta::new(self.usdc_account(sender), false),
+            AccountMeta::new(self.mailer_usdc, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ];
+        accounts.push(self.rent_payer_account(sender, args.rent_payer));
+        accounts.extend(self.send_optional_accounts(sender, &args));
+        accounts.extend(self.claim_rent_payer_accounts(&claim, args.revenue_share_to_receiver));
+
+        self.instruction(
+            MailerInstruction::ClaimAndSend {
+                amount,
+                to: args.to,
+                subject: args.subject,
+                _body: args.body,
+                revenue_share_to_receiver: args.revenue_share_to_receiver,
+                resolve_sender_to_name: args.resolve_sender_to_name,
+                request_read_receipt: args.request_read_receipt,
+                receipt_nonce: args.receipt_nonce,
+                memo: args.memo,
+                strict: args.strict,
+            },
+            accounts,
+        )
+    }
+
+    /// `ClaimOwnerShare` into the owner's associated USDC account, paying out the treasury
+    /// balance too once a treasury is registered
+    pub fn claim_owner_share(&self, owner: &Pubkey) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(self.mailer, false),
+            AccountMeta::new(self.usdc_account(owner), false),
+            AccountMeta::new(self.mailer_usdc, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        ];
+        accounts.extend(self.stats_accounts());
+        if let Some(treasury) = self.treasury {
+            accounts.push(AccountMeta::new(treasury, false));
+            accounts.push(AccountMeta::new_readonly(
+                pda::treasury(&self.program_id).0,
+                false,
+            ));
+        }
+
+        self.instruction(MailerInstruction::ClaimOwnerShare, accounts)
+    }
+
+    /// `DelegateTo` paid from the delegator's associated USDC account;
+    /// `None` clears the delegation. `previous` is the delegator's current delegate, whose
+    /// reverse index the delegator leaves.
+    pub fn delegate_to(
+        &self,
+        delegator: &Pubkey,
+        delegate: Option<Pubkey>,
+        previous: Option<Pubkey>,
+    ) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new(*delegator, true),
+            AccountMeta::new(pda::delegation(&self.program_id, delegator).0, false),
+            AccountMeta::new(self.mailer, false),
+            AccountMeta::new(self.usdc_account(delegator), false),
+            AccountMeta::new(self.mailer_usdc, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ];
+        let indexed = |key: Option<Pubkey>| key.filter(|key| *key != Pubkey::default());
+        if indexed(delegate) != indexed(previous) {
+            for key in [delegate, previous].into_iter().filter_map(indexed) {
+                accounts.push(AccountMeta::new(
+                    pda::delegate_index(&self.program_id, &key).0,
+                    false,
+                ));
+            }
+        }
+
+        self.instruction(MailerInstruction::DelegateTo { delegate }, accounts)
+    }
+
+    /// `SendAsDelegator` from `delegator`, signed by its current delegate and paid from the
+    /// delegate's associated USDC account; receipts, memos, read receipts and thread ids in
+    /// `args` are not supported and ignored
+    pub fn send_as_delegator(
+        &self,
+        delegate: &Pubkey,
+        delegator: &Pubkey,
+        args: SendArgs,
+    ) -> Instruction {
+        let (claim, _) = pda::recipient_claim(&self.program_id, &args.to);
+        let mut accounts = vec![
+            AccountMeta::new(*delegate, true),
+            AccountMeta::new_readonly(*delegator, false),
+            AccountMeta::new_readonly(pda::delegation(&self.program_id, delegator).0, false),
+            AccountMeta::new(claim, false),
+            AccountMeta::new(self.mailer, false),
+            AccountMeta::new(self.usdc_account(delegate), false),
+            AccountMeta::new(self.mailer_usdc, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ];
+        accounts.extend(self.fee_accounts(delegator, Some(&args.to)));
+        accounts.extend(self.sanctions_accounts(delegate));
+        accounts.extend(self.claim_rent_payer_accounts(&claim, args.revenue_share_to_receiver));
+
+        self.instruction(
+            MailerInstruction::SendAsDelegator {
+                to: args.to,
+                subject: args.subject,
+                _body: args.body,
+                revenue_share_to_receiver: args.revenue_share_to_receiver,
+                strict: args.strict,
+            },
+            accounts,
+        )
+    }
+
+    /// `Send` paid from the sender's associated account of the whitelisted fee `mint`,
+    /// into the mailer's associated account of it
+    pub fn send_in_mint(&self, sender: &Pubkey, mint: &Pubkey, args: SendArgs) -> Instruction {
+        let (claim, _) = pda::recipient_claim_in_mint(&self.program_id, &args.to, mint);
+        let mut accounts = vec![
+            AccountMeta::new(*sender, true),
+            AccountMeta::new(claim, false),
+            AccountMeta::new(self.mailer, false),
+            AccountMeta::new(associated_token_address(sender, mint), false),
+            AccountMeta::new(associated_token_address(&self.mailer, mint), false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ];
+        accounts.push(self.rent_payer_account(sender, args.rent_payer));
+        accounts.extend(self.send_optional_accounts(sender, &args));
+        accounts.push(AccountMeta::new(
+            pda::fee_mint(&self.program_id, mint).0,
+            false,
+        ));
+        accounts.extend(self.claim_rent_payer_accounts(&claim, args.revenue_share_to_receiver));
+
+        self.instruction(
+            MailerInstruction::Send {
+                to: args.to,
+                subject: args.subject,
+                _body: args.body,
+                revenue_share_to_receiver: args.revenue_share_to_receiver,
+                resolve_sender_to_name: args.resolve_sender_to_name,
+                request_read_receipt: args.request_read_receipt,
+                receipt_nonce: args.receipt_nonce,
+                memo: args.memo,
+                strict: args.strict,
+                thread_id: args.thread_id,
+                in_reply_to: args.in_reply_to,
+                allow_downgrade: args.allow_downgrade,
+                tip: args.tip,
+                bridge: args.bridge_message.is_some(),
+                idempotency_nonce: args.idempotency_nonce,
+            },
+            accounts,
+        )
+    }
+
+    /// Standard `Send` paying the lamport send fee into the lamport vault
+    pub fn send_in_lamports(&self, sender: &Pubkey, args: SendArgs) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new(*sender, true),
+            AccountMeta::new(pda::recipient_claim(&self.program_id, &args.to).0, false),
+            AccountMeta::new(self.mailer, false),
+            AccountMeta::new(*sender, false),
+            AccountMeta::new(pda::lamport_vault(&self.program_id).0, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ];
+        accounts.push(self.rent_payer_account(sender, args.rent_payer));
+        accounts.extend(self.send_optional_accounts(sender, &args));
+        if let Some(oracle) = self.fee_oracle {
+            accounts.push(AccountMeta::new_readonly(oracle, false));
+        }
+
+        self.instruction(
+            MailerInstruction::Send {
+                to: args.to,
+                subject: args.subject,
+                _body: args.body,
+                revenue_share_to_receiver: args.revenue_share_to_receiver,
+                resolve_sender_to_name: args.resolve_sender_to_name,
+                request_read_receipt: args.request_read_receipt,
+                receipt_nonce: args.receipt_nonce,
+                memo: args.memo,
+                strict: args.strict,
+                thread_id: args.thread_id,
+                in_reply_to: args.in_reply_to,
+                allow_downgrade: args.allow_downgrade,
+                tip: args.tip,
+                bridge: args.bridge_message.is_some(),
+                idempotency_nonce: args.idempotency_nonce,
+            },
+            accounts,
+        )
+    }
+
+    /// `ClaimOwnerLamports` from the lamport vault into the owner's wallet
+    pub fn claim_owner_lamports(&self, owner: &Pubkey) -> Instruction {
+        self.instruction(
+            MailerInstruction::ClaimOwnerLamports,
+            vec![
+                AccountMeta::new_readonly(*owner, true),
+                AccountMeta::new(self.mailer, false),
+                AccountMeta::new(pda::lamport_vault(&self.program_id).0, false),
+                AccountMeta::new(*owner, false),
+            ],
+        )
+    }
+
+    /// `AddFeeMint` whitelisting `mint` with its own send and delegation fees
+    pub fn add_fee_mint(
+        &self,
+        owner: &Pubkey,
+        mint: Pubkey,
+        send_fee: u64,
+        delegation_fee: u64,
+    ) -> Instruction {
+        self.instruction(
+            MailerInstruction::AddFeeMint {
+                mint,
+                send_fee,
+                delegation_fee,
+            },
+            vec![
+                AccountMeta::new(*owner, true),
+                AccountMeta::new_readonly(self.mailer, false),
+                AccountMeta::new(pda::fee_mint(&self.program_id, &mint).0, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// `RedeemPromo` of `code` into the user's fee discount
+    pub fn redeem_promo(&self, user: &Pubkey, code: impl Into<String>) -> Instruction {
+        let code = code.into();
+        let code_hash = solana_sdk::hash::hash(code.as_bytes()).to_bytes();
+        self.instruction(
+            MailerInstruction::RedeemPromo { code },
+            vec![
+                AccountMeta::new(*user, true),
+                AccountMeta::new(pda::promo(&self.program_id, &code_hash).0, false),
+                AccountMeta::new(pda::fee_discount(&self.program_id, user).0, false),
+                AccountMeta::new(self.mailer, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// `RegisterWebhook` of `webhook_id`, committing to the hash of its endpoint and
+    /// charging `surcharge` on every send through it
+    pub fn register_webhook(
+        &self,
+        owner: &Pubkey,
+        webhook_id: impl Into<String>,
+        endpoint_hash: [u8; 32],
+        surcharge: u64,
+    ) -> Instruction {
+        let webhook_id = webhook_id.into();
+        let id_hash = solana_sdk::hash::hash(webhook_id.as_bytes()).to_bytes();
+        self.instruction(
+            MailerInstruction::RegisterWebhook {
+                webhook_id,
+                endpoint_hash,
+                surcharge,
+            },
+            vec![
+                AccountMeta::new(*owner, true),
+                AccountMeta::new_readonly(self.mailer, false),
+                AccountMeta::new(pda::webhook(&self.program_id, &id_hash).0, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// `ClaimWebhookRevenue` of the webhook `webhook_id` into its owner's associated USDC account
+    pub fn claim_webhook_revenue(&self, owner: &Pubkey, webhook_id: &str) -> Instruction {
+        let id_hash = solana_sdk::hash::hash(webhook_id.as_bytes()).to_bytes();
+        let mut accounts = vec![
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(pda::webhook(&self.program_id, &id_hash).0, false),
+            AccountMeta::new_readonly(self.mailer, false),
+            AccountMeta::new(self.usdc_account(owner), false),
+            AccountMeta::new(self.mailer_usdc, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        ];
+        accounts.extend(self.sanctions_accounts(owner));
+        accounts.extend(self.stats_accounts());
+
+        self.instruction(MailerInstruction::ClaimWebhookRevenue, accounts)
+    }
+
+    /// `RecordDeliveryStatus` of the send from `owner` to `to` in `sent_slot` through the
+    /// webhook `webhook_id`
+    pub fn record_delivery_status(
+        &self,
+        owner: &Pubkey,
+        webhook_id: &str,
+        to: Pubkey,
+        nonce: u64,
+        sent_slot: u64,
+        delivered: bool,
+    ) -> Instruction {
+        let id_hash = solana_sdk::hash::hash(webhook_id.as_bytes()).to_bytes();
+        self.instruction(
+            MailerInstruction::RecordDeliveryStatus {
+                to,
+                nonce,
+                sent_slot,
+                delivered,
+            },
+            vec![
+                AccountMeta::new_readonly(*owner, true),
+                AccountMeta::new(pda::webhook(&self.program_id, &id_hash).0, false),
+            ],
+        )
+    }
+
+    /// `GetWebhookStats` of the webhook `webhook_id`, returning a `WebhookStats`
+    pub fn get_webhook_stats(&self, webhook_id: &str) -> Instruction {
+        let id_hash = solana_sdk::hash::hash(webhook_id.as_bytes()).to_bytes();
+        self.instruction(
+            MailerInstruction::GetWebhookStats,
+            vec![AccountMeta::new_readonly(
+                pda::webhook(&self.program_id, &id_hash).0,
+                false,
+            )],
+        )
+    }
+
+    /// `RejectDelegation` signed by the current delegate of `delegator`
+    pub fn reject_delegation(&self, delegate: &Pubkey, delegator: &Pubkey) -> Instruction {
+        self.instruction(
+            MailerInstruction::RejectDelegation,
+            vec![
+                AccountMeta::new_readonly(*delegate, true),
+                AccountMeta::new(pda::delegation(&self.program_id, delegator).0, false),
+                AccountMeta::new(self.mailer, false),
+                AccountMeta::new(pda::delegate_index(&self.program_id, delegate).0, false),
+            ],
+        )
+    }
+
+    /// `AcceptDelegation` signed by the current delegate of `delegator`
+    pub fn accept_delegation(&self, delegate: &Pubkey, delegator: &Pubkey) -> Instruction {
+        self.instruction(
+            MailerInstruction::AcceptDelegation,
+            vec![
+                AccountMeta::new_readonly(*delegate, true),
+                AccountMeta::new(pda::delegation(&self.program_id, delegator).0, false),
+                AccountMeta::new(self.mailer, false),
+            ],
+        )
+    }
+
+    /// `RefundDelegationFee` of `delegator` into its associated USDC account
+    pub fn refund_delegation_fee(&self, delegator: &Pubkey) -> Instruction {
+        self.instruction(
+            MailerInstruction::RefundDelegationFee,
+            vec![
+                AccountMeta::new_readonly(*delegator, true),
+                AccountMeta::new(pda::delegation(&self.program_id, delegator).0, false),
+                AccountMeta::new_readonly(self.mailer, false),
+                AccountMeta::new(self.usdc_account(delegator), false),
+                AccountMeta::new(self.mailer_usdc, false),
+                AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            ],
+        )
+    }
+
+    /// `DepositSendCredits` of `amount` from `sender`'s associated USDC account
+    pub fn deposit_send_credits(&self, sender: &Pubkey, amount: u64) -> Instruction {
+        self.instruction(
+            MailerInstruction::DepositSendCredits { amount },
+            vec![
+                AccountMeta::new(*sender, true),
+                AccountMeta::new(pda::send_credits(&self.program_id, sender).0, false),
+                AccountMeta::new(self.mailer, false),
+                AccountMeta::new(self.usdc_account(sender), false),
+                AccountMeta::new(self.mailer_usdc, false),
+                AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// `WithdrawSendCredits` of `sender` into its associated USDC account, all of them for `None`
+    pub fn withdraw_send_credits(&self, sender: &Pubkey, amount: Option<u64>) -> Instruction {
+        self.instruction(
+            MailerInstruction::WithdrawSendCredits { amount },
+            vec![
+                AccountMeta::new_readonly(*sender, true),
+                AccountMeta::new(pda::send_credits(&self.program_id, sender).0, false),
+                AccountMeta::new(self.mailer, false),
+                AccountMeta::new(self.usdc_account(sender), false),
+                AccountMeta::new(self.mailer_usdc, false),
+                AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            ],
+        )
+    }
+
+    /// `Subscribe` to plan `plan_id`, paid from the subscriber's associated USDC account
+    pub fn subscribe(&self, subscriber: &Pubkey, plan_id: u64) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new(*subscriber, true),
+            AccountMeta::new_readonly(pda::plan(&self.program_id, plan_id).0, false),
+            AccountMeta::new(pda::subscription(&self.program_id, subscriber).0, false),
+            AccountMeta::new(self.mailer, false),
+            AccountMeta::new(self.usdc_account(subscriber), false),
+            AccountMeta::new(self.mailer_usdc, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ];
+        accounts.extend(self.sanctions_accounts(subscriber));
+        self.instruction(MailerInstruction::Subscribe { plan_id }, accounts)
+    }
+
+    /// `RenewSubscription` of the subscriber's subscription to plan `plan_id`
+    pub fn renew_subscription(&self, subscriber: &Pubkey, plan_id: u64) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new_readonly(*subscriber, true),
+            AccountMeta::new_readonly(pda::plan(&self.program_id, plan_id).0, false),
+            AccountMeta::new(pda::subscription(&self.program_id, subscriber).0, false),
+            AccountMeta::new(self.mailer, false),
+            AccountMeta::new(self.usdc_account(subscriber), false),
+            AccountMeta::new(self.mailer_usdc, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        ];
+        accounts.extend(self.sanctions_accounts(subscriber));
+        self.instruction(MailerInstruction::RenewSubscription, accounts)
+    }
+
+    /// `SetClaimNoExpiry`: opt `recipient`'s USDC claim in or out of no-expiry status
+    pub fn set_claim_no_expiry(&self, recipient: &Pubkey, enabled: bool) -> Instruction {
+        self.instruction(
+            MailerInstruction::SetClaimNoExpiry { enabled },
+            vec![
+                AccountMeta::new_readonly(*recipient, true),
+                AccountMeta::new(pda::recipient_claim(&self.program_id, recipient).0, false),
+                AccountMeta::new(self.mailer, false),
+            ],
+        )
+    }
+
+    /// `ExtendClaimWindow`: restart `recipient`'s USDC claim window for a fee from the balance
+    pub fn extend_claim_window(&self, recipient: &Pubkey) -> Instruction {
+        self.instruction(
+            MailerInstruction::ExtendClaimWindow,
+            vec![
+                AccountMeta::new_readonly(*recipient, true),
+                AccountMeta::new(pda::recipient_claim(&self.program_id, recipient).0, false),
+                AccountMeta::new(self.mailer, false),
+            ],
+        )
+    }
+
+    /// `SetClaimBeneficiary`: register, replace or (with `None`) remove `recipient`'s
+    /// backup beneficiary
+    pub fn set_claim_beneficiary(
+        &self,
+        recipient: &Pubkey,
+        beneficiary: Option<Pubkey>,
+    ) -> Instruction {
+        self.instruction(
+            MailerInstruction::SetClaimBeneficiary { beneficiary },
+            vec![
+                AccountMeta::new(*recipient, true),
+                AccountMeta::new(pda::claim_beneficiary(&self.program_id, recipient).0, false),
+                AccountMeta::new_readonly(self.mailer, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// `ClaimAsBeneficiary`: collect `recipient`'s expired claim into the beneficiary's
+    /// associated USDC account
+    pub fn claim_as_beneficiary(&self, beneficiary: &Pubkey, recipient: &Pubkey) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new_readonly(*beneficiary, true),
+            AccountMeta::new_readonly(pda::claim_beneficiary(&self.program_id, recipient).0, false),
+            AccountMeta::new(pda::recipient_claim(&self.program_id, recipient).0, false),
+            AccountMeta::new(self.mailer, false),
+            AccountMeta::new(self.usdc_account(beneficiary), false),
+            AccountMeta::new(self.mailer_usdc, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        ];
+        accounts.extend(self.sanctions_accounts(beneficiary));
+        accounts.extend(self.stats_accounts());
+        self.instruction(
+            MailerInstruction::ClaimAsBeneficiary {
+                recipient: *recipient,
+            },
+            accounts,
+        )
+    }
+
+    /// `MigrateState`: upgrade the mailer state to the next layout version, with `owner`
+    /// topping up its rent
+    pub fn migrate_state(&self, owner: &Pubkey) -> Instruction {
+        self.instruction(
+            MailerInstruction::MigrateState,
+            vec![
+                AccountMeta::new(*owner, true),
+                AccountMeta::new(self.mailer, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// `SetTreasury`: route the owner share of USDC send fees to the `treasury` token account,
+    /// such as [`treasury_usdc`](Self::treasury_usdc), or back into the vault with `None`
+    pub fn set_treasury(&self, owner: &Pubkey, treasury: Option<&Pubkey>) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(self.mailer, false),
+        ];
+        accounts.extend(treasury.map(|treasury| AccountMeta::new_readonly(*treasury, false)));
+        self.instruction(
+            MailerInstruction::SetTreasury {
+                token_account: treasury.copied(),
+            },
+            accounts,
+        )
+    }
+
+    /// `VerifySolvency` over the default-mint claims of `recipients`, sorted into the
+    /// ascending order the program requires; returns a `SolvencyReport`
+    pub fn verify_solvency(&self, recipients: &[Pubkey]) -> Instruction {
+        let mut claims: Vec<Pubkey> = recipients
+            .iter()
+            .map(|recipient| pda::recipient_claim(&self.program_id, recipient).0)
+            .collect();
+        claims.sort();
+        claims.dedup();
+
+        let mut accounts = vec![
+            AccountMeta::new_readonly(self.mailer, false),
+            AccountMeta::new_readonly(self.mailer_usdc, false),
+        ];
+        accounts.extend(
+            claims
+                .into_iter()
+                .map(|claim| AccountMeta::new_readonly(claim, false)),
+        );
+        self.instruction(MailerInstruction::VerifySolvency, accounts)
+    }
+
+    /// `SetClaimMerkleRoot`: fund merkle airdrop `distribution_id` with `total` USDC from the
+    /// owner's associated account, for the tree built with [`mailer::merkle::root`]
+    pub fn set_claim_merkle_root(
+        &self,
+        owner: &Pubkey,
+        distribution_id: u64,
+        merkle_root: [u8; 32],
+        leaf_count: u64,
+        total: u64,
+    ) -> Instruction {
+        self.instruction(
+            MailerInstruction::SetClaimMerkleRoot {
+                distribution_id,
+                merkle_root,
+                leaf_count,
+                total,
+            },
+            vec![
+                AccountMeta::new(*owner, true),
+                AccountMeta::new_readonly(self.mailer, false),
+                AccountMeta::new(
+                    pda::claim_distribution(&self.program_id, distribution_id).0,
+                    false,
+                ),
+                AccountMeta::new(self.usdc_account(owner), false),
+                AccountMeta::new(self.mailer_usdc, false),
+                AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// `ClaimFromMerkle`: credit leaf `index` of distribution `distribution_id` to
+    /// `recipient`'s claim, with the proof from [`mailer::merkle::proof`]
+    pub fn claim_from_merkle(
+        &self,
+        recipient: &Pubkey,
+        distribution_id: u64,
+        index: u64,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new(*recipient, true),
+            AccountMeta::new(
+                pda::claim_distribution(&self.program_id, distribution_id).0,
+                false,
+            ),
+            AccountMeta::new(pda::recipient_claim(&self.program_id, recipient).0, false),
+            AccountMeta::new(self.mailer, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ];
+        accounts.extend(self.sanctions_accounts(recipient));
+        self.instruction(
+            MailerInstruction::ClaimFromMerkle {
+                distribution_id,
+                index,
+                amount,
+                proof,
+            },
+            accounts,
+        )
+    }
+
+    /// `SendEscrowed`: send to `to` with the priority fee held in escrow `nonce` of the sender
+    /// until `to` accepts the message or the sender reclaims the fee after 7 days
+    pub fn send_escrowed(
+        &self,
+        sender: &Pubkey,
+        to: &Pubkey,
+        subject: &str,
+        body: &str,
+        nonce: u64,
+    ) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new(*sender, true),
+            AccountMeta::new(pda::escrow(&self.program_id, sender, nonce).0, false),
+            AccountMeta::new_readonly(self.mailer, false),
+            AccountMeta::new(self.usdc_account(sender), false),
+            AccountMeta::new(self.mailer_usdc, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(pda::fee_discount(&self.program_id, sender).0, false),
+            AccountMeta::new_readonly(pda::inbox_policy(&self.program_id, to).0, false),
+        ];
+        accounts.extend(self.sanctions_accounts(sender));
+        accounts.extend(self.sanctions_accounts(to));
+        accounts.push(AccountMeta::new(
+            pda::message_nonce(&self.program_id, sender).0,
+            false,
+        ));
+        self.instruction(
+            MailerInstruction::SendEscrowed {
+                to: *to,
+                subject: subject.to_string(),
+                _body: body.to_string(),
+                nonce,
+            },
+            accounts,
+        )
+    }
+
+    /// `AcceptMessage`: pay the fee of `sender`'s escrowed message `nonce` out to
+    /// `recipient`'s claim, refunding the escrow rent to `sender`
+    pub fn accept_message(&self, recipient: &Pubkey, sender: &Pubkey, nonce: u64) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new(*recipient, true),
+            AccountMeta::new(pda::escrow(&self.program_id, sender, nonce).0, false),
+            AccountMeta::new(pda::recipient_claim(&self.program_id, recipient).0, false),
+            AccountMeta::new(self.mailer, false),
+            AccountMeta::new(self.mailer_usdc, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(*sender, false),
+        ];
+        accounts.extend(
+            self.treasury
+                .map(|treasury| AccountMeta::new(treasury, false)),
+        );
+        self.instruction(
+            MailerInstruction::AcceptMessage {
+                sender: *sender,
+                nonce,
+            },
+            accounts,
+        )
+    }
+
+    /// `ReclaimEscrow`: return the fee of the sender's expired escrow `nonce` to their
+    /// associated USDC account
+    pub fn reclaim_escrow(&self, sender: &Pubkey, nonce: u64) -> Instruction {
+        self.instruction(
+            MailerInstruction::ReclaimEscrow { nonce },
+            vec![
+                AccountMeta::new(*sender, true),
+                AccountMeta::new(pda::escrow(&self.program_id, sender, nonce).0, false),
+                AccountMeta::new_readonly(self.mailer, false),
+                AccountMeta::new(self.usdc_account(sender), false),
+                AccountMeta::new(self.mailer_usdc, false),
+                AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            ],
+        )
+    }
+
+    /// `CreateList`: create `owner`'s empty mailing list `list_id`
+    pub fn create_list(&self, owner: &Pubkey, list_id: u64) -> Instruction {
+        self.instruction(
+            MailerInstruction::CreateList { list_id },
+            vec![
+                AccountMeta::new(*owner, true),
+                AccountMeta::new(pda::mailing_list(&self.program_id, owner, list_id).0, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// `AddListMember`: add `member` to chunk `chunk` of `owner`'s mailing list `list_id`,
+    /// creating the chunk when it is the list's next one
+    pub fn add_list_member(
+        &self,
+        owner: &Pubkey,
+        list_id: u64,
+        chunk: u64,
+        member: &Pubkey,
+    ) -> Instruction {
+        let list = pda::mailing_list(&self.program_id, owner, list_id).0;
+        self.instruction(
+            MailerInstruction::AddListMember {
+                list_id,
+                chunk,
+                member: *member,
+            },
+            vec![
+                AccountMeta::new(*owner, true),
+                AccountMeta::new(list, false),
+                AccountMeta::new(pda::list_chunk(&self.program_id, &list, chunk).0, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// `RemoveListMember`: remove `member` from chunk `chunk` of `owner`'s mailing list
+    pub fn remove_list_member(
+        &self,
+        owner: &Pubkey,
+        list_id: u64,
+        chunk: u64,
+        member: &Pubkey,
+    ) -> Instruction {
+        let list = pda::mailing_list(&self.program_id, owner, list_id).0;
+        self.instruction(
+            MailerInstruction::RemoveListMember {
+                list_id,
+                chunk,
+                member: *member,
+            },
+            vec![
+                AccountMeta::new_readonly(*owner, true),
+                AccountMeta::new(list, false),
+                AccountMeta::new(pda::list_chunk(&self.program_id, &list, chunk).0, false),
+            ],
+        )
+    }
+
+    /// `SendToList`: broadcast to every member of `owner`'s mailing list `list_id`, paying
+    /// the standard fee per member from the owner's associated USDC account
+    pub fn send_to_list(
+        &self,
+        owner: &Pubkey,
+        list_id: u64,
+        subject: &str,
+        body: &str,
+    ) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new_readonly(pda::mailing_list(&self.program_id, owner, list_id).0, false),
+            AccountMeta::new(self.mailer, false),
+            AccountMeta::new(self.usdc_account(owner), false),
+            AccountMeta::new(self.mailer_usdc, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(pda::fee_discount(&self.program_id, owner).0, false),
+        ];
+        accounts.extend(self.sanctions_accounts(owner));
+        accounts.extend(
+            self.treasury
+                .map(|treasury| AccountMeta::new(treasury, false)),
+        );
+        accounts.push(AccountMeta::new(
+            pda::message_nonce(&self.program_id, owner).0,
+            false,
+        ));
+        accounts.push(AccountMeta::new_readonly(system_program::id(), false));
+        self.instruction(
+            MailerInstruction::SendToList {
+                list_id,
+                subject: subject.to_string(),
+                _body: body.to_string(),
+            },
+            accounts,
+        )
+    }
+
+    /// `SendToDomain` paid from the sender's associated USDC account; `owner` is the
+    /// domain's current owner, as returned by [`MailerClient::resolve_domain`](crate::MailerClient::resolve_domain)
+    pub fn send_to_domain(
+        &self,
+        sender: &Pubkey,
+        domain: &str,
+        owner: &Pubkey,
+        subject: &str,
+        body: &str,
+        revenue_share_to_receiver: bool,
+    ) -> Instruction {
+        let (claim, _) = pda::recipient_claim(&self.program_id, owner);
+        let mut accounts = vec![
+            AccountMeta::new(*sender, true),
+            AccountMeta::new_readonly(sns::domain_key(domain), false),
+            AccountMeta::new(claim, false),
+            AccountMeta::new(self.mailer, false),
+            AccountMeta::new(self.usdc_account(sender), false),
+            AccountMeta::new(self.mailer_usdc, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ];
+        accounts.push(self.rent_payer_account(sender, None));
+        accounts.extend(self.fee_accounts(sender, Some(owner)));
+        accounts.extend(self.claim_rent_payer_accounts(&claim, revenue_share_to_receiver));
+
+        self.instruction(
+            MailerInstruction::SendToDomain {
+                domain: domain.to_string(),
+                subject: subject.to_string(),
+                _body: body.to_string(),
+                revenue_share_to_receiver,
+                strict: false,
+            },
+            accounts,
+        )
+    }
+
+    /// `BindEvmAddress` for `wallet`; must directly follow the secp256k1 program instruction
+    /// checking the EVM key's signature over [`mailer::evm::binding_message`]
+    pub fn bind_evm_address(&self, wallet: &Pubkey, evm_address: [u8; 20]) -> Instruction {
+        self.instruction(
+            MailerInstruction::BindEvmAddress { evm_address },
+            vec![
+                AccountMeta::new(*wallet, true),
+                AccountMeta::new(pda::evm_mapping(&self.program_id, &evm_address).0, false),
+                AccountMeta::new_readonly(sysvar::instructions::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// `SendToEvmAddress` paid from the sender's associated USDC account; `wallet` is the
+    /// bound wallet, as returned by [`MailerClient::resolve_evm_address`](crate::MailerClient::resolve_evm_address)
+    pub fn send_to_evm_address(
+        &self,
+        sender: &Pubkey,
+        evm_address: [u8; 20],
+        wallet: &Pubkey,
+        subject: &str,
+        body: &str,
+        revenue_share_to_receiver: bool,
+    ) -> Instruction {
+        let (claim, _) = pda::recipient_claim(&self.program_id, wallet);
+        let mut accounts = vec![
+            AccountMeta::new(*sender, true),
+            AccountMeta::new_readonly(pda::evm_mapping(&self.program_id, &evm_address).0, false),
+            AccountMeta::new(claim, false),
+            AccountMeta::new(self.mailer, false),
+            AccountMeta::new(self.usdc_account(sender), false),
+            AccountMeta::new(self.mailer_usdc, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ];
+        accounts.push(self.rent_payer_account(sender, None));
+        accounts.extend(self.fee_accounts(sender, Some(wallet)));
+        accounts.extend(self.claim_rent_payer_accounts(&claim, revenue_share_to_receiver));
+
+        self.instruction(
+            MailerInstruction::SendToEvmAddress {
+                evm_address,
+                subject: subject.to_string(),
+                _body: body.to_string(),
+                revenue_share_to_receiver,
+                strict: false,
+            },
+            accounts,
+        )
+    }
+
+    /// `SetWormholeBridge` pointing bridged sends at `core_bridge`, or switching them off
+    pub fn set_wormhole_bridge(&self, owner: &Pubkey, core_bridge: Option<Pubkey>) -> Instruction {
+        self.instruction(
+            MailerInstruction::SetWormholeBridge { core_bridge },
+            vec![
+                AccountMeta::new(*owner, true),
+                AccountMeta::new_readonly(self.mailer, false),
+                AccountMeta::new(pda::wormhole_config(&self.program_id).0, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// `SetDiscountSchedule` replacing the volume discount tiers; no tiers switch them off
+    pub fn set_discount_schedule(&self, owner: &Pubkey, tiers: Vec<VolumeTier>) -> Instruction {
+        self.instruction(
+            MailerInstruction::SetDiscountSchedule {
+                tiers,
+                reserved: Reserved::default(),
+            },
+            vec![
+                AccountMeta::new(*owner, true),
+                AccountMeta::new_readonly(self.mailer, false),
+                AccountMeta::new(pda::discount_schedule(&self.program_id).0, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// `GetFee`, returning a `FeeView`
+    pub fn get_fee(&self) -> Instruction {
+        self.instruction(
+            MailerInstruction::GetFee {
+                reserved: Reserved::default(),
+            },
+            vec![AccountMeta::new_readonly(self.mailer, false)],
+        )
+    }
+
+    /// `GetClaimable` of the recipient `account`, returning a `ClaimableView`
+    pub fn get_claimable(&self, account: &Pubkey) -> Instruction {
+        self.instruction(
+            MailerInstruction::GetClaimable {
+                account: *account,
+                reserved: Reserved::default(),
+            },
+            vec![
+                AccountMeta::new_readonly(self.mailer, false),
+                AccountMeta::new_readonly(pda::recipient_claim(&self.program_id, account).0, false),
+            ],
+        )
+    }
+
+    /// `GetDelegation` of `delegator`, returning a `DelegationView`
+    pub fn get_delegation(&self, delegator: &Pubkey) -> Instruction {
+        self.instruction(
+            MailerInstruction::GetDelegation {
+                delegator: *delegator,
+                reserved: Reserved::default(),
+            },
+            vec![AccountMeta::new_readonly(
+                pda::delegation(&self.program_id, delegator).0,
+                false,
+            )],
+        )
+    }
+
+    /// Associated USDC account of the treasury PDA
+    pub fn treasury_usdc(&self) -> Pubkey {
+        associated_token_address(&pda::treasury(&self.program_id).0, &self.usdc_mint)
+    }
+}
@@ -0,0 +1,314 @@
+//! # Mailer Client
+//!
+//! Off-chain Rust client for the Mailer program. [`MailerClient`] wraps an async
+//! `solana-client` RPC connection to fetch and decode program accounts, estimate the
+//! USDC a send will charge, and build unsigned transactions with the account metas
+//! each instruction expects. The pieces are usable on their own:
+//! - [`accounts`] decodes account data with owner and discriminator checks
+//! - [`instructions`] builds instructions from pubkeys, without RPC
+//! - [`fees`] mirrors the program's fee calculation
+//!
+//! ```rust,no_run
+//! use mailer_client::{MailerClient, SendArgs};
+//! use solana_client::nonblocking::rpc_client::RpcClient;
+//! use solana_sdk::{signature::Keypair, signer::Signer};
+//!
+//! # async fn example(sender: Keypair, to: solana_sdk::pubkey::Pubkey) -> Result<(), mailer_client::ClientError> {
+//! let rpc = RpcClient::new("https://api.devnet.solana.com".to_string());
+//! let client = MailerClient::connect(rpc, mailer::id()).await?;
+//!
+//! let args = SendArgs::new(to, "Subject", "Body").priority();
+//! let estimate = client.estimate_send(&sender.pubkey(), &args).await?;
+//! println!("fee: {} USDC units, rent: {} lamports", estimate.usdc, estimate.rent_lamports);
+//!
+//! let mut tx = client.build_send(&sender.pubkey(), args).await?;
+//! let blockhash = tx.message.recent_blockhash;
+//! tx.sign(&[&sender], blockhash);
+//! client.rpc().send_and_confirm_transaction(&tx).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod accounts;
+pub mod fees;
+pub mod instructions;
+
+mod error;
+
+pub use error::ClientError;
+pub use instructions::{MailerAccounts, SendArgs};
+
+use mailer::{
+    costs, pda, sns, DelegateIndex, Delegation, EvmMapping, FeeDiscount, InboxPolicy, MailerState,
+    MailerStats, PayoutConfig, RecipientClaim,
+};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    clock::Clock, instruction::Instruction, message::Message, pubkey::Pubkey, sysvar,
+    transaction::Transaction,
+};
+
+use accounts::{decode_account, decode_mailer_state_account, MailerAccount};
+
+/// USDC and SOL required by a send
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SendEstimate {
+    /// USDC (base units) transferred from the sender
+    pub usdc: u64,
+    /// Lamports for accounts the send creates (claim, receipt, claim history)
+    pub rent_lamports: u64,
+}
+
+/// Async RPC client for a Mailer deployment
+pub struct MailerClient {
+    rpc: RpcClient,
+    accounts: MailerAccounts,
+}
+
+impl MailerClient {
+    /// Client for a deployment with known accounts
+    pub fn new(rpc: RpcClient, accounts: MailerAccounts) -> Self {
+        Self { rpc, accounts }
+    }
+
+    /// Client for the deployment of `program_id`, reading the USDC mint from its state
+    pub async fn connect(rpc: RpcClient, program_id: Pubkey) -> Result<Self, ClientError> {
+        let (mailer, _) = pda::mailer_state(&program_id);
+        let account = rpc.get_account(&mailer).await?;
+        let state = decode_mailer_state_account(&program_id, &mailer, &account)?;
+        Ok(Self::new(
+            rpc,
+            MailerAccounts::new(program_id, state.usdc_mint),
+        ))
+    }
+
+    /// Underlying RPC connection
+    pub fn rpc(&self) -> &RpcClient {
+        &self.rpc
+    }
+
+    /// Deployment accounts used to build instructions
+    pub fn accounts(&self) -> &MailerAccounts {
+        &self.accounts
+    }
+
+    /// Fetch and decode a Mailer account, `None` if it does not exist
+    pub async fn fetch<T: MailerAccount>(
+        &self,
+        address: &Pubkey,
+    ) -> Result<Option<T>, ClientError> {
+        self.rpc
+            .get_account_with_commitment(address, self.rpc.commitment())
+            .await?
+            .value
+            .map(|account| decode_account(&self.accounts.program_id, address, &account))
+            .transpose()
+    }
+
+    /// Global mailer state, upgraded to the current layout if not yet migrated
+    pub async fn mailer_state(&self) -> Result<MailerState, ClientError> {
+        let account = self
+            .rpc
+            .get_account_with_commitment(&self.accounts.mailer, self.rpc.commitment())
+            .await?
+            .value
+            .ok_or(ClientError::AccountNotFound(self.accounts.mailer))?;
+        decode_mailer_state_account(&self.accounts.program_id, &self.accounts.mailer, &account)
+    }
+
+    /// Program-wide send and claim counters, `None` until the owner initializes them
+    pub async fn stats(&self) -> Result<Option<MailerStats>, ClientError> {
+        self.fetch(&pda::stats(&self.accounts.program_id).0).await
+    }
+
+    /// Claimable share of `recipient`
+    pub async fn recipient_claim(
+        &self,
+        recipient: &Pubkey,
+    ) -> Result<Option<RecipientClaim>, ClientError> {
+        self.fetch(&pda::recipient_claim(&self.accounts.program_id, recipient).0)
+            .await
+    }
+
+    /// Delegation of `delegator`
+    pub async fn delegation(&self, delegator: &Pubkey) -> Result<Option<Delegation>, ClientError> {
+        self.fetch(&pda::delegation(&self.accounts.program_id, delegator).0)
+            .await
+    }
+
+    /// Reverse index of the delegators of `delegate`
+    pub async fn delegate_index(
+        &self,
+        delegate: &Pubkey,
+    ) -> Result<Option<DelegateIndex>, ClientError> {
+        self.fetch(&pda::delegate_index(&self.accounts.program_id, delegate).0)
+            .await
+    }
+
+    /// Custom fee discount of `account`
+    pub async fn fee_discount(&self, account: &Pubkey) -> Result<Option<FeeDiscount>, ClientError> {
+        self.fetch(&pda::fee_discount(&self.accounts.program_id, account).0)
+            .await
+    }
+
+    /// Inbox policy of `recipient`
+    pub async fn inbox_policy(
+        &self,
+        recipient: &Pubkey,
+    ) -> Result<Option<InboxPolicy>, ClientError> {
+        self.fetch(&pda::inbox_policy(&self.accounts.program_id, recipient).0)
+            .await
+    }
+
+    /// Payout config of `recipient`
+    pub async fn payout_config(
+        &self,
+        recipient: &Pubkey,
+    ) -> Result<Option<PayoutConfig>, ClientError> {
+        self.fetch(&pda::payout_config(&self.accounts.program_id, recipient).0)
+            .await
+    }
+
+    /// Current owner of the `.sol` domain `domain`, `None` if it is not registered
+    pub async fn resolve_domain(&self, domain: &str) -> Result<Option<Pubkey>, ClientError> {
+        let account = self
+            .rpc
+            .get_account_with_commitment(&sns::domain_key(domain), self.rpc.commitment())
+            .await?
+            .value;
+        Ok(account
+            .filter(|account| account.owner == sns::NAME_PROGRAM_ID)
+            .and_then(|account| sns::record_owner(&account.data)))
+    }
+
+    /// Wallet the EVM address `evm_address` is bound to, `None` if it is not bound
+    pub async fn resolve_evm_address(
+        &self,
+        evm_address: [u8; 20],
+    ) -> Result<Option<Pubkey>, ClientError> {
+        let mapping: Option<EvmMapping> = self
+            .fetch(&pda::evm_mapping(&self.accounts.program_id, &evm_address).0)
+            .await?;
+        Ok(mapping.map(|mapping| mapping.wallet))
+    }
+
+    async fn exists(&self, address: &Pubkey) -> Result<bool, ClientError> {
+        Ok(self
+            .rpc
+            .get_account_with_commitment(address, self.rpc.commitment())
+            .await?
+            .value
+            .is_some())
+    }
+
+    async fn unix_timestamp(&self) -> Result<i64, ClientError> {
+        let account = self.rpc.get_account(&sysvar::clock::id()).await?;
+        let clock: Clock = solana_sdk::account::from_account(&account)
+            .ok_or(ClientError::AccountNotFound(sysvar::clock::id()))?;
+        Ok(clock.unix_timestamp)
+    }
+
+    /// USDC and rent a `Send` with `args` would cost `sender` right now
+    pub async fn estimate_send(
+        &self,
+        sender: &Pubkey,
+        args: &SendArgs,
+    ) -> Result<SendEstimate, ClientError> {
+        let program_id = &self.accounts.program_id;
+        let state = self.mailer_state().await?;
+        let now = self.unix_timestamp().await?;
+        let discount = self.fee_discount(sender).await?;
+        let policy = self.inbox_policy(&args.to).await?;
+        let usdc = fees::send_cost(
+            &state,
+            now,
+            sender,
+            discount.as_ref(),
+            policy.as_ref(),
+            args.revenue_share_to_receiver,
+            args.tip,
+        )?;
+
+        let mut space = Vec::new();
+        if args.revenue_share_to_receiver
+            && !self
+                .exists(&pda::recipient_claim(program_id, &args.to).0)
+                .await?
+        {
+            space.push(costs::space::RECIPIENT_CLAIM);
+        }
+        if args.receipt_nonce.is_some() {
+            space.push(costs::space::MESSAGE_RECEIPT);
+        }
+        if args.revenue_share_to_receiver
+            && args.memo.is_some()
+            && !self
+                .exists(&pda::claim_history(program_id, &args.to, sender).0)
+                .await?
+        {
+            space.push(costs::space::CLAIM_HISTORY_ENTRY);
+        }
+        let mut rent_lamports = 0;
+        for len in space {
+            rent_lamports += self.rpc.get_minimum_balance_for_rent_exemption(len).await?;
+        }
+
+        Ok(SendEstimate {
+            usdc,
+            rent_lamports,
+        })
+    }
+
+    /// Unsigned transaction paid by `payer`, using the latest blockhash
+    pub async fn build_transaction(
+        &self,
+        instructions: &[Instruction],
+        payer: &Pubkey,
+    ) -> Result<Transaction, ClientError> {
+        let blockhash = self.rpc.get_latest_blockhash().await?;
+        Ok(Transaction::new_unsigned(Message::new_with_blockhash(
+            instructions,
+            Some(payer),
+            &blockhash,
+        )))
+    }
+
+    /// Unsigned `Send` transaction paid by `sender`
+    pub async fn build_send(
+        &self,
+        sender: &Pubkey,
+        args: SendArgs,
+    ) -> Result<Transaction, ClientError> {
+        self.build_transaction(&[self.accounts.send(sender, args)], sender)
+            .await
+    }
+
+    /// Unsigned `ClaimRecipientShare` transaction, paying out to the recipient's
+    /// configured payout account or their associated USDC account
+    pub async fn build_claim(
+        &self,
+        recipient: &Pubkey,
+        amount: Option<u64>,
+    ) -> Result<Transaction, ClientError> {
+        let payout = self.payout_config(recipient).await?;
+        let destination = payout
+            .as_ref()
+            .and_then(|p| p.payout_token_account)
+            .unwrap_or_else(|| self.accounts.usdc_account(recipient));
+        let ix =
+            self.accounts
+                .claim_recipient_share(recipient, &destination, amount, payout.is_some());
+        self.build_transaction(&[ix], recipient).await
+    }
+
+    /// Unsigned `DelegateTo` transaction paid by `delegator`
+    pub async fn build_delegate(
+        &self,
+        delegator: &Pubkey,
+        delegate: Option<Pubkey>,
+    ) -> Result<Transaction, ClientError> {
+        let previous = self.delegation(delegator).await?.and_then(|d| d.delegate);
+        let ix = self.accounts.delegate_to(delegator, delegate, previous);
+        self.build_transaction(&[ix], delegator).await
+    }
+}
@@ -0,0 +1,405 @@
+use mailer::{
+    DelegateIndex, Delegation, FeeDiscount, InboxMode, InboxPolicy, MailerInstruction, MailerState,
+    RecipientClaim,
+};
+use mailer_client::{accounts, fees, instructions, ClientError, MailerAccounts, SendArgs};
+use solana_program_test::*;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::Transaction,
+};
+use spl_token::{
+    instruction as spl_instruction,
+    state::{Account as TokenAccount, Mint},
+};
+
+async fn process(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) {
+    let blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let mut transaction = Transaction::new_with_payer(instructions, Some(&context.payer.pubkey()));
+    transaction.sign(&all_signers, blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+}
+
+/// Create the USDC mint, initialize the mailer and its associated USDC vault
+async fn setup() -> (ProgramTestContext, MailerAccounts) {
+    let program_test = ProgramTest::new(
+        "mailer",
+        mailer::id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.pubkey();
+
+    let mint = Keypair::new();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    process(
+        &mut context,
+        &[
+            solana_sdk::system_instruction::create_account(
+                &payer,
+                &mint.pubkey(),
+                rent.minimum_balance(Mint::LEN),
+                Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_instruction::initialize_mint(&spl_token::id(), &mint.pubkey(), &payer, None, 6)
+                .unwrap(),
+        ],
+        &[&mint],
+    )
+    .await;
+
+    let accounts = MailerAccounts::new(mailer::id(), mint.pubkey());
+    process(
+        &mut context,
+        &[
+            Instruction::new_with_borsh(
+                mailer::id(),
+                &MailerInstruction::Initialize {
+                    usdc_mint: mint.pubkey(),
+                },
+                vec![
+                    AccountMeta::new(payer, true),
+                    AccountMeta::new(accounts.mailer, false),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                ],
+            ),
+            create_associated_token_account(&payer, &accounts.mailer, &mint.pubkey()),
+        ],
+        &[],
+    )
+    .await;
+
+    (context, accounts)
+}
+
+fn create_associated_token_account(payer: &Pubkey, owner: &Pubkey, mint: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: instructions::ASSOCIATED_TOKEN_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(instructions::associated_token_address(owner, mint), false),
+            AccountMeta::new_readonly(*owner, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: vec![],
+    }
+}
+
+/// Funded user with an associated USDC account
+async fn create_user(
+    context: &mut ProgramTestContext,
+    accounts: &MailerAccounts,
+    usdc: u64,
+) -> Keypair {
+    let user = Keypair::new();
+    let payer = context.payer.pubkey();
+    let mut instructions = vec![
+        solana_sdk::system_instruction::transfer(&payer, &user.pubkey(), 1_000_000_000),
+        create_associated_token_account(&payer, &user.pubkey(), &accounts.usdc_mint),
+    ];
+    if usdc > 0 {
+        instructions.push(
+            spl_instruction::mint_to(
+                &spl_token::id(),
+                &accounts.usdc_mint,
+                &accounts.usdc_account(&user.pubkey()),
+                &payer,
+                &[],
+                usdc,
+            )
+            .unwrap(),
+        );
+    }
+    process(context, &instructions, &[]).await;
+    user
+}
+
+async fn fetch<T: accounts::MailerAccount>(
+    context: &mut ProgramTestContext,
+    address: &Pubkey,
+) -> T {
+    let account = context
+        .banks_client
+        .get_account(*address)
+        .await
+        .unwrap()
+        .unwrap();
+    accounts::decode_account(&mailer::id(), address, &account).unwrap()
+}
+
+async fn usdc_balance(context: &mut ProgramTestContext, address: &Pubkey) -> u64 {
+    let account = context
+        .banks_client
+        .get_account(*address)
+        .await
+        .unwrap()
+        .unwrap();
+    TokenAccount::unpack(&account.data).unwrap().amount
+}
+
+#[tokio::test]
+async fn test_client_builders_send_claim_and_delegate() {
+    let (mut context, accounts) = setup().await;
+    let sender = create_user(&mut context, &accounts, 100_000_000).await;
+    let recipient = create_user(&mut context, &accounts, 0).await;
+    let sender_usdc = accounts.usdc_account(&sender.pubkey());
+    let state: MailerState = fetch(&mut context, &accounts.mailer).await;
+
+    // Priority send charges the estimated fee and credits the recipient's claim
+    let args = SendArgs::new(recipient.pubkey(), "Hello", "Body").priority();
+    let expected = fees::send_cost(&state, 0, &sender.pubkey(), None, None, true, 0).unwrap();
+    process(
+        &mut context,
+        &[accounts.send(&sender.pubkey(), args)],
+        &[&sender],
+    )
+    .await;
+    assert_eq!(
+        usdc_balance(&mut context, &sender_usdc).await,
+        100_000_000 - expected
+    );
+
+    let (claim_pda, _) = mailer::pda::recipient_claim(&mailer::id(), &recipient.pubkey());
+    let claim: RecipientClaim = fetch(&mut context, &claim_pda).await;
+    assert_eq!(claim.recipient, recipient.pubkey());
+    assert_eq!(claim.amount, expected * 90 / 100);
+
+    // Standard send charges only the owner share
+    let args = SendArgs::new(recipient.pubkey(), "Hi", "Body");
+    let standard = fees::send_cost(&state, 0, &sender.pubkey(), None, None, false, 0).unwrap();
+    process(
+        &mut context,
+        &[accounts.send(&sender.pubkey(), args)],
+        &[&sender],
+    )
+    .await;
+    assert_eq!(
+        usdc_balance(&mut context, &sender_usdc).await,
+        100_000_000 - expected - standard
+    );
+
+    // Claim into the recipient's associated USDC account
+    let recipient_usdc = accounts.usdc_account(&recipient.pubkey());
+    process(
+        &mut context,
+        &[accounts.claim_recipient_share(&recipient.pubkey(), &recipient_usdc, None, false)],
+        &[&recipient],
+    )
+    .await;
+    assert_eq!(
+        usdc_balance(&mut context, &recipient_usdc).await,
+        claim.amount
+    );
+
+    // Delegate, then let the delegate reject it
+    let delegate = create_user(&mut context, &accounts, 0).await;
+    let before = usdc_balance(&mut context, &sender_usdc).await;
+    process(
+        &mut context,
+        &[accounts.delegate_to(&sender.pubkey(), Some(delegate.pubkey()), None)],
+        &[&sender],
+    )
+    .await;
+    assert_eq!(
+        before - usdc_balance(&mut context, &sender_usdc).await,
+        fees::delegation_cost(&state, Some(&delegate.pubkey()))
+    );
+
+    let (delegation_pda, _) = mailer::pda::delegation(&mailer::id(), &sender.pubkey());
+    let delegation: Delegation = fetch(&mut context, &delegation_pda).await;
+    assert_eq!(delegation.delegate, Some(delegate.pubkey()));
+    let (index_pda, _) = mailer::pda::delegate_index(&mailer::id(), &delegate.pubkey());
+    let index: DelegateIndex = fetch(&mut context, &index_pda).await;
+    assert_eq!(index.count, 1);
+    assert_eq!(
+        index.accumulator,
+        DelegateIndex::accumulate(&[sender.pubkey()])
+    );
+
+    process(
+        &mut context,
+        &[accounts.reject_delegation(&delegate.pubkey(), &sender.pubkey())],
+        &[&delegate],
+    )
+    .await;
+    let delegation: Delegation = fetch(&mut context, &delegation_pda).await;
+    assert_eq!(delegation.delegate, None);
+    let index: DelegateIndex = fetch(&mut context, &index_pda).await;
+    assert_eq!((index.count, index.accumulator), (0, [0u8; 32]));
+}
+
+#[test]
+fn test_send_cost_applies_discount_and_policy() {
+    let vectors = mailer::test_vectors::account_vectors();
+    let state_bytes = &vectors
+        .iter()
+        .find(|v| v.name == "MailerState")
+        .unwrap()
+        .bytes;
+    let mut state: MailerState = accounts::decode(&accounts_key(), state_bytes).unwrap();
+    state.fee_paused = false;
+    state.pending_fee = None;
+    state.send_fee = 100_000;
+
+    let sender = Pubkey::new_unique();
+    let discount = FeeDiscount {
+        account: sender,
+        discount: 50,
+        bump: 255,
+    };
+    let policy = InboxPolicy {
+        recipient: Pubkey::new_unique(),
+        mode: InboxMode::Allowlist,
+        senders: vec![],
+        penalty_multiplier: 3,
+        bump: 255,
+        min_fee: 0,
+    };
+
+    assert_eq!(
+        fees::send_cost(&state, 0, &sender, None, None, true, 0).unwrap(),
+        100_000
+    );
+    assert_eq!(
+        fees::send_cost(&state, 0, &sender, None, None, false, 0).unwrap(),
+        10_000
+    );
+    assert_eq!(
+        fees::send_cost(&state, 0, &sender, Some(&discount), None, true, 0).unwrap(),
+        50_000
+    );
+    assert_eq!(
+        fees::send_cost(&state, 0, &sender, Some(&discount), Some(&policy), false, 0).unwrap(),
+        15_000
+    );
+    assert_eq!(fees::email_send_cost(&state, 0, Some(&discount)), 5_000);
+
+    // Priority sends must meet the recipient's minimum, tips included
+    let paywalled = InboxPolicy {
+        mode: InboxMode::Open,
+        min_fee: 150_000,
+        ..policy.clone()
+    };
+    assert!(matches!(
+        fees::send_cost(&state, 0, &sender, None, Some(&paywalled), true, 0),
+        Err(ClientError::RecipientFeeTooLow)
+    ));
+    assert_eq!(
+        fees::send_cost(&state, 0, &sender, None, Some(&paywalled), true, 50_000).unwrap(),
+        150_000
+    );
+    assert_eq!(
+        fees::send_cost(&state, 0, &sender, None, Some(&paywalled), false, 0).unwrap(),
+        10_000
+    );
+
+    let rejecting = InboxPolicy {
+        penalty_multiplier: 0,
+        ..policy
+    };
+    assert!(matches!(
+        fees::send_cost(&state, 0, &sender, None, Some(&rejecting), true, 0),
+        Err(ClientError::SenderNotAllowed)
+    ));
+    let blocking = InboxPolicy {
+        mode: InboxMode::Blocklist,
+        senders: vec![sender],
+        ..rejecting
+    };
+    assert!(matches!(
+        fees::send_cost(&state, 0, &sender, None, Some(&blocking), true, 0),
+        Err(ClientError::RecipientBlocked)
+    ));
+
+    state.fee_paused = true;
+    assert_eq!(
+        fees::send_cost(&state, 0, &sender, None, None, true, 0).unwrap(),
+        0
+    );
+    assert_eq!(fees::delegation_cost(&state, Some(&sender)), 0);
+}
+
+#[test]
+fn test_decode_reads_recipient_claims_in_the_v1_layout() {
+    let vectors = mailer::test_vectors::account_vectors();
+    let claim_bytes = &vectors
+        .iter()
+        .find(|v| v.name == "RecipientClaim")
+        .unwrap()
+        .bytes;
+
+    // The counters were appended, so a V1 claim is the current encoding without them
+    let v1_bytes = &claim_bytes[..8 + mailer::state_versions::RecipientClaimV1::LEN];
+    let claim: RecipientClaim = accounts::decode(&accounts_key(), v1_bytes).unwrap();
+    assert_eq!(claim.amount, 90_000);
+    assert_eq!(claim.messages_received, 0);
+    assert_eq!(claim.total_claimed, 0);
+
+    let claim: RecipientClaim = accounts::decode(&accounts_key(), claim_bytes).unwrap();
+    assert_eq!(claim.messages_received, 12);
+    assert_eq!(claim.total_claimed, 450_000);
+}
+
+#[test]
+fn test_decode_rejects_mismatched_accounts() {
+    let vectors = mailer::test_vectors::account_vectors();
+    let claim_bytes = &vectors
+        .iter()
+        .find(|v| v.name == "RecipientClaim")
+        .unwrap()
+        .bytes;
+
+    let claim: RecipientClaim = accounts::decode(&accounts_key(), claim_bytes).unwrap();
+    assert_eq!(claim.recipient, mailer::test_vectors::key(2));
+
+    // Accounts not yet migrated keep their legacy discriminator
+    let mut legacy_bytes = claim_bytes.clone();
+    legacy_bytes[..8].copy_from_slice(&mailer::discriminator::legacy("RecipientClaim").unwrap());
+    let claim: RecipientClaim = accounts::decode(&accounts_key(), &legacy_bytes).unwrap();
+    assert_eq!(claim.recipient, mailer::test_vectors::key(2));
+
+    assert!(matches!(
+        accounts::decode::<MailerState>(&accounts_key(), claim_bytes),
+        Err(ClientError::InvalidDiscriminator {
+            expected: "MailerState",
+            ..
+        })
+    ));
+    assert!(matches!(
+        accounts::decode::<RecipientClaim>(&accounts_key(), &claim_bytes[..4]),
+        Err(ClientError::InvalidDiscriminator { .. })
+    ));
+
+    let account = solana_sdk::account::Account {
+        lamports: 1,
+        data: claim_bytes.clone(),
+        owner: Pubkey::new_unique(),
+        executable: false,
+        rent_epoch: 0,
+    };
+    assert!(matches!(
+        accounts::decode_account::<RecipientClaim>(&mailer::id(), &accounts_key(), &account),
+        Err(ClientError::InvalidOwner { .. })
+    ));
+}
+
+fn accounts_key() -> Pubkey {
+    Pubkey::new_from_array([9; 32])
+}
@@ -46,6 +46,8 @@ pub enum ExampleInstruction {
     /// 5. `[writable]` Mailer's USDC account
     /// 6. `[]` SPL Token program
     /// 7. `[]` System program
+    /// 8. `[]` Recipient's inbox policy PDA (passed even while uncreated)
+    /// 9. `[writable]` User's message nonce PDA
     SendNotification {
         recipient: Pubkey,
         message: String,
@@ -63,12 +65,14 @@ pub enum ExampleInstruction {
     /// Example 3: Send to email address (no wallet needed)
     ///
     /// Accounts:
-    /// 0. `[signer]` User (sender)
+    /// 0. `[writable, signer]` User (sender)
     /// 1. `[]` Mailer program
     /// 2. `[]` Mailer state PDA
     /// 3. `[writable]` User's USDC account
     /// 4. `[writable]` Mailer's USDC account
     /// 5. `[]` SPL Token program
+    /// 6. `[writable]` User's message nonce PDA
+    /// 7. `[]` System program
     SendEmailNotification {
         email: String,
         subject: String,
@@ -85,7 +89,8 @@ pub enum ExampleInstruction {
 
     /// Example 5: Send via webhook
     ///
-    /// Accounts: Same as SendNotification
+    /// Accounts: Same as SendNotification, with the webhook PDA inserted before the
+    /// recipient's inbox policy PDA
     SendViaWebhook {
         recipient: Pubkey,
         webhook_id: String,
@@ -144,6 +149,8 @@ fn process_send_notification(
     let mailer_usdc = next_account_info(account_info_iter)?;
     let token_program = next_account_info(account_info_iter)?;
     let system_program = next_account_info(account_info_iter)?;
+    let inbox_policy = next_account_info(account_info_iter)?;
+    let message_nonce = next_account_info(account_info_iter)?;
 
     // Verify user is signer
     if !user.is_signer {
@@ -162,6 +169,8 @@ fn process_send_notification(
         mailer_usdc,
         token_program,
         system_program,
+        inbox_policy,
+        message_nonce,
         recipient,
         "Notification".to_string(),
         message,
@@ -195,6 +204,8 @@ fn process_send_priority_message(
     let mailer_usdc = next_account_info(account_info_iter)?;
     let token_program = next_account_info(account_info_iter)?;
     let system_program = next_account_info(account_info_iter)?;
+    let inbox_policy = next_account_info(account_info_iter)?;
+    let message_nonce = next_account_info(account_info_iter)?;
 
     if !user.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
@@ -211,6 +222,8 @@ fn process_send_priority_message(
         mailer_usdc,
         token_program,
         system_program,
+        inbox_policy,
+        message_nonce,
         recipient,
         subject,
         body,
@@ -240,6 +253,8 @@ fn process_send_email_notification(
     let user_usdc = next_account_info(account_info_iter)?;
     let mailer_usdc = next_account_info(account_info_iter)?;
     let token_program = next_account_info(account_info_iter)?;
+    let message_nonce = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
 
     if !user.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
@@ -254,6 +269,8 @@ fn process_send_email_notification(
         user_usdc,
         mailer_usdc,
         token_program,
+        message_nonce,
+        system_program,
         email,
         subject,
         body,
@@ -282,6 +299,8 @@ fn process_send_prepared_notification(
     let mailer_usdc = next_account_info(account_info_iter)?;
     let token_program = next_account_info(account_info_iter)?;
     let system_program = next_account_info(account_info_iter)?;
+    let inbox_policy = next_account_info(account_info_iter)?;
+    let message_nonce = next_account_info(account_info_iter)?;
 
     if !user.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
@@ -298,6 +317,8 @@ fn process_send_prepared_notification(
         mailer_usdc,
         token_program,
         system_program,
+        inbox_policy,
+        message_nonce,
         recipient,
         mail_id,
         false, // Standard fee
@@ -326,6 +347,9 @@ fn process_send_via_webhook(
     let mailer_usdc = next_account_info(account_info_iter)?;
     let token_program = next_account_info(account_info_iter)?;
     let system_program = next_account_info(account_info_iter)?;
+    let webhook = next_account_info(account_info_iter)?;
+    let inbox_policy = next_account_info(account_info_iter)?;
+    let message_nonce = next_account_info(account_info_iter)?;
 
     if !user.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
@@ -342,6 +366,9 @@ fn process_send_via_webhook(
         mailer_usdc,
         token_program,
         system_program,
+        webhook,
+        inbox_policy,
+        message_nonce,
         recipient,
         webhook_id,
         false, // Standard fee
@@ -0,0 +1,1089 @@
+#ifndef MAILER_FFI_H
+#define MAILER_FFI_H
+
+/* Generated by cbindgen from src/ffi.rs; do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Result of a C ABI call
+ */
+typedef enum MailerFfiStatus {
+  MailerFfiStatus_Ok = 0,
+  /**
+   * A required pointer argument was null
+   */
+  MailerFfiStatus_NullPointer = 1,
+  /**
+   * A string argument was not valid UTF-8
+   */
+  MailerFfiStatus_InvalidUtf8 = 2,
+  /**
+   * An enum argument was out of range
+   */
+  MailerFfiStatus_InvalidEnum = 3,
+  /**
+   * `out_capacity` is smaller than the instruction data; `out_len` holds the required length
+   */
+  MailerFfiStatus_BufferTooSmall = 4,
+  /**
+   * The instruction could not be serialized
+   */
+  MailerFfiStatus_SerializationFailed = 5,
+} MailerFfiStatus;
+
+/**
+ * Borrowed byte string: `len` bytes at `ptr`
+ */
+typedef struct MailerBytes {
+  const uint8_t *ptr;
+  size_t len;
+} MailerBytes;
+
+/**
+ * Optional u64, `None` unless `present`
+ */
+typedef struct MailerOptionU64 {
+  bool present;
+  uint64_t value;
+} MailerOptionU64;
+
+/**
+ * Claim split entry: `bps` basis points of the claim paid to the token account `destination`
+ */
+typedef struct MailerClaimSplit {
+  uint8_t destination[32];
+  uint16_t bps;
+} MailerClaimSplit;
+
+/**
+ * Maximum payload field lengths in bytes; 0 leaves a field unlimited
+ */
+typedef struct MailerPayloadLimits {
+  uint16_t max_subject_len;
+  uint16_t max_body_len;
+  uint16_t max_mail_id_len;
+  uint16_t max_email_len;
+  uint16_t max_webhook_id_len;
+} MailerPayloadLimits;
+
+/**
+ * Volume discount tier: `discount` percent off once `min_fees_paid` USDC fees are paid
+ */
+typedef struct MailerVolumeTier {
+  uint64_t min_fees_paid;
+  uint8_t discount;
+} MailerVolumeTier;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Write the Mailer program ID (32 bytes) to `out`
+ */
+enum MailerFfiStatus mailer_program_id(uint8_t *out);
+
+/**
+ * Serialize `Initialize`
+ */
+enum MailerFfiStatus mailer_ix_initialize(const uint8_t *usdc_mint,
+                                          uint8_t *out,
+                                          size_t out_capacity,
+                                          size_t *out_len);
+
+/**
+ * Serialize `Send`
+ */
+enum MailerFfiStatus mailer_ix_send(const uint8_t *to,
+                                    struct MailerBytes subject,
+                                    struct MailerBytes body,
+                                    bool revenue_share_to_receiver,
+                                    bool resolve_sender_to_name,
+                                    bool request_read_receipt,
+                                    struct MailerOptionU64 receipt_nonce,
+                                    struct MailerBytes memo,
+                                    bool strict,
+                                    const uint8_t *thread_id,
+                                    const uint8_t *in_reply_to,
+                                    bool allow_downgrade,
+                                    uint64_t tip,
+                                    bool bridge,
+                                    struct MailerOptionU64 idempotency_nonce,
+                                    uint8_t *out,
+                                    size_t out_capacity,
+                                    size_t *out_len);
+
+/**
+ * Serialize `SendPrepared`
+ */
+enum MailerFfiStatus mailer_ix_send_prepared(const uint8_t *to,
+                                             struct MailerBytes mail_id,
+                                             bool revenue_share_to_receiver,
+                                             bool resolve_sender_to_name,
+                                             bool strict,
+                                             const uint8_t *thread_id,
+                                             const uint8_t *in_reply_to,
+                                             bool allow_downgrade,
+                                             uint64_t tip,
+                                             uint8_t *out,
+                                             size_t out_capacity,
+                                             size_t *out_len);
+
+/**
+ * Serialize `SendToEmail`
+ */
+enum MailerFfiStatus mailer_ix_send_to_email(struct MailerBytes to_email,
+                                             struct MailerBytes subject,
+                                             struct MailerBytes body,
+                                             bool strict,
+                                             uint8_t *out,
+                                             size_t out_capacity,
+                                             size_t *out_len);
+
+/**
+ * Serialize `SendPreparedToEmail`
+ */
+enum MailerFfiStatus mailer_ix_send_prepared_to_email(struct MailerBytes to_email,
+                                                      struct MailerBytes mail_id,
+                                                      bool strict,
+                                                      uint8_t *out,
+                                                      size_t out_capacity,
+                                                      size_t *out_len);
+
+/**
+ * Serialize `SendThroughWebhook`
+ */
+enum MailerFfiStatus mailer_ix_send_through_webhook(const uint8_t *to,
+                                                    struct MailerBytes webhook_id,
+                                                    bool revenue_share_to_receiver,
+                                                    bool resolve_sender_to_name,
+                                                    bool strict,
+                                                    const uint8_t *thread_id,
+                                                    const uint8_t *in_reply_to,
+                                                    bool allow_downgrade,
+                                                    uint64_t tip,
+                                                    uint8_t *out,
+                                                    size_t out_capacity,
+                                                    size_t *out_len);
+
+/**
+ * Serialize `ClaimRecipientShare`
+ */
+enum MailerFfiStatus mailer_ix_claim_recipient_share(struct MailerOptionU64 amount,
+                                                     const struct MailerClaimSplit *split,
+                                                     size_t split_count,
+                                                     const uint8_t *close_to,
+                                                     uint8_t *out,
+                                                     size_t out_capacity,
+                                                     size_t *out_len);
+
+/**
+ * Serialize `ClaimOwnerShare`
+ */
+enum MailerFfiStatus mailer_ix_claim_owner_share(uint8_t *out,
+                                                 size_t out_capacity,
+                                                 size_t *out_len);
+
+/**
+ * Serialize `SetFee`
+ */
+enum MailerFfiStatus mailer_ix_set_fee(uint64_t new_fee,
+                                       struct MailerOptionU64 expected_current_value,
+                                       uint8_t *out,
+                                       size_t out_capacity,
+                                       size_t *out_len);
+
+/**
+ * Serialize `DelegateTo`
+ */
+enum MailerFfiStatus mailer_ix_delegate_to(const uint8_t *delegate,
+                                           uint8_t *out,
+                                           size_t out_capacity,
+                                           size_t *out_len);
+
+/**
+ * Serialize `RejectDelegation`
+ */
+enum MailerFfiStatus mailer_ix_reject_delegation(uint8_t *out,
+                                                 size_t out_capacity,
+                                                 size_t *out_len);
+
+/**
+ * Serialize `SetDelegationFee`
+ */
+enum MailerFfiStatus mailer_ix_set_delegation_fee(uint64_t new_fee,
+                                                  struct MailerOptionU64 expected_current_value,
+                                                  uint8_t *out,
+                                                  size_t out_capacity,
+                                                  size_t *out_len);
+
+/**
+ * Serialize `SetCustomFeePercentage`
+ */
+enum MailerFfiStatus mailer_ix_set_custom_fee_percentage(const uint8_t *account,
+                                                         uint8_t percentage,
+                                                         uint8_t *out,
+                                                         size_t out_capacity,
+                                                         size_t *out_len);
+
+/**
+ * Serialize `ClearCustomFeePercentage`
+ */
+enum MailerFfiStatus mailer_ix_clear_custom_fee_percentage(const uint8_t *account,
+                                                           uint8_t *out,
+                                                           size_t out_capacity,
+                                                           size_t *out_len);
+
+/**
+ * Serialize `Pause`
+ */
+enum MailerFfiStatus mailer_ix_pause(uint8_t *out, size_t out_capacity, size_t *out_len);
+
+/**
+ * Serialize `Unpause`
+ */
+enum MailerFfiStatus mailer_ix_unpause(uint8_t *out, size_t out_capacity, size_t *out_len);
+
+/**
+ * Serialize `DistributeClaimableFunds`
+ */
+enum MailerFfiStatus mailer_ix_distribute_claimable_funds(const uint8_t *recipient,
+                                                          uint8_t *out,
+                                                          size_t out_capacity,
+                                                          size_t *out_len);
+
+/**
+ * Serialize `ClaimExpiredShares`
+ */
+enum MailerFfiStatus mailer_ix_claim_expired_shares(const uint8_t *recipient,
+                                                    uint8_t *out,
+                                                    size_t out_capacity,
+                                                    size_t *out_len);
+
+/**
+ * Serialize `EmergencyUnpause`
+ */
+enum MailerFfiStatus mailer_ix_emergency_unpause(uint8_t *out,
+                                                 size_t out_capacity,
+                                                 size_t *out_len);
+
+/**
+ * Serialize `SetFeePaused`
+ */
+enum MailerFfiStatus mailer_ix_set_fee_paused(bool fee_paused,
+                                              uint8_t *out,
+                                              size_t out_capacity,
+                                              size_t *out_len);
+
+/**
+ * Serialize `SetRecipientPrefs`
+ */
+enum MailerFfiStatus mailer_ix_set_recipient_prefs(bool read_receipts_opt_out,
+                                                   uint8_t *out,
+                                                   size_t out_capacity,
+                                                   size_t *out_len);
+
+/**
+ * Serialize `AcknowledgeMessage`
+ */
+enum MailerFfiStatus mailer_ix_acknowledge_message(const uint8_t *sender,
+                                                   struct MailerOptionU64 receipt_nonce,
+                                                   uint8_t *out,
+                                                   size_t out_capacity,
+                                                   size_t *out_len);
+
+/**
+ * Serialize `SetProtocolSendsEnabled`
+ */
+enum MailerFfiStatus mailer_ix_set_protocol_sends_enabled(bool enabled,
+                                                          uint8_t *out,
+                                                          size_t out_capacity,
+                                                          size_t *out_len);
+
+/**
+ * Serialize `SendProtocolMessage`
+ */
+enum MailerFfiStatus mailer_ix_send_protocol_message(const uint8_t *to,
+                                                     struct MailerBytes subject,
+                                                     struct MailerBytes body,
+                                                     uint8_t *out,
+                                                     size_t out_capacity,
+                                                     size_t *out_len);
+
+/**
+ * Serialize `TransferOwnership`
+ */
+enum MailerFfiStatus mailer_ix_transfer_ownership(const uint8_t *new_owner,
+                                                  uint8_t *out,
+                                                  size_t out_capacity,
+                                                  size_t *out_len);
+
+/**
+ * Serialize `AcceptOwnership`
+ */
+enum MailerFfiStatus mailer_ix_accept_ownership(uint8_t *out, size_t out_capacity, size_t *out_len);
+
+/**
+ * Serialize `SetAdmins`
+ */
+enum MailerFfiStatus mailer_ix_set_admins(const uint8_t *admins,
+                                          size_t admins_count,
+                                          uint8_t threshold,
+                                          uint8_t *out,
+                                          size_t out_capacity,
+                                          size_t *out_len);
+
+/**
+ * Serialize `SweepExpiredPage`
+ */
+enum MailerFfiStatus mailer_ix_sweep_expired_page(uint8_t max_items,
+                                                  uint8_t *out,
+                                                  size_t out_capacity,
+                                                  size_t *out_len);
+
+/**
+ * Serialize `ResetSweepCursor`
+ */
+enum MailerFfiStatus mailer_ix_reset_sweep_cursor(uint8_t *out,
+                                                  size_t out_capacity,
+                                                  size_t *out_len);
+
+/**
+ * Serialize `ProposeFee`
+ */
+enum MailerFfiStatus mailer_ix_propose_fee(uint64_t new_fee,
+                                           uint8_t *out,
+                                           size_t out_capacity,
+                                           size_t *out_len);
+
+/**
+ * Serialize `SetFeeTimelock`
+ */
+enum MailerFfiStatus mailer_ix_set_fee_timelock(int64_t delay,
+                                                uint8_t *out,
+                                                size_t out_capacity,
+                                                size_t *out_len);
+
+/**
+ * Serialize `SetInboxPolicy`
+ */
+enum MailerFfiStatus mailer_ix_set_inbox_policy(uint8_t mode,
+                                                const uint8_t *senders,
+                                                size_t senders_count,
+                                                uint8_t penalty_multiplier,
+                                                uint64_t min_fee,
+                                                uint8_t *out,
+                                                size_t out_capacity,
+                                                size_t *out_len);
+
+/**
+ * Serialize `ClaimRecipientShareToAta`
+ */
+enum MailerFfiStatus mailer_ix_claim_recipient_share_to_ata(uint8_t *out,
+                                                            size_t out_capacity,
+                                                            size_t *out_len);
+
+/**
+ * Serialize `RegisterSessionKey`
+ */
+enum MailerFfiStatus mailer_ix_register_session_key(const uint8_t *session_key,
+                                                    uint8_t scope,
+                                                    int64_t expires_at,
+                                                    uint8_t *out,
+                                                    size_t out_capacity,
+                                                    size_t *out_len);
+
+/**
+ * Serialize `RevokeSessionKey`
+ */
+enum MailerFfiStatus mailer_ix_revoke_session_key(const uint8_t *session_key,
+                                                  uint8_t *out,
+                                                  size_t out_capacity,
+                                                  size_t *out_len);
+
+/**
+ * Serialize `SetPayoutAddress`
+ */
+enum MailerFfiStatus mailer_ix_set_payout_address(const uint8_t *payout_token_account,
+                                                  uint8_t *out,
+                                                  size_t out_capacity,
+                                                  size_t *out_len);
+
+/**
+ * Serialize `SetConversionVault`
+ */
+enum MailerFfiStatus mailer_ix_set_conversion_vault(const uint8_t *mint,
+                                                    bool enabled,
+                                                    uint8_t *out,
+                                                    size_t out_capacity,
+                                                    size_t *out_len);
+
+/**
+ * Serialize `ClaimRecipientShareAs`
+ */
+enum MailerFfiStatus mailer_ix_claim_recipient_share_as(const uint8_t *mint,
+                                                        uint8_t *out,
+                                                        size_t out_capacity,
+                                                        size_t *out_len);
+
+/**
+ * Serialize `GetConfig`
+ */
+enum MailerFfiStatus mailer_ix_get_config(uint8_t *out, size_t out_capacity, size_t *out_len);
+
+/**
+ * Serialize `CreatePreauthorization`
+ */
+enum MailerFfiStatus mailer_ix_create_preauthorization(const uint8_t *relayer,
+                                                       struct MailerBytes webhook_id,
+                                                       uint64_t max_sends,
+                                                       uint64_t max_fee_per_send,
+                                                       int64_t expires_at,
+                                                       uint8_t *out,
+                                                       size_t out_capacity,
+                                                       size_t *out_len);
+
+/**
+ * Serialize `RevokePreauthorization`
+ */
+enum MailerFfiStatus mailer_ix_revoke_preauthorization(const uint8_t *relayer,
+                                                       uint8_t *out,
+                                                       size_t out_capacity,
+                                                       size_t *out_len);
+
+/**
+ * Serialize `SendPreauthorized`
+ */
+enum MailerFfiStatus mailer_ix_send_preauthorized(const uint8_t *to,
+                                                  struct MailerBytes webhook_id,
+                                                  bool revenue_share_to_receiver,
+                                                  uint8_t *out,
+                                                  size_t out_capacity,
+                                                  size_t *out_len);
+
+/**
+ * Serialize `SetInstructionDisabled`
+ */
+enum MailerFfiStatus mailer_ix_set_instruction_disabled(uint8_t tag,
+                                                        bool disabled,
+                                                        uint8_t *out,
+                                                        size_t out_capacity,
+                                                        size_t *out_len);
+
+/**
+ * Serialize `MigrateDiscriminator`
+ */
+enum MailerFfiStatus mailer_ix_migrate_discriminator(uint8_t *out,
+                                                     size_t out_capacity,
+                                                     size_t *out_len);
+
+/**
+ * Serialize `CloseRecipientClaim`
+ */
+enum MailerFfiStatus mailer_ix_close_recipient_claim(const uint8_t *recipient,
+                                                     uint8_t *out,
+                                                     size_t out_capacity,
+                                                     size_t *out_len);
+
+/**
+ * Serialize `CloseDelegation`
+ */
+enum MailerFfiStatus mailer_ix_close_delegation(uint8_t *out, size_t out_capacity, size_t *out_len);
+
+/**
+ * Serialize `CloseFeeDiscount`
+ */
+enum MailerFfiStatus mailer_ix_close_fee_discount(const uint8_t *account,
+                                                  uint8_t *out,
+                                                  size_t out_capacity,
+                                                  size_t *out_len);
+
+/**
+ * Serialize `MigrateAccountVersion`
+ */
+enum MailerFfiStatus mailer_ix_migrate_account_version(uint8_t kind,
+                                                       uint8_t *out,
+                                                       size_t out_capacity,
+                                                       size_t *out_len);
+
+/**
+ * Serialize `SetSpamOracle`
+ */
+enum MailerFfiStatus mailer_ix_set_spam_oracle(const uint8_t *oracle,
+                                               struct MailerOptionU64 threshold,
+                                               uint8_t *out,
+                                               size_t out_capacity,
+                                               size_t *out_len);
+
+/**
+ * Serialize `SetRateLimit`
+ */
+enum MailerFfiStatus mailer_ix_set_rate_limit(uint64_t max_sends,
+                                              int64_t window,
+                                              uint8_t *out,
+                                              size_t out_capacity,
+                                              size_t *out_len);
+
+/**
+ * Serialize `CreatePromo`
+ */
+enum MailerFfiStatus mailer_ix_create_promo(const uint8_t *code_hash,
+                                            uint8_t discount,
+                                            uint64_t max_redemptions,
+                                            int64_t expiry,
+                                            uint8_t *out,
+                                            size_t out_capacity,
+                                            size_t *out_len);
+
+/**
+ * Serialize `RedeemPromo`
+ */
+enum MailerFfiStatus mailer_ix_redeem_promo(struct MailerBytes code,
+                                            uint8_t *out,
+                                            size_t out_capacity,
+                                            size_t *out_len);
+
+/**
+ * Serialize `ClaimAndSend`
+ */
+enum MailerFfiStatus mailer_ix_claim_and_send(struct MailerOptionU64 amount,
+                                              const uint8_t *to,
+                                              struct MailerBytes subject,
+                                              struct MailerBytes body,
+                                              bool revenue_share_to_receiver,
+                                              bool resolve_sender_to_name,
+                                              bool request_read_receipt,
+                                              struct MailerOptionU64 receipt_nonce,
+                                              struct MailerBytes memo,
+                                              bool strict,
+                                              uint8_t *out,
+                                              size_t out_capacity,
+                                              size_t *out_len);
+
+/**
+ * Serialize `AddFeeMint`
+ */
+enum MailerFfiStatus mailer_ix_add_fee_mint(const uint8_t *mint,
+                                            uint64_t send_fee,
+                                            uint64_t delegation_fee,
+                                            uint8_t *out,
+                                            size_t out_capacity,
+                                            size_t *out_len);
+
+/**
+ * Serialize `RemoveFeeMint`
+ */
+enum MailerFfiStatus mailer_ix_remove_fee_mint(const uint8_t *mint,
+                                               uint8_t *out,
+                                               size_t out_capacity,
+                                               size_t *out_len);
+
+/**
+ * Serialize `SetLamportFee`
+ */
+enum MailerFfiStatus mailer_ix_set_lamport_fee(uint64_t lamport_fee,
+                                               uint8_t *out,
+                                               size_t out_capacity,
+                                               size_t *out_len);
+
+/**
+ * Serialize `ClaimOwnerLamports`
+ */
+enum MailerFfiStatus mailer_ix_claim_owner_lamports(uint8_t *out,
+                                                    size_t out_capacity,
+                                                    size_t *out_len);
+
+/**
+ * Serialize `SetFeeOracle`
+ */
+enum MailerFfiStatus mailer_ix_set_fee_oracle(const uint8_t *oracle,
+                                              uint64_t target_fee,
+                                              int64_t max_age,
+                                              uint8_t *out,
+                                              size_t out_capacity,
+                                              size_t *out_len);
+
+/**
+ * Serialize `SetClaimCreationPolicy`
+ */
+enum MailerFfiStatus mailer_ix_set_claim_creation_policy(uint8_t policy,
+                                                         uint8_t *out,
+                                                         size_t out_capacity,
+                                                         size_t *out_len);
+
+/**
+ * Serialize `CloseFundedClaim`
+ */
+enum MailerFfiStatus mailer_ix_close_funded_claim(uint8_t *out,
+                                                  size_t out_capacity,
+                                                  size_t *out_len);
+
+/**
+ * Serialize `SendEncrypted`
+ */
+enum MailerFfiStatus mailer_ix_send_encrypted(const uint8_t *to,
+                                              const uint8_t *ephemeral_key,
+                                              const uint8_t *nonce,
+                                              struct MailerBytes ciphertext,
+                                              bool revenue_share_to_receiver,
+                                              bool strict,
+                                              uint8_t *out,
+                                              size_t out_capacity,
+                                              size_t *out_len);
+
+/**
+ * Serialize `RegisterWebhook`
+ */
+enum MailerFfiStatus mailer_ix_register_webhook(struct MailerBytes webhook_id,
+                                                const uint8_t *endpoint_hash,
+                                                uint64_t surcharge,
+                                                uint8_t *out,
+                                                size_t out_capacity,
+                                                size_t *out_len);
+
+/**
+ * Serialize `UpdateWebhook`
+ */
+enum MailerFfiStatus mailer_ix_update_webhook(const uint8_t *endpoint_hash,
+                                              uint64_t surcharge,
+                                              uint8_t *out,
+                                              size_t out_capacity,
+                                              size_t *out_len);
+
+/**
+ * Serialize `RevokeWebhook`
+ */
+enum MailerFfiStatus mailer_ix_revoke_webhook(uint8_t *out, size_t out_capacity, size_t *out_len);
+
+/**
+ * Serialize `ClaimWebhookRevenue`
+ */
+enum MailerFfiStatus mailer_ix_claim_webhook_revenue(uint8_t *out,
+                                                     size_t out_capacity,
+                                                     size_t *out_len);
+
+/**
+ * Serialize `InitializeClosureLog`
+ */
+enum MailerFfiStatus mailer_ix_initialize_closure_log(uint8_t *out,
+                                                      size_t out_capacity,
+                                                      size_t *out_len);
+
+/**
+ * Serialize `SendAsDelegator`
+ */
+enum MailerFfiStatus mailer_ix_send_as_delegator(const uint8_t *to,
+                                                 struct MailerBytes subject,
+                                                 struct MailerBytes body,
+                                                 bool revenue_share_to_receiver,
+                                                 bool strict,
+                                                 uint8_t *out,
+                                                 size_t out_capacity,
+                                                 size_t *out_len);
+
+/**
+ * Serialize `SanctionAddress`
+ */
+enum MailerFfiStatus mailer_ix_sanction_address(const uint8_t *address,
+                                                uint8_t *out,
+                                                size_t out_capacity,
+                                                size_t *out_len);
+
+/**
+ * Serialize `UnsanctionAddress`
+ */
+enum MailerFfiStatus mailer_ix_unsanction_address(const uint8_t *address,
+                                                  uint8_t *out,
+                                                  size_t out_capacity,
+                                                  size_t *out_len);
+
+/**
+ * Serialize `SetSanctionsEnabled`
+ */
+enum MailerFfiStatus mailer_ix_set_sanctions_enabled(bool enabled,
+                                                     uint8_t *out,
+                                                     size_t out_capacity,
+                                                     size_t *out_len);
+
+/**
+ * Serialize `SetBackupMint`
+ */
+enum MailerFfiStatus mailer_ix_set_backup_mint(const uint8_t *mint,
+                                               uint8_t *out,
+                                               size_t out_capacity,
+                                               size_t *out_len);
+
+/**
+ * Serialize `InitializeStats`
+ */
+enum MailerFfiStatus mailer_ix_initialize_stats(uint8_t *out, size_t out_capacity, size_t *out_len);
+
+/**
+ * Serialize `SetClaimNoExpiry`
+ */
+enum MailerFfiStatus mailer_ix_set_claim_no_expiry(bool enabled,
+                                                   uint8_t *out,
+                                                   size_t out_capacity,
+                                                   size_t *out_len);
+
+/**
+ * Serialize `ClaimExpiredSharesBatch`
+ */
+enum MailerFfiStatus mailer_ix_claim_expired_shares_batch(uint8_t *out,
+                                                          size_t out_capacity,
+                                                          size_t *out_len);
+
+/**
+ * Serialize `SetPauseFlags`
+ */
+enum MailerFfiStatus mailer_ix_set_pause_flags(uint8_t flags,
+                                               uint8_t *out,
+                                               size_t out_capacity,
+                                               size_t *out_len);
+
+/**
+ * Serialize `SetKycVerifier`
+ */
+enum MailerFfiStatus mailer_ix_set_kyc_verifier(const uint8_t *verifier,
+                                                uint8_t *out,
+                                                size_t out_capacity,
+                                                size_t *out_len);
+
+/**
+ * Serialize `SetDelegationRefundWindow`
+ */
+enum MailerFfiStatus mailer_ix_set_delegation_refund_window(int64_t window,
+                                                            uint8_t *out,
+                                                            size_t out_capacity,
+                                                            size_t *out_len);
+
+/**
+ * Serialize `AcceptDelegation`
+ */
+enum MailerFfiStatus mailer_ix_accept_delegation(uint8_t *out,
+                                                 size_t out_capacity,
+                                                 size_t *out_len);
+
+/**
+ * Serialize `RefundDelegationFee`
+ */
+enum MailerFfiStatus mailer_ix_refund_delegation_fee(uint8_t *out,
+                                                     size_t out_capacity,
+                                                     size_t *out_len);
+
+/**
+ * Serialize `RecordDeliveryStatus`
+ */
+enum MailerFfiStatus mailer_ix_record_delivery_status(const uint8_t *to,
+                                                      uint64_t nonce,
+                                                      uint64_t sent_slot,
+                                                      bool delivered,
+                                                      uint8_t *out,
+                                                      size_t out_capacity,
+                                                      size_t *out_len);
+
+/**
+ * Serialize `GetWebhookStats`
+ */
+enum MailerFfiStatus mailer_ix_get_webhook_stats(uint8_t *out,
+                                                 size_t out_capacity,
+                                                 size_t *out_len);
+
+/**
+ * Serialize `DepositSendCredits`
+ */
+enum MailerFfiStatus mailer_ix_deposit_send_credits(uint64_t amount,
+                                                    uint8_t *out,
+                                                    size_t out_capacity,
+                                                    size_t *out_len);
+
+/**
+ * Serialize `WithdrawSendCredits`
+ */
+enum MailerFfiStatus mailer_ix_withdraw_send_credits(struct MailerOptionU64 amount,
+                                                     uint8_t *out,
+                                                     size_t out_capacity,
+                                                     size_t *out_len);
+
+/**
+ * Serialize `SetClaimCap`
+ */
+enum MailerFfiStatus mailer_ix_set_claim_cap(uint64_t cap,
+                                             uint8_t *out,
+                                             size_t out_capacity,
+                                             size_t *out_len);
+
+/**
+ * Serialize `SetPlan`
+ */
+enum MailerFfiStatus mailer_ix_set_plan(uint64_t plan_id,
+                                        uint64_t price,
+                                        int64_t duration,
+                                        uint64_t max_messages,
+                                        bool active,
+                                        uint8_t *out,
+                                        size_t out_capacity,
+                                        size_t *out_len);
+
+/**
+ * Serialize `Subscribe`
+ */
+enum MailerFfiStatus mailer_ix_subscribe(uint64_t plan_id,
+                                         uint8_t *out,
+                                         size_t out_capacity,
+                                         size_t *out_len);
+
+/**
+ * Serialize `RenewSubscription`
+ */
+enum MailerFfiStatus mailer_ix_renew_subscription(uint8_t *out,
+                                                  size_t out_capacity,
+                                                  size_t *out_len);
+
+/**
+ * Serialize `SetPayloadLimits`
+ */
+enum MailerFfiStatus mailer_ix_set_payload_limits(struct MailerPayloadLimits limits,
+                                                  uint16_t fee_tier_len,
+                                                  uint16_t fee_tier_percent,
+                                                  uint8_t *out,
+                                                  size_t out_capacity,
+                                                  size_t *out_len);
+
+/**
+ * Serialize `SendToEmailHash`
+ */
+enum MailerFfiStatus mailer_ix_send_to_email_hash(struct MailerBytes email_hash,
+                                                  uint64_t hint_id,
+                                                  struct MailerBytes subject,
+                                                  struct MailerBytes body,
+                                                  bool strict,
+                                                  uint8_t *out,
+                                                  size_t out_capacity,
+                                                  size_t *out_len);
+
+/**
+ * Serialize `SetSolvencyThreshold`
+ */
+enum MailerFfiStatus mailer_ix_set_solvency_threshold(uint16_t min_coverage_bps,
+                                                      uint8_t *out,
+                                                      size_t out_capacity,
+                                                      size_t *out_len);
+
+/**
+ * Serialize `ExtendClaimWindow`
+ */
+enum MailerFfiStatus mailer_ix_extend_claim_window(uint8_t *out,
+                                                   size_t out_capacity,
+                                                   size_t *out_len);
+
+/**
+ * Serialize `SetClaimBeneficiary`
+ */
+enum MailerFfiStatus mailer_ix_set_claim_beneficiary(const uint8_t *beneficiary,
+                                                     uint8_t *out,
+                                                     size_t out_capacity,
+                                                     size_t *out_len);
+
+/**
+ * Serialize `ClaimAsBeneficiary`
+ */
+enum MailerFfiStatus mailer_ix_claim_as_beneficiary(const uint8_t *recipient,
+                                                    uint8_t *out,
+                                                    size_t out_capacity,
+                                                    size_t *out_len);
+
+/**
+ * Serialize `MigrateState`
+ */
+enum MailerFfiStatus mailer_ix_migrate_state(uint8_t *out, size_t out_capacity, size_t *out_len);
+
+/**
+ * Serialize `SetTreasury`
+ */
+enum MailerFfiStatus mailer_ix_set_treasury(const uint8_t *token_account,
+                                            uint8_t *out,
+                                            size_t out_capacity,
+                                            size_t *out_len);
+
+/**
+ * Serialize `VerifySolvency`
+ */
+enum MailerFfiStatus mailer_ix_verify_solvency(uint8_t *out, size_t out_capacity, size_t *out_len);
+
+/**
+ * Serialize `SetClaimMerkleRoot`
+ * `merkle_root` points to 32 bytes.
+ */
+enum MailerFfiStatus mailer_ix_set_claim_merkle_root(uint64_t distribution_id,
+                                                     const uint8_t *merkle_root,
+                                                     uint64_t leaf_count,
+                                                     uint64_t total,
+                                                     uint8_t *out,
+                                                     size_t out_capacity,
+                                                     size_t *out_len);
+
+/**
+ * Serialize `ClaimFromMerkle`
+ * `proof` points to `proof_count` consecutive 32-byte hashes.
+ */
+enum MailerFfiStatus mailer_ix_claim_from_merkle(uint64_t distribution_id,
+                                                 uint64_t index,
+                                                 uint64_t amount,
+                                                 const uint8_t *proof,
+                                                 size_t proof_count,
+                                                 uint8_t *out,
+                                                 size_t out_capacity,
+                                                 size_t *out_len);
+
+/**
+ * Serialize `SendEscrowed`
+ */
+enum MailerFfiStatus mailer_ix_send_escrowed(const uint8_t *to,
+                                             struct MailerBytes subject,
+                                             struct MailerBytes body,
+                                             uint64_t nonce,
+                                             uint8_t *out,
+                                             size_t out_capacity,
+                                             size_t *out_len);
+
+/**
+ * Serialize `AcceptMessage`
+ */
+enum MailerFfiStatus mailer_ix_accept_message(const uint8_t *sender,
+                                              uint64_t nonce,
+                                              uint8_t *out,
+                                              size_t out_capacity,
+                                              size_t *out_len);
+
+/**
+ * Serialize `ReclaimEscrow`
+ */
+enum MailerFfiStatus mailer_ix_reclaim_escrow(uint64_t nonce,
+                                              uint8_t *out,
+                                              size_t out_capacity,
+                                              size_t *out_len);
+
+/**
+ * Serialize `CreateList`
+ */
+enum MailerFfiStatus mailer_ix_create_list(uint64_t list_id,
+                                           uint8_t *out,
+                                           size_t out_capacity,
+                                           size_t *out_len);
+
+/**
+ * Serialize `AddListMember`
+ */
+enum MailerFfiStatus mailer_ix_add_list_member(uint64_t list_id,
+                                               uint64_t chunk,
+                                               const uint8_t *member,
+                                               uint8_t *out,
+                                               size_t out_capacity,
+                                               size_t *out_len);
+
+/**
+ * Serialize `RemoveListMember`
+ */
+enum MailerFfiStatus mailer_ix_remove_list_member(uint64_t list_id,
+                                                  uint64_t chunk,
+                                                  const uint8_t *member,
+                                                  uint8_t *out,
+                                                  size_t out_capacity,
+                                                  size_t *out_len);
+
+/**
+ * Serialize `SendToList`
+ */
+enum MailerFfiStatus mailer_ix_send_to_list(uint64_t list_id,
+                                            struct MailerBytes subject,
+                                            struct MailerBytes body,
+                                            uint8_t *out,
+                                            size_t out_capacity,
+                                            size_t *out_len);
+
+/**
+ * Serialize `SendToDomain`
+ */
+enum MailerFfiStatus mailer_ix_send_to_domain(struct MailerBytes domain,
+                                              struct MailerBytes subject,
+                                              struct MailerBytes body,
+                                              bool revenue_share_to_receiver,
+                                              bool strict,
+                                              uint8_t *out,
+                                              size_t out_capacity,
+                                              size_t *out_len);
+
+/**
+ * Serialize `BindEvmAddress`
+ * `evm_address` points to 20 bytes.
+ */
+enum MailerFfiStatus mailer_ix_bind_evm_address(const uint8_t *evm_address,
+                                                uint8_t *out,
+                                                size_t out_capacity,
+                                                size_t *out_len);
+
+/**
+ * Serialize `SendToEvmAddress`
+ * `evm_address` points to 20 bytes.
+ */
+enum MailerFfiStatus mailer_ix_send_to_evm_address(const uint8_t *evm_address,
+                                                   struct MailerBytes subject,
+                                                   struct MailerBytes body,
+                                                   bool revenue_share_to_receiver,
+                                                   bool strict,
+                                                   uint8_t *out,
+                                                   size_t out_capacity,
+                                                   size_t *out_len);
+
+/**
+ * Serialize `SetWormholeBridge`
+ * `core_bridge` points to 32 bytes, or is null to switch bridging off.
+ */
+enum MailerFfiStatus mailer_ix_set_wormhole_bridge(const uint8_t *core_bridge,
+                                                   uint8_t *out,
+                                                   size_t out_capacity,
+                                                   size_t *out_len);
+
+/**
+ * Serialize `GetFee`
+ */
+enum MailerFfiStatus mailer_ix_get_fee(uint8_t *out, size_t out_capacity, size_t *out_len);
+
+/**
+ * Serialize `GetClaimable`
+ * `account` points to 32 bytes.
+ */
+enum MailerFfiStatus mailer_ix_get_claimable(const uint8_t *account,
+                                             uint8_t *out,
+                                             size_t out_capacity,
+                                             size_t *out_len);
+
+/**
+ * Serialize `GetDelegation`
+ * `delegator` points to 32 bytes.
+ */
+enum MailerFfiStatus mailer_ix_get_delegation(const uint8_t *delegator,
+                                              uint8_t *out,
+                                              size_t out_capacity,
+                                              size_t *out_len);
+
+/**
+ * Serialize `SetDiscountSchedule`
+ */
+enum MailerFfiStatus mailer_ix_set_discount_schedule(const struct MailerVolumeTier *tiers,
+                                                     size_t tiers_count,
+                                                     uint8_t *out,
+                                                     size_t out_capacity,
+                                                     size_t *out_len);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* MAILER_FFI_H */
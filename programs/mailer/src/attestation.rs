@@ -0,0 +1,50 @@
+//! # Verifier Attestation Interface
+//!
+//! Regulated deployments can register a verifier program with `SetKycVerifier`. Priority
+//! sends (`revenue_share_to_receiver`) then require the sender to hold a valid attestation
+//! issued by that program, and are rejected with `VerificationRequired` otherwise; standard
+//! sends are never checked.
+//!
+//! An attestation is an account owned by the verifier program at [`attestation_address`],
+//! whose data starts with the attested wallet and a little-endian `i64` expiry timestamp
+//! (0 never expires), as written by [`encode`]. Senders pass it as a trailing account.
+//!
+//! ```rust
+//! use mailer::attestation;
+//! use solana_program::pubkey::Pubkey;
+//!
+//! let wallet = Pubkey::new_unique();
+//! let data = attestation::encode(&wallet, 1_700_000_000);
+//! assert!(attestation::is_valid(&data, &wallet, 1_699_999_999));
+//! assert!(!attestation::is_valid(&data, &wallet, 1_700_000_001));
+//! ```
+
+use solana_program::pubkey::Pubkey;
+
+/// Seed prefix of attestation accounts, under the verifier program
+pub const SEED: &[u8] = b"attestation";
+
+/// Length of the attestation data read by the Mailer program
+pub const LEN: usize = 32 + 8;
+
+/// Address of `wallet`'s attestation issued by `verifier`
+pub fn attestation_address(verifier: &Pubkey, wallet: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[SEED, wallet.as_ref()], verifier)
+}
+
+/// Attestation data for `wallet`, valid until `expires_at` (0 never expires)
+pub fn encode(wallet: &Pubkey, expires_at: i64) -> [u8; LEN] {
+    let mut data = [0u8; LEN];
+    data[..32].copy_from_slice(wallet.as_ref());
+    data[32..].copy_from_slice(&expires_at.to_le_bytes());
+    data
+}
+
+/// Whether attestation data attests `wallet` at `now`
+pub fn is_valid(data: &[u8], wallet: &Pubkey, now: i64) -> bool {
+    let (Some(attested), Some(expires_at)) = (data.get(..32), data.get(32..LEN)) else {
+        return false;
+    };
+    let expires_at = i64::from_le_bytes(expires_at.try_into().unwrap());
+    attested == wallet.as_ref() && (expires_at == 0 || now <= expires_at)
+}
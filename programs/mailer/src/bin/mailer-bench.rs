@@ -0,0 +1,497 @@
+//! # Mailer Instruction Benchmarks
+//!
+//! Runs the hot-path instructions against solana-program-test and prints the compute
+//! units they consume, followed by the size and rent of every account type:
+//!
+//! ```text
+//! cargo run --bin mailer-bench --features bench [-- <iterations>]
+//! ```
+//!
+//! Without `SBF_OUT_DIR` the program runs as a native builtin, which the runtime does not
+//! meter, so the figures only cover the token and system program CPIs. Build the program
+//! with `cargo build-sbf` and run with `SBF_OUT_DIR=target/deploy` for on-chain numbers.
+
+use mailer::{layout, pda, MailerInstruction};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    system_instruction, system_program,
+};
+use solana_program_test::{processor, BanksClient, ProgramTest};
+use solana_sdk::{
+    hash::Hash,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+use spl_token::state::{Account as TokenAccount, Mint};
+
+const DEFAULT_ITERATIONS: usize = 10;
+
+/// Lamports given to the fee payer of each measured transaction
+const FEE_PAYER_LAMPORTS: u64 = 10_000_000;
+
+/// A running program-test bank with an initialized mailer and funded USDC accounts
+struct Bench {
+    banks_client: BanksClient,
+    payer: Keypair,
+    recent_blockhash: Hash,
+    mailer: Pubkey,
+    usdc_mint: Pubkey,
+    payer_usdc: Pubkey,
+    mailer_usdc: Pubkey,
+}
+
+impl Bench {
+    async fn start() -> Self {
+        let program_test = ProgramTest::new(
+            "mailer",
+            mailer::id(),
+            processor!(mailer::process_instruction),
+        );
+        let (banks_client, payer, recent_blockhash) = program_test.start().await;
+        let (mailer, _) = pda::mailer_state(&mailer::id());
+
+        let mut bench = Self {
+            banks_client,
+            payer,
+            recent_blockhash,
+            mailer,
+            usdc_mint: Pubkey::default(),
+            payer_usdc: Pubkey::default(),
+            mailer_usdc: Pubkey::default(),
+        };
+
+        let mint = Keypair::new();
+        let rent = bench.banks_client.get_rent().await.unwrap();
+        bench
+            .process(
+                &[
+                    system_instruction::create_account(
+                        &bench.payer.pubkey(),
+                        &mint.pubkey(),
+                        rent.minimum_balance(Mint::LEN),
+                        Mint::LEN as u64,
+                        &spl_token::id(),
+                    ),
+                    spl_token::instruction::initialize_mint(
+                        &spl_token::id(),
+                        &mint.pubkey(),
+                        &bench.payer.pubkey(),
+                        None,
+                        6,
+                    )
+                    .unwrap(),
+                ],
+                &[&mint],
+            )
+            .await;
+        bench.usdc_mint = mint.pubkey();
+
+        let initialize = Instruction::new_with_borsh(
+            mailer::id(),
+            &MailerInstruction::Initialize {
+                usdc_mint: bench.usdc_mint,
+            },
+            vec![
+                AccountMeta::new(bench.payer.pubkey(), true),
+                AccountMeta::new(bench.mailer, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        bench.process(&[initialize], &[]).await;
+
+        bench.payer_usdc = bench.token_account(&bench.payer.pubkey()).await;
+        bench.mailer_usdc = bench.token_account(&mailer).await;
+        let mint_to = spl_token::instruction::mint_to(
+            &spl_token::id(),
+            &bench.usdc_mint,
+            &bench.payer_usdc,
+            &bench.payer.pubkey(),
+            &[],
+            u64::MAX / 2,
+        )
+        .unwrap();
+        bench.process(&[mint_to], &[]).await;
+
+        bench
+    }
+
+    /// Process a setup transaction paid and signed by the payer
+    async fn process(&mut self, instructions: &[Instruction], signers: &[&Keypair]) {
+        let mut transaction = Transaction::new_with_payer(instructions, Some(&self.payer.pubkey()));
+        let mut all_signers = vec![&self.payer];
+        all_signers.extend_from_slice(signers);
+        transaction.sign(&all_signers, self.recent_blockhash);
+        self.banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap();
+    }
+
+    /// USDC token account owned by `owner`
+    async fn token_account(&mut self, owner: &Pubkey) -> Pubkey {
+        let account = Keypair::new();
+        let rent = self.banks_client.get_rent().await.unwrap();
+        let instructions = [
+            system_instruction::create_account(
+                &self.payer.pubkey(),
+                &account.pubkey(),
+                rent.minimum_balance(TokenAccount::LEN),
+                TokenAccount::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_account(
+                &spl_token::id(),
+                &account.pubkey(),
+                &self.usdc_mint,
+                owner,
+            )
+            .unwrap(),
+        ];
+        self.process(&instructions, &[&account]).await;
+        account.pubkey()
+    }
+
+    /// Compute units consumed by `instruction`, signed by `signers` and the payer when the
+    /// payer is one of its signers
+    /// Each measurement has its own fee payer, so repeating an identical instruction
+    /// still produces a distinct transaction.
+    async fn measure(&mut self, instruction: Instruction, signers: &[&Keypair]) -> u64 {
+        let payer_signs = instruction
+            .accounts
+            .iter()
+            .any(|meta| meta.is_signer && meta.pubkey == self.payer.pubkey());
+        let fee_payer = Keypair::new();
+        let fund = system_instruction::transfer(
+            &self.payer.pubkey(),
+            &fee_payer.pubkey(),
+            FEE_PAYER_LAMPORTS,
+        );
+        self.process(&[fund], &[]).await;
+
+        let mut transaction =
+            Transaction::new_with_payer(&[instruction], Some(&fee_payer.pubkey()));
+        let mut all_signers = vec![&fee_payer];
+        if payer_signs {
+            all_signers.push(&self.payer);
+        }
+        all_signers.extend_from_slice(signers);
+        transaction.sign(&all_signers, self.recent_blockhash);
+
+        let result = self
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+            .unwrap();
+        if let Err(err) = result.result {
+            panic!("benchmarked instruction failed: {}", err);
+        }
+        result.metadata.unwrap().compute_units_consumed
+    }
+
+    fn send(&self, to: Pubkey, subject: String, revenue_share_to_receiver: bool) -> Instruction {
+        Instruction::new_with_borsh(
+            mailer::id(),
+            &MailerInstruction::Send {
+                to,
+                subject,
+                _body: "Benchmark body".to_string(),
+                revenue_share_to_receiver,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: true,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            self.wallet_send_accounts(&to),
+        )
+    }
+
+    /// Accounts of `Send` and `SendPrepared`: the payer pays any claim rent, followed by the
+    /// recipient's inbox policy and the payer's message nonce
+    fn wallet_send_accounts(&self, to: &Pubkey) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(self.payer.pubkey(), true),
+            AccountMeta::new(pda::recipient_claim(&mailer::id(), to).0, false),
+            AccountMeta::new(self.mailer, false),
+            AccountMeta::new(self.payer_usdc, false),
+            AccountMeta::new(self.mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(self.payer.pubkey(), true),
+            AccountMeta::new_readonly(pda::inbox_policy(&mailer::id(), to).0, false),
+            AccountMeta::new(
+                pda::message_nonce(&mailer::id(), &self.payer.pubkey()).0,
+                false,
+            ),
+        ]
+    }
+
+    /// Accounts of `SendToEmail` and `SendPreparedToEmail`, with the payer's message nonce
+    fn email_send_accounts(&self) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(self.payer.pubkey(), true),
+            AccountMeta::new(self.mailer, false),
+            AccountMeta::new(self.payer_usdc, false),
+            AccountMeta::new(self.mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(
+                pda::message_nonce(&mailer::id(), &self.payer.pubkey()).0,
+                false,
+            ),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ]
+    }
+}
+
+/// Compute units of one instruction over every iteration
+struct Sample {
+    name: &'static str,
+    units: Vec<u64>,
+}
+
+impl Sample {
+    fn min(&self) -> u64 {
+        self.units.iter().copied().min().unwrap_or_default()
+    }
+
+    fn max(&self) -> u64 {
+        self.units.iter().copied().max().unwrap_or_default()
+    }
+
+    fn mean(&self) -> u64 {
+        self.units.iter().sum::<u64>() / self.units.len().max(1) as u64
+    }
+}
+
+async fn run(bench: &mut Bench, iterations: usize) -> Vec<Sample> {
+    let mut samples = Vec::new();
+
+    // Standard sends never create accounts; priority sends create the claim account once
+    let mut units = Vec::new();
+    for i in 0..iterations {
+        let send = bench.send(Pubkey::new_unique(), format!("Standard {}", i), false);
+        units.push(bench.measure(send, &[]).await);
+    }
+    samples.push(Sample {
+        name: "Send (standard)",
+        units,
+    });
+
+    let recipient = Pubkey::new_unique();
+    let mut units = Vec::new();
+    for i in 0..iterations {
+        let send = bench.send(recipient, format!("Priority {}", i), true);
+        units.push(bench.measure(send, &[]).await);
+    }
+    samples.push(Sample {
+        name: "Send (priority)",
+        units,
+    });
+
+    let mut units = Vec::new();
+    for i in 0..iterations {
+        let to = Pubkey::new_unique();
+        let send = Instruction::new_with_borsh(
+            mailer::id(),
+            &MailerInstruction::SendPrepared {
+                to,
+                mail_id: format!("mail-{}", i),
+                revenue_share_to_receiver: false,
+                resolve_sender_to_name: false,
+                strict: true,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+            },
+            bench.wallet_send_accounts(&to),
+        );
+        units.push(bench.measure(send, &[]).await);
+    }
+    samples.push(Sample {
+        name: "SendPrepared",
+        units,
+    });
+
+    let mut units = Vec::new();
+    for i in 0..iterations {
+        let send = Instruction::new_with_borsh(
+            mailer::id(),
+            &MailerInstruction::SendToEmail {
+                to_email: format!("user{}@example.com", i),
+                subject: "Benchmark".to_string(),
+                _body: "Benchmark body".to_string(),
+                strict: true,
+            },
+            bench.email_send_accounts(),
+        );
+        units.push(bench.measure(send, &[]).await);
+    }
+    samples.push(Sample {
+        name: "SendToEmail",
+        units,
+    });
+
+    let mut units = Vec::new();
+    for i in 0..iterations {
+        let send = Instruction::new_with_borsh(
+            mailer::id(),
+            &MailerInstruction::SendPreparedToEmail {
+                to_email: format!("user{}@example.com", i),
+                mail_id: format!("mail-{}", i),
+                strict: true,
+            },
+            bench.email_send_accounts(),
+        );
+        units.push(bench.measure(send, &[]).await);
+    }
+    samples.push(Sample {
+        name: "SendPreparedToEmail",
+        units,
+    });
+
+    let delegation = pda::delegation(&mailer::id(), &bench.payer.pubkey()).0;
+    let mut units = Vec::new();
+    let mut previous: Option<Pubkey> = None;
+    for _ in 0..iterations {
+        let delegate = Pubkey::new_unique();
+        let mut accounts = vec![
+            AccountMeta::new(bench.payer.pubkey(), true),
+            AccountMeta::new(delegation, false),
+            AccountMeta::new(bench.mailer, false),
+            AccountMeta::new(bench.payer_usdc, false),
+            AccountMeta::new(bench.mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(pda::delegate_index(&mailer::id(), &delegate).0, false),
+        ];
+        if let Some(previous) = previous {
+            accounts.push(AccountMeta::new(
+                pda::delegate_index(&mailer::id(), &previous).0,
+                false,
+            ));
+        }
+        let delegate_to = Instruction::new_with_borsh(
+            mailer::id(),
+            &MailerInstruction::DelegateTo {
+                delegate: Some(delegate),
+            },
+            accounts,
+        );
+        units.push(bench.measure(delegate_to, &[]).await);
+        previous = Some(delegate);
+    }
+    samples.push(Sample {
+        name: "DelegateTo",
+        units,
+    });
+
+    // Each claim drains a share accrued by an unmeasured priority send just before it
+    let recipient = Keypair::new();
+    let recipient_usdc = bench.token_account(&recipient.pubkey()).await;
+    let mut units = Vec::new();
+    for i in 0..iterations {
+        let send = bench.send(recipient.pubkey(), format!("Claimable {}", i), true);
+        bench.process(&[send], &[]).await;
+
+        let claim = Instruction::new_with_borsh(
+            mailer::id(),
+            &MailerInstruction::ClaimRecipientShare {
+                amount: None,
+                split: None,
+                close_to: None,
+            },
+            vec![
+                AccountMeta::new_readonly(recipient.pubkey(), true),
+                AccountMeta::new(
+                    pda::recipient_claim(&mailer::id(), &recipient.pubkey()).0,
+                    false,
+                ),
+                AccountMeta::new_readonly(bench.mailer, false),
+                AccountMeta::new(recipient_usdc, false),
+                AccountMeta::new(bench.mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+            ],
+        );
+        units.push(bench.measure(claim, &[&recipient]).await);
+    }
+    samples.push(Sample {
+        name: "ClaimRecipientShare",
+        units,
+    });
+
+    let mut units = Vec::new();
+    for i in 0..iterations {
+        let send = bench.send(Pubkey::new_unique(), format!("Owner share {}", i), false);
+        bench.process(&[send], &[]).await;
+
+        let claim = Instruction::new_with_borsh(
+            mailer::id(),
+            &MailerInstruction::ClaimOwnerShare,
+            vec![
+                AccountMeta::new_readonly(bench.payer.pubkey(), true),
+                AccountMeta::new(bench.mailer, false),
+                AccountMeta::new(bench.payer_usdc, false),
+                AccountMeta::new(bench.mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+            ],
+        );
+        units.push(bench.measure(claim, &[]).await);
+    }
+    samples.push(Sample {
+        name: "ClaimOwnerShare",
+        units,
+    });
+
+    samples
+}
+
+#[tokio::main]
+async fn main() {
+    let iterations = match std::env::args().nth(1) {
+        Some(arg) => arg.parse().expect("usage: mailer-bench [iterations]"),
+        None => DEFAULT_ITERATIONS,
+    };
+
+    let mut bench = Bench::start().await;
+    let samples = run(&mut bench, iterations).await;
+
+    println!("Compute units ({} iterations)", iterations);
+    println!(
+        "{:<22} {:>10} {:>10} {:>10}",
+        "Instruction", "Min", "Mean", "Max"
+    );
+    for sample in &samples {
+        println!(
+            "{:<22} {:>10} {:>10} {:>10}",
+            sample.name,
+            sample.min(),
+            sample.mean(),
+            sample.max()
+        );
+    }
+
+    let rent = bench.banks_client.get_rent().await.unwrap();
+    println!();
+    println!("Accounts");
+    println!(
+        "{:<22} {:>10} {:>16}",
+        "Account", "Bytes", "Rent (lamports)"
+    );
+    for account in layout::accounts() {
+        println!(
+            "{:<22} {:>10} {:>16}",
+            account.name,
+            account.space,
+            rent.minimum_balance(account.space)
+        );
+    }
+}
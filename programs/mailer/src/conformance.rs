@@ -0,0 +1,196 @@
+//! # Seed and Layout Conformance Fixture
+//!
+//! Derives every PDA from [`pda::LAYOUTS`] and describes every account from
+//! [`layout::accounts`], using the fixed inputs of [`test_vectors`]. The result is the
+//! golden file `test-vectors/conformance.json`, which client SDKs check their own seed
+//! derivation and account decoding against.
+//!
+//! Fixed PDA inputs: `recipient` = `key(2)`, `delegator`/`sender`/`user`/`subscriber` =
+//! `key(1)`, `account` = `key(3)`, `mint` = `key(14)`, `session_key` = `key(12)`,
+//! `relayer` = `key(16)`, `claim` = `key(19)`, `address` = `key(22)`, `delegate` = `key(24)`,
+//! `owner` = `key(4)`, `list` = `key(30)`, every `U64Le` input (`nonce`, `plan_id`, ...) = 7,
+//! `code_hash`/`id_hash` = `sha256("WELCOME10")` and `evm_address` = 20 bytes of `0xab`, with
+//! the program id from [`crate::id`].
+//!
+//! Regenerate the fixture with:
+//! ```text
+//! UPDATE_TEST_VECTORS=1 cargo test -p mailer --test integration_tests conformance
+//! ```
+
+use solana_program::{hash::hash, pubkey::Pubkey};
+
+use crate::layout::{self, AccountLayout};
+use crate::pda::{self, PdaLayout, SeedComponent, SeedInput};
+use crate::test_vectors::{self, hex, json_escape, key};
+
+/// Fixed nonce (or plan id) used for `U64Le` seed inputs
+pub const NONCE: u64 = 7;
+
+/// Fixed promo code whose hash is used for `Hash` seed inputs
+pub const PROMO_CODE: &str = "WELCOME10";
+
+/// Fixed address used for `EvmAddress` seed inputs
+pub const EVM_ADDRESS: [u8; 20] = [0xab; 20];
+
+/// A PDA derived with fixed inputs
+#[derive(Debug, Clone)]
+pub struct PdaVector {
+    pub account: &'static str,
+    pub inputs: Vec<SeedInput>,
+    pub address: Pubkey,
+    pub bump: u8,
+}
+
+/// Fixed pubkey for a named seed input
+pub fn input_key(name: &str) -> Pubkey {
+    match name {
+        "recipient" => key(2),
+        "delegator" | "sender" | "user" | "subscriber" => key(1),
+        "account" => key(3),
+        "mint" => key(14),
+        "session_key" => key(12),
+        "relayer" => key(16),
+        "claim" => key(19),
+        "address" => key(22),
+        "delegate" => key(24),
+        "owner" => key(4),
+        "list" => key(30),
+        other => panic!("no fixed input for seed component `{}`", other),
+    }
+}
+
+fn inputs(layout: &PdaLayout) -> Vec<SeedInput> {
+    layout
+        .seeds
+        .iter()
+        .filter_map(|component| match component {
+            SeedComponent::Pubkey(name) => Some(SeedInput::Pubkey(input_key(name))),
+            SeedComponent::U64Le(_) => Some(SeedInput::U64(NONCE)),
+            SeedComponent::Hash(_) => Some(SeedInput::Hash(hash(PROMO_CODE.as_bytes()).to_bytes())),
+            SeedComponent::EvmAddress(_) => Some(SeedInput::EvmAddress(EVM_ADDRESS)),
+            SeedComponent::Literal(_) | SeedComponent::Version => None,
+        })
+        .collect()
+}
+
+/// Every PDA derived from its seed layout with the fixed inputs
+pub fn pda_vectors() -> Vec<PdaVector> {
+    pda::LAYOUTS
+        .iter()
+        .map(|layout| {
+            let inputs = inputs(layout);
+            let (address, bump) = layout
+                .find_address(&crate::id(), &inputs)
+                .expect("fixed inputs match the layout");
+            PdaVector {
+                account: layout.account,
+                inputs,
+                address,
+                bump,
+            }
+        })
+        .collect()
+}
+
+fn seed_json(component: &SeedComponent) -> String {
+    match component {
+        SeedComponent::Literal(bytes) => format!(
+            "{{ \"kind\": \"literal\", \"value\": \"{}\", \"hex\": \"{}\" }}",
+            json_escape(&String::from_utf8_lossy(bytes)),
+            hex(bytes)
+        ),
+        SeedComponent::Version => format!(
+            "{{ \"kind\": \"version\", \"value\": {} }}",
+            pda::PDA_VERSION
+        ),
+        SeedComponent::Pubkey(name) => {
+            format!("{{ \"kind\": \"pubkey\", \"name\": \"{}\" }}", name)
+        }
+        SeedComponent::U64Le(name) => format!("{{ \"kind\": \"u64le\", \"name\": \"{}\" }}", name),
+        SeedComponent::Hash(name) => format!("{{ \"kind\": \"hash\", \"name\": \"{}\" }}", name),
+        SeedComponent::EvmAddress(name) => {
+            format!("{{ \"kind\": \"evm_address\", \"name\": \"{}\" }}", name)
+        }
+    }
+}
+
+fn input_json(input: &SeedInput) -> String {
+    match input {
+        SeedInput::Pubkey(key) => format!("\"{}\"", key),
+        SeedInput::U64(value) => format!("\"{}\"", value),
+        SeedInput::Hash(bytes) => format!("\"{}\"", hex(bytes)),
+        SeedInput::EvmAddress(bytes) => format!("\"{}\"", hex(bytes)),
+    }
+}
+
+fn pdas_json() -> String {
+    pda::LAYOUTS
+        .iter()
+        .zip(pda_vectors())
+        .map(|(layout, v)| {
+            format!(
+                "    {{\n      \"account\": \"{}\",\n      \"seeds\": [{}],\n      \"inputs\": [{}],\n      \"address\": \"{}\",\n      \"bump\": {}\n    }}",
+                v.account,
+                layout.seeds.iter().map(seed_json).collect::<Vec<_>>().join(", "),
+                v.inputs.iter().map(input_json).collect::<Vec<_>>().join(", "),
+                v.address,
+                v.bump
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",\n")
+}
+
+fn fields_json(layout: &AccountLayout) -> String {
+    layout
+        .fields
+        .iter()
+        .zip(layout.fixed_offsets())
+        .map(|(field, offset)| {
+            format!(
+                "        {{ \"name\": \"{}\", \"type\": \"{}\", \"max_size\": {}, \"offset\": {} }}",
+                field.name,
+                field.ty.type_name(),
+                field.ty.max_size(),
+                offset.map_or_else(|| "null".to_string(), |o| o.to_string())
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",\n")
+}
+
+fn accounts_json() -> String {
+    let samples = test_vectors::account_vectors();
+    layout::accounts()
+        .iter()
+        .map(|layout| {
+            let sample = samples
+                .iter()
+                .find(|v| v.name == layout.name)
+                .expect("every account layout has a test vector");
+            format!(
+                "    {{\n      \"name\": \"{}\",\n      \"discriminator\": \"{}\",\n      \"legacy_discriminator\": {},\n      \"space\": {},\n      \"fields\": [\n{}\n      ],\n      \"sample\": \"{}\"\n    }}",
+                layout.name,
+                hex(&layout.discriminator),
+                layout
+                    .legacy_discriminator
+                    .map_or_else(|| "null".to_string(), |d| format!("\"{}\"", hex(&d))),
+                layout.space,
+                fields_json(layout),
+                hex(&sample.bytes)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",\n")
+}
+
+/// Render the golden file checked into `test-vectors/conformance.json`
+pub fn to_json() -> String {
+    format!(
+        "{{\n  \"program_id\": \"{}\",\n  \"pda_version\": {},\n  \"pdas\": [\n{}\n  ],\n  \"accounts\": [\n{}\n  ]\n}}\n",
+        crate::id(),
+        pda::PDA_VERSION,
+        pdas_json(),
+        accounts_json()
+    )
+}
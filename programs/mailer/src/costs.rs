@@ -0,0 +1,261 @@
+//! # Rent Cost Helpers
+//!
+//! Rent-exempt lamport costs for every account type the Mailer program creates, so
+//! integrators can budget SOL up front. Each account is allocated as an 8-byte
+//! discriminator followed by the Borsh data (`8 + T::LEN` bytes).
+//!
+//! ```rust
+//! use mailer::costs;
+//! use solana_program::rent::Rent;
+//!
+//! // SOL needed for a priority send to a recipient without a claim account yet
+//! let lamports = costs::recipient_claim(&Rent::default());
+//! ```
+
+use solana_program::rent::Rent;
+
+use crate::{
+    AdminSet, ClaimBeneficiary, ClaimDistribution, ClaimHistoryEntry, ClaimRentPayer, ClosureLog,
+    ConversionVault, DelegateIndex, Delegation, DiscountSchedule, Escrow, EvmMapping, FeeDiscount,
+    FeeMint, InboxPolicy, ListChunk, MailerState, MailerStats, MailingList, MessageNonce,
+    MessageReceipt, PayoutConfig, Plan, Preauthorization, Promo, RateLimit, RecipientClaim,
+    RecipientInbox, RecipientPrefs, Sanctioned, SendCredits, SendNonce, SenderStats, SessionKey,
+    Subscription, SweepCursor, Webhook, WormholeConfig,
+};
+
+/// Size of the account discriminator prefix
+pub const DISCRIMINATOR_LEN: usize = 8;
+
+/// Allocated account sizes (discriminator + data)
+pub mod space {
+    use super::*;
+
+    pub const MAILER_STATE: usize = DISCRIMINATOR_LEN + MailerState::LEN;
+    pub const RECIPIENT_CLAIM: usize = DISCRIMINATOR_LEN + RecipientClaim::LEN;
+    pub const DELEGATION: usize = DISCRIMINATOR_LEN + Delegation::LEN;
+    pub const FEE_DISCOUNT: usize = DISCRIMINATOR_LEN + FeeDiscount::LEN;
+    pub const RECIPIENT_PREFS: usize = DISCRIMINATOR_LEN + RecipientPrefs::LEN;
+    pub const ADMIN_SET: usize = DISCRIMINATOR_LEN + AdminSet::LEN;
+    pub const SWEEP_CURSOR: usize = DISCRIMINATOR_LEN + SweepCursor::LEN;
+    pub const INBOX_POLICY: usize = DISCRIMINATOR_LEN + InboxPolicy::LEN;
+    pub const MESSAGE_RECEIPT: usize = DISCRIMINATOR_LEN + MessageReceipt::LEN;
+    pub const CLAIM_HISTORY_ENTRY: usize = DISCRIMINATOR_LEN + ClaimHistoryEntry::LEN;
+    pub const SESSION_KEY: usize = DISCRIMINATOR_LEN + SessionKey::LEN;
+    pub const PAYOUT_CONFIG: usize = DISCRIMINATOR_LEN + PayoutConfig::LEN;
+    pub const CONVERSION_VAULT: usize = DISCRIMINATOR_LEN + ConversionVault::LEN;
+    pub const PREAUTHORIZATION: usize = DISCRIMINATOR_LEN + Preauthorization::LEN;
+    pub const RATE_LIMIT: usize = DISCRIMINATOR_LEN + RateLimit::LEN;
+    pub const PROMO: usize = DISCRIMINATOR_LEN + Promo::LEN;
+    pub const FEE_MINT: usize = DISCRIMINATOR_LEN + FeeMint::LEN;
+    pub const RECIPIENT_INBOX: usize = DISCRIMINATOR_LEN + RecipientInbox::LEN;
+    pub const CLAIM_RENT_PAYER: usize = DISCRIMINATOR_LEN + ClaimRentPayer::LEN;
+    pub const WEBHOOK: usize = DISCRIMINATOR_LEN + Webhook::LEN;
+    pub const CLOSURE_LOG: usize = DISCRIMINATOR_LEN + ClosureLog::LEN;
+    pub const SANCTIONED: usize = DISCRIMINATOR_LEN + Sanctioned::LEN;
+    pub const DELEGATE_INDEX: usize = DISCRIMINATOR_LEN + DelegateIndex::LEN;
+    pub const MAILER_STATS: usize = DISCRIMINATOR_LEN + MailerStats::LEN;
+    pub const SEND_CREDITS: usize = DISCRIMINATOR_LEN + SendCredits::LEN;
+    pub const PLAN: usize = DISCRIMINATOR_LEN + Plan::LEN;
+    pub const SUBSCRIPTION: usize = DISCRIMINATOR_LEN + Subscription::LEN;
+    pub const CLAIM_BENEFICIARY: usize = DISCRIMINATOR_LEN + ClaimBeneficiary::LEN;
+    pub const CLAIM_DISTRIBUTION: usize = DISCRIMINATOR_LEN + ClaimDistribution::LEN;
+    pub const ESCROW: usize = DISCRIMINATOR_LEN + Escrow::LEN;
+    pub const MAILING_LIST: usize = DISCRIMINATOR_LEN + MailingList::LEN;
+    pub const LIST_CHUNK: usize = DISCRIMINATOR_LEN + ListChunk::LEN;
+    pub const EVM_MAPPING: usize = DISCRIMINATOR_LEN + EvmMapping::LEN;
+    pub const WORMHOLE_CONFIG: usize = DISCRIMINATOR_LEN + WormholeConfig::LEN;
+    pub const MESSAGE_NONCE: usize = DISCRIMINATOR_LEN + MessageNonce::LEN;
+    pub const SEND_NONCE: usize = DISCRIMINATOR_LEN + SendNonce::LEN;
+    pub const DISCOUNT_SCHEDULE: usize = DISCRIMINATOR_LEN + DiscountSchedule::LEN;
+    pub const SENDER_STATS: usize = DISCRIMINATOR_LEN + SenderStats::LEN;
+}
+
+/// Rent for the global mailer state account (paid once by the owner at Initialize)
+pub fn mailer_state(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::MAILER_STATE)
+}
+
+/// Rent for a recipient claim account (paid by the sender of the first priority message)
+pub fn recipient_claim(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::RECIPIENT_CLAIM)
+}
+
+/// Rent for a delegation account (paid by the delegator)
+pub fn delegation(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::DELEGATION)
+}
+
+/// Rent for a fee discount account (paid by the owner)
+pub fn fee_discount(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::FEE_DISCOUNT)
+}
+
+/// Rent for a recipient preferences account (paid by the recipient)
+pub fn recipient_prefs(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::RECIPIENT_PREFS)
+}
+
+/// Rent for the admin set account (paid by the owner)
+pub fn admin_set(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::ADMIN_SET)
+}
+
+/// Rent for the sweep cursor account (paid by the owner)
+pub fn sweep_cursor(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::SWEEP_CURSOR)
+}
+
+/// Rent for an inbox policy account (paid by the recipient)
+pub fn inbox_policy(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::INBOX_POLICY)
+}
+
+/// Rent for a message receipt account (paid by the sender)
+pub fn message_receipt(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::MESSAGE_RECEIPT)
+}
+
+/// Rent for a claim history entry (paid by the sender of the first memo)
+pub fn claim_history_entry(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::CLAIM_HISTORY_ENTRY)
+}
+
+/// Rent for a recipient session key account (paid by the recipient)
+pub fn session_key(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::SESSION_KEY)
+}
+
+/// Rent for a recipient payout config account (paid by the recipient)
+pub fn payout_config(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::PAYOUT_CONFIG)
+}
+
+/// Rent for a conversion vault account (paid by the owner)
+pub fn conversion_vault(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::CONVERSION_VAULT)
+}
+
+/// Rent for a send pre-authorization account (paid by the user)
+pub fn preauthorization(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::PREAUTHORIZATION)
+}
+
+/// Rent for a sender rate limit account (paid by the sender on their first limited send)
+pub fn rate_limit(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::RATE_LIMIT)
+}
+
+/// Rent for a promo code account (paid by the owner)
+pub fn promo(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::PROMO)
+}
+
+/// Rent for a whitelisted fee mint account (paid by the owner)
+pub fn fee_mint(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::FEE_MINT)
+}
+
+/// Rent for a recipient inbox account (paid by the first sender passing it)
+pub fn recipient_inbox(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::RECIPIENT_INBOX)
+}
+
+/// Rent for a claim rent payer record (paid by the sender whose fee soft-failed)
+pub fn claim_rent_payer(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::CLAIM_RENT_PAYER)
+}
+
+/// Rent for a registered webhook account (paid by the webhook owner)
+pub fn webhook(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::WEBHOOK)
+}
+
+/// Rent for the global closure log account (paid once by the owner)
+pub fn closure_log(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::CLOSURE_LOG)
+}
+
+/// Rent for a sanctions registry entry (paid by the owner, refunded on removal)
+pub fn sanctioned(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::SANCTIONED)
+}
+
+/// Rent for a delegate's reverse index (paid by the first delegator to delegate to them)
+pub fn delegate_index(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::DELEGATE_INDEX)
+}
+
+/// Rent for the global stats account (paid once by the owner)
+pub fn mailer_stats(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::MAILER_STATS)
+}
+
+/// Rent for a sender's prepaid send credits account (paid by the sender on first deposit)
+pub fn send_credits(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::SEND_CREDITS)
+}
+
+/// Rent for a subscription plan account (paid by the owner when defining the plan)
+pub fn plan(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::PLAN)
+}
+
+/// Rent for a subscriber's subscription account (paid by the subscriber on first subscribe)
+pub fn subscription(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::SUBSCRIPTION)
+}
+
+/// Rent for a recipient's claim beneficiary account (paid by the recipient when registering)
+pub fn claim_beneficiary(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::CLAIM_BENEFICIARY)
+}
+
+/// Rent for a merkle airdrop distribution account (paid by the owner when funding it)
+pub fn claim_distribution(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::CLAIM_DISTRIBUTION)
+}
+
+/// Rent for a message escrow account (paid by the sender, refunded when the escrow closes)
+pub fn escrow(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::ESCROW)
+}
+
+/// Rent for a mailing list account (paid by the list owner at CreateList)
+pub fn mailing_list(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::MAILING_LIST)
+}
+
+/// Rent for a mailing list member chunk (paid by the list owner when adding its first member)
+pub fn list_chunk(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::LIST_CHUNK)
+}
+
+/// Rent for an EVM address binding (paid by the wallet that first binds the address)
+pub fn evm_mapping(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::EVM_MAPPING)
+}
+
+/// Rent for the wormhole config account (paid once by the owner at SetWormholeBridge)
+pub fn wormhole_config(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::WORMHOLE_CONFIG)
+}
+
+/// Rent for a sender's message nonce account (paid by the first send that passes it)
+pub fn message_nonce(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::MESSAGE_NONCE)
+}
+
+/// Rent for a sender's idempotency nonce ring buffer (paid by the first send carrying a nonce)
+pub fn send_nonce(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::SEND_NONCE)
+}
+
+/// Rent for the discount schedule account (paid once by the owner at SetDiscountSchedule)
+pub fn discount_schedule(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::DISCOUNT_SCHEDULE)
+}
+
+/// Rent for a sender's stats account (paid by their first send counted toward volume discounts)
+pub fn sender_stats(rent: &Rent) -> u64 {
+    rent.minimum_balance(space::SENDER_STATS)
+}
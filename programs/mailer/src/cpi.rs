@@ -1,7 +1,19 @@
 //! # Mailer CPI (Cross-Program Invocation) Module
 //!
-//! This module provides helper functions for other Solana programs to invoke
-//! the Mailer program's instructions via CPI.
+//! This module provides typed helpers for other Solana programs to invoke
+//! the Mailer program's instructions via CPI. Enabling the `cpi` feature also
+//! enables `no-entrypoint`, so depending on it never links the Mailer entrypoint
+//! into the calling program.
+//!
+//! Every instruction variant has an account struct in [`accounts`] named after it.
+//! Each struct lists the fixed accounts in instruction order and provides:
+//! - `instruction(program_id, args..)` building the [`Instruction`]
+//! - `invoke(mailer_program, args..)` and `invoke_signed(mailer_program, signer_seeds, args..)`
+//! - `invoke_with_remaining(mailer_program, remaining_accounts, signer_seeds, args..)` for
+//!   optional trailing accounts (payout config, session key, admin signers, sanctions entries, stats, ...)
+//!
+//! [`INSTRUCTIONS`] describes the same accounts and arguments as data, for tooling
+//! such as the IDL generator.
 //!
 //! ## Usage
 //!
@@ -12,49 +24,1465 @@
 //! ```
 //!
 //! Then in your program:
-//! ```rust
-//! use mailer::cpi;
+//! ```rust,ignore
+//! use mailer::cpi::accounts;
 //!
-//! // Send a message via CPI
-//! cpi::send(
-//!     mailer_program,
+//! // Send a message via CPI; the recipient claim's rent payer (the sender or a sponsor, signing),
+//! // the recipient's inbox policy PDA and the sender's message nonce PDA are required trailing
+//! // accounts
+//! accounts::Send {
 //!     sender,
-//!     recipient_claim_pda,
-//!     mailer_state_pda,
+//!     recipient_claim,
+//!     mailer_account,
 //!     sender_usdc,
 //!     mailer_usdc,
 //!     token_program,
 //!     system_program,
+//! }
+//! .invoke_with_remaining(
+//!     mailer_program,
+//!     &[rent_payer, recipient_inbox_policy, sender_message_nonce],
+//!     &[],
 //!     recipient_pubkey,
 //!     "Subject".to_string(),
 //!     "Message body".to_string(),
 //!     false, // revenue_share_to_receiver
 //!     true,  // resolve_sender_to_name
+//!     false, // request_read_receipt
+//!     None,  // receipt_nonce
+//!     None,  // memo
+//!     false, // strict: keep soft-fail so fee failures don't abort the caller
+//!     None,  // thread_id
+//!     None,  // in_reply_to
+//!     false, // allow_downgrade
+//!     0,     // tip
+//!     false, // bridge
+//!     None,  // idempotency_nonce
 //! )?;
 //! ```
 
-use borsh::BorshSerialize;
+use borsh::BorshDeserialize;
 use solana_program::{
     account_info::AccountInfo,
     entrypoint::ProgramResult,
     instruction::{AccountMeta, Instruction},
-    program::invoke,
+    program::{get_return_data, invoke_signed},
+    program_error::ProgramError,
     pubkey::Pubkey,
 };
 
-use crate::MailerInstruction;
+use crate::instruction_versions::Reserved;
+use crate::{
+    ClaimCreationPolicy, ClaimSplit, ClaimableView, DelegationView, FeeView, InboxMode,
+    MailerError, MailerInstruction, MigrationKind, PayloadLimits, SendResult, VolumeTier,
+};
+
+/// Account metas for optional trailing accounts, keeping each account's own signer
+/// and writable flags
+pub fn remaining_account_metas(remaining_accounts: &[AccountInfo]) -> Vec<AccountMeta> {
+    remaining_accounts
+        .iter()
+        .map(|account| AccountMeta {
+            pubkey: *account.key,
+            is_signer: account.is_signer,
+            is_writable: account.is_writable,
+        })
+        .collect()
+}
+
+macro_rules! account_meta {
+    (signer, $key:expr) => {
+        AccountMeta::new_readonly($key, true)
+    };
+    (writable_signer, $key:expr) => {
+        AccountMeta::new($key, true)
+    };
+    (writable, $key:expr) => {
+        AccountMeta::new($key, false)
+    };
+    (readonly, $key:expr) => {
+        AccountMeta::new_readonly($key, false)
+    };
+}
+
+/// Description of one fixed account of an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountSpec {
+    pub name: &'static str,
+    pub signer: bool,
+    pub writable: bool,
+}
+
+/// Description of one instruction argument; `ty` is the Rust type as written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArgSpec {
+    pub name: &'static str,
+    pub ty: &'static str,
+}
+
+/// Fixed accounts and arguments of one instruction variant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstructionSpec {
+    pub name: &'static str,
+    pub accounts: &'static [AccountSpec],
+    pub args: &'static [ArgSpec],
+}
+
+macro_rules! account_spec {
+    ($name:ident, signer) => {
+        AccountSpec {
+            name: stringify!($name),
+            signer: true,
+            writable: false,
+        }
+    };
+    ($name:ident, writable_signer) => {
+        AccountSpec {
+            name: stringify!($name),
+            signer: true,
+            writable: true,
+        }
+    };
+    ($name:ident, writable) => {
+        AccountSpec {
+            name: stringify!($name),
+            signer: false,
+            writable: true,
+        }
+    };
+    ($name:ident, readonly) => {
+        AccountSpec {
+            name: stringify!($name),
+            signer: false,
+            writable: false,
+        }
+    };
+}
+
+macro_rules! cpi_accounts {
+    ($(
+        $(#[$doc:meta])*
+        $variant:ident {
+            accounts { $($account:ident: $role:ident),* $(,)? }
+            args { $($arg:ident: $ty:ty),* $(,)? }
+        }
+    )*) => {
+        $(
+            $(#[$doc])*
+            #[derive(Clone)]
+            pub struct $variant<'a> {
+                $(pub $account: AccountInfo<'a>,)*
+            }
+
+            impl<'a> $variant<'a> {
+                /// Account metas for the fixed accounts, in instruction order
+                pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+                    vec![$(account_meta!($role, *self.$account.key)),*]
+                }
+
+                /// Account infos for the fixed accounts, in instruction order
+                pub fn to_account_infos(&self) -> Vec<AccountInfo<'a>> {
+                    vec![$(self.$account.clone()),*]
+                }
+
+                /// Build the instruction for these accounts
+                #[allow(clippy::too_many_arguments)]
+                pub fn instruction(&self, program_id: &Pubkey, $($arg: $ty),*) -> Instruction {
+                    let data = borsh::to_vec(&MailerInstruction::$variant { $($arg),* })
+                        .expect("instruction serializes");
+                    Instruction {
+                        program_id: *program_id,
+                        accounts: self.to_account_metas(),
+                        data,
+                    }
+                }
+
+                /// Invoke the instruction, propagating the caller's signatures
+                #[allow(clippy::too_many_arguments)]
+                pub fn invoke(&self, mailer_program: &AccountInfo<'a>, $($arg: $ty),*) -> ProgramResult {
+                    self.invoke_with_remaining(mailer_program, &[], &[], $($arg),*)
+                }
+
+                /// Invoke the instruction, signing for PDAs with `signer_seeds`
+                #[allow(clippy::too_many_arguments)]
+                pub fn invoke_signed(
+                    &self,
+                    mailer_program: &AccountInfo<'a>,
+                    signer_seeds: &[&[&[u8]]],
+                    $($arg: $ty),*
+                ) -> ProgramResult {
+                    self.invoke_with_remaining(mailer_program, &[], signer_seeds, $($arg),*)
+                }
+
+                /// Invoke the instruction with optional trailing accounts appended
+                #[allow(clippy::too_many_arguments)]
+                pub fn invoke_with_remaining(
+                    &self,
+                    mailer_program: &AccountInfo<'a>,
+                    remaining_accounts: &[AccountInfo<'a>],
+                    signer_seeds: &[&[&[u8]]],
+                    $($arg: $ty),*
+                ) -> ProgramResult {
+                    let mut ix = self.instruction(mailer_program.key, $($arg),*);
+                    ix.accounts.extend(remaining_account_metas(remaining_accounts));
+
+                    let mut account_infos = self.to_account_infos();
+                    account_infos.extend_from_slice(remaining_accounts);
+                    account_infos.push(mailer_program.clone());
+
+                    invoke_signed(&ix, &account_infos, signer_seeds)
+                }
+            }
+        )*
+
+        /// Fixed accounts and arguments of every instruction, in declaration order
+        pub const INSTRUCTIONS: &[InstructionSpec] = &[$(
+            InstructionSpec {
+                name: stringify!($variant),
+                accounts: &[$(account_spec!($account, $role)),*],
+                args: &[$(ArgSpec { name: stringify!($arg), ty: stringify!($ty) }),*],
+            }
+        ),*];
+    };
+}
+
+pub use accounts::INSTRUCTIONS;
+
+/// Account structs for every Mailer instruction, named after the instruction variant
+pub mod accounts {
+    use super::*;
+
+    cpi_accounts! {
+        /// Accounts for `Initialize`
+        Initialize {
+            accounts { owner: writable_signer, mailer_account: writable, system_program: readonly }
+            args { usdc_mint: Pubkey }
+        }
+
+        /// Accounts for `Send`
+        /// Required trailing accounts: the recipient's inbox policy PDA, passed even while uncreated,
+        /// and the sender's message nonce PDA
+        /// First trailing account: the recipient claim's rent payer, the sender or a sponsor, signing
+        /// Optional trailing accounts: message receipt, claim history, fee discount, spam oracle program,
+        /// rate limit, fee mint
+        Send {
+            accounts {
+                sender: writable_signer,
+                recipient_claim: writable,
+                mailer_account: writable,
+                sender_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+                system_program: readonly,
+            }
+            args {
+                to: Pubkey,
+                subject: String,
+                _body: String,
+                revenue_share_to_receiver: bool,
+                resolve_sender_to_name: bool,
+                request_read_receipt: bool,
+                receipt_nonce: Option<u64>,
+                memo: Option<String>,
+                strict: bool,
+                thread_id: Option<[u8; 32]>,
+                in_reply_to: Option<[u8; 32]>,
+                allow_downgrade: bool,
+                tip: u64,
+                bridge: bool,
+                idempotency_nonce: Option<u64>,
+            }
+        }
+
+        /// Accounts for `SendPrepared`
+        /// Trailing accounts: as for `Send`
+        SendPrepared {
+            accounts {
+                sender: writable_signer,
+                recipient_claim: writable,
+                mailer_account: writable,
+                sender_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+                system_program: readonly,
+            }
+            args {
+                to: Pubkey,
+                mail_id: String,
+                revenue_share_to_receiver: bool,
+                resolve_sender_to_name: bool,
+                strict: bool,
+                thread_id: Option<[u8; 32]>,
+                in_reply_to: Option<[u8; 32]>,
+                allow_downgrade: bool,
+                tip: u64,
+            }
+        }
+
+        /// Accounts for `SendToEmail`
+        /// Required trailing account: the sender's message nonce PDA, with the system program
+        /// until it exists
+        SendToEmail {
+            accounts {
+                sender: writable_signer,
+                mailer_account: writable,
+                sender_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+            }
+            args { to_email: String, subject: String, _body: String, strict: bool }
+        }
+
+        /// Accounts for `SendPreparedToEmail`
+        /// Required trailing account: the sender's message nonce PDA, with the system program
+        /// until it exists
+        SendPreparedToEmail {
+            accounts {
+                sender: writable_signer,
+                mailer_account: writable,
+                sender_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+            }
+            args { to_email: String, mail_id: String, strict: bool }
+        }
+
+        /// Accounts for `SendThroughWebhook`
+        /// Trailing accounts: as for `Send`
+        SendThroughWebhook {
+            accounts {
+                sender: writable_signer,
+                recipient_claim: writable,
+                mailer_account: writable,
+                sender_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+                system_program: readonly,
+                webhook: writable,
+            }
+            args {
+                to: Pubkey,
+                webhook_id: String,
+                revenue_share_to_receiver: bool,
+                resolve_sender_to_name: bool,
+                strict: bool,
+                thread_id: Option<[u8; 32]>,
+                in_reply_to: Option<[u8; 32]>,
+                allow_downgrade: bool,
+                tip: u64,
+            }
+        }
+
+        /// Accounts for `ClaimRecipientShare`
+        /// Optional trailing accounts: payout config, split destinations, refund destination and
+        /// closure log (when closing)
+        ClaimRecipientShare {
+            accounts {
+                recipient: signer,
+                recipient_claim: writable,
+                mailer_account: readonly,
+                recipient_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+            }
+            args { amount: Option<u64>, split: Option<Vec<ClaimSplit>>, close_to: Option<Pubkey> }
+        }
+
+        /// Accounts for `ClaimOwnerShare`
+        /// Optional trailing accounts: fee mint (when claiming in a whitelisted fee mint),
+        /// treasury USDC account and treasury PDA (to pay out the treasury balance)
+        ClaimOwnerShare {
+            accounts {
+                owner: signer,
+                mailer_account: writable,
+                owner_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+            }
+            args {}
+        }
+
+        /// Accounts for `SetFee`
+        SetFee {
+            accounts { owner: signer, mailer_account: writable }
+            args { new_fee: u64, expected_current_value: Option<u64> }
+        }
+
+        /// Accounts for `DelegateTo`
+        /// Optional trailing accounts: fee mint (when paying in a whitelisted fee mint), delegate
+        /// indexes of the new and previous delegate (when the delegate changes)
+        DelegateTo {
+            accounts {
+                delegator: writable_signer,
+                delegation: writable,
+                mailer_account: writable,
+                delegator_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+                system_program: readonly,
+            }
+            args { delegate: Option<Pubkey> }
+        }
+
+        /// Accounts for `RejectDelegation`
+        RejectDelegation {
+            accounts {
+                rejector: signer,
+                delegation: writable,
+                mailer_account: writable,
+                delegate_index: writable,
+            }
+            args {}
+        }
+
+        /// Accounts for `SetDelegationFee`
+        SetDelegationFee {
+            accounts { owner: signer, mailer_account: writable }
+            args { new_fee: u64, expected_current_value: Option<u64> }
+        }
+
+        /// Accounts for `SetCustomFeePercentage`
+        SetCustomFeePercentage {
+            accounts {
+                owner: signer,
+                mailer_account: writable,
+                fee_discount: writable,
+                target_account: readonly,
+                payer: writable_signer,
+                system_program: readonly,
+            }
+            args { account: Pubkey, percentage: u8 }
+        }
+
+        /// Accounts for `ClearCustomFeePercentage`
+        ClearCustomFeePercentage {
+            accounts { owner: signer, mailer_account: readonly, fee_discount: writable }
+            args { account: Pubkey }
+        }
+
+        /// Accounts for `Pause`
+        Pause {
+            accounts {
+                owner: signer,
+                mailer_account: writable,
+                owner_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+            }
+            args {}
+        }
+
+        /// Accounts for `Unpause`
+        Unpause {
+            accounts { owner: signer, mailer_account: writable }
+            args {}
+        }
+
+        /// Accounts for `DistributeClaimableFunds`
+        /// Optional trailing account: payout config
+        DistributeClaimableFunds {
+            accounts {
+                caller: signer,
+                mailer_account: readonly,
+                recipient_claim: writable,
+                recipient_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+            }
+            args { recipient: Pubkey }
+        }
+
+        /// Accounts for `ClaimExpiredShares`
+        ClaimExpiredShares {
+            accounts {
+                owner: signer,
+                mailer_account: writable,
+                recipient_claim: writable,
+                claim_beneficiary: readonly,
+            }
+            args { recipient: Pubkey }
+        }
+
+        /// Accounts for `EmergencyUnpause`
+        EmergencyUnpause {
+            accounts { owner: signer, mailer_account: writable }
+            args {}
+        }
+
+        /// Accounts for `SetFeePaused`
+        SetFeePaused {
+            accounts { owner: signer, mailer_account: writable }
+            args { fee_paused: bool }
+        }
+
+        /// Accounts for `SetRecipientPrefs`
+        /// Optional trailing account: session key (when `authority` is a session key)
+        SetRecipientPrefs {
+            accounts {
+                authority: writable_signer,
+                recipient_prefs: writable,
+                system_program: readonly,
+            }
+            args { read_receipts_opt_out: bool }
+        }
+
+        /// Accounts for `AcknowledgeMessage`
+        /// Optional trailing account: message receipt (required with `receipt_nonce`)
+        AcknowledgeMessage {
+            accounts { recipient: signer, recipient_prefs: readonly }
+            args { sender: Pubkey, receipt_nonce: Option<u64> }
+        }
+
+        /// Accounts for `SetProtocolSendsEnabled`
+        SetProtocolSendsEnabled {
+            accounts { owner: signer, mailer_account: writable }
+            args { enabled: bool }
+        }
+
+        /// Accounts for `SendProtocolMessage`
+        /// Required trailing account: the mailer PDA's message nonce PDA, with the system
+        /// program until it exists
+        SendProtocolMessage {
+            accounts { owner: writable_signer, mailer_account: readonly }
+            args { to: Pubkey, subject: String, _body: String }
+        }
+
+        /// Accounts for `TransferOwnership`
+        TransferOwnership {
+            accounts { owner: signer, mailer_account: writable }
+            args { new_owner: Pubkey }
+        }
+
+        /// Accounts for `AcceptOwnership`
+        AcceptOwnership {
+            accounts { pending_owner: signer, mailer_account: writable }
+            args {}
+        }
+
+        /// Accounts for `SetAdmins`
+        /// Optional trailing accounts: additional admin signers
+        SetAdmins {
+            accounts {
+                authority: writable_signer,
+                mailer_account: readonly,
+                admin_set: writable,
+                system_program: readonly,
+            }
+            args { admins: Vec<Pubkey>, threshold: u8 }
+        }
+
+        /// Accounts for `SweepExpiredPage`
+        /// Trailing accounts: recipient claim accounts to sweep
+        SweepExpiredPage {
+            accounts {
+                owner: writable_signer,
+                mailer_account: writable,
+                sweep_cursor: writable,
+                system_program: readonly,
+            }
+            args { max_items: u8 }
+        }
+
+        /// Accounts for `ResetSweepCursor`
+        ResetSweepCursor {
+            accounts { owner: signer, mailer_account: readonly, sweep_cursor: writable }
+            args {}
+        }
+
+        /// Accounts for `ProposeFee`
+        ProposeFee {
+            accounts { owner: signer, mailer_account: writable }
+            args { new_fee: u64 }
+        }
+
+        /// Accounts for `SetFeeTimelock`
+        SetFeeTimelock {
+            accounts { owner: signer, mailer_account: writable }
+            args { delay: i64 }
+        }
+
+        /// Accounts for `SetInboxPolicy`
+        /// Optional trailing account: session key (when `authority` is a session key)
+        SetInboxPolicy {
+            accounts {
+                authority: writable_signer,
+                inbox_policy: writable,
+                system_program: readonly,
+            }
+            args { mode: InboxMode, senders: Vec<Pubkey>, penalty_multiplier: u8, min_fee: u64 }
+        }
+
+        /// Accounts for `ClaimRecipientShareToAta`
+        ClaimRecipientShareToAta {
+            accounts {
+                recipient: writable_signer,
+                recipient_claim: writable,
+                mailer_account: readonly,
+                recipient_ata: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+                usdc_mint: readonly,
+                system_program: readonly,
+                associated_token_program: readonly,
+            }
+            args {}
+        }
+
+        /// Accounts for `RegisterSessionKey`
+        RegisterSessionKey {
+            accounts {
+                recipient: writable_signer,
+                session_key_account: writable,
+                system_program: readonly,
+            }
+            args { session_key: Pubkey, scope: u8, expires_at: i64 }
+        }
+
+        /// Accounts for `RevokeSessionKey`
+        RevokeSessionKey {
+            accounts { recipient: signer, session_key_account: writable }
+            args { session_key: Pubkey }
+        }
+
+        /// Accounts for `SetPayoutAddress`
+        /// Trailing account: payout USDC token account (required when setting)
+        SetPayoutAddress {
+            accounts {
+                recipient: writable_signer,
+                payout_config: writable,
+                mailer_account: readonly,
+                system_program: readonly,
+            }
+            args { payout_token_account: Option<Pubkey> }
+        }
+
+        /// Accounts for `SetConversionVault`
+        SetConversionVault {
+            accounts {
+                owner: writable_signer,
+                mailer_account: readonly,
+                conversion_vault: writable,
+                vault_token_account: readonly,
+                system_program: readonly,
+            }
+            args { mint: Pubkey, enabled: bool }
+        }
+
+        /// Accounts for `ClaimRecipientShareAs`
+        /// Optional trailing account: payout config (applies to the fallback)
+        ClaimRecipientShareAs {
+            accounts {
+                recipient: signer,
+                recipient_claim: writable,
+                mailer_account: writable,
+                recipient_token_account: writable,
+                conversion_vault: writable,
+                vault_token_account: writable,
+                recipient_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+            }
+            args { mint: Pubkey }
+        }
+
+        /// Accounts for `GetConfig`
+        GetConfig {
+            accounts { mailer_account: readonly }
+            args {}
+        }
+
+        /// Accounts for `CreatePreauthorization`
+        CreatePreauthorization {
+            accounts {
+                user: writable_signer,
+                preauthorization: writable,
+                user_usdc: writable,
+                mailer_account: readonly,
+                token_program: readonly,
+                system_program: readonly,
+            }
+            args {
+                relayer: Pubkey,
+                webhook_id: String,
+                max_sends: u64,
+                max_fee_per_send: u64,
+                expires_at: i64,
+            }
+        }
+
+        /// Accounts for `RevokePreauthorization`
+        RevokePreauthorization {
+            accounts {
+                user: signer,
+                preauthorization: writable,
+                user_usdc: writable,
+                token_program: readonly,
+            }
+            args { relayer: Pubkey }
+        }
+
+        /// Accounts for `SendPreauthorized`
+        /// The relayer may be a PDA of the calling program, signed with `invoke_signed`.
+        /// Required trailing accounts: the recipient's inbox policy PDA and the user's message
+        /// nonce PDA
+        SendPreauthorized {
+            accounts {
+                relayer: writable_signer,
+                preauthorization: writable,
+                user: readonly,
+                recipient_claim: writable,
+                mailer_account: writable,
+                user_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+                system_program: readonly,
+            }
+            args { to: Pubkey, webhook_id: String, revenue_share_to_receiver: bool }
+        }
+
+        /// Accounts for `SetInstructionDisabled`
+        SetInstructionDisabled {
+            accounts { owner: signer, mailer_account: writable }
+            args { tag: u8, disabled: bool }
+        }
+
+        /// Accounts for `MigrateDiscriminator`
+        /// Trailing accounts: additional program-owned accounts to migrate
+        MigrateDiscriminator {
+            accounts { account: writable }
+            args {}
+        }
+
+        /// Accounts for `CloseRecipientClaim`
+        CloseRecipientClaim {
+            accounts {
+                owner: signer,
+                mailer_account: writable,
+                recipient_claim: writable,
+                refund_destination: writable,
+                closure_log: writable,
+            }
+            args { recipient: Pubkey }
+        }
+
+        /// Accounts for `CloseDelegation`
+        CloseDelegation {
+            accounts {
+                delegator: writable_signer,
+                delegation: writable,
+                mailer_account: writable,
+                closure_log: writable,
+            }
+            args {}
+        }
+
+        /// Accounts for `CloseFeeDiscount`
+        CloseFeeDiscount {
+            accounts {
+                owner: signer,
+                mailer_account: writable,
+                fee_discount: writable,
+                refund_destination: writable,
+            }
+            args { account: Pubkey }
+        }
+
+        /// Accounts for `MigrateAccountVersion`
+        MigrateAccountVersion {
+            accounts {
+                subject: writable_signer,
+                old_account: writable,
+                new_account: writable,
+                mailer_account: writable,
+                system_program: readonly,
+            }
+            args { kind: MigrationKind }
+        }
+
+        /// Accounts for `SetSpamOracle`
+        SetSpamOracle {
+            accounts { owner: signer, mailer_account: writable }
+            args { oracle: Option<Pubkey>, threshold: Option<u64> }
+        }
+
+        /// Accounts for `SetRateLimit`
+        SetRateLimit {
+            accounts { owner: signer, mailer_account: writable }
+            args { max_sends: u64, window: i64 }
+        }
+
+        /// Accounts for `CreatePromo`
+        CreatePromo {
+            accounts {
+                owner: writable_signer,
+                mailer_account: readonly,
+                promo_account: writable,
+                system_program: readonly,
+            }
+            args { code_hash: [u8; 32], discount: u8, max_redemptions: u64, expiry: i64 }
+        }
+
+        /// Accounts for `RedeemPromo`
+        RedeemPromo {
+            accounts {
+                user: writable_signer,
+                promo_account: writable,
+                fee_discount_account: writable,
+                mailer_account: writable,
+                system_program: readonly,
+            }
+            args { code: String }
+        }
+
+        /// Accounts for `ClaimAndSend`
+        /// Trailing accounts: those of `ClaimRecipientShare` and `Send`
+        ClaimAndSend {
+            accounts {
+                sender: writable_signer,
+                sender_claim: writable,
+                recipient_claim: writable,
+                mailer_account: writable,
+                sender_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+                system_program: readonly,
+            }
+            args {
+                amount: Option<u64>,
+                to: Pubkey,
+                subject: String,
+                _body: String,
+                revenue_share_to_receiver: bool,
+                resolve_sender_to_name: bool,
+                request_read_receipt: bool,
+                receipt_nonce: Option<u64>,
+                memo: Option<String>,
+                strict: bool,
+            }
+        }
+
+        /// Accounts for `AddFeeMint`
+        AddFeeMint {
+            accounts {
+                owner: writable_signer,
+                mailer_account: readonly,
+                fee_mint_account: writable,
+                system_program: readonly,
+            }
+            args { mint: Pubkey, send_fee: u64, delegation_fee: u64 }
+        }
+
+        /// Accounts for `RemoveFeeMint`
+        RemoveFeeMint {
+            accounts {
+                owner: writable_signer,
+                mailer_account: readonly,
+                fee_mint_account: writable,
+            }
+            args { mint: Pubkey }
+        }
+
+        /// Accounts for `SetLamportFee`
+        SetLamportFee {
+            accounts {
+                owner: writable_signer,
+                mailer_account: writable,
+                lamport_vault: writable,
+                system_program: readonly,
+            }
+            args { lamport_fee: u64 }
+        }
+
+        /// Accounts for `ClaimOwnerLamports`
+        ClaimOwnerLamports {
+            accounts {
+                owner: signer,
+                mailer_account: writable,
+                lamport_vault: writable,
+                owner_wallet: writable,
+            }
+            args {}
+        }
+
+        /// Accounts for `SetFeeOracle`
+        SetFeeOracle {
+            accounts { owner: signer, mailer_account: writable }
+            args { oracle: Option<Pubkey>, target_fee: u64, max_age: i64 }
+        }
+
+        /// Accounts for `SetClaimCreationPolicy`
+        SetClaimCreationPolicy {
+            accounts { owner: signer, mailer_account: writable }
+            args { policy: ClaimCreationPolicy }
+        }
+
+        /// Accounts for `CloseFundedClaim`
+        CloseFundedClaim {
+            accounts {
+                payer: writable_signer,
+                mailer_account: writable,
+                recipient_claim: writable,
+                claim_rent_payer: writable,
+                closure_log: writable,
+            }
+            args {}
+        }
+
+        /// Accounts for `SendEncrypted`
+        /// Trailing accounts: as for `Send`
+        SendEncrypted {
+            accounts {
+                sender: writable_signer,
+                recipient_claim: writable,
+                mailer_account: writable,
+                sender_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+                system_program: readonly,
+            }
+            args {
+                to: Pubkey,
+                ephemeral_key: [u8; 32],
+                nonce: [u8; 12],
+                ciphertext: Vec<u8>,
+                revenue_share_to_receiver: bool,
+                strict: bool,
+            }
+        }
+
+        /// Accounts for `RegisterWebhook`
+        RegisterWebhook {
+            accounts {
+                owner: writable_signer,
+                mailer_account: readonly,
+                webhook: writable,
+                system_program: readonly,
+            }
+            args { webhook_id: String, endpoint_hash: [u8; 32], surcharge: u64 }
+        }
+
+        /// Accounts for `UpdateWebhook`
+        UpdateWebhook {
+            accounts { owner: signer, webhook: writable }
+            args { endpoint_hash: [u8; 32], surcharge: u64 }
+        }
+
+        /// Accounts for `RevokeWebhook`
+        RevokeWebhook {
+            accounts { owner: signer, webhook: writable }
+            args {}
+        }
+
+        /// Accounts for `ClaimWebhookRevenue`
+        ClaimWebhookRevenue {
+            accounts {
+                owner: signer,
+                webhook: writable,
+                mailer_account: readonly,
+                owner_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+            }
+            args {}
+        }
+
+        /// Accounts for `InitializeClosureLog`
+        InitializeClosureLog {
+            accounts {
+                owner: writable_signer,
+                mailer_account: readonly,
+                closure_log: writable,
+                system_program: readonly,
+            }
+            args {}
+        }
+
+        /// Accounts for `SendAsDelegator`
+        /// Required trailing accounts: the recipient's inbox policy PDA and the delegator's message
+        /// nonce PDA
+        SendAsDelegator {
+            accounts {
+                delegate: writable_signer,
+                delegator: readonly,
+                delegation: readonly,
+                recipient_claim: writable,
+                mailer_account: writable,
+                delegate_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+                system_program: readonly,
+            }
+            args {
+                to: Pubkey,
+                subject: String,
+                _body: String,
+                revenue_share_to_receiver: bool,
+                strict: bool,
+            }
+        }
+
+        /// Accounts for `SanctionAddress`
+        SanctionAddress {
+            accounts {
+                owner: writable_signer,
+                mailer_account: readonly,
+                sanctioned: writable,
+                system_program: readonly,
+            }
+            args { address: Pubkey }
+        }
+
+        /// Accounts for `UnsanctionAddress`
+        UnsanctionAddress {
+            accounts { owner: writable_signer, mailer_account: readonly, sanctioned: writable }
+            args { address: Pubkey }
+        }
+
+        /// Accounts for `SetSanctionsEnabled`
+        SetSanctionsEnabled {
+            accounts { owner: signer, mailer_account: writable }
+            args { enabled: bool }
+        }
+
+        /// Accounts for `SetBackupMint`
+        /// Trailing account when setting: the backup mint's conversion vault
+        SetBackupMint {
+            accounts { owner: signer, mailer_account: writable }
+            args { mint: Option<Pubkey> }
+        }
+
+        /// Accounts for `InitializeStats`
+        InitializeStats {
+            accounts {
+                owner: writable_signer,
+                mailer_account: writable,
+                stats: writable,
+                system_program: readonly,
+            }
+            args {}
+        }
+        /// Accounts for `SetClaimNoExpiry`
+        SetClaimNoExpiry {
+            accounts {
+                recipient: signer,
+                recipient_claim: writable,
+                mailer_account: writable,
+            }
+            args { enabled: bool }
+        }
+        /// Accounts for `ClaimExpiredSharesBatch`
+        /// Trailing accounts: recipient claim accounts
+        ClaimExpiredSharesBatch {
+            accounts { owner: signer, mailer_account: writable }
+            args {}
+        }
+        /// Accounts for `SetPauseFlags`
+        SetPauseFlags {
+            accounts { owner: signer, mailer_account: writable }
+            args { flags: u8 }
+        }
+        /// Accounts for `SetKycVerifier`
+        SetKycVerifier {
+            accounts { owner: signer, mailer_account: writable }
+            args { verifier: Option<Pubkey> }
+        }
+        /// Accounts for `SetDelegationRefundWindow`
+        SetDelegationRefundWindow {
+            accounts { owner: signer, mailer_account: writable }
+            args { window: i64 }
+        }
+        /// Accounts for `AcceptDelegation`
+        AcceptDelegation {
+            accounts { delegate: signer, delegation: writable, mailer_account: writable }
+            args {}
+        }
+        /// Accounts for `RefundDelegationFee`
+        RefundDelegationFee {
+            accounts {
+                delegator: signer,
+                delegation: writable,
+                mailer_account: readonly,
+                delegator_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+            }
+            args {}
+        }
+        /// Accounts for `RecordDeliveryStatus`
+        RecordDeliveryStatus {
+            accounts { owner: signer, webhook: writable }
+            args { to: Pubkey, nonce: u64, sent_slot: u64, delivered: bool }
+        }
+        /// Accounts for `GetWebhookStats`
+        GetWebhookStats {
+            accounts { webhook: readonly }
+            args {}
+        }
+        /// Accounts for `DepositSendCredits`
+        DepositSendCredits {
+            accounts {
+                sender: writable_signer,
+                send_credits: writable,
+                mailer_account: writable,
+                sender_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+                system_program: readonly,
+            }
+            args { amount: u64 }
+        }
+        /// Accounts for `WithdrawSendCredits`
+        WithdrawSendCredits {
+            accounts {
+                sender: signer,
+                send_credits: writable,
+                mailer_account: writable,
+                sender_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+            }
+            args { amount: Option<u64> }
+        }
+        /// Accounts for `SetClaimCap`
+        SetClaimCap {
+            accounts { owner: signer, mailer_account: writable }
+            args { cap: u64 }
+        }
+        /// Accounts for `SetPlan`
+        SetPlan {
+            accounts {
+                owner: writable_signer,
+                mailer_account: readonly,
+                plan: writable,
+                system_program: readonly,
+            }
+            args { plan_id: u64, price: u64, duration: i64, max_messages: u64, active: bool }
+        }
+        /// Accounts for `Subscribe`
+        Subscribe {
+            accounts {
+                subscriber: writable_signer,
+                plan: readonly,
+                subscription: writable,
+                mailer_account: writable,
+                subscriber_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+                system_program: readonly,
+            }
+            args { plan_id: u64 }
+        }
+        /// Accounts for `RenewSubscription`
+        RenewSubscription {
+            accounts {
+                subscriber: signer,
+                plan: readonly,
+                subscription: writable,
+                mailer_account: writable,
+                subscriber_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+            }
+            args {}
+        }
+        /// Accounts for `SetPayloadLimits`
+        SetPayloadLimits {
+            accounts { owner: signer, mailer_account: writable }
+            args { limits: PayloadLimits, fee_tier_len: u16, fee_tier_percent: u16 }
+        }
+        /// Accounts for `SendToEmailHash`
+        /// Required trailing account: the sender's message nonce PDA, with the system program
+        /// until it exists
+        SendToEmailHash {
+            accounts {
+                sender: writable_signer,
+                mailer_account: writable,
+                sender_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+            }
+            args { email_hash: Vec<u8>, hint_id: u64, subject: String, _body: String, strict: bool }
+        }
+        /// Accounts for `SetSolvencyThreshold`
+        SetSolvencyThreshold {
+            accounts { owner: signer, mailer_account: writable }
+            args { min_coverage_bps: u16 }
+        }
+        /// Accounts for `ExtendClaimWindow`
+        ExtendClaimWindow {
+            accounts {
+                recipient: signer,
+                recipient_claim: writable,
+                mailer_account: writable,
+            }
+            args {}
+        }
+        /// Accounts for `SetClaimBeneficiary`
+        SetClaimBeneficiary {
+            accounts {
+                recipient: writable_signer,
+                claim_beneficiary: writable,
+                mailer_account: readonly,
+                system_program: readonly,
+            }
+            args { beneficiary: Option<Pubkey> }
+        }
+        /// Accounts for `ClaimAsBeneficiary`
+        ClaimAsBeneficiary {
+            accounts {
+                beneficiary: signer,
+                claim_beneficiary: readonly,
+                recipient_claim: writable,
+                mailer_account: writable,
+                beneficiary_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+            }
+            args { recipient: Pubkey }
+        }
+        /// Accounts for `MigrateState`
+        MigrateState {
+            accounts { owner: writable_signer, mailer_account: writable, system_program: readonly }
+            args {}
+        }
+
+        /// Accounts for `SetTreasury`
+        /// Trailing account when setting: the treasury USDC account
+        SetTreasury {
+            accounts { owner: signer, mailer_account: writable }
+            args { token_account: Option<Pubkey> }
+        }
+
+        /// Accounts for `VerifySolvency`
+        /// Trailing accounts: up to 32 recipient claims, in ascending address order
+        VerifySolvency {
+            accounts { mailer_account: readonly, mailer_usdc: readonly }
+            args {}
+        }
+
+        /// Accounts for `SetClaimMerkleRoot`
+        SetClaimMerkleRoot {
+            accounts {
+                owner: writable_signer,
+                mailer_account: readonly,
+                claim_distribution: writable,
+                owner_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+                system_program: readonly,
+            }
+            args { distribution_id: u64, merkle_root: [u8; 32], leaf_count: u64, total: u64 }
+        }
+
+        /// Accounts for `ClaimFromMerkle`
+        ClaimFromMerkle {
+            accounts {
+                recipient: writable_signer,
+                claim_distribution: writable,
+                recipient_claim: writable,
+                mailer_account: writable,
+                system_program: readonly,
+            }
+            args { distribution_id: u64, index: u64, amount: u64, proof: Vec<[u8; 32]> }
+        }
+
+        /// Accounts for `SendEscrowed`
+        /// Required trailing accounts: the recipient's inbox policy PDA and the sender's message
+        /// nonce PDA
+        /// Optional trailing accounts: fee discount
+        SendEscrowed {
+            accounts {
+                sender: writable_signer,
+                escrow: writable,
+                mailer_account: readonly,
+                sender_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+                system_program: readonly,
+            }
+            args { to: Pubkey, subject: String, _body: String, nonce: u64 }
+        }
+
+        /// Accounts for `AcceptMessage`
+        /// Trailing account when a treasury is set: the treasury USDC account
+        AcceptMessage {
+            accounts {
+                recipient: writable_signer,
+                escrow: writable,
+                recipient_claim: writable,
+                mailer_account: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+                system_program: readonly,
+                sender: writable,
+            }
+            args { sender: Pubkey, nonce: u64 }
+        }
+
+        /// Accounts for `ReclaimEscrow`
+        ReclaimEscrow {
+            accounts {
+                sender: writable_signer,
+                escrow: writable,
+                mailer_account: readonly,
+                sender_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+            }
+            args { nonce: u64 }
+        }
+
+        /// Accounts for `CreateList`
+        CreateList {
+            accounts { owner: writable_signer, mailing_list: writable, system_program: readonly }
+            args { list_id: u64 }
+        }
+
+        /// Accounts for `AddListMember`
+        AddListMember {
+            accounts {
+                owner: writable_signer,
+                mailing_list: writable,
+                list_chunk: writable,
+                system_program: readonly,
+            }
+            args { list_id: u64, chunk: u64, member: Pubkey }
+        }
+
+        /// Accounts for `RemoveListMember`
+        RemoveListMember {
+            accounts { owner: signer, mailing_list: writable, list_chunk: writable }
+            args { list_id: u64, chunk: u64, member: Pubkey }
+        }
+
+        /// Accounts for `SendToList`
+        /// Required trailing account: the owner's message nonce PDA, with the system program until
+        /// it exists
+        /// Optional trailing accounts: fee discount, and the treasury USDC account once set
+        SendToList {
+            accounts {
+                owner: writable_signer,
+                mailing_list: readonly,
+                mailer_account: writable,
+                owner_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+            }
+            args { list_id: u64, subject: String, _body: String }
+        }
+
+        /// Accounts for `SendToDomain`
+        /// Trailing accounts: as for `Send`, keyed to the domain owner
+        SendToDomain {
+            accounts {
+                sender: writable_signer,
+                name_account: readonly,
+                recipient_claim: writable,
+                mailer_account: writable,
+                sender_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+                system_program: readonly,
+            }
+            args {
+                domain: String,
+                subject: String,
+                _body: String,
+                revenue_share_to_receiver: bool,
+                strict: bool,
+            }
+        }
+
+        /// Accounts for `BindEvmAddress`
+        /// The secp256k1 program instruction proving the binding must directly precede it
+        BindEvmAddress {
+            accounts {
+                wallet: writable_signer,
+                evm_mapping: writable,
+                instructions_sysvar: readonly,
+                system_program: readonly,
+            }
+            args { evm_address: [u8; 20] }
+        }
+
+        /// Accounts for `SendToEvmAddress`
+        /// Trailing accounts: as for `Send`, keyed to the bound wallet
+        SendToEvmAddress {
+            accounts {
+                sender: writable_signer,
+                evm_mapping: readonly,
+                recipient_claim: writable,
+                mailer_account: writable,
+                sender_usdc: writable,
+                mailer_usdc: writable,
+                token_program: readonly,
+                system_program: readonly,
+            }
+            args {
+                evm_address: [u8; 20],
+                subject: String,
+                _body: String,
+                revenue_share_to_receiver: bool,
+                strict: bool,
+            }
+        }
+
+        /// Accounts for `SetWormholeBridge`
+        SetWormholeBridge {
+            accounts {
+                owner: writable_signer,
+                mailer_account: readonly,
+                wormhole_config: writable,
+                system_program: readonly,
+            }
+            args { core_bridge: Option<Pubkey> }
+        }
+
+        /// Accounts for `GetFee`
+        GetFee {
+            accounts { mailer_account: readonly }
+            args { reserved: Reserved }
+        }
+
+        /// Accounts for `GetClaimable`
+        GetClaimable {
+            accounts { mailer_account: readonly, recipient_claim: readonly }
+            args { account: Pubkey, reserved: Reserved }
+        }
+
+        /// Accounts for `GetDelegation`
+        GetDelegation {
+            accounts { delegation: readonly }
+            args { delegator: Pubkey, reserved: Reserved }
+        }
+
+        /// Accounts for `SetDiscountSchedule`
+        SetDiscountSchedule {
+            accounts {
+                owner: writable_signer,
+                mailer_account: readonly,
+                discount_schedule: writable,
+                system_program: readonly,
+            }
+            args { tiers: Vec<VolumeTier>, reserved: Reserved }
+        }
+    }
+}
 
 /// Send a message to a wallet address via CPI
 ///
 /// # Arguments
 /// * `mailer_program` - Mailer program account
-/// * `sender` - Sender account (must be signer)
+/// * `sender` - Sender account (must be signer; pays for the claim account on first priority send,
+///   unless `accounts::Send` is invoked with a sponsor as the rent payer)
 /// * `recipient_claim_pda` - Recipient's claim PDA account (writable)
-/// * `mailer_state` - Mailer state PDA account
+/// * `mailer_state` - Mailer state PDA account (writable)
 /// * `sender_usdc` - Sender's USDC token account (writable)
 /// * `mailer_usdc` - Mailer's USDC token account (writable)
 /// * `token_program` - SPL Token program
 /// * `system_program` - System program
+/// * `inbox_policy` - Recipient's inbox policy PDA, passed even while uncreated
+/// * `message_nonce` - Sender's message nonce PDA (writable), created on the first send
 /// * `to` - Recipient's wallet address
 /// * `subject` - Message subject
 /// * `body` - Message body
@@ -70,48 +1498,42 @@ pub fn send<'a>(
     mailer_usdc: &AccountInfo<'a>,
     token_program: &AccountInfo<'a>,
     system_program: &AccountInfo<'a>,
+    inbox_policy: &AccountInfo<'a>,
+    message_nonce: &AccountInfo<'a>,
     to: Pubkey,
     subject: String,
     body: String,
     revenue_share_to_receiver: bool,
     resolve_sender_to_name: bool,
 ) -> ProgramResult {
-    let instruction = MailerInstruction::Send {
+    accounts::Send {
+        sender: sender.clone(),
+        recipient_claim: recipient_claim_pda.clone(),
+        mailer_account: mailer_state.clone(),
+        sender_usdc: sender_usdc.clone(),
+        mailer_usdc: mailer_usdc.clone(),
+        token_program: token_program.clone(),
+        system_program: system_program.clone(),
+    }
+    .invoke_with_remaining(
+        mailer_program,
+        &[sender.clone(), inbox_policy.clone(), message_nonce.clone()],
+        &[],
         to,
         subject,
-        _body: body,
+        body,
         revenue_share_to_receiver,
         resolve_sender_to_name,
-    };
-
-    let accounts = vec![
-        AccountMeta::new_readonly(*sender.key, true),
-        AccountMeta::new(*recipient_claim_pda.key, false),
-        AccountMeta::new_readonly(*mailer_state.key, false),
-        AccountMeta::new(*sender_usdc.key, false),
-        AccountMeta::new(*mailer_usdc.key, false),
-        AccountMeta::new_readonly(*token_program.key, false),
-        AccountMeta::new_readonly(*system_program.key, false),
-    ];
-
-    let instruction_data = instruction.try_to_vec()?;
-    let ix = Instruction {
-        program_id: *mailer_program.key,
-        accounts,
-        data: instruction_data,
-    };
-
-    invoke(
-        &ix,
-        &[
-            sender.clone(),
-            recipient_claim_pda.clone(),
-            mailer_state.clone(),
-            sender_usdc.clone(),
-            mailer_usdc.clone(),
-            token_program.clone(),
-            system_program.clone(),
-        ],
+        false,
+        None,
+        None,
+        false,
+        None,
+        None,
+        false,
+        0,
+        false,
+        None,
     )
 }
 
@@ -128,52 +1550,42 @@ pub fn send_prepared<'a>(
     mailer_usdc: &AccountInfo<'a>,
     token_program: &AccountInfo<'a>,
     system_program: &AccountInfo<'a>,
+    inbox_policy: &AccountInfo<'a>,
+    message_nonce: &AccountInfo<'a>,
     to: Pubkey,
     mail_id: String,
     revenue_share_to_receiver: bool,
     resolve_sender_to_name: bool,
 ) -> ProgramResult {
-    let instruction = MailerInstruction::SendPrepared {
+    accounts::SendPrepared {
+        sender: sender.clone(),
+        recipient_claim: recipient_claim_pda.clone(),
+        mailer_account: mailer_state.clone(),
+        sender_usdc: sender_usdc.clone(),
+        mailer_usdc: mailer_usdc.clone(),
+        token_program: token_program.clone(),
+        system_program: system_program.clone(),
+    }
+    .invoke_with_remaining(
+        mailer_program,
+        &[sender.clone(), inbox_policy.clone(), message_nonce.clone()],
+        &[],
         to,
         mail_id,
         revenue_share_to_receiver,
         resolve_sender_to_name,
-    };
-
-    let accounts = vec![
-        AccountMeta::new_readonly(*sender.key, true),
-        AccountMeta::new(*recipient_claim_pda.key, false),
-        AccountMeta::new_readonly(*mailer_state.key, false),
-        AccountMeta::new(*sender_usdc.key, false),
-        AccountMeta::new(*mailer_usdc.key, false),
-        AccountMeta::new_readonly(*token_program.key, false),
-        AccountMeta::new_readonly(*system_program.key, false),
-    ];
-
-    let instruction_data = instruction.try_to_vec()?;
-    let ix = Instruction {
-        program_id: *mailer_program.key,
-        accounts,
-        data: instruction_data,
-    };
-
-    invoke(
-        &ix,
-        &[
-            sender.clone(),
-            recipient_claim_pda.clone(),
-            mailer_state.clone(),
-            sender_usdc.clone(),
-            mailer_usdc.clone(),
-            token_program.clone(),
-            system_program.clone(),
-        ],
+        false,
+        None,
+        None,
+        false,
+        0,
     )
 }
 
 /// Send a message to an email address (when wallet is unknown) via CPI
 ///
 /// Always charges standard 10% fee since there's no recipient wallet for revenue sharing
+#[allow(clippy::too_many_arguments)]
 pub fn send_to_email<'a>(
     mailer_program: &AccountInfo<'a>,
     sender: &AccountInfo<'a>,
@@ -181,44 +1593,70 @@ pub fn send_to_email<'a>(
     sender_usdc: &AccountInfo<'a>,
     mailer_usdc: &AccountInfo<'a>,
     token_program: &AccountInfo<'a>,
+    message_nonce: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
     to_email: String,
     subject: String,
     body: String,
 ) -> ProgramResult {
-    let instruction = MailerInstruction::SendToEmail {
+    accounts::SendToEmail {
+        sender: sender.clone(),
+        mailer_account: mailer_state.clone(),
+        sender_usdc: sender_usdc.clone(),
+        mailer_usdc: mailer_usdc.clone(),
+        token_program: token_program.clone(),
+    }
+    .invoke_with_remaining(
+        mailer_program,
+        &[message_nonce.clone(), system_program.clone()],
+        &[],
         to_email,
         subject,
-        _body: body,
-    };
-
-    let accounts = vec![
-        AccountMeta::new_readonly(*sender.key, true),
-        AccountMeta::new_readonly(*mailer_state.key, false),
-        AccountMeta::new(*sender_usdc.key, false),
-        AccountMeta::new(*mailer_usdc.key, false),
-        AccountMeta::new_readonly(*token_program.key, false),
-    ];
-
-    let instruction_data = instruction.try_to_vec()?;
-    let ix = Instruction {
-        program_id: *mailer_program.key,
-        accounts,
-        data: instruction_data,
-    };
+        body,
+        false,
+    )
+}
 
-    invoke(
-        &ix,
-        &[
-            sender.clone(),
-            mailer_state.clone(),
-            sender_usdc.clone(),
-            mailer_usdc.clone(),
-            token_program.clone(),
-        ],
+/// Send a message to a salted email hash (see [`crate::hash_email`]) via CPI, keeping the
+/// address out of the transaction
+///
+/// Charges the standard 10% fee like [`send_to_email`]
+#[allow(clippy::too_many_arguments)]
+pub fn send_to_email_hash<'a>(
+    mailer_program: &AccountInfo<'a>,
+    sender: &AccountInfo<'a>,
+    mailer_state: &AccountInfo<'a>,
+    sender_usdc: &AccountInfo<'a>,
+    mailer_usdc: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    message_nonce: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    email_hash: [u8; 32],
+    hint_id: u64,
+    subject: String,
+    body: String,
+) -> ProgramResult {
+    accounts::SendToEmailHash {
+        sender: sender.clone(),
+        mailer_account: mailer_state.clone(),
+        sender_usdc: sender_usdc.clone(),
+        mailer_usdc: mailer_usdc.clone(),
+        token_program: token_program.clone(),
+    }
+    .invoke_with_remaining(
+        mailer_program,
+        &[message_nonce.clone(), system_program.clone()],
+        &[],
+        email_hash.to_vec(),
+        hint_id,
+        subject,
+        body,
+        false,
     )
 }
 
 /// Send a prepared message to an email address via CPI
+#[allow(clippy::too_many_arguments)]
 pub fn send_prepared_to_email<'a>(
     mailer_program: &AccountInfo<'a>,
     sender: &AccountInfo<'a>,
@@ -226,41 +1664,33 @@ pub fn send_prepared_to_email<'a>(
     sender_usdc: &AccountInfo<'a>,
     mailer_usdc: &AccountInfo<'a>,
     token_program: &AccountInfo<'a>,
+    message_nonce: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
     to_email: String,
     mail_id: String,
 ) -> ProgramResult {
-    let instruction = MailerInstruction::SendPreparedToEmail { to_email, mail_id };
-
-    let accounts = vec![
-        AccountMeta::new_readonly(*sender.key, true),
-        AccountMeta::new_readonly(*mailer_state.key, false),
-        AccountMeta::new(*sender_usdc.key, false),
-        AccountMeta::new(*mailer_usdc.key, false),
-        AccountMeta::new_readonly(*token_program.key, false),
-    ];
-
-    let instruction_data = instruction.try_to_vec()?;
-    let ix = Instruction {
-        program_id: *mailer_program.key,
-        accounts,
-        data: instruction_data,
-    };
-
-    invoke(
-        &ix,
-        &[
-            sender.clone(),
-            mailer_state.clone(),
-            sender_usdc.clone(),
-            mailer_usdc.clone(),
-            token_program.clone(),
-        ],
+    accounts::SendPreparedToEmail {
+        sender: sender.clone(),
+        mailer_account: mailer_state.clone(),
+        sender_usdc: sender_usdc.clone(),
+        mailer_usdc: mailer_usdc.clone(),
+        token_program: token_program.clone(),
+    }
+    .invoke_with_remaining(
+        mailer_program,
+        &[message_nonce.clone(), system_program.clone()],
+        &[],
+        to_email,
+        mail_id,
+        false,
     )
 }
 
 /// Send a message through a webhook via CPI
 ///
-/// Useful for integration with external notification systems
+/// Useful for integration with external notification systems. The webhook must be
+/// registered by `sender` (typically the calling program's PDA) and active. Its surcharge,
+/// if any, is paid from `sender_usdc` on top of the send fee.
 #[allow(clippy::too_many_arguments)]
 pub fn send_through_webhook<'a>(
     mailer_program: &AccountInfo<'a>,
@@ -271,60 +1701,122 @@ pub fn send_through_webhook<'a>(
     mailer_usdc: &AccountInfo<'a>,
     token_program: &AccountInfo<'a>,
     system_program: &AccountInfo<'a>,
+    webhook: &AccountInfo<'a>,
+    inbox_policy: &AccountInfo<'a>,
+    message_nonce: &AccountInfo<'a>,
     to: Pubkey,
     webhook_id: String,
     revenue_share_to_receiver: bool,
     resolve_sender_to_name: bool,
 ) -> ProgramResult {
-    let instruction = MailerInstruction::SendThroughWebhook {
+    accounts::SendThroughWebhook {
+        sender: sender.clone(),
+        recipient_claim: recipient_claim_pda.clone(),
+        mailer_account: mailer_state.clone(),
+        sender_usdc: sender_usdc.clone(),
+        mailer_usdc: mailer_usdc.clone(),
+        token_program: token_program.clone(),
+        system_program: system_program.clone(),
+        webhook: webhook.clone(),
+    }
+    .invoke_with_remaining(
+        mailer_program,
+        &[sender.clone(), inbox_policy.clone(), message_nonce.clone()],
+        &[],
         to,
         webhook_id,
         revenue_share_to_receiver,
         resolve_sender_to_name,
-    };
+        false,
+        None,
+        None,
+        false,
+        0,
+    )
+}
 
-    let accounts = vec![
-        AccountMeta::new_readonly(*sender.key, true),
-        AccountMeta::new(*recipient_claim_pda.key, false),
-        AccountMeta::new_readonly(*mailer_state.key, false),
-        AccountMeta::new(*sender_usdc.key, false),
-        AccountMeta::new(*mailer_usdc.key, false),
-        AccountMeta::new_readonly(*token_program.key, false),
-        AccountMeta::new_readonly(*system_program.key, false),
-    ];
-
-    let instruction_data = instruction.try_to_vec()?;
-    let ix = Instruction {
-        program_id: *mailer_program.key,
-        accounts,
-        data: instruction_data,
-    };
+/// Read the fees the Mailer program charges now via CPI
+///
+/// # Arguments
+/// * `mailer_program` - Mailer program account
+/// * `mailer_state` - Mailer state PDA account
+pub fn get_fee<'a>(
+    mailer_program: &AccountInfo<'a>,
+    mailer_state: &AccountInfo<'a>,
+) -> Result<FeeView, ProgramError> {
+    accounts::GetFee {
+        mailer_account: mailer_state.clone(),
+    }
+    .invoke(mailer_program, Reserved::default())?;
+    view_return_data(mailer_program.key)
+}
 
-    invoke(
-        &ix,
-        &[
-            sender.clone(),
-            recipient_claim_pda.clone(),
-            mailer_state.clone(),
-            sender_usdc.clone(),
-            mailer_usdc.clone(),
-            token_program.clone(),
-            system_program.clone(),
-        ],
-    )
+/// Read the recipient share `account` could claim now via CPI
+///
+/// # Arguments
+/// * `mailer_program` - Mailer program account
+/// * `mailer_state` - Mailer state PDA account
+/// * `recipient_claim_pda` - Claim PDA account of `account`, which need not exist yet
+/// * `account` - Recipient wallet
+pub fn get_claimable<'a>(
+    mailer_program: &AccountInfo<'a>,
+    mailer_state: &AccountInfo<'a>,
+    recipient_claim_pda: &AccountInfo<'a>,
+    account: Pubkey,
+) -> Result<ClaimableView, ProgramError> {
+    accounts::GetClaimable {
+        mailer_account: mailer_state.clone(),
+        recipient_claim: recipient_claim_pda.clone(),
+    }
+    .invoke(mailer_program, account, Reserved::default())?;
+    view_return_data(mailer_program.key)
+}
+
+/// Read the delegation of `delegator` via CPI
+///
+/// # Arguments
+/// * `mailer_program` - Mailer program account
+/// * `delegation_pda` - Delegation PDA account of `delegator`, which need not exist yet
+/// * `delegator` - Delegating wallet
+pub fn get_delegation<'a>(
+    mailer_program: &AccountInfo<'a>,
+    delegation_pda: &AccountInfo<'a>,
+    delegator: Pubkey,
+) -> Result<DelegationView, ProgramError> {
+    accounts::GetDelegation {
+        delegation: delegation_pda.clone(),
+    }
+    .invoke(mailer_program, delegator, Reserved::default())?;
+    view_return_data(mailer_program.key)
+}
+
+/// Message id of the message just sent through any Send* helper or account struct, read from
+/// the send's [`SendResult`] return data
+///
+/// Call it right after the send, before invoking another program, which would clear the
+/// return data.
+pub fn sent_message_id(mailer_program: &AccountInfo) -> Result<[u8; 32], ProgramError> {
+    view_return_data::<SendResult>(mailer_program.key).map(|result| result.message_id)
+}
+
+/// Decode the return data a view instruction of the Mailer program just set
+fn view_return_data<T: BorshDeserialize>(mailer_program_id: &Pubkey) -> Result<T, ProgramError> {
+    match get_return_data() {
+        Some((program_id, data)) if program_id == *mailer_program_id => {
+            Ok(T::try_from_slice(&data)?)
+        }
+        _ => Err(MailerError::MissingReturnData.into()),
+    }
 }
 
 /// Helper function to derive the recipient claim PDA
 ///
 /// Use this to get the correct PDA address for recipient claims
 pub fn derive_recipient_claim_pda(mailer_program_id: &Pubkey, recipient: &Pubkey) -> (Pubkey, u8) {
-    Pubkey::find_program_address(
-        &[b"claim", &[1], recipient.as_ref()],
-        mailer_program_id,
-    )
+    crate::pda::recipient_claim(mailer_program_id, recipient)
 }
 
 /// Helper function to derive the mailer state PDA
 pub fn derive_mailer_state_pda(mailer_program_id: &Pubkey) -> (Pubkey, u8) {
-    Pubkey::find_program_address(&[b"mailer"], mailer_program_id)
+    crate::pda::mailer_state(mailer_program_id)
 }
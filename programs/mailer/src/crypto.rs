@@ -0,0 +1,180 @@
+//! # Encrypted Envelopes
+//!
+//! `SendEncrypted` carries a body sealed to the recipient's wallet key. The sender picks an
+//! ephemeral X25519 key pair, agrees a shared secret with the recipient's ed25519 wallet key
+//! converted to X25519, and encrypts the body with AES-256-GCM-SIV under a key derived from
+//! that secret. The envelope on chain is the ephemeral public key, the 12-byte nonce and the
+//! ciphertext; the program only checks its structure with [`is_well_formed`] and never sees
+//! the plaintext.
+//!
+//! With the `crypto` feature, [`seal`] and [`open`] implement the scheme off-chain. Callers
+//! supply the ephemeral secret and nonce, which must come from a secure random source and
+//! never be reused.
+//!
+//! ```rust
+//! # #[cfg(feature = "crypto")]
+//! # {
+//! use mailer::crypto;
+//! use solana_program::pubkey::Pubkey;
+//!
+//! // The recipient's wallet: its 32-byte ed25519 secret seed and public key
+//! let seed = [7u8; 32];
+//! let recipient = Pubkey::new_from_array(crypto::wallet_public_key(&seed));
+//!
+//! let envelope = crypto::seal(&recipient, b"Hello", [1u8; 32], [2u8; 12]).unwrap();
+//! assert!(crypto::is_well_formed(&envelope.ephemeral_key, &envelope.ciphertext));
+//! assert_eq!(crypto::open(&seed, &envelope).unwrap(), b"Hello");
+//! # }
+//! ```
+
+use solana_program::hash::hashv;
+
+/// Length of an envelope nonce
+pub const NONCE_LEN: usize = 12;
+
+/// Length of the authentication tag ending every ciphertext
+pub const TAG_LEN: usize = 16;
+
+/// Maximum ciphertext length, tag included, so a `SendEncrypted` fits in one transaction
+pub const MAX_CIPHERTEXT_LEN: usize = 512;
+
+/// A body sealed to a recipient's wallet key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Envelope {
+    /// Sender's ephemeral X25519 public key
+    pub ephemeral_key: [u8; 32],
+    pub nonce: [u8; NONCE_LEN],
+    /// AES-256-GCM-SIV ciphertext followed by its tag
+    pub ciphertext: Vec<u8>,
+}
+
+/// Whether an envelope has a non-zero ephemeral key and a ciphertext holding at least a tag
+/// and at most [`MAX_CIPHERTEXT_LEN`] bytes
+pub fn is_well_formed(ephemeral_key: &[u8; 32], ciphertext: &[u8]) -> bool {
+    ephemeral_key != &[0u8; 32] && (TAG_LEN..=MAX_CIPHERTEXT_LEN).contains(&ciphertext.len())
+}
+
+/// SHA-256 of an envelope's ephemeral key, nonce and ciphertext, logged by `SendEncrypted`
+/// so indexers can match the event to the instruction data
+pub fn envelope_digest(
+    ephemeral_key: &[u8; 32],
+    nonce: &[u8; NONCE_LEN],
+    ciphertext: &[u8],
+) -> [u8; 32] {
+    hashv(&[ephemeral_key, nonce, ciphertext]).to_bytes()
+}
+
+#[cfg(feature = "crypto")]
+pub use sealing::{open, seal, wallet_public_key, EnvelopeError};
+
+#[cfg(feature = "crypto")]
+mod sealing {
+    use super::{Envelope, MAX_CIPHERTEXT_LEN, NONCE_LEN, TAG_LEN};
+    use aes_gcm_siv::{
+        aead::{Aead, NewAead},
+        Aes256GcmSiv,
+    };
+    use curve25519_dalek::{
+        constants::{ED25519_BASEPOINT_TABLE, X25519_BASEPOINT},
+        edwards::CompressedEdwardsY,
+        montgomery::MontgomeryPoint,
+        scalar::Scalar,
+    };
+    use sha2::{Digest, Sha256, Sha512};
+    use solana_program::pubkey::Pubkey;
+
+    /// Domain separator of the envelope key derivation
+    const KEY_DOMAIN: &[u8] = b"mailer-envelope-v1";
+
+    /// Why an envelope could not be sealed or opened
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum EnvelopeError {
+        /// The recipient key is not a valid ed25519 point, or the shared secret is degenerate
+        InvalidKey,
+        /// The plaintext does not fit in [`MAX_CIPHERTEXT_LEN`]
+        TooLong,
+        /// The ciphertext was altered or sealed to another key
+        Decryption,
+    }
+
+    /// Seal `plaintext` to the wallet `recipient` with the sender's ephemeral secret and nonce
+    pub fn seal(
+        recipient: &Pubkey,
+        plaintext: &[u8],
+        ephemeral_secret: [u8; 32],
+        nonce: [u8; NONCE_LEN],
+    ) -> Result<Envelope, EnvelopeError> {
+        if plaintext.len() + TAG_LEN > MAX_CIPHERTEXT_LEN {
+            return Err(EnvelopeError::TooLong);
+        }
+        let recipient_point = CompressedEdwardsY(recipient.to_bytes())
+            .decompress()
+            .filter(|point| !point.is_small_order())
+            .ok_or(EnvelopeError::InvalidKey)?
+            .to_montgomery();
+
+        let ephemeral = clamp(ephemeral_secret);
+        let ephemeral_key = (X25519_BASEPOINT * ephemeral).to_bytes();
+        let key = envelope_key(&(recipient_point * ephemeral), &ephemeral_key, recipient)?;
+        let ciphertext = cipher(&key)
+            .encrypt((&nonce).into(), plaintext)
+            .map_err(|_| EnvelopeError::TooLong)?;
+
+        Ok(Envelope {
+            ephemeral_key,
+            nonce,
+            ciphertext,
+        })
+    }
+
+    /// Open an envelope with the recipient wallet's 32-byte ed25519 secret seed
+    pub fn open(wallet_seed: &[u8; 32], envelope: &Envelope) -> Result<Vec<u8>, EnvelopeError> {
+        let recipient = Pubkey::new_from_array(wallet_public_key(wallet_seed));
+        let shared = MontgomeryPoint(envelope.ephemeral_key) * wallet_scalar(wallet_seed);
+        let key = envelope_key(&shared, &envelope.ephemeral_key, &recipient)?;
+        cipher(&key)
+            .decrypt((&envelope.nonce).into(), envelope.ciphertext.as_slice())
+            .map_err(|_| EnvelopeError::Decryption)
+    }
+
+    /// Ed25519 public key of a wallet's 32-byte secret seed
+    pub fn wallet_public_key(wallet_seed: &[u8; 32]) -> [u8; 32] {
+        (&wallet_scalar(wallet_seed) * &ED25519_BASEPOINT_TABLE)
+            .compress()
+            .to_bytes()
+    }
+
+    /// The ed25519 signing scalar of a seed, which is also its X25519 secret
+    fn wallet_scalar(wallet_seed: &[u8; 32]) -> Scalar {
+        let mut expanded = [0u8; 32];
+        expanded.copy_from_slice(&Sha512::digest(wallet_seed)[..32]);
+        clamp(expanded)
+    }
+
+    fn clamp(mut bytes: [u8; 32]) -> Scalar {
+        bytes[0] &= 248;
+        bytes[31] &= 127;
+        bytes[31] |= 64;
+        Scalar::from_bits(bytes)
+    }
+
+    fn envelope_key(
+        shared: &MontgomeryPoint,
+        ephemeral_key: &[u8; 32],
+        recipient: &Pubkey,
+    ) -> Result<[u8; 32], EnvelopeError> {
+        if shared.as_bytes() == &[0u8; 32] {
+            return Err(EnvelopeError::InvalidKey);
+        }
+        let mut hasher = Sha256::new();
+        hasher.update(KEY_DOMAIN);
+        hasher.update(shared.as_bytes());
+        hasher.update(ephemeral_key);
+        hasher.update(recipient.as_ref());
+        Ok(hasher.finalize().into())
+    }
+
+    fn cipher(key: &[u8; 32]) -> Aes256GcmSiv {
+        Aes256GcmSiv::new(key.into())
+    }
+}
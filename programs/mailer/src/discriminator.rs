@@ -0,0 +1,146 @@
+//! # Account Discriminators
+//!
+//! Every Mailer account starts with an 8-byte discriminator naming its type. Accounts
+//! are created with Anchor-style discriminators, the first 8 bytes of
+//! `sha256("account:<Name>")`, which are stable across toolchains.
+//!
+//! Accounts created by earlier deployments carry a legacy discriminator produced by
+//! the standard library's `DefaultHasher`, whose output is not guaranteed across Rust
+//! releases. The legacy values are frozen in [`LEGACY`] instead of being recomputed, so
+//! those accounts stay recognizable during the migration window; the permissionless
+//! `MigrateDiscriminator` instruction rewrites them in place. Account types added since
+//! then are listed in [`SHA256_ONLY`] and never had a legacy value.
+//!
+//! ```rust
+//! use mailer::discriminator::{self, Version};
+//!
+//! let current = discriminator::account("RecipientClaim");
+//! assert_eq!(discriminator::identify(&current), Some(("RecipientClaim", Version::Sha256)));
+//! ```
+
+use solana_program::hash::hash;
+
+/// Discriminator scheme an account was written with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    /// Frozen `DefaultHasher` value, accepted until the account is migrated
+    Legacy,
+    /// `sha256("account:<Name>")[..8]`
+    Sha256,
+}
+
+/// Legacy discriminators of every account type, as written by earlier deployments
+pub const LEGACY: &[(&str, [u8; 8])] = &[
+    (
+        "MailerState",
+        [0x6f, 0xc2, 0xc4, 0x45, 0xea, 0x02, 0x79, 0xdb],
+    ),
+    (
+        "RecipientClaim",
+        [0x8a, 0x3a, 0xf8, 0x7d, 0xf5, 0x45, 0xd5, 0x3a],
+    ),
+    (
+        "Delegation",
+        [0x0b, 0x5e, 0x26, 0xb6, 0x24, 0xc0, 0xed, 0x26],
+    ),
+    (
+        "FeeDiscount",
+        [0xd2, 0x82, 0xef, 0x2c, 0x29, 0xfb, 0xec, 0xb6],
+    ),
+    (
+        "RecipientPrefs",
+        [0x96, 0x25, 0xaa, 0x07, 0xf5, 0x71, 0x8a, 0x06],
+    ),
+    (
+        "PayoutConfig",
+        [0x4c, 0x59, 0x2a, 0x5c, 0xcb, 0x8a, 0x15, 0xac],
+    ),
+    (
+        "ConversionVault",
+        [0x6f, 0xee, 0xda, 0x91, 0x3e, 0x5a, 0x58, 0x11],
+    ),
+    (
+        "MessageReceipt",
+        [0xc6, 0x0a, 0xeb, 0xf0, 0xa4, 0x03, 0x3a, 0xdf],
+    ),
+    (
+        "ClaimHistoryEntry",
+        [0x8b, 0x29, 0xe7, 0xaa, 0x0f, 0x98, 0x65, 0xba],
+    ),
+    (
+        "InboxPolicy",
+        [0xb2, 0xb2, 0x28, 0xbd, 0xee, 0x39, 0x69, 0x5d],
+    ),
+    (
+        "SessionKey",
+        [0x1a, 0x41, 0x67, 0xfc, 0x22, 0x98, 0x2e, 0xb8],
+    ),
+    (
+        "Preauthorization",
+        [0xa3, 0x13, 0x4b, 0xf2, 0x95, 0xa1, 0xa5, 0x09],
+    ),
+    ("AdminSet", [0x6f, 0xf8, 0x4c, 0xde, 0x3c, 0xcd, 0x00, 0xbb]),
+    (
+        "SweepCursor",
+        [0x5e, 0x95, 0xfc, 0xa7, 0x71, 0x32, 0xac, 0xf7],
+    ),
+];
+
+/// Account types introduced after the migration, written only with SHA-256 discriminators
+pub const SHA256_ONLY: &[&str] = &[
+    "RateLimit",
+    "Promo",
+    "FeeMint",
+    "RecipientInbox",
+    "ClaimRentPayer",
+    "Webhook",
+    "ClosureLog",
+    "Sanctioned",
+    "DelegateIndex",
+    "MailerStats",
+    "ClaimBeneficiary",
+    "ClaimDistribution",
+    "Escrow",
+    "MailingList",
+    "ListChunk",
+    "EvmMapping",
+    "WormholeConfig",
+    "MessageNonce",
+    "SendNonce",
+    "DiscountSchedule",
+    "SenderStats",
+];
+
+/// Current discriminator of the account type `name`
+pub fn account(name: &str) -> [u8; 8] {
+    let digest = hash(format!("account:{}", name).as_bytes());
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&digest.to_bytes()[..8]);
+    discriminator
+}
+
+/// Legacy discriminator of the account type `name`
+pub fn legacy(name: &str) -> Option<[u8; 8]> {
+    LEGACY.iter().find(|(n, _)| *n == name).map(|(_, d)| *d)
+}
+
+/// Account type and scheme of a discriminator, `None` if it is not a Mailer account
+pub fn identify(discriminator: &[u8]) -> Option<(&'static str, Version)> {
+    LEGACY
+        .iter()
+        .find_map(|(name, legacy)| {
+            if discriminator == legacy {
+                Some((*name, Version::Legacy))
+            } else if discriminator == account(name) {
+                Some((*name, Version::Sha256))
+            } else {
+                None
+            }
+        })
+        .or_else(|| {
+            SHA256_ONLY
+                .iter()
+                .find(|name| discriminator == account(name))
+                .map(|name| (*name, Version::Sha256))
+        })
+}
@@ -0,0 +1,448 @@
+//! # Program Errors
+//!
+//! Every [`MailerError`] reaches clients as `ProgramError::Custom(code)`. Codes start at
+//! [`ERROR_CODE_OFFSET`], clear of the small codes other programs return through CPI, such as
+//! spl-token's `InsufficientFunds` at `Custom(1)`, so a failed transaction's code names one
+//! Mailer error and maps back with [`MailerError::from_code`]. Codes follow declaration order:
+//! new variants are appended, never inserted or removed.
+//!
+//! The program logs the name and message of the error failing an instruction through
+//! [`PrintProgramError`], next to the runtime's hexadecimal code.
+//!
+//! ```rust
+//! use mailer::error::{MailerError, ERROR_CODE_OFFSET};
+//! use solana_program::program_error::ProgramError;
+//!
+//! assert_eq!(MailerError::OnlyOwner.code(), ERROR_CODE_OFFSET);
+//! let error: ProgramError = MailerError::ContractPaused.into();
+//! assert_eq!(error, ProgramError::Custom(MailerError::ContractPaused.code()));
+//! assert!(matches!(MailerError::from_code(MailerError::ContractPaused.code()), Some(MailerError::ContractPaused)));
+//! assert!(MailerError::from_code(1).is_none());
+//! ```
+
+use num_derive::FromPrimitive;
+use solana_program::{
+    decode_error::DecodeError,
+    msg,
+    program_error::{PrintProgramError, ProgramError},
+};
+use thiserror::Error;
+
+/// Code of the first Mailer error
+pub const ERROR_CODE_OFFSET: u32 = 6000;
+
+/// Custom program errors
+#[derive(Error, Debug, Copy, Clone, PartialEq, Eq, FromPrimitive)]
+#[repr(u32)]
+pub enum MailerError {
+    #[error("Only the owner can perform this action")]
+    OnlyOwner = ERROR_CODE_OFFSET,
+    #[error("No claimable amount available")]
+    NoClaimableAmount,
+    #[error("Claim period has expired")]
+    ClaimPeriodExpired,
+    #[error("Claim period has not expired yet")]
+    ClaimPeriodNotExpired,
+    #[error("Invalid recipient")]
+    InvalidRecipient,
+    #[error("No delegation to reject")]
+    NoDelegationToReject,
+    #[error("Invalid delegator")]
+    InvalidDelegator,
+    #[error("Account already initialized")]
+    AlreadyInitialized,
+    #[error("Account not initialized")]
+    NotInitialized,
+    #[error("Invalid PDA")]
+    InvalidPDA,
+    #[error("Invalid account owner")]
+    InvalidAccountOwner,
+    #[error("Invalid token mint")]
+    InvalidMint,
+    #[error("Invalid token program")]
+    InvalidTokenProgram,
+    #[error("Contract is paused")]
+    ContractPaused,
+    #[error("Contract is not paused")]
+    ContractNotPaused,
+    #[error("Invalid percentage (must be 0-100)")]
+    InvalidPercentage,
+    #[error("Math overflow")]
+    MathOverflow,
+    #[error("Recipient has opted out of read receipts")]
+    ReadReceiptsDisabled,
+    #[error("Protocol sends are disabled")]
+    ProtocolSendsDisabled,
+    #[error("Only the pending owner can accept ownership")]
+    NotPendingOwner,
+    #[error("Invalid admin configuration")]
+    InvalidAdminConfig,
+    #[error("Sweep accounts must be in ascending order after the cursor")]
+    InvalidSweepOrder,
+    #[error("Fee timelock is active; only proposed send fees can change")]
+    FeeTimelockActive,
+    #[error("Invalid fee timelock")]
+    InvalidFeeTimelock,
+    #[error("Sender not allowed by recipient inbox policy")]
+    SenderNotAllowed,
+    #[error("Invalid inbox policy")]
+    InvalidInboxPolicy,
+    #[error("Message receipt already exists")]
+    ReceiptAlreadyExists,
+    #[error("Memo too long")]
+    MemoTooLong,
+    #[error("Invalid associated token account")]
+    InvalidAssociatedTokenAccount,
+    #[error("Claim amount must be non-zero and within the claimable balance")]
+    InvalidClaimAmount,
+    #[error("Invalid session key")]
+    InvalidSessionKey,
+    #[error("Session key has expired")]
+    SessionKeyExpired,
+    #[error("Session key scope does not allow this instruction")]
+    SessionScopeDenied,
+    #[error("Destination does not match the recipient's payout account")]
+    InvalidPayoutAccount,
+    #[error("Invalid system program")]
+    InvalidSystemProgram,
+    #[error("Invalid pre-authorization")]
+    InvalidPreauthorization,
+    #[error("Pre-authorization has expired or has no sends left")]
+    PreauthorizationExhausted,
+    #[error("Send fee exceeds the pre-authorized maximum")]
+    PreauthorizedFeeExceeded,
+    #[error("Instruction is disabled")]
+    InstructionDisabled,
+    #[error("Account discriminator is not a known Mailer account type")]
+    UnknownDiscriminator,
+    #[error("Account still holds a balance or active state")]
+    AccountNotEmpty,
+    #[error("Fee payment failed")]
+    FeePaymentFailed,
+    #[error("Spam oracle account missing or returned no score")]
+    InvalidSpamOracle,
+    #[error("Spam score exceeds the configured threshold")]
+    SpamScoreTooHigh,
+    #[error("Sender has reached the send rate limit for the current window")]
+    RateLimitExceeded,
+    #[error("Unknown promo code")]
+    InvalidPromoCode,
+    #[error("Promo code has expired or has no redemptions left")]
+    PromoExhausted,
+    #[error("Account already has an equal or larger fee discount")]
+    DiscountAlreadyApplied,
+    #[error("Lamport fee payments are disabled")]
+    LamportFeesDisabled,
+    #[error("Priority and surcharged webhook sends cannot pay their fee in lamports")]
+    LamportFeeUnsupported,
+    #[error("Fee oracle price account missing or not a trading price")]
+    InvalidFeeOracle,
+    #[error("Fee oracle price is older than the configured maximum age")]
+    StaleOraclePrice,
+    #[error("Signer is not the recorded rent payer of the claim")]
+    NotClaimRentPayer,
+    #[error("Encrypted envelope is malformed or too long")]
+    InvalidEnvelope,
+    #[error("On-chain value differs from the expected current value")]
+    StaleState,
+    #[error("Webhook is not registered by the sender or has been revoked")]
+    InvalidWebhook,
+    #[error("Signer is not the delegator's current delegate")]
+    NotDelegate,
+    #[error("Address is sanctioned")]
+    AddressSanctioned,
+    #[error("Backup vault cannot honor the claim in contingency mode")]
+    BackupVaultUnavailable,
+    #[error("Claimable balance cannot cover the no-expiry surcharge")]
+    NoExpirySurchargeUnpaid,
+    #[error("Batch has more accounts than allowed")]
+    BatchTooLarge,
+    #[error("Priority sends require a valid attestation from the KYC verifier")]
+    VerificationRequired,
+    #[error("Claim split must have 1 to 4 distinct destinations weighing 10000 bps in total")]
+    InvalidClaimSplit,
+    #[error("Delegation refund window must be between 0 and 30 days")]
+    InvalidDelegationRefundWindow,
+    #[error("No delegation to accept")]
+    NoDelegationToAccept,
+    #[error("Delegation fee is not refundable")]
+    DelegationFeeNotRefundable,
+    #[error("Refund the escrowed delegation fee before delegating again")]
+    DelegationFeeRefundPending,
+    #[error("Webhook has no routed send awaiting a delivery status")]
+    NoPendingDelivery,
+    #[error("Priority send would take the recipient claim past the claim cap")]
+    ClaimCapExceeded,
+    #[error("Plan needs a positive duration and message allowance")]
+    InvalidPlan,
+    #[error("Subscription plan is not active")]
+    PlanInactive,
+    #[error("Subscription is still active; renew it instead")]
+    SubscriptionActive,
+    #[error("Payload field exceeds its size limit")]
+    PayloadTooLarge,
+    #[error("Email hash must be 32 bytes")]
+    InvalidEmailHash,
+    #[error("Mailer vault does not cover its claim liabilities")]
+    TemporarilyInsolvent,
+    #[error("Invalid email address")]
+    InvalidEmailAddress,
+    #[error("Claim window extension limit reached")]
+    ClaimExtensionLimitReached,
+    #[error("Claimable balance cannot cover the claim extension fee")]
+    ClaimExtensionFeeUnpaid,
+    #[error("Signer is not the claim's registered beneficiary")]
+    NotClaimBeneficiary,
+    #[error("Claim is still in its beneficiary's grace period")]
+    BeneficiaryGracePeriodActive,
+    #[error("Beneficiary grace period has ended")]
+    BeneficiaryGracePeriodEnded,
+    #[error("Mailer state must be migrated to the current layout")]
+    StateMigrationRequired,
+    #[error("Mailer state is already in the current layout")]
+    StateAlreadyCurrent,
+    #[error("Treasury token account missing or not owned by the treasury PDA")]
+    InvalidTreasury,
+    #[error("Claim accounts must be distinct and in ascending order")]
+    UnsortedClaimBatch,
+    #[error("Distribution needs 1 to 32768 leaves and a non-zero total")]
+    InvalidDistribution,
+    #[error("Merkle proof does not match the distribution root")]
+    InvalidMerkleProof,
+    #[error("Distribution leaf has already been claimed")]
+    DistributionLeafClaimed,
+    #[error("Only priority sends can carry a tip")]
+    TipRequiresPriority,
+    #[error("Priority send pays less than the recipient's minimum fee")]
+    RecipientFeeTooLow,
+    #[error("Escrowed message can no longer be accepted")]
+    EscrowExpired,
+    #[error("Escrowed message can still be accepted by its recipient")]
+    EscrowNotExpired,
+    #[error("List chunk is full or not the next chunk to create")]
+    InvalidListChunk,
+    #[error("Address is already a member of the list chunk")]
+    ListMemberExists,
+    #[error("Address is not a member of the list chunk")]
+    NotListMember,
+    #[error("Mailing list has no members")]
+    EmptyList,
+    #[error("Name account is not the registered .sol domain")]
+    InvalidDomain,
+    #[error("No secp256k1 signature by the EVM address over the binding message")]
+    InvalidEvmSignature,
+    #[error("EVM address is not bound to a wallet")]
+    EvmAddressNotBound,
+    #[error("No Wormhole bridge is configured")]
+    WormholeNotConfigured,
+    #[error("Program built without Wormhole support")]
+    WormholeDisabled,
+    #[error("Unsupported instruction data format")]
+    UnsupportedInstructionFormat,
+    #[error("Sender's token or lamport balance cannot cover the fee")]
+    InsufficientFee,
+    #[error("Recipient has blocked the sender")]
+    RecipientBlocked,
+    #[error("Account is not rent-exempt")]
+    AccountNotRentExempt,
+    #[error("Unknown pause flags")]
+    InvalidPauseFlags,
+    #[error("Instruction tag cannot be disabled")]
+    InvalidInstructionTag,
+    #[error("Spam score threshold requires a spam oracle")]
+    InvalidSpamOracleConfig,
+    #[error("Rate limit needs a positive window")]
+    InvalidRateLimit,
+    #[error("Promo code needs redemptions and a future expiry")]
+    InvalidPromoConfig,
+    #[error("Fee peg needs a target fee and a positive maximum price age")]
+    InvalidFeePeg,
+    #[error("Webhook id cannot be empty")]
+    EmptyWebhookId,
+    #[error("Delivery status cannot predate its send")]
+    InvalidDeliverySlot,
+    #[error("Amount must be non-zero")]
+    ZeroAmount,
+    #[error("Wormhole bridge config account is malformed")]
+    InvalidBridgeConfig,
+    #[error("Invalid instructions sysvar")]
+    InvalidInstructionsSysvar,
+    #[error("View instruction set no return data")]
+    MissingReturnData,
+    #[error("Idempotent send requires the sender's SendNonce account")]
+    MissingSendNonceAccount,
+    #[error("Discount tiers must ascend in volume and discount, up to 100%")]
+    InvalidDiscountSchedule,
+    #[error("Sends to a wallet require the recipient's InboxPolicy account")]
+    MissingInboxPolicyAccount,
+    #[error("Sends require the sender's MessageNonce account")]
+    MissingMessageNonceAccount,
+    #[error("Instructions the owner can disable require the mailer state account")]
+    MissingMailerStateAccount,
+    #[error("Token-2022 transfers require the mint account")]
+    MissingMintAccount,
+}
+
+impl MailerError {
+    /// Every error, in declaration and code order
+    pub const ALL: &'static [MailerError] = &[
+        MailerError::OnlyOwner,
+        MailerError::NoClaimableAmount,
+        MailerError::ClaimPeriodExpired,
+        MailerError::ClaimPeriodNotExpired,
+        MailerError::InvalidRecipient,
+        MailerError::NoDelegationToReject,
+        MailerError::InvalidDelegator,
+        MailerError::AlreadyInitialized,
+        MailerError::NotInitialized,
+        MailerError::InvalidPDA,
+        MailerError::InvalidAccountOwner,
+        MailerError::InvalidMint,
+        MailerError::InvalidTokenProgram,
+        MailerError::ContractPaused,
+        MailerError::ContractNotPaused,
+        MailerError::InvalidPercentage,
+        MailerError::MathOverflow,
+        MailerError::ReadReceiptsDisabled,
+        MailerError::ProtocolSendsDisabled,
+        MailerError::NotPendingOwner,
+        MailerError::InvalidAdminConfig,
+        MailerError::InvalidSweepOrder,
+        MailerError::FeeTimelockActive,
+        MailerError::InvalidFeeTimelock,
+        MailerError::SenderNotAllowed,
+        MailerError::InvalidInboxPolicy,
+        MailerError::ReceiptAlreadyExists,
+        MailerError::MemoTooLong,
+        MailerError::InvalidAssociatedTokenAccount,
+        MailerError::InvalidClaimAmount,
+        MailerError::InvalidSessionKey,
+        MailerError::SessionKeyExpired,
+        MailerError::SessionScopeDenied,
+        MailerError::InvalidPayoutAccount,
+        MailerError::InvalidSystemProgram,
+        MailerError::InvalidPreauthorization,
+        MailerError::PreauthorizationExhausted,
+        MailerError::PreauthorizedFeeExceeded,
+        MailerError::InstructionDisabled,
+        MailerError::UnknownDiscriminator,
+        MailerError::AccountNotEmpty,
+        MailerError::FeePaymentFailed,
+        MailerError::InvalidSpamOracle,
+        MailerError::SpamScoreTooHigh,
+        MailerError::RateLimitExceeded,
+        MailerError::InvalidPromoCode,
+        MailerError::PromoExhausted,
+        MailerError::DiscountAlreadyApplied,
+        MailerError::LamportFeesDisabled,
+        MailerError::LamportFeeUnsupported,
+        MailerError::InvalidFeeOracle,
+        MailerError::StaleOraclePrice,
+        MailerError::NotClaimRentPayer,
+        MailerError::InvalidEnvelope,
+        MailerError::StaleState,
+        MailerError::InvalidWebhook,
+        MailerError::NotDelegate,
+        MailerError::AddressSanctioned,
+        MailerError::BackupVaultUnavailable,
+        MailerError::NoExpirySurchargeUnpaid,
+        MailerError::BatchTooLarge,
+        MailerError::VerificationRequired,
+        MailerError::InvalidClaimSplit,
+        MailerError::InvalidDelegationRefundWindow,
+        MailerError::NoDelegationToAccept,
+        MailerError::DelegationFeeNotRefundable,
+        MailerError::DelegationFeeRefundPending,
+        MailerError::NoPendingDelivery,
+        MailerError::ClaimCapExceeded,
+        MailerError::InvalidPlan,
+        MailerError::PlanInactive,
+        MailerError::SubscriptionActive,
+        MailerError::PayloadTooLarge,
+        MailerError::InvalidEmailHash,
+        MailerError::TemporarilyInsolvent,
+        MailerError::InvalidEmailAddress,
+        MailerError::ClaimExtensionLimitReached,
+        MailerError::ClaimExtensionFeeUnpaid,
+        MailerError::NotClaimBeneficiary,
+        MailerError::BeneficiaryGracePeriodActive,
+        MailerError::BeneficiaryGracePeriodEnded,
+        MailerError::StateMigrationRequired,
+        MailerError::StateAlreadyCurrent,
+        MailerError::InvalidTreasury,
+        MailerError::UnsortedClaimBatch,
+        MailerError::InvalidDistribution,
+        MailerError::InvalidMerkleProof,
+        MailerError::DistributionLeafClaimed,
+        MailerError::TipRequiresPriority,
+        MailerError::RecipientFeeTooLow,
+        MailerError::EscrowExpired,
+        MailerError::EscrowNotExpired,
+        MailerError::InvalidListChunk,
+        MailerError::ListMemberExists,
+        MailerError::NotListMember,
+        MailerError::EmptyList,
+        MailerError::InvalidDomain,
+        MailerError::InvalidEvmSignature,
+        MailerError::EvmAddressNotBound,
+        MailerError::WormholeNotConfigured,
+        MailerError::WormholeDisabled,
+        MailerError::UnsupportedInstructionFormat,
+        MailerError::InsufficientFee,
+        MailerError::RecipientBlocked,
+        MailerError::AccountNotRentExempt,
+        MailerError::InvalidPauseFlags,
+        MailerError::InvalidInstructionTag,
+        MailerError::InvalidSpamOracleConfig,
+        MailerError::InvalidRateLimit,
+        MailerError::InvalidPromoConfig,
+        MailerError::InvalidFeePeg,
+        MailerError::EmptyWebhookId,
+        MailerError::InvalidDeliverySlot,
+        MailerError::ZeroAmount,
+        MailerError::InvalidBridgeConfig,
+        MailerError::InvalidInstructionsSysvar,
+        MailerError::MissingReturnData,
+        MailerError::MissingSendNonceAccount,
+        MailerError::InvalidDiscountSchedule,
+        MailerError::MissingInboxPolicyAccount,
+        MailerError::MissingMessageNonceAccount,
+        MailerError::MissingMailerStateAccount,
+        MailerError::MissingMintAccount,
+    ];
+
+    /// Code carried by `ProgramError::Custom`
+    pub fn code(self) -> u32 {
+        self as u32
+    }
+
+    /// Error with the custom code `code`, `None` for codes of other programs
+    pub fn from_code(code: u32) -> Option<Self> {
+        num_traits::FromPrimitive::from_u32(code)
+    }
+}
+
+impl From<MailerError> for ProgramError {
+    fn from(e: MailerError) -> Self {
+        ProgramError::Custom(e.code())
+    }
+}
+
+impl<T> DecodeError<T> for MailerError {
+    fn type_of() -> &'static str {
+        "MailerError"
+    }
+}
+
+impl PrintProgramError for MailerError {
+    fn print<E>(&self)
+    where
+        E: 'static
+            + std::error::Error
+            + DecodeError<E>
+            + PrintProgramError
+            + num_traits::FromPrimitive,
+    {
+        msg!("Error: {:?} ({}): {}", self, self.code(), self);
+    }
+}
@@ -0,0 +1,71 @@
+//! # EVM Address Recipients
+//!
+//! Users known only by an Ethereum address can receive mail once they bind the address to a
+//! Solana wallet with `BindEvmAddress`. The binding transaction carries a secp256k1 program
+//! instruction right before `BindEvmAddress`, checking a signature by the EVM key over
+//! [`binding_message`] for the wallet. The runtime fails the whole transaction on a bad
+//! signature; `BindEvmAddress` reads the checked address and message back through the
+//! instructions sysvar with [`verified_signer`]. `SendToEvmAddress` then routes a send to the
+//! bound wallet, and a later binding by the same EVM key moves it to another wallet.
+//!
+//! The message is an EIP-191 personal message, so `personal_sign` in EVM wallets produces
+//! the signature; its recovery byte (27 or 28) is passed to the secp256k1 program minus 27.
+//!
+//! ```rust
+//! use mailer::evm;
+//! use solana_program::pubkey::Pubkey;
+//!
+//! let wallet = Pubkey::new_unique();
+//! let message = evm::binding_message(&mailer::id(), &wallet);
+//! assert!(message.starts_with(b"\x19Ethereum Signed Message:\n"));
+//! assert_eq!(evm::to_hex(&[0xab; 20]), format!("0x{}", "ab".repeat(20)));
+//! ```
+
+use solana_program::pubkey::Pubkey;
+
+/// Size of an EVM address
+pub const EVM_ADDRESS_LEN: usize = 20;
+
+/// Size of one signature's offsets in secp256k1 program instruction data
+const SIGNATURE_OFFSETS_LEN: usize = 11;
+
+/// EIP-191 personal message an EVM key signs to bind its address to `wallet`
+pub fn binding_message(program_id: &Pubkey, wallet: &Pubkey) -> Vec<u8> {
+    let text = format!(
+        "Link this address to Solana wallet {} on Mailer {}",
+        wallet, program_id
+    );
+    let mut message = format!("\x19Ethereum Signed Message:\n{}", text.len()).into_bytes();
+    message.extend_from_slice(text.as_bytes());
+    message
+}
+
+/// EVM address and message checked by the secp256k1 program instruction at `index`, whose
+/// data is `data`
+/// `None` unless the instruction checks exactly one signature and holds its address,
+/// signature and message itself.
+pub fn verified_signer(data: &[u8], index: u8) -> Option<([u8; EVM_ADDRESS_LEN], &[u8])> {
+    if *data.first()? != 1 {
+        return None;
+    }
+    let offsets = data.get(1..1 + SIGNATURE_OFFSETS_LEN)?;
+    let read_u16 = |at: usize| u16::from_le_bytes([offsets[at], offsets[at + 1]]) as usize;
+    if offsets[2] != index || offsets[5] != index || offsets[10] != index {
+        return None;
+    }
+
+    let address_offset = read_u16(3);
+    let address = data
+        .get(address_offset..address_offset + EVM_ADDRESS_LEN)?
+        .try_into()
+        .ok()?;
+    let message_offset = read_u16(6);
+    let message = data.get(message_offset..message_offset + read_u16(8))?;
+    Some((address, message))
+}
+
+/// `0x`-prefixed lowercase hex of an EVM address
+pub fn to_hex(address: &[u8; EVM_ADDRESS_LEN]) -> String {
+    let digits: String = address.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("0x{}", digits)
+}
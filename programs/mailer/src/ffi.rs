@@ -0,0 +1,2128 @@
+//! # Mailer C ABI
+//!
+//! `extern "C"` functions serializing every [`MailerInstruction`] variant into a
+//! caller-provided buffer, for signers that build transactions without a Rust toolchain.
+//! Enabling the `ffi` feature also enables `no-entrypoint`, so the library exports these
+//! functions instead of the program entrypoint. The C header is checked in at
+//! `include/mailer_ffi.h`; regenerate it with `UPDATE_FFI_HEADER=1` after changing this module.
+//!
+//! Each `mailer_ix_*` function takes the variant's fields in declaration order, followed by
+//! `out`, `out_capacity` and `out_len`, and returns a [`MailerFfiStatus`]. On success the
+//! instruction data is written to `out` and its length to `out_len`; when the buffer is too
+//! small nothing is written to `out` and `out_len` receives the required length.
+//!
+//! Field types map as follows:
+//! - `Pubkey` and `[u8; N]`: pointer to 32 or N bytes; `Option` of either is a null pointer for `None`
+//! - `String` and `Vec<u8>`: [`MailerBytes`]; `Option<String>` is `None` when `ptr` is null
+//! - `Option<u64>`: [`MailerOptionU64`]
+//! - `Vec<Pubkey>`: pointer to `count` consecutive 32-byte keys, followed by `count`
+//! - `Option<Vec<ClaimSplit>>`: pointer to `count` [`MailerClaimSplit`] entries, followed by
+//!   `count`; a null pointer for `None`
+//! - `Vec<VolumeTier>`: pointer to `count` [`MailerVolumeTier`] entries, followed by `count`
+//! - `InboxMode`, `ClaimCreationPolicy` and `MigrationKind`: `u8` variant index
+//! - `PayloadLimits`: [`MailerPayloadLimits`], passed by value
+//!
+//! # Safety
+//!
+//! Every pointer argument must be null (where `None` is allowed) or valid for reads of the
+//! documented length, and `out` must be valid for writes of `out_capacity` bytes. Required
+//! pointers that are null are rejected with [`MailerFfiStatus::NullPointer`].
+
+#![allow(clippy::missing_safety_doc, clippy::too_many_arguments)]
+
+use std::slice;
+
+use solana_program::pubkey::Pubkey;
+
+use crate::instruction_versions::Reserved;
+use crate::{
+    ClaimCreationPolicy, ClaimSplit, InboxMode, MailerInstruction, MigrationKind, PayloadLimits,
+    VolumeTier,
+};
+
+/// Result of a C ABI call
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MailerFfiStatus {
+    Ok = 0,
+    /// A required pointer argument was null
+    NullPointer = 1,
+    /// A string argument was not valid UTF-8
+    InvalidUtf8 = 2,
+    /// An enum argument was out of range
+    InvalidEnum = 3,
+    /// `out_capacity` is smaller than the instruction data; `out_len` holds the required length
+    BufferTooSmall = 4,
+    /// The instruction could not be serialized
+    SerializationFailed = 5,
+}
+
+/// Borrowed byte string: `len` bytes at `ptr`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct MailerBytes {
+    pub ptr: *const u8,
+    pub len: usize,
+}
+
+/// Optional u64, `None` unless `present`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct MailerOptionU64 {
+    pub present: bool,
+    pub value: u64,
+}
+
+impl MailerOptionU64 {
+    fn into_option(self) -> Option<u64> {
+        self.present.then_some(self.value)
+    }
+}
+
+/// Claim split entry: `bps` basis points of the claim paid to the token account `destination`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct MailerClaimSplit {
+    pub destination: [u8; 32],
+    pub bps: u16,
+}
+
+/// Volume discount tier: `discount` percent off once `min_fees_paid` USDC fees are paid
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct MailerVolumeTier {
+    pub min_fees_paid: u64,
+    pub discount: u8,
+}
+
+/// Maximum payload field lengths in bytes; 0 leaves a field unlimited
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct MailerPayloadLimits {
+    pub max_subject_len: u16,
+    pub max_body_len: u16,
+    pub max_mail_id_len: u16,
+    pub max_email_len: u16,
+    pub max_webhook_id_len: u16,
+}
+
+impl MailerPayloadLimits {
+    fn into_limits(self) -> PayloadLimits {
+        PayloadLimits {
+            max_subject_len: self.max_subject_len,
+            max_body_len: self.max_body_len,
+            max_mail_id_len: self.max_mail_id_len,
+            max_email_len: self.max_email_len,
+            max_webhook_id_len: self.max_webhook_id_len,
+        }
+    }
+}
+
+unsafe fn bytes<'a>(value: MailerBytes) -> Result<&'a [u8], MailerFfiStatus> {
+    if value.len == 0 {
+        return Ok(&[]);
+    }
+    if value.ptr.is_null() {
+        return Err(MailerFfiStatus::NullPointer);
+    }
+    Ok(slice::from_raw_parts(value.ptr, value.len))
+}
+
+unsafe fn string(value: MailerBytes) -> Result<String, MailerFfiStatus> {
+    let bytes = bytes(value)?;
+    String::from_utf8(bytes.to_vec()).map_err(|_| MailerFfiStatus::InvalidUtf8)
+}
+
+unsafe fn opt_string(value: MailerBytes) -> Result<Option<String>, MailerFfiStatus> {
+    if value.ptr.is_null() {
+        return Ok(None);
+    }
+    string(value).map(Some)
+}
+
+unsafe fn opt_array<const N: usize>(ptr: *const u8) -> Option<[u8; N]> {
+    if ptr.is_null() {
+        return None;
+    }
+    let mut array = [0u8; N];
+    array.copy_from_slice(slice::from_raw_parts(ptr, N));
+    Some(array)
+}
+
+unsafe fn array<const N: usize>(ptr: *const u8) -> Result<[u8; N], MailerFfiStatus> {
+    opt_array(ptr).ok_or(MailerFfiStatus::NullPointer)
+}
+
+unsafe fn opt_pubkey(ptr: *const u8) -> Option<Pubkey> {
+    opt_array(ptr).map(Pubkey::new_from_array)
+}
+
+unsafe fn pubkey(ptr: *const u8) -> Result<Pubkey, MailerFfiStatus> {
+    array(ptr).map(Pubkey::new_from_array)
+}
+
+unsafe fn pubkeys(ptr: *const u8, count: usize) -> Result<Vec<Pubkey>, MailerFfiStatus> {
+    let bytes = bytes(MailerBytes {
+        ptr,
+        len: count * 32,
+    })?;
+    Ok(bytes
+        .chunks_exact(32)
+        .map(|key| Pubkey::new_from_array(key.try_into().unwrap()))
+        .collect())
+}
+
+unsafe fn opt_claim_split(ptr: *const MailerClaimSplit, count: usize) -> Option<Vec<ClaimSplit>> {
+    if ptr.is_null() {
+        return None;
+    }
+    let entries = if count == 0 {
+        &[][..]
+    } else {
+        slice::from_raw_parts(ptr, count)
+    };
+    Some(
+        entries
+            .iter()
+            .map(|entry| ClaimSplit {
+                destination: Pubkey::new_from_array(entry.destination),
+                bps: entry.bps,
+            })
+            .collect(),
+    )
+}
+
+unsafe fn volume_tiers(
+    ptr: *const MailerVolumeTier,
+    count: usize,
+) -> Result<Vec<VolumeTier>, MailerFfiStatus> {
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+    if ptr.is_null() {
+        return Err(MailerFfiStatus::NullPointer);
+    }
+    Ok(slice::from_raw_parts(ptr, count)
+        .iter()
+        .map(|entry| VolumeTier {
+            min_fees_paid: entry.min_fees_paid,
+            discount: entry.discount,
+        })
+        .collect())
+}
+
+fn inbox_mode(index: u8) -> Result<InboxMode, MailerFfiStatus> {
+    match index {
+        0 => Ok(InboxMode::Open),
+        1 => Ok(InboxMode::Allowlist),
+        2 => Ok(InboxMode::Blocklist),
+        _ => Err(MailerFfiStatus::InvalidEnum),
+    }
+}
+
+fn claim_creation_policy(index: u8) -> Result<ClaimCreationPolicy, MailerFfiStatus> {
+    match index {
+        0 => Ok(ClaimCreationPolicy::Eager),
+        1 => Ok(ClaimCreationPolicy::AfterTransfer),
+        2 => Ok(ClaimCreationPolicy::RefundPayer),
+        _ => Err(MailerFfiStatus::InvalidEnum),
+    }
+}
+
+fn migration_kind(index: u8) -> Result<MigrationKind, MailerFfiStatus> {
+    match index {
+        0 => Ok(MigrationKind::RecipientClaim),
+        1 => Ok(MigrationKind::Delegation),
+        2 => Ok(MigrationKind::FeeDiscount),
+        _ => Err(MailerFfiStatus::InvalidEnum),
+    }
+}
+
+/// Build an instruction and serialize it into `out`
+unsafe fn encode(
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+    build: impl FnOnce() -> Result<MailerInstruction, MailerFfiStatus>,
+) -> MailerFfiStatus {
+    if out_len.is_null() {
+        return MailerFfiStatus::NullPointer;
+    }
+    let instruction = match build() {
+        Ok(instruction) => instruction,
+        Err(status) => return status,
+    };
+    let data = match borsh::to_vec(&instruction) {
+        Ok(data) => data,
+        Err(_) => return MailerFfiStatus::SerializationFailed,
+    };
+
+    *out_len = data.len();
+    if data.len() > out_capacity {
+        return MailerFfiStatus::BufferTooSmall;
+    }
+    if out.is_null() {
+        return MailerFfiStatus::NullPointer;
+    }
+    slice::from_raw_parts_mut(out, data.len()).copy_from_slice(&data);
+    MailerFfiStatus::Ok
+}
+
+/// Write the Mailer program ID (32 bytes) to `out`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_program_id(out: *mut u8) -> MailerFfiStatus {
+    if out.is_null() {
+        return MailerFfiStatus::NullPointer;
+    }
+    slice::from_raw_parts_mut(out, 32).copy_from_slice(crate::id().as_ref());
+    MailerFfiStatus::Ok
+}
+
+/// Serialize `Initialize`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_initialize(
+    usdc_mint: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::Initialize {
+            usdc_mint: pubkey(usdc_mint)?,
+        })
+    })
+}
+
+/// Serialize `Send`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_send(
+    to: *const u8,
+    subject: MailerBytes,
+    body: MailerBytes,
+    revenue_share_to_receiver: bool,
+    resolve_sender_to_name: bool,
+    request_read_receipt: bool,
+    receipt_nonce: MailerOptionU64,
+    memo: MailerBytes,
+    strict: bool,
+    thread_id: *const u8,
+    in_reply_to: *const u8,
+    allow_downgrade: bool,
+    tip: u64,
+    bridge: bool,
+    idempotency_nonce: MailerOptionU64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::Send {
+            to: pubkey(to)?,
+            subject: string(subject)?,
+            _body: string(body)?,
+            revenue_share_to_receiver,
+            resolve_sender_to_name,
+            request_read_receipt,
+            receipt_nonce: receipt_nonce.into_option(),
+            memo: opt_string(memo)?,
+            strict,
+            thread_id: opt_array(thread_id),
+            in_reply_to: opt_array(in_reply_to),
+            allow_downgrade,
+            tip,
+            bridge,
+            idempotency_nonce: idempotency_nonce.into_option(),
+        })
+    })
+}
+
+/// Serialize `SendPrepared`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_send_prepared(
+    to: *const u8,
+    mail_id: MailerBytes,
+    revenue_share_to_receiver: bool,
+    resolve_sender_to_name: bool,
+    strict: bool,
+    thread_id: *const u8,
+    in_reply_to: *const u8,
+    allow_downgrade: bool,
+    tip: u64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SendPrepared {
+            to: pubkey(to)?,
+            mail_id: string(mail_id)?,
+            revenue_share_to_receiver,
+            resolve_sender_to_name,
+            strict,
+            thread_id: opt_array(thread_id),
+            in_reply_to: opt_array(in_reply_to),
+            allow_downgrade,
+            tip,
+        })
+    })
+}
+
+/// Serialize `SendToEmail`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_send_to_email(
+    to_email: MailerBytes,
+    subject: MailerBytes,
+    body: MailerBytes,
+    strict: bool,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SendToEmail {
+            to_email: string(to_email)?,
+            subject: string(subject)?,
+            _body: string(body)?,
+            strict,
+        })
+    })
+}
+
+/// Serialize `SendPreparedToEmail`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_send_prepared_to_email(
+    to_email: MailerBytes,
+    mail_id: MailerBytes,
+    strict: bool,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SendPreparedToEmail {
+            to_email: string(to_email)?,
+            mail_id: string(mail_id)?,
+            strict,
+        })
+    })
+}
+
+/// Serialize `SendThroughWebhook`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_send_through_webhook(
+    to: *const u8,
+    webhook_id: MailerBytes,
+    revenue_share_to_receiver: bool,
+    resolve_sender_to_name: bool,
+    strict: bool,
+    thread_id: *const u8,
+    in_reply_to: *const u8,
+    allow_downgrade: bool,
+    tip: u64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SendThroughWebhook {
+            to: pubkey(to)?,
+            webhook_id: string(webhook_id)?,
+            revenue_share_to_receiver,
+            resolve_sender_to_name,
+            strict,
+            thread_id: opt_array(thread_id),
+            in_reply_to: opt_array(in_reply_to),
+            allow_downgrade,
+            tip,
+        })
+    })
+}
+
+/// Serialize `ClaimRecipientShare`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_claim_recipient_share(
+    amount: MailerOptionU64,
+    split: *const MailerClaimSplit,
+    split_count: usize,
+    close_to: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::ClaimRecipientShare {
+            amount: amount.into_option(),
+            split: opt_claim_split(split, split_count),
+            close_to: opt_pubkey(close_to),
+        })
+    })
+}
+
+/// Serialize `ClaimOwnerShare`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_claim_owner_share(
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::ClaimOwnerShare)
+    })
+}
+
+/// Serialize `SetFee`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_fee(
+    new_fee: u64,
+    expected_current_value: MailerOptionU64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetFee {
+            new_fee,
+            expected_current_value: expected_current_value.into_option(),
+        })
+    })
+}
+
+/// Serialize `DelegateTo`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_delegate_to(
+    delegate: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::DelegateTo {
+            delegate: opt_pubkey(delegate),
+        })
+    })
+}
+
+/// Serialize `RejectDelegation`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_reject_delegation(
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::RejectDelegation)
+    })
+}
+
+/// Serialize `SetDelegationFee`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_delegation_fee(
+    new_fee: u64,
+    expected_current_value: MailerOptionU64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetDelegationFee {
+            new_fee,
+            expected_current_value: expected_current_value.into_option(),
+        })
+    })
+}
+
+/// Serialize `SetCustomFeePercentage`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_custom_fee_percentage(
+    account: *const u8,
+    percentage: u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetCustomFeePercentage {
+            account: pubkey(account)?,
+            percentage,
+        })
+    })
+}
+
+/// Serialize `ClearCustomFeePercentage`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_clear_custom_fee_percentage(
+    account: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::ClearCustomFeePercentage {
+            account: pubkey(account)?,
+        })
+    })
+}
+
+/// Serialize `Pause`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_pause(
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || Ok(MailerInstruction::Pause))
+}
+
+/// Serialize `Unpause`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_unpause(
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::Unpause)
+    })
+}
+
+/// Serialize `DistributeClaimableFunds`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_distribute_claimable_funds(
+    recipient: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::DistributeClaimableFunds {
+            recipient: pubkey(recipient)?,
+        })
+    })
+}
+
+/// Serialize `ClaimExpiredShares`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_claim_expired_shares(
+    recipient: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::ClaimExpiredShares {
+            recipient: pubkey(recipient)?,
+        })
+    })
+}
+
+/// Serialize `EmergencyUnpause`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_emergency_unpause(
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::EmergencyUnpause)
+    })
+}
+
+/// Serialize `SetFeePaused`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_fee_paused(
+    fee_paused: bool,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetFeePaused { fee_paused })
+    })
+}
+
+/// Serialize `SetRecipientPrefs`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_recipient_prefs(
+    read_receipts_opt_out: bool,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetRecipientPrefs {
+            read_receipts_opt_out,
+        })
+    })
+}
+
+/// Serialize `AcknowledgeMessage`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_acknowledge_message(
+    sender: *const u8,
+    receipt_nonce: MailerOptionU64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::AcknowledgeMessage {
+            sender: pubkey(sender)?,
+            receipt_nonce: receipt_nonce.into_option(),
+        })
+    })
+}
+
+/// Serialize `SetProtocolSendsEnabled`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_protocol_sends_enabled(
+    enabled: bool,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetProtocolSendsEnabled { enabled })
+    })
+}
+
+/// Serialize `SendProtocolMessage`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_send_protocol_message(
+    to: *const u8,
+    subject: MailerBytes,
+    body: MailerBytes,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SendProtocolMessage {
+            to: pubkey(to)?,
+            subject: string(subject)?,
+            _body: string(body)?,
+        })
+    })
+}
+
+/// Serialize `TransferOwnership`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_transfer_ownership(
+    new_owner: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::TransferOwnership {
+            new_owner: pubkey(new_owner)?,
+        })
+    })
+}
+
+/// Serialize `AcceptOwnership`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_accept_ownership(
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::AcceptOwnership)
+    })
+}
+
+/// Serialize `SetAdmins`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_admins(
+    admins: *const u8,
+    admins_count: usize,
+    threshold: u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetAdmins {
+            admins: pubkeys(admins, admins_count)?,
+            threshold,
+        })
+    })
+}
+
+/// Serialize `SweepExpiredPage`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_sweep_expired_page(
+    max_items: u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SweepExpiredPage { max_items })
+    })
+}
+
+/// Serialize `ResetSweepCursor`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_reset_sweep_cursor(
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::ResetSweepCursor)
+    })
+}
+
+/// Serialize `ProposeFee`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_propose_fee(
+    new_fee: u64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::ProposeFee { new_fee })
+    })
+}
+
+/// Serialize `SetFeeTimelock`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_fee_timelock(
+    delay: i64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetFeeTimelock { delay })
+    })
+}
+
+/// Serialize `SetInboxPolicy`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_inbox_policy(
+    mode: u8,
+    senders: *const u8,
+    senders_count: usize,
+    penalty_multiplier: u8,
+    min_fee: u64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetInboxPolicy {
+            mode: inbox_mode(mode)?,
+            senders: pubkeys(senders, senders_count)?,
+            penalty_multiplier,
+            min_fee,
+        })
+    })
+}
+
+/// Serialize `ClaimRecipientShareToAta`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_claim_recipient_share_to_ata(
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::ClaimRecipientShareToAta)
+    })
+}
+
+/// Serialize `RegisterSessionKey`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_register_session_key(
+    session_key: *const u8,
+    scope: u8,
+    expires_at: i64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::RegisterSessionKey {
+            session_key: pubkey(session_key)?,
+            scope,
+            expires_at,
+        })
+    })
+}
+
+/// Serialize `RevokeSessionKey`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_revoke_session_key(
+    session_key: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::RevokeSessionKey {
+            session_key: pubkey(session_key)?,
+        })
+    })
+}
+
+/// Serialize `SetPayoutAddress`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_payout_address(
+    payout_token_account: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetPayoutAddress {
+            payout_token_account: opt_pubkey(payout_token_account),
+        })
+    })
+}
+
+/// Serialize `SetConversionVault`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_conversion_vault(
+    mint: *const u8,
+    enabled: bool,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetConversionVault {
+            mint: pubkey(mint)?,
+            enabled,
+        })
+    })
+}
+
+/// Serialize `ClaimRecipientShareAs`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_claim_recipient_share_as(
+    mint: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::ClaimRecipientShareAs {
+            mint: pubkey(mint)?,
+        })
+    })
+}
+
+/// Serialize `GetConfig`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_get_config(
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::GetConfig)
+    })
+}
+
+/// Serialize `CreatePreauthorization`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_create_preauthorization(
+    relayer: *const u8,
+    webhook_id: MailerBytes,
+    max_sends: u64,
+    max_fee_per_send: u64,
+    expires_at: i64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::CreatePreauthorization {
+            relayer: pubkey(relayer)?,
+            webhook_id: string(webhook_id)?,
+            max_sends,
+            max_fee_per_send,
+            expires_at,
+        })
+    })
+}
+
+/// Serialize `RevokePreauthorization`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_revoke_preauthorization(
+    relayer: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::RevokePreauthorization {
+            relayer: pubkey(relayer)?,
+        })
+    })
+}
+
+/// Serialize `SendPreauthorized`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_send_preauthorized(
+    to: *const u8,
+    webhook_id: MailerBytes,
+    revenue_share_to_receiver: bool,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SendPreauthorized {
+            to: pubkey(to)?,
+            webhook_id: string(webhook_id)?,
+            revenue_share_to_receiver,
+        })
+    })
+}
+
+/// Serialize `SetInstructionDisabled`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_instruction_disabled(
+    tag: u8,
+    disabled: bool,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetInstructionDisabled { tag, disabled })
+    })
+}
+
+/// Serialize `MigrateDiscriminator`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_migrate_discriminator(
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::MigrateDiscriminator)
+    })
+}
+
+/// Serialize `CloseRecipientClaim`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_close_recipient_claim(
+    recipient: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::CloseRecipientClaim {
+            recipient: pubkey(recipient)?,
+        })
+    })
+}
+
+/// Serialize `CloseDelegation`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_close_delegation(
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::CloseDelegation)
+    })
+}
+
+/// Serialize `CloseFeeDiscount`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_close_fee_discount(
+    account: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::CloseFeeDiscount {
+            account: pubkey(account)?,
+        })
+    })
+}
+
+/// Serialize `MigrateAccountVersion`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_migrate_account_version(
+    kind: u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::MigrateAccountVersion {
+            kind: migration_kind(kind)?,
+        })
+    })
+}
+
+/// Serialize `SetSpamOracle`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_spam_oracle(
+    oracle: *const u8,
+    threshold: MailerOptionU64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetSpamOracle {
+            oracle: opt_pubkey(oracle),
+            threshold: threshold.into_option(),
+        })
+    })
+}
+
+/// Serialize `SetRateLimit`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_rate_limit(
+    max_sends: u64,
+    window: i64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetRateLimit { max_sends, window })
+    })
+}
+
+/// Serialize `CreatePromo`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_create_promo(
+    code_hash: *const u8,
+    discount: u8,
+    max_redemptions: u64,
+    expiry: i64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::CreatePromo {
+            code_hash: array(code_hash)?,
+            discount,
+            max_redemptions,
+            expiry,
+        })
+    })
+}
+
+/// Serialize `RedeemPromo`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_redeem_promo(
+    code: MailerBytes,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::RedeemPromo {
+            code: string(code)?,
+        })
+    })
+}
+
+/// Serialize `ClaimAndSend`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_claim_and_send(
+    amount: MailerOptionU64,
+    to: *const u8,
+    subject: MailerBytes,
+    body: MailerBytes,
+    revenue_share_to_receiver: bool,
+    resolve_sender_to_name: bool,
+    request_read_receipt: bool,
+    receipt_nonce: MailerOptionU64,
+    memo: MailerBytes,
+    strict: bool,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::ClaimAndSend {
+            amount: amount.into_option(),
+            to: pubkey(to)?,
+            subject: string(subject)?,
+            _body: string(body)?,
+            revenue_share_to_receiver,
+            resolve_sender_to_name,
+            request_read_receipt,
+            receipt_nonce: receipt_nonce.into_option(),
+            memo: opt_string(memo)?,
+            strict,
+        })
+    })
+}
+
+/// Serialize `AddFeeMint`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_add_fee_mint(
+    mint: *const u8,
+    send_fee: u64,
+    delegation_fee: u64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::AddFeeMint {
+            mint: pubkey(mint)?,
+            send_fee,
+            delegation_fee,
+        })
+    })
+}
+
+/// Serialize `RemoveFeeMint`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_remove_fee_mint(
+    mint: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::RemoveFeeMint {
+            mint: pubkey(mint)?,
+        })
+    })
+}
+
+/// Serialize `SetLamportFee`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_lamport_fee(
+    lamport_fee: u64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetLamportFee { lamport_fee })
+    })
+}
+
+/// Serialize `ClaimOwnerLamports`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_claim_owner_lamports(
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::ClaimOwnerLamports)
+    })
+}
+
+/// Serialize `SetFeeOracle`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_fee_oracle(
+    oracle: *const u8,
+    target_fee: u64,
+    max_age: i64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetFeeOracle {
+            oracle: opt_pubkey(oracle),
+            target_fee,
+            max_age,
+        })
+    })
+}
+
+/// Serialize `SetClaimCreationPolicy`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_claim_creation_policy(
+    policy: u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetClaimCreationPolicy {
+            policy: claim_creation_policy(policy)?,
+        })
+    })
+}
+
+/// Serialize `CloseFundedClaim`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_close_funded_claim(
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::CloseFundedClaim)
+    })
+}
+
+/// Serialize `SendEncrypted`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_send_encrypted(
+    to: *const u8,
+    ephemeral_key: *const u8,
+    nonce: *const u8,
+    ciphertext: MailerBytes,
+    revenue_share_to_receiver: bool,
+    strict: bool,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SendEncrypted {
+            to: pubkey(to)?,
+            ephemeral_key: array(ephemeral_key)?,
+            nonce: array(nonce)?,
+            ciphertext: bytes(ciphertext)?.to_vec(),
+            revenue_share_to_receiver,
+            strict,
+        })
+    })
+}
+
+/// Serialize `RegisterWebhook`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_register_webhook(
+    webhook_id: MailerBytes,
+    endpoint_hash: *const u8,
+    surcharge: u64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::RegisterWebhook {
+            webhook_id: string(webhook_id)?,
+            endpoint_hash: array(endpoint_hash)?,
+            surcharge,
+        })
+    })
+}
+
+/// Serialize `UpdateWebhook`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_update_webhook(
+    endpoint_hash: *const u8,
+    surcharge: u64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::UpdateWebhook {
+            endpoint_hash: array(endpoint_hash)?,
+            surcharge,
+        })
+    })
+}
+
+/// Serialize `RevokeWebhook`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_revoke_webhook(
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::RevokeWebhook)
+    })
+}
+
+/// Serialize `ClaimWebhookRevenue`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_claim_webhook_revenue(
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::ClaimWebhookRevenue)
+    })
+}
+
+/// Serialize `InitializeClosureLog`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_initialize_closure_log(
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::InitializeClosureLog)
+    })
+}
+
+/// Serialize `SendAsDelegator`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_send_as_delegator(
+    to: *const u8,
+    subject: MailerBytes,
+    body: MailerBytes,
+    revenue_share_to_receiver: bool,
+    strict: bool,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SendAsDelegator {
+            to: pubkey(to)?,
+            subject: string(subject)?,
+            _body: string(body)?,
+            revenue_share_to_receiver,
+            strict,
+        })
+    })
+}
+
+/// Serialize `SanctionAddress`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_sanction_address(
+    address: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SanctionAddress {
+            address: pubkey(address)?,
+        })
+    })
+}
+
+/// Serialize `UnsanctionAddress`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_unsanction_address(
+    address: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::UnsanctionAddress {
+            address: pubkey(address)?,
+        })
+    })
+}
+
+/// Serialize `SetSanctionsEnabled`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_sanctions_enabled(
+    enabled: bool,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetSanctionsEnabled { enabled })
+    })
+}
+
+/// Serialize `SetBackupMint`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_backup_mint(
+    mint: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetBackupMint {
+            mint: opt_pubkey(mint),
+        })
+    })
+}
+
+/// Serialize `InitializeStats`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_initialize_stats(
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::InitializeStats)
+    })
+}
+
+/// Serialize `SetClaimNoExpiry`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_claim_no_expiry(
+    enabled: bool,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetClaimNoExpiry { enabled })
+    })
+}
+
+/// Serialize `ClaimExpiredSharesBatch`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_claim_expired_shares_batch(
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::ClaimExpiredSharesBatch)
+    })
+}
+
+/// Serialize `SetPauseFlags`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_pause_flags(
+    flags: u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetPauseFlags { flags })
+    })
+}
+
+/// Serialize `SetKycVerifier`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_kyc_verifier(
+    verifier: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetKycVerifier {
+            verifier: opt_pubkey(verifier),
+        })
+    })
+}
+
+/// Serialize `SetDelegationRefundWindow`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_delegation_refund_window(
+    window: i64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetDelegationRefundWindow { window })
+    })
+}
+
+/// Serialize `AcceptDelegation`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_accept_delegation(
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::AcceptDelegation)
+    })
+}
+
+/// Serialize `RefundDelegationFee`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_refund_delegation_fee(
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::RefundDelegationFee)
+    })
+}
+
+/// Serialize `RecordDeliveryStatus`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_record_delivery_status(
+    to: *const u8,
+    nonce: u64,
+    sent_slot: u64,
+    delivered: bool,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::RecordDeliveryStatus {
+            to: pubkey(to)?,
+            nonce,
+            sent_slot,
+            delivered,
+        })
+    })
+}
+
+/// Serialize `GetWebhookStats`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_get_webhook_stats(
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::GetWebhookStats)
+    })
+}
+
+/// Serialize `DepositSendCredits`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_deposit_send_credits(
+    amount: u64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::DepositSendCredits { amount })
+    })
+}
+
+/// Serialize `WithdrawSendCredits`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_withdraw_send_credits(
+    amount: MailerOptionU64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::WithdrawSendCredits {
+            amount: amount.into_option(),
+        })
+    })
+}
+
+/// Serialize `SetClaimCap`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_claim_cap(
+    cap: u64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetClaimCap { cap })
+    })
+}
+
+/// Serialize `SetPlan`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_plan(
+    plan_id: u64,
+    price: u64,
+    duration: i64,
+    max_messages: u64,
+    active: bool,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetPlan {
+            plan_id,
+            price,
+            duration,
+            max_messages,
+            active,
+        })
+    })
+}
+
+/// Serialize `Subscribe`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_subscribe(
+    plan_id: u64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::Subscribe { plan_id })
+    })
+}
+
+/// Serialize `RenewSubscription`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_renew_subscription(
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::RenewSubscription)
+    })
+}
+
+/// Serialize `SetPayloadLimits`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_payload_limits(
+    limits: MailerPayloadLimits,
+    fee_tier_len: u16,
+    fee_tier_percent: u16,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetPayloadLimits {
+            limits: limits.into_limits(),
+            fee_tier_len,
+            fee_tier_percent,
+        })
+    })
+}
+
+/// Serialize `SendToEmailHash`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_send_to_email_hash(
+    email_hash: MailerBytes,
+    hint_id: u64,
+    subject: MailerBytes,
+    body: MailerBytes,
+    strict: bool,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SendToEmailHash {
+            email_hash: bytes(email_hash)?.to_vec(),
+            hint_id,
+            subject: string(subject)?,
+            _body: string(body)?,
+            strict,
+        })
+    })
+}
+
+/// Serialize `SetSolvencyThreshold`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_solvency_threshold(
+    min_coverage_bps: u16,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetSolvencyThreshold { min_coverage_bps })
+    })
+}
+
+/// Serialize `ExtendClaimWindow`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_extend_claim_window(
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::ExtendClaimWindow)
+    })
+}
+
+/// Serialize `SetClaimBeneficiary`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_claim_beneficiary(
+    beneficiary: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetClaimBeneficiary {
+            beneficiary: opt_pubkey(beneficiary),
+        })
+    })
+}
+
+/// Serialize `ClaimAsBeneficiary`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_claim_as_beneficiary(
+    recipient: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::ClaimAsBeneficiary {
+            recipient: pubkey(recipient)?,
+        })
+    })
+}
+
+/// Serialize `MigrateState`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_migrate_state(
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::MigrateState)
+    })
+}
+
+/// Serialize `SetTreasury`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_treasury(
+    token_account: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetTreasury {
+            token_account: opt_pubkey(token_account),
+        })
+    })
+}
+
+/// Serialize `VerifySolvency`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_verify_solvency(
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::VerifySolvency)
+    })
+}
+
+/// Serialize `SetClaimMerkleRoot`
+/// `merkle_root` points to 32 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_claim_merkle_root(
+    distribution_id: u64,
+    merkle_root: *const u8,
+    leaf_count: u64,
+    total: u64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetClaimMerkleRoot {
+            distribution_id,
+            merkle_root: array(merkle_root)?,
+            leaf_count,
+            total,
+        })
+    })
+}
+
+/// Serialize `ClaimFromMerkle`
+/// `proof` points to `proof_count` consecutive 32-byte hashes.
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_claim_from_merkle(
+    distribution_id: u64,
+    index: u64,
+    amount: u64,
+    proof: *const u8,
+    proof_count: usize,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        let proof = bytes(MailerBytes {
+            ptr: proof,
+            len: proof_count * 32,
+        })?
+        .chunks_exact(32)
+        .map(|hash| hash.try_into().unwrap())
+        .collect();
+        Ok(MailerInstruction::ClaimFromMerkle {
+            distribution_id,
+            index,
+            amount,
+            proof,
+        })
+    })
+}
+
+/// Serialize `SendEscrowed`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_send_escrowed(
+    to: *const u8,
+    subject: MailerBytes,
+    body: MailerBytes,
+    nonce: u64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SendEscrowed {
+            to: pubkey(to)?,
+            subject: string(subject)?,
+            _body: string(body)?,
+            nonce,
+        })
+    })
+}
+
+/// Serialize `AcceptMessage`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_accept_message(
+    sender: *const u8,
+    nonce: u64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::AcceptMessage {
+            sender: pubkey(sender)?,
+            nonce,
+        })
+    })
+}
+
+/// Serialize `ReclaimEscrow`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_reclaim_escrow(
+    nonce: u64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::ReclaimEscrow { nonce })
+    })
+}
+
+/// Serialize `CreateList`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_create_list(
+    list_id: u64,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::CreateList { list_id })
+    })
+}
+
+/// Serialize `AddListMember`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_add_list_member(
+    list_id: u64,
+    chunk: u64,
+    member: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::AddListMember {
+            list_id,
+            chunk,
+            member: pubkey(member)?,
+        })
+    })
+}
+
+/// Serialize `RemoveListMember`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_remove_list_member(
+    list_id: u64,
+    chunk: u64,
+    member: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::RemoveListMember {
+            list_id,
+            chunk,
+            member: pubkey(member)?,
+        })
+    })
+}
+
+/// Serialize `SendToList`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_send_to_list(
+    list_id: u64,
+    subject: MailerBytes,
+    body: MailerBytes,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SendToList {
+            list_id,
+            subject: string(subject)?,
+            _body: string(body)?,
+        })
+    })
+}
+
+/// Serialize `SendToDomain`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_send_to_domain(
+    domain: MailerBytes,
+    subject: MailerBytes,
+    body: MailerBytes,
+    revenue_share_to_receiver: bool,
+    strict: bool,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SendToDomain {
+            domain: string(domain)?,
+            subject: string(subject)?,
+            _body: string(body)?,
+            revenue_share_to_receiver,
+            strict,
+        })
+    })
+}
+
+/// Serialize `BindEvmAddress`
+/// `evm_address` points to 20 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_bind_evm_address(
+    evm_address: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::BindEvmAddress {
+            evm_address: array(evm_address)?,
+        })
+    })
+}
+
+/// Serialize `SendToEvmAddress`
+/// `evm_address` points to 20 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_send_to_evm_address(
+    evm_address: *const u8,
+    subject: MailerBytes,
+    body: MailerBytes,
+    revenue_share_to_receiver: bool,
+    strict: bool,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SendToEvmAddress {
+            evm_address: array(evm_address)?,
+            subject: string(subject)?,
+            _body: string(body)?,
+            revenue_share_to_receiver,
+            strict,
+        })
+    })
+}
+
+/// Serialize `SetWormholeBridge`
+/// `core_bridge` points to 32 bytes, or is null to switch bridging off.
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_wormhole_bridge(
+    core_bridge: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetWormholeBridge {
+            core_bridge: opt_pubkey(core_bridge),
+        })
+    })
+}
+
+/// Serialize `GetFee`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_get_fee(
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::GetFee {
+            reserved: Reserved::default(),
+        })
+    })
+}
+
+/// Serialize `GetClaimable`
+/// `account` points to 32 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_get_claimable(
+    account: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::GetClaimable {
+            account: pubkey(account)?,
+            reserved: Reserved::default(),
+        })
+    })
+}
+
+/// Serialize `GetDelegation`
+/// `delegator` points to 32 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_get_delegation(
+    delegator: *const u8,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::GetDelegation {
+            delegator: pubkey(delegator)?,
+            reserved: Reserved::default(),
+        })
+    })
+}
+
+/// Serialize `SetDiscountSchedule`
+#[no_mangle]
+pub unsafe extern "C" fn mailer_ix_set_discount_schedule(
+    tiers: *const MailerVolumeTier,
+    tiers_count: usize,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MailerFfiStatus {
+    encode(out, out_capacity, out_len, || {
+        Ok(MailerInstruction::SetDiscountSchedule {
+            tiers: volume_tiers(tiers, tiers_count)?,
+            reserved: Reserved::default(),
+        })
+    })
+}
@@ -0,0 +1,85 @@
+//! # In-place Account Fields
+//!
+//! Borsh decodes and re-encodes a whole account to change a single field. Fields ahead of
+//! an account's first variable-size field sit at fixed offsets, published as `*_OFFSET`
+//! constants on the account types, so hot paths read and update them in place instead:
+//! crediting the owner on every send touches 8 bytes of the mailer state rather than all
+//! of it, and cannot write back stale copies of the other fields.
+//!
+//! Offsets include the 8-byte discriminator. The integration tests check them against
+//! [`layout`](crate::layout).
+//!
+//! ```rust
+//! use mailer::{fields, MailerState};
+//!
+//! let mut data = vec![0u8; 8 + MailerState::LEN];
+//! fields::add_u64(&mut data, MailerState::OWNER_CLAIMABLE_OFFSET, 5_000).unwrap();
+//! assert_eq!(fields::read_u64(&data, MailerState::OWNER_CLAIMABLE_OFFSET).unwrap(), 5_000);
+//! ```
+
+use solana_program::program_error::ProgramError;
+
+use crate::MailerError;
+
+/// Little-endian `u64` at `offset` of account data
+pub fn read_u64(data: &[u8], offset: usize) -> Result<u64, ProgramError> {
+    let bytes = data
+        .get(offset..offset + 8)
+        .ok_or(ProgramError::InvalidAccountData)?;
+    let mut value = [0u8; 8];
+    value.copy_from_slice(bytes);
+    Ok(u64::from_le_bytes(value))
+}
+
+/// Overwrite the little-endian `u64` at `offset` of account data
+pub fn write_u64(data: &mut [u8], offset: usize, value: u64) -> Result<(), ProgramError> {
+    data.get_mut(offset..offset + 8)
+        .ok_or(ProgramError::InvalidAccountData)?
+        .copy_from_slice(&value.to_le_bytes());
+    Ok(())
+}
+
+/// Overwrite the little-endian `i64` at `offset` of account data
+pub fn write_i64(data: &mut [u8], offset: usize, value: i64) -> Result<(), ProgramError> {
+    write_u64(data, offset, value as u64)
+}
+
+/// Add `amount` to the `u64` at `offset` of account data, returning the new value
+/// Fails with `MathOverflow`, leaving the data unchanged, if the sum overflows.
+pub fn add_u64(data: &mut [u8], offset: usize, amount: u64) -> Result<u64, ProgramError> {
+    let value = read_u64(data, offset)?
+        .checked_add(amount)
+        .ok_or(MailerError::MathOverflow)?;
+    write_u64(data, offset, value)?;
+    Ok(value)
+}
+
+/// Offset of `recipient_liabilities` within mailer state account data, discriminator
+/// included
+/// The field follows the mailer's optional fields, one byte each while `None`, so its
+/// offset is found by walking their tags.
+pub fn recipient_liabilities_offset(data: &[u8]) -> Result<usize, ProgramError> {
+    // Fixed-size bytes ahead of each optional field, and the size of its value
+    const OPTIONAL_FIELDS: [(usize, usize); 7] = [
+        (32 + 32 + 8 + 8 + 8 + 1 + 1 + 1 + 1, 32), // pending_owner
+        (8, 16),                                   // pending_fee
+        (8 + 8 + 8 + 8 + 8 + 16, 32),              // spam_oracle
+        (0, 8),                                    // spam_threshold
+        (8 + 8 + 8 + 8, 32),                       // fee_oracle
+        (8 + 8 + 1 + 1 + 1, 32),                   // backup_mint
+        (1 + 1, 32),                               // kyc_verifier
+    ];
+    // delegation_refund_window through payload_fee_tier_percent
+    const TRAILING_FIXED: usize = 8 + 8 + 10 + 2 + 2;
+
+    let mut offset = 8;
+    for (fixed, value) in OPTIONAL_FIELDS {
+        offset += fixed;
+        offset += match data.get(offset) {
+            Some(0) => 1,
+            Some(1) => 1 + value,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+    }
+    Ok(offset + TRAILING_FIXED)
+}
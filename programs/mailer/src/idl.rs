@@ -0,0 +1,399 @@
+//! # IDL Generation
+//!
+//! Emits an Anchor-compatible (spec 0.1.0) JSON IDL describing every instruction,
+//! account, PDA and error, so explorers, wallets and Anchor-based TypeScript tooling
+//! can decode Mailer accounts and instructions.
+//!
+//! The IDL is built from the same tables the program and clients use, rather than
+//! from doc comments:
+//! - instruction accounts and arguments from [`crate::cpi::INSTRUCTIONS`]
+//! - instruction discriminators from the Borsh variant index (1 byte, not Anchor's
+//!   8-byte sighash), taken from [`crate::test_vectors`]
+//! - account fields and discriminators (Anchor's `sha256("account:<Name>")[..8]`)
+//!   from [`crate::layout`]
+//! - PDA seeds from [`crate::pda::LAYOUTS`], in a non-standard `pdas` section
+//! - error codes from [`MailerError::ALL`]; codes are the raw `Custom(n)` values, from
+//!   [`crate::error::ERROR_CODE_OFFSET`] up
+//!
+//! Regenerate the checked-in `idl/mailer.json` with:
+//! ```text
+//! UPDATE_IDL=1 cargo test -p mailer --test integration_tests idl
+//! ```
+
+use crate::cpi::{InstructionSpec, INSTRUCTIONS};
+use crate::layout::{self, FieldType};
+use crate::pda::{self, SeedComponent};
+use crate::{test_vectors, MailerError};
+
+/// Every error variant, in code order
+pub const ERRORS: &[MailerError] = MailerError::ALL;
+
+/// Minimal JSON value with deterministic, pretty-printed output
+enum Json {
+    Str(String),
+    Num(u64),
+    Bool(bool),
+    Arr(Vec<Json>),
+    Obj(Vec<(&'static str, Json)>),
+}
+
+impl Json {
+    fn str(s: impl Into<String>) -> Json {
+        Json::Str(s.into())
+    }
+
+    fn bytes(bytes: &[u8]) -> Json {
+        Json::Arr(bytes.iter().map(|b| Json::Num(*b as u64)).collect())
+    }
+
+    fn render(&self, indent: usize, out: &mut String) {
+        let pad = "  ".repeat(indent + 1);
+        match self {
+            Json::Str(s) => out.push_str(&format!("\"{}\"", test_vectors::json_escape(s))),
+            Json::Num(n) => out.push_str(&n.to_string()),
+            Json::Bool(b) => out.push_str(&b.to_string()),
+            // Byte arrays and other scalar lists stay on one line
+            Json::Arr(items) if items.iter().all(|i| matches!(i, Json::Num(_))) => {
+                let items: Vec<String> = items
+                    .iter()
+                    .map(|i| {
+                        let mut s = String::new();
+                        i.render(0, &mut s);
+                        s
+                    })
+                    .collect();
+                out.push_str(&format!("[{}]", items.join(", ")));
+            }
+            Json::Arr(items) if items.is_empty() => out.push_str("[]"),
+            Json::Arr(items) => {
+                out.push_str("[\n");
+                for (i, item) in items.iter().enumerate() {
+                    out.push_str(&pad);
+                    item.render(indent + 1, out);
+                    out.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+                }
+                out.push_str(&"  ".repeat(indent));
+                out.push(']');
+            }
+            Json::Obj(fields) => {
+                out.push_str("{\n");
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    out.push_str(&format!("{}\"{}\": ", pad, key));
+                    value.render(indent + 1, out);
+                    out.push_str(if i + 1 < fields.len() { ",\n" } else { "\n" });
+                }
+                out.push_str(&"  ".repeat(indent));
+                out.push('}');
+            }
+        }
+    }
+}
+
+/// `SendPrepared` -> `send_prepared`
+fn snake_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// IDL type for a Rust type as written in an instruction argument
+fn arg_type(ty: &str) -> Json {
+    let ty: String = ty.chars().filter(|c| !c.is_whitespace()).collect();
+    let generic = |prefix: &str| {
+        ty.strip_prefix(prefix)
+            .and_then(|rest| rest.strip_suffix('>'))
+            .map(arg_type)
+    };
+    if let Some(inner) = generic("Option<") {
+        return Json::Obj(vec![("option", inner)]);
+    }
+    if let Some(inner) = generic("Vec<") {
+        return Json::Obj(vec![("vec", inner)]);
+    }
+    if let Some(len) = ty
+        .strip_prefix("[u8;")
+        .and_then(|rest| rest.strip_suffix(']'))
+        .and_then(|len| len.parse().ok())
+    {
+        return byte_array(len);
+    }
+    match ty.as_str() {
+        "bool" | "u8" | "u16" | "u32" | "u64" | "i64" | "u128" => Json::str(ty.as_str()),
+        "Pubkey" => Json::str("pubkey"),
+        "String" => Json::str("string"),
+        defined => defined_type(defined),
+    }
+}
+
+/// Fixed-size byte array, e.g. a 32-byte hash
+fn byte_array(len: usize) -> Json {
+    Json::Obj(vec![(
+        "array",
+        Json::Arr(vec![Json::str("u8"), Json::Num(len as u64)]),
+    )])
+}
+
+fn defined_type(name: &str) -> Json {
+    Json::Obj(vec![(
+        "defined",
+        Json::Obj(vec![("name", Json::str(name))]),
+    )])
+}
+
+/// IDL type for an account field
+fn field_type(ty: &FieldType) -> Json {
+    match ty {
+        FieldType::Bool => Json::str("bool"),
+        FieldType::U8 => Json::str("u8"),
+        FieldType::U16 => Json::str("u16"),
+        FieldType::U64 => Json::str("u64"),
+        FieldType::I64 => Json::str("i64"),
+        FieldType::U128 => Json::str("u128"),
+        FieldType::Pubkey => Json::str("pubkey"),
+        FieldType::Bytes { len } => byte_array(*len),
+        FieldType::String { .. } => Json::str("string"),
+        FieldType::Option(inner) => Json::Obj(vec![("option", field_type(inner))]),
+        FieldType::Vec { item, .. } => Json::Obj(vec![("vec", field_type(item))]),
+        FieldType::Enum { name, .. } | FieldType::Struct { name, .. } => defined_type(name),
+    }
+}
+
+fn struct_type(name: &str, fields: &[layout::Field]) -> Json {
+    Json::Obj(vec![
+        ("name", Json::str(name)),
+        (
+            "type",
+            Json::Obj(vec![
+                ("kind", Json::str("struct")),
+                (
+                    "fields",
+                    Json::Arr(
+                        fields
+                            .iter()
+                            .map(|f| {
+                                Json::Obj(vec![
+                                    ("name", Json::str(f.name)),
+                                    ("type", field_type(&f.ty)),
+                                ])
+                            })
+                            .collect(),
+                    ),
+                ),
+            ]),
+        ),
+    ])
+}
+
+/// Nested struct and enum types referenced by account fields or arguments, first use first
+fn nested_types(ty: &FieldType, found: &mut Vec<FieldType>) {
+    match ty {
+        FieldType::Option(inner) | FieldType::Vec { item: inner, .. } => nested_types(inner, found),
+        FieldType::Enum { .. } | FieldType::Struct { .. } if !found.contains(ty) => {
+            found.push(*ty);
+        }
+        _ => {}
+    }
+}
+
+/// Borsh variant index of each instruction, from its test vector
+fn instruction_discriminator(spec: &InstructionSpec) -> u8 {
+    test_vectors::instruction_vectors()
+        .iter()
+        .find(|v| v.name == spec.name)
+        .map(|v| v.bytes[0])
+        .expect("every instruction has a test vector")
+}
+
+fn instructions() -> Json {
+    Json::Arr(
+        INSTRUCTIONS
+            .iter()
+            .map(|spec| {
+                Json::Obj(vec![
+                    ("name", Json::str(snake_case(spec.name))),
+                    (
+                        "discriminator",
+                        Json::bytes(&[instruction_discriminator(spec)]),
+                    ),
+                    (
+                        "accounts",
+                        Json::Arr(
+                            spec.accounts
+                                .iter()
+                                .map(|a| {
+                                    Json::Obj(vec![
+                                        ("name", Json::str(a.name)),
+                                        ("writable", Json::Bool(a.writable)),
+                                        ("signer", Json::Bool(a.signer)),
+                                    ])
+                                })
+                                .collect(),
+                        ),
+                    ),
+                    (
+                        "args",
+                        Json::Arr(
+                            spec.args
+                                .iter()
+                                .map(|a| {
+                                    Json::Obj(vec![
+                                        ("name", Json::str(a.name)),
+                                        ("type", arg_type(a.ty)),
+                                    ])
+                                })
+                                .collect(),
+                        ),
+                    ),
+                ])
+            })
+            .collect(),
+    )
+}
+
+fn seed(component: &SeedComponent) -> Json {
+    match component {
+        SeedComponent::Literal(bytes) => Json::Obj(vec![
+            ("kind", Json::str("const")),
+            ("value", Json::bytes(bytes)),
+        ]),
+        SeedComponent::Version => Json::Obj(vec![
+            ("kind", Json::str("const")),
+            ("value", Json::bytes(&[pda::PDA_VERSION])),
+        ]),
+        SeedComponent::Pubkey(name) => Json::Obj(vec![
+            ("kind", Json::str("input")),
+            ("name", Json::str(*name)),
+            ("type", Json::str("pubkey")),
+        ]),
+        SeedComponent::U64Le(name) => Json::Obj(vec![
+            ("kind", Json::str("input")),
+            ("name", Json::str(*name)),
+            ("type", Json::str("u64")),
+        ]),
+        SeedComponent::Hash(name) => Json::Obj(vec![
+            ("kind", Json::str("input")),
+            ("name", Json::str(*name)),
+            ("type", byte_array(32)),
+        ]),
+        SeedComponent::EvmAddress(name) => Json::Obj(vec![
+            ("kind", Json::str("input")),
+            ("name", Json::str(*name)),
+            ("type", byte_array(20)),
+        ]),
+    }
+}
+
+fn idl() -> Json {
+    let accounts = layout::accounts();
+
+    let mut types: Vec<Json> = accounts
+        .iter()
+        .map(|a| struct_type(a.name, a.fields))
+        .collect();
+    let mut nested = Vec::new();
+    for field in accounts.iter().flat_map(|a| a.fields) {
+        nested_types(&field.ty, &mut nested);
+    }
+    for ty in layout::ARG_TYPES {
+        nested_types(ty, &mut nested);
+    }
+    for ty in nested {
+        types.push(match ty {
+            FieldType::Struct { name, fields } => struct_type(name, fields),
+            FieldType::Enum { name, variants } => Json::Obj(vec![
+                ("name", Json::str(name)),
+                (
+                    "type",
+                    Json::Obj(vec![
+                        ("kind", Json::str("enum")),
+                        (
+                            "variants",
+                            Json::Arr(
+                                variants
+                                    .iter()
+                                    .map(|v| Json::Obj(vec![("name", Json::str(*v))]))
+                                    .collect(),
+                            ),
+                        ),
+                    ]),
+                ),
+            ]),
+            _ => unreachable!("only structs and enums are nested types"),
+        });
+    }
+
+    Json::Obj(vec![
+        ("address", Json::str(crate::id().to_string())),
+        (
+            "metadata",
+            Json::Obj(vec![
+                ("name", Json::str(env!("CARGO_PKG_NAME"))),
+                ("version", Json::str(env!("CARGO_PKG_VERSION"))),
+                ("spec", Json::str("0.1.0")),
+                ("description", Json::str(env!("CARGO_PKG_DESCRIPTION"))),
+            ]),
+        ),
+        ("instructions", instructions()),
+        (
+            "accounts",
+            Json::Arr(
+                accounts
+                    .iter()
+                    .map(|a| {
+                        Json::Obj(vec![
+                            ("name", Json::str(a.name)),
+                            ("discriminator", Json::bytes(&a.discriminator)),
+                        ])
+                    })
+                    .collect(),
+            ),
+        ),
+        (
+            "errors",
+            Json::Arr(
+                ERRORS
+                    .iter()
+                    .map(|e| {
+                        Json::Obj(vec![
+                            ("code", Json::Num(*e as u64)),
+                            ("name", Json::str(format!("{:?}", e))),
+                            ("msg", Json::str(e.to_string())),
+                        ])
+                    })
+                    .collect(),
+            ),
+        ),
+        ("types", Json::Arr(types)),
+        (
+            "pdas",
+            Json::Arr(
+                pda::LAYOUTS
+                    .iter()
+                    .map(|l| {
+                        Json::Obj(vec![
+                            ("account", Json::str(l.account)),
+                            ("seeds", Json::Arr(l.seeds.iter().map(seed).collect())),
+                        ])
+                    })
+                    .collect(),
+            ),
+        ),
+    ])
+}
+
+/// Render the IDL checked into `idl/mailer.json`
+pub fn to_json() -> String {
+    let mut out = String::new();
+    idl().render(0, &mut out);
+    out.push('\n');
+    out
+}
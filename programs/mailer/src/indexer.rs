@@ -0,0 +1,442 @@
+//! # Reference Indexer
+//!
+//! Pure decoding of Mailer transaction data into typed [`MailerActivity`] records. This is
+//! the canonical reference for off-chain services: the integration tests decode the logs
+//! of real transactions with it, so any change to on-chain log formats must update this
+//! module in the same change.
+//!
+//! Enable with the `indexer` feature:
+//! ```toml
+//! [dependencies]
+//! mailer = { path = "../mailer", features = ["indexer"] }
+//! ```
+//!
+//! Only decode logs of successful transactions; failed transactions roll back every
+//! effect their logs describe.
+//!
+//! Event logs about a wallet recipient open with its [`crate::recipient_log_prefix`], so a
+//! service notifying one user can keep only the lines containing that prefix before decoding.
+
+use solana_program::{hash::Hash, pubkey::Pubkey};
+use std::str::FromStr;
+
+use crate::{instruction_versions, MailerInstruction, RECIPIENT_LOG_KEY_LEN};
+
+/// Destination of a sent message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SendTarget {
+    Wallet(Pubkey),
+    Email(String),
+    /// Salted email hash of a `SendToEmailHash` and the off-chain hint id to resolve it with
+    EmailHash {
+        hash: [u8; 32],
+        hint_id: u64,
+    },
+}
+
+/// A message send decoded from the program logs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SentMail {
+    pub sender: Pubkey,
+    pub payer: Pubkey,
+    pub to: SendTarget,
+    /// Subject for direct sends; `None` for prepared and webhook sends
+    pub subject: Option<String>,
+    pub mail_id: Option<String>,
+    pub webhook_id: Option<String>,
+    /// Priority send with revenue sharing to the recipient
+    pub revenue_share: bool,
+    /// Fee-exempt protocol-origin send
+    pub protocol: bool,
+    pub effective_fee: u64,
+    /// Score from the registered spam oracle, if one was consulted
+    pub spam_score: Option<u64>,
+    /// Mint the fee was paid in; `None` for lamport fees
+    pub fee_mint: Option<Pubkey>,
+    /// Decimals of `effective_fee`, logged for USDC and lamport fees but not whitelisted mints
+    pub fee_decimals: Option<u8>,
+    /// Fee paid in lamports into the lamport vault; `effective_fee` is then in lamports
+    pub fee_in_lamports: bool,
+    /// USDC surcharge collected for the webhook owner on top of `effective_fee`
+    pub webhook_surcharge: Option<u64>,
+    /// Tip credited in full to the recipient's claim on top of a priority send's fee
+    pub tip: Option<u64>,
+    /// Position of the send in the recipient's inbox, when its RecipientInbox was passed
+    pub inbox_sequence: Option<u64>,
+    /// Deterministic id of the send, see [`crate::derive_message_id`]
+    pub message_id: Option<[u8; 32]>,
+    /// Conversation the message belongs to, as chosen by the sender
+    pub thread_id: Option<[u8; 32]>,
+    /// Message this one replies to
+    pub in_reply_to: Option<[u8; 32]>,
+}
+
+/// Source of a claim payout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClaimKind {
+    /// Recipient claimed their share in the default mint
+    Recipient,
+    /// Recipient claimed their share in an alternate mint via a conversion vault
+    Converted { mint: Pubkey },
+    /// Recipient claimed their share from the backup mint's vault in contingency mode
+    Backup { mint: Pubkey },
+    /// Share distributed to the recipient while the contract was paused
+    Distributed,
+    /// Expired share moved under owner control
+    Expired,
+    /// Owner claimed accumulated fees
+    Owner,
+    /// Webhook owner claimed the surcharges accrued by `webhook`
+    Webhook { webhook: Pubkey },
+}
+
+/// A typed Mailer activity record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MailerActivity {
+    /// Message sent with its fee paid (or no fee due)
+    Sent(SentMail),
+    /// Message sent but the fee transfer failed
+    FeeFailed(SentMail),
+    /// Soft-fail detail logged just before a `FeeFailed` send: why the fee was not paid
+    MailDropped {
+        sender: Pubkey,
+        to: SendTarget,
+        reason: String,
+    },
+    /// Priority send logged just before its send, which went out as a standard send because
+    /// the recipient's claim is frozen or capped (`reason`)
+    DowngradedToStandard {
+        sender: Pubkey,
+        to: Pubkey,
+        reason: String,
+    },
+    /// Envelope of a `SendEncrypted`, logged just after its send; the ciphertext itself is
+    /// in the instruction data and matches `digest` (see [`crate::crypto::envelope_digest`])
+    EncryptedMail {
+        sender: Pubkey,
+        to: Pubkey,
+        ephemeral_key: [u8; 32],
+        ciphertext_len: usize,
+        digest: [u8; 32],
+    },
+    Claimed {
+        account: Pubkey,
+        amount: u64,
+        kind: ClaimKind,
+    },
+    /// Part of a split recipient claim, logged after its `Claimed` record for each destination
+    ClaimSplit {
+        recipient: Pubkey,
+        destination: Pubkey,
+        amount: u64,
+        bps: u16,
+    },
+    Delegated {
+        delegator: Pubkey,
+        delegate: Option<Pubkey>,
+    },
+    DelegationRejected {
+        delegate: Pubkey,
+    },
+}
+
+/// Decode the Mailer activity in a transaction's log messages, in order
+/// Only `Program log:` lines emitted while the Mailer program is the innermost
+/// invocation are considered, so logs from CPI'd or calling programs never leak in.
+/// Frames are tracked by invoke depth, so duplicated invoke/success lines (as emitted
+/// by some local test harnesses) do not desynchronize the call stack.
+pub fn decode_logs<S: AsRef<str>>(logs: &[S]) -> Vec<MailerActivity> {
+    let program_id = crate::id().to_string();
+    let mut stack: Vec<&str> = Vec::new();
+    let mut activity = Vec::new();
+
+    for line in logs {
+        let line = line.as_ref();
+        if let Some(log) = line.strip_prefix("Program log: ") {
+            if stack.last() == Some(&program_id.as_str()) {
+                activity.extend(decode_log(log));
+            }
+        } else if let Some(rest) = line.strip_prefix("Program ") {
+            let mut parts = rest.split(' ');
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some(id), Some("invoke"), Some(depth))
+                    if depth == format!("[{}]", stack.len() + 1) =>
+                {
+                    stack.push(id);
+                }
+                (Some(id), Some("success" | "failed:"), _) if stack.last() == Some(&id) => {
+                    stack.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    activity
+}
+
+/// Decode a single Mailer log message (without the `Program log: ` prefix)
+pub fn decode_log(log: &str) -> Option<MailerActivity> {
+    let log = split_recipient_key(log).map_or(log, |(_, event)| event);
+    decode_send(log)
+        .or_else(|| decode_dropped(log))
+        .or_else(|| decode_downgraded(log))
+        .or_else(|| decode_encrypted(log))
+        .or_else(|| decode_claim(log))
+        .or_else(|| decode_claim_split(log))
+        .or_else(|| decode_delegation(log))
+}
+
+/// Recipient key bytes and event of a log opening with a [`crate::recipient_log_prefix`]
+pub fn split_recipient_key(log: &str) -> Option<([u8; RECIPIENT_LOG_KEY_LEN], &str)> {
+    let (digits, event) = log.strip_prefix("[rcpt:")?.split_once("] ")?;
+    if digits.len() != 2 * RECIPIENT_LOG_KEY_LEN {
+        return None;
+    }
+    let mut key = [0u8; RECIPIENT_LOG_KEY_LEN];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(digits.get(2 * i..2 * i + 2)?, 16).ok()?;
+    }
+    Some((key, event))
+}
+
+/// Decode Mailer instruction data in any supported format, e.g. from a top-level or inner
+/// instruction
+pub fn decode_instruction(data: &[u8]) -> Option<MailerInstruction> {
+    instruction_versions::decode(data)
+        .ok()
+        .map(|decoded| decoded.instruction)
+}
+
+fn key(s: &str) -> Option<Pubkey> {
+    Pubkey::from_str(s).ok()
+}
+
+/// Split "`head` (`details`)" at the final parenthetical
+fn split_details(s: &str) -> Option<(&str, &str)> {
+    let start = s.rfind(" (")?;
+    Some((&s[..start], s[start + 2..].strip_suffix(')')?))
+}
+
+/// Value of a `name: value` entry in a comma-separated details list
+fn detail<'a>(details: &'a str, name: &str) -> Option<&'a str> {
+    details
+        .split(", ")
+        .find_map(|entry| entry.strip_prefix(name)?.strip_prefix(": "))
+}
+
+fn decode_send(log: &str) -> Option<MailerActivity> {
+    let (kind, rest) = [
+        "Priority mail sent from ",
+        "Standard mail sent from ",
+        "Priority prepared mail sent from ",
+        "Standard prepared mail sent from ",
+        "Mail sent from ",
+        "Prepared mail sent from ",
+        "Webhook mail sent from ",
+        "Protocol mail sent from ",
+    ]
+    .iter()
+    .find_map(|prefix| Some((*prefix, log.strip_prefix(prefix)?)))?;
+
+    let (head, details) = split_details(rest)?;
+    let prepared = kind.contains("prepared") || kind.starts_with("Prepared");
+    let protocol = kind.starts_with("Protocol");
+
+    // "{sender} payer {payer} to {target}[: {subject}]"; protocol sends have no payer
+    let (sender, payer, rest) = if protocol {
+        let (sender, rest) = head.split_once(" to ")?;
+        let sender = key(sender)?;
+        (sender, sender, rest)
+    } else {
+        let (sender, rest) = head.split_once(" payer ")?;
+        let (payer, rest) = rest.split_once(" to ")?;
+        (key(sender)?, key(payer)?, rest)
+    };
+
+    let (target, subject) = if prepared || kind.starts_with("Webhook") {
+        (rest, None)
+    } else {
+        let (target, subject) = rest.split_once(": ")?;
+        (target, Some(subject.to_string()))
+    };
+    let to = send_target(target)?;
+
+    let mail = SentMail {
+        sender,
+        payer,
+        to,
+        subject,
+        mail_id: detail(details, "mailId").map(str::to_string),
+        webhook_id: detail(details, "webhookId").map(str::to_string),
+        revenue_share: details
+            .split(", ")
+            .any(|entry| entry == "revenue share enabled"),
+        protocol,
+        effective_fee: detail(details, "effective fee")?.parse().ok()?,
+        spam_score: detail(details, "spam score").and_then(|score| score.parse().ok()),
+        fee_mint: detail(details, "fee mint").and_then(key),
+        fee_decimals: detail(details, "fee decimals").and_then(|decimals| decimals.parse().ok()),
+        fee_in_lamports: details.split(", ").any(|entry| entry == "fee in lamports"),
+        webhook_surcharge: detail(details, "webhook surcharge")
+            .and_then(|amount| amount.parse().ok()),
+        tip: detail(details, "tip").and_then(|amount| amount.parse().ok()),
+        inbox_sequence: detail(details, "inbox sequence")
+            .and_then(|sequence| sequence.parse().ok()),
+        message_id: detail(details, "message id").and_then(message_hash),
+        thread_id: detail(details, "thread").and_then(message_hash),
+        in_reply_to: detail(details, "in reply to").and_then(message_hash),
+    };
+
+    match detail(details, "fee paid")? {
+        "true" => Some(MailerActivity::Sent(mail)),
+        "false" => Some(MailerActivity::FeeFailed(mail)),
+        _ => None,
+    }
+}
+
+/// Base58 32-byte value: a message, thread or parent id, an ephemeral key, a digest or an
+/// email hash
+fn message_hash(value: &str) -> Option<[u8; 32]> {
+    Hash::from_str(value).ok().map(|hash| hash.to_bytes())
+}
+
+/// "`{pubkey}`", "email hash `{hash}` hint `{hint_id}`" or "email `{address}`"
+fn send_target(target: &str) -> Option<SendTarget> {
+    if let Some(rest) = target.strip_prefix("email hash ") {
+        let (hash, hint_id) = rest.split_once(" hint ")?;
+        return Some(SendTarget::EmailHash {
+            hash: message_hash(hash)?,
+            hint_id: hint_id.parse().ok()?,
+        });
+    }
+    match target.strip_prefix("email ") {
+        Some(email) => Some(SendTarget::Email(email.to_string())),
+        None => Some(SendTarget::Wallet(key(target)?)),
+    }
+}
+
+fn decode_dropped(log: &str) -> Option<MailerActivity> {
+    // "MailDropped { sender: {sender}, to: {target}, reason: {reason} }"
+    let fields = log
+        .strip_prefix("MailDropped { sender: ")?
+        .strip_suffix(" }")?;
+    let (sender, rest) = fields.split_once(", to: ")?;
+    let (target, reason) = rest.rsplit_once(", reason: ")?;
+    Some(MailerActivity::MailDropped {
+        sender: key(sender)?,
+        to: send_target(target)?,
+        reason: reason.to_string(),
+    })
+}
+
+fn decode_downgraded(log: &str) -> Option<MailerActivity> {
+    // "DowngradedToStandard { sender: {sender}, to: {to}, reason: {reason} }"
+    let fields = log
+        .strip_prefix("DowngradedToStandard { ")?
+        .strip_suffix(" }")?;
+    Some(MailerActivity::DowngradedToStandard {
+        sender: key(detail(fields, "sender")?)?,
+        to: key(detail(fields, "to")?)?,
+        reason: detail(fields, "reason")?.to_string(),
+    })
+}
+
+fn decode_encrypted(log: &str) -> Option<MailerActivity> {
+    // "EncryptedMail { sender: {sender}, to: {to}, ephemeral key: {key}, ciphertext: {len} bytes, digest: {digest} }"
+    let fields = log.strip_prefix("EncryptedMail { ")?.strip_suffix(" }")?;
+    Some(MailerActivity::EncryptedMail {
+        sender: key(detail(fields, "sender")?)?,
+        to: key(detail(fields, "to")?)?,
+        ephemeral_key: message_hash(detail(fields, "ephemeral key")?)?,
+        ciphertext_len: detail(fields, "ciphertext")?
+            .strip_suffix(" bytes")?
+            .parse()
+            .ok()?,
+        digest: message_hash(detail(fields, "digest")?)?,
+    })
+}
+
+fn decode_claim(log: &str) -> Option<MailerActivity> {
+    if let Some(rest) = log.strip_prefix("Recipient ") {
+        let (account, rest) = rest.split_once(" claimed ")?;
+        // "{amount} as {mint} from {conversion|backup} vault; ..." for converted claims
+        let (amount, kind) = match rest.split_once(" as ") {
+            Some((amount, rest)) => {
+                let (mint, source) = rest.split_once(' ')?;
+                let mint = key(mint)?;
+                if source.starts_with("from backup vault") {
+                    (amount, ClaimKind::Backup { mint })
+                } else {
+                    (amount, ClaimKind::Converted { mint })
+                }
+            }
+            None => (rest, ClaimKind::Recipient),
+        };
+        return Some(MailerActivity::Claimed {
+            account: key(account)?,
+            amount: amount.parse().ok()?,
+            kind,
+        });
+    }
+
+    let (account, amount, kind) = if let Some(rest) = log.strip_prefix("Owner ") {
+        let (account, amount) = rest.split_once(" claimed ")?;
+        (account, amount, ClaimKind::Owner)
+    } else if let Some(rest) = log.strip_prefix("Webhook owner ") {
+        // "{owner} claimed {amount} from {webhook}"
+        let (account, rest) = rest.split_once(" claimed ")?;
+        let (amount, webhook) = rest.split_once(" from ")?;
+        (
+            account,
+            amount,
+            ClaimKind::Webhook {
+                webhook: key(webhook)?,
+            },
+        )
+    } else if let Some(rest) = log.strip_prefix("Distributed claimable funds to ") {
+        let (account, amount) = rest.split_once(": ")?;
+        (account, amount, ClaimKind::Distributed)
+    } else if let Some(rest) = log.strip_prefix("Expired shares claimed for ") {
+        let (account, amount) = rest.split_once(": ")?;
+        (account, amount, ClaimKind::Expired)
+    } else {
+        return None;
+    };
+
+    Some(MailerActivity::Claimed {
+        account: key(account)?,
+        amount: amount.parse().ok()?,
+        kind,
+    })
+}
+
+fn decode_claim_split(log: &str) -> Option<MailerActivity> {
+    // "ClaimSplit { recipient: {recipient}, destination: {destination}, amount: {amount}, bps: {bps} }"
+    let fields = log.strip_prefix("ClaimSplit { ")?.strip_suffix(" }")?;
+    Some(MailerActivity::ClaimSplit {
+        recipient: key(detail(fields, "recipient")?)?,
+        destination: key(detail(fields, "destination")?)?,
+        amount: detail(fields, "amount")?.parse().ok()?,
+        bps: detail(fields, "bps")?.parse().ok()?,
+    })
+}
+
+fn decode_delegation(log: &str) -> Option<MailerActivity> {
+    if let Some(rest) = log.strip_prefix("Delegation set from ") {
+        let (delegator, delegate) = rest.split_once(" to ")?;
+        let delegate = match delegate {
+            "None" => None,
+            some => Some(key(some.strip_prefix("Some(")?.strip_suffix(')')?)?),
+        };
+        return Some(MailerActivity::Delegated {
+            delegator: key(delegator)?,
+            delegate,
+        });
+    }
+
+    let delegate = log.strip_prefix("Delegation rejected by ")?;
+    Some(MailerActivity::DelegationRejected {
+        delegate: key(delegate)?,
+    })
+}
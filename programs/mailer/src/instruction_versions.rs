@@ -0,0 +1,110 @@
+//! # Instruction Data Versions
+//!
+//! Legacy instruction data is the bare Borsh encoding of [`MailerInstruction`], whose first
+//! byte is the variant index. A legacy-encoded variant cannot grow: data with fewer or more
+//! fields than the program expects fails to decode.
+//!
+//! Version 2 data opens with the format byte [`FORMAT_V2`] followed by the same Borsh
+//! encoding, and decodes forgivingly:
+//! - fields missing at the end of the data decode as zero (`false`, `0`, `None`, empty
+//!   strings and vectors, the first enum variant), so a field appended to a variant reaches
+//!   the program as its zero value from clients that predate it;
+//! - bytes after the fields this program knows are extension bytes written by newer clients
+//!   and are ignored.
+//!
+//! Format bytes start at [`FORMAT_PREFIX`], above every variant index, so the two encodings
+//! never collide and legacy data keeps decoding strictly; unknown format bytes fail with
+//! `UnsupportedInstructionFormat`. Fields appended to a variant must therefore read zero as
+//! "absent". Variants added since version 2 end with a [`Reserved`] block, zero until a later
+//! field claims it, so legacy-encoded clients of those variants keep a fixed size as well.
+//!
+//! ```rust
+//! use mailer::instruction_versions::{self, InstructionFormat, FORMAT_V2};
+//! use mailer::MailerInstruction;
+//!
+//! let data = instruction_versions::encode_v2(&MailerInstruction::ClaimOwnerShare);
+//! assert_eq!(data[0], FORMAT_V2);
+//!
+//! // Extension bytes from a newer client are skipped
+//! let mut extended = data.clone();
+//! extended.extend_from_slice(&[1, 2, 3]);
+//! let decoded = instruction_versions::decode(&extended).unwrap();
+//! assert!(matches!(decoded.instruction, MailerInstruction::ClaimOwnerShare));
+//! assert_eq!(decoded.format, InstructionFormat::V2);
+//! ```
+
+use std::io::Read;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::program_error::ProgramError;
+
+use crate::{MailerError, MailerInstruction};
+
+/// Lowest format byte; variant indexes stay below it
+pub const FORMAT_PREFIX: u8 = 0xF0;
+
+/// Format byte of version 2 instruction data
+pub const FORMAT_V2: u8 = FORMAT_PREFIX | 2;
+
+/// Size of the extension block ending variants added since version 2
+pub const RESERVED_LEN: usize = 8;
+
+/// Zero bytes filled in at most for fields missing from version 2 data: a transaction
+/// packet, so a missing field can never decode into more than a transaction could carry
+const MAX_ZERO_FILL: u64 = 1232;
+
+/// Extension bytes reserved at the end of variants added since version 2
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Reserved(pub [u8; RESERVED_LEN]);
+
+/// Encoding of decoded instruction data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstructionFormat {
+    /// Bare Borsh, decoded strictly
+    Legacy,
+    /// Format byte, then Borsh with zero-filled missing fields and ignored extension bytes
+    V2,
+}
+
+/// Instruction decoded from data in any supported format
+#[derive(Debug)]
+pub struct DecodedInstruction {
+    pub instruction: MailerInstruction,
+    /// Variant index, as used by the owner's instruction switches
+    pub tag: u8,
+    pub format: InstructionFormat,
+}
+
+/// Decode instruction data in the legacy or version 2 format
+pub fn decode(data: &[u8]) -> Result<DecodedInstruction, ProgramError> {
+    match data.split_first() {
+        Some((&FORMAT_V2, body)) => {
+            let tag = *body.first().ok_or(ProgramError::InvalidInstructionData)?;
+            let mut reader = body.chain(std::io::repeat(0).take(MAX_ZERO_FILL));
+            let instruction = MailerInstruction::deserialize_reader(&mut reader)?;
+            Ok(DecodedInstruction {
+                instruction,
+                tag,
+                format: InstructionFormat::V2,
+            })
+        }
+        Some((&format, _)) if format >= FORMAT_PREFIX => {
+            Err(MailerError::UnsupportedInstructionFormat.into())
+        }
+        Some((&tag, _)) => Ok(DecodedInstruction {
+            instruction: MailerInstruction::try_from_slice(data)?,
+            tag,
+            format: InstructionFormat::Legacy,
+        }),
+        None => Err(ProgramError::InvalidInstructionData),
+    }
+}
+
+/// Version 2 instruction data of `instruction`
+pub fn encode_v2(instruction: &MailerInstruction) -> Vec<u8> {
+    let mut data = vec![FORMAT_V2];
+    instruction
+        .serialize(&mut data)
+        .expect("serializing into a Vec cannot fail");
+    data
+}
@@ -0,0 +1,675 @@
+//! # Account Layouts
+//!
+//! Field-by-field Borsh layouts of every account type, so clients can decode accounts
+//! and build `getProgramAccounts` memcmp filters without mirroring struct definitions
+//! by hand. Each account is stored as an 8-byte discriminator followed by its fields
+//! in declaration order; `space` is the allocated size including the discriminator.
+//!
+//! Variable-length fields (strings, vectors, populated options) shift every later
+//! field, so [`AccountLayout::fixed_offsets`] only reports offsets up to the first one.
+
+use crate::costs::DISCRIMINATOR_LEN;
+use crate::instruction_versions::RESERVED_LEN;
+use crate::{
+    discriminator, AdminSet, ClaimBeneficiary, ClaimDistribution, ClaimHistoryEntry,
+    ClaimRentPayer, ClosureLog, ConversionVault, DelegateIndex, Delegation, DiscountSchedule,
+    Escrow, EvmMapping, FeeDiscount, FeeMint, InboxPolicy, ListChunk, MailerState, MailerStats,
+    MailingList, MessageNonce, MessageReceipt, PayoutConfig, Plan, Preauthorization, Promo,
+    RateLimit, RecipientClaim, RecipientInbox, RecipientPrefs, Sanctioned, SendCredits, SendNonce,
+    SenderStats, SessionKey, Subscription, SweepCursor, Webhook, WormholeConfig,
+    CLOSURE_LOG_CAPACITY, MAX_ADMINS, MAX_DISCOUNT_TIERS, MAX_DISTRIBUTION_LEAVES,
+    MAX_LIST_CHUNK_MEMBERS, MAX_MEMO_LEN, MAX_POLICY_SENDERS, MAX_WEBHOOK_ID_LEN,
+    SEND_NONCE_CAPACITY,
+};
+
+/// Borsh type of an account field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    Bool,
+    U8,
+    U16,
+    U64,
+    I64,
+    U128,
+    Pubkey,
+    /// Fixed-size byte array, e.g. a 32-byte hash
+    Bytes {
+        len: usize,
+    },
+    /// 1-byte tag followed by the value when present
+    Option(&'static FieldType),
+    /// u32 length prefix followed by at most `max_len` UTF-8 bytes
+    String {
+        max_len: usize,
+    },
+    /// u32 length prefix followed by at most `max_items` items
+    Vec {
+        item: &'static FieldType,
+        max_items: usize,
+    },
+    /// Fieldless enum encoded as a 1-byte variant index
+    Enum {
+        name: &'static str,
+        variants: &'static [&'static str],
+    },
+    /// Nested struct encoded as its fields in order
+    Struct {
+        name: &'static str,
+        fields: &'static [Field],
+    },
+}
+
+impl FieldType {
+    /// Maximum encoded size in bytes
+    pub fn max_size(&self) -> usize {
+        match self {
+            FieldType::Bool | FieldType::U8 | FieldType::Enum { .. } => 1,
+            FieldType::U16 => 2,
+            FieldType::U64 | FieldType::I64 => 8,
+            FieldType::U128 => 16,
+            FieldType::Pubkey => 32,
+            FieldType::Bytes { len } => *len,
+            FieldType::Option(inner) => 1 + inner.max_size(),
+            FieldType::String { max_len } => 4 + max_len,
+            FieldType::Vec { item, max_items } => 4 + item.max_size() * max_items,
+            FieldType::Struct { fields, .. } => fields.iter().map(|f| f.ty.max_size()).sum(),
+        }
+    }
+
+    /// Whether the encoded size is the same for every value
+    pub fn is_fixed_size(&self) -> bool {
+        match self {
+            FieldType::Option(_) | FieldType::String { .. } | FieldType::Vec { .. } => false,
+            FieldType::Struct { fields, .. } => fields.iter().all(|f| f.ty.is_fixed_size()),
+            _ => true,
+        }
+    }
+
+    /// Type name used in the conformance fixture, e.g. `option<pubkey>` or `vec<pubkey;16>`
+    pub fn type_name(&self) -> String {
+        match self {
+            FieldType::Bool => "bool".to_string(),
+            FieldType::U8 => "u8".to_string(),
+            FieldType::U16 => "u16".to_string(),
+            FieldType::U64 => "u64".to_string(),
+            FieldType::I64 => "i64".to_string(),
+            FieldType::U128 => "u128".to_string(),
+            FieldType::Pubkey => "pubkey".to_string(),
+            FieldType::Bytes { len } => format!("bytes<{}>", len),
+            FieldType::Option(inner) => format!("option<{}>", inner.type_name()),
+            FieldType::String { max_len } => format!("string<{}>", max_len),
+            FieldType::Vec { item, max_items } => {
+                format!("vec<{};{}>", item.type_name(), max_items)
+            }
+            FieldType::Enum { name, .. } | FieldType::Struct { name, .. } => name.to_string(),
+        }
+    }
+}
+
+/// A named account field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Field {
+    pub name: &'static str,
+    pub ty: FieldType,
+}
+
+const fn field(name: &'static str, ty: FieldType) -> Field {
+    Field { name, ty }
+}
+
+/// Layout of one account type
+#[derive(Debug, Clone)]
+pub struct AccountLayout {
+    pub name: &'static str,
+    /// `sha256("account:<Name>")[..8]`, written by current deployments
+    pub discriminator: [u8; 8],
+    /// Discriminator written by earlier deployments, accepted until migrated; `None` for
+    /// account types added since
+    pub legacy_discriminator: Option<[u8; 8]>,
+    /// Allocated account size, discriminator included
+    pub space: usize,
+    pub fields: &'static [Field],
+}
+
+impl AccountLayout {
+    /// Byte offset of each field within the account data (discriminator included), or
+    /// `None` once a preceding field has a variable size
+    pub fn fixed_offsets(&self) -> Vec<Option<usize>> {
+        let mut offset = Some(DISCRIMINATOR_LEN);
+        self.fields
+            .iter()
+            .map(|f| {
+                let current = offset;
+                offset = match offset {
+                    Some(o) if f.ty.is_fixed_size() => Some(o + f.ty.max_size()),
+                    _ => None,
+                };
+                current
+            })
+            .collect()
+    }
+
+    /// Maximum encoded size of the fields, excluding the discriminator
+    pub fn data_len(&self) -> usize {
+        self.fields.iter().map(|f| f.ty.max_size()).sum()
+    }
+}
+
+const PENDING_FEE_CHANGE: FieldType = FieldType::Struct {
+    name: "PendingFeeChange",
+    fields: &[
+        field("new_fee", FieldType::U64),
+        field("effective_at", FieldType::I64),
+    ],
+};
+
+const PAYLOAD_LIMITS: FieldType = FieldType::Struct {
+    name: "PayloadLimits",
+    fields: &[
+        field("max_subject_len", FieldType::U16),
+        field("max_body_len", FieldType::U16),
+        field("max_mail_id_len", FieldType::U16),
+        field("max_email_len", FieldType::U16),
+        field("max_webhook_id_len", FieldType::U16),
+    ],
+};
+
+const INBOX_MODE: FieldType = FieldType::Enum {
+    name: "InboxMode",
+    variants: &["Open", "Allowlist", "Blocklist"],
+};
+
+const CLAIM_CREATION_POLICY: FieldType = FieldType::Enum {
+    name: "ClaimCreationPolicy",
+    variants: &["Eager", "AfterTransfer", "RefundPayer"],
+};
+
+const MAILER_STATE: &[Field] = &[
+    field("owner", FieldType::Pubkey),
+    field("usdc_mint", FieldType::Pubkey),
+    field("send_fee", FieldType::U64),
+    field("delegation_fee", FieldType::U64),
+    field("owner_claimable", FieldType::U64),
+    field("paused", FieldType::Bool),
+    field("fee_paused", FieldType::Bool),
+    field("bump", FieldType::U8),
+    field("protocol_sends_enabled", FieldType::Bool),
+    field("pending_owner", FieldType::Option(&FieldType::Pubkey)),
+    field("fee_timelock", FieldType::I64),
+    field("pending_fee", FieldType::Option(&PENDING_FEE_CHANGE)),
+    field("paused_at", FieldType::I64),
+    field("total_paused_duration", FieldType::I64),
+    field("claim_rent_reserve", FieldType::U64),
+    field("delegation_rent_reserve", FieldType::U64),
+    field("discount_rent_reserve", FieldType::U64),
+    field("disabled_instructions", FieldType::U128),
+    field("spam_oracle", FieldType::Option(&FieldType::Pubkey)),
+    field("spam_threshold", FieldType::Option(&FieldType::U64)),
+    field("rate_limit_max_sends", FieldType::U64),
+    field("rate_limit_window", FieldType::I64),
+    field("lamport_send_fee", FieldType::U64),
+    field("owner_claimable_lamports", FieldType::U64),
+    field("fee_oracle", FieldType::Option(&FieldType::Pubkey)),
+    field("fee_oracle_target", FieldType::U64),
+    field("fee_oracle_max_age", FieldType::I64),
+    field("claim_creation_policy", CLAIM_CREATION_POLICY),
+    field("usdc_decimals", FieldType::U8),
+    field("sanctions_enabled", FieldType::Bool),
+    field("backup_mint", FieldType::Option(&FieldType::Pubkey)),
+    field("stats_enabled", FieldType::Bool),
+    field("pause_flags", FieldType::U8),
+    field("kyc_verifier", FieldType::Option(&FieldType::Pubkey)),
+    field("delegation_refund_window", FieldType::I64),
+    field("claim_cap", FieldType::U64),
+    field("payload_limits", PAYLOAD_LIMITS),
+    field("payload_fee_tier_len", FieldType::U16),
+    field("payload_fee_tier_percent", FieldType::U16),
+    field("recipient_liabilities", FieldType::U64),
+    field("min_coverage_bps", FieldType::U16),
+    field("version", FieldType::U8),
+    field("treasury", FieldType::Option(&FieldType::Pubkey)),
+    field("credit_liabilities", FieldType::U64),
+];
+
+const RECIPIENT_CLAIM: &[Field] = &[
+    field("recipient", FieldType::Pubkey),
+    field("amount", FieldType::U64),
+    field("timestamp", FieldType::I64),
+    field("bump", FieldType::U8),
+    field("paused_offset", FieldType::I64),
+    field("no_expiry", FieldType::Bool),
+    field("surcharge_paid_until", FieldType::I64),
+    field("extensions", FieldType::U8),
+    field("messages_received", FieldType::U64),
+    field("total_claimed", FieldType::U64),
+];
+
+const DELEGATION: &[Field] = &[
+    field("delegator", FieldType::Pubkey),
+    field("delegate", FieldType::Option(&FieldType::Pubkey)),
+    field("bump", FieldType::U8),
+    field("escrowed_fee", FieldType::U64),
+    field("refund_deadline", FieldType::I64),
+    field("fee_refundable", FieldType::Bool),
+];
+
+const FEE_DISCOUNT: &[Field] = &[
+    field("account", FieldType::Pubkey),
+    field("discount", FieldType::U8),
+    field("bump", FieldType::U8),
+];
+
+const RECIPIENT_PREFS: &[Field] = &[
+    field("recipient", FieldType::Pubkey),
+    field("read_receipts_opt_out", FieldType::Bool),
+    field("bump", FieldType::U8),
+];
+
+const PAYOUT_CONFIG: &[Field] = &[
+    field("recipient", FieldType::Pubkey),
+    field(
+        "payout_token_account",
+        FieldType::Option(&FieldType::Pubkey),
+    ),
+    field("bump", FieldType::U8),
+];
+
+const CONVERSION_VAULT: &[Field] = &[
+    field("mint", FieldType::Pubkey),
+    field("vault", FieldType::Pubkey),
+    field("enabled", FieldType::Bool),
+    field("total_converted", FieldType::U64),
+    field("bump", FieldType::U8),
+];
+
+const MESSAGE_RECEIPT: &[Field] = &[
+    field("sender", FieldType::Pubkey),
+    field("recipient", FieldType::Pubkey),
+    field("nonce", FieldType::U64),
+    field("sent_at", FieldType::I64),
+    field("read_at", FieldType::I64),
+    field("bump", FieldType::U8),
+];
+
+const CLAIM_HISTORY_ENTRY: &[Field] = &[
+    field("recipient", FieldType::Pubkey),
+    field("sender", FieldType::Pubkey),
+    field("total_amount", FieldType::U64),
+    field("last_amount", FieldType::U64),
+    field(
+        "last_memo",
+        FieldType::String {
+            max_len: MAX_MEMO_LEN,
+        },
+    ),
+    field("last_timestamp", FieldType::I64),
+    field("bump", FieldType::U8),
+];
+
+const INBOX_POLICY: &[Field] = &[
+    field("recipient", FieldType::Pubkey),
+    field("mode", INBOX_MODE),
+    field(
+        "senders",
+        FieldType::Vec {
+            item: &FieldType::Pubkey,
+            max_items: MAX_POLICY_SENDERS,
+        },
+    ),
+    field("penalty_multiplier", FieldType::U8),
+    field("bump", FieldType::U8),
+    field("min_fee", FieldType::U64),
+];
+
+const SESSION_KEY: &[Field] = &[
+    field("recipient", FieldType::Pubkey),
+    field("session_key", FieldType::Pubkey),
+    field("scope", FieldType::U8),
+    field("expires_at", FieldType::I64),
+    field("bump", FieldType::U8),
+];
+
+const PREAUTHORIZATION: &[Field] = &[
+    field("user", FieldType::Pubkey),
+    field("relayer", FieldType::Pubkey),
+    field(
+        "webhook_id",
+        FieldType::String {
+            max_len: MAX_WEBHOOK_ID_LEN,
+        },
+    ),
+    field("max_sends", FieldType::U64),
+    field("sends_used", FieldType::U64),
+    field("max_fee_per_send", FieldType::U64),
+    field("expires_at", FieldType::I64),
+    field("bump", FieldType::U8),
+];
+
+const ADMIN_SET: &[Field] = &[
+    field(
+        "admins",
+        FieldType::Vec {
+            item: &FieldType::Pubkey,
+            max_items: MAX_ADMINS,
+        },
+    ),
+    field("threshold", FieldType::U8),
+    field("bump", FieldType::U8),
+];
+
+const SWEEP_CURSOR: &[Field] = &[
+    field("last_processed", FieldType::Pubkey),
+    field("processed_total", FieldType::U64),
+    field("swept_total", FieldType::U64),
+    field("bump", FieldType::U8),
+];
+
+const RATE_LIMIT: &[Field] = &[
+    field("sender", FieldType::Pubkey),
+    field("window_start", FieldType::I64),
+    field("count", FieldType::U64),
+    field("bump", FieldType::U8),
+];
+
+const PROMO: &[Field] = &[
+    field("code_hash", FieldType::Bytes { len: 32 }),
+    field("discount", FieldType::U8),
+    field("remaining_redemptions", FieldType::U64),
+    field("expiry", FieldType::I64),
+    field("bump", FieldType::U8),
+];
+
+const FEE_MINT: &[Field] = &[
+    field("mint", FieldType::Pubkey),
+    field("send_fee", FieldType::U64),
+    field("delegation_fee", FieldType::U64),
+    field("owner_claimable", FieldType::U64),
+    field("bump", FieldType::U8),
+];
+
+const CLAIM_RENT_PAYER: &[Field] = &[
+    field("claim", FieldType::Pubkey),
+    field("payer", FieldType::Pubkey),
+    field("bump", FieldType::U8),
+];
+
+const RECIPIENT_INBOX: &[Field] = &[
+    field("recipient", FieldType::Pubkey),
+    field("sequence", FieldType::U64),
+    field("bump", FieldType::U8),
+];
+
+const WEBHOOK: &[Field] = &[
+    field("owner", FieldType::Pubkey),
+    field("id_hash", FieldType::Bytes { len: 32 }),
+    field("endpoint_hash", FieldType::Bytes { len: 32 }),
+    field("active", FieldType::Bool),
+    field("surcharge", FieldType::U64),
+    field("claimable", FieldType::U64),
+    field("bump", FieldType::U8),
+    field("sends_routed", FieldType::U64),
+    field("statuses_recorded", FieldType::U64),
+    field("deliveries_failed", FieldType::U64),
+    field("total_ack_latency_slots", FieldType::U64),
+];
+
+const CLOSURE_RECORD: FieldType = FieldType::Struct {
+    name: "ClosureRecord",
+    fields: &[
+        field("account", FieldType::Pubkey),
+        field("state_hash", FieldType::Bytes { len: 32 }),
+        field("closed_at", FieldType::I64),
+    ],
+};
+
+const CLOSURE_LOG: &[Field] = &[
+    field("total", FieldType::U64),
+    field(
+        "records",
+        FieldType::Vec {
+            item: &CLOSURE_RECORD,
+            max_items: CLOSURE_LOG_CAPACITY,
+        },
+    ),
+    field("bump", FieldType::U8),
+];
+
+const SANCTIONED: &[Field] = &[
+    field("address", FieldType::Pubkey),
+    field("sanctioned_at", FieldType::I64),
+    field("bump", FieldType::U8),
+];
+
+const DELEGATE_INDEX: &[Field] = &[
+    field("delegate", FieldType::Pubkey),
+    field("count", FieldType::U64),
+    field("accumulator", FieldType::Bytes { len: 32 }),
+    field("bump", FieldType::U8),
+];
+
+const MAILER_STATS: &[Field] = &[
+    field("messages_sent", FieldType::U64),
+    field("priority_sent", FieldType::U64),
+    field("standard_sent", FieldType::U64),
+    field("fees_collected", FieldType::U64),
+    field("total_claimed", FieldType::U64),
+    field("bump", FieldType::U8),
+];
+
+const SEND_CREDITS: &[Field] = &[
+    field("sender", FieldType::Pubkey),
+    field("balance", FieldType::U64),
+    field("bump", FieldType::U8),
+];
+
+const PLAN: &[Field] = &[
+    field("plan_id", FieldType::U64),
+    field("price", FieldType::U64),
+    field("duration", FieldType::I64),
+    field("max_messages", FieldType::U64),
+    field("active", FieldType::Bool),
+    field("bump", FieldType::U8),
+];
+
+const SUBSCRIPTION: &[Field] = &[
+    field("subscriber", FieldType::Pubkey),
+    field("plan_id", FieldType::U64),
+    field("expires_at", FieldType::I64),
+    field("messages_remaining", FieldType::U64),
+    field("bump", FieldType::U8),
+];
+
+const CLAIM_BENEFICIARY: &[Field] = &[
+    field("recipient", FieldType::Pubkey),
+    field("beneficiary", FieldType::Pubkey),
+    field("bump", FieldType::U8),
+];
+
+const CLAIM_DISTRIBUTION: &[Field] = &[
+    field("distribution_id", FieldType::U64),
+    field("merkle_root", FieldType::Bytes { len: 32 }),
+    field("leaf_count", FieldType::U64),
+    field("total", FieldType::U64),
+    field("remaining", FieldType::U64),
+    field("bump", FieldType::U8),
+    field(
+        "claimed",
+        FieldType::Vec {
+            item: &FieldType::U8,
+            max_items: MAX_DISTRIBUTION_LEAVES / 8,
+        },
+    ),
+];
+
+const ESCROW: &[Field] = &[
+    field("sender", FieldType::Pubkey),
+    field("recipient", FieldType::Pubkey),
+    field("nonce", FieldType::U64),
+    field("amount", FieldType::U64),
+    field("created_at", FieldType::I64),
+    field("expires_at", FieldType::I64),
+    field("bump", FieldType::U8),
+];
+
+const MAILING_LIST: &[Field] = &[
+    field("owner", FieldType::Pubkey),
+    field("list_id", FieldType::U64),
+    field("member_count", FieldType::U64),
+    field("chunk_count", FieldType::U64),
+    field("bump", FieldType::U8),
+];
+
+const LIST_CHUNK: &[Field] = &[
+    field("list", FieldType::Pubkey),
+    field("index", FieldType::U64),
+    field("bump", FieldType::U8),
+    field(
+        "members",
+        FieldType::Vec {
+            item: &FieldType::Pubkey,
+            max_items: MAX_LIST_CHUNK_MEMBERS,
+        },
+    ),
+];
+
+const EVM_MAPPING: &[Field] = &[
+    field("evm_address", FieldType::Bytes { len: 20 }),
+    field("wallet", FieldType::Pubkey),
+    field("bound_at", FieldType::I64),
+    field("bump", FieldType::U8),
+];
+
+const WORMHOLE_CONFIG: &[Field] = &[
+    field("core_bridge", FieldType::Pubkey),
+    field("bridge", FieldType::Pubkey),
+    field("fee_collector", FieldType::Pubkey),
+    field("sequence", FieldType::Pubkey),
+    field("emitter_bump", FieldType::U8),
+    field("message_count", FieldType::U64),
+    field("bump", FieldType::U8),
+];
+
+const MESSAGE_NONCE: &[Field] = &[
+    field("sender", FieldType::Pubkey),
+    field("nonce", FieldType::U64),
+    field("bump", FieldType::U8),
+];
+
+const SEND_NONCE: &[Field] = &[
+    field("sender", FieldType::Pubkey),
+    field("total", FieldType::U64),
+    field(
+        "nonces",
+        FieldType::Vec {
+            item: &FieldType::U64,
+            max_items: SEND_NONCE_CAPACITY,
+        },
+    ),
+    field("bump", FieldType::U8),
+];
+
+const VOLUME_TIER: FieldType = FieldType::Struct {
+    name: "VolumeTier",
+    fields: &[
+        field("min_fees_paid", FieldType::U64),
+        field("discount", FieldType::U8),
+    ],
+};
+
+const DISCOUNT_SCHEDULE: &[Field] = &[
+    field("bump", FieldType::U8),
+    field(
+        "tiers",
+        FieldType::Vec {
+            item: &VOLUME_TIER,
+            max_items: MAX_DISCOUNT_TIERS,
+        },
+    ),
+];
+
+const SENDER_STATS: &[Field] = &[
+    field("sender", FieldType::Pubkey),
+    field("fees_paid", FieldType::U64),
+    field("bump", FieldType::U8),
+];
+
+/// Types that appear only in instruction arguments, not in any account
+pub const ARG_TYPES: &[FieldType] = &[
+    FieldType::Enum {
+        name: "MigrationKind",
+        variants: &["RecipientClaim", "Delegation", "FeeDiscount"],
+    },
+    FieldType::Struct {
+        name: "ClaimSplit",
+        fields: &[
+            field("destination", FieldType::Pubkey),
+            field("bps", FieldType::U16),
+        ],
+    },
+    FieldType::Struct {
+        name: "Reserved",
+        fields: &[field("bytes", FieldType::Bytes { len: RESERVED_LEN })],
+    },
+];
+
+fn layout(name: &'static str, len: usize, fields: &'static [Field]) -> AccountLayout {
+    AccountLayout {
+        name,
+        discriminator: discriminator::account(name),
+        legacy_discriminator: discriminator::legacy(name),
+        space: DISCRIMINATOR_LEN + len,
+        fields,
+    }
+}
+
+/// Layouts for every account type, in declaration order
+pub fn accounts() -> Vec<AccountLayout> {
+    vec![
+        layout("MailerState", MailerState::LEN, MAILER_STATE),
+        layout("RecipientClaim", RecipientClaim::LEN, RECIPIENT_CLAIM),
+        layout("Delegation", Delegation::LEN, DELEGATION),
+        layout("FeeDiscount", FeeDiscount::LEN, FEE_DISCOUNT),
+        layout("RecipientPrefs", RecipientPrefs::LEN, RECIPIENT_PREFS),
+        layout("PayoutConfig", PayoutConfig::LEN, PAYOUT_CONFIG),
+        layout("ConversionVault", ConversionVault::LEN, CONVERSION_VAULT),
+        layout("MessageReceipt", MessageReceipt::LEN, MESSAGE_RECEIPT),
+        layout(
+            "ClaimHistoryEntry",
+            ClaimHistoryEntry::LEN,
+            CLAIM_HISTORY_ENTRY,
+        ),
+        layout("InboxPolicy", InboxPolicy::LEN, INBOX_POLICY),
+        layout("SessionKey", SessionKey::LEN, SESSION_KEY),
+        layout("Preauthorization", Preauthorization::LEN, PREAUTHORIZATION),
+        layout("AdminSet", AdminSet::LEN, ADMIN_SET),
+        layout("SweepCursor", SweepCursor::LEN, SWEEP_CURSOR),
+        layout("RateLimit", RateLimit::LEN, RATE_LIMIT),
+        layout("Promo", Promo::LEN, PROMO),
+        layout("FeeMint", FeeMint::LEN, FEE_MINT),
+        layout("RecipientInbox", RecipientInbox::LEN, RECIPIENT_INBOX),
+        layout("ClaimRentPayer", ClaimRentPayer::LEN, CLAIM_RENT_PAYER),
+        layout("Webhook", Webhook::LEN, WEBHOOK),
+        layout("ClosureLog", ClosureLog::LEN, CLOSURE_LOG),
+        layout("Sanctioned", Sanctioned::LEN, SANCTIONED),
+        layout("DelegateIndex", DelegateIndex::LEN, DELEGATE_INDEX),
+        layout("MailerStats", MailerStats::LEN, MAILER_STATS),
+        layout("SendCredits", SendCredits::LEN, SEND_CREDITS),
+        layout("Plan", Plan::LEN, PLAN),
+        layout("Subscription", Subscription::LEN, SUBSCRIPTION),
+        layout("ClaimBeneficiary", ClaimBeneficiary::LEN, CLAIM_BENEFICIARY),
+        layout(
+            "ClaimDistribution",
+            ClaimDistribution::LEN,
+            CLAIM_DISTRIBUTION,
+        ),
+        layout("Escrow", Escrow::LEN, ESCROW),
+        layout("MailingList", MailingList::LEN, MAILING_LIST),
+        layout("ListChunk", ListChunk::LEN, LIST_CHUNK),
+        layout("EvmMapping", EvmMapping::LEN, EVM_MAPPING),
+        layout("WormholeConfig", WormholeConfig::LEN, WORMHOLE_CONFIG),
+        layout("MessageNonce", MessageNonce::LEN, MESSAGE_NONCE),
+        layout("SendNonce", SendNonce::LEN, SEND_NONCE),
+        layout("DiscountSchedule", DiscountSchedule::LEN, DISCOUNT_SCHEDULE),
+        layout("SenderStats", SenderStats::LEN, SENDER_STATS),
+    ]
+}
@@ -16,7 +16,25 @@
 //! - Mailer state: `[b"mailer"]` (no version - global singleton)
 //! - Recipient claims: `[b"claim", &[1], recipient.key()]` (v1)
 //! - Delegations: `[b"delegation", &[1], delegator.key()]` (v1)
+//! - Delegate indexes: `[b"delegate_index", &[1], delegate.key()]` (v1)
 //! - Fee discounts: `[b"discount", &[1], account.key()]` (v1)
+//! - Recipient preferences: `[b"prefs", &[1], recipient.key()]` (v1)
+//! - Inbox policies: `[b"policy", &[1], recipient.key()]` (v1)
+//! - Message receipts: `[b"receipt", &[1], recipient.key(), nonce.to_le_bytes()]` (v1)
+//! - Claim history: `[b"history", &[1], recipient.key(), sender.key()]` (v1)
+//! - Send pre-authorizations: `[b"preauth", &[1], user.key(), relayer.key()]` (v1)
+//! - Admin set: `[b"admins", &[1]]` (v1 - global singleton)
+//! - Sweep cursor: `[b"sweep_cursor", &[1]]` (v1 - global singleton)
+//! - Stats: `[b"stats", &[1]]` (v1 - global singleton)
+//! - Send credits: `[b"send_credits", &[1], sender.key()]` (v1)
+//! - Subscription plans: `[b"plan", &[1], plan_id.to_le_bytes()]` (v1)
+//! - Subscriptions: `[b"subscription", &[1], subscriber.key()]` (v1)
+//! - Claim beneficiaries: `[b"beneficiary", &[1], recipient.key()]` (v1)
+//! - Devnet faucet drips: `[b"faucet", &[1], caller.key()]` (v1, `devnet-faucet` feature only)
+//!
+//! The [`pda`] module exposes every seed layout and derivation helper programmatically;
+//! `test-vectors/conformance.json` records the derived addresses and account layouts
+//! for client SDKs.
 //!
 //! ## Fee Structure
 //!
@@ -30,18 +48,23 @@ use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
     clock::Clock,
+    compute_units::sol_remaining_compute_units,
     entrypoint::ProgramResult,
+    hash::{hashv, Hash},
+    instruction::{AccountMeta, Instruction},
     msg,
-    program::{invoke, invoke_signed},
-    program_error::ProgramError,
-    program_pack::Pack,
+    program::{get_return_data, invoke, invoke_signed, set_return_data, MAX_RETURN_DATA},
+    program_error::{PrintProgramError, ProgramError},
+    program_option::COption,
     pubkey::Pubkey,
     rent::Rent,
-    system_instruction,
-    sysvar::Sysvar,
+    secp256k1_program, system_instruction,
+    sysvar::{self, Sysvar},
 };
-use spl_token::state::Account as TokenAccount;
-use thiserror::Error;
+use spl_token_2022::extension::StateWithExtensions;
+
+use pda::seeds;
+use state_versions::{RecipientClaimV1, VersionedMailerState, VersionedRecipientClaim};
 
 // Program ID for the Native Mailer program
 solana_program::declare_id!("9FLkBDGpZBcR8LMsQ7MwwV6X9P4TDFgN3DeRh5qYyHJF");
@@ -52,17 +75,199 @@ const SEND_FEE: u64 = 100_000;
 /// Delegation fee in USDC (with 6 decimals): 10 USDC
 const DELEGATION_FEE: u64 = 10_000_000;
 
+/// Decimals of USDC, assumed when Initialize is not given the mint account
+const USDC_DECIMALS: u8 = 6;
+
+/// Decimals of lamport fees (lamports per SOL)
+const LAMPORT_DECIMALS: u8 = 9;
+
 /// Claim period for revenue shares: 60 days in seconds
 const CLAIM_PERIOD: i64 = 60 * 24 * 60 * 60;
 
+/// Surcharge in USDC (with 6 decimals) for each period of no-expiry claim status: 0.05 USDC
+const NO_EXPIRY_SURCHARGE: u64 = 50_000;
+
+/// Period covered by one no-expiry surcharge: 30 days in seconds
+const NO_EXPIRY_PERIOD: i64 = 30 * 24 * 60 * 60;
+
+/// Fee in USDC (with 6 decimals) for each claim window extension: 0.02 USDC
+const CLAIM_EXTENSION_FEE: u64 = 20_000;
+
+/// Maximum number of claim window extensions between two recorded shares
+const MAX_CLAIM_EXTENSIONS: u8 = 2;
+
+/// Grace period after a claim expires in which its beneficiary may claim it: 30 days in seconds
+const BENEFICIARY_GRACE_PERIOD: i64 = 30 * 24 * 60 * 60;
+
+/// Time a recipient has to accept an escrowed message before its sender may reclaim the fee:
+/// 7 days in seconds
+const ESCROW_PERIOD: i64 = 7 * 24 * 60 * 60;
+
+/// Pause flag stopping sends
+pub const PAUSE_SEND: u8 = 1 << 0;
+
+/// Pause flag stopping delegation changes
+pub const PAUSE_DELEGATE: u8 = 1 << 1;
+
+/// Pause flag stopping claims and expired-share sweeps
+pub const PAUSE_CLAIM: u8 = 1 << 2;
+
+/// Pause flag stopping fee changes and account maintenance
+pub const PAUSE_ADMIN: u8 = 1 << 3;
+
+/// Subsystems stopped by `Pause`; claims stay open so recipients can withdraw
+pub const PAUSE_GLOBAL: u8 = PAUSE_SEND | PAUSE_DELEGATE | PAUSE_ADMIN;
+
+/// Maximum fee change timelock: 30 days in seconds
+const MAX_FEE_TIMELOCK: i64 = 30 * 24 * 60 * 60;
+
+/// Maximum delegation fee refund window: 30 days in seconds
+const MAX_DELEGATION_REFUND_WINDOW: i64 = 30 * 24 * 60 * 60;
+
+/// Maximum number of admins in the admin set
+const MAX_ADMINS: usize = 10;
+
+/// Maximum number of senders in an inbox policy list
+const MAX_POLICY_SENDERS: usize = 16;
+
+/// Maximum length of a sender memo in bytes
+const MAX_MEMO_LEN: usize = 64;
+
+/// Maximum lifetime of a recipient session key: 30 days in seconds
+const MAX_SESSION_DURATION: i64 = 30 * 24 * 60 * 60;
+
+/// Maximum length of a pre-authorization webhook id in bytes
+const MAX_WEBHOOK_ID_LEN: usize = 64;
+
+/// Shortest email address SendToEmail accepts, as in `a@b.co`
+const MIN_EMAIL_LEN: usize = 6;
+
+/// Longest email address SendToEmail accepts (the RFC 5321 path limit)
+const MAX_EMAIL_LEN: usize = 254;
+
+/// Maximum number of recipient claims in a `ClaimExpiredSharesBatch`
+const MAX_EXPIRED_BATCH: usize = 32;
+
+/// Maximum number of recipient claims summed by one `VerifySolvency`
+const MAX_SOLVENCY_BATCH: usize = 32;
+
+/// Maximum number of destinations in a claim split
+const MAX_CLAIM_SPLITS: usize = 4;
+
+/// Basis points in a whole claim split
+const CLAIM_SPLIT_TOTAL_BPS: u16 = 10_000;
+
+/// Number of records kept by the closure log before the oldest are overwritten
+const CLOSURE_LOG_CAPACITY: usize = 64;
+
+/// Number of idempotency nonces a sender's SendNonce account remembers
+const SEND_NONCE_CAPACITY: usize = 16;
+
+/// Maximum number of tiers in the volume discount schedule
+const MAX_DISCOUNT_TIERS: usize = 8;
+
+/// Maximum number of leaves in a claim distribution, one claimed bit each
+const MAX_DISTRIBUTION_LEAVES: usize = 32_768;
+
+/// Maximum number of members held by one chunk account of a mailing list
+const MAX_LIST_CHUNK_MEMBERS: usize = 32;
+
+/// Maximum compute unit limit of a transaction
+const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+/// Largest amount of the fee token a single `DevFaucet` call mints: 100 USDC
+#[cfg(feature = "devnet-faucet")]
+const DEV_FAUCET_MAX_AMOUNT: u64 = 100_000_000;
+
+/// Time a caller waits between `DevFaucet` calls: 1 hour in seconds
+#[cfg(feature = "devnet-faucet")]
+const DEV_FAUCET_COOLDOWN: i64 = 60 * 60;
+
+/// Associated Token Account program ID
+const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    solana_program::pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
 /// PDA version byte for forward compatibility
 /// Allows future upgrades to use different PDA structures without collision
-const PDA_VERSION: u8 = 1;
+pub const PDA_VERSION: u8 = 1;
+
+/// Layout version of the mailer state account written by this program
+pub const MAILER_STATE_VERSION: u8 = 4;
+
+// Program errors and their custom codes
+pub mod error;
+pub use error::MailerError;
+
+// PDA seeds and derivation helpers shared by the program and its clients
+pub mod pda;
+
+// Account discriminators, current and legacy
+pub mod discriminator;
+
+// Earlier mailer state layouts and their upgrades
+pub mod state_versions;
+
+// Legacy and versioned instruction data decoding
+pub mod instruction_versions;
+
+// In-place access to fixed-offset account fields
+pub mod fields;
+
+// Interface of owner-registered spam oracle programs
+pub mod spam_oracle;
+
+// Attestations issued by the owner-registered KYC verifier program
+pub mod attestation;
+
+// Pyth price decoding for oracle-pegged lamport fees
+pub mod price_oracle;
+
+// Encrypted message envelopes (sealing and opening with the `crypto` feature)
+pub mod crypto;
+
+// Merkle trees of claim distributions
+pub mod merkle;
+
+// Solana Name Service domain resolution for SendToDomain
+pub mod sns;
+
+// EVM address bindings proven with secp256k1 signatures
+pub mod evm;
+
+// Wormhole messages mirroring sends to the EVM deployments
+pub mod wormhole;
 
 // CPI module for cross-program invocations
 #[cfg(feature = "cpi")]
 pub mod cpi;
 
+// Rent cost helpers for every account type
+pub mod costs;
+
+// Reference log decoder for off-chain indexers
+#[cfg(feature = "indexer")]
+pub mod indexer;
+
+// C ABI serializing instructions for non-Rust signers
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+// Canonical serialization vectors for cross-language clients (off-chain only)
+#[cfg(not(target_os = "solana"))]
+pub mod test_vectors;
+
+// Field-level account layouts for client decoders (off-chain only)
+#[cfg(not(target_os = "solana"))]
+pub mod layout;
+
+// Seed and layout golden file shared with client SDKs (off-chain only)
+#[cfg(not(target_os = "solana"))]
+pub mod conformance;
+
+// Anchor-compatible JSON IDL for explorers and TypeScript tooling (off-chain only)
+#[cfg(all(feature = "idl", not(target_os = "solana")))]
+pub mod idl;
+
 #[cfg(not(feature = "no-entrypoint"))]
 solana_program::entrypoint!(process_instruction);
 
@@ -77,10 +282,300 @@ pub struct MailerState {
     pub paused: bool,
     pub fee_paused: bool,
     pub bump: u8,
+    pub protocol_sends_enabled: bool,
+    pub pending_owner: Option<Pubkey>,
+    pub fee_timelock: i64,
+    pub pending_fee: Option<PendingFeeChange>,
+    pub paused_at: i64,
+    pub total_paused_duration: i64,
+    /// Rent-exempt lamports held by recipient claim accounts
+    pub claim_rent_reserve: u64,
+    /// Rent-exempt lamports held by delegation accounts
+    pub delegation_rent_reserve: u64,
+    /// Rent-exempt lamports held by fee discount accounts
+    pub discount_rent_reserve: u64,
+    /// Bitmap of disabled instructions, indexed by `MailerInstruction` variant tag
+    pub disabled_instructions: u128,
+    /// Owner-registered spam oracle program consulted on sends to a wallet
+    pub spam_oracle: Option<Pubkey>,
+    /// Spam score above which sends are rejected; scores are only recorded when `None`
+    pub spam_threshold: Option<u64>,
+    /// Sends allowed per sender in each rate limit window; 0 disables rate limiting
+    pub rate_limit_max_sends: u64,
+    /// Length of a sender's rate limit window in seconds
+    pub rate_limit_window: i64,
+    /// Send fee in lamports for sends paying into the lamport vault; 0 disables lamport fees
+    pub lamport_send_fee: u64,
+    /// Lamport fees held in the lamport vault for the owner to claim
+    pub owner_claimable_lamports: u64,
+    /// Pyth SOL/USD price account pegging the lamport send fee; `None` uses `lamport_send_fee`
+    pub fee_oracle: Option<Pubkey>,
+    /// Pegged lamport send fee in micro-USD
+    pub fee_oracle_target: u64,
+    /// Maximum age in seconds of an oracle price used for fees
+    pub fee_oracle_max_age: i64,
+    /// Claim account creation on priority sends
+    pub claim_creation_policy: ClaimCreationPolicy,
+    /// Decimals of `usdc_mint`, read at Initialize and logged with fees for display
+    pub usdc_decimals: u8,
+    /// Whether sends and claims check the sanctions registry; deployments that never enable
+    /// it stay censorship-resistant
+    pub sanctions_enabled: bool,
+    /// Backup mint whose conversion vault honors claims while the default mint is disrupted;
+    /// `Some` puts the mailer in contingency mode
+    pub backup_mint: Option<Pubkey>,
+    /// Whether the MailerStats account exists, so sends and claims must pass it
+    pub stats_enabled: bool,
+    /// Subsystems paused with `SetPauseFlags`, independently of `paused` (`PAUSE_*` bits)
+    pub pause_flags: u8,
+    /// Verifier program whose attestations priority sends require; `None` leaves them open
+    pub kyc_verifier: Option<Pubkey>,
+    /// Seconds after a delegation within which a rejection makes its fee refundable;
+    /// 0 keeps delegation fees non-refundable
+    pub delegation_refund_window: i64,
+    /// Largest claimable balance a priority send may leave in a recipient claim; 0 for no cap
+    pub claim_cap: u64,
+    /// Maximum payload field lengths accepted by sends
+    pub payload_limits: PayloadLimits,
+    /// Payload bytes per fee tier; 0 charges the same fee for every payload length
+    pub payload_fee_tier_len: u16,
+    /// Percentage of the send fee added for each full fee tier of payload bytes
+    pub payload_fee_tier_percent: u16,
+    /// USDC recipient claim balances accrued since tracking began; with `owner_claimable`
+    /// these are the liabilities the mailer vault must cover
+    pub recipient_liabilities: u64,
+    /// Vault coverage of tracked liabilities, in basis points, below which priority sends
+    /// fail with `TemporarilyInsolvent`; 0 disables the check
+    pub min_coverage_bps: u16,
+    /// Layout version, `MAILER_STATE_VERSION`; fields added in later layouts follow it
+    pub version: u8,
+    /// Token account of the treasury PDA receiving the owner's share of USDC send fees;
+    /// None keeps that share in the mailer vault as `owner_claimable`
+    pub treasury: Option<Pubkey>,
+    /// Unspent USDC send credits deposited since tracking began, which the mailer vault
+    /// must cover alongside `owner_claimable` and `recipient_liabilities`
+    pub credit_liabilities: u64,
+}
+
+/// Proposed send fee awaiting its timelock
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PendingFeeChange {
+    pub new_fee: u64,
+    pub effective_at: i64,
+}
+
+/// Maximum payload field lengths in bytes; 0 leaves a field unlimited
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PayloadLimits {
+    pub max_subject_len: u16,
+    pub max_body_len: u16,
+    pub max_mail_id_len: u16,
+    pub max_email_len: u16,
+    pub max_webhook_id_len: u16,
 }
 
 impl MailerState {
-    pub const LEN: usize = 32 + 32 + 8 + 8 + 8 + 1 + 1 + 1; // 91 bytes
+    pub const LEN: usize = 32
+        + 32
+        + 8
+        + 8
+        + 8
+        + 1
+        + 1
+        + 1
+        + 1
+        + 33
+        + 8
+        + 17
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 16
+        + 33
+        + 9
+        + 8
+        + 8
+        + 8
+        + 8
+        + 33
+        + 8
+        + 8
+        + 1
+        + 1
+        + 1
+        + 33
+        + 1
+        + 1
+        + 33
+        + 8
+        + 8
+        + 10
+        + 2
+        + 2
+        + 8
+        + 2
+        + 1
+        + 33
+        + 8; // 482 bytes
+    /// Offset of `owner_claimable` within the account data, discriminator included
+    pub const OWNER_CLAIMABLE_OFFSET: usize = 8 + 32 + 32 + 8 + 8;
+    /// Offset of `bump` within the account data, discriminator included
+    pub const BUMP_OFFSET: usize = 8 + 32 + 32 + 8 + 8 + 8 + 1 + 1;
+
+    /// Whether the subsystem behind `flag` is paused, by `Pause` or by its pause flag
+    pub fn is_paused(&self, flag: u8) -> bool {
+        let global = if self.paused { PAUSE_GLOBAL } else { 0 };
+        (self.pause_flags | global) & flag != 0
+    }
+
+    /// Scale `fee` by the payload fee tiers: each full `payload_fee_tier_len` bytes of
+    /// `payload_len` adds `payload_fee_tier_percent` percent of the fee
+    pub fn payload_fee(&self, fee: u64, payload_len: usize) -> Result<u64, ProgramError> {
+        if self.payload_fee_tier_len == 0 {
+            return Ok(fee);
+        }
+        let tiers = (payload_len / self.payload_fee_tier_len as usize) as u128;
+        let surcharge = fee as u128 * tiers * self.payload_fee_tier_percent as u128 / 100;
+        u64::try_from(fee as u128 + surcharge).map_err(|_| MailerError::MathOverflow.into())
+    }
+
+    /// Whether claim windows are frozen: during a global pause or while claims are paused
+    fn claim_windows_frozen(&self) -> bool {
+        self.paused || self.pause_flags & PAUSE_CLAIM != 0
+    }
+
+    /// Change the global pause and pause flags at `now`
+    /// The current frozen interval is folded into the paused duration and a new one starts
+    /// if claim windows stay frozen, so overlapping pauses are never counted twice.
+    pub fn set_pause(&mut self, paused: bool, pause_flags: u8, now: i64) {
+        if self.claim_windows_frozen() {
+            self.end_pause(now);
+        }
+        self.paused = paused;
+        self.pause_flags = pause_flags;
+        if self.claim_windows_frozen() {
+            self.paused_at = now;
+        }
+    }
+
+    /// Cumulative paused duration at `now`, including an ongoing pause
+    pub fn paused_duration_at(&self, now: i64) -> i64 {
+        let ongoing = if self.claim_windows_frozen() && self.paused_at > 0 {
+            now.saturating_sub(self.paused_at)
+        } else {
+            0
+        };
+        self.total_paused_duration.saturating_add(ongoing)
+    }
+
+    /// Close the current pause interval, folding it into the cumulative paused duration
+    pub fn end_pause(&mut self, now: i64) {
+        self.total_paused_duration = self.paused_duration_at(now);
+        self.paused_at = 0;
+    }
+
+    /// Expiry time of a claim, extended by time paused since the claim was last refreshed
+    /// Pauses after the claim's timestamp always extend it, erring in the recipient's favour.
+    /// Claims with no-expiry status never expire.
+    pub fn claim_expiry(&self, claim: &RecipientClaim, now: i64) -> i64 {
+        if claim.no_expiry {
+            return i64::MAX;
+        }
+        let paused_since_claim = self
+            .paused_duration_at(now)
+            .saturating_sub(claim.paused_offset)
+            .max(0);
+        claim
+            .timestamp
+            .saturating_add(CLAIM_PERIOD)
+            .saturating_add(paused_since_claim)
+    }
+
+    /// Debit the no-expiry surcharge periods started by `now` from a claim's balance
+    /// Returns the amount debited, which the caller credits to the owner. A balance that
+    /// cannot cover every period lapses the status; the claim then starts a fresh claim window.
+    pub fn settle_no_expiry_surcharge(&self, claim: &mut RecipientClaim, now: i64) -> u64 {
+        if !claim.no_expiry || now < claim.surcharge_paid_until {
+            return 0;
+        }
+
+        let periods = (now.saturating_sub(claim.surcharge_paid_until) / NO_EXPIRY_PERIOD)
+            .saturating_add(1) as u64;
+        let covered = periods.min(claim.amount / NO_EXPIRY_SURCHARGE);
+        let debited = covered * NO_EXPIRY_SURCHARGE;
+        claim.amount -= debited;
+        claim.surcharge_paid_until = claim
+            .surcharge_paid_until
+            .saturating_add((covered as i64).saturating_mul(NO_EXPIRY_PERIOD));
+
+        if covered < periods {
+            claim.no_expiry = false;
+            claim.timestamp = now;
+            claim.paused_offset = self.paused_duration_at(now);
+        }
+        debited
+    }
+
+    /// Fees charged at `now`, as returned by GetFee
+    pub fn fee_view(&self, now: i64) -> Result<FeeView, ProgramError> {
+        let (priority_fee, delegation_fee) = if self.fee_paused {
+            (0, 0)
+        } else {
+            (self.effective_send_fee(now), self.delegation_fee)
+        };
+        Ok(FeeView {
+            priority_fee,
+            standard_fee: standard_fee(priority_fee)?,
+            delegation_fee,
+            fee_paused: self.fee_paused,
+        })
+    }
+
+    /// What a claim of `claim` would pay out at `now`, as returned by GetClaimable
+    pub fn claimable_view(&self, claim: &RecipientClaim, now: i64) -> ClaimableView {
+        let mut claim = claim.clone();
+        self.settle_no_expiry_surcharge(&mut claim, now);
+        let expires_at = self.claim_expiry(&claim, now);
+        ClaimableView {
+            amount: claim.amount,
+            expires_at,
+            expired: now > expires_at,
+        }
+    }
+
+    /// Send fee in force at `now`, including a pending change whose timelock has elapsed
+    pub fn effective_send_fee(&self, now: i64) -> u64 {
+        match self.pending_fee {
+            Some(pending) if now >= pending.effective_at => pending.new_fee,
+            _ => self.send_fee,
+        }
+    }
+
+    /// Fold a matured pending fee change into `send_fee`
+    pub fn apply_matured_fee(&mut self, now: i64) {
+        if let Some(pending) = self.pending_fee {
+            if now >= pending.effective_at {
+                self.send_fee = pending.new_fee;
+                self.pending_fee = None;
+            }
+        }
+    }
+
+    /// Whether the instruction with the given variant tag has been disabled by the owner
+    pub fn is_instruction_disabled(&self, tag: u8) -> bool {
+        tag < 128 && self.disabled_instructions & (1u128 << tag) != 0
+    }
+
+    /// Disable or re-enable the instruction with the given variant tag
+    pub fn set_instruction_disabled(&mut self, tag: u8, disabled: bool) {
+        if disabled {
+            self.disabled_instructions |= 1u128 << tag;
+        } else {
+            self.disabled_instructions &= !(1u128 << tag);
+        }
+    }
 
     pub fn increase_owner_claimable(&mut self, amount: u64) -> Result<(), ProgramError> {
         if amount == 0 {
@@ -94,32 +589,292 @@ impl MailerState {
 
         Ok(())
     }
+
+    /// Drop paid-out or forfeited USDC recipient balances from the tracked liabilities
+    /// Balances accrued before tracking began floor the aggregate at 0.
+    pub fn release_recipient_liability(&mut self, amount: u64) {
+        self.recipient_liabilities = self.recipient_liabilities.saturating_sub(amount);
+    }
+
+    /// Move `amount` of USDC recipient balances to the owner's claimable balance
+    pub fn move_claim_to_owner(&mut self, amount: u64) -> Result<(), ProgramError> {
+        self.release_recipient_liability(amount);
+        self.increase_owner_claimable(amount)
+    }
+
+    /// Drop spent or withdrawn send credits from the tracked liabilities
+    /// Credits deposited before tracking began floor the aggregate at 0.
+    pub fn release_credit_liability(&mut self, amount: u64) {
+        self.credit_liabilities = self.credit_liabilities.saturating_sub(amount);
+    }
+
+    /// Owner, recipient and send credit USDC balances the mailer vault must be able to pay out
+    pub fn tracked_liabilities(&self) -> u64 {
+        self.owner_claimable
+            .saturating_add(self.recipient_liabilities)
+            .saturating_add(self.credit_liabilities)
+    }
+
+    /// Whether a vault holding `vault_balance` covers `min_coverage_bps` of the tracked liabilities
+    pub fn is_solvent(&self, vault_balance: u64) -> bool {
+        vault_balance as u128 * 10_000
+            >= self.tracked_liabilities() as u128 * self.min_coverage_bps as u128
+    }
+
+    fn rent_reserve_mut(&mut self, reserve: RentReserve) -> &mut u64 {
+        match reserve {
+            RentReserve::Claims => &mut self.claim_rent_reserve,
+            RentReserve::Delegations => &mut self.delegation_rent_reserve,
+            RentReserve::Discounts => &mut self.discount_rent_reserve,
+        }
+    }
+
+    /// Record lamports locked in a newly created account of the given family
+    pub fn add_rent_reserve(&mut self, reserve: RentReserve, lamports: u64) {
+        let total = self.rent_reserve_mut(reserve);
+        *total = total.saturating_add(lamports);
+    }
+
+    /// Release lamports returned when an account of the given family is closed
+    pub fn release_rent_reserve(&mut self, reserve: RentReserve, lamports: u64) {
+        let total = self.rent_reserve_mut(reserve);
+        *total = total.saturating_sub(lamports);
+    }
+
+    /// Total rent-exempt lamports locked across all tracked account families
+    pub fn total_rent_reserve(&self) -> u64 {
+        self.claim_rent_reserve
+            .saturating_add(self.delegation_rent_reserve)
+            .saturating_add(self.discount_rent_reserve)
+    }
+}
+
+/// Compute units left when a send finished, opening the return data of every Send*
+/// instruction (see [`SendResult`])
+/// Clients that requested `limit` compute units for a send consumed `limit - remaining_units`
+/// and can size the ComputeBudget limit of later sends from that instead of a generous default.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComputeReport {
+    pub remaining_units: u64,
+}
+
+/// Return data of every Send* instruction
+/// Starts with the fields of [`ComputeReport`], so decoders of the compute report alone keep
+/// working.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SendResult {
+    pub remaining_units: u64,
+    /// Id of the message sent, see [`derive_message_id`]
+    pub message_id: [u8; 32],
+}
+
+impl SendResult {
+    pub fn compute_report(&self) -> ComputeReport {
+        ComputeReport {
+            remaining_units: self.remaining_units,
+        }
+    }
+}
+
+impl ComputeReport {
+    /// Compute units consumed by a send that requested `limit` units
+    pub fn consumed_units(&self, limit: u64) -> u64 {
+        limit.saturating_sub(self.remaining_units)
+    }
+
+    /// Compute unit limit for a similar send: consumption plus 10% headroom
+    pub fn recommended_limit(&self, limit: u64) -> u32 {
+        let consumed = self.consumed_units(limit);
+        let padded = consumed.saturating_add(consumed / 10);
+        padded.min(MAX_COMPUTE_UNIT_LIMIT as u64) as u32
+    }
+}
+
+/// Return data of GetFee: the USDC fees charged now, before per-sender discounts,
+/// subscriptions and inbox policies
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeView {
+    /// Fee of a priority send, including a pending change whose timelock has elapsed
+    pub priority_fee: u64,
+    /// Fee of a standard send: the 10% owner share of `priority_fee`
+    pub standard_fee: u64,
+    pub delegation_fee: u64,
+    /// Whether fees are paused, in which case every fee above is 0
+    pub fee_paused: bool,
+}
+
+/// Return data of GetClaimable
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClaimableView {
+    /// Recipient share a claim would pay out now, after any no-expiry surcharge due
+    pub amount: u64,
+    /// Time after which the share can no longer be claimed; `i64::MAX` for no-expiry claims
+    pub expires_at: i64,
+    pub expired: bool,
+}
+
+/// Return data of GetDelegation
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DelegationView {
+    pub delegate: Option<Pubkey>,
+    /// Delegation fee held until the delegate accepts or rejects
+    pub escrowed_fee: u64,
+    /// Time until which an escrowed fee is refundable; 0 when none is
+    pub refund_deadline: i64,
+}
+
+impl From<&Delegation> for DelegationView {
+    fn from(delegation: &Delegation) -> Self {
+        DelegationView {
+            delegate: delegation.delegate,
+            escrowed_fee: delegation.escrowed_fee,
+            refund_deadline: delegation.refund_deadline,
+        }
+    }
+}
+
+/// Decode the return data of a view instruction as read from a transaction or simulation
+/// The runtime strips trailing zero bytes from return data it reports, so the missing bytes
+/// are read as zero; programs reading it through `get_return_data` get it whole.
+pub fn decode_view<T: BorshDeserialize>(data: &[u8]) -> Result<T, ProgramError> {
+    use std::io::Read;
+    let mut reader = data.chain(std::io::repeat(0).take(MAX_RETURN_DATA as u64));
+    Ok(T::deserialize_reader(&mut reader)?)
+}
+
+/// Program-owned account families whose rent-exempt reserves are tracked in `MailerState`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RentReserve {
+    Claims,
+    Delegations,
+    Discounts,
+}
+
+/// Account families `MigrateAccountVersion` moves from previous-version PDA seeds
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationKind {
+    RecipientClaim,
+    Delegation,
+    FeeDiscount,
+}
+
+impl MigrationKind {
+    /// Seed prefix of the account family
+    pub fn seed(&self) -> &'static [u8] {
+        match self {
+            MigrationKind::RecipientClaim => seeds::CLAIM,
+            MigrationKind::Delegation => seeds::DELEGATION,
+            MigrationKind::FeeDiscount => seeds::DISCOUNT,
+        }
+    }
+
+    /// Account type name, as used for discriminators
+    pub fn account_name(&self) -> &'static str {
+        match self {
+            MigrationKind::RecipientClaim => "RecipientClaim",
+            MigrationKind::Delegation => "Delegation",
+            MigrationKind::FeeDiscount => "FeeDiscount",
+        }
+    }
+
+    /// Data length of the account, including the discriminator
+    pub fn space(&self) -> usize {
+        8 + match self {
+            MigrationKind::RecipientClaim => RecipientClaim::LEN,
+            MigrationKind::Delegation => Delegation::LEN,
+            MigrationKind::FeeDiscount => FeeDiscount::LEN,
+        }
+    }
+
+    fn rent_reserve(&self) -> RentReserve {
+        match self {
+            MigrationKind::RecipientClaim => RentReserve::Claims,
+            MigrationKind::Delegation => RentReserve::Delegations,
+            MigrationKind::FeeDiscount => RentReserve::Discounts,
+        }
+    }
 }
 
 /// Recipient claim account (optimized for smaller rent cost)
 /// Timestamp uses i64 for long-term compatibility with EVM implementation
+/// `paused_offset` snapshots the cumulative paused duration when the timestamp was set,
+/// so later pauses extend the claim window instead of burning it.
+/// `no_expiry` claims never expire to the owner; they prepay a surcharge from their balance
+/// for each 30-day period, covered through `surcharge_paid_until`.
+/// `extensions` counts ExtendClaimWindow calls since the last recorded share.
+/// `messages_received` counts priority messages that credited the claim and `total_claimed`
+/// the amount ever paid out to the recipient, for reputation displays. Claims created before
+/// these counters keep the [`RecipientClaimV1`] layout until their next priority send.
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
 pub struct RecipientClaim {
     pub recipient: Pubkey,
     pub amount: u64,
     pub timestamp: i64,
     pub bump: u8,
+    pub paused_offset: i64,
+    pub no_expiry: bool,
+    pub surcharge_paid_until: i64,
+    pub extensions: u8,
+    pub messages_received: u64,
+    pub total_claimed: u64,
 }
 
 impl RecipientClaim {
-    pub const LEN: usize = 32 + 8 + 8 + 1; // 49 bytes
+    pub const LEN: usize = 32 + 8 + 8 + 1 + 8 + 1 + 8 + 1 + 8 + 8; // 83 bytes
+    /// Offset of `amount` within the account data, discriminator included
+    pub const AMOUNT_OFFSET: usize = 8 + 32;
+    /// Offset of `timestamp` within the account data, discriminator included
+    pub const TIMESTAMP_OFFSET: usize = 8 + 32 + 8;
+    /// Offset of `bump` within the account data, discriminator included
+    pub const BUMP_OFFSET: usize = 8 + 32 + 8 + 8;
+    /// Offset of `paused_offset` within the account data, discriminator included
+    pub const PAUSED_OFFSET_OFFSET: usize = 8 + 32 + 8 + 8 + 1;
+    /// Offset of `no_expiry` within the account data, discriminator included
+    pub const NO_EXPIRY_OFFSET: usize = 8 + 32 + 8 + 8 + 1 + 8;
+    /// Offset of `extensions` within the account data, discriminator included
+    pub const EXTENSIONS_OFFSET: usize = 8 + 32 + 8 + 8 + 1 + 8 + 1 + 8;
+    /// Offset of `messages_received` within the account data, discriminator included;
+    /// absent from [`RecipientClaimV1`] accounts
+    pub const MESSAGES_RECEIVED_OFFSET: usize = 8 + 32 + 8 + 8 + 1 + 8 + 1 + 8 + 1;
+
+    /// Whether `data_len` is the size of a claim account in either layout
+    pub fn is_account_len(data_len: usize) -> bool {
+        data_len == 8 + Self::LEN || data_len == 8 + RecipientClaimV1::LEN
+    }
+
+    /// Decode claim account data following the discriminator in either layout
+    pub fn from_account_data(data: &[u8]) -> Result<Self, ProgramError> {
+        VersionedRecipientClaim::deserialize(data).map(VersionedRecipientClaim::into_current)
+    }
+
+    /// Encode into claim account data following the discriminator, in the layout of the
+    /// account; a [`RecipientClaimV1`] account keeps no counters
+    pub fn write_account_data(&self, data: &mut [u8]) -> ProgramResult {
+        if data.len() == RecipientClaimV1::LEN {
+            RecipientClaimV1::from(self).serialize(&mut &mut data[..])?;
+        } else {
+            self.serialize(&mut &mut data[..])?;
+        }
+        Ok(())
+    }
 }
 
 /// Delegation account
+/// With a delegation refund window configured, the USDC delegation fee is escrowed in
+/// `escrowed_fee` until the delegate accepts (crediting the owner) or rejects. A rejection
+/// by `refund_deadline` sets `fee_refundable` so the delegator can reclaim the fee.
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
 pub struct Delegation {
     pub delegator: Pubkey,
     pub delegate: Option<Pubkey>,
     pub bump: u8,
+    pub escrowed_fee: u64,
+    pub refund_deadline: i64,
+    pub fee_refundable: bool,
 }
 
 impl Delegation {
-    pub const LEN: usize = 32 + 1 + 32 + 1; // 66 bytes (max with Some(Pubkey))
+    pub const LEN: usize = 32 + 1 + 32 + 1 + 8 + 8 + 1; // 83 bytes (max with Some(Pubkey))
 }
 
 /// Fee discount account for custom fee percentages
@@ -134,1326 +889,13959 @@ pub struct FeeDiscount {
 
 impl FeeDiscount {
     pub const LEN: usize = 32 + 1 + 1; // 34 bytes
+    /// Offset of `bump` within the account data, discriminator included
+    pub const BUMP_OFFSET: usize = 8 + 32 + 1;
 }
 
-/// Instructions
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
-pub enum MailerInstruction {
-    /// Initialize the program
-    /// Accounts:
-    /// 0. `[writable, signer]` Owner account
-    /// 1. `[writable]` Mailer state account (PDA)
-    /// 2. `[]` System program
-    Initialize { usdc_mint: Pubkey },
+/// Recipient preferences account
+/// Holds recipient-controlled messaging settings. Read receipts are allowed by default;
+/// a recipient who opts out globally will never emit receipts back to senders.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct RecipientPrefs {
+    pub recipient: Pubkey,
+    pub read_receipts_opt_out: bool,
+    pub bump: u8,
+}
 
-    /// Send message with optional revenue sharing
-    /// SOFT-FAIL BEHAVIOR: Does not revert on fee payment failure. No log message emitted if payment fails.
-    /// This design allows composability - calling programs won't fail if message sending fails.
-    /// Monitor program logs: if transaction succeeds but no log appears, message was dropped due to fee failure.
-    /// Accounts:
-    /// 0. `[signer]` Sender
-    /// 1. `[writable]` Recipient claim account (PDA)
-    /// 2. `[]` Mailer state account (PDA)
-    /// 3. `[writable]` Sender USDC account
-    /// 4. `[writable]` Mailer USDC account
-    /// 5. `[]` Token program
-    /// 6. `[]` System program
-    Send {
-        to: Pubkey,
-        subject: String,
-        _body: String,
-        revenue_share_to_receiver: bool,
-        resolve_sender_to_name: bool,
-    },
+impl RecipientPrefs {
+    pub const LEN: usize = 32 + 1 + 1; // 34 bytes
+}
 
-    /// Send prepared message with optional revenue sharing (references off-chain content via mailId)
-    /// SOFT-FAIL BEHAVIOR: Does not revert on fee payment failure. See Send instruction for details.
-    /// Accounts:
-    /// 0. `[signer]` Sender
-    /// 1. `[writable]` Recipient claim account (PDA)
-    /// 2. `[]` Mailer state account (PDA)
-    /// 3. `[writable]` Sender USDC account
-    /// 4. `[writable]` Mailer USDC account
-    /// 5. `[]` Token program
-    /// 6. `[]` System program
-    SendPrepared {
-        to: Pubkey,
-        mail_id: String,
-        revenue_share_to_receiver: bool,
-        resolve_sender_to_name: bool,
-    },
+/// Recipient payout configuration
+/// When set, recipient share payouts (ClaimRecipientShare, DistributeClaimableFunds) must go
+/// to `payout_token_account`, e.g. a cold-storage wallet's USDC account.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct PayoutConfig {
+    pub recipient: Pubkey,
+    pub payout_token_account: Option<Pubkey>,
+    pub bump: u8,
+}
 
-    /// Send message to email address (no wallet address known)
-    /// Charges only 10% owner fee since recipient wallet is unknown
-    /// SOFT-FAIL BEHAVIOR: Does not revert on fee payment failure. See Send instruction for details.
-    /// Accounts:
-    /// 0. `[signer]` Sender
-    /// 1. `[]` Mailer state account (PDA)
-    /// 2. `[writable]` Sender USDC account
-    /// 3. `[writable]` Mailer USDC account
-    /// 4. `[]` Token program
-    SendToEmail {
-        to_email: String,
-        subject: String,
-        _body: String,
-    },
+impl PayoutConfig {
+    pub const LEN: usize = 32 + 33 + 1; // 66 bytes
+}
 
-    /// Send prepared message to email address (no wallet address known)
-    /// Charges only 10% owner fee since recipient wallet is unknown
-    /// SOFT-FAIL BEHAVIOR: Does not revert on fee payment failure. See Send instruction for details.
-    /// Accounts:
-    /// 0. `[signer]` Sender
-    /// 1. `[]` Mailer state account (PDA)
-    /// 2. `[writable]` Sender USDC account
-    /// 3. `[writable]` Mailer USDC account
-    /// 4. `[]` Token program
-    SendPreparedToEmail { to_email: String, mail_id: String },
+/// Share of a `ClaimRecipientShare` payout sent to `destination`, in basis points
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClaimSplit {
+    pub destination: Pubkey,
+    pub bps: u16,
+}
 
-    /// Send message through webhook (referenced by webhookId)
-    /// SOFT-FAIL BEHAVIOR: Does not revert on fee payment failure. See Send instruction for details.
+/// Owner-funded conversion vault for paying recipient shares in an alternate mint
+/// `vault` is a mailer-owned token account of `mint`. Conversions pay 1:1 from it and
+/// credit the equivalent default-mint amount to the owner, so the owner recovers the
+/// liquidity they provided. `total_converted` tracks the lifetime volume.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct ConversionVault {
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    pub enabled: bool,
+    pub total_converted: u64,
+    pub bump: u8,
+}
+
+impl ConversionVault {
+    pub const LEN: usize = 32 + 32 + 1 + 8 + 1; // 74 bytes
+}
+
+/// On-chain message receipt proving delivery, optionally marked read by the recipient
+/// `read_at` is 0 until the recipient acknowledges the message.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct MessageReceipt {
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub nonce: u64,
+    pub sent_at: i64,
+    pub read_at: i64,
+    pub bump: u8,
+}
+
+impl MessageReceipt {
+    pub const LEN: usize = 32 + 32 + 8 + 8 + 8 + 1; // 89 bytes
+}
+
+/// Per-sender claim history entry for a recipient
+/// Tracks revenue shares accrued from one sender together with the sender's latest memo
+/// (e.g. an invoice ID) so recipients can attribute claimed income from chain data.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct ClaimHistoryEntry {
+    pub recipient: Pubkey,
+    pub sender: Pubkey,
+    pub total_amount: u64,
+    pub last_amount: u64,
+    pub last_memo: String,
+    pub last_timestamp: i64,
+    pub bump: u8,
+}
+
+impl ClaimHistoryEntry {
+    pub const LEN: usize = 32 + 32 + 8 + 8 + 4 + MAX_MEMO_LEN + 8 + 1; // 157 bytes (max with 64-byte memo)
+}
+
+/// When priority sends create the recipient claim account, and who bears its rent when the
+/// fee then soft-fails
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClaimCreationPolicy {
+    /// Create the claim before the fee transfer; its rent stays with the claim
+    Eager,
+    /// Create the claim only once the fee has arrived
+    AfterTransfer,
+    /// Create the claim before the fee transfer; when the fee soft-fails, record the rent
+    /// payer so they can close the empty claim with `CloseFundedClaim`
+    RefundPayer,
+}
+
+/// Inbox filtering mode for a recipient
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InboxMode {
+    /// Accept mail from any sender
+    Open,
+    /// Accept mail only from listed senders
+    Allowlist,
+    /// Accept mail from anyone except listed senders
+    Blocklist,
+}
+
+/// Recipient-owned inbox policy account
+/// Disallowed senders are rejected when `penalty_multiplier` is 0, otherwise they
+/// pay the effective fee multiplied by `penalty_multiplier`. Priority sends paying the
+/// recipient less than `min_fee` are rejected.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct InboxPolicy {
+    pub recipient: Pubkey,
+    pub mode: InboxMode,
+    pub senders: Vec<Pubkey>,
+    pub penalty_multiplier: u8,
+    pub bump: u8,
+    pub min_fee: u64,
+}
+
+impl InboxPolicy {
+    pub const LEN: usize = Self::V1_LEN + 8; // 559 bytes (max with 16 senders)
+    /// Size of policies created before `min_fee`, which decode with no minimum
+    pub const V1_LEN: usize = 32 + 1 + 4 + 32 * MAX_POLICY_SENDERS + 1 + 1; // 551 bytes
+
+    /// Offset of `bump` within account data, discriminator included; it follows the senders
+    pub fn bump_offset(data: &[u8]) -> Option<usize> {
+        let len = u32::from_le_bytes(data.get(8 + 32 + 1..8 + 32 + 1 + 4)?.try_into().ok()?);
+        Some(8 + 32 + 1 + 4 + 32 * len as usize + 1)
+    }
+
+    /// Decode policy account data following the discriminator in either layout
+    pub fn from_account_data(data: &[u8]) -> Result<Self, ProgramError> {
+        let mut padded = data.to_vec();
+        padded.resize(data.len().max(Self::LEN), 0);
+        Ok(Self::deserialize(&mut &padded[..])?)
+    }
+
+    /// Whether `sender` passes this policy
+    pub fn allows(&self, sender: &Pubkey) -> bool {
+        match self.mode {
+            InboxMode::Open => true,
+            InboxMode::Allowlist => self.senders.contains(sender),
+            InboxMode::Blocklist => !self.senders.contains(sender),
+        }
+    }
+}
+
+/// Recipient-registered session key account
+/// A session key may sign preference-mutation instructions on the recipient's behalf,
+/// limited to the `scope` bits granted and only until `expires_at`. It can never claim
+/// funds or manage delegations.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct SessionKey {
+    pub recipient: Pubkey,
+    pub session_key: Pubkey,
+    pub scope: u8,
+    pub expires_at: i64,
+    pub bump: u8,
+}
+
+impl SessionKey {
+    pub const LEN: usize = 32 + 32 + 1 + 8 + 1; // 74 bytes
+
+    /// May update recipient preferences (SetRecipientPrefs)
+    pub const SCOPE_PREFS: u8 = 1 << 0;
+    /// May update the inbox policy (SetInboxPolicy)
+    pub const SCOPE_INBOX_POLICY: u8 = 1 << 1;
+    /// All grantable scopes
+    pub const SCOPE_ALL: u8 = Self::SCOPE_PREFS | Self::SCOPE_INBOX_POLICY;
+}
+
+/// User-created send pre-authorization account
+/// Lets `relayer` (a relayer key or a calling program's PDA) send mail on the user's
+/// behalf with SendPreauthorized, paying from the user's USDC account through the token
+/// delegation approved to this PDA. Sends are limited to `max_sends`, each costing at
+/// most `max_fee_per_send`, until `expires_at`; a non-empty `webhook_id` scopes them to
+/// that webhook.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct Preauthorization {
+    pub user: Pubkey,
+    pub relayer: Pubkey,
+    pub webhook_id: String,
+    pub max_sends: u64,
+    pub sends_used: u64,
+    pub max_fee_per_send: u64,
+    pub expires_at: i64,
+    pub bump: u8,
+}
+
+impl Preauthorization {
+    pub const LEN: usize = 32 + 32 + 4 + MAX_WEBHOOK_ID_LEN + 8 + 8 + 8 + 8 + 1; // 165 bytes (max webhook id)
+
+    /// Whether the pre-authorization still allows a send at `current_time`
+    pub fn is_active(&self, current_time: i64) -> bool {
+        self.sends_used < self.max_sends && current_time < self.expires_at
+    }
+}
+
+/// Admin set account for threshold administration
+/// Owner-only instructions accept either the legacy owner signature or
+/// signatures from at least `threshold` distinct admins listed here.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct AdminSet {
+    pub admins: Vec<Pubkey>,
+    pub threshold: u8,
+    pub bump: u8,
+}
+
+impl AdminSet {
+    pub const LEN: usize = 4 + 32 * MAX_ADMINS + 1 + 1; // 326 bytes (max with 10 admins)
+}
+
+/// Sweep cursor account for paginated expired-claim sweeps
+/// Off-chain crankers supply claim PDAs in ascending key order; the cursor records the
+/// last processed key so successive pages resume where the previous one stopped.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct SweepCursor {
+    pub last_processed: Pubkey,
+    pub processed_total: u64,
+    pub swept_total: u64,
+    pub bump: u8,
+}
+
+impl SweepCursor {
+    pub const LEN: usize = 32 + 8 + 8 + 1; // 49 bytes
+}
+
+/// Per-sender rate limit account, created on the sender's first send while rate limiting
+/// is enabled
+/// Counts the sends made since `window_start`; once `rate_limit_window` seconds have
+/// passed the next send starts a new window.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct RateLimit {
+    pub sender: Pubkey,
+    pub window_start: i64,
+    pub count: u64,
+    pub bump: u8,
+}
+
+impl RateLimit {
+    pub const LEN: usize = 32 + 8 + 8 + 1; // 49 bytes
+
+    /// Whether a send at `now` would start a new window
+    pub fn window_elapsed(&self, window: i64, now: i64) -> bool {
+        now >= self.window_start.saturating_add(window)
+    }
+}
+
+/// Owner-created promo code account, addressed by the SHA-256 hash of the code
+/// Each RedeemPromo before `expiry` grants the redeeming user a fee discount of `discount`
+/// percent and uses up one of the `remaining_redemptions`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct Promo {
+    pub code_hash: [u8; 32],
+    pub discount: u8,
+    pub remaining_redemptions: u64,
+    pub expiry: i64,
+    pub bump: u8,
+}
+
+impl Promo {
+    pub const LEN: usize = 32 + 1 + 8 + 8 + 1; // 50 bytes
+
+    /// Whether the code can still be redeemed at `current_time`
+    pub fn is_redeemable(&self, current_time: i64) -> bool {
+        self.remaining_redemptions > 0 && current_time < self.expiry
+    }
+}
+
+/// Owner-whitelisted fee mint besides USDC
+/// Send and DelegateTo charge these fees instead of the USDC fees when the payer's token
+/// account is of `mint`, paying into the mailer's token account for that mint. The owner's
+/// share accrues here; recipient shares accrue in the recipient's claim account for the mint.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct FeeMint {
+    pub mint: Pubkey,
+    pub send_fee: u64,
+    pub delegation_fee: u64,
+    pub owner_claimable: u64,
+    pub bump: u8,
+}
+
+impl FeeMint {
+    pub const LEN: usize = 32 + 8 + 8 + 8 + 1; // 57 bytes
+    /// Offset of `owner_claimable` within the account data, discriminator included
+    pub const OWNER_CLAIMABLE_OFFSET: usize = 8 + 32 + 8 + 8;
+}
+
+/// Per-recipient inbox account numbering the sends made to a wallet
+/// Created by the first send that passes it; every send passing it takes the next
+/// `sequence` and logs it, so clients can page through an inbox and spot gaps.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct RecipientInbox {
+    pub recipient: Pubkey,
+    /// Sequence number of the latest send; the first send is 1
+    pub sequence: u64,
+    pub bump: u8,
+}
+
+impl RecipientInbox {
+    pub const LEN: usize = 32 + 8 + 1; // 41 bytes
+    /// Offset of `bump` within the account data, discriminator included
+    pub const BUMP_OFFSET: usize = 8 + 32 + 8;
+}
+
+/// Payer of an empty recipient claim's rent, recorded under the RefundPayer policy when the
+/// priority send that created the claim failed to pay its fee
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct ClaimRentPayer {
+    pub claim: Pubkey,
+    pub payer: Pubkey,
+    pub bump: u8,
+}
+
+impl ClaimRentPayer {
+    pub const LEN: usize = 32 + 32 + 1; // 65 bytes
+}
+
+/// Registered webhook account, addressed by the SHA-256 hash of the webhook id
+/// The first RegisterWebhook of an id makes its signer the `owner`; SendThroughWebhook only
+/// accepts the id from its owner while the webhook is `active`. The endpoint itself stays
+/// off-chain, committed to by `endpoint_hash`. Sends through the webhook pay `surcharge`
+/// USDC on top of the send fee, accruing to the owner as `claimable`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct Webhook {
+    pub owner: Pubkey,
+    pub id_hash: [u8; 32],
+    pub endpoint_hash: [u8; 32],
+    pub active: bool,
+    pub surcharge: u64,
+    pub claimable: u64,
+    pub bump: u8,
+    /// Sends routed through the webhook by SendThroughWebhook, dropped ones included
+    pub sends_routed: u64,
+    /// Delivery statuses recorded with RecordDeliveryStatus
+    pub statuses_recorded: u64,
+    /// Recorded statuses reporting a failed delivery
+    pub deliveries_failed: u64,
+    /// Slots between each send and its recorded status, summed over all statuses
+    pub total_ack_latency_slots: u64,
+}
+
+impl Webhook {
+    pub const LEN: usize = 32 + 32 + 32 + 1 + 8 + 8 + 1 + 8 + 8 + 8 + 8; // 146 bytes
+
+    /// SLA counters of the webhook, as returned by GetWebhookStats
+    pub fn stats(&self) -> WebhookStats {
+        WebhookStats {
+            sends_routed: self.sends_routed,
+            statuses_recorded: self.statuses_recorded,
+            deliveries_failed: self.deliveries_failed,
+            average_ack_latency_slots: self
+                .total_ack_latency_slots
+                .checked_div(self.statuses_recorded)
+                .unwrap_or(0),
+        }
+    }
+}
+
+/// Return data of GetWebhookStats
+/// `average_ack_latency_slots` is 0 until a delivery status has been recorded.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WebhookStats {
+    pub sends_routed: u64,
+    pub statuses_recorded: u64,
+    pub deliveries_failed: u64,
+    pub average_ack_latency_slots: u64,
+}
+
+/// Return data of VerifySolvency
+/// `delta` is the vault balance less the owner's claimable balance, the unspent send credits
+/// and the larger of the tracked recipient liabilities and the batch's claims total; it is
+/// negative when the vault is short.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SolvencyReport {
+    pub vault_balance: u64,
+    pub owner_claimable: u64,
+    pub recipient_liabilities: u64,
+    pub credit_liabilities: u64,
+    pub claims_checked: u64,
+    pub claims_total: u64,
+    pub delta: i128,
+}
+
+/// Sanctions registry entry: sends from and claims by `address` are refused while the
+/// account exists and sanctions are enabled in the mailer state
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct Sanctioned {
+    pub address: Pubkey,
+    pub sanctioned_at: i64,
+    pub bump: u8,
+}
+
+impl Sanctioned {
+    pub const LEN: usize = 32 + 8 + 1; // 41 bytes
+}
+
+/// Final state commitment of a closed account
+/// `state_hash` is the SHA-256 of the account data, discriminator included, as it was just
+/// before the account was closed.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClosureRecord {
+    pub account: Pubkey,
+    pub state_hash: [u8; 32],
+    pub closed_at: i64,
+}
+
+impl ClosureRecord {
+    pub const LEN: usize = 32 + 32 + 8; // 72 bytes
+}
+
+/// Global ring buffer of the final states of closed claim and delegation accounts
+/// Records are appended in closing order until `CLOSURE_LOG_CAPACITY` is reached; after
+/// that record `total % CLOSURE_LOG_CAPACITY` is overwritten, so the log always holds the
+/// most recent closures while `total` counts every closure ever recorded.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct ClosureLog {
+    pub total: u64,
+    pub records: Vec<ClosureRecord>,
+    pub bump: u8,
+}
+
+impl ClosureLog {
+    pub const LEN: usize = 8 + 4 + ClosureRecord::LEN * CLOSURE_LOG_CAPACITY + 1; // 4621 bytes (max with 64 records)
+}
+
+/// Reverse index of the delegators currently delegating to `delegate`
+/// `accumulator` is the XOR of `sha256(delegator)` over those delegators, so a client holding
+/// a candidate set can verify it against `count` and `accumulator` without scanning every
+/// Delegation PDA, and any change to the set changes the accumulator.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct DelegateIndex {
+    pub delegate: Pubkey,
+    pub count: u64,
+    pub accumulator: [u8; 32],
+    pub bump: u8,
+}
+
+impl DelegateIndex {
+    pub const LEN: usize = 32 + 8 + 32 + 1; // 73 bytes
+
+    /// Accumulator contribution of a single delegator
+    pub fn delegator_hash(delegator: &Pubkey) -> [u8; 32] {
+        hashv(&[delegator.as_ref()]).to_bytes()
+    }
+
+    /// Accumulator of a full delegator set, for comparing against `accumulator`
+    pub fn accumulate<'a>(delegators: impl IntoIterator<Item = &'a Pubkey>) -> [u8; 32] {
+        let mut accumulator = [0u8; 32];
+        for delegator in delegators {
+            xor_into(&mut accumulator, &Self::delegator_hash(delegator));
+        }
+        accumulator
+    }
+
+    pub fn insert(&mut self, delegator: &Pubkey) {
+        xor_into(&mut self.accumulator, &Self::delegator_hash(delegator));
+        self.count = self.count.saturating_add(1);
+    }
+
+    pub fn remove(&mut self, delegator: &Pubkey) {
+        xor_into(&mut self.accumulator, &Self::delegator_hash(delegator));
+        self.count = self.count.saturating_sub(1);
+    }
+}
+
+fn xor_into(accumulator: &mut [u8; 32], hash: &[u8; 32]) {
+    for (byte, other) in accumulator.iter_mut().zip(hash) {
+        *byte ^= other;
+    }
+}
+
+/// Prepaid USDC balance a sender's fees are paid from before falling back to token transfers
+/// Deposits sit in the mailer vault, so spending credits needs no token CPI.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct SendCredits {
+    pub sender: Pubkey,
+    pub balance: u64,
+    pub bump: u8,
+}
+
+impl SendCredits {
+    pub const LEN: usize = 32 + 8 + 1; // 41 bytes
+    /// Offset of `bump` within the account data, discriminator included
+    pub const BUMP_OFFSET: usize = 8 + 32 + 8;
+}
+
+/// Subscription plan defined by the owner: `price` in USDC buys `duration` seconds of sends
+/// without per-message fees, up to `max_messages` of them
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct Plan {
+    pub plan_id: u64,
+    pub price: u64,
+    pub duration: i64,
+    pub max_messages: u64,
+    /// Whether new subscriptions and renewals are accepted
+    pub active: bool,
+    pub bump: u8,
+}
+
+impl Plan {
+    pub const LEN: usize = 8 + 8 + 8 + 8 + 1 + 1; // 34 bytes
+}
+
+/// A subscriber's current plan period
+/// Sends passing this account skip per-message fees until `expires_at` or until
+/// `messages_remaining` runs out, whichever comes first.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct Subscription {
+    pub subscriber: Pubkey,
+    pub plan_id: u64,
+    pub expires_at: i64,
+    pub messages_remaining: u64,
+    pub bump: u8,
+}
+
+impl Subscription {
+    pub const LEN: usize = 32 + 8 + 8 + 8 + 1; // 57 bytes
+    /// Offset of `bump` within the account data, discriminator included
+    pub const BUMP_OFFSET: usize = 8 + 32 + 8 + 8 + 8;
+
+    /// Whether the subscription covers a send at `now`
+    pub fn is_active(&self, now: i64) -> bool {
+        now < self.expires_at && self.messages_remaining > 0
+    }
+}
+
+/// Backup beneficiary of a recipient's USDC claim
+/// Once the claim window expires, `beneficiary` may collect the balance with
+/// ClaimAsBeneficiary for a further grace period; only then can the owner sweep it.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct ClaimBeneficiary {
+    pub recipient: Pubkey,
+    pub beneficiary: Pubkey,
+    pub bump: u8,
+}
+
+impl ClaimBeneficiary {
+    pub const LEN: usize = 32 + 32 + 1; // 65 bytes
+}
+
+/// Owner-funded airdrop of claimable USDC shares, committed to by a merkle root
+/// Leaf `i` of the tree credits an amount to a recipient's claim (see [`merkle::leaf`]);
+/// bit `i` of `claimed` is set once the leaf is claimed, so each leaf pays out once, and
+/// `remaining` is the funded USDC not yet credited to claims.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct ClaimDistribution {
+    pub distribution_id: u64,
+    pub merkle_root: [u8; 32],
+    pub leaf_count: u64,
+    pub total: u64,
+    pub remaining: u64,
+    pub bump: u8,
+    pub claimed: Vec<u8>,
+}
+
+impl ClaimDistribution {
+    pub const LEN: usize = 8 + 32 + 8 + 8 + 8 + 1 + 4 + MAX_DISTRIBUTION_LEAVES / 8; // 4165 bytes (max with 32,768 leaves)
+
+    /// Whether leaf `index` has been claimed
+    pub fn is_claimed(&self, index: u64) -> bool {
+        self.claimed
+            .get((index / 8) as usize)
+            .is_some_and(|byte| byte & (1 << (index % 8)) != 0)
+    }
+}
+
+/// Fee of a message sent with `SendEscrowed`, held in the mailer vault until the recipient
+/// accepts the message or, once `expires_at` has passed, its sender reclaims the fee
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct Escrow {
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub nonce: u64,
+    pub amount: u64,
+    pub created_at: i64,
+    pub expires_at: i64,
+    pub bump: u8,
+}
+
+impl Escrow {
+    pub const LEN: usize = 32 + 32 + 8 + 8 + 8 + 8 + 1; // 97 bytes
+}
+
+/// Broadcast list `list_id` of `owner`, its members held in `chunk_count` [`ListChunk`]
+/// accounts numbered from 0
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct MailingList {
+    pub owner: Pubkey,
+    pub list_id: u64,
+    pub member_count: u64,
+    pub chunk_count: u64,
+    pub bump: u8,
+}
+
+impl MailingList {
+    pub const LEN: usize = 32 + 8 + 8 + 8 + 1; // 57 bytes
+}
+
+/// Up to 32 members of a mailing list; `SendToList` logs one event per chunk so delivery
+/// can fan out over the chunk accounts
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct ListChunk {
+    pub list: Pubkey,
+    pub index: u64,
+    pub bump: u8,
+    pub members: Vec<Pubkey>,
+}
+
+impl ListChunk {
+    pub const LEN: usize = 32 + 8 + 1 + 4 + 32 * MAX_LIST_CHUNK_MEMBERS; // 1069 bytes (max with 32 members)
+}
+
+/// Solana wallet an EVM address is bound to, proven by a signature of the EVM key
+/// (see [`evm`]); sends to the EVM address are routed to `wallet`
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct EvmMapping {
+    pub evm_address: [u8; 20],
+    pub wallet: Pubkey,
+    pub bound_at: i64,
+    pub bump: u8,
+}
+
+impl EvmMapping {
+    pub const LEN: usize = 20 + 32 + 8 + 1; // 61 bytes
+}
+
+/// Wormhole core bridge that bridged sends are posted through (see [`wormhole`])
+/// `core_bridge` is the default pubkey while bridging is switched off; `message_count`
+/// numbers the message accounts and keeps counting across reconfigurations.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct WormholeConfig {
+    pub core_bridge: Pubkey,
+    pub bridge: Pubkey,
+    pub fee_collector: Pubkey,
+    pub sequence: Pubkey,
+    pub emitter_bump: u8,
+    pub message_count: u64,
+    pub bump: u8,
+}
+
+impl WormholeConfig {
+    pub const LEN: usize = 32 + 32 + 32 + 32 + 1 + 8 + 1; // 138 bytes
+}
+
+/// Per-sender account numbering the messages a wallet sends
+/// Created by the sender's first send; every send takes the next `nonce`,
+/// which goes into the send's message id so a sender's ids never repeat.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct MessageNonce {
+    pub sender: Pubkey,
+    /// Nonce of the latest send; the first send is 1
+    pub nonce: u64,
+    pub bump: u8,
+}
+
+impl MessageNonce {
+    pub const LEN: usize = 32 + 8 + 1; // 41 bytes
+    /// Offset of `bump` within the account data, discriminator included
+    pub const BUMP_OFFSET: usize = 8 + 32 + 8;
+}
+
+/// Per-sender ring buffer of the idempotency nonces of a wallet's latest sends
+/// Nonces are appended until `SEND_NONCE_CAPACITY` is reached; after that nonce
+/// `total % SEND_NONCE_CAPACITY` is overwritten, so a retried send is recognized while its
+/// nonce is among the sender's last `SEND_NONCE_CAPACITY`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct SendNonce {
+    pub sender: Pubkey,
+    pub total: u64,
+    pub nonces: Vec<u64>,
+    pub bump: u8,
+}
+
+impl SendNonce {
+    pub const LEN: usize = 32 + 8 + 4 + 8 * SEND_NONCE_CAPACITY + 1; // 173 bytes (max with 16 nonces)
+
+    /// Offset of `bump` within account data, discriminator included; it follows the nonces
+    pub fn bump_offset(data: &[u8]) -> Option<usize> {
+        let len = u32::from_le_bytes(data.get(8 + 32 + 8..8 + 32 + 8 + 4)?.try_into().ok()?);
+        Some(8 + 32 + 8 + 4 + 8 * len as usize)
+    }
+}
+
+/// Volume discount reached once the owner has kept `min_fees_paid` of a sender's USDC fees
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VolumeTier {
+    pub min_fees_paid: u64,
+    /// Percentage off the fee, as in [`FeeDiscount`]
+    pub discount: u8,
+}
+
+impl VolumeTier {
+    pub const LEN: usize = 8 + 1; // 9 bytes
+}
+
+/// Owner-configured volume discount tiers, ascending in `min_fees_paid` and `discount`
+/// The bump comes first so the account is recognized by its stored bump ahead of the
+/// variable-length tiers.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct DiscountSchedule {
+    pub bump: u8,
+    pub tiers: Vec<VolumeTier>,
+}
+
+impl DiscountSchedule {
+    pub const LEN: usize = 1 + 4 + VolumeTier::LEN * MAX_DISCOUNT_TIERS; // 77 bytes (max with 8 tiers)
+    pub const BUMP_OFFSET: usize = 8;
+
+    /// Discount of the highest tier `fees_paid` reaches, 0 below the first tier
+    pub fn discount(&self, fees_paid: u64) -> u8 {
+        self.tiers
+            .iter()
+            .rev()
+            .find(|tier| fees_paid >= tier.min_fees_paid)
+            .map_or(0, |tier| tier.discount)
+    }
+}
+
+/// Owner share of the USDC fees a sender has paid, counted by sends passing the discount
+/// schedule
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct SenderStats {
+    pub sender: Pubkey,
+    pub fees_paid: u64,
+    pub bump: u8,
+}
+
+impl SenderStats {
+    pub const LEN: usize = 32 + 8 + 1; // 41 bytes
+}
+
+/// Last devnet faucet drip of a caller, created on the caller's first `DevFaucet` call
+/// Only compiled with the `devnet-faucet` feature, so it is absent from the account layouts
+/// and IDL published for mainnet.
+#[cfg(feature = "devnet-faucet")]
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct FaucetDrip {
+    pub caller: Pubkey,
+    pub last_drip_at: i64,
+    pub bump: u8,
+}
+
+#[cfg(feature = "devnet-faucet")]
+impl FaucetDrip {
+    pub const LEN: usize = 32 + 8 + 1; // 41 bytes
+
+    /// Whether the caller may drip again at `now`
+    pub fn cooled_down(&self, now: i64) -> bool {
+        now >= self.last_drip_at.saturating_add(DEV_FAUCET_COOLDOWN)
+    }
+}
+
+/// Program-wide counters updated by sends and claims once created
+/// `fees_collected` sums the send fees received and `total_claimed` the recipient, owner and
+/// webhook revenue claims paid out, both in USDC base units. Sends paying in whitelisted mints
+/// or lamports are counted without adding to `fees_collected`, and claims paid in other mints
+/// are left out. Counters saturate instead of overflowing.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct MailerStats {
+    pub messages_sent: u64,
+    pub priority_sent: u64,
+    pub standard_sent: u64,
+    pub fees_collected: u64,
+    pub total_claimed: u64,
+    pub bump: u8,
+}
+
+impl MailerStats {
+    pub const LEN: usize = 8 + 8 + 8 + 8 + 8 + 1; // 41 bytes
+
+    pub fn record_send(&mut self, priority: bool, usdc_fee: u64) {
+        self.messages_sent = self.messages_sent.saturating_add(1);
+        if priority {
+            self.priority_sent = self.priority_sent.saturating_add(1);
+        } else {
+            self.standard_sent = self.standard_sent.saturating_add(1);
+        }
+        self.fees_collected = self.fees_collected.saturating_add(usdc_fee);
+    }
+
+    pub fn record_claim(&mut self, usdc_amount: u64) {
+        self.total_claimed = self.total_claimed.saturating_add(usdc_amount);
+    }
+}
+
+/// Instructions
+/// Encoded as bare Borsh or in a versioned format, see [`instruction_versions`]; variants
+/// added since version 2 end with a `reserved` extension block.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub enum MailerInstruction {
+    /// Initialize the program
+    /// Passing the USDC mint as a trailing account records its decimals, logged with send
+    /// fees for display; they default to 6 otherwise.
+    /// Accounts:
+    /// 0. `[writable, signer]` Owner account
+    /// 1. `[writable]` Mailer state account (PDA)
+    /// 2. `[]` System program
+    Initialize { usdc_mint: Pubkey },
+
+    /// Send message with optional revenue sharing
+    /// SOFT-FAIL BEHAVIOR: Does not revert on fee payment failure.
+    /// This design allows composability - calling programs won't fail if message sending fails.
+    /// Monitor program logs: a failed fee payment is logged as
+    /// `MailDropped { sender, to, reason }` ahead of the send log, as in all other Send* instructions.
+    /// STRICT MODE: with `strict` set, a failed fee payment fails the instruction with
+    /// `InsufficientFee` when the sender's balance cannot cover the fee, or `FeePaymentFailed`
+    /// otherwise. Wallets sending directly should set it; soft-fail stays the
+    /// default for CPI callers.
+    /// READ RECEIPTS: `request_read_receipt` asks the recipient for a read receipt. The request is
+    /// declined when the recipient's preferences PDA (passed as an optional trailing account) opts out.
+    /// MESSAGE RECEIPTS: `receipt_nonce` creates a MessageReceipt PDA keyed by (recipient, nonce),
+    /// paid for by the sender and passed as a trailing writable account.
+    /// MEMO: priority senders may attach a memo (max 64 bytes) recorded with the accrued share in
+    /// the (recipient, sender) ClaimHistoryEntry PDA, passed as a trailing writable account.
+    /// RETURN DATA: sets the return data to a Borsh `SendResult` holding the compute units left
+    /// when the send finished and the send's message id, as do all other Send* instructions.
+    /// MESSAGE IDS: the sender's MessageNonce PDA is a required trailing writable account
+    /// (missing fails with `MissingMessageNonceAccount`); its next nonce goes into the message
+    /// id (see [`derive_message_id`]), so a sender's ids never repeat. The account is created on
+    /// first use, paid by the sender, which then needs the system program among the accounts.
+    /// Every other Send* instruction requires it the same way.
+    /// FEE MINTS: paying from a token account of a whitelisted fee mint charges that mint's
+    /// `send_fee`; pass its FeeMint PDA as a trailing account, the mailer's token account for
+    /// the mint as the mailer USDC account, and the recipient's claim account for the mint.
+    /// LAMPORT FEES: once the owner sets a `lamport_send_fee`, standard sends may pay in SOL by
+    /// passing the sender as the sender USDC account and the lamport vault PDA as the mailer USDC
+    /// account; the fee accrues to `owner_claimable_lamports`. Priority sends pay in tokens only.
+    /// While a fee oracle is set, pass its price account as a trailing account; the fee is
+    /// then pegged to the oracle's target (see [`price_oracle`]).
+    /// SEND CREDITS: passing the sender's SendCredits PDA as a trailing writable account pays
+    /// USDC fees from its prepaid balance without a token transfer while it covers them (see
+    /// DepositSendCredits).
+    /// SUBSCRIPTIONS: passing the sender's Subscription PDA as a trailing writable account skips
+    /// the per-message fee while the subscription is active and has messages left, using one of
+    /// them (see Subscribe). This applies to every Send* instruction; for SendPreauthorized and
+    /// SendAsDelegator it is the user's or delegator's subscription.
+    /// CLAIM CREATION: under the AfterTransfer policy the claim account is created only once the
+    /// fee has arrived. Under RefundPayer, a send that creates the claim and then soft-fails
+    /// records the sender as its rent payer in the ClaimRentPayer PDA, passed as a trailing
+    /// writable account. SendPrepared, SendThroughWebhook and SendPreauthorized (where the
+    /// relayer pays) behave the same way.
+    /// RENT PAYER: the writable account right after the fixed accounts pays the claim
+    /// account's rent (and its ClaimRentPayer record). The sender passes itself; a protocol can
+    /// instead sponsor the rent from a treasury. It must sign, or the send fails with
+    /// `MissingRequiredSignature`. Every wallet Send* routed through the same engine
+    /// (SendPrepared, SendThroughWebhook, SendEncrypted, ClaimAndSend, SendToDomain and
+    /// SendToEvmAddress) takes it the same way.
+    /// INBOX SEQUENCE: passing the recipient's RecipientInbox PDA as a trailing writable account
+    /// numbers the send in their inbox (created on first use, paid by the sender) and logs the
+    /// number as `inbox sequence: N`. SendPrepared, SendThroughWebhook and SendPreauthorized
+    /// take it the same way.
+    /// THREADS: every send logs its `message id` and, when given, the `thread` and
+    /// `in reply to` ids, so clients can group conversations.
+    /// SendPrepared and SendThroughWebhook take the same ids.
+    /// DOWNGRADES: a priority send cannot credit a recipient whose claim is frozen (sanctioned
+    /// recipients cannot claim; pass their Sanctioned PDA as a trailing account) or whose
+    /// balance would exceed the owner's claim cap. With `allow_downgrade` set, such a send goes
+    /// out as a standard send, charging only the 10% owner fee, and logs
+    /// `DowngradedToStandard { sender, to, reason }` first. Otherwise a capped send fails with
+    /// `ClaimCapExceeded`. SendPrepared and SendThroughWebhook take the same flag.
+    /// TIPS: a priority send may add a `tip`, collected in the fee's mint on top of the fee once
+    /// it is paid and credited in full to the recipient's claim. The send log carries it as
+    /// `tip: N` so clients can sort inboxes by it. Standard sends cannot tip
+    /// (`TipRequiresPriority`), a downgraded send drops its tip, and the claim cap counts it.
+    /// SendPrepared and SendThroughWebhook take the same tip.
+    /// PAYLOAD LIMITS: payload fields longer than the owner's limits fail with `PayloadTooLarge`,
+    /// and the fee grows with the payload's length under the owner's fee tiers (see
+    /// SetPayloadLimits), as in all other Send* instructions that carry payload fields.
+    /// BRIDGING: with `bridge` set, the send is also posted as a Wormhole message for the EVM
+    /// deployments to mirror (see [`wormhole`]), through the core bridge accounts listed under
+    /// SetWormholeBridge as trailing accounts. The sender pays the bridge's message fee and the
+    /// message account's rent. Fails with `WormholeNotConfigured` while no bridge is set, and
+    /// with `WormholeDisabled` in builds without the `wormhole` feature.
+    /// IDEMPOTENCY: with `idempotency_nonce` set, the sender's SendNonce PDA must be passed as a
+    /// trailing writable account (created on first use, paid by the sender; missing fails with
+    /// `MissingSendNonceAccount`). It keeps the sender's last 16 nonces: a send reusing one of
+    /// them logs `Duplicate send nonce N from SENDER, skipped` and succeeds without sending,
+    /// charging or setting return data, so a wallet can retry a send under a new blockhash
+    /// without paying twice. The nonce is recorded once the message is sent with its fee paid:
+    /// a send dropped for an unpaid fee leaves it free for the retry.
+    /// Only `Send` takes a nonce, so the other Send* variants keep their instruction layout for
+    /// existing clients; the runtime deduplicates their retries only while the blockhash lasts.
+    /// TOKEN-2022: fees may be paid from Token-2022 accounts by passing the Token-2022 program
+    /// as the token program and the fee's mint as a trailing account (missing fails with
+    /// `MissingMintAccount`). Passing the mint also checks SPL Token transfers against its
+    /// decimals. Mints charging a transfer fee credit shares on the amount the vault received
+    /// and log `Transfer skim detected: sent N, received M, skimmed K`.
+    /// Accounts:
+    /// 0. `[writable, signer]` Sender (pays for account creation)
+    /// 1. `[writable]` Recipient claim account (PDA)
+    /// 2. `[writable]` Mailer state account (PDA)
+    /// 3. `[writable]` Sender USDC account
+    /// 4. `[writable]` Mailer USDC account
+    /// 5. `[]` Token program
+    /// 6. `[]` System program
+    /// 7. `[writable, signer]` Rent payer (optional; defaults to the sender)
+    Send {
+        to: Pubkey,
+        subject: String,
+        _body: String,
+        revenue_share_to_receiver: bool,
+        resolve_sender_to_name: bool,
+        request_read_receipt: bool,
+        receipt_nonce: Option<u64>,
+        memo: Option<String>,
+        strict: bool,
+        thread_id: Option<[u8; 32]>,
+        in_reply_to: Option<[u8; 32]>,
+        allow_downgrade: bool,
+        tip: u64,
+        bridge: bool,
+        idempotency_nonce: Option<u64>,
+    },
+
+    /// Send prepared message with optional revenue sharing (references off-chain content via mailId)
+    /// SOFT-FAIL BEHAVIOR: Does not revert on fee payment failure unless `strict` is set.
+    /// See Send instruction for details.
+    /// Accounts:
+    /// 0. `[writable, signer]` Sender (pays for account creation)
+    /// 1. `[writable]` Recipient claim account (PDA)
+    /// 2. `[writable]` Mailer state account (PDA)
+    /// 3. `[writable]` Sender USDC account
+    /// 4. `[writable]` Mailer USDC account
+    /// 5. `[]` Token program
+    /// 6. `[]` System program
+    /// 7. `[writable, signer]` Rent payer (optional; defaults to the sender)
+    SendPrepared {
+        to: Pubkey,
+        mail_id: String,
+        revenue_share_to_receiver: bool,
+        resolve_sender_to_name: bool,
+        strict: bool,
+        thread_id: Option<[u8; 32]>,
+        in_reply_to: Option<[u8; 32]>,
+        allow_downgrade: bool,
+        tip: u64,
+    },
+
+    /// Send message to email address (no wallet address known)
+    /// Charges only 10% owner fee since recipient wallet is unknown
+    /// SOFT-FAIL BEHAVIOR: Does not revert on fee payment failure unless `strict` is set.
+    /// See Send instruction for details.
+    /// `to_email` is validated and lowercased by [`normalize_email`] before any fee is charged;
+    /// malformed addresses fail with `InvalidEmailAddress`.
+    /// The sender's MessageNonce PDA is required as for Send; its first send creates it and
+    /// needs the system program as a trailing account, as do lamport fees.
+    /// Accounts:
+    /// 0. `[writable, signer]` Sender (pays for its message nonce account)
+    /// 1. `[writable]` Mailer state account (PDA)
+    /// 2. `[writable]` Sender USDC account
+    /// 3. `[writable]` Mailer USDC account
+    /// 4. `[]` Token program
+    SendToEmail {
+        to_email: String,
+        subject: String,
+        _body: String,
+        strict: bool,
+    },
+
+    /// Send prepared message to email address (no wallet address known)
+    /// Charges only 10% owner fee since recipient wallet is unknown
+    /// SOFT-FAIL BEHAVIOR: Does not revert on fee payment failure unless `strict` is set.
+    /// See Send instruction for details.
+    /// The sender's MessageNonce PDA is required as for Send; its first send creates it and
+    /// needs the system program as a trailing account, as do lamport fees.
+    /// Accounts:
+    /// 0. `[writable, signer]` Sender (pays for its message nonce account)
+    /// 1. `[writable]` Mailer state account (PDA)
+    /// 2. `[writable]` Sender USDC account
+    /// 3. `[writable]` Mailer USDC account
+    /// 4. `[]` Token program
+    SendPreparedToEmail {
+        to_email: String,
+        mail_id: String,
+        strict: bool,
+    },
+
+    /// Send message through webhook (referenced by webhookId)
+    /// SOFT-FAIL BEHAVIOR: Does not revert on fee payment failure unless `strict` is set.
+    /// See Send instruction for details.
+    /// The webhook must be registered by the sender and active (see RegisterWebhook).
+    /// Once the send fee is paid, the webhook's surcharge is collected in USDC on top of it,
+    /// without discounts and even while fees are paused, and accrues to the webhook owner;
+    /// a failed surcharge transfer fails the send. Surcharged webhooks cannot pay in lamports.
+    /// Accounts:
+    /// 0. `[writable, signer]` Sender (pays for account creation)
+    /// 1. `[writable]` Recipient claim account (PDA)
+    /// 2. `[writable]` Mailer state account (PDA)
+    /// 3. `[writable]` Sender USDC account
+    /// 4. `[writable]` Mailer USDC account
+    /// 5. `[]` Token program
+    /// 6. `[]` System program
+    /// 7. `[writable]` Webhook account (PDA)
+    /// 8. `[writable, signer]` Rent payer (optional; defaults to the sender)
+    SendThroughWebhook {
+        to: Pubkey,
+        webhook_id: String,
+        revenue_share_to_receiver: bool,
+        resolve_sender_to_name: bool,
+        strict: bool,
+        thread_id: Option<[u8; 32]>,
+        in_reply_to: Option<[u8; 32]>,
+        allow_downgrade: bool,
+        tip: u64,
+    },
+
+    /// Claim recipient share
+    /// TIMESTAMP DEPENDENCY: Uses Clock::get()?.unix_timestamp for expiration checks (60 days).
+    /// Validators can manipulate timestamps by ±30 seconds or more. Claims near the deadline
+    /// have a small risk of denial. Recommended: Claim well before the 60-day deadline.
+    /// Accounts:
+    /// 0. `[signer]` Recipient
+    /// 1. `[writable]` Recipient claim account (PDA)
+    /// 2. `[]` Mailer state account (PDA; writable when the claim has no-expiry status)
+    /// 3. `[writable]` Recipient USDC account (the configured payout account, if any)
+    /// 4. `[writable]` Mailer USDC account
+    /// 5. `[]` Token program
+    /// 6. `[]` Payout config account (PDA, optional; enforced when supplied)
+    ///
+    /// `amount` withdraws only part of the balance; the remainder keeps the original
+    /// timestamp and expires with it. `None` claims everything.
+    ///
+    /// Shares accrued in a whitelisted fee mint are claimed by passing the recipient's claim
+    /// account for that mint and token accounts of it; payout configs only apply to USDC. A
+    /// no-expiry surcharge due on such a claim is credited to the mint's FeeMint account,
+    /// which must then be passed as a trailing writable account.
+    ///
+    /// `split` pays the claimed amount to up to 4 distinct destinations instead of account 3,
+    /// by basis-point weights summing to 10,000; rounding dust goes to the first destination.
+    /// Destinations are passed as trailing writable accounts and must each pass the checks
+    /// made on account 3, so a configured payout account cannot be split around.
+    ///
+    /// `close_to` closes the claim account once it is emptied, refunding its rent to that
+    /// address, passed as a trailing writable account along with the closure log PDA; the
+    /// mailer state must then be writable. A claim left with a balance fails with
+    /// `AccountNotEmpty`. The next priority send to the recipient recreates the claim, without
+    /// its counters or no-expiry status.
+    ClaimRecipientShare {
+        amount: Option<u64>,
+        split: Option<Vec<ClaimSplit>>,
+        close_to: Option<Pubkey>,
+    },
+
+    /// Claim owner share
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    /// 2. `[writable]` Owner USDC account
+    /// 3. `[writable]` Mailer USDC account
+    /// 4. `[]` Token program
+    /// 5. `[writable]` FeeMint account (PDA, required when claiming in a whitelisted fee mint)
+    ///
+    /// Claims the owner share in the mint of the mailer token account passed. Once a
+    /// treasury is set, passing its USDC account (writable) and the treasury PDA as trailing
+    /// accounts pays out the treasury balance as well.
+    ClaimOwnerShare,
+
+    /// Set send fee (owner only)
+    /// WARNING: Fee changes take effect IMMEDIATELY with no time delay or notification.
+    /// This allows quick response to market conditions but requires user trust.
+    ///   - No maximum fee cap enforced
+    ///   - Users with pending transactions may pay different fees than expected
+    ///   - Monitor program logs for FeeUpdated events
+    ///   - Rejected once a fee timelock is configured; use ProposeFee instead
+    ///   - With `expected_current_value`, fails with `StaleState` unless the fee still equals it,
+    ///     so tools racing on the same setting cannot silently overwrite each other
+    ///
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    SetFee {
+        new_fee: u64,
+        expected_current_value: Option<u64>,
+    },
+
+    /// Delegate to another address
+    /// The delegate may then send messages as the delegator with SendAsDelegator.
+    /// WARNING: Delegation fee is NON-REFUNDABLE, even if the delegate rejects the delegation,
+    /// unless the owner configured a refund window with SetDelegationRefundWindow.
+    /// The fee is an anti-spam measure and goes to the contract owner regardless of delegation outcome.
+    /// The fee can be paid in a whitelisted fee mint as for Send, with its FeeMint PDA trailing.
+    /// With a refund window, a USDC fee is escrowed on the delegation until the delegate
+    /// accepts or rejects; whitelisted mint fees are never escrowed. Replacing or clearing a
+    /// delegation credits a still-escrowed fee to the owner, and fails with
+    /// `DelegationFeeRefundPending` while a refundable fee awaits RefundDelegationFee.
+    /// Accounts:
+    /// 0. `[writable, signer]` Delegator (pays for account creation)
+    /// 1. `[writable]` Delegation account (PDA)
+    /// 2. `[writable]` Mailer state account (PDA)
+    /// 3. `[writable]` Delegator USDC account
+    /// 4. `[writable]` Mailer USDC account
+    /// 5. `[]` Token program
+    /// 6. `[]` System program
+    /// 7. `[writable]` DelegateIndex of the new delegate (PDA), created on demand; omitted when clearing
+    /// 8. `[writable]` DelegateIndex of the previous delegate (PDA), when one was set and it changes
+    DelegateTo { delegate: Option<Pubkey> },
+
+    /// Reject delegation
+    /// NOTE: Rejecting a delegation does NOT refund the delegation fee paid by the delegator.
+    /// The fee is an anti-spam measure and is non-refundable by design. An escrowed fee
+    /// becomes refundable with RefundDelegationFee when rejected within the refund window,
+    /// and is credited to the owner when rejected after it.
+    /// Accounts:
+    /// 0. `[signer]` Rejector
+    /// 1. `[writable]` Delegation account (PDA)
+    /// 2. `[writable]` Mailer state account (PDA; written only to credit a late-rejected escrowed fee)
+    /// 3. `[writable]` DelegateIndex of the rejector (PDA)
+    RejectDelegation,
+
+    /// Set delegation fee (owner only)
+    /// WARNING: Fee changes take effect IMMEDIATELY with no time delay.
+    /// See SetFee instruction for detailed implications of instant fee changes.
+    /// `expected_current_value` guards against stale state as for SetFee.
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    SetDelegationFee {
+        new_fee: u64,
+        expected_current_value: Option<u64>,
+    },
+
+    /// Set custom fee percentage for a specific address (owner only)
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    /// 2. `[writable]` Fee discount account (PDA)
+    /// 3. `[]` Account to set custom fee for
+    /// 4. `[signer]` Payer for account creation
+    /// 5. `[]` System program
+    SetCustomFeePercentage {
+        account: Pubkey,
+        percentage: u8, // 0-100: 0 = free, 100 = full fee
+    },
+
+    /// Clear custom fee percentage for a specific address (owner only)
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[]` Mailer state account (PDA)
+    /// 2. `[writable]` Fee discount account (PDA)
+    ClearCustomFeePercentage { account: Pubkey },
+
+    /// Pause the contract (owner only)
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    /// 2. `[writable]` Owner USDC account
+    /// 3. `[writable]` Mailer USDC account  
+    /// 4. `[]` Token program
+    Pause,
+
+    /// Unpause the contract (owner only)
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    Unpause,
+
+    /// Distribute claimable funds (when paused)
+    /// Accounts:
+    /// 0. `[signer]` Anyone can call
+    /// 1. `[]` Mailer state account (PDA)
+    /// 2. `[writable]` Recipient claim account (PDA)
+    /// 3. `[writable]` Recipient USDC account (the configured payout account, if any)
+    /// 4. `[writable]` Mailer USDC account
+    /// 5. `[]` Token program
+    /// 6. `[]` Payout config account (PDA, optional; enforced when supplied)
+    DistributeClaimableFunds { recipient: Pubkey },
+
+    /// Claim expired recipient shares (owner only)
+    /// When the recipient has registered a beneficiary, the sweep waits out the beneficiary's
+    /// grace period and fails with `BeneficiaryGracePeriodActive` until it ends.
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    /// 2. `[writable]` Recipient claim account (PDA)
+    /// 3. `[]` Claim beneficiary account (PDA, registered or not)
+    ClaimExpiredShares { recipient: Pubkey },
+
+    /// Emergency unpause without fund distribution (owner only)
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    EmergencyUnpause,
+
+    /// Toggle fee collection on or off (owner only)
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    SetFeePaused { fee_paused: bool },
+
+    /// Set recipient messaging preferences (recipient or session key with prefs scope)
+    /// Accounts:
+    /// 0. `[writable, signer]` Recipient or session key (pays for account creation)
+    /// 1. `[writable]` Recipient preferences account (PDA)
+    /// 2. `[]` System program
+    /// 3. `[]` Session key account (PDA, optional; required when signing with a session key)
+    SetRecipientPrefs { read_receipts_opt_out: bool },
+
+    /// Acknowledge (read) a message from a sender, emitting a read receipt
+    /// Fails with ReadReceiptsDisabled if the recipient has globally opted out of read receipts.
+    /// With `receipt_nonce`, also marks the matching MessageReceipt PDA as read.
+    /// Accounts:
+    /// 0. `[signer]` Recipient
+    /// 1. `[]` Recipient preferences account (PDA, may be uninitialized)
+    /// 2. `[writable]` Message receipt account (PDA, required with `receipt_nonce`)
+    AcknowledgeMessage {
+        sender: Pubkey,
+        receipt_nonce: Option<u64>,
+    },
+
+    /// Enable or disable protocol-internal sends (owner only)
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    SetProtocolSendsEnabled { enabled: bool },
+
+    /// Send a protocol-origin notification (owner only)
+    /// Protocol messages originate from the mailer PDA and are exempt from fees,
+    /// so the owner never routes fees back to itself. Requires protocol sends to be enabled.
+    /// The mailer PDA's MessageNonce PDA is a required trailing writable account; the owner
+    /// pays for it on the first protocol send, with the system program as a trailing account.
+    /// Accounts:
+    /// 0. `[writable, signer]` Owner
+    /// 1. `[]` Mailer state account (PDA)
+    SendProtocolMessage {
+        to: Pubkey,
+        subject: String,
+        _body: String,
+    },
+
+    /// Propose a new owner (owner only)
+    /// Step one of a two-step transfer: ownership only moves once the proposed
+    /// owner signs AcceptOwnership. Proposing again replaces the pending owner.
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    TransferOwnership { new_owner: Pubkey },
+
+    /// Accept a pending ownership transfer (pending owner only)
+    /// Accounts:
+    /// 0. `[signer]` Pending owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    AcceptOwnership,
+
+    /// Configure the admin set and signature threshold (owner or admin quorum)
+    /// An empty admin list with threshold 0 disables threshold administration.
+    /// Accounts:
+    /// 0. `[writable, signer]` Owner or admin (pays for account creation)
+    /// 1. `[]` Mailer state account (PDA)
+    /// 2. `[writable]` Admin set account (PDA)
+    /// 3. `[]` System program
+    /// 4. `[signer]` Additional admin signers (optional, when authorizing via quorum)
+    SetAdmins { admins: Vec<Pubkey>, threshold: u8 },
+
+    /// Sweep a page of expired recipient claims into owner control (owner only)
+    /// Claim PDAs are passed as remaining accounts in strictly ascending key order, all greater
+    /// than the cursor's last processed key. Unexpired or empty claims, and claims still in their
+    /// beneficiary's grace period, are skipped; no-expiry surcharges due on the page's claims
+    /// are collected along the way.
+    /// Accounts:
+    /// 0. `[writable, signer]` Owner (pays for cursor creation)
+    /// 1. `[writable]` Mailer state account (PDA)
+    /// 2. `[writable]` Sweep cursor account (PDA)
+    /// 3. `[]` System program
+    /// 4. `[writable]` Recipient claim accounts (PDAs, up to `max_items`)
+    /// 5. `[]` Claim beneficiary accounts of the expired claims (PDAs, registered or not)
+    SweepExpiredPage { max_items: u8 },
+
+    /// Reset the sweep cursor to start a new full sweep (owner only)
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[]` Mailer state account (PDA)
+    /// 2. `[writable]` Sweep cursor account (PDA)
+    ResetSweepCursor,
+
+    /// Propose a send fee change that applies once the fee timelock elapses (owner only)
+    /// Send paths only apply the proposed fee after `fee_timelock` seconds.
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    ProposeFee { new_fee: u64 },
+
+    /// Set the minimum delay for proposed fee changes (owner only)
+    /// The delay can only be increased, up to 30 days, so integrators can rely on it.
+    /// While it is set, the send fee changes only through ProposeFee: the other fee setters
+    /// (delegation, lamport, fee mint, payload tier and discount fees, and the fee oracle)
+    /// fail with `FeeTimelockActive`.
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    SetFeeTimelock { delay: i64 },
+
+    /// Set the recipient's inbox policy (recipient or session key with inbox policy scope)
+    /// Send, SendPrepared and SendThroughWebhook enforce the policy when the recipient's
+    /// policy PDA is supplied as a trailing account; off-chain delivery should apply the
+    /// same policy to sends that omit it.
+    /// `min_fee` is the least a priority send must pay, as its effective fee plus any tip,
+    /// to reach the recipient; cheaper priority sends fail with `RecipientFeeTooLow`, in
+    /// SendPreauthorized and SendAsDelegator too. 0 sets no minimum. A policy created
+    /// before minimums existed is resized on update, the signer paying the extra rent.
+    /// Accounts:
+    /// 0. `[writable, signer]` Recipient or session key (pays for account creation)
+    /// 1. `[writable]` Inbox policy account (PDA)
+    /// 2. `[]` System program
+    /// 3. `[]` Session key account (PDA, optional; required when signing with a session key)
+    SetInboxPolicy {
+        mode: InboxMode,
+        senders: Vec<Pubkey>,
+        penalty_multiplier: u8,
+        min_fee: u64,
+    },
+
+    /// Claim recipient share into the recipient's associated token account
+    /// Creates the associated token account first when it does not exist, so first-time
+    /// recipients can claim without a separate setup transaction.
+    /// Accounts:
+    /// 0. `[writable, signer]` Recipient (pays for token account creation)
+    /// 1. `[writable]` Recipient claim account (PDA)
+    /// 2. `[]` Mailer state account (PDA)
+    /// 3. `[writable]` Recipient associated USDC token account
+    /// 4. `[writable]` Mailer USDC account
+    /// 5. `[]` Token program
+    /// 6. `[]` USDC mint
+    /// 7. `[]` System program
+    /// 8. `[]` Associated token account program
+    ClaimRecipientShareToAta,
+
+    /// Register or replace a session key for preference updates (recipient only)
+    /// The session key can then sign SetRecipientPrefs and/or SetInboxPolicy (per `scope`,
+    /// see `SessionKey::SCOPE_*`) until `expires_at`, at most 30 days ahead.
+    /// Accounts:
+    /// 0. `[writable, signer]` Recipient (pays for account creation)
+    /// 1. `[writable]` Session key account (PDA)
+    /// 2. `[]` System program
+    RegisterSessionKey {
+        session_key: Pubkey,
+        scope: u8,
+        expires_at: i64,
+    },
+
+    /// Revoke a session key immediately (recipient only)
+    /// Accounts:
+    /// 0. `[signer]` Recipient
+    /// 1. `[writable]` Session key account (PDA)
+    RevokeSessionKey { session_key: Pubkey },
+
+    /// Set or clear the recipient's payout token account (recipient only)
+    /// Recipient share payouts then go to this USDC account instead of the recipient's own.
+    /// Accounts:
+    /// 0. `[writable, signer]` Recipient (pays for account creation)
+    /// 1. `[writable]` Payout config account (PDA)
+    /// 2. `[]` Mailer state account (PDA)
+    /// 3. `[]` System program
+    /// 4. `[]` Payout USDC token account (required when setting)
+    SetPayoutAddress {
+        payout_token_account: Option<Pubkey>,
+    },
+
+    /// Register, enable or disable a conversion vault for an alternate payout mint (owner only)
+    /// The owner funds the vault token account directly with SPL transfers.
+    /// Accounts:
+    /// 0. `[writable, signer]` Owner (pays for account creation)
+    /// 1. `[]` Mailer state account (PDA)
+    /// 2. `[writable]` Conversion vault account (PDA)
+    /// 3. `[]` Vault token account (owned by the mailer PDA, of `mint`)
+    /// 4. `[]` System program
+    SetConversionVault { mint: Pubkey, enabled: bool },
+
+    /// Claim the full recipient share paid in an alternate mint
+    /// Pays 1:1 from the mint's conversion vault when it is enabled and holds enough
+    /// liquidity; otherwise falls back to a regular default-mint claim. For the backup mint
+    /// in contingency mode there is no fallback: an unavailable vault fails the claim with
+    /// `BackupVaultUnavailable`.
+    /// Accounts:
+    /// 0. `[signer]` Recipient
+    /// 1. `[writable]` Recipient claim account (PDA)
+    /// 2. `[writable]` Mailer state account (PDA)
+    /// 3. `[writable]` Recipient token account for `mint`
+    /// 4. `[writable]` Conversion vault account (PDA)
+    /// 5. `[writable]` Vault token account
+    /// 6. `[writable]` Recipient USDC account (fallback destination)
+    /// 7. `[writable]` Mailer USDC account
+    /// 8. `[]` Token program
+    /// 9. `[]` Payout config account (PDA, optional; applies to the fallback)
+    ClaimRecipientShareAs { mint: Pubkey },
+
+    /// Read the program configuration without modifying it
+    /// Sets the return data to the Borsh-serialized `MailerState`, which includes the
+    /// rent-exempt lamports locked in claim, delegation and discount accounts.
+    /// Accounts:
+    /// 0. `[]` Mailer state account (PDA)
+    GetConfig,
+
+    /// Create or replace a send pre-authorization for a relayer (user only)
+    /// Approves the pre-authorization PDA as delegate of the user's USDC account for
+    /// `max_sends * max_fee_per_send` and resets the send count. An empty `webhook_id`
+    /// allows any webhook.
+    /// Accounts:
+    /// 0. `[writable, signer]` User (pays for account creation)
+    /// 1. `[writable]` Pre-authorization account (PDA)
+    /// 2. `[writable]` User USDC account
+    /// 3. `[]` Mailer state account (PDA)
+    /// 4. `[]` Token program
+    /// 5. `[]` System program
+    CreatePreauthorization {
+        relayer: Pubkey,
+        webhook_id: String,
+        max_sends: u64,
+        max_fee_per_send: u64,
+        expires_at: i64,
+    },
+
+    /// Revoke a send pre-authorization immediately (user only)
+    /// Also revokes the token delegation when it is still held by the pre-authorization.
+    /// Accounts:
+    /// 0. `[signer]` User
+    /// 1. `[writable]` Pre-authorization account (PDA)
+    /// 2. `[writable]` User USDC account
+    /// 3. `[]` Token program
+    RevokePreauthorization { relayer: Pubkey },
+
+    /// Send a webhook message on a user's behalf within a pre-authorization (relayer only)
+    /// The fee (with the user's discount and the recipient's inbox policy applied) must not
+    /// exceed `max_fee_per_send`; it is paid from the user's USDC account by the
+    /// pre-authorization PDA. Sends whose fee transfer fails are logged but not counted.
+    /// Accounts:
+    /// 0. `[writable, signer]` Relayer (pays for claim account creation)
+    /// 1. `[writable]` Pre-authorization account (PDA)
+    /// 2. `[]` User
+    /// 3. `[writable]` Recipient claim account (PDA)
+    /// 4. `[writable]` Mailer state account (PDA)
+    /// 5. `[writable]` User USDC account
+    /// 6. `[writable]` Mailer USDC account
+    /// 7. `[]` Token program
+    /// 8. `[]` System program
+    SendPreauthorized {
+        to: Pubkey,
+        webhook_id: String,
+        revenue_share_to_receiver: bool,
+    },
+
+    /// Disable or re-enable a single instruction by its variant tag (owner only)
+    /// Disabled instructions fail with `InstructionDisabled` before their handler runs,
+    /// independently of the pause flags, and other instructions fail with
+    /// `MissingMailerStateAccount` when not passed the mailer state. Initialize,
+    /// MigrateState, MigrateDiscriminator, this instruction and the wallet-scoped
+    /// instructions that take no mailer state (SetRecipientPrefs, AcknowledgeMessage,
+    /// SetInboxPolicy, the session key, webhook, list and EVM binding instructions,
+    /// RevokePreauthorization and GetDelegation) cannot be disabled.
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    SetInstructionDisabled { tag: u8, disabled: bool },
+
+    /// Rewrite legacy account discriminators to SHA-256 discriminators (permissionless)
+    /// The account type is recognized from its legacy discriminator and only the
+    /// discriminator bytes change; accounts that are already migrated are skipped.
+    /// Accounts:
+    /// 0. `[writable]` Program-owned account to migrate
+    /// 1. `[writable]` Additional program-owned accounts to migrate (optional)
+    MigrateDiscriminator,
+
+    /// Close an emptied recipient claim account and return its rent to the owner (owner only)
+    /// The claim must have no claimable amount left.
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    /// 2. `[writable]` Recipient claim account (PDA)
+    /// 3. `[writable]` Refund destination (the mailer owner)
+    /// 4. `[writable]` Closure log account (PDA)
+    CloseRecipientClaim { recipient: Pubkey },
+
+    /// Close a cleared delegation account and return its rent to the delegator
+    /// The delegation must have no delegate set and no escrowed fee.
+    /// Accounts:
+    /// 0. `[writable, signer]` Delegator (refund destination)
+    /// 1. `[writable]` Delegation account (PDA)
+    /// 2. `[writable]` Mailer state account (PDA)
+    /// 3. `[writable]` Closure log account (PDA)
+    CloseDelegation,
+
+    /// Close a cleared fee discount account and return its rent to the owner (owner only)
+    /// The discount must have been cleared to 0 first.
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    /// 2. `[writable]` Fee discount account (PDA)
+    /// 3. `[writable]` Refund destination (the mailer owner)
+    CloseFeeDiscount { account: Pubkey },
+
+    /// Move the caller's claim, delegation or discount account from `PREVIOUS_PDA_VERSION`
+    /// seeds to `PDA_VERSION` seeds, closing the old account and refunding its rent
+    /// The caller pays rent for the new account. If the new account already exists, a
+    /// claim's balances are merged; a delegation or discount set under the new seeds is
+    /// kept and the old one discarded.
+    /// Accounts:
+    /// 0. `[writable, signer]` Subject: recipient, delegator or discounted account
+    /// 1. `[writable]` Account under previous-version seeds (PDA)
+    /// 2. `[writable]` Account under current seeds (PDA)
+    /// 3. `[writable]` Mailer state account (PDA)
+    /// 4. `[]` System program
+    MigrateAccountVersion { kind: MigrationKind },
+
+    /// Register or clear the spam oracle consulted on sends to a wallet (owner only)
+    /// Send, SendPrepared, SendThroughWebhook and SendPreauthorized then invoke the oracle
+    /// (passed as a trailing account) and log its score; with a `threshold`, sends scoring
+    /// above it fail with `SpamScoreTooHigh`. See [`spam_oracle`] for the interface.
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    SetSpamOracle {
+        oracle: Option<Pubkey>,
+        threshold: Option<u64>,
+    },
+
+    /// Limit every sender to `max_sends` sends per `window` seconds (owner only)
+    /// `max_sends = 0` disables rate limiting. While enabled, every Send* instruction
+    /// takes the sender's rate limit PDA as a trailing writable account (plus the system
+    /// program where it is not already required) and fails with `RateLimitExceeded` once
+    /// the window's sends are used up. The PDA is created on the first send, paid by the
+    /// sender, or by the relayer for SendPreauthorized.
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    SetRateLimit { max_sends: u64, window: i64 },
+
+    /// Create a promo code granting a `discount` percent fee discount (owner only)
+    /// The code itself stays off-chain until redeemed; the promo PDA is derived from
+    /// `code_hash = sha256(code)`. It can be redeemed `max_redemptions` times until `expiry`.
+    /// Accounts:
+    /// 0. `[writable, signer]` Owner (pays for account creation)
+    /// 1. `[]` Mailer state account (PDA)
+    /// 2. `[writable]` Promo account (PDA)
+    /// 3. `[]` System program
+    CreatePromo {
+        code_hash: [u8; 32],
+        discount: u8,
+        max_redemptions: u64,
+        expiry: i64,
+    },
+
+    /// Redeem a promo code, setting the caller's fee discount to the promo's discount
+    /// The caller pays rent for a new fee discount account. Redemptions that would not
+    /// raise an existing discount fail with `DiscountAlreadyApplied`, so a user cannot
+    /// use up a code's redemptions by redeeming it twice.
+    /// Accounts:
+    /// 0. `[writable, signer]` User
+    /// 1. `[writable]` Promo account (PDA)
+    /// 2. `[writable]` User's fee discount account (PDA)
+    /// 3. `[writable]` Mailer state account (PDA)
+    /// 4. `[]` System program
+    RedeemPromo { code: String },
+
+    /// Claim the caller's recipient share into their USDC account, then send a message
+    /// paid from that account, in one instruction
+    /// Behaves as ClaimRecipientShare { amount } followed by Send with the remaining
+    /// arguments; the claimed funds are available to the send's fee.
+    /// Accounts:
+    /// 0. `[writable, signer]` Sender (claims their own share)
+    /// 1. `[writable]` Sender's recipient claim account (PDA)
+    /// 2. `[writable]` Recipient claim account of `to` (PDA)
+    /// 3. `[writable]` Mailer state account (PDA)
+    /// 4. `[writable]` Sender USDC account (claim destination and fee source)
+    /// 5. `[writable]` Mailer USDC account
+    /// 6. `[]` Token program
+    /// 7. `[]` System program
+    ///
+    /// Optional trailing accounts are those of ClaimRecipientShare and Send (payout
+    /// config, message receipt, claim history, fee discount, ...).
+    ClaimAndSend {
+        amount: Option<u64>,
+        to: Pubkey,
+        subject: String,
+        _body: String,
+        revenue_share_to_receiver: bool,
+        resolve_sender_to_name: bool,
+        request_read_receipt: bool,
+        receipt_nonce: Option<u64>,
+        memo: Option<String>,
+        strict: bool,
+    },
+
+    /// Whitelist a fee mint, or update its fees (owner only)
+    /// Accounts:
+    /// 0. `[writable, signer]` Owner (pays for account creation)
+    /// 1. `[]` Mailer state account (PDA)
+    /// 2. `[writable]` FeeMint account (PDA)
+    /// 3. `[]` System program
+    AddFeeMint {
+        mint: Pubkey,
+        send_fee: u64,
+        delegation_fee: u64,
+    },
+
+    /// Remove a fee mint from the whitelist, closing its account (owner only)
+    /// Fails with `AccountNotEmpty` while the owner still has fees to claim in the mint.
+    /// Recipients keep claiming shares already accrued in it.
+    /// Accounts:
+    /// 0. `[writable, signer]` Owner (receives the rent refund)
+    /// 1. `[]` Mailer state account (PDA)
+    /// 2. `[writable]` FeeMint account (PDA)
+    RemoveFeeMint { mint: Pubkey },
+
+    /// Set the send fee for lamport payments, creating the lamport vault (owner only)
+    /// 0 disables lamport payments. Sends pay 10% of it, as standard sends do of `send_fee`.
+    /// Accounts:
+    /// 0. `[writable, signer]` Owner (pays for the vault on first use)
+    /// 1. `[writable]` Mailer state account (PDA)
+    /// 2. `[writable]` Lamport vault (PDA)
+    /// 3. `[]` System program
+    SetLamportFee { lamport_fee: u64 },
+
+    /// Claim the owner's lamport fees from the lamport vault
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    /// 2. `[writable]` Lamport vault (PDA)
+    /// 3. `[writable]` Owner wallet (receives the lamports)
+    ClaimOwnerLamports,
+
+    /// Peg the lamport send fee to `target_fee` micro-USD with a Pyth price account (owner only)
+    /// Lamport sends then price their fee from the oracle's aggregate price, rejecting prices
+    /// older than `max_age` seconds with `StaleOraclePrice`. `None` clears the peg.
+    /// See [`price_oracle`] for the price account format.
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    SetFeeOracle {
+        oracle: Option<Pubkey>,
+        target_fee: u64,
+        max_age: i64,
+    },
+
+    /// Choose when priority sends create recipient claim accounts (owner only)
+    /// See [`ClaimCreationPolicy`].
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    SetClaimCreationPolicy { policy: ClaimCreationPolicy },
+
+    /// Close a never-credited recipient claim and its ClaimRentPayer record, refunding both
+    /// rents to the recorded payer
+    /// The claim is skipped when it has already been closed.
+    /// Accounts:
+    /// 0. `[writable, signer]` Rent payer (receives the refunds)
+    /// 1. `[writable]` Mailer state account (PDA)
+    /// 2. `[writable]` Recipient claim account (PDA)
+    /// 3. `[writable]` ClaimRentPayer account (PDA)
+    /// 4. `[writable]` Closure log account (PDA)
+    CloseFundedClaim,
+
+    /// Send a message whose body is an envelope sealed to the recipient's wallet key
+    /// The envelope is the sender's ephemeral X25519 public key, a nonce and the ciphertext;
+    /// see [`crypto`] for the scheme. Malformed envelopes fail with `InvalidEnvelope`.
+    /// Fees, soft-fail behavior and optional trailing accounts are those of Send, and the send
+    /// is logged as one with an empty subject, followed by
+    /// `EncryptedMail { sender, to, ephemeral key, ciphertext, digest }` where the digest is
+    /// [`crypto::envelope_digest`].
+    /// Accounts: those of Send
+    SendEncrypted {
+        to: Pubkey,
+        ephemeral_key: [u8; 32],
+        nonce: [u8; 12],
+        ciphertext: Vec<u8>,
+        revenue_share_to_receiver: bool,
+        strict: bool,
+    },
+
+    /// Register a webhook id, making the signer its owner
+    /// Fails with `AlreadyInitialized` when the id is taken, even by a revoked webhook.
+    /// `surcharge` is the USDC amount added to every send through the webhook.
+    /// Accounts:
+    /// 0. `[writable, signer]` Webhook owner (pays for account creation)
+    /// 1. `[]` Mailer state account (PDA)
+    /// 2. `[writable]` Webhook account (PDA)
+    /// 3. `[]` System program
+    RegisterWebhook {
+        webhook_id: String,
+        endpoint_hash: [u8; 32],
+        surcharge: u64,
+    },
+
+    /// Point a webhook at a new endpoint and surcharge, reactivating it if it was revoked
+    /// (webhook owner only)
+    /// Accounts:
+    /// 0. `[signer]` Webhook owner
+    /// 1. `[writable]` Webhook account (PDA)
+    UpdateWebhook {
+        endpoint_hash: [u8; 32],
+        surcharge: u64,
+    },
+
+    /// Deactivate a webhook so SendThroughWebhook rejects it (webhook owner only)
+    /// The id stays reserved to its owner.
+    /// Accounts:
+    /// 0. `[signer]` Webhook owner
+    /// 1. `[writable]` Webhook account (PDA)
+    RevokeWebhook,
+
+    /// Claim the surcharges accrued by a webhook (webhook owner only)
+    /// Accounts:
+    /// 0. `[signer]` Webhook owner
+    /// 1. `[writable]` Webhook account (PDA)
+    /// 2. `[]` Mailer state account (PDA)
+    /// 3. `[writable]` Webhook owner USDC account
+    /// 4. `[writable]` Mailer USDC account
+    /// 5. `[]` Token program
+    ClaimWebhookRevenue,
+
+    /// Create the closure log that CloseRecipientClaim, CloseDelegation and CloseFundedClaim
+    /// record the final state of closed accounts into (owner only)
+    /// Accounts:
+    /// 0. `[writable, signer]` Owner (pays for account creation)
+    /// 1. `[]` Mailer state account (PDA)
+    /// 2. `[writable]` Closure log account (PDA)
+    /// 3. `[]` System program
+    InitializeClosureLog,
+
+    /// Send a message as a delegator (current delegate only)
+    /// The delegate signs and pays the fee from its own USDC account; the message is sent
+    /// and logged as from the delegator, whose discount, rate limit, spam score and standing
+    /// in the recipient's inbox policy apply. Soft-fail and `strict` behave as in Send.
+    /// Accounts:
+    /// 0. `[writable, signer]` Delegate (pays the fee and for account creation)
+    /// 1. `[]` Delegator
+    /// 2. `[]` Delegation account of the delegator (PDA)
+    /// 3. `[writable]` Recipient claim account (PDA)
+    /// 4. `[writable]` Mailer state account (PDA)
+    /// 5. `[writable]` Delegate USDC account
+    /// 6. `[writable]` Mailer USDC account
+    /// 7. `[]` Token program
+    /// 8. `[]` System program
+    SendAsDelegator {
+        to: Pubkey,
+        subject: String,
+        _body: String,
+        revenue_share_to_receiver: bool,
+        strict: bool,
+    },
+
+    /// Add an address to the sanctions registry (owner only)
+    /// Accounts:
+    /// 0. `[writable, signer]` Owner (pays for account creation)
+    /// 1. `[]` Mailer state account (PDA)
+    /// 2. `[writable]` Sanctioned account of the address (PDA)
+    /// 3. `[]` System program
+    SanctionAddress { address: Pubkey },
+
+    /// Remove an address from the sanctions registry, refunding the entry's rent (owner only)
+    /// Accounts:
+    /// 0. `[writable, signer]` Owner (refund destination)
+    /// 1. `[]` Mailer state account (PDA)
+    /// 2. `[writable]` Sanctioned account of the address (PDA)
+    UnsanctionAddress { address: Pubkey },
+
+    /// Enable or disable sanctions checks (owner only)
+    /// While enabled, every send and claim must pass the Sanctioned PDA of each acting
+    /// address (sender, fee payer or claimant) as a trailing account, whether or not it has
+    /// been created, and fails with `AddressSanctioned` if it exists.
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    SetSanctionsEnabled { enabled: bool },
+
+    /// Enter or leave contingency mode (owner only)
+    /// `Some(mint)` registers `mint` as the backup mint while the default mint is frozen or
+    /// otherwise disrupted; its conversion vault must already be registered, enabled and
+    /// funded with `SetConversionVault`. Recipients then claim from it 1:1 with
+    /// `ClaimRecipientShareAs`. `None` leaves contingency mode.
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    /// 2. `[]` Conversion vault account of `mint` (PDA, required when setting)
+    SetBackupMint { mint: Option<Pubkey> },
+
+    /// Create the MailerStats account (owner only)
+    /// From then on every send and claim must pass it as a trailing writable account and
+    /// updates its counters.
+    /// Accounts:
+    /// 0. `[writable, signer]` Owner (pays for account creation)
+    /// 1. `[writable]` Mailer state account (PDA)
+    /// 2. `[writable]` Stats account (PDA)
+    /// 3. `[]` System program
+    InitializeStats,
+
+    /// Opt the caller's USDC claim in or out of no-expiry status
+    /// A no-expiry claim never expires to the owner. Each 30-day period of the status is
+    /// prepaid with a USDC surcharge debited from the claimable balance, the first on opt-in;
+    /// later periods are settled as shares are recorded, claimed or swept. A balance that
+    /// cannot cover a period lapses the status and starts a fresh claim window. Opting out
+    /// refunds nothing and starts a fresh claim window for the remaining balance.
+    /// Accounts:
+    /// 0. `[signer]` Recipient
+    /// 1. `[writable]` Recipient claim account (PDA)
+    /// 2. `[writable]` Mailer state account (PDA)
+    SetClaimNoExpiry { enabled: bool },
+
+    /// Claim the expired shares of up to 32 recipients in one instruction (owner only)
+    /// Each claim's expiry is checked independently. Accounts that are not writable canonical
+    /// recipient claims (in the default mint), are empty, have not expired or are still in
+    /// their beneficiary's grace period are skipped, so one stale entry does not fail the batch.
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    /// 2. `[writable]` Recipient claim accounts (PDAs, up to 32), each followed by its
+    ///    `[]` claim beneficiary account (PDA, registered or not)
+    ClaimExpiredSharesBatch,
+
+    /// Pause subsystems independently (owner only)
+    /// `flags` is a mask of `PAUSE_SEND`, `PAUSE_DELEGATE`, `PAUSE_CLAIM` and `PAUSE_ADMIN`
+    /// and replaces the current mask; `Pause` still stops sends, delegation and admin
+    /// changes on top of it. Claim windows are extended for as long as claims are paused.
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    SetPauseFlags { flags: u8 },
+
+    /// Register or clear the KYC verifier program (owner only)
+    /// While set, priority sends fail with `VerificationRequired` unless the sender passes
+    /// a valid attestation from `verifier` as a trailing account; standard sends are
+    /// unaffected. See [`attestation`] for the interface.
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    SetKycVerifier { verifier: Option<Pubkey> },
+
+    /// Set the delegation fee refund window in seconds (owner only)
+    /// While non-zero, USDC delegation fees are escrowed until the delegate accepts or
+    /// rejects, and rejections within `window` seconds of the delegation refund the fee.
+    /// 0 restores non-refundable fees; the maximum is 30 days. Delegations keep the
+    /// deadline they were made with.
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    SetDelegationRefundWindow { window: i64 },
+
+    /// Accept a delegation, releasing its escrowed fee to the owner (delegate only)
+    /// Accepting is optional for delegations without an escrowed fee.
+    /// Accounts:
+    /// 0. `[signer]` Delegate
+    /// 1. `[writable]` Delegation account (PDA)
+    /// 2. `[writable]` Mailer state account (PDA)
+    AcceptDelegation,
+
+    /// Reclaim an escrowed delegation fee the delegate rejected within the refund window
+    /// Stays available while the contract is paused, like claims.
+    /// Accounts:
+    /// 0. `[signer]` Delegator
+    /// 1. `[writable]` Delegation account (PDA)
+    /// 2. `[]` Mailer state account (PDA)
+    /// 3. `[writable]` Delegator USDC account
+    /// 4. `[writable]` Mailer USDC account
+    /// 5. `[]` Token program
+    RefundDelegationFee,
+
+    /// Record the delivery status of a send routed through a webhook (webhook owner only)
+    /// The send is identified by its recipient, message nonce and slot, which the program
+    /// hashes with the webhook id into the message id logged by SendThroughWebhook so indexers
+    /// can match the two logs; the ack latency is the number of slots since `sent_slot`. A
+    /// webhook cannot record more statuses than it routed sends.
+    /// Accounts:
+    /// 0. `[signer]` Webhook owner
+    /// 1. `[writable]` Webhook account (PDA)
+    RecordDeliveryStatus {
+        to: Pubkey,
+        nonce: u64,
+        sent_slot: u64,
+        delivered: bool,
+    },
+
+    /// Return a webhook's SLA counters as a Borsh `WebhookStats` in the return data
+    /// Accounts:
+    /// 0. `[]` Webhook account (PDA)
+    GetWebhookStats,
+
+    /// Deposit USDC into the sender's prepaid send credits, creating the account on first use
+    /// Send, SendPrepared, SendToEmail, SendPreparedToEmail, SendThroughWebhook and
+    /// SendAsDelegator pay USDC fees from the payer's credits, without a token transfer,
+    /// when its SendCredits PDA is passed as a trailing writable account and covers the fee;
+    /// otherwise they transfer the fee as usual.
+    /// Accounts:
+    /// 0. `[writable, signer]` Sender (pays for account creation)
+    /// 1. `[writable]` Send credits account (PDA)
+    /// 2. `[writable]` Mailer state account (PDA)
+    /// 3. `[writable]` Sender USDC account
+    /// 4. `[writable]` Mailer USDC account
+    /// 5. `[]` Token program
+    /// 6. `[]` System program
+    DepositSendCredits { amount: u64 },
+
+    /// Withdraw unspent send credits to the sender's USDC account, all of them when `amount`
+    /// is `None`. Stays available while the contract is paused, like claims.
+    /// Accounts:
+    /// 0. `[signer]` Sender
+    /// 1. `[writable]` Send credits account (PDA)
+    /// 2. `[writable]` Mailer state account (PDA)
+    /// 3. `[writable]` Sender USDC account
+    /// 4. `[writable]` Mailer USDC account
+    /// 5. `[]` Token program
+    WithdrawSendCredits { amount: Option<u64> },
+
+    /// Set the claim cap (owner only)
+    /// Priority sends that would leave a recipient claim holding more than `cap` fail with
+    /// `ClaimCapExceeded`, or downgrade to standard sends when the sender allows it. 0 removes
+    /// the cap.
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    SetClaimCap { cap: u64 },
+
+    /// Create or update a subscription plan (owner only)
+    /// `duration` and `max_messages` must be positive. Changes apply to subscriptions and
+    /// renewals made afterwards; inactive plans accept neither.
+    /// Accounts:
+    /// 0. `[writable, signer]` Owner (pays for account creation)
+    /// 1. `[]` Mailer state account (PDA)
+    /// 2. `[writable]` Plan account (PDA)
+    /// 3. `[]` System program
+    SetPlan {
+        plan_id: u64,
+        price: u64,
+        duration: i64,
+        max_messages: u64,
+        active: bool,
+    },
+
+    /// Subscribe to a plan, paying its price in USDC for one period from now
+    /// The price accrues to the owner. Fails with `SubscriptionActive` while the subscriber's
+    /// current subscription is active; renew it instead. An expired or exhausted subscription
+    /// is replaced, possibly with another plan.
+    /// Accounts:
+    /// 0. `[writable, signer]` Subscriber (pays for account creation)
+    /// 1. `[]` Plan account (PDA)
+    /// 2. `[writable]` Subscription account (PDA)
+    /// 3. `[writable]` Mailer state account (PDA)
+    /// 4. `[writable]` Subscriber USDC account
+    /// 5. `[writable]` Mailer USDC account
+    /// 6. `[]` Token program
+    /// 7. `[]` System program
+    Subscribe { plan_id: u64 },
+
+    /// Renew a subscription for another period of its plan, paying the plan's current price
+    /// An unexpired subscription is extended by the plan's duration and its remaining
+    /// messages topped up by the plan's allowance; an expired one restarts from now.
+    /// Accounts:
+    /// 0. `[signer]` Subscriber
+    /// 1. `[]` Plan account of the subscription (PDA)
+    /// 2. `[writable]` Subscription account (PDA)
+    /// 3. `[writable]` Mailer state account (PDA)
+    /// 4. `[writable]` Subscriber USDC account
+    /// 5. `[writable]` Mailer USDC account
+    /// 6. `[]` Token program
+    RenewSubscription,
+
+    /// Set payload size limits and fee tiers (owner only)
+    /// Sends whose subject, body, mail id, email or webhook id is longer than its limit fail
+    /// with `PayloadTooLarge`, as do webhook registrations and pre-authorizations. With a
+    /// non-zero `fee_tier_len`, each full `fee_tier_len` bytes of a send's payload fields add
+    /// `fee_tier_percent` percent of the send fee before discounts.
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    SetPayloadLimits {
+        limits: PayloadLimits,
+        fee_tier_len: u16,
+        fee_tier_percent: u16,
+    },
+
+    /// Send message to a hashed email address, keeping the address out of transaction data
+    /// `email_hash` is the 32-byte salted SHA-256 of the address (see [`hash_email`]); other
+    /// lengths fail with `InvalidEmailHash`. `hint_id` is an opaque off-chain id the delivery
+    /// backend uses to find the salt and address, and is logged with the hash as
+    /// `email hash {base58} hint {hint_id}` in place of the address. Fees, soft-fail behavior
+    /// and payload limits are as for SendToEmail, with the hash counting as the email, and so
+    /// are the trailing MessageNonce PDA and system program.
+    /// Accounts:
+    /// 0. `[writable, signer]` Sender (pays for its message nonce account)
+    /// 1. `[writable]` Mailer state account (PDA)
+    /// 2. `[writable]` Sender USDC account
+    /// 3. `[writable]` Mailer USDC account
+    /// 4. `[]` Token program
+    SendToEmailHash {
+        email_hash: Vec<u8>,
+        hint_id: u64,
+        subject: String,
+        _body: String,
+        strict: bool,
+    },
+
+    /// Set the vault solvency threshold (owner only)
+    /// Priority sends paid in USDC fail with `TemporarilyInsolvent`, even when not strict, while
+    /// the mailer USDC vault holds less than `min_coverage_bps` of the owner and recipient
+    /// claimable balances and the unspent send credits, and log `SolvencyAlert` with the vault balance and liabilities for
+    /// operators. 0 disables the check.
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    SetSolvencyThreshold { min_coverage_bps: u16 },
+
+    /// Restart the caller's USDC claim window before it expires
+    /// Debits a 0.02 USDC fee from the claimable balance to the owner and starts a fresh 60-day
+    /// window for the remaining balance. At most 2 extensions are allowed between two recorded
+    /// shares; further ones fail with `ClaimExtensionLimitReached`, and balances that cannot
+    /// cover the fee with `ClaimExtensionFeeUnpaid`.
+    /// Accounts:
+    /// 0. `[signer]` Recipient
+    /// 1. `[writable]` Recipient claim account (PDA)
+    /// 2. `[writable]` Mailer state account (PDA)
+    ExtendClaimWindow,
+
+    /// Register, replace or remove the backup beneficiary of the caller's USDC claim
+    /// A registered beneficiary may collect the claim during a 30-day grace period after it
+    /// expires, before the owner can sweep it. `None` closes the beneficiary account and
+    /// refunds its rent to the recipient.
+    /// Accounts:
+    /// 0. `[writable, signer]` Recipient (pays for the beneficiary account on first registration)
+    /// 1. `[writable]` Claim beneficiary account (PDA)
+    /// 2. `[]` Mailer state account (PDA)
+    /// 3. `[]` System program
+    SetClaimBeneficiary { beneficiary: Option<Pubkey> },
+
+    /// Collect an expired USDC claim as the recipient's registered beneficiary
+    /// Open from the claim's expiry until the 30-day grace period ends; earlier calls fail with
+    /// `ClaimPeriodNotExpired` and later ones with `BeneficiaryGracePeriodEnded`.
+    /// Accounts:
+    /// 0. `[signer]` Beneficiary
+    /// 1. `[]` Claim beneficiary account (PDA)
+    /// 2. `[writable]` Recipient claim account (PDA)
+    /// 3. `[writable]` Mailer state account (PDA)
+    /// 4. `[writable]` Beneficiary USDC account
+    /// 5. `[writable]` Mailer USDC account
+    /// 6. `[]` Token program
+    ClaimAsBeneficiary { recipient: Pubkey },
+
+    /// Upgrade the mailer state account to the next layout version (owner only)
+    /// Reallocs the account to the next layout's size, with the owner topping up its rent,
+    /// and converts the state. Every other instruction fails with `StateMigrationRequired`
+    /// until the state reaches `MAILER_STATE_VERSION`; further calls fail with
+    /// `StateAlreadyCurrent`.
+    /// Accounts:
+    /// 0. `[writable, signer]` Owner (pays for the additional rent)
+    /// 1. `[writable]` Mailer state account (PDA)
+    /// 2. `[]` System program
+    MigrateState,
+
+    /// Set or clear the treasury token account (owner only)
+    /// `Some(token_account)` routes the owner's share of USDC send fees, both the standard
+    /// 10% and the owner cut of priority fees, from the mailer vault to `token_account`,
+    /// which must be a USDC account owned by the treasury PDA. Sends must then pass it as a
+    /// trailing writable account and fail with `InvalidTreasury` without it, and
+    /// `ClaimOwnerShare` pays out its balance. Delegation fees, surcharges and fees paid in
+    /// lamports or whitelisted mints still accrue to `owner_claimable`. `None` keeps the
+    /// owner share in the vault again.
+    /// Accounts:
+    /// 0. `[signer]` Owner
+    /// 1. `[writable]` Mailer state account (PDA)
+    /// 2. `[]` Treasury USDC account (required when setting)
+    SetTreasury { token_account: Option<Pubkey> },
+
+    /// Check that the mailer USDC vault covers its liabilities (permissionless)
+    /// Sums the balances of up to 32 recipient claims in the default mint, passed in
+    /// ascending address order, and requires the vault to hold at least `owner_claimable` and
+    /// `credit_liabilities` plus the larger of that sum and the incrementally tracked
+    /// `recipient_liabilities`.
+    /// Logs `SolvencyCheck` with the balances and their delta and sets the return data to a
+    /// Borsh `SolvencyReport`; a vault short of its liabilities fails with
+    /// `TemporarilyInsolvent`. Monitoring bots can simulate it after incidents, paging through
+    /// the claims in batches.
+    /// Accounts:
+    /// 0. `[]` Mailer state account (PDA)
+    /// 1. `[]` Mailer USDC account
+    /// 2. `[]` Recipient claim accounts (PDAs, up to 32, ascending)
+    VerifySolvency,
+
+    /// Fund a merkle airdrop of claimable USDC shares (owner only)
+    /// Creates distribution `distribution_id` for the tree `merkle_root` over `leaf_count`
+    /// leaves (1 to 32,768) and transfers `total` from the owner into the mailer vault. Each
+    /// leaf is then credited once to its recipient's claim with `ClaimFromMerkle`; an existing
+    /// distribution fails with `AlreadyInitialized`.
+    /// Accounts:
+    /// 0. `[writable, signer]` Owner (pays for the distribution account)
+    /// 1. `[]` Mailer state account (PDA)
+    /// 2. `[writable]` Claim distribution account (PDA)
+    /// 3. `[writable]` Owner USDC account
+    /// 4. `[writable]` Mailer USDC account
+    /// 5. `[]` Token program
+    /// 6. `[]` System program
+    SetClaimMerkleRoot {
+        distribution_id: u64,
+        merkle_root: [u8; 32],
+        leaf_count: u64,
+        total: u64,
+    },
+
+    /// Credit a recipient's claim with their leaf of a merkle airdrop
+    /// `proof` holds the sibling hashes from leaf `index`, committing to the recipient and
+    /// `amount`, up to the distribution's root; a wrong proof fails with `InvalidMerkleProof`
+    /// and a second claim of the leaf with `DistributionLeafClaimed`. The amount joins the
+    /// recipient's USDC claim, created if needed, and starts a fresh claim window; it is then
+    /// paid out with `ClaimRecipientShare` like any other share.
+    /// Accounts:
+    /// 0. `[writable, signer]` Recipient (pays for the claim account on first use)
+    /// 1. `[writable]` Claim distribution account (PDA)
+    /// 2. `[writable]` Recipient claim account (PDA)
+    /// 3. `[writable]` Mailer state account (PDA)
+    /// 4. `[]` System program
+    ClaimFromMerkle {
+        distribution_id: u64,
+        index: u64,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+    },
+
+    /// Send a priority message whose fee is held in escrow until the recipient accepts it
+    /// The full priority fee, after discounts and the recipient's inbox policy, moves into the
+    /// mailer vault and is recorded in escrow `nonce` of the sender. The recipient has 7 days
+    /// to `AcceptMessage`, paying the fee out with the usual revenue split; after that the
+    /// sender may take it back with `ReclaimEscrow`.
+    /// Accounts:
+    /// 0. `[writable, signer]` Sender (pays for the escrow account)
+    /// 1. `[writable]` Escrow account (PDA)
+    /// 2. `[]` Mailer state account (PDA)
+    /// 3. `[writable]` Sender USDC account
+    /// 4. `[writable]` Mailer USDC account
+    /// 5. `[]` Token program
+    /// 6. `[]` System program
+    SendEscrowed {
+        to: Pubkey,
+        subject: String,
+        _body: String,
+        nonce: u64,
+    },
+
+    /// Accept an escrowed message, paying its fee out as a priority send (recipient only)
+    /// The recipient's claim is credited 90% of the escrowed fee and the owner 10% (or the
+    /// treasury, once set); the escrow is closed and its rent returned to the sender. Expired
+    /// escrows fail with `EscrowExpired`.
     /// Accounts:
-    /// 0. `[signer]` Sender
-    /// 1. `[writable]` Recipient claim account (PDA)
+    /// 0. `[writable, signer]` Recipient (pays for the claim account on first use)
+    /// 1. `[writable]` Escrow account (PDA)
+    /// 2. `[writable]` Recipient claim account (PDA)
+    /// 3. `[writable]` Mailer state account (PDA)
+    /// 4. `[writable]` Mailer USDC account
+    /// 5. `[]` Token program
+    /// 6. `[]` System program
+    /// 7. `[writable]` Sender (escrow rent refund)
+    /// 8. `[writable]` Treasury USDC account (when a treasury is set)
+    AcceptMessage { sender: Pubkey, nonce: u64 },
+
+    /// Take back the fee of an escrowed message its recipient did not accept in time
+    /// Fails with `EscrowNotExpired` while the recipient may still accept it. The fee is
+    /// returned from the mailer vault and the escrow closed, refunding its rent.
+    /// Accounts:
+    /// 0. `[writable, signer]` Sender
+    /// 1. `[writable]` Escrow account (PDA)
     /// 2. `[]` Mailer state account (PDA)
     /// 3. `[writable]` Sender USDC account
     /// 4. `[writable]` Mailer USDC account
     /// 5. `[]` Token program
+    ReclaimEscrow { nonce: u64 },
+
+    /// Create an empty mailing list `list_id` owned by the signer
+    /// Accounts:
+    /// 0. `[writable, signer]` List owner (pays for the list account)
+    /// 1. `[writable]` Mailing list account (PDA)
+    /// 2. `[]` System program
+    CreateList { list_id: u64 },
+
+    /// Add `member` to chunk `chunk` of one of the signer's mailing lists
+    /// `chunk` is an existing chunk with room left or, to create it, the list's `chunk_count`;
+    /// anything else fails with `InvalidListChunk`. A member already in the chunk fails with
+    /// `ListMemberExists`.
+    /// Accounts:
+    /// 0. `[writable, signer]` List owner (pays for new chunk accounts)
+    /// 1. `[writable]` Mailing list account (PDA)
+    /// 2. `[writable]` List chunk account (PDA)
+    /// 3. `[]` System program
+    AddListMember {
+        list_id: u64,
+        chunk: u64,
+        member: Pubkey,
+    },
+
+    /// Remove `member` from chunk `chunk` of one of the signer's mailing lists
+    /// Fails with `NotListMember` when the chunk does not hold `member`.
+    /// Accounts:
+    /// 0. `[signer]` List owner
+    /// 1. `[writable]` Mailing list account (PDA)
+    /// 2. `[writable]` List chunk account (PDA)
+    RemoveListMember {
+        list_id: u64,
+        chunk: u64,
+        member: Pubkey,
+    },
+
+    /// Broadcast a message to every member of one of the signer's mailing lists
+    /// The signer pays the standard send fee (10% of the send fee, after their discount) once
+    /// per member, and one `ListMailSent` event is logged per chunk for off-chain delivery to
+    /// fan out. Lists without members fail with `EmptyList`. The owner's MessageNonce PDA is
+    /// required as for Send, with the system program as a trailing account until it exists.
+    /// Accounts:
+    /// 0. `[writable, signer]` List owner
+    /// 1. `[]` Mailing list account (PDA)
+    /// 2. `[writable]` Mailer state account (PDA)
+    /// 3. `[writable]` Sender USDC account
+    /// 4. `[writable]` Mailer USDC account
+    /// 5. `[]` Token program
+    /// 6. `[writable]` Treasury USDC account (when a treasury is set)
+    SendToList {
+        list_id: u64,
+        subject: String,
+        _body: String,
+    },
+
+    /// Send a message to the current owner of a `.sol` domain
+    /// `domain` may carry its `.sol` suffix. The name account must be the Solana Name Service
+    /// account of the domain; its recorded owner becomes the recipient and the send then runs
+    /// as a `Send` to that wallet, with the same trailing accounts. Unregistered domains and
+    /// mismatched name accounts fail with `InvalidDomain`.
+    /// Accounts:
+    /// 0. `[writable, signer]` Sender
+    /// 1. `[]` Domain name account (Solana Name Service)
+    /// 2. `[writable]` Recipient claim account of the domain owner (PDA)
+    /// 3. `[writable]` Mailer state account (PDA)
+    /// 4. `[writable]` Sender USDC account
+    /// 5. `[writable]` Mailer USDC account
+    /// 6. `[]` Token program
+    /// 7. `[]` System program
+    SendToDomain {
+        domain: String,
+        subject: String,
+        _body: String,
+        revenue_share_to_receiver: bool,
+        strict: bool,
+    },
+
+    /// Bind an EVM address to the signing wallet
+    /// The instruction before this one must be a secp256k1 program instruction checking one
+    /// signature by `evm_address` over [`evm::binding_message`] for the wallet, with the
+    /// address, signature and message in its own data; otherwise the bind fails with
+    /// `InvalidEvmSignature`. Binding an already bound address moves it to the new wallet.
+    /// Accounts:
+    /// 0. `[writable, signer]` Wallet (pays for the mapping account on first bind)
+    /// 1. `[writable]` EVM mapping account (PDA)
+    /// 2. `[]` Instructions sysvar
+    /// 3. `[]` System program
+    BindEvmAddress { evm_address: [u8; 20] },
+
+    /// Send a message to the wallet an EVM address is bound to
+    /// The bound wallet becomes the recipient and the send then runs as a `Send` to it, with
+    /// the same trailing accounts. Unbound addresses fail with `EvmAddressNotBound`.
+    /// Accounts:
+    /// 0. `[writable, signer]` Sender
+    /// 1. `[]` EVM mapping account (PDA)
+    /// 2. `[writable]` Recipient claim account of the bound wallet (PDA)
+    /// 3. `[writable]` Mailer state account (PDA)
+    /// 4. `[writable]` Sender USDC account
+    /// 5. `[writable]` Mailer USDC account
+    /// 6. `[]` Token program
+    /// 7. `[]` System program
+    SendToEvmAddress {
+        evm_address: [u8; 20],
+        subject: String,
+        _body: String,
+        revenue_share_to_receiver: bool,
+        strict: bool,
+    },
+
+    /// Point bridged sends at a Wormhole core bridge, or switch bridging off with `None`
+    /// (owner only)
+    /// The bridge's config, fee collector and the program emitter's sequence account are
+    /// derived and stored in the wormhole config account, created on first use. Bridged sends
+    /// then take as trailing accounts: the wormhole config `[writable]`, the core bridge
+    /// program, its config `[writable]`, the next message account `[writable]` (PDA numbered
+    /// by the config's message count), the emitter (PDA), its sequence account `[writable]`,
+    /// the fee collector `[writable]`, and the clock and rent sysvars.
+    /// Accounts:
+    /// 0. `[writable, signer]` Owner (pays for the wormhole config account on first use)
+    /// 1. `[]` Mailer state account (PDA)
+    /// 2. `[writable]` Wormhole config account (PDA)
+    /// 3. `[]` System program
+    SetWormholeBridge { core_bridge: Option<Pubkey> },
+
+    /// Read the fees charged now as a Borsh `FeeView` in the return data
+    /// Programs read it with `get_return_data` after invoking, as `cpi::get_fee` does.
+    /// Accounts:
+    /// 0. `[]` Mailer state account (PDA)
+    GetFee {
+        reserved: instruction_versions::Reserved,
+    },
+
+    /// Read the recipient share `account` could claim now as a Borsh `ClaimableView` in the
+    /// return data
+    /// A claim account not created yet reads as an empty share.
+    /// Accounts:
+    /// 0. `[]` Mailer state account (PDA)
+    /// 1. `[]` Recipient claim account of `account` (PDA)
+    GetClaimable {
+        account: Pubkey,
+        reserved: instruction_versions::Reserved,
+    },
+
+    /// Read the delegation of `delegator` as a Borsh `DelegationView` in the return data
+    /// A delegation account not created yet reads as no delegate.
+    /// Accounts:
+    /// 0. `[]` Delegation account of `delegator` (PDA)
+    GetDelegation {
+        delegator: Pubkey,
+        reserved: instruction_versions::Reserved,
+    },
+
+    /// Replace the volume discount tiers applied to send fees (owner only)
+    /// At most 8 tiers, ascending in `min_fees_paid` and `discount`, with discounts of at most
+    /// 100% (`InvalidDiscountSchedule` otherwise); no tiers switch volume discounts off. The
+    /// discount schedule account is created on first use.
+    /// Sends passing the schedule and the sender's SenderStats PDA as trailing accounts are
+    /// charged the discount of the highest tier the sender's paid fees reach, or their
+    /// FeeDiscount when that is larger. Send, SendPrepared, SendThroughWebhook and the email
+    /// sends then add the owner's share of the USDC fee they paid, the whole standard fee or
+    /// what a priority fee leaves after the recipient share, to the stats account, created
+    /// on first use and paid by the sender; the send crossing a tier is charged at the
+    /// previous one.
+    /// Accounts:
+    /// 0. `[writable, signer]` Owner (pays for the discount schedule account on first use)
+    /// 1. `[]` Mailer state account (PDA)
+    /// 2. `[writable]` Discount schedule account (PDA)
+    /// 3. `[]` System program
+    SetDiscountSchedule {
+        tiers: Vec<VolumeTier>,
+        reserved: instruction_versions::Reserved,
+    },
+
+    /// Mint up to 100 USDC of the fee token to the caller (devnet builds only)
+    /// Compiled only with the `devnet-faucet` feature and kept last so mainnet builds keep
+    /// every other instruction tag. The USDC mint's authority must be the mailer PDA, which
+    /// only devnet deployments set up. Each caller may drip once an hour; earlier calls fail
+    /// with `RateLimitExceeded`, and amounts of 0 or over the maximum with `InvalidClaimAmount`.
+    /// Accounts:
+    /// 0. `[writable, signer]` Caller (pays for the faucet drip account on first use)
+    /// 1. `[writable]` Faucet drip account (PDA)
+    /// 2. `[]` Mailer state account (PDA)
+    /// 3. `[writable]` USDC mint
+    /// 4. `[writable]` Caller USDC account
+    /// 5. `[]` Token program
     /// 6. `[]` System program
-    SendThroughWebhook {
+    #[cfg(feature = "devnet-faucet")]
+    DevFaucet { amount: u64 },
+}
+
+/// Message id logged and returned by every Send* instruction: the SHA-256 of the sender,
+/// recipient, payload hash, slot and nonce.
+/// The recipient is the wallet's key, the email address as logged, the email hash, or the
+/// mailing list account. The payload hash is [`hash_message_body`] for inline content and the
+/// SHA-256 of the mailId or webhookId otherwise. The nonce is taken from the sender's
+/// MessageNonce account, so identical sends between the same parties in one slot get
+/// different ids.
+pub fn derive_message_id(
+    sender: &Pubkey,
+    recipient: &[u8],
+    payload_hash: &[u8; 32],
+    slot: u64,
+    nonce: u64,
+) -> [u8; 32] {
+    hashv(&[
+        sender.as_ref(),
+        recipient,
+        payload_hash,
+        &slot.to_le_bytes(),
+        &nonce.to_le_bytes(),
+    ])
+    .to_bytes()
+}
+
+/// Payload hash of a message with inline content: the SHA-256 of the Borsh encoding of its
+/// subject and body
+pub fn hash_message_body(subject: &str, body: &str) -> [u8; 32] {
+    hashv(&[
+        &(subject.len() as u32).to_le_bytes(),
+        subject.as_bytes(),
+        &(body.len() as u32).to_le_bytes(),
+        body.as_bytes(),
+    ])
+    .to_bytes()
+}
+
+/// Bytes of the recipient key keying its event logs, see [`recipient_log_prefix`]
+pub const RECIPIENT_LOG_KEY_LEN: usize = 8;
+
+/// Prefix opening the structured event logs about the wallet `recipient`: `[rcpt:`, the
+/// lowercase hex of its first [`RECIPIENT_LOG_KEY_LEN`] bytes, then `] `.
+/// Sends to a wallet, their MailDropped, DowngradedToStandard and EncryptedMail logs, and
+/// recipient claims with their ClaimSplit logs carry it, so per-user notification services
+/// can filter program logs by substring instead of decoding every one.
+pub fn recipient_log_prefix(recipient: &Pubkey) -> String {
+    let digits: String = recipient.as_ref()[..RECIPIENT_LOG_KEY_LEN]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("[rcpt:{}] ", digits)
+}
+
+/// Email hash taken by SendToEmailHash: the SHA-256 of `salt` followed by the address bytes.
+/// The address is hashed as given, so senders and the delivery backend must agree on its
+/// normalization, such as [`normalize_email`].
+pub fn hash_email(salt: &[u8], email: &str) -> [u8; 32] {
+    hashv(&[salt, email.as_bytes()]).to_bytes()
+}
+
+/// Email address as SendToEmail logs it: checked and lowercased ASCII.
+/// Addresses must be 6 to 254 bytes of printable ASCII without spaces, with a single '@'
+/// between a non-empty local part and a domain holding a dot that neither starts nor ends
+/// it; anything else is `InvalidEmailAddress`.
+pub fn normalize_email(email: &str) -> Result<String, MailerError> {
+    let valid = (MIN_EMAIL_LEN..=MAX_EMAIL_LEN).contains(&email.len())
+        && email.bytes().all(|byte| byte.is_ascii_graphic())
+        && match email.split_once('@') {
+            Some((local, domain)) => {
+                !local.is_empty()
+                    && !domain.contains('@')
+                    && domain.contains('.')
+                    && !domain.starts_with('.')
+                    && !domain.ends_with('.')
+            }
+            None => false,
+        };
+    if !valid {
+        return Err(MailerError::InvalidEmailAddress);
+    }
+    Ok(email.to_ascii_lowercase())
+}
+
+/// Main instruction processor
+/// Logs the name of the Mailer error failing the instruction, if any.
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let result = dispatch_instruction(program_id, accounts, instruction_data);
+    if let Err(error) = &result {
+        error.print::<MailerError>();
+    }
+    result
+}
+
+/// Decode and process one instruction
+fn dispatch_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let decoded = instruction_versions::decode(instruction_data)?;
+    let (instruction, tag) = (decoded.instruction, decoded.tag);
+
+    assert_instruction_enabled(program_id, accounts, &instruction, tag)?;
+
+    match instruction {
+        MailerInstruction::Initialize { usdc_mint } => {
+            process_initialize(program_id, accounts, usdc_mint)
+        }
+        MailerInstruction::Send {
+            to,
+            subject,
+            _body,
+            revenue_share_to_receiver,
+            resolve_sender_to_name,
+            request_read_receipt,
+            receipt_nonce,
+            memo,
+            strict,
+            thread_id,
+            in_reply_to,
+            allow_downgrade,
+            tip,
+            bridge,
+            idempotency_nonce,
+        } => process_send(
+            program_id,
+            accounts,
+            to,
+            subject,
+            _body,
+            revenue_share_to_receiver,
+            resolve_sender_to_name,
+            request_read_receipt,
+            receipt_nonce,
+            memo,
+            strict,
+            thread_id,
+            in_reply_to,
+            allow_downgrade,
+            tip,
+            bridge,
+            idempotency_nonce,
+        ),
+        MailerInstruction::SendPrepared {
+            to,
+            mail_id,
+            revenue_share_to_receiver,
+            resolve_sender_to_name,
+            strict,
+            thread_id,
+            in_reply_to,
+            allow_downgrade,
+            tip,
+        } => process_send_prepared(
+            program_id,
+            accounts,
+            to,
+            mail_id,
+            revenue_share_to_receiver,
+            resolve_sender_to_name,
+            strict,
+            thread_id,
+            in_reply_to,
+            allow_downgrade,
+            tip,
+        ),
+        MailerInstruction::SendToEmail {
+            to_email,
+            subject,
+            _body,
+            strict,
+        } => process_send_to_email(program_id, accounts, to_email, subject, _body, strict),
+        MailerInstruction::SendPreparedToEmail {
+            to_email,
+            mail_id,
+            strict,
+        } => process_send_prepared_to_email(program_id, accounts, to_email, mail_id, strict),
+        MailerInstruction::SendThroughWebhook {
+            to,
+            webhook_id,
+            revenue_share_to_receiver,
+            resolve_sender_to_name,
+            strict,
+            thread_id,
+            in_reply_to,
+            allow_downgrade,
+            tip,
+        } => process_send_through_webhook(
+            program_id,
+            accounts,
+            to,
+            webhook_id,
+            revenue_share_to_receiver,
+            resolve_sender_to_name,
+            strict,
+            thread_id,
+            in_reply_to,
+            allow_downgrade,
+            tip,
+        ),
+        MailerInstruction::ClaimRecipientShare {
+            amount,
+            split,
+            close_to,
+        } => process_claim_recipient_share(program_id, accounts, amount, split, close_to),
+        MailerInstruction::ClaimOwnerShare => process_claim_owner_share(program_id, accounts),
+        MailerInstruction::SetFee {
+            new_fee,
+            expected_current_value,
+        } => process_set_fee(program_id, accounts, new_fee, expected_current_value),
+        MailerInstruction::DelegateTo { delegate } => {
+            process_delegate_to(program_id, accounts, delegate)
+        }
+        MailerInstruction::RejectDelegation => process_reject_delegation(program_id, accounts),
+        MailerInstruction::SetDelegationFee {
+            new_fee,
+            expected_current_value,
+        } => process_set_delegation_fee(program_id, accounts, new_fee, expected_current_value),
+        MailerInstruction::SetCustomFeePercentage {
+            account,
+            percentage,
+        } => process_set_custom_fee_percentage(program_id, accounts, account, percentage),
+        MailerInstruction::ClearCustomFeePercentage { account } => {
+            process_clear_custom_fee_percentage(program_id, accounts, account)
+        }
+        MailerInstruction::Pause => process_pause(program_id, accounts),
+        MailerInstruction::Unpause => process_unpause(program_id, accounts),
+        MailerInstruction::DistributeClaimableFunds { recipient } => {
+            process_distribute_claimable_funds(program_id, accounts, recipient)
+        }
+        MailerInstruction::ClaimExpiredShares { recipient } => {
+            process_claim_expired_shares(program_id, accounts, recipient)
+        }
+        MailerInstruction::EmergencyUnpause => process_emergency_unpause(program_id, accounts),
+        MailerInstruction::SetFeePaused { fee_paused } => {
+            process_set_fee_paused(program_id, accounts, fee_paused)
+        }
+        MailerInstruction::SetRecipientPrefs {
+            read_receipts_opt_out,
+        } => process_set_recipient_prefs(program_id, accounts, read_receipts_opt_out),
+        MailerInstruction::AcknowledgeMessage {
+            sender,
+            receipt_nonce,
+        } => process_acknowledge_message(program_id, accounts, sender, receipt_nonce),
+        MailerInstruction::SetProtocolSendsEnabled { enabled } => {
+            process_set_protocol_sends_enabled(program_id, accounts, enabled)
+        }
+        MailerInstruction::SendProtocolMessage { to, subject, _body } => {
+            process_send_protocol_message(program_id, accounts, to, subject, _body)
+        }
+        MailerInstruction::TransferOwnership { new_owner } => {
+            process_transfer_ownership(program_id, accounts, new_owner)
+        }
+        MailerInstruction::AcceptOwnership => process_accept_ownership(program_id, accounts),
+        MailerInstruction::SetAdmins { admins, threshold } => {
+            process_set_admins(program_id, accounts, admins, threshold)
+        }
+        MailerInstruction::SweepExpiredPage { max_items } => {
+            process_sweep_expired_page(program_id, accounts, max_items)
+        }
+        MailerInstruction::ResetSweepCursor => process_reset_sweep_cursor(program_id, accounts),
+        MailerInstruction::ProposeFee { new_fee } => {
+            process_propose_fee(program_id, accounts, new_fee)
+        }
+        MailerInstruction::SetFeeTimelock { delay } => {
+            process_set_fee_timelock(program_id, accounts, delay)
+        }
+        MailerInstruction::SetInboxPolicy {
+            mode,
+            senders,
+            penalty_multiplier,
+            min_fee,
+        } => process_set_inbox_policy(
+            program_id,
+            accounts,
+            mode,
+            senders,
+            penalty_multiplier,
+            min_fee,
+        ),
+        MailerInstruction::ClaimRecipientShareToAta => {
+            process_claim_recipient_share_to_ata(program_id, accounts)
+        }
+        MailerInstruction::RegisterSessionKey {
+            session_key,
+            scope,
+            expires_at,
+        } => process_register_session_key(program_id, accounts, session_key, scope, expires_at),
+        MailerInstruction::RevokeSessionKey { session_key } => {
+            process_revoke_session_key(program_id, accounts, session_key)
+        }
+        MailerInstruction::SetPayoutAddress {
+            payout_token_account,
+        } => process_set_payout_address(program_id, accounts, payout_token_account),
+        MailerInstruction::SetConversionVault { mint, enabled } => {
+            process_set_conversion_vault(program_id, accounts, mint, enabled)
+        }
+        MailerInstruction::ClaimRecipientShareAs { mint } => {
+            process_claim_recipient_share_as(program_id, accounts, mint)
+        }
+        MailerInstruction::GetConfig => process_get_config(program_id, accounts),
+        MailerInstruction::CreatePreauthorization {
+            relayer,
+            webhook_id,
+            max_sends,
+            max_fee_per_send,
+            expires_at,
+        } => process_create_preauthorization(
+            program_id,
+            accounts,
+            relayer,
+            webhook_id,
+            max_sends,
+            max_fee_per_send,
+            expires_at,
+        ),
+        MailerInstruction::RevokePreauthorization { relayer } => {
+            process_revoke_preauthorization(program_id, accounts, relayer)
+        }
+        MailerInstruction::SendPreauthorized {
+            to,
+            webhook_id,
+            revenue_share_to_receiver,
+        } => process_send_preauthorized(
+            program_id,
+            accounts,
+            to,
+            webhook_id,
+            revenue_share_to_receiver,
+        ),
+        MailerInstruction::SetInstructionDisabled {
+            tag: target,
+            disabled,
+        } => process_set_instruction_disabled(program_id, accounts, target, disabled),
+        MailerInstruction::MigrateDiscriminator => {
+            process_migrate_discriminator(program_id, accounts)
+        }
+        MailerInstruction::CloseRecipientClaim { recipient } => {
+            process_close_recipient_claim(program_id, accounts, recipient)
+        }
+        MailerInstruction::CloseDelegation => process_close_delegation(program_id, accounts),
+        MailerInstruction::CloseFeeDiscount { account } => {
+            process_close_fee_discount(program_id, accounts, account)
+        }
+        MailerInstruction::MigrateAccountVersion { kind } => {
+            process_migrate_account_version(program_id, accounts, kind)
+        }
+        MailerInstruction::SetSpamOracle { oracle, threshold } => {
+            process_set_spam_oracle(program_id, accounts, oracle, threshold)
+        }
+        MailerInstruction::SetRateLimit { max_sends, window } => {
+            process_set_rate_limit(program_id, accounts, max_sends, window)
+        }
+        MailerInstruction::CreatePromo {
+            code_hash,
+            discount,
+            max_redemptions,
+            expiry,
+        } => process_create_promo(
+            program_id,
+            accounts,
+            code_hash,
+            discount,
+            max_redemptions,
+            expiry,
+        ),
+        MailerInstruction::RedeemPromo { code } => process_redeem_promo(program_id, accounts, code),
+        MailerInstruction::ClaimAndSend {
+            amount,
+            to,
+            subject,
+            _body,
+            revenue_share_to_receiver,
+            resolve_sender_to_name,
+            request_read_receipt,
+            receipt_nonce,
+            memo,
+            strict,
+        } => process_claim_and_send(
+            program_id,
+            accounts,
+            amount,
+            to,
+            subject,
+            _body,
+            revenue_share_to_receiver,
+            resolve_sender_to_name,
+            request_read_receipt,
+            receipt_nonce,
+            memo,
+            strict,
+        ),
+        MailerInstruction::AddFeeMint {
+            mint,
+            send_fee,
+            delegation_fee,
+        } => process_add_fee_mint(program_id, accounts, mint, send_fee, delegation_fee),
+        MailerInstruction::RemoveFeeMint { mint } => {
+            process_remove_fee_mint(program_id, accounts, mint)
+        }
+        MailerInstruction::SetLamportFee { lamport_fee } => {
+            process_set_lamport_fee(program_id, accounts, lamport_fee)
+        }
+        MailerInstruction::ClaimOwnerLamports => process_claim_owner_lamports(program_id, accounts),
+        MailerInstruction::SetFeeOracle {
+            oracle,
+            target_fee,
+            max_age,
+        } => process_set_fee_oracle(program_id, accounts, oracle, target_fee, max_age),
+        MailerInstruction::SetClaimCreationPolicy { policy } => {
+            process_set_claim_creation_policy(program_id, accounts, policy)
+        }
+        MailerInstruction::CloseFundedClaim => process_close_funded_claim(program_id, accounts),
+        MailerInstruction::SendEncrypted {
+            to,
+            ephemeral_key,
+            nonce,
+            ciphertext,
+            revenue_share_to_receiver,
+            strict,
+        } => process_send_encrypted(
+            program_id,
+            accounts,
+            to,
+            ephemeral_key,
+            nonce,
+            ciphertext,
+            revenue_share_to_receiver,
+            strict,
+        ),
+        MailerInstruction::RegisterWebhook {
+            webhook_id,
+            endpoint_hash,
+            surcharge,
+        } => process_register_webhook(program_id, accounts, webhook_id, endpoint_hash, surcharge),
+        MailerInstruction::UpdateWebhook {
+            endpoint_hash,
+            surcharge,
+        } => process_update_webhook(program_id, accounts, Some((endpoint_hash, surcharge))),
+        MailerInstruction::RevokeWebhook => process_update_webhook(program_id, accounts, None),
+        MailerInstruction::ClaimWebhookRevenue => {
+            process_claim_webhook_revenue(program_id, accounts)
+        }
+        MailerInstruction::InitializeClosureLog => {
+            process_initialize_closure_log(program_id, accounts)
+        }
+        MailerInstruction::SendAsDelegator {
+            to,
+            subject,
+            _body,
+            revenue_share_to_receiver,
+            strict,
+        } => process_send_as_delegator(
+            program_id,
+            accounts,
+            to,
+            subject,
+            _body,
+            revenue_share_to_receiver,
+            strict,
+        ),
+        MailerInstruction::SanctionAddress { address } => {
+            process_sanction_address(program_id, accounts, address)
+        }
+        MailerInstruction::UnsanctionAddress { address } => {
+            process_unsanction_address(program_id, accounts, address)
+        }
+        MailerInstruction::SetSanctionsEnabled { enabled } => {
+            process_set_sanctions_enabled(program_id, accounts, enabled)
+        }
+        MailerInstruction::SetBackupMint { mint } => {
+            process_set_backup_mint(program_id, accounts, mint)
+        }
+        MailerInstruction::InitializeStats => process_initialize_stats(program_id, accounts),
+        MailerInstruction::SetClaimNoExpiry { enabled } => {
+            process_set_claim_no_expiry(program_id, accounts, enabled)
+        }
+        MailerInstruction::ClaimExpiredSharesBatch => {
+            process_claim_expired_shares_batch(program_id, accounts)
+        }
+        MailerInstruction::SetPauseFlags { flags } => {
+            process_set_pause_flags(program_id, accounts, flags)
+        }
+        MailerInstruction::SetKycVerifier { verifier } => {
+            process_set_kyc_verifier(program_id, accounts, verifier)
+        }
+        MailerInstruction::SetDelegationRefundWindow { window } => {
+            process_set_delegation_refund_window(program_id, accounts, window)
+        }
+        MailerInstruction::AcceptDelegation => process_accept_delegation(program_id, accounts),
+        MailerInstruction::RefundDelegationFee => {
+            process_refund_delegation_fee(program_id, accounts)
+        }
+        MailerInstruction::RecordDeliveryStatus {
+            to,
+            nonce,
+            sent_slot,
+            delivered,
+        } => process_record_delivery_status(program_id, accounts, to, nonce, sent_slot, delivered),
+        MailerInstruction::GetWebhookStats => process_get_webhook_stats(program_id, accounts),
+        MailerInstruction::DepositSendCredits { amount } => {
+            process_deposit_send_credits(program_id, accounts, amount)
+        }
+        MailerInstruction::WithdrawSendCredits { amount } => {
+            process_withdraw_send_credits(program_id, accounts, amount)
+        }
+        MailerInstruction::SetClaimCap { cap } => process_set_claim_cap(program_id, accounts, cap),
+        MailerInstruction::SetPlan {
+            plan_id,
+            price,
+            duration,
+            max_messages,
+            active,
+        } => process_set_plan(
+            program_id,
+            accounts,
+            plan_id,
+            price,
+            duration,
+            max_messages,
+            active,
+        ),
+        MailerInstruction::Subscribe { plan_id } => {
+            process_subscribe(program_id, accounts, plan_id)
+        }
+        MailerInstruction::RenewSubscription => process_renew_subscription(program_id, accounts),
+        MailerInstruction::SetPayloadLimits {
+            limits,
+            fee_tier_len,
+            fee_tier_percent,
+        } => {
+            process_set_payload_limits(program_id, accounts, limits, fee_tier_len, fee_tier_percent)
+        }
+        MailerInstruction::SendToEmailHash {
+            email_hash,
+            hint_id,
+            subject,
+            _body,
+            strict,
+        } => process_send_to_email_hash(
+            program_id, accounts, email_hash, hint_id, subject, _body, strict,
+        ),
+        MailerInstruction::SetSolvencyThreshold { min_coverage_bps } => {
+            process_set_solvency_threshold(program_id, accounts, min_coverage_bps)
+        }
+        MailerInstruction::ExtendClaimWindow => process_extend_claim_window(program_id, accounts),
+        MailerInstruction::SetClaimBeneficiary { beneficiary } => {
+            process_set_claim_beneficiary(program_id, accounts, beneficiary)
+        }
+        MailerInstruction::ClaimAsBeneficiary { recipient } => {
+            process_claim_as_beneficiary(program_id, accounts, recipient)
+        }
+        MailerInstruction::MigrateState => process_migrate_state(program_id, accounts),
+        MailerInstruction::SetTreasury { token_account } => {
+            process_set_treasury(program_id, accounts, token_account)
+        }
+        MailerInstruction::VerifySolvency => process_verify_solvency(program_id, accounts),
+        MailerInstruction::SetClaimMerkleRoot {
+            distribution_id,
+            merkle_root,
+            leaf_count,
+            total,
+        } => process_set_claim_merkle_root(
+            program_id,
+            accounts,
+            distribution_id,
+            merkle_root,
+            leaf_count,
+            total,
+        ),
+        MailerInstruction::ClaimFromMerkle {
+            distribution_id,
+            index,
+            amount,
+            proof,
+        } => process_claim_from_merkle(program_id, accounts, distribution_id, index, amount, proof),
+        MailerInstruction::SendEscrowed {
+            to,
+            subject,
+            _body,
+            nonce,
+        } => process_send_escrowed(program_id, accounts, to, subject, _body, nonce),
+        MailerInstruction::AcceptMessage { sender, nonce } => {
+            process_accept_message(program_id, accounts, sender, nonce)
+        }
+        MailerInstruction::ReclaimEscrow { nonce } => {
+            process_reclaim_escrow(program_id, accounts, nonce)
+        }
+        MailerInstruction::CreateList { list_id } => {
+            process_create_list(program_id, accounts, list_id)
+        }
+        MailerInstruction::AddListMember {
+            list_id,
+            chunk,
+            member,
+        } => process_add_list_member(program_id, accounts, list_id, chunk, member),
+        MailerInstruction::RemoveListMember {
+            list_id,
+            chunk,
+            member,
+        } => process_remove_list_member(program_id, accounts, list_id, chunk, member),
+        MailerInstruction::SendToList {
+            list_id,
+            subject,
+            _body,
+        } => process_send_to_list(program_id, accounts, list_id, subject, _body),
+        MailerInstruction::SendToDomain {
+            domain,
+            subject,
+            _body,
+            revenue_share_to_receiver,
+            strict,
+        } => process_send_to_domain(
+            program_id,
+            accounts,
+            domain,
+            subject,
+            _body,
+            revenue_share_to_receiver,
+            strict,
+        ),
+        MailerInstruction::BindEvmAddress { evm_address } => {
+            process_bind_evm_address(program_id, accounts, evm_address)
+        }
+        MailerInstruction::SendToEvmAddress {
+            evm_address,
+            subject,
+            _body,
+            revenue_share_to_receiver,
+            strict,
+        } => process_send_to_evm_address(
+            program_id,
+            accounts,
+            evm_address,
+            subject,
+            _body,
+            revenue_share_to_receiver,
+            strict,
+        ),
+        MailerInstruction::SetWormholeBridge { core_bridge } => {
+            process_set_wormhole_bridge(program_id, accounts, core_bridge)
+        }
+        MailerInstruction::GetFee { .. } => process_get_fee(program_id, accounts),
+        MailerInstruction::GetClaimable { account, .. } => {
+            process_get_claimable(program_id, accounts, account)
+        }
+        MailerInstruction::GetDelegation { delegator, .. } => {
+            process_get_delegation(program_id, accounts, delegator)
+        }
+        MailerInstruction::SetDiscountSchedule { tiers, .. } => {
+            process_set_discount_schedule(program_id, accounts, tiers)
+        }
+        #[cfg(feature = "devnet-faucet")]
+        MailerInstruction::DevFaucet { amount } => process_dev_faucet(program_id, accounts, amount),
+    }
+}
+
+/// Whether the owner can disable `instruction` with SetInstructionDisabled
+/// Instructions that run before the mailer state exists or is in the current layout, and
+/// the setter itself, stay enabled so a mailer can always be set up and recovered. So do
+/// the wallet-scoped instructions whose accounts do not include the mailer state.
+fn is_switchable(instruction: &MailerInstruction) -> bool {
+    !matches!(
+        instruction,
+        MailerInstruction::Initialize { .. }
+            | MailerInstruction::MigrateState
+            | MailerInstruction::MigrateDiscriminator
+            | MailerInstruction::SetInstructionDisabled { .. }
+            | MailerInstruction::SetRecipientPrefs { .. }
+            | MailerInstruction::AcknowledgeMessage { .. }
+            | MailerInstruction::SetInboxPolicy { .. }
+            | MailerInstruction::RegisterSessionKey { .. }
+            | MailerInstruction::RevokeSessionKey { .. }
+            | MailerInstruction::RevokePreauthorization { .. }
+            | MailerInstruction::UpdateWebhook { .. }
+            | MailerInstruction::RevokeWebhook
+            | MailerInstruction::RecordDeliveryStatus { .. }
+            | MailerInstruction::GetWebhookStats
+            | MailerInstruction::CreateList { .. }
+            | MailerInstruction::AddListMember { .. }
+            | MailerInstruction::RemoveListMember { .. }
+            | MailerInstruction::BindEvmAddress { .. }
+            | MailerInstruction::GetDelegation { .. }
+    )
+}
+
+/// Reject instructions the owner has disabled
+/// Switchable instructions must be passed the mailer state in the current layout, so one
+/// cannot run by leaving the mailer out; it is located among the accounts by discriminator.
+fn assert_instruction_enabled(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction: &MailerInstruction,
+    tag: u8,
+) -> ProgramResult {
+    if !is_switchable(instruction) {
+        return Ok(());
+    }
+
+    let current = discriminator::account("MailerState");
+    let legacy = discriminator::legacy("MailerState");
+    let mailer_account = accounts
+        .iter()
+        .find(|acc| {
+            let discriminator = acc.try_borrow_data().ok().and_then(|data| {
+                let mut discriminator = [0u8; 8];
+                discriminator.copy_from_slice(data.get(..8)?);
+                Some(discriminator)
+            });
+            acc.owner == program_id
+                && discriminator.is_some()
+                && (discriminator == Some(current) || discriminator == legacy)
+        })
+        .ok_or(MailerError::MissingMailerStateAccount)?;
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    if mailer_state.is_instruction_disabled(tag) {
+        msg!("Instruction {} is disabled", tag);
+        return Err(MailerError::InstructionDisabled.into());
+    }
+    Ok(())
+}
+
+/// Initialize the program
+fn process_initialize(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    usdc_mint: Pubkey,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_system_program(system_program)?;
+
+    // Verify mailer account PDA
+    let (mailer_pda, bump) = pda::mailer_state(program_id);
+    if mailer_account.key != &mailer_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    // Snapshot the mint's decimals for send logs, if the mint account was passed
+    let usdc_decimals = match accounts.iter().find(|acc| acc.key == &usdc_mint) {
+        Some(mint_account) => mint_decimals(mint_account)?,
+        None => USDC_DECIMALS,
+    };
+
+    // Create mailer account
+    let rent = Rent::get()?;
+    let space = 8 + MailerState::LEN; // 8 bytes for discriminator
+    let lamports = rent.minimum_balance(space);
+
+    invoke_signed(
+        &system_instruction::create_account(
+            owner.key,
+            mailer_account.key,
+            lamports,
+            space as u64,
+            program_id,
+        ),
+        &[
+            owner.clone(),
+            mailer_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[seeds::MAILER, &[bump]]],
+    )?;
+
+    // Initialize state
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    mailer_data[0..8].copy_from_slice(&discriminator::account("MailerState"));
+
+    let mailer_state = MailerState {
+        owner: *owner.key,
+        usdc_mint,
+        send_fee: SEND_FEE,
+        delegation_fee: DELEGATION_FEE,
+        owner_claimable: 0,
+        paused: false,
+        fee_paused: false,
+        bump,
+        protocol_sends_enabled: false,
+        pending_owner: None,
+        fee_timelock: 0,
+        pending_fee: None,
+        paused_at: 0,
+        total_paused_duration: 0,
+        claim_rent_reserve: 0,
+        delegation_rent_reserve: 0,
+        discount_rent_reserve: 0,
+        disabled_instructions: 0,
+        spam_oracle: None,
+        spam_threshold: None,
+        rate_limit_max_sends: 0,
+        rate_limit_window: 0,
+        lamport_send_fee: 0,
+        owner_claimable_lamports: 0,
+        fee_oracle: None,
+        fee_oracle_target: 0,
+        fee_oracle_max_age: 0,
+        claim_creation_policy: ClaimCreationPolicy::Eager,
+        usdc_decimals,
+        sanctions_enabled: false,
+        backup_mint: None,
+        stats_enabled: false,
+        pause_flags: 0,
+        kyc_verifier: None,
+        delegation_refund_window: 0,
+        claim_cap: 0,
+        payload_limits: PayloadLimits::default(),
+        payload_fee_tier_len: 0,
+        payload_fee_tier_percent: 0,
+        recipient_liabilities: 0,
+        min_coverage_bps: 0,
+        version: MAILER_STATE_VERSION,
+        treasury: None,
+        credit_liabilities: 0,
+    };
+
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+
+    msg!("Mailer initialized with owner: {}", owner.key);
+    Ok(())
+}
+
+/// Content carried by a send, charged by its length and checked against the owner's limits
+enum SendPayload {
+    /// Subject and body inline
+    Body { subject: String, body: String },
+    /// Off-chain content referenced by mailId
+    MailId(String),
+    /// Webhook the send is routed through, passed after the system program
+    WebhookId(String),
+}
+
+impl SendPayload {
+    /// Payload hash going into the send's message id
+    fn hash(&self) -> [u8; 32] {
+        match self {
+            SendPayload::Body { subject, body } => hash_message_body(subject, body),
+            SendPayload::MailId(id) | SendPayload::WebhookId(id) => {
+                solana_program::hash::hash(id.as_bytes()).to_bytes()
+            }
+        }
+    }
+}
+
+/// Recipient of a send
+enum SendRecipient {
+    /// Wallet recipient, sharing priority fees through its recipient claim
+    Wallet {
+        to: Pubkey,
+        priority: bool,
+        resolve_sender_to_name: bool,
+        thread_id: Option<[u8; 32]>,
+        in_reply_to: Option<[u8; 32]>,
+        allow_downgrade: bool,
+        tip: u64,
+    },
+    /// Email address, validated and lowercased when `normalize` is set
+    Email { address: String, normalize: bool },
+    /// Hashed email address with the backend's hint
+    EmailHash { hash: Vec<u8>, hint_id: u64 },
+}
+
+/// Parameters of a send other than its payload
+struct SendParams {
+    recipient: SendRecipient,
+    /// Fail the send with `FeePaymentFailed` instead of dropping an unpaid fee
+    strict: bool,
+    /// Accept fees in whitelisted mints as well as USDC and lamports
+    fee_mints: bool,
+    /// Memo attached to the claim history entry of a priority send
+    memo: Option<String>,
+    request_read_receipt: bool,
+    /// Nonce of the delivery receipt to create, if any
+    receipt_nonce: Option<u64>,
+    /// Mirror the send through the Wormhole bridge
+    bridge: bool,
+    /// Client nonce making a retried send a no-op, checked against the sender's SendNonce
+    idempotency_nonce: Option<u64>,
+}
+
+/// Send message with optional revenue sharing
+#[allow(clippy::too_many_arguments)]
+fn process_send(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    to: Pubkey,
+    subject: String,
+    _body: String,
+    revenue_share_to_receiver: bool,
+    _resolve_sender_to_name: bool,
+    request_read_receipt: bool,
+    receipt_nonce: Option<u64>,
+    memo: Option<String>,
+    strict: bool,
+    thread_id: Option<[u8; 32]>,
+    in_reply_to: Option<[u8; 32]>,
+    allow_downgrade: bool,
+    tip: u64,
+    bridge: bool,
+    idempotency_nonce: Option<u64>,
+) -> ProgramResult {
+    execute_send(
+        program_id,
+        accounts,
+        SendParams {
+            recipient: SendRecipient::Wallet {
+                to,
+                priority: revenue_share_to_receiver,
+                resolve_sender_to_name: _resolve_sender_to_name,
+                thread_id,
+                in_reply_to,
+                allow_downgrade,
+                tip,
+            },
+            strict,
+            fee_mints: true,
+            memo,
+            request_read_receipt,
+            receipt_nonce,
+            bridge,
+            idempotency_nonce,
+        },
+        SendPayload::Body {
+            subject,
+            body: _body,
+        },
+    )
+}
+
+/// Send prepared message with optional revenue sharing (references off-chain content via mailId)
+#[allow(clippy::too_many_arguments)]
+fn process_send_prepared(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    to: Pubkey,
+    mail_id: String,
+    revenue_share_to_receiver: bool,
+    _resolve_sender_to_name: bool,
+    strict: bool,
+    thread_id: Option<[u8; 32]>,
+    in_reply_to: Option<[u8; 32]>,
+    allow_downgrade: bool,
+    tip: u64,
+) -> ProgramResult {
+    execute_send(
+        program_id,
+        accounts,
+        SendParams {
+            recipient: SendRecipient::Wallet {
+                to,
+                priority: revenue_share_to_receiver,
+                resolve_sender_to_name: _resolve_sender_to_name,
+                thread_id,
+                in_reply_to,
+                allow_downgrade,
+                tip,
+            },
+            strict,
+            fee_mints: false,
+            memo: None,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            bridge: false,
+            idempotency_nonce: None,
+        },
+        SendPayload::MailId(mail_id),
+    )
+}
+
+/// Process send to email address (no wallet known, only owner fee)
+fn process_send_to_email(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    to_email: String,
+    subject: String,
+    _body: String,
+    strict: bool,
+) -> ProgramResult {
+    execute_send(
+        program_id,
+        accounts,
+        SendParams {
+            recipient: SendRecipient::Email {
+                address: to_email,
+                normalize: true,
+            },
+            strict,
+            fee_mints: false,
+            memo: None,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            bridge: false,
+            idempotency_nonce: None,
+        },
+        SendPayload::Body {
+            subject,
+            body: _body,
+        },
+    )
+}
+
+/// Process send to a hashed email address (no wallet known, only owner fee)
+fn process_send_to_email_hash(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    email_hash: Vec<u8>,
+    hint_id: u64,
+    subject: String,
+    _body: String,
+    strict: bool,
+) -> ProgramResult {
+    execute_send(
+        program_id,
+        accounts,
+        SendParams {
+            recipient: SendRecipient::EmailHash {
+                hash: email_hash,
+                hint_id,
+            },
+            strict,
+            fee_mints: false,
+            memo: None,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            bridge: false,
+            idempotency_nonce: None,
+        },
+        SendPayload::Body {
+            subject,
+            body: _body,
+        },
+    )
+}
+
+/// Process send prepared to email address (no wallet known, only owner fee)
+fn process_send_prepared_to_email(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    to_email: String,
+    mail_id: String,
+    strict: bool,
+) -> ProgramResult {
+    execute_send(
+        program_id,
+        accounts,
+        SendParams {
+            recipient: SendRecipient::Email {
+                address: to_email,
+                normalize: false,
+            },
+            strict,
+            fee_mints: false,
+            memo: None,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            bridge: false,
+            idempotency_nonce: None,
+        },
+        SendPayload::MailId(mail_id),
+    )
+}
+
+/// Send message through webhook (references webhook by webhookId)
+#[allow(clippy::too_many_arguments)]
+fn process_send_through_webhook(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    to: Pubkey,
+    webhook_id: String,
+    revenue_share_to_receiver: bool,
+    _resolve_sender_to_name: bool,
+    strict: bool,
+    thread_id: Option<[u8; 32]>,
+    in_reply_to: Option<[u8; 32]>,
+    allow_downgrade: bool,
+    tip: u64,
+) -> ProgramResult {
+    execute_send(
+        program_id,
+        accounts,
+        SendParams {
+            recipient: SendRecipient::Wallet {
+                to,
+                priority: revenue_share_to_receiver,
+                resolve_sender_to_name: _resolve_sender_to_name,
+                thread_id,
+                in_reply_to,
+                allow_downgrade,
+                tip,
+            },
+            strict,
+            fee_mints: false,
+            memo: None,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            bridge: false,
+            idempotency_nonce: None,
+        },
+        SendPayload::WebhookId(webhook_id),
+    )
+}
+
+/// Shared engine of the send instructions
+/// Wallet sends take the sender, recipient claim, mailer state, sender and mailer token
+/// accounts, token program and system program, followed by the webhook for webhook payloads
+/// and then the claim's rent payer; email sends omit the recipient claim, system program and
+/// rent payer. Checks the send, charges its fee through `collect_fee` and logs it in the
+/// format the indexer parses for its kind.
+fn execute_send(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    params: SendParams,
+    payload: SendPayload,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let sender = next_account_info(account_iter)?;
+    let recipient_claim = match params.recipient {
+        SendRecipient::Wallet { .. } => Some(next_account_info(account_iter)?),
+        _ => None,
+    };
+    let mailer_account = next_account_info(account_iter)?;
+    let sender_usdc = next_account_info(account_iter)?;
+    let mailer_usdc = next_account_info(account_iter)?;
+    let token_program = next_account_info(account_iter)?;
+    let system_program = match params.recipient {
+        SendRecipient::Wallet { .. } => Some(next_account_info(account_iter)?),
+        _ => None,
+    };
+    let webhook_account = match payload {
+        SendPayload::WebhookId(_) => Some(next_account_info(account_iter)?),
+        _ => None,
+    };
+    // Wallet sends read the recipient claim's rent payer right after the fixed accounts: the
+    // sender passes itself, a sponsor its own account, and either way it must sign
+    let rent_payer = match (&params.recipient, account_iter.next()) {
+        (SendRecipient::Wallet { .. }, Some(rent_payer)) if !rent_payer.is_signer => {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        (SendRecipient::Wallet { .. }, Some(rent_payer)) => rent_payer,
+        _ => sender,
+    };
+
+    if !sender.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if let Some(system_program) = system_program {
+        assert_system_program(system_program)?;
+    }
+
+    // A retry of a send that already landed succeeds without sending or charging again
+    if let Some(nonce) = params.idempotency_nonce {
+        if send_nonce_used(program_id, sender.key, nonce, accounts)? {
+            msg!(
+                "Duplicate send nonce {} from {}, skipped",
+                nonce,
+                sender.key
+            );
+            return Ok(());
+        }
+    }
+
+    // Describe the recipient as logged, checking hashed addresses are 32 bytes
+    let mut target = match &params.recipient {
+        SendRecipient::Wallet { to, .. } => to.to_string(),
+        SendRecipient::Email { address, .. } => format!("email {}", address),
+        SendRecipient::EmailHash { hash, hint_id } => {
+            let email_hash: [u8; 32] = hash
+                .as_slice()
+                .try_into()
+                .map_err(|_| MailerError::InvalidEmailHash)?;
+            format!(
+                "email hash {} hint {}",
+                Hash::new_from_array(email_hash),
+                hint_id
+            )
+        }
+    };
+
+    if let Some(memo) = &params.memo {
+        if memo.len() > MAX_MEMO_LEN {
+            return Err(MailerError::MemoTooLong.into());
+        }
+    }
+
+    // Only the registered owner of an active webhook may send through it
+    let webhook = match (&payload, webhook_account) {
+        (SendPayload::WebhookId(webhook_id), Some(webhook_account)) => Some(assert_webhook(
+            program_id,
+            webhook_account,
+            webhook_id,
+            sender.key,
+        )?),
+        _ => None,
+    };
+    let surcharge = webhook.as_ref().map_or(0, |webhook| webhook.surcharge);
+
+    // Load mailer state
+    let (mailer_pda, _) = assert_mailer_account(program_id, accounts, mailer_account)?;
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    // Fees are paid in lamports when the lamport vault is passed, otherwise in the mint of
+    // the sender's token account: USDC, or a whitelisted mint where the instruction allows it
+    assert_token_program(token_program)?;
+    let pays_lamports =
+        pays_in_lamports(program_id, &mailer_state, sender, sender_usdc, mailer_usdc)?;
+    if let SendRecipient::Wallet { priority, tip, .. } = params.recipient {
+        if tip > 0 && !priority {
+            return Err(MailerError::TipRequiresPriority.into());
+        }
+        if pays_lamports && (priority || surcharge > 0) {
+            return Err(MailerError::LamportFeeUnsupported.into());
+        }
+    }
+    let fee_mint = if params.fee_mints && !pays_lamports {
+        token_mint(sender_usdc)?
+    } else {
+        mailer_state.usdc_mint
+    };
+    let fee_mint_account = fee_mint_account(program_id, &mailer_state, &fee_mint, accounts)?;
+    if !pays_lamports {
+        assert_token_account(accounts, "sender_usdc", sender_usdc, sender.key, &fee_mint)?;
+        assert_token_account(accounts, "mailer_usdc", mailer_usdc, &mailer_pda, &fee_mint)?;
+    }
+
+    // Reject malformed addresses before charging for them
+    let mut email_len = 0;
+    let mut recipient_id = match &params.recipient {
+        SendRecipient::Wallet { to, .. } => to.to_bytes().to_vec(),
+        SendRecipient::Email { address, .. } => address.clone().into_bytes(),
+        SendRecipient::EmailHash { hash, .. } => hash.clone(),
+    };
+    if let SendRecipient::Email { address, normalize } = &params.recipient {
+        let address = if *normalize {
+            normalize_email(address)?
+        } else {
+            address.clone()
+        };
+        assert_payload_len(&address, mailer_state.payload_limits.max_email_len)?;
+        target = format!("email {}", address);
+        email_len = address.len();
+        recipient_id = address.into_bytes();
+    }
+    if let SendRecipient::EmailHash { hash, .. } = &params.recipient {
+        email_len = hash.len();
+    }
+
+    // Reject payloads over the owner's size limits
+    let limits = &mailer_state.payload_limits;
+    let payload_len = match &payload {
+        SendPayload::Body { subject, body } => {
+            assert_payload_len(subject, limits.max_subject_len)?;
+            assert_payload_len(body, limits.max_body_len)?;
+            subject.len() + body.len()
+        }
+        SendPayload::MailId(mail_id) => {
+            assert_payload_len(mail_id, limits.max_mail_id_len)?;
+            mail_id.len()
+        }
+        SendPayload::WebhookId(webhook_id) => {
+            assert_payload_len(webhook_id, limits.max_webhook_id_len)?;
+            webhook_id.len()
+        }
+    };
+
+    // Check if contract is paused
+    if mailer_state.is_paused(PAUSE_SEND) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    // Refuse service to sanctioned senders
+    assert_not_sanctioned(program_id, &mailer_state, sender.key, accounts)?;
+
+    let mut spam_score = None;
+    let mut inbox_sequence = None;
+    let fee_recipient = match (&params.recipient, recipient_claim, system_program) {
+        (
+            SendRecipient::Wallet {
+                to,
+                priority,
+                allow_downgrade,
+                tip,
+                ..
+            },
+            Some(recipient_claim),
+            Some(system_program),
+        ) => {
+            // Priority sends require the sender to be attested by the KYC verifier, if registered
+            if *priority {
+                assert_verified(&mailer_state, sender.key, accounts)?;
+            }
+
+            // Score the send with the registered spam oracle (rejects scores above its threshold)
+            spam_score = check_spam_score(&mailer_state, sender.key, to, accounts)?;
+
+            // Count the send against the sender's rate limit window
+            enforce_rate_limit(program_id, &mailer_state, sender.key, sender, accounts)?;
+
+            // Number the send in the recipient's inbox, if its inbox account was passed
+            inbox_sequence = next_inbox_sequence(program_id, to, sender, accounts)?;
+
+            FeeRecipient::Wallet {
+                to: *to,
+                priority: *priority,
+                allow_downgrade: *allow_downgrade,
+                tip: *tip,
+                recipient_claim,
+                system_program,
+                rent_payer,
+            }
+        }
+        _ => {
+            // Count the send against the sender's rate limit window
+            enforce_rate_limit(program_id, &mailer_state, sender.key, sender, accounts)?;
+
+            // Email recipients pay the 10% owner fee (no revenue share since no wallet address)
+            FeeRecipient::Email { target: &target }
+        }
+    };
+
+    // Identify the message for the caller and threaded conversations
+    let message_nonce = next_message_nonce(program_id, sender.key, sender, accounts)?;
+    let message_id = derive_message_id(
+        sender.key,
+        &recipient_id,
+        &payload.hash(),
+        Clock::get()?.slot,
+        message_nonce,
+    );
+    let (thread_id, in_reply_to) = match params.recipient {
+        SendRecipient::Wallet {
+            thread_id,
+            in_reply_to,
+            ..
+        } => (thread_id, in_reply_to),
+        _ => (None, None),
+    };
+
+    // Charge the fee, sharing it with the recipient's claim for priority sends
+    let fee = collect_fee(
+        &FeeContext {
+            program_id,
+            mailer_state: &mailer_state,
+            mailer_account,
+            sender,
+            sender_usdc,
+            mailer_usdc,
+            token_program,
+            accounts,
+            pays_lamports,
+            fee_mint,
+            fee_mint_account,
+        },
+        FeePolicy {
+            recipient: fee_recipient,
+            payload_len: email_len + payload_len,
+            strict: params.strict,
+            webhook: webhook_account.map(|webhook_account| (webhook_account, surcharge)),
+        },
+    )?;
+
+    // Attach the sender's memo to the per-sender claim history entry
+    if let (
+        SendRecipient::Wallet { to, .. },
+        Some(system_program),
+        Some(recipient_amount),
+        Some(memo),
+    ) = (
+        &params.recipient,
+        system_program,
+        fee.recipient_amount,
+        params.memo,
+    ) {
+        record_claim_history(
+            program_id,
+            sender,
+            system_program,
+            to,
+            recipient_amount,
+            memo,
+            accounts,
+        )?;
+    }
+
+    // Always log the message with fee_paid status (payer = sender in Solana)
+    let wallet = match &params.recipient {
+        SendRecipient::Wallet {
+            resolve_sender_to_name,
+            ..
+        } => Some(*resolve_sender_to_name),
+        _ => None,
+    };
+    let kind = match (&payload, wallet.map(|_| fee.priority)) {
+        (SendPayload::WebhookId(_), _) => "Webhook mail",
+        (SendPayload::Body { .. }, Some(true)) => "Priority mail",
+        (SendPayload::Body { .. }, Some(false)) => "Standard mail",
+        (SendPayload::Body { .. }, None) => "Mail",
+        (SendPayload::MailId(_), Some(true)) => "Priority prepared mail",
+        (SendPayload::MailId(_), Some(false)) => "Standard prepared mail",
+        (SendPayload::MailId(_), None) => "Prepared mail",
+    };
+    let content = match &payload {
+        SendPayload::Body { subject, .. } => format!(": {} (", subject),
+        SendPayload::MailId(mail_id) => format!(" (mailId: {}, ", mail_id),
+        SendPayload::WebhookId(webhook_id) => format!(" (webhookId: {}, ", webhook_id),
+    };
+    let recipient_prefix = match &params.recipient {
+        SendRecipient::Wallet { to, .. } => recipient_log_prefix(to),
+        _ => String::new(),
+    };
+    let sender_detail = match wallet {
+        Some(resolve_sender_to_name) => format!(
+            "{}resolve sender: {}, ",
+            if fee.priority {
+                "revenue share enabled, "
+            } else {
+                ""
+            },
+            resolve_sender_to_name
+        ),
+        None => String::new(),
+    };
+    msg!(
+        "{}{} sent from {} payer {} to {}{}{}effective fee: {}, fee paid: {}{}{}{}{}{}{}{})",
+        recipient_prefix,
+        kind,
+        sender.key,
+        sender.key,
+        target,
+        content,
+        sender_detail,
+        fee.effective_fee,
+        fee.paid,
+        fee_token_detail(&mailer_state, pays_lamports, fee_mint_account, &fee_mint),
+        webhook_surcharge_detail(fee.surcharge),
+        tip_detail(fee.tip),
+        lamport_fee_detail(pays_lamports),
+        spam_score_detail(spam_score),
+        inbox_sequence_detail(inbox_sequence),
+        thread_detail(&message_id, thread_id, in_reply_to)
+    );
+
+    // Count the send toward the webhook's SLA counters
+    if let Some(webhook_account) = webhook_account {
+        record_webhook_route(webhook_account)?;
+    }
+
+    // Surcharges are webhook revenue, not protocol fees
+    record_send_stats(
+        program_id,
+        &mailer_state,
+        accounts,
+        fee.priority,
+        fee.usdc_fee,
+    )?;
+
+    if let (SendRecipient::Wallet { to, .. }, Some(system_program)) =
+        (&params.recipient, system_program)
+    {
+        // Negotiate read receipt against the recipient's preferences
+        if params.request_read_receipt {
+            let read_receipt_accepted = read_receipts_allowed(program_id, to, accounts)?;
+            msg!(
+                "Read receipt requested by {} from {}: {}",
+                sender.key,
+                to,
+                if read_receipt_accepted {
+                    "accepted"
+                } else {
+                    "declined"
+                }
+            );
+        }
+
+        // Create the on-chain delivery receipt if requested
+        if let Some(nonce) = params.receipt_nonce {
+            create_message_receipt(program_id, sender, system_program, to, nonce, accounts)?;
+        }
+    }
+
+    // Mirror the send to the EVM deployments
+    if let (true, SendRecipient::Wallet { to, .. }, SendPayload::Body { subject, .. }) =
+        (params.bridge, &params.recipient, &payload)
+    {
+        let send = wormhole::MirroredSend {
+            sender: *sender.key,
+            recipient: *to,
+            priority: fee.priority,
+            subject: subject.clone(),
+        };
+        post_bridge_message(program_id, sender, &send, accounts)?;
+    }
+
+    // Only a delivered send uses up its nonce, so a retry of a dropped one goes through
+    if let (Some(nonce), true) = (params.idempotency_nonce, fee.paid) {
+        record_send_nonce(program_id, sender, nonce, accounts)?;
+    }
+
+    report_send(message_id)
+}
+
+/// Send a webhook message on behalf of a user within their pre-authorization
+fn process_send_preauthorized(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    to: Pubkey,
+    webhook_id: String,
+    revenue_share_to_receiver: bool,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let relayer = next_account_info(account_iter)?;
+    let preauth_account = next_account_info(account_iter)?;
+    let user = next_account_info(account_iter)?;
+    let recipient_claim = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let user_usdc = next_account_info(account_iter)?;
+    let mailer_usdc = next_account_info(account_iter)?;
+    let token_program = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !relayer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_system_program(system_program)?;
+
+    // Load and check the pre-authorization granted to this relayer
+    let (preauth_pda, preauth_bump) = pda::preauthorization(program_id, user.key, relayer.key);
+
+    if preauth_account.key != &preauth_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    if preauth_account.owner != program_id || preauth_account.data_len() < 8 + Preauthorization::LEN
+    {
+        return Err(MailerError::InvalidPreauthorization.into());
+    }
+
+    let preauth_data = preauth_account.try_borrow_data()?;
+    let mut preauth: Preauthorization = BorshDeserialize::deserialize(&mut &preauth_data[8..])?;
+    drop(preauth_data);
+
+    if !preauth.webhook_id.is_empty() && preauth.webhook_id != webhook_id {
+        return Err(MailerError::InvalidPreauthorization.into());
+    }
+
+    let current_time = Clock::get()?.unix_timestamp;
+    if !preauth.is_active(current_time) {
+        return Err(MailerError::PreauthorizationExhausted.into());
+    }
+
+    // Load mailer state
+    let (mailer_pda, _) = assert_mailer_account(program_id, accounts, mailer_account)?;
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    assert_token_program(token_program)?;
+    assert_token_account(
+        accounts,
+        "user_usdc",
+        user_usdc,
+        user.key,
+        &mailer_state.usdc_mint,
+    )?;
+    assert_token_account(
+        accounts,
+        "mailer_usdc",
+        mailer_usdc,
+        &mailer_pda,
+        &mailer_state.usdc_mint,
+    )?;
+
+    // Reject payloads over the owner's size limits
+    assert_payload_len(&webhook_id, mailer_state.payload_limits.max_webhook_id_len)?;
+
+    // Check if contract is paused
+    if mailer_state.is_paused(PAUSE_SEND) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    // Refuse service when either party is sanctioned
+    assert_not_sanctioned(program_id, &mailer_state, user.key, accounts)?;
+    assert_not_sanctioned(program_id, &mailer_state, relayer.key, accounts)?;
+
+    // Priority sends require the sender to be attested by the KYC verifier, if registered
+    if revenue_share_to_receiver {
+        assert_verified(&mailer_state, user.key, accounts)?;
+    }
+
+    // Score the send with the registered spam oracle (rejects scores above its threshold)
+    let spam_score = check_spam_score(&mailer_state, user.key, &to, accounts)?;
+
+    // The user's rate limit applies; the relayer funds its account on first use
+    enforce_rate_limit(program_id, &mailer_state, user.key, relayer, accounts)?;
+
+    // Number the send in the recipient's inbox, if its inbox account was passed
+    let inbox_sequence = next_inbox_sequence(program_id, &to, relayer, accounts)?;
+
+    // Identify the message for the caller; the relayer funds the user's nonce account
+    let message_nonce = next_message_nonce(program_id, user.key, relayer, accounts)?;
+    let payload_hash = solana_program::hash::hash(webhook_id.as_bytes()).to_bytes();
+    let message_id = derive_message_id(
+        user.key,
+        to.as_ref(),
+        &payload_hash,
+        Clock::get()?.slot,
+        message_nonce,
+    );
+
+    // The user is the sender: their discount and the recipient's policy for them apply
+    let effective_fee =
+        if mailer_state.fee_paused || use_subscription(program_id, user.key, accounts)? {
+            0
+        } else {
+            let current_fee = mailer_state.effective_send_fee(current_time);
+            let current_fee = mailer_state.payload_fee(current_fee, webhook_id.len())?;
+            calculate_fee_with_discount(program_id, user.key, accounts, current_fee)?
+        };
+    let effective_fee = apply_inbox_policy(program_id, user.key, &to, accounts, effective_fee)?;
+    if revenue_share_to_receiver {
+        assert_recipient_min_fee(program_id, &to, accounts, effective_fee)?;
+    }
+
+    let charge = if revenue_share_to_receiver {
+        effective_fee
+    } else {
+        standard_fee(effective_fee)? // Standard mode: 10% owner fee only
+    };
+
+    if charge > preauth.max_fee_per_send {
+        msg!(
+            "Preauthorized fee exceeded: charge {}, max {}",
+            charge,
+            preauth.max_fee_per_send
+        );
+        return Err(MailerError::PreauthorizedFeeExceeded.into());
+    }
+
+    let preauth_seeds: &[&[u8]] = &[
+        seeds::PREAUTH,
+        &[PDA_VERSION],
+        user.key.as_ref(),
+        relayer.key.as_ref(),
+        &[preauth_bump],
+    ];
+
+    let mut fee_received = 0;
+    let fee_paid = if revenue_share_to_receiver {
+        // Priority mode: full fee with revenue sharing
+        let (claim_pda, claim_bump) = cached_pda(
+            program_id,
+            recipient_claim,
+            RecipientClaim::BUMP_OFFSET,
+            |bump| pda::recipient_claim_with_bump(program_id, &to, bump),
+            || pda::recipient_claim(program_id, &to),
+        );
+
+        if recipient_claim.key != &claim_pda {
+            return Err(MailerError::InvalidPDA.into());
+        }
+
+        let claim_seeds: &[&[u8]] = &[seeds::CLAIM, &[PDA_VERSION], to.as_ref(), &[claim_bump]];
+
+        // Create the claim account up front (paid by the relayer), or once the fee has arrived
+        // under the AfterTransfer policy
+        let defer_claim = mailer_state.claim_creation_policy == ClaimCreationPolicy::AfterTransfer;
+        let claim_created = !defer_claim
+            && create_recipient_claim(
+                program_id,
+                relayer,
+                recipient_claim,
+                system_program,
+                mailer_account,
+                to,
+                claim_bump,
+                claim_seeds,
+            )?;
+
+        let fee_paid = if charge > 0 {
+            let transfer_result = transfer_to_vault_signed(
+                token_program,
+                user_usdc,
+                mailer_usdc,
+                preauth_account,
+                accounts,
+                charge,
+                &[preauth_seeds],
+            );
+
+            // Under AfterTransfer the claim account is created only once the fee has arrived
+            if defer_claim && transfer_result.is_ok() {
+                create_recipient_claim(
+                    program_id,
+                    relayer,
+                    recipient_claim,
+                    system_program,
+                    mailer_account,
+                    to,
+                    claim_bump,
+                    claim_seeds,
+                )?;
+            }
+
+            // Record revenue shares on the amount actually received (only if transfer succeeded)
+            match transfer_result {
+                Ok(received) => {
+                    fee_received = received;
+                    let recorded = record_shares(
+                        recipient_claim,
+                        mailer_account,
+                        mailer_usdc,
+                        None,
+                        None,
+                        to,
+                        received,
+                    );
+                    fail_if_insolvent(&recorded)?;
+                    recorded.is_ok()
+                }
+                Err(_) => false,
+            }
+        } else {
+            true // No fee required
+        };
+
+        // Under RefundPayer the relayer may later close the empty claim this send funded
+        if claim_created && !fee_paid {
+            record_claim_rent_payer(
+                program_id,
+                &mailer_state,
+                relayer,
+                recipient_claim,
+                accounts,
+            )?;
+        }
+        fee_paid
+    } else if charge > 0 {
+        match transfer_to_vault_signed(
+            token_program,
+            user_usdc,
+            mailer_usdc,
+            preauth_account,
+            accounts,
+            charge,
+            &[preauth_seeds],
+        ) {
+            Ok(owner_received) => {
+                credit_owner(mailer_account, None, owner_received)?;
+                fee_received = owner_received;
+                true
+            }
+            Err(_) => false,
+        }
+    } else {
+        true // No fee required
+    };
+
+    // Only sends that were paid for count against the pre-authorization
+    if fee_paid {
+        preauth.sends_used += 1;
+        let mut preauth_data = preauth_account.try_borrow_mut_data()?;
+        preauth.serialize(&mut &mut preauth_data[8..])?;
+    }
+
+    msg!(
+        "Preauthorized mail sent from {} payer {} to {} (webhookId: {}, relayer: {}, revenue share: {}, effective fee: {}, fee paid: {}, sends used: {}/{}{}{}{}{})",
+        user.key,
+        user.key,
+        to,
+        webhook_id,
+        relayer.key,
+        revenue_share_to_receiver,
+        effective_fee,
+        fee_paid,
+        preauth.sends_used,
+        preauth.max_sends,
+        fee_token_detail(&mailer_state, false, None, &mailer_state.usdc_mint),
+        spam_score_detail(spam_score),
+        inbox_sequence_detail(inbox_sequence),
+        thread_detail(&message_id, None, None)
+    );
+
+    let usdc_fee = if fee_paid { fee_received } else { 0 };
+    record_send_stats(
+        program_id,
+        &mailer_state,
+        accounts,
+        revenue_share_to_receiver,
+        usdc_fee,
+    )?;
+
+    report_send(message_id)
+}
+
+/// Process claim recipient share
+fn process_claim_recipient_share(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    requested: Option<u64>,
+    split: Option<Vec<ClaimSplit>>,
+    close_to: Option<Pubkey>,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let recipient = next_account_info(account_iter)?;
+    let recipient_claim = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let recipient_usdc = next_account_info(account_iter)?;
+    let mailer_usdc = next_account_info(account_iter)?;
+    let token_program = next_account_info(account_iter)?;
+
+    if !recipient.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if let Some(split) = &split {
+        validate_claim_split(split)?;
+    }
+
+    let (mailer_pda, _) = assert_mailer_account(_program_id, accounts, mailer_account)?;
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    if mailer_state.is_paused(PAUSE_CLAIM) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    let usdc_mint = mailer_state.usdc_mint;
+    drop(mailer_data);
+
+    assert_not_sanctioned(_program_id, &mailer_state, recipient.key, accounts)?;
+
+    // Shares paid in a whitelisted fee mint accrue in a separate claim account per mint
+    let claim_mint = token_mint(mailer_usdc)?;
+    let (claim_pda, _) = cached_pda(
+        _program_id,
+        recipient_claim,
+        RecipientClaim::BUMP_OFFSET,
+        |bump| {
+            if claim_mint == usdc_mint {
+                pda::recipient_claim_with_bump(_program_id, recipient.key, bump)
+            } else {
+                pda::recipient_claim_in_mint_with_bump(
+                    _program_id,
+                    recipient.key,
+                    &claim_mint,
+                    bump,
+                )
+            }
+        },
+        || {
+            if claim_mint == usdc_mint {
+                pda::recipient_claim(_program_id, recipient.key)
+            } else {
+                pda::recipient_claim_in_mint(_program_id, recipient.key, &claim_mint)
+            }
+        },
+    );
+    if recipient_claim.key != &claim_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    // Load claim state
+    let mut claim_data = recipient_claim.try_borrow_mut_data()?;
+    let mut claim_state = RecipientClaim::from_account_data(&claim_data[8..])?;
+
+    if claim_state.recipient != *recipient.key {
+        return Err(MailerError::InvalidRecipient.into());
+    }
+
+    // Load mailer state for pause-adjusted expiry and PDA signing
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    // No-expiry surcharges due are paid before the balance is withdrawn
+    let current_time = Clock::get()?.unix_timestamp;
+    let surcharge = mailer_state.settle_no_expiry_surcharge(&mut claim_state, current_time);
+    if surcharge > 0 {
+        // As for shares, the owner's cut of a fee mint claim accrues in its FeeMint account
+        match fee_mint_account(_program_id, &mailer_state, &claim_mint, accounts)? {
+            Some(fee_mint_account) => {
+                credit_owner(mailer_account, Some(fee_mint_account), surcharge)?
+            }
+            None => move_claim_to_owner(mailer_account, surcharge)?,
+        }
+        msg!(
+            "No-expiry surcharge debited from {}: {}",
+            recipient.key,
+            surcharge
+        );
+    }
+
+    if claim_state.amount == 0 {
+        return Err(MailerError::NoClaimableAmount.into());
+    }
+
+    // Check if claim period has expired
+    if current_time > mailer_state.claim_expiry(&claim_state, current_time) {
+        return Err(MailerError::ClaimPeriodExpired.into());
+    }
+
+    // Partial claims leave the remainder (and its original timestamp) in place
+    let amount = match requested {
+        None => claim_state.amount,
+        Some(amount) if amount == 0 || amount > claim_state.amount => {
+            return Err(MailerError::InvalidClaimAmount.into());
+        }
+        Some(amount) => amount,
+    };
+    claim_state.amount -= amount;
+    if claim_state.amount == 0 {
+        claim_state.timestamp = 0;
+    } else if close_to.is_some() {
+        return Err(MailerError::AccountNotEmpty.into());
+    }
+    claim_state.total_claimed = claim_state.total_claimed.saturating_add(amount);
+    claim_state.write_account_data(&mut claim_data[8..])?;
+    drop(claim_data);
+
+    assert_token_program(token_program)?;
+    let assert_destination = |label: &str, destination: &AccountInfo| {
+        if claim_mint == mailer_state.usdc_mint {
+            assert_payout_account(
+                _program_id,
+                recipient.key,
+                destination,
+                &mailer_state.usdc_mint,
+                accounts,
+            )
+        } else {
+            assert_token_account(accounts, label, destination, recipient.key, &claim_mint)
+        }
+    };
+    assert_destination("recipient_usdc", recipient_usdc)?;
+    assert_token_account(
+        accounts,
+        "mailer_usdc",
+        mailer_usdc,
+        &mailer_pda,
+        &claim_mint,
+    )?;
+
+    // Pay the whole amount to the recipient's account, or each split destination its share
+    let payouts = match &split {
+        None => vec![(recipient_usdc, amount, CLAIM_SPLIT_TOTAL_BPS)],
+        Some(split) => {
+            let mut payouts = Vec::with_capacity(split.len());
+            for (entry, share) in split.iter().zip(claim_split_amounts(amount, split)) {
+                let destination = accounts
+                    .iter()
+                    .find(|acc| acc.key == &entry.destination)
+                    .ok_or(MailerError::InvalidClaimSplit)?;
+                assert_destination("split_destination", destination)?;
+                payouts.push((destination, share, entry.bps));
+            }
+            payouts
+        }
+    };
+
+    // Transfer USDC from mailer to recipient
+    for (destination, share, _) in &payouts {
+        invoke_signed(
+            &spl_token::instruction::transfer(
+                token_program.key,
+                mailer_usdc.key,
+                destination.key,
+                mailer_account.key,
+                &[],
+                *share,
+            )?,
+            &[
+                mailer_usdc.clone(),
+                (*destination).clone(),
+                mailer_account.clone(),
+                token_program.clone(),
+            ],
+            &[&[seeds::MAILER, &[mailer_state.bump]]],
+        )?;
+    }
+
+    msg!(
+        "{}Recipient {} claimed {}",
+        recipient_log_prefix(recipient.key),
+        recipient.key,
+        amount
+    );
+    if split.is_some() {
+        for (destination, share, bps) in &payouts {
+            msg!(
+                "{}ClaimSplit {{ recipient: {}, destination: {}, amount: {}, bps: {} }}",
+                recipient_log_prefix(recipient.key),
+                recipient.key,
+                destination.key,
+                share,
+                bps
+            );
+        }
+    }
+
+    let usdc_amount = if claim_mint == mailer_state.usdc_mint {
+        amount
+    } else {
+        0
+    };
+    release_recipient_liability(mailer_account, usdc_amount)?;
+    record_claim_stats(_program_id, &mailer_state, accounts, usdc_amount)?;
+
+    // Refund the emptied claim's rent; the next priority send recreates it
+    if let Some(close_to) = close_to {
+        if close_to == *recipient_claim.key {
+            return Err(MailerError::InvalidRecipient.into());
+        }
+        let refund_destination = accounts
+            .iter()
+            .find(|acc| acc.key == &close_to)
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        let (closure_log_pda, _) = pda::closure_log(_program_id);
+        let closure_log = accounts
+            .iter()
+            .find(|acc| acc.key == &closure_log_pda)
+            .ok_or(MailerError::InvalidPDA)?;
+
+        record_closure(_program_id, closure_log, recipient_claim)?;
+        let lamports = close_account(recipient_claim, refund_destination)?;
+        release_rent_reserve(mailer_account, RentReserve::Claims, lamports)?;
+
+        msg!(
+            "Recipient claim for {} closed, {} lamports refunded to {}",
+            recipient.key,
+            lamports,
+            refund_destination.key
+        );
+    }
+    Ok(())
+}
+
+/// Check a claim split: 1 to `MAX_CLAIM_SPLITS` distinct destinations with non-zero
+/// weights summing to `CLAIM_SPLIT_TOTAL_BPS`
+fn validate_claim_split(split: &[ClaimSplit]) -> ProgramResult {
+    let total: u32 = split.iter().map(|entry| entry.bps as u32).sum();
+    let distinct = split.iter().enumerate().all(|(i, entry)| {
+        split[..i]
+            .iter()
+            .all(|other| other.destination != entry.destination)
+    });
+
+    if split.is_empty()
+        || split.len() > MAX_CLAIM_SPLITS
+        || split.iter().any(|entry| entry.bps == 0)
+        || total != CLAIM_SPLIT_TOTAL_BPS as u32
+        || !distinct
+    {
+        return Err(MailerError::InvalidClaimSplit.into());
+    }
+    Ok(())
+}
+
+/// Amount paid to each destination of a validated split; rounding dust goes to the first
+pub fn claim_split_amounts(amount: u64, split: &[ClaimSplit]) -> Vec<u64> {
+    let mut amounts: Vec<u64> = split
+        .iter()
+        .map(|entry| (amount as u128 * entry.bps as u128 / CLAIM_SPLIT_TOTAL_BPS as u128) as u64)
+        .collect();
+    let dust = amount - amounts.iter().sum::<u64>();
+    if let Some(first) = amounts.first_mut() {
+        *first += dust;
+    }
+    amounts
+}
+
+/// Claim the sender's recipient share into their USDC account, then send from it
+/// Runs the ClaimRecipientShare and Send handlers on their own account lists, so both
+/// keep every check they make on their own.
+#[allow(clippy::too_many_arguments)]
+fn process_claim_and_send(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: Option<u64>,
+    to: Pubkey,
+    subject: String,
+    _body: String,
+    revenue_share_to_receiver: bool,
+    resolve_sender_to_name: bool,
+    request_read_receipt: bool,
+    receipt_nonce: Option<u64>,
+    memo: Option<String>,
+    strict: bool,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let sender = next_account_info(account_iter)?;
+    let sender_claim = next_account_info(account_iter)?;
+    let recipient_claim = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let sender_usdc = next_account_info(account_iter)?;
+    let mailer_usdc = next_account_info(account_iter)?;
+    let token_program = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+    let remaining = account_iter.as_slice();
+
+    let mut claim_accounts = vec![
+        sender.clone(),
+        sender_claim.clone(),
+        mailer_account.clone(),
+        sender_usdc.clone(),
+        mailer_usdc.clone(),
+        token_program.clone(),
+    ];
+    claim_accounts.extend_from_slice(remaining);
+    process_claim_recipient_share(program_id, &claim_accounts, amount, None, None)?;
+
+    let mut send_accounts = vec![
+        sender.clone(),
+        recipient_claim.clone(),
+        mailer_account.clone(),
+        sender_usdc.clone(),
+        mailer_usdc.clone(),
+        token_program.clone(),
+        system_program.clone(),
+    ];
+    send_accounts.extend_from_slice(remaining);
+    process_send(
+        program_id,
+        &send_accounts,
+        to,
+        subject,
+        _body,
+        revenue_share_to_receiver,
+        resolve_sender_to_name,
+        request_read_receipt,
+        receipt_nonce,
+        memo,
+        strict,
+        None,
+        None,
+        false,
+        0,
+        false,
+        None,
+    )
+}
+
+/// Claim recipient share into the recipient's associated token account, creating it if needed
+fn process_claim_recipient_share_to_ata(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let recipient = next_account_info(account_iter)?;
+    let recipient_claim = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let recipient_ata = next_account_info(account_iter)?;
+    let mailer_usdc = next_account_info(account_iter)?;
+    let token_program = next_account_info(account_iter)?;
+    let usdc_mint = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+    let ata_program = next_account_info(account_iter)?;
+
+    if !recipient.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_system_program(system_program)?;
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+    assert_token_program(token_program)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    assert_not_sanctioned(program_id, &mailer_state, recipient.key, accounts)?;
+
+    if usdc_mint.key != &mailer_state.usdc_mint {
+        return Err(MailerError::InvalidMint.into());
+    }
+
+    if ata_program.key != &ASSOCIATED_TOKEN_PROGRAM_ID {
+        return Err(MailerError::InvalidAssociatedTokenAccount.into());
+    }
+
+    // Verify the destination is the recipient's canonical associated token account
+    let (expected_ata, _) = Pubkey::find_program_address(
+        &[
+            recipient.key.as_ref(),
+            token_program.key.as_ref(),
+            usdc_mint.key.as_ref(),
+        ],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    );
+    if recipient_ata.key != &expected_ata {
+        return Err(MailerError::InvalidAssociatedTokenAccount.into());
+    }
+
+    // Create the associated token account if needed (CreateIdempotent)
+    if recipient_ata.lamports() == 0 {
+        invoke(
+            &Instruction {
+                program_id: ASSOCIATED_TOKEN_PROGRAM_ID,
+                accounts: vec![
+                    AccountMeta::new(*recipient.key, true),
+                    AccountMeta::new(*recipient_ata.key, false),
+                    AccountMeta::new_readonly(*recipient.key, false),
+                    AccountMeta::new_readonly(*usdc_mint.key, false),
+                    AccountMeta::new_readonly(*system_program.key, false),
+                    AccountMeta::new_readonly(*token_program.key, false),
+                ],
+                data: vec![1],
+            },
+            &[
+                recipient.clone(),
+                recipient_ata.clone(),
+                usdc_mint.clone(),
+                system_program.clone(),
+                token_program.clone(),
+                ata_program.clone(),
+            ],
+        )?;
+
+        msg!(
+            "Associated token account {} created for {}",
+            recipient_ata.key,
+            recipient.key
+        );
+    }
+
+    // Trailing accounts (sanctions entry, stats) carry over to the claim
+    let mut claim_accounts = vec![
+        recipient.clone(),
+        recipient_claim.clone(),
+        mailer_account.clone(),
+        recipient_ata.clone(),
+        mailer_usdc.clone(),
+        token_program.clone(),
+    ];
+    claim_accounts.extend_from_slice(account_iter.as_slice());
+    process_claim_recipient_share(program_id, &claim_accounts, None, None, None)
+}
+
+/// Claim the recipient share in an alternate mint via its conversion vault
+fn process_claim_recipient_share_as(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    mint: Pubkey,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let recipient = next_account_info(account_iter)?;
+    let recipient_claim = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let recipient_token = next_account_info(account_iter)?;
+    let conversion_account = next_account_info(account_iter)?;
+    let vault_token = next_account_info(account_iter)?;
+    let recipient_usdc = next_account_info(account_iter)?;
+    let mailer_usdc = next_account_info(account_iter)?;
+    let token_program = next_account_info(account_iter)?;
+
+    if !recipient.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (mailer_pda, _) = assert_mailer_account(program_id, accounts, mailer_account)?;
+    assert_token_program(token_program)?;
+
+    let (claim_pda, _) = pda::recipient_claim(program_id, recipient.key);
+    if recipient_claim.key != &claim_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    let (conversion_pda, _) = pda::conversion_vault(program_id, &mint);
+    if conversion_account.key != &conversion_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    let claim_data = recipient_claim.try_borrow_data()?;
+    let claimable = fields::read_u64(&claim_data, RecipientClaim::AMOUNT_OFFSET)?;
+    drop(claim_data);
+
+    // In contingency mode the default mint cannot pay, so claims in the backup mint never fall back
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let contingency = MailerState::deserialize(&mut &mailer_data[8..])?.backup_mint == Some(mint);
+    drop(mailer_data);
+
+    // Use the conversion vault only when it is enabled and liquid enough for the whole share
+    let mut conversion = None;
+    if conversion_account.lamports() > 0 && conversion_account.owner == program_id {
+        let conversion_data = conversion_account.try_borrow_data()?;
+        let vault: ConversionVault = BorshDeserialize::deserialize(&mut &conversion_data[8..])?;
+        drop(conversion_data);
+
+        if vault.enabled
+            && vault.vault == *vault_token.key
+            && token_balance(vault_token)? >= claimable
+        {
+            conversion = Some(vault);
+        }
+    }
+
+    let mut vault = match conversion {
+        Some(vault) => vault,
+        None if contingency => {
+            msg!(
+                "Backup vault for {} cannot pay {} to {}",
+                mint,
+                claimable,
+                recipient.key
+            );
+            return Err(MailerError::BackupVaultUnavailable.into());
+        }
+        None => {
+            msg!(
+                "Conversion to {} unavailable for {}; paying {} in the default mint",
+                mint,
+                recipient.key,
+                claimable
+            );
+            let mut fallback_accounts = vec![
+                recipient.clone(),
+                recipient_claim.clone(),
+                mailer_account.clone(),
+                recipient_usdc.clone(),
+                mailer_usdc.clone(),
+                token_program.clone(),
+            ];
+            fallback_accounts.extend(account_iter.cloned());
+            return process_claim_recipient_share(program_id, &fallback_accounts, None, None, None);
+        }
+    };
+
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    if mailer_state.is_paused(PAUSE_CLAIM) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    assert_not_sanctioned(program_id, &mailer_state, recipient.key, accounts)?;
+
+    let mut claim_data = recipient_claim.try_borrow_mut_data()?;
+    let mut claim_state = RecipientClaim::from_account_data(&claim_data[8..])?;
+
+    if claim_state.recipient != *recipient.key {
+        return Err(MailerError::InvalidRecipient.into());
+    }
+
+    let current_time = Clock::get()?.unix_timestamp;
+    let surcharge = mailer_state.settle_no_expiry_surcharge(&mut claim_state, current_time);
+    if surcharge > 0 {
+        mailer_state.move_claim_to_owner(surcharge)?;
+        msg!(
+            "No-expiry surcharge debited from {}: {}",
+            recipient.key,
+            surcharge
+        );
+    }
+
+    if claim_state.amount == 0 {
+        return Err(MailerError::NoClaimableAmount.into());
+    }
+
+    if current_time > mailer_state.claim_expiry(&claim_state, current_time) {
+        return Err(MailerError::ClaimPeriodExpired.into());
+    }
+
+    assert_token_account(
+        accounts,
+        "recipient_token",
+        recipient_token,
+        recipient.key,
+        &mint,
+    )?;
+    assert_token_account(accounts, "vault_token", vault_token, &mailer_pda, &mint)?;
+
+    // The default-mint share stays in the mailer vault and reimburses the owner's liquidity
+    let amount = claim_state.amount;
+    claim_state.amount = 0;
+    claim_state.timestamp = 0;
+    claim_state.total_claimed = claim_state.total_claimed.saturating_add(amount);
+    claim_state.write_account_data(&mut claim_data[8..])?;
+    drop(claim_data);
+
+    mailer_state.move_claim_to_owner(amount)?;
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+    drop(mailer_data);
+    record_claim_stats(program_id, &mailer_state, accounts, amount)?;
+
+    vault.total_converted = vault
+        .total_converted
+        .checked_add(amount)
+        .ok_or(MailerError::MathOverflow)?;
+    let mut conversion_data = conversion_account.try_borrow_mut_data()?;
+    vault.serialize(&mut &mut conversion_data[8..])?;
+    drop(conversion_data);
+
+    invoke_signed(
+        &spl_token::instruction::transfer(
+            token_program.key,
+            vault_token.key,
+            recipient_token.key,
+            mailer_account.key,
+            &[],
+            amount,
+        )?,
+        &[
+            vault_token.clone(),
+            recipient_token.clone(),
+            mailer_account.clone(),
+            token_program.clone(),
+        ],
+        &[&[seeds::MAILER, &[mailer_state.bump]]],
+    )?;
+
+    msg!(
+        "{}Recipient {} claimed {} as {} from {} vault; {} credited to owner",
+        recipient_log_prefix(recipient.key),
+        recipient.key,
+        amount,
+        mint,
+        if contingency { "backup" } else { "conversion" },
+        amount
+    );
+    Ok(())
+}
+
+/// Process claim owner share
+fn process_claim_owner_share(_program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let owner_usdc = next_account_info(account_iter)?;
+    let mailer_usdc = next_account_info(account_iter)?;
+    let token_program = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (mailer_pda, _) = assert_mailer_account(_program_id, accounts, mailer_account)?;
+
+    // Load and update mailer state
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    if mailer_state.is_paused(PAUSE_CLAIM) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    assert_owner_authority(_program_id, &mailer_state, owner, accounts)?;
+
+    // The owner share of a whitelisted fee mint accrues in its FeeMint account
+    let claim_mint = token_mint(mailer_usdc)?;
+    let amount = match fee_mint_account(_program_id, &mailer_state, &claim_mint, accounts)? {
+        Some(fee_mint_account) => {
+            let mut fee_mint_data = fee_mint_account.try_borrow_mut_data()?;
+            let mut fee_mint: FeeMint = BorshDeserialize::deserialize(&mut &fee_mint_data[8..])?;
+            if fee_mint.owner_claimable == 0 {
+                return Err(MailerError::NoClaimableAmount.into());
+            }
+
+            let amount = fee_mint.owner_claimable;
+            fee_mint.owner_claimable = 0;
+            fee_mint.serialize(&mut &mut fee_mint_data[8..])?;
+            amount
+        }
+        None => {
+            let amount = mailer_state.owner_claimable;
+            mailer_state.owner_claimable = 0;
+            mailer_state.serialize(&mut &mut mailer_data[8..])?;
+            amount
+        }
+    };
+    drop(mailer_data);
+
+    // USDC fees forwarded to the treasury are paid out along with the vault's owner share,
+    // when the treasury account is passed
+    let treasury = match mailer_state.treasury {
+        Some(treasury) if claim_mint == mailer_state.usdc_mint => {
+            accounts.iter().find(|acc| acc.key == &treasury)
+        }
+        _ => None,
+    };
+    let treasury_amount = match treasury {
+        Some(treasury) => token_balance(treasury)?,
+        None => 0,
+    };
+    if amount == 0 && treasury_amount == 0 {
+        return Err(MailerError::NoClaimableAmount.into());
+    }
+
+    assert_token_program(token_program)?;
+    assert_token_account(
+        accounts,
+        "owner_usdc",
+        owner_usdc,
+        &mailer_state.owner,
+        &claim_mint,
+    )?;
+    assert_token_account(
+        accounts,
+        "mailer_usdc",
+        mailer_usdc,
+        &mailer_pda,
+        &claim_mint,
+    )?;
+
+    // Transfer USDC from mailer to owner
+    if amount > 0 {
+        invoke_signed(
+            &spl_token::instruction::transfer(
+                token_program.key,
+                mailer_usdc.key,
+                owner_usdc.key,
+                mailer_account.key,
+                &[],
+                amount,
+            )?,
+            &[
+                mailer_usdc.clone(),
+                owner_usdc.clone(),
+                mailer_account.clone(),
+                token_program.clone(),
+            ],
+            &[&[seeds::MAILER, &[mailer_state.bump]]],
+        )?;
+    }
+
+    // Transfer the treasury balance, signed by the treasury PDA
+    if let Some(treasury) = treasury.filter(|_| treasury_amount > 0) {
+        let (treasury_pda, treasury_bump) = pda::treasury(_program_id);
+        let treasury_authority = accounts
+            .iter()
+            .find(|acc| acc.key == &treasury_pda)
+            .ok_or(MailerError::InvalidTreasury)?;
+        invoke_signed(
+            &spl_token::instruction::transfer(
+                token_program.key,
+                treasury.key,
+                owner_usdc.key,
+                &treasury_pda,
+                &[],
+                treasury_amount,
+            )?,
+            &[
+                treasury.clone(),
+                owner_usdc.clone(),
+                treasury_authority.clone(),
+                token_program.clone(),
+            ],
+            &[&[seeds::TREASURY, &[PDA_VERSION], &[treasury_bump]]],
+        )?;
+        msg!("Treasury paid out {}", treasury_amount);
+    }
+
+    let total = amount
+        .checked_add(treasury_amount)
+        .ok_or(MailerError::MathOverflow)?;
+    msg!("Owner {} claimed {}", owner.key, total);
+
+    let usdc_amount = if claim_mint == mailer_state.usdc_mint {
+        total
+    } else {
+        0
+    };
+    record_claim_stats(_program_id, &mailer_state, accounts, usdc_amount)?;
+    Ok(())
+}
+
+/// Set send fee (owner only)
+fn process_set_fee(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    new_fee: u64,
+    expected_current_value: Option<u64>,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_mailer_account(_program_id, accounts, mailer_account)?;
+
+    // Load and update mailer state
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    assert_owner_authority(_program_id, &mailer_state, owner, accounts)?;
+
+    // Check if contract is paused
+    if mailer_state.is_paused(PAUSE_ADMIN) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    assert_fee_not_timelocked(&mailer_state)?;
+
+    assert_current_value(mailer_state.send_fee, expected_current_value)?;
+
+    let old_fee = mailer_state.send_fee;
+    mailer_state.send_fee = new_fee;
+    mailer_state.pending_fee = None;
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+
+    msg!("Fee updated from {} to {}", old_fee, new_fee);
+    Ok(())
+}
+
+/// Reject immediate changes to the fees sends pay once a fee timelock is configured
+/// The notice period would otherwise only cover the send fee: with a timelock, fees change
+/// through ProposeFee alone.
+fn assert_fee_not_timelocked(mailer_state: &MailerState) -> ProgramResult {
+    if mailer_state.fee_timelock > 0 {
+        return Err(MailerError::FeeTimelockActive.into());
+    }
+    Ok(())
+}
+
+/// Propose a timelocked send fee change (owner only)
+fn process_propose_fee(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    new_fee: u64,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+
+    // Check if contract is paused
+    if mailer_state.is_paused(PAUSE_ADMIN) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    let current_time = Clock::get()?.unix_timestamp;
+    mailer_state.apply_matured_fee(current_time);
+
+    let effective_at = current_time
+        .checked_add(mailer_state.fee_timelock)
+        .ok_or(MailerError::MathOverflow)?;
+    mailer_state.pending_fee = Some(PendingFeeChange {
+        new_fee,
+        effective_at,
+    });
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+
+    msg!(
+        "Fee change proposed from {} to {}, effective at {}",
+        mailer_state.send_fee,
+        new_fee,
+        effective_at
+    );
+    Ok(())
+}
+
+/// Set the fee change timelock (owner only)
+fn process_set_fee_timelock(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    delay: i64,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+
+    // Lowering the delay would let the owner shorten an advertised notice period
+    if delay < mailer_state.fee_timelock || delay > MAX_FEE_TIMELOCK {
+        return Err(MailerError::InvalidFeeTimelock.into());
+    }
+
+    let old_delay = mailer_state.fee_timelock;
+    mailer_state.fee_timelock = delay;
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+
+    msg!("Fee timelock updated from {} to {}", old_delay, delay);
+    Ok(())
+}
+
+/// Delegate to another address
+fn process_delegate_to(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    delegate: Option<Pubkey>,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let delegator = next_account_info(account_iter)?;
+    let delegation_account = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let delegator_usdc = next_account_info(account_iter)?;
+    let mailer_usdc = next_account_info(account_iter)?;
+    let token_program = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !delegator.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_system_program(system_program)?;
+
+    let (mailer_pda, _) = assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    // Load mailer state
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    // The delegation fee is paid in the mint of the delegator's token account
+    assert_token_program(token_program)?;
+    let fee_mint = token_mint(delegator_usdc)?;
+    let fee_mint_account = fee_mint_account(program_id, &mailer_state, &fee_mint, accounts)?;
+    assert_token_account(
+        accounts,
+        "delegator_usdc",
+        delegator_usdc,
+        delegator.key,
+        &fee_mint,
+    )?;
+    assert_token_account(accounts, "mailer_usdc", mailer_usdc, &mailer_pda, &fee_mint)?;
+
+    // Check if contract is paused
+    if mailer_state.is_paused(PAUSE_DELEGATE) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    // Verify delegation account PDA
+    let (delegation_pda, delegation_bump) = pda::delegation(program_id, delegator.key);
+
+    if delegation_account.key != &delegation_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    // Create delegation account if needed
+    if delegation_account.lamports() == 0 {
+        let rent = Rent::get()?;
+        let space = 8 + Delegation::LEN;
+        let lamports = rent.minimum_balance(space);
+
+        invoke_signed(
+            &system_instruction::create_account(
+                delegator.key,
+                delegation_account.key,
+                lamports,
+                space as u64,
+                program_id,
+            ),
+            &[
+                delegator.clone(),
+                delegation_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                seeds::DELEGATION,
+                &[PDA_VERSION],
+                delegator.key.as_ref(),
+                &[delegation_bump],
+            ]],
+        )?;
+
+        // Verify account is rent-exempt
+        let account_lamports = delegation_account.lamports();
+        if !rent.is_exempt(account_lamports, space) {
+            msg!(
+                "ERROR: Delegation account not rent-exempt! {} lamports for {} bytes",
+                account_lamports,
+                space
+            );
+            return Err(MailerError::AccountNotRentExempt.into());
+        }
+        msg!(
+            "Created rent-exempt delegation account: {} lamports for {} bytes",
+            account_lamports,
+            space
+        );
+        track_rent_reserve(mailer_account, RentReserve::Delegations, account_lamports)?;
+
+        // Initialize delegation account
+        let mut delegation_data = delegation_account.try_borrow_mut_data()?;
+        delegation_data[0..8].copy_from_slice(&discriminator::account("Delegation"));
+
+        let delegation_state = Delegation {
+            delegator: *delegator.key,
+            delegate: None,
+            bump: delegation_bump,
+            escrowed_fee: 0,
+            refund_deadline: 0,
+            fee_refundable: false,
+        };
+
+        delegation_state.serialize(&mut &mut delegation_data[8..])?;
+        drop(delegation_data);
+    }
+
+    let mut delegation_data = delegation_account.try_borrow_mut_data()?;
+    let mut delegation_state: Delegation =
+        BorshDeserialize::deserialize(&mut &delegation_data[8..])?;
+
+    // A fee still escrowed for the replaced delegation was earned; a refundable one is
+    // the delegator's and must be reclaimed first
+    if delegation_state.escrowed_fee > 0 {
+        if delegation_state.fee_refundable {
+            return Err(MailerError::DelegationFeeRefundPending.into());
+        }
+        credit_owner(mailer_account, None, delegation_state.escrowed_fee)?;
+        delegation_state.escrowed_fee = 0;
+        delegation_state.refund_deadline = 0;
+    }
+
+    // If setting delegation (not clearing), charge fee (unless fee_paused)
+    if let Some(delegate_key) = delegate {
+        if delegate_key != Pubkey::default() && !mailer_state.fee_paused {
+            let delegation_fee = match fee_mint_account {
+                Some(fee_mint_account) => load_fee_mint(fee_mint_account)?.delegation_fee,
+                None => mailer_state.delegation_fee,
+            };
+            let received = transfer_to_vault(
+                token_program,
+                delegator_usdc,
+                mailer_usdc,
+                delegator,
+                accounts,
+                delegation_fee,
+            )?;
+
+            if mailer_state.delegation_refund_window > 0 && fee_mint_account.is_none() {
+                // Held on the delegation until the delegate accepts or rejects
+                delegation_state.escrowed_fee = received;
+                delegation_state.refund_deadline = Clock::get()?
+                    .unix_timestamp
+                    .checked_add(mailer_state.delegation_refund_window)
+                    .ok_or(MailerError::MathOverflow)?;
+                msg!(
+                    "Delegation fee of {} escrowed until {}",
+                    received,
+                    delegation_state.refund_deadline
+                );
+            } else {
+                // Mirror EVM behavior: delegation fees become owner-claimable
+                credit_owner(mailer_account, fee_mint_account, received)?;
+            }
+        }
+    }
+
+    // Update delegation
+    let previous = indexed_delegate(delegation_state.delegate);
+    delegation_state.delegate = delegate;
+    delegation_state.serialize(&mut &mut delegation_data[8..])?;
+    drop(delegation_data);
+
+    // Move the delegator between the delegates' reverse indexes
+    let next = indexed_delegate(delegate);
+    if previous != next {
+        if let Some(previous) = previous {
+            remove_from_delegate_index(program_id, &previous, delegator.key, accounts)?;
+        }
+        if let Some(next) = next {
+            add_to_delegate_index(program_id, &next, delegator, system_program, accounts)?;
+        }
+    }
+
+    msg!("Delegation set from {} to {:?}", delegator.key, delegate);
+
+    // Announce the delegation to the delegate as a fee-exempt protocol message
+    if let Some(delegate_key) = delegate {
+        if delegate_key != Pubkey::default() && mailer_state.protocol_sends_enabled {
+            log_protocol_message(
+                &mailer_pda,
+                &delegate_key,
+                &format!("Delegation received from {}", delegator.key),
+                None,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Reject delegation
+fn process_reject_delegation(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let rejector = next_account_info(account_iter)?;
+    let delegation_account = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+
+    if !rejector.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Verify mailer state PDA and ensure contract is not paused
+    let (_mailer_pda, _) = assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    if mailer_state.is_paused(PAUSE_DELEGATE) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    // Load and update delegation state
+    let mut delegation_data = delegation_account.try_borrow_mut_data()?;
+    let mut delegation_state: Delegation =
+        BorshDeserialize::deserialize(&mut &delegation_data[8..])?;
+
+    // Verify the rejector is the current delegate
+    if delegation_state.delegate != Some(*rejector.key) {
+        return Err(MailerError::NoDelegationToReject.into());
+    }
+
+    delegation_state.delegate = None;
+
+    // An escrowed fee is refundable when rejected in time, and earned by the owner after
+    if delegation_state.escrowed_fee > 0 {
+        if Clock::get()?.unix_timestamp <= delegation_state.refund_deadline {
+            delegation_state.fee_refundable = true;
+            msg!(
+                "Delegation fee of {} refundable to {}",
+                delegation_state.escrowed_fee,
+                delegation_state.delegator
+            );
+        } else {
+            credit_owner(mailer_account, None, delegation_state.escrowed_fee)?;
+            delegation_state.escrowed_fee = 0;
+            delegation_state.refund_deadline = 0;
+        }
+    }
+
+    delegation_state.serialize(&mut &mut delegation_data[8..])?;
+    drop(delegation_data);
+
+    remove_from_delegate_index(
+        program_id,
+        rejector.key,
+        &delegation_state.delegator,
+        accounts,
+    )?;
+
+    msg!("Delegation rejected by {}", rejector.key);
+    Ok(())
+}
+
+/// Accept a delegation, crediting its escrowed fee to the owner
+fn process_accept_delegation(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let delegate = next_account_info(account_iter)?;
+    let delegation_account = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+
+    if !delegate.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    if mailer_state.is_paused(PAUSE_DELEGATE) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    if delegation_account.owner != program_id {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    let mut delegation_data = delegation_account.try_borrow_mut_data()?;
+    let mut delegation_state: Delegation =
+        BorshDeserialize::deserialize(&mut &delegation_data[8..])?;
+
+    if delegation_state.delegate != Some(*delegate.key) || *delegate.key == Pubkey::default() {
+        return Err(MailerError::NoDelegationToAccept.into());
+    }
+
+    let released = delegation_state.escrowed_fee;
+    if released > 0 {
+        credit_owner(mailer_account, None, released)?;
+        delegation_state.escrowed_fee = 0;
+        delegation_state.refund_deadline = 0;
+        delegation_state.serialize(&mut &mut delegation_data[8..])?;
+    }
+    drop(delegation_data);
+
+    msg!(
+        "Delegation from {} accepted by {}, {} released to owner",
+        delegation_state.delegator,
+        delegate.key,
+        released
+    );
+    Ok(())
+}
+
+/// Refund an escrowed delegation fee rejected within the refund window
+fn process_refund_delegation_fee(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let delegator = next_account_info(account_iter)?;
+    let delegation_account = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let delegator_usdc = next_account_info(account_iter)?;
+    let mailer_usdc = next_account_info(account_iter)?;
+    let token_program = next_account_info(account_iter)?;
+
+    if !delegator.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (mailer_pda, _) = assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    if mailer_state.is_paused(PAUSE_CLAIM) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    let (delegation_pda, _) = pda::delegation(program_id, delegator.key);
+
+    if delegation_account.key != &delegation_pda || delegation_account.owner != program_id {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    let mut delegation_data = delegation_account.try_borrow_mut_data()?;
+    let mut delegation_state: Delegation =
+        BorshDeserialize::deserialize(&mut &delegation_data[8..])?;
+
+    if !delegation_state.fee_refundable || delegation_state.escrowed_fee == 0 {
+        return Err(MailerError::DelegationFeeNotRefundable.into());
+    }
+
+    let amount = delegation_state.escrowed_fee;
+    delegation_state.escrowed_fee = 0;
+    delegation_state.refund_deadline = 0;
+    delegation_state.fee_refundable = false;
+    delegation_state.serialize(&mut &mut delegation_data[8..])?;
+    drop(delegation_data);
+
+    assert_token_program(token_program)?;
+    let usdc_mint = mailer_state.usdc_mint;
+    assert_token_account(
+        accounts,
+        "delegator_usdc",
+        delegator_usdc,
+        delegator.key,
+        &usdc_mint,
+    )?;
+    assert_token_account(
+        accounts,
+        "mailer_usdc",
+        mailer_usdc,
+        &mailer_pda,
+        &usdc_mint,
+    )?;
+
+    invoke_signed(
+        &spl_token::instruction::transfer(
+            token_program.key,
+            mailer_usdc.key,
+            delegator_usdc.key,
+            mailer_account.key,
+            &[],
+            amount,
+        )?,
+        &[
+            mailer_usdc.clone(),
+            delegator_usdc.clone(),
+            mailer_account.clone(),
+            token_program.clone(),
+        ],
+        &[&[seeds::MAILER, &[mailer_state.bump]]],
+    )?;
+
+    msg!("Delegation fee of {} refunded to {}", amount, delegator.key);
+    Ok(())
+}
+
+/// Delegate tracked by the reverse indexes; the default key clears a delegation like `None`
+fn indexed_delegate(delegate: Option<Pubkey>) -> Option<Pubkey> {
+    delegate.filter(|key| *key != Pubkey::default())
+}
+
+/// Locate the DelegateIndex PDA of `delegate` among the instruction's accounts
+fn delegate_index_account<'a, 'b>(
+    program_id: &Pubkey,
+    delegate: &Pubkey,
+    accounts: &'a [AccountInfo<'b>],
+) -> Result<(&'a AccountInfo<'b>, u8), ProgramError> {
+    let (index_pda, index_bump) = pda::delegate_index(program_id, delegate);
+    let index_account = accounts
+        .iter()
+        .find(|account| account.key == &index_pda)
+        .ok_or(MailerError::InvalidPDA)?;
+
+    if !index_account.is_writable
+        || (index_account.lamports() > 0 && index_account.owner != program_id)
+    {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    Ok((index_account, index_bump))
+}
+
+/// Add `delegator` to the reverse index of `delegate`, creating the index at the delegator's expense
+fn add_to_delegate_index<'a>(
+    program_id: &Pubkey,
+    delegate: &Pubkey,
+    delegator: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    accounts: &[AccountInfo<'a>],
+) -> ProgramResult {
+    let (index_account, index_bump) = delegate_index_account(program_id, delegate, accounts)?;
+
+    if index_account.lamports() == 0 {
+        let rent = Rent::get()?;
+        let space = 8 + DelegateIndex::LEN;
+
+        invoke_signed(
+            &system_instruction::create_account(
+                delegator.key,
+                index_account.key,
+                rent.minimum_balance(space),
+                space as u64,
+                program_id,
+            ),
+            &[
+                delegator.clone(),
+                index_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                seeds::DELEGATE_INDEX,
+                &[PDA_VERSION],
+                delegate.as_ref(),
+                &[index_bump],
+            ]],
+        )?;
+
+        let mut index_data = index_account.try_borrow_mut_data()?;
+        index_data[0..8].copy_from_slice(&discriminator::account("DelegateIndex"));
+
+        let index = DelegateIndex {
+            delegate: *delegate,
+            count: 0,
+            accumulator: [0u8; 32],
+            bump: index_bump,
+        };
+        index.serialize(&mut &mut index_data[8..])?;
+    }
+
+    let mut index_data = index_account.try_borrow_mut_data()?;
+    let mut index: DelegateIndex = BorshDeserialize::deserialize(&mut &index_data[8..])?;
+    index.insert(delegator.key);
+    index.serialize(&mut &mut index_data[8..])?;
+    Ok(())
+}
+
+/// Remove `delegator` from the reverse index of `delegate`
+/// Delegations set before the delegate had an index were never added, so a missing index is skipped.
+fn remove_from_delegate_index(
+    program_id: &Pubkey,
+    delegate: &Pubkey,
+    delegator: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let (index_account, _) = delegate_index_account(program_id, delegate, accounts)?;
+
+    if index_account.lamports() == 0 {
+        return Ok(());
+    }
+
+    let mut index_data = index_account.try_borrow_mut_data()?;
+    let mut index: DelegateIndex = BorshDeserialize::deserialize(&mut &index_data[8..])?;
+    index.remove(delegator);
+    index.serialize(&mut &mut index_data[8..])?;
+    Ok(())
+}
+
+/// Set delegation fee (owner only)
+fn process_set_delegation_fee(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    new_fee: u64,
+    expected_current_value: Option<u64>,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_mailer_account(_program_id, accounts, mailer_account)?;
+
+    // Load and update mailer state
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    assert_owner_authority(_program_id, &mailer_state, owner, accounts)?;
+
+    // Check if contract is paused
+    if mailer_state.is_paused(PAUSE_ADMIN) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    assert_fee_not_timelocked(&mailer_state)?;
+    assert_current_value(mailer_state.delegation_fee, expected_current_value)?;
+
+    let old_fee = mailer_state.delegation_fee;
+    mailer_state.delegation_fee = new_fee;
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+
+    msg!("Delegation fee updated from {} to {}", old_fee, new_fee);
+    Ok(())
+}
+
+/// Set custom fee percentage for a specific address (owner only)
+fn process_set_custom_fee_percentage(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    account: Pubkey,
+    percentage: u8,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let fee_discount_account = next_account_info(account_iter)?;
+    let _target_account = next_account_info(account_iter)?;
+    let payer = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_system_program(system_program)?;
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    // Load mailer state and verify owner
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+
+    // Check if contract is paused
+    if mailer_state.is_paused(PAUSE_ADMIN) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    assert_fee_not_timelocked(&mailer_state)?;
+
+    // Validate percentage
+    if percentage > 100 {
+        return Err(MailerError::InvalidPercentage.into());
+    }
+
+    // Verify fee discount account PDA
+    let (discount_pda, bump) = pda::fee_discount(program_id, &account);
+
+    if fee_discount_account.key != &discount_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    // Create or update fee discount account
+    if fee_discount_account.lamports() == 0 {
+        let rent = Rent::get()?;
+        let space = 8 + FeeDiscount::LEN;
+        let lamports = rent.minimum_balance(space);
+
+        invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                fee_discount_account.key,
+                lamports,
+                space as u64,
+                program_id,
+            ),
+            &[
+                payer.clone(),
+                fee_discount_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[seeds::DISCOUNT, &[PDA_VERSION], account.as_ref(), &[bump]]],
+        )?;
+
+        // Verify account is rent-exempt
+        let account_lamports = fee_discount_account.lamports();
+        if !rent.is_exempt(account_lamports, space) {
+            msg!(
+                "ERROR: Fee discount account not rent-exempt! {} lamports for {} bytes",
+                account_lamports,
+                space
+            );
+            return Err(MailerError::AccountNotRentExempt.into());
+        }
+        msg!(
+            "Created rent-exempt fee discount account: {} lamports for {} bytes",
+            account_lamports,
+            space
+        );
+        track_rent_reserve(mailer_account, RentReserve::Discounts, account_lamports)?;
+
+        // Initialize discount account
+        let mut discount_data = fee_discount_account.try_borrow_mut_data()?;
+        discount_data[0..8].copy_from_slice(&discriminator::account("FeeDiscount"));
+
+        let fee_discount = FeeDiscount {
+            account,
+            discount: 100 - percentage, // Store as discount: 0% fee = 100 discount, 100% fee = 0 discount
+            bump,
+        };
+
+        fee_discount.serialize(&mut &mut discount_data[8..])?;
+    } else {
+        // Update existing discount account
+        let mut discount_data = fee_discount_account.try_borrow_mut_data()?;
+        let mut fee_discount: FeeDiscount =
+            BorshDeserialize::deserialize(&mut &discount_data[8..])?;
+        fee_discount.discount = 100 - percentage; // Store as discount
+        fee_discount.serialize(&mut &mut discount_data[8..])?;
+    }
+
+    msg!("Custom fee percentage set for {}: {}%", account, percentage);
+    Ok(())
+}
+
+/// Clear custom fee percentage for a specific address (owner only)
+fn process_clear_custom_fee_percentage(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    account: Pubkey,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let fee_discount_account = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    // Load mailer state and verify owner
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+
+    // Check if contract is paused
+    if mailer_state.is_paused(PAUSE_ADMIN) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    // Verify fee discount account PDA
+    let (discount_pda, _) = pda::fee_discount(program_id, &account);
+
+    if fee_discount_account.key != &discount_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    // Clear by setting discount to 0 (no discount = 100% fee = default behavior)
+    if fee_discount_account.lamports() > 0 {
+        let mut discount_data = fee_discount_account.try_borrow_mut_data()?;
+        let mut fee_discount: FeeDiscount =
+            BorshDeserialize::deserialize(&mut &discount_data[8..])?;
+        fee_discount.discount = 0; // 0 discount = 100% fee = default
+        fee_discount.serialize(&mut &mut discount_data[8..])?;
+    }
+
+    msg!(
+        "Custom fee percentage cleared for {} (reset to 100%)",
+        account
+    );
+    Ok(())
+}
+
+/// Close an emptied recipient claim account, refunding its rent to the owner
+fn process_close_recipient_claim(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    recipient: Pubkey,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let recipient_claim = next_account_info(account_iter)?;
+    let refund_destination = next_account_info(account_iter)?;
+    let closure_log = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+
+    if mailer_state.is_paused(PAUSE_ADMIN) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    // Rent of claim accounts is not attributed to a payer, so it returns to the owner
+    if refund_destination.key != &mailer_state.owner {
+        return Err(MailerError::OnlyOwner.into());
+    }
+
+    let (claim_pda, _) = pda::recipient_claim(program_id, &recipient);
+
+    if recipient_claim.key != &claim_pda || recipient_claim.owner != program_id {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    let claim_data = recipient_claim.try_borrow_data()?;
+    let claim_state = RecipientClaim::from_account_data(&claim_data[8..])?;
+    drop(claim_data);
+
+    if claim_state.amount > 0 {
+        return Err(MailerError::AccountNotEmpty.into());
+    }
+
+    record_closure(program_id, closure_log, recipient_claim)?;
+    let lamports = close_account(recipient_claim, refund_destination)?;
+    release_rent_reserve(mailer_account, RentReserve::Claims, lamports)?;
+
+    msg!(
+        "Recipient claim for {} closed, {} lamports refunded to {}",
+        recipient,
+        lamports,
+        refund_destination.key
+    );
+    Ok(())
+}
+
+/// Close a cleared delegation account, refunding its rent to the delegator
+fn process_close_delegation(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let delegator = next_account_info(account_iter)?;
+    let delegation_account = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let closure_log = next_account_info(account_iter)?;
+
+    if !delegator.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    if mailer_state.is_paused(PAUSE_DELEGATE) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    let (delegation_pda, _) = pda::delegation(program_id, delegator.key);
+
+    if delegation_account.key != &delegation_pda || delegation_account.owner != program_id {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    let delegation_data = delegation_account.try_borrow_data()?;
+    let delegation_state: Delegation = BorshDeserialize::deserialize(&mut &delegation_data[8..])?;
+    drop(delegation_data);
+
+    if delegation_state.delegate.is_some() || delegation_state.escrowed_fee > 0 {
+        return Err(MailerError::AccountNotEmpty.into());
+    }
+
+    record_closure(program_id, closure_log, delegation_account)?;
+    let lamports = close_account(delegation_account, delegator)?;
+    release_rent_reserve(mailer_account, RentReserve::Delegations, lamports)?;
+
+    msg!(
+        "Delegation of {} closed, {} lamports refunded",
+        delegator.key,
+        lamports
+    );
+    Ok(())
+}
+
+/// Close a cleared fee discount account, refunding its rent to the owner
+fn process_close_fee_discount(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    account: Pubkey,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let fee_discount_account = next_account_info(account_iter)?;
+    let refund_destination = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+
+    if mailer_state.is_paused(PAUSE_ADMIN) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    // The owner paid for the discount account when setting it
+    if refund_destination.key != &mailer_state.owner {
+        return Err(MailerError::OnlyOwner.into());
+    }
+
+    let (discount_pda, _) = pda::fee_discount(program_id, &account);
+
+    if fee_discount_account.key != &discount_pda || fee_discount_account.owner != program_id {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    let discount_data = fee_discount_account.try_borrow_data()?;
+    let fee_discount: FeeDiscount = BorshDeserialize::deserialize(&mut &discount_data[8..])?;
+    drop(discount_data);
+
+    if fee_discount.discount > 0 {
+        return Err(MailerError::AccountNotEmpty.into());
+    }
+
+    let lamports = close_account(fee_discount_account, refund_destination)?;
+    release_rent_reserve(mailer_account, RentReserve::Discounts, lamports)?;
+
+    msg!(
+        "Fee discount for {} closed, {} lamports refunded to {}",
+        account,
+        lamports,
+        refund_destination.key
+    );
+    Ok(())
+}
+
+/// Move a claim, delegation or discount account from previous-version seeds to current ones
+fn process_migrate_account_version(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    kind: MigrationKind,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let subject = next_account_info(account_iter)?;
+    let old_account = next_account_info(account_iter)?;
+    let new_account = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !subject.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_system_program(system_program)?;
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    if mailer_state.is_paused(PAUSE_ADMIN) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    let (old_pda, _) = pda::versioned(program_id, kind, subject.key, pda::PREVIOUS_PDA_VERSION);
+    let (new_pda, new_bump) = pda::versioned(program_id, kind, subject.key, PDA_VERSION);
+
+    if old_account.key != &old_pda || old_account.owner != program_id || new_account.key != &new_pda
+    {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    let old_data = old_account.try_borrow_data()?.to_vec();
+    match old_data.get(..8).and_then(discriminator::identify) {
+        Some((name, _)) if name == kind.account_name() => {}
+        _ => return Err(MailerError::UnknownDiscriminator.into()),
+    }
+
+    let created = new_account.lamports() == 0;
+    if created {
+        let rent = Rent::get()?;
+        let space = kind.space();
+
+        invoke_signed(
+            &system_instruction::create_account(
+                subject.key,
+                new_account.key,
+                rent.minimum_balance(space),
+                space as u64,
+                program_id,
+            ),
+            &[subject.clone(), new_account.clone(), system_program.clone()],
+            &[&[
+                kind.seed(),
+                &[PDA_VERSION],
+                subject.key.as_ref(),
+                &[new_bump],
+            ]],
+        )?;
+        track_rent_reserve(mailer_account, kind.rent_reserve(), new_account.lamports())?;
+
+        new_account.try_borrow_mut_data()?[..8]
+            .copy_from_slice(&discriminator::account(kind.account_name()));
+    } else if new_account.owner != program_id {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    let mut new_data = new_account.try_borrow_mut_data()?;
+    match kind {
+        MigrationKind::RecipientClaim => {
+            let mut claim = RecipientClaim::from_account_data(&old_data[8..])?;
+            if !created {
+                // Sends after the version bump already credited the new claim
+                let existing = RecipientClaim::from_account_data(&new_data[8..])?;
+                claim.amount = claim
+                    .amount
+                    .checked_add(existing.amount)
+                    .ok_or(MailerError::MathOverflow)?;
+                if existing.timestamp > claim.timestamp {
+                    claim.timestamp = existing.timestamp;
+                    claim.paused_offset = existing.paused_offset;
+                }
+                if existing.no_expiry {
+                    claim.no_expiry = true;
+                    claim.surcharge_paid_until = existing.surcharge_paid_until;
+                }
+            }
+            claim.bump = new_bump;
+            claim.serialize(&mut &mut new_data[8..])?;
+        }
+        MigrationKind::Delegation if created => {
+            let mut delegation: Delegation = BorshDeserialize::deserialize(&mut &old_data[8..])?;
+            delegation.bump = new_bump;
+            delegation.serialize(&mut &mut new_data[8..])?;
+        }
+        MigrationKind::FeeDiscount if created => {
+            let mut fee_discount: FeeDiscount = BorshDeserialize::deserialize(&mut &old_data[8..])?;
+            fee_discount.bump = new_bump;
+            fee_discount.serialize(&mut &mut new_data[8..])?;
+        }
+        // State set under the current seeds supersedes the old account
+        MigrationKind::Delegation | MigrationKind::FeeDiscount => {}
+    }
+    drop(new_data);
+
+    let lamports = close_account(old_account, subject)?;
+    release_rent_reserve(mailer_account, kind.rent_reserve(), lamports)?;
+
+    msg!(
+        "Account migrated: {:?} of {} moved from {} to {}",
+        kind,
+        subject.key,
+        old_account.key,
+        new_account.key
+    );
+    msg!("{} lamports of rent refunded to {}", lamports, subject.key);
+    Ok(())
+}
+
+/// Load the webhook of `webhook_id`, failing with `InvalidWebhook` unless it is active and
+/// registered by `sender`
+fn assert_webhook(
+    program_id: &Pubkey,
+    webhook_account: &AccountInfo,
+    webhook_id: &str,
+    sender: &Pubkey,
+) -> Result<Webhook, ProgramError> {
+    let id_hash = solana_program::hash::hash(webhook_id.as_bytes()).to_bytes();
+    if webhook_account.key != &pda::webhook(program_id, &id_hash).0 {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    if webhook_account.owner != program_id || webhook_account.data_len() < 8 + Webhook::LEN {
+        return Err(MailerError::InvalidWebhook.into());
+    }
+
+    let webhook: Webhook =
+        BorshDeserialize::deserialize(&mut &webhook_account.try_borrow_data()?[8..])?;
+    if !webhook.active || webhook.owner != *sender {
+        return Err(MailerError::InvalidWebhook.into());
+    }
+    Ok(webhook)
+}
+
+/// Collect a webhook's surcharge for a send whose fee was paid, crediting the amount
+/// received to the webhook owner
+#[allow(clippy::too_many_arguments)]
+fn collect_webhook_surcharge<'a>(
+    webhook_account: &AccountInfo<'a>,
+    surcharge: u64,
+    token_program: &AccountInfo<'a>,
+    sender_usdc: &AccountInfo<'a>,
+    mailer_usdc: &AccountInfo<'a>,
+    sender: &AccountInfo<'a>,
+    accounts: &[AccountInfo<'a>],
+    fee_paid: bool,
+) -> Result<u64, ProgramError> {
+    if !fee_paid || surcharge == 0 {
+        return Ok(0);
+    }
+
+    let received = transfer_to_vault(
+        token_program,
+        sender_usdc,
+        mailer_usdc,
+        sender,
+        accounts,
+        surcharge,
+    )?;
+
+    let mut webhook_data = webhook_account.try_borrow_mut_data()?;
+    let mut webhook: Webhook = BorshDeserialize::deserialize(&mut &webhook_data[8..])?;
+    webhook.claimable = webhook
+        .claimable
+        .checked_add(received)
+        .ok_or(MailerError::MathOverflow)?;
+    webhook.serialize(&mut &mut webhook_data[8..])?;
+    Ok(received)
+}
+
+/// Count a send routed through the webhook
+fn record_webhook_route(webhook_account: &AccountInfo) -> ProgramResult {
+    let mut webhook_data = webhook_account.try_borrow_mut_data()?;
+    let mut webhook: Webhook = BorshDeserialize::deserialize(&mut &webhook_data[8..])?;
+    webhook.sends_routed = webhook
+        .sends_routed
+        .checked_add(1)
+        .ok_or(MailerError::MathOverflow)?;
+    webhook.serialize(&mut &mut webhook_data[8..])?;
+    Ok(())
+}
+
+/// Fail with `StaleState` when an owner setter expected a different current value
+fn assert_current_value(current: u64, expected: Option<u64>) -> ProgramResult {
+    if expected.is_some_and(|expected| expected != current) {
+        return Err(MailerError::StaleState.into());
+    }
+    Ok(())
+}
+
+/// Accept the SPL Token and Token-2022 programs
+fn assert_token_program(token_program: &AccountInfo) -> Result<(), ProgramError> {
+    if token_program.key != &spl_token::id() && token_program.key != &spl_token_2022::id() {
+        return Err(MailerError::InvalidTokenProgram.into());
+    }
+    Ok(())
+}
+
+fn assert_system_program(system_program: &AccountInfo) -> Result<(), ProgramError> {
+    if system_program.key != &solana_program::system_program::id() {
+        return Err(MailerError::InvalidSystemProgram.into());
+    }
+    Ok(())
+}
+
+/// Log which account of an instruction failed a check
+/// `index` is the account's position in the instruction accounts, so integrators can tell
+/// which of several token accounts was wrong when only a generic error code comes back.
+fn log_account_mismatch(
+    accounts: &[AccountInfo],
+    label: &str,
+    account: &AccountInfo,
+    expected: &str,
+    found: &str,
+) {
+    let index = accounts
+        .iter()
+        .position(|acc| acc.key == account.key)
+        .map_or_else(|| "?".to_string(), |index| index.to_string());
+    msg!(
+        "AccountMismatch {{ account: {}, index: {}, key: {}, expected: {}, found: {} }}",
+        label,
+        index,
+        account.key,
+        expected,
+        found
+    );
+}
+
+/// Check a token account's owner and mint, logging the offending account under `label`
+fn assert_token_account(
+    accounts: &[AccountInfo],
+    label: &str,
+    token_account_info: &AccountInfo,
+    expected_owner: &Pubkey,
+    expected_mint: &Pubkey,
+) -> Result<(), ProgramError> {
+    let data = token_account_info.try_borrow_data()?;
+    let token_account = match unpack_token_account(&data) {
+        Ok(token_account) => token_account,
+        Err(err) => {
+            log_account_mismatch(
+                accounts,
+                label,
+                token_account_info,
+                "token account",
+                "invalid token account data",
+            );
+            return Err(err);
+        }
+    };
+    drop(data);
+
+    if token_account.owner != *expected_owner {
+        log_account_mismatch(
+            accounts,
+            label,
+            token_account_info,
+            &format!("owner {}", expected_owner),
+            &format!("owner {}", token_account.owner),
+        );
+        return Err(MailerError::InvalidAccountOwner.into());
+    }
+
+    if token_account.mint != *expected_mint {
+        log_account_mismatch(
+            accounts,
+            label,
+            token_account_info,
+            &format!("mint {}", expected_mint),
+            &format!("mint {}", token_account.mint),
+        );
+        return Err(MailerError::InvalidMint.into());
+    }
+
+    Ok(())
+}
+
+/// Mint of a token account
+fn token_mint(token_account_info: &AccountInfo) -> Result<Pubkey, ProgramError> {
+    let data = token_account_info.try_borrow_data()?;
+    Ok(unpack_token_account(&data)?.mint)
+}
+
+/// FeeMint account whitelisting `mint` for fees, `None` for the USDC mint
+/// Any other mint must be whitelisted with `AddFeeMint` and its FeeMint account passed.
+fn fee_mint_account<'a, 'b>(
+    program_id: &Pubkey,
+    mailer_state: &MailerState,
+    mint: &Pubkey,
+    accounts: &'b [AccountInfo<'a>],
+) -> Result<Option<&'b AccountInfo<'a>>, ProgramError> {
+    if *mint == mailer_state.usdc_mint {
+        return Ok(None);
+    }
+
+    let (fee_mint_pda, _) = pda::fee_mint(program_id, mint);
+    accounts
+        .iter()
+        .find(|acc| acc.key == &fee_mint_pda)
+        .filter(|acc| acc.owner == program_id && acc.data_len() >= 8 + FeeMint::LEN)
+        .map(Some)
+        .ok_or_else(|| MailerError::InvalidMint.into())
+}
+
+/// Load a whitelisted fee mint
+fn load_fee_mint(fee_mint_account: &AccountInfo) -> Result<FeeMint, ProgramError> {
+    let fee_mint_data = fee_mint_account.try_borrow_data()?;
+    Ok(BorshDeserialize::deserialize(&mut &fee_mint_data[8..])?)
+}
+
+/// Credit the owner share of a fee, to the FeeMint account when paid in a whitelisted mint
+fn credit_owner(
+    mailer_account: &AccountInfo,
+    fee_mint_account: Option<&AccountInfo>,
+    amount: u64,
+) -> ProgramResult {
+    // Updated in place rather than through a full decode and re-encode on every send
+    match fee_mint_account {
+        Some(fee_mint_account) => {
+            let mut fee_mint_data = fee_mint_account.try_borrow_mut_data()?;
+            fields::add_u64(&mut fee_mint_data, FeeMint::OWNER_CLAIMABLE_OFFSET, amount)?;
+        }
+        None => {
+            let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+            fields::add_u64(
+                &mut mailer_data,
+                MailerState::OWNER_CLAIMABLE_OFFSET,
+                amount,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn assert_mailer_account(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    mailer_account: &AccountInfo,
+) -> Result<(Pubkey, u8), ProgramError> {
+    let (mailer_pda, bump) = cached_pda(
+        program_id,
+        mailer_account,
+        MailerState::BUMP_OFFSET,
+        |bump| pda::mailer_state_with_bump(program_id, bump),
+        || pda::mailer_state(program_id),
+    );
+    if mailer_account.key != &mailer_pda {
+        log_account_mismatch(
+            accounts,
+            "mailer",
+            mailer_account,
+            &format!("mailer PDA {}", mailer_pda),
+            &format!("{}", mailer_account.key),
+        );
+        return Err(MailerError::InvalidPDA.into());
+    }
+    if mailer_account.data_len() < 8 + MailerState::LEN {
+        msg!(
+            "Mailer state predates layout version {}; run MigrateState",
+            MAILER_STATE_VERSION
+        );
+        return Err(MailerError::StateMigrationRequired.into());
+    }
+    Ok((mailer_pda, bump))
+}
+
+/// PDA of `account` and its bump, rebuilt from the bump stored at `bump_offset` of its data
+/// with `with_bump`, or searched for with `find` when that does not give the account's address
+/// Accounts the program created hold their canonical bump, so an existing account costs one
+/// `create_program_address` instead of a `find_program_address` search from bump 255; only
+/// accounts not created yet, and mismatches being reported, pay for the search.
+fn cached_pda(
+    program_id: &Pubkey,
+    account: &AccountInfo,
+    bump_offset: usize,
+    with_bump: impl FnOnce(u8) -> Option<Pubkey>,
+    find: impl FnOnce() -> (Pubkey, u8),
+) -> (Pubkey, u8) {
+    if account.owner == program_id {
+        let stored_bump = account
+            .try_borrow_data()
+            .ok()
+            .and_then(|data| data.get(bump_offset).copied());
+        if let Some(bump) = stored_bump {
+            if with_bump(bump).as_ref() == Some(account.key) {
+                return (*account.key, bump);
+            }
+        }
+    }
+    find()
+}
+
+/// Created account of type `name` among `accounts` at the PDA `with_bump` rebuilds from the
+/// bump stored at `bump_offset` of its data, with that bump
+/// Each candidate costs one `create_program_address`, so accounts that are only used once
+/// created are found without searching for their PDA.
+fn created_pda_account<'b, 'a>(
+    program_id: &Pubkey,
+    accounts: &'b [AccountInfo<'a>],
+    name: &str,
+    bump_offset: impl Fn(&[u8]) -> Option<usize>,
+    with_bump: impl Fn(u8) -> Option<Pubkey>,
+) -> Option<(&'b AccountInfo<'a>, u8)> {
+    let current = discriminator::account(name);
+    let legacy = discriminator::legacy(name);
+    accounts.iter().find_map(|acc| {
+        if acc.owner != program_id {
+            return None;
+        }
+        let data = acc.try_borrow_data().ok()?;
+        let discriminator = data.get(..8)?;
+        if discriminator != current && Some(discriminator) != legacy.as_ref().map(|d| &d[..]) {
+            return None;
+        }
+        let bump = *data.get(bump_offset(&data)?)?;
+        (with_bump(bump).as_ref() == Some(acc.key)).then_some((acc, bump))
+    })
+}
+
+/// Trailing account at a PDA, created or not, with its bump; `None` when it is not passed
+/// A created account is found as by [`created_pda_account`]; the PDA is only searched for
+/// with `find` when none is and an account yet to be created (holding no lamports) was
+/// passed.
+fn trailing_pda_account<'b, 'a>(
+    program_id: &Pubkey,
+    accounts: &'b [AccountInfo<'a>],
+    name: &str,
+    bump_offset: impl Fn(&[u8]) -> Option<usize>,
+    with_bump: impl Fn(u8) -> Option<Pubkey>,
+    find: impl FnOnce() -> (Pubkey, u8),
+) -> Option<(&'b AccountInfo<'a>, u8)> {
+    let created = created_pda_account(program_id, accounts, name, bump_offset, with_bump);
+    if created.is_some() || accounts.iter().all(|acc| acc.lamports() > 0) {
+        return created;
+    }
+    let (address, bump) = find();
+    accounts
+        .iter()
+        .find(|acc| acc.key == &address)
+        .map(|acc| (acc, bump))
+}
+
+/// Base state of an SPL Token or Token-2022 token account, its extensions skipped
+fn unpack_token_account(data: &[u8]) -> Result<spl_token_2022::state::Account, ProgramError> {
+    Ok(StateWithExtensions::<spl_token_2022::state::Account>::unpack(data)?.base)
+}
+
+/// Decimals of an SPL Token or Token-2022 mint account
+fn mint_decimals(mint_account: &AccountInfo) -> Result<u8, ProgramError> {
+    if mint_account.owner != &spl_token::id() && mint_account.owner != &spl_token_2022::id() {
+        return Err(MailerError::InvalidAccountOwner.into());
+    }
+    let data = mint_account.try_borrow_data()?;
+    Ok(
+        StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?
+            .base
+            .decimals,
+    )
+}
+
+/// Read the token amount of a token account, Token-2022 accounts with extensions included
+fn token_balance(token_account: &AccountInfo) -> Result<u64, ProgramError> {
+    let data = token_account.try_borrow_data()?;
+    Ok(unpack_token_account(&data)?.amount)
+}
+
+/// Transfer tokens into the mailer vault and return the amount actually received
+/// The vault balance is read before and after the transfer so fee-on-transfer mints
+/// cannot inflate claim accounting; any shortfall is logged as a skim.
+fn transfer_to_vault<'a>(
+    token_program: &AccountInfo<'a>,
+    source: &AccountInfo<'a>,
+    vault: &AccountInfo<'a>,
+    authority: &AccountInfo<'a>,
+    accounts: &[AccountInfo<'a>],
+    amount: u64,
+) -> Result<u64, ProgramError> {
+    transfer_to_vault_signed(
+        token_program,
+        source,
+        vault,
+        authority,
+        accounts,
+        amount,
+        &[],
+    )
+}
+
+/// Transfer tokens into the mailer vault with a PDA authority signing via `signer_seeds`
+/// The transfer is checked against the vault's mint when that account is among `accounts`,
+/// which Token-2022 requires; SPL Token vaults fall back to an unchecked transfer without it.
+fn transfer_to_vault_signed<'a>(
+    token_program: &AccountInfo<'a>,
+    source: &AccountInfo<'a>,
+    vault: &AccountInfo<'a>,
+    authority: &AccountInfo<'a>,
+    accounts: &[AccountInfo<'a>],
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<u64, ProgramError> {
+    let balance_before = token_balance(vault)?;
+    let mint = token_mint(vault)?;
+
+    match accounts.iter().find(|acc| acc.key == &mint) {
+        Some(mint_account) => invoke_signed(
+            &spl_token_2022::instruction::transfer_checked(
+                token_program.key,
+                source.key,
+                mint_account.key,
+                vault.key,
+                authority.key,
+                &[],
+                amount,
+                mint_decimals(mint_account)?,
+            )?,
+            &[
+                source.clone(),
+                mint_account.clone(),
+                vault.clone(),
+                authority.clone(),
+                token_program.clone(),
+            ],
+            signer_seeds,
+        )?,
+        None if token_program.key == &spl_token::id() => invoke_signed(
+            &spl_token::instruction::transfer(
+                token_program.key,
+                source.key,
+                vault.key,
+                authority.key,
+                &[],
+                amount,
+            )?,
+            &[
+                source.clone(),
+                vault.clone(),
+                authority.clone(),
+                token_program.clone(),
+            ],
+            signer_seeds,
+        )?,
+        None => return Err(MailerError::MissingMintAccount.into()),
+    }
+
+    let received = token_balance(vault)?
+        .checked_sub(balance_before)
+        .ok_or(MailerError::MathOverflow)?;
+
+    if received < amount {
+        msg!(
+            "Transfer skim detected: sent {}, received {}, skimmed {}",
+            amount,
+            received,
+            amount - received
+        );
+    }
+    Ok(received)
+}
+
+/// Score a send to `to` with the registered spam oracle, if any
+/// The oracle program must be among the supplied accounts. Scores above the configured
+/// threshold reject the send.
+fn check_spam_score(
+    mailer_state: &MailerState,
+    sender: &Pubkey,
+    to: &Pubkey,
+    accounts: &[AccountInfo],
+) -> Result<Option<u64>, ProgramError> {
+    let oracle = match mailer_state.spam_oracle {
+        Some(oracle) => oracle,
+        None => return Ok(None),
+    };
+    let oracle_program = accounts
+        .iter()
+        .find(|acc| acc.key == &oracle)
+        .ok_or(MailerError::InvalidSpamOracle)?;
+
+    invoke(
+        &spam_oracle::score_instruction(&oracle, sender, to),
+        std::slice::from_ref(oracle_program),
+    )?;
+    let score = match get_return_data() {
+        Some((program, data)) if program == oracle => spam_oracle::decode_score(&data),
+        _ => None,
+    }
+    .ok_or(MailerError::InvalidSpamOracle)?;
+
+    if matches!(mailer_state.spam_threshold, Some(threshold) if score > threshold) {
+        msg!(
+            "Send from {} to {} rejected: spam score {}",
+            sender,
+            to,
+            score
+        );
+        return Err(MailerError::SpamScoreTooHigh.into());
+    }
+    Ok(Some(score))
+}
+
+/// Fail with `PayloadTooLarge` when `value` is longer than `max_len` bytes; 0 allows any length
+fn assert_payload_len(value: &str, max_len: u16) -> ProgramResult {
+    if max_len > 0 && value.len() > max_len as usize {
+        return Err(MailerError::PayloadTooLarge.into());
+    }
+    Ok(())
+}
+
+/// Fail with `AddressSanctioned` when sanctions are enabled and `address` is in the registry
+/// The address's Sanctioned PDA must then be among the supplied accounts even when it has
+/// not been created, so it cannot be left out.
+fn assert_not_sanctioned(
+    program_id: &Pubkey,
+    mailer_state: &MailerState,
+    address: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if !mailer_state.sanctions_enabled {
+        return Ok(());
+    }
+
+    let (sanctioned_pda, _) = pda::sanctioned(program_id, address);
+    let sanctioned_account = accounts
+        .iter()
+        .find(|acc| acc.key == &sanctioned_pda)
+        .ok_or(MailerError::InvalidPDA)?;
+
+    if sanctioned_account.owner == program_id && sanctioned_account.lamports() > 0 {
+        msg!("Address {} is sanctioned", address);
+        return Err(MailerError::AddressSanctioned.into());
+    }
+    Ok(())
+}
+
+/// Fail with `VerificationRequired` when a KYC verifier is registered and `sender` holds no
+/// valid attestation from it among the supplied accounts
+fn assert_verified(
+    mailer_state: &MailerState,
+    sender: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let verifier = match mailer_state.kyc_verifier {
+        Some(verifier) => verifier,
+        None => return Ok(()),
+    };
+
+    let (attestation_pda, _) = attestation::attestation_address(&verifier, sender);
+    let now = Clock::get()?.unix_timestamp;
+    let verified = accounts
+        .iter()
+        .find(|acc| acc.key == &attestation_pda)
+        .filter(|acc| acc.owner == &verifier)
+        .map(|acc| attestation::is_valid(&acc.data.borrow(), sender, now))
+        .unwrap_or(false);
+
+    if !verified {
+        msg!(
+            "Priority send by {} rejected: no valid attestation from {}",
+            sender,
+            verifier
+        );
+        return Err(MailerError::VerificationRequired.into());
+    }
+    Ok(())
+}
+
+/// Count a send by `sender` against the owner-configured rate limit, if enabled
+/// The sender's rate limit PDA must be among the supplied accounts; it is created on the
+/// sender's first limited send, with `payer` funding the rent through the system program
+/// (also looked up in the supplied accounts).
+fn enforce_rate_limit<'a>(
+    program_id: &Pubkey,
+    mailer_state: &MailerState,
+    sender: &Pubkey,
+    payer: &AccountInfo<'a>,
+    accounts: &[AccountInfo<'a>],
+) -> ProgramResult {
+    if mailer_state.rate_limit_max_sends == 0 {
+        return Ok(());
+    }
+
+    let (rate_limit_pda, rate_limit_bump) = pda::rate_limit(program_id, sender);
+    let rate_limit_account = accounts
+        .iter()
+        .find(|acc| acc.key == &rate_limit_pda)
+        .ok_or(MailerError::InvalidPDA)?;
+
+    // Create the rate limit account on the sender's first limited send
+    if rate_limit_account.lamports() == 0 {
+        let system_program = accounts
+            .iter()
+            .find(|acc| acc.key == &solana_program::system_program::id())
+            .ok_or(MailerError::InvalidSystemProgram)?;
+        let rent = Rent::get()?;
+        let space = 8 + RateLimit::LEN;
+
+        invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                rate_limit_account.key,
+                rent.minimum_balance(space),
+                space as u64,
+                program_id,
+            ),
+            &[
+                payer.clone(),
+                rate_limit_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                seeds::RATE_LIMIT,
+                &[PDA_VERSION],
+                sender.as_ref(),
+                &[rate_limit_bump],
+            ]],
+        )?;
+
+        let mut rate_limit_data = rate_limit_account.try_borrow_mut_data()?;
+        rate_limit_data[0..8].copy_from_slice(&discriminator::account("RateLimit"));
+        RateLimit {
+            sender: *sender,
+            window_start: 0,
+            count: 0,
+            bump: rate_limit_bump,
+        }
+        .serialize(&mut &mut rate_limit_data[8..])?;
+    } else if rate_limit_account.owner != program_id {
+        return Err(MailerError::InvalidAccountOwner.into());
+    }
+
+    let mut rate_limit_data = rate_limit_account.try_borrow_mut_data()?;
+    let mut rate_limit: RateLimit = BorshDeserialize::deserialize(&mut &rate_limit_data[8..])?;
+
+    let now = Clock::get()?.unix_timestamp;
+    if rate_limit.window_elapsed(mailer_state.rate_limit_window, now) {
+        rate_limit.window_start = now;
+        rate_limit.count = 0;
+    }
+
+    if rate_limit.count >= mailer_state.rate_limit_max_sends {
+        msg!(
+            "Send from {} rejected: rate limit of {} sends per {} seconds reached",
+            sender,
+            mailer_state.rate_limit_max_sends,
+            mailer_state.rate_limit_window
+        );
+        return Err(MailerError::RateLimitExceeded.into());
+    }
+
+    rate_limit.count += 1;
+    rate_limit.serialize(&mut &mut rate_limit_data[8..])?;
+    Ok(())
+}
+
+/// Create the recipient claim account of `to` if it does not exist, returning whether it was
+/// created
+/// An existing claim still in the [`RecipientClaimV1`] layout is upgraded in place instead.
+#[allow(clippy::too_many_arguments)]
+fn create_recipient_claim<'a>(
+    program_id: &Pubkey,
+    payer: &AccountInfo<'a>,
+    recipient_claim: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    mailer_account: &AccountInfo<'a>,
+    to: Pubkey,
+    claim_bump: u8,
+    claim_seeds: &[&[u8]],
+) -> Result<bool, ProgramError> {
+    if recipient_claim.lamports() > 0 {
+        if recipient_claim.owner == program_id
+            && recipient_claim.data_len() == 8 + RecipientClaimV1::LEN
+        {
+            upgrade_recipient_claim(payer, recipient_claim, system_program, mailer_account)?;
+        }
+        return Ok(false);
+    }
+
+    let rent = Rent::get()?;
+    let space = 8 + RecipientClaim::LEN;
+    let lamports = rent.minimum_balance(space);
+
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            recipient_claim.key,
+            lamports,
+            space as u64,
+            program_id,
+        ),
+        &[
+            payer.clone(),
+            recipient_claim.clone(),
+            system_program.clone(),
+        ],
+        &[claim_seeds],
+    )?;
+
+    // Verify account is rent-exempt
+    let account_lamports = recipient_claim.lamports();
+    if !rent.is_exempt(account_lamports, space) {
+        msg!(
+            "ERROR: Recipient claim account not rent-exempt! {} lamports for {} bytes",
+            account_lamports,
+            space
+        );
+        return Err(MailerError::AccountNotRentExempt.into());
+    }
+    msg!(
+        "Created rent-exempt recipient claim account: {} lamports for {} bytes",
+        account_lamports,
+        space
+    );
+    track_rent_reserve(mailer_account, RentReserve::Claims, account_lamports)?;
+
+    // Initialize claim account
+    let mut claim_data = recipient_claim.try_borrow_mut_data()?;
+    claim_data[0..8].copy_from_slice(&discriminator::account("RecipientClaim"));
+    RecipientClaim {
+        recipient: to,
+        amount: 0,
+        timestamp: 0,
+        bump: claim_bump,
+        paused_offset: 0,
+        no_expiry: false,
+        surcharge_paid_until: 0,
+        extensions: 0,
+        messages_received: 0,
+        total_claimed: 0,
+    }
+    .serialize(&mut &mut claim_data[8..])?;
+    Ok(true)
+}
+
+/// Realloc a [`RecipientClaimV1`] account to the current layout so its counters are kept,
+/// `payer` funding the extra rent
+fn upgrade_recipient_claim<'a>(
+    payer: &AccountInfo<'a>,
+    recipient_claim: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    mailer_account: &AccountInfo<'a>,
+) -> ProgramResult {
+    let claim = RecipientClaim::from_account_data(&recipient_claim.try_borrow_data()?[8..])?;
+
+    let space = 8 + RecipientClaim::LEN;
+    let shortfall = Rent::get()?
+        .minimum_balance(space)
+        .saturating_sub(recipient_claim.lamports());
+    if shortfall > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, recipient_claim.key, shortfall),
+            &[
+                payer.clone(),
+                recipient_claim.clone(),
+                system_program.clone(),
+            ],
+        )?;
+        track_rent_reserve(mailer_account, RentReserve::Claims, shortfall)?;
+    }
+    recipient_claim.realloc(space, true)?;
+    claim.serialize(&mut &mut recipient_claim.try_borrow_mut_data()?[8..])?;
+
+    msg!(
+        "Recipient claim of {} upgraded to {} bytes",
+        claim.recipient,
+        space
+    );
+    Ok(())
+}
+
+/// Record `payer` as the rent payer of a claim created by an unpaid send, under RefundPayer
+/// The claim's ClaimRentPayer PDA and the system program must be among the supplied accounts;
+/// `payer` funds the record's rent too.
+fn record_claim_rent_payer<'a>(
+    program_id: &Pubkey,
+    mailer_state: &MailerState,
+    payer: &AccountInfo<'a>,
+    recipient_claim: &AccountInfo<'a>,
+    accounts: &[AccountInfo<'a>],
+) -> ProgramResult {
+    if mailer_state.claim_creation_policy != ClaimCreationPolicy::RefundPayer {
+        return Ok(());
+    }
+
+    let (record_pda, record_bump) = pda::claim_rent_payer(program_id, recipient_claim.key);
+    let record_account = accounts
+        .iter()
+        .find(|acc| acc.key == &record_pda)
+        .ok_or(MailerError::InvalidPDA)?;
+
+    // A record left behind by an earlier claim at this address is taken over
+    if record_account.lamports() == 0 {
+        let system_program = accounts
+            .iter()
+            .find(|acc| acc.key == &solana_program::system_program::id())
+            .ok_or(MailerError::InvalidSystemProgram)?;
+        let space = 8 + ClaimRentPayer::LEN;
+
+        invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                record_account.key,
+                Rent::get()?.minimum_balance(space),
+                space as u64,
+                program_id,
+            ),
+            &[
+                payer.clone(),
+                record_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                seeds::CLAIM_RENT,
+                &[PDA_VERSION],
+                recipient_claim.key.as_ref(),
+                &[record_bump],
+            ]],
+        )?;
+    } else if record_account.owner != program_id {
+        return Err(MailerError::InvalidAccountOwner.into());
+    }
+
+    let mut record_data = record_account.try_borrow_mut_data()?;
+    record_data[0..8].copy_from_slice(&discriminator::account("ClaimRentPayer"));
+    ClaimRentPayer {
+        claim: *recipient_claim.key,
+        payer: *payer.key,
+        bump: record_bump,
+    }
+    .serialize(&mut &mut record_data[8..])?;
+
+    msg!(
+        "Rent of claim {} refundable to {}",
+        recipient_claim.key,
+        payer.key
+    );
+    Ok(())
+}
+
+/// Take the next sequence number of `to`'s inbox, `None` when its inbox account is not passed
+/// The inbox account is created on first use, with `payer` funding the rent through the
+/// system program.
+fn next_inbox_sequence<'a>(
+    program_id: &Pubkey,
+    to: &Pubkey,
+    payer: &AccountInfo<'a>,
+    accounts: &[AccountInfo<'a>],
+) -> Result<Option<u64>, ProgramError> {
+    let (inbox_account, inbox_bump) = match trailing_pda_account(
+        program_id,
+        accounts,
+        "RecipientInbox",
+        |_| Some(RecipientInbox::BUMP_OFFSET),
+        |bump| pda::recipient_inbox_with_bump(program_id, to, bump),
+        || pda::recipient_inbox(program_id, to),
+    ) {
+        Some(found) => found,
+        None => return Ok(None),
+    };
+
+    if inbox_account.lamports() == 0 {
+        let system_program = accounts
+            .iter()
+            .find(|acc| acc.key == &solana_program::system_program::id())
+            .ok_or(MailerError::InvalidSystemProgram)?;
+        let rent = Rent::get()?;
+        let space = 8 + RecipientInbox::LEN;
+
+        invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                inbox_account.key,
+                rent.minimum_balance(space),
+                space as u64,
+                program_id,
+            ),
+            &[payer.clone(), inbox_account.clone(), system_program.clone()],
+            &[&[seeds::INBOX, &[PDA_VERSION], to.as_ref(), &[inbox_bump]]],
+        )?;
+
+        let mut inbox_data = inbox_account.try_borrow_mut_data()?;
+        inbox_data[0..8].copy_from_slice(&discriminator::account("RecipientInbox"));
+        RecipientInbox {
+            recipient: *to,
+            sequence: 0,
+            bump: inbox_bump,
+        }
+        .serialize(&mut &mut inbox_data[8..])?;
+    } else if inbox_account.owner != program_id {
+        return Err(MailerError::InvalidAccountOwner.into());
+    }
+
+    let mut inbox_data = inbox_account.try_borrow_mut_data()?;
+    let mut inbox: RecipientInbox = BorshDeserialize::deserialize(&mut &inbox_data[8..])?;
+    inbox.sequence = inbox
+        .sequence
+        .checked_add(1)
+        .ok_or(MailerError::MathOverflow)?;
+    inbox.serialize(&mut &mut inbox_data[8..])?;
+    Ok(Some(inbox.sequence))
+}
+
+/// Take the next nonce of `sender`'s MessageNonce account, creating the account paid by
+/// `payer` on first use
+/// The account must be passed as a trailing account, so identical sends never share an id.
+fn next_message_nonce<'a>(
+    program_id: &Pubkey,
+    sender: &Pubkey,
+    payer: &AccountInfo<'a>,
+    accounts: &[AccountInfo<'a>],
+) -> Result<u64, ProgramError> {
+    let (nonce_account, nonce_bump) = trailing_pda_account(
+        program_id,
+        accounts,
+        "MessageNonce",
+        |_| Some(MessageNonce::BUMP_OFFSET),
+        |bump| pda::message_nonce_with_bump(program_id, sender, bump),
+        || pda::message_nonce(program_id, sender),
+    )
+    .ok_or(MailerError::MissingMessageNonceAccount)?;
+
+    if nonce_account.lamports() == 0 {
+        let system_program = accounts
+            .iter()
+            .find(|acc| acc.key == &solana_program::system_program::id())
+            .ok_or(MailerError::InvalidSystemProgram)?;
+        let rent = Rent::get()?;
+        let space = 8 + MessageNonce::LEN;
+
+        invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                nonce_account.key,
+                rent.minimum_balance(space),
+                space as u64,
+                program_id,
+            ),
+            &[payer.clone(), nonce_account.clone(), system_program.clone()],
+            &[&[
+                seeds::MESSAGE_NONCE,
+                &[PDA_VERSION],
+                sender.as_ref(),
+                &[nonce_bump],
+            ]],
+        )?;
+
+        let mut nonce_data = nonce_account.try_borrow_mut_data()?;
+        nonce_data[0..8].copy_from_slice(&discriminator::account("MessageNonce"));
+        MessageNonce {
+            sender: *sender,
+            nonce: 0,
+            bump: nonce_bump,
+        }
+        .serialize(&mut &mut nonce_data[8..])?;
+    } else if nonce_account.owner != program_id {
+        return Err(MailerError::InvalidAccountOwner.into());
+    }
+
+    let mut nonce_data = nonce_account.try_borrow_mut_data()?;
+    let mut message_nonce: MessageNonce = BorshDeserialize::deserialize(&mut &nonce_data[8..])?;
+    message_nonce.nonce = message_nonce
+        .nonce
+        .checked_add(1)
+        .ok_or(MailerError::MathOverflow)?;
+    message_nonce.serialize(&mut &mut nonce_data[8..])?;
+    Ok(message_nonce.nonce)
+}
+
+/// Whether the idempotency `nonce` is among those kept in `sender`'s SendNonce ring buffer
+/// The account must be passed as a trailing account; an uncreated one holds no nonces.
+fn send_nonce_used(
+    program_id: &Pubkey,
+    sender: &Pubkey,
+    nonce: u64,
+    accounts: &[AccountInfo],
+) -> Result<bool, ProgramError> {
+    let (nonce_account, _) = trailing_pda_account(
+        program_id,
+        accounts,
+        "SendNonce",
+        SendNonce::bump_offset,
+        |bump| pda::send_nonce_with_bump(program_id, sender, bump),
+        || pda::send_nonce(program_id, sender),
+    )
+    .ok_or(MailerError::MissingSendNonceAccount)?;
+    if nonce_account.lamports() == 0 {
+        return Ok(false);
+    }
+    if nonce_account.owner != program_id {
+        return Err(MailerError::InvalidAccountOwner.into());
+    }
+
+    let nonce_data = nonce_account.try_borrow_data()?;
+    let send_nonce: SendNonce = BorshDeserialize::deserialize(&mut &nonce_data[8..])?;
+    Ok(send_nonce.nonces.contains(&nonce))
+}
+
+/// Record the idempotency `nonce` in `sender`'s SendNonce ring buffer once its send went out
+/// The account must be passed as a trailing account; it is created on first use, with
+/// `sender` funding the rent through the system program.
+fn record_send_nonce<'a>(
+    program_id: &Pubkey,
+    sender: &AccountInfo<'a>,
+    nonce: u64,
+    accounts: &[AccountInfo<'a>],
+) -> ProgramResult {
+    let (nonce_account, nonce_bump) = trailing_pda_account(
+        program_id,
+        accounts,
+        "SendNonce",
+        SendNonce::bump_offset,
+        |bump| pda::send_nonce_with_bump(program_id, sender.key, bump),
+        || pda::send_nonce(program_id, sender.key),
+    )
+    .ok_or(MailerError::MissingSendNonceAccount)?;
+
+    if nonce_account.lamports() == 0 {
+        let system_program = accounts
+            .iter()
+            .find(|acc| acc.key == &solana_program::system_program::id())
+            .ok_or(MailerError::InvalidSystemProgram)?;
+        let rent = Rent::get()?;
+        let space = 8 + SendNonce::LEN;
+
+        invoke_signed(
+            &system_instruction::create_account(
+                sender.key,
+                nonce_account.key,
+                rent.minimum_balance(space),
+                space as u64,
+                program_id,
+            ),
+            &[
+                sender.clone(),
+                nonce_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                seeds::SEND_NONCE,
+                &[PDA_VERSION],
+                sender.key.as_ref(),
+                &[nonce_bump],
+            ]],
+        )?;
+
+        let mut nonce_data = nonce_account.try_borrow_mut_data()?;
+        nonce_data[0..8].copy_from_slice(&discriminator::account("SendNonce"));
+        SendNonce {
+            sender: *sender.key,
+            total: 0,
+            nonces: Vec::new(),
+            bump: nonce_bump,
+        }
+        .serialize(&mut &mut nonce_data[8..])?;
+    } else if nonce_account.owner != program_id {
+        return Err(MailerError::InvalidAccountOwner.into());
+    }
+
+    let mut nonce_data = nonce_account.try_borrow_mut_data()?;
+    let mut send_nonce: SendNonce = BorshDeserialize::deserialize(&mut &nonce_data[8..])?;
+    let slot = (send_nonce.total % SEND_NONCE_CAPACITY as u64) as usize;
+    if slot < send_nonce.nonces.len() {
+        send_nonce.nonces[slot] = nonce;
+    } else {
+        send_nonce.nonces.push(nonce);
+    }
+    send_nonce.total = send_nonce
+        .total
+        .checked_add(1)
+        .ok_or(MailerError::MathOverflow)?;
+    send_nonce.serialize(&mut &mut nonce_data[8..])?;
+    Ok(())
+}
+
+/// Whether a send pays its fee in lamports: the lamport vault is passed as the mailer USDC
+/// account and the sender as their own USDC account
+fn pays_in_lamports(
+    program_id: &Pubkey,
+    mailer_state: &MailerState,
+    sender: &AccountInfo,
+    sender_usdc: &AccountInfo,
+    mailer_usdc: &AccountInfo,
+) -> Result<bool, ProgramError> {
+    // The lamport vault is owned by the program once created; funded accounts owned by
+    // another program, such as token vaults, are never it
+    if mailer_usdc.owner != program_id && mailer_usdc.lamports() > 0 {
+        return Ok(false);
+    }
+    let (vault_pda, _) = pda::lamport_vault(program_id);
+    if mailer_usdc.key != &vault_pda {
+        return Ok(false);
+    }
+
+    if mailer_state.lamport_send_fee == 0 {
+        return Err(MailerError::LamportFeesDisabled.into());
+    }
+    if mailer_usdc.owner != program_id {
+        return Err(MailerError::InvalidPDA.into());
+    }
+    if sender_usdc.key != sender.key {
+        return Err(MailerError::InvalidAccountOwner.into());
+    }
+    Ok(true)
+}
+
+/// Lamport send fee, pegged to the fee oracle's target when one is set
+/// The oracle's price account must be among the supplied accounts.
+fn lamport_send_fee(
+    mailer_state: &MailerState,
+    accounts: &[AccountInfo],
+) -> Result<u64, ProgramError> {
+    let oracle = match mailer_state.fee_oracle {
+        Some(oracle) => oracle,
+        None => return Ok(mailer_state.lamport_send_fee),
+    };
+    let price_account = accounts
+        .iter()
+        .find(|acc| acc.key == &oracle)
+        .ok_or(MailerError::InvalidFeeOracle)?;
+    let price = price_oracle::decode_price(&price_account.try_borrow_data()?)
+        .ok_or(MailerError::InvalidFeeOracle)?;
+
+    let age = Clock::get()?
+        .unix_timestamp
+        .saturating_sub(price.publish_time);
+    if age > mailer_state.fee_oracle_max_age {
+        msg!("Fee oracle price is {} seconds old", age);
+        return Err(MailerError::StaleOraclePrice.into());
+    }
+
+    price_oracle::pegged_fee(mailer_state.fee_oracle_target, &price)
+        .ok_or_else(|| MailerError::MathOverflow.into())
+}
+
+/// Accounts a send's fee is collected with
+struct FeeContext<'b, 'a> {
+    program_id: &'b Pubkey,
+    mailer_state: &'b MailerState,
+    mailer_account: &'b AccountInfo<'a>,
+    sender: &'b AccountInfo<'a>,
+    sender_usdc: &'b AccountInfo<'a>,
+    mailer_usdc: &'b AccountInfo<'a>,
+    token_program: &'b AccountInfo<'a>,
+    accounts: &'b [AccountInfo<'a>],
+    /// Whether the fee is paid in lamports into the lamport vault
+    pays_lamports: bool,
+    /// Mint the fee is paid in; the USDC mint for lamport fees
+    fee_mint: Pubkey,
+    /// FeeMint account of a whitelisted fee mint, `None` for USDC and lamport fees
+    fee_mint_account: Option<&'b AccountInfo<'a>>,
+}
+
+/// Recipient a send's fee is charged for
+enum FeeRecipient<'b, 'a> {
+    /// Wallet recipient, subject to their inbox policy. Priority sends pay the full fee and
+    /// share it with the recipient's claim, unless downgraded; others pay the owner's 10%.
+    Wallet {
         to: Pubkey,
-        webhook_id: String,
-        revenue_share_to_receiver: bool,
-        resolve_sender_to_name: bool,
+        priority: bool,
+        allow_downgrade: bool,
+        /// Amount added on top of a priority fee and credited in full to the recipient's claim
+        tip: u64,
+        recipient_claim: &'b AccountInfo<'a>,
+        system_program: &'b AccountInfo<'a>,
+        /// Signer funding the recipient claim's rent, the sender unless sponsored
+        rent_payer: &'b AccountInfo<'a>,
     },
+    /// Off-chain recipient such as an email address, paying the owner's 10%
+    Email { target: &'b dyn std::fmt::Display },
+}
+
+/// How `collect_fee` charges a send
+struct FeePolicy<'b, 'a> {
+    recipient: FeeRecipient<'b, 'a>,
+    /// Payload bytes charged by the payload-length fee tiers
+    payload_len: usize,
+    /// Fail the send with `FeePaymentFailed` instead of dropping an unpaid fee
+    strict: bool,
+    /// Webhook routing the send and its surcharge, collected once the fee is paid
+    webhook: Option<(&'b AccountInfo<'a>, u64)>,
+}
+
+/// Fee charged for a send by `collect_fee`
+struct FeeOutcome {
+    /// Fee due after subscriptions, payload tiers, discounts and inbox policies
+    effective_fee: u64,
+    /// Whether the send went out as priority, after any downgrade
+    priority: bool,
+    /// Whether the fee was paid, or none was due
+    paid: bool,
+    /// USDC received into the vault, as counted by send stats
+    usdc_fee: u64,
+    /// Recipient's share of a paid priority fee
+    recipient_amount: Option<u64>,
+    /// Webhook surcharge collected
+    surcharge: u64,
+    /// Tip credited to the recipient's claim
+    tip: u64,
+}
+
+/// Charge a send its fee under `policy`
+/// Prices the fee, transfers it into the vault and credits the owner and recipient shares.
+/// Unpaid fees fail strict sends and are logged as MailDropped otherwise; an insolvent vault
+/// always fails the send.
+fn collect_fee<'b, 'a>(
+    ctx: &FeeContext<'b, 'a>,
+    policy: FeePolicy<'b, 'a>,
+) -> Result<FeeOutcome, ProgramError> {
+    let mailer_state = ctx.mailer_state;
+
+    // The owner share of USDC fees goes straight to the treasury once one is set
+    let treasury = if ctx.fee_mint_account.is_none() && !ctx.pays_lamports {
+        treasury_account(mailer_state, ctx.accounts)?.map(|account| Treasury {
+            account,
+            token_program: ctx.token_program,
+            mailer_bump: mailer_state.bump,
+        })
+    } else {
+        None
+    };
+
+    // Calculate effective fee based on custom discount (if any), or skip if fee_paused or subscribed
+    let mut effective_fee = if mailer_state.fee_paused
+        || use_subscription(ctx.program_id, ctx.sender.key, ctx.accounts)?
+    {
+        0 // Skip fee collection when fee_paused is true or the sender's subscription covers it
+    } else {
+        let current_fee = match ctx.fee_mint_account {
+            Some(fee_mint_account) => load_fee_mint(fee_mint_account)?.send_fee,
+            None if ctx.pays_lamports => lamport_send_fee(mailer_state, ctx.accounts)?,
+            None => mailer_state.effective_send_fee(Clock::get()?.unix_timestamp),
+        };
+        let current_fee = mailer_state.payload_fee(current_fee, policy.payload_len)?;
+        calculate_fee_with_discount(ctx.program_id, ctx.sender.key, ctx.accounts, current_fee)?
+    };
+
+    let mut priority = false;
+    if let FeeRecipient::Wallet {
+        to,
+        priority: requested,
+        allow_downgrade,
+        tip,
+        recipient_claim,
+        ..
+    } = &policy.recipient
+    {
+        // Enforce the recipient's inbox policy (rejects or surcharges disallowed senders)
+        effective_fee = apply_inbox_policy(
+            ctx.program_id,
+            ctx.sender.key,
+            to,
+            ctx.accounts,
+            effective_fee,
+        )?;
+
+        // Downgrade priority sends the recipient's claim cannot take, if the sender allows it
+        priority = *requested
+            && !downgrade_priority_send(
+                ctx.program_id,
+                mailer_state,
+                ctx.sender.key,
+                to,
+                recipient_claim,
+                ctx.accounts,
+                effective_fee,
+                *tip,
+                *allow_downgrade,
+            )?;
+
+        // The recipient's minimum counts everything a priority send pays them for
+        if priority {
+            assert_recipient_min_fee(
+                ctx.program_id,
+                to,
+                ctx.accounts,
+                effective_fee.saturating_add(*tip),
+            )?;
+        }
+    }
+
+    let mut received = 0;
+    let mut recipient_amount = None;
+    let mut tip_received = 0;
+    let mut funded_claim = None;
+    let mut claim_rent_payer = ctx.sender;
+    let fee_error = match &policy.recipient {
+        FeeRecipient::Wallet {
+            to,
+            tip,
+            recipient_claim,
+            system_program,
+            rent_payer,
+            ..
+        } if priority => {
+            // Priority mode: full fee with revenue sharing, into one claim account per fee mint
+            let (claim_pda, claim_bump) = cached_pda(
+                ctx.program_id,
+                recipient_claim,
+                RecipientClaim::BUMP_OFFSET,
+                |bump| match ctx.fee_mint_account {
+                    Some(_) => pda::recipient_claim_in_mint_with_bump(
+                        ctx.program_id,
+                        to,
+                        &ctx.fee_mint,
+                        bump,
+                    ),
+                    None => pda::recipient_claim_with_bump(ctx.program_id, to, bump),
+                },
+                || match ctx.fee_mint_account {
+                    Some(_) => pda::recipient_claim_in_mint(ctx.program_id, to, &ctx.fee_mint),
+                    None => pda::recipient_claim(ctx.program_id, to),
+                },
+            );
+            let bump_seed = [claim_bump];
+            let claim_seeds: Vec<&[u8]> = match ctx.fee_mint_account {
+                Some(_) => vec![
+                    seeds::CLAIM,
+                    &[PDA_VERSION],
+                    to.as_ref(),
+                    ctx.fee_mint.as_ref(),
+                    &bump_seed,
+                ],
+                None => vec![seeds::CLAIM, &[PDA_VERSION], to.as_ref(), &bump_seed],
+            };
+
+            if recipient_claim.key != &claim_pda {
+                return Err(MailerError::InvalidPDA.into());
+            }
+
+            // Create the claim account up front (paid by the rent payer), or once the fee has
+            // arrived under the AfterTransfer policy
+            let create_claim = || {
+                create_recipient_claim(
+                    ctx.program_id,
+                    rent_payer,
+                    recipient_claim,
+                    system_program,
+                    ctx.mailer_account,
+                    *to,
+                    claim_bump,
+                    &claim_seeds,
+                )
+            };
+            let defer_claim =
+                mailer_state.claim_creation_policy == ClaimCreationPolicy::AfterTransfer;
+            claim_rent_payer = rent_payer;
+            if !defer_claim && create_claim()? {
+                funded_claim = Some(*recipient_claim);
+            }
+
+            let fee_error = if effective_fee > 0 {
+                let transfer_result = pay_send_fee(
+                    ctx.program_id,
+                    &mailer_state.usdc_mint,
+                    ctx.mailer_account,
+                    ctx.token_program,
+                    ctx.sender_usdc,
+                    ctx.mailer_usdc,
+                    ctx.sender,
+                    ctx.accounts,
+                    effective_fee,
+                );
+
+                // Under AfterTransfer the claim account is created only once the fee has arrived
+                if defer_claim && transfer_result.is_ok() {
+                    create_claim()?;
+                }
+
+                // Record revenue shares on the amount actually received (only if transfer succeeded)
+                let recorded = transfer_result.and_then(|amount| {
+                    received = amount;
+                    record_shares(
+                        recipient_claim,
+                        ctx.mailer_account,
+                        ctx.mailer_usdc,
+                        ctx.fee_mint_account,
+                        treasury.as_ref(),
+                        *to,
+                        amount,
+                    )
+                });
+                fail_if_insolvent(&recorded)?;
+                recipient_amount = recorded.as_ref().ok().copied();
+                recorded.err()
+            } else {
+                None // No fee required
+            };
+
+            // Tips ride on a paid fee, in its mint, and go to the recipient in full
+            if fee_error.is_none() && *tip > 0 {
+                if defer_claim {
+                    create_claim()?;
+                }
+                tip_received = transfer_to_vault(
+                    ctx.token_program,
+                    ctx.sender_usdc,
+                    ctx.mailer_usdc,
+                    ctx.sender,
+                    ctx.accounts,
+                    *tip,
+                )?;
+                credit_tip(
+                    recipient_claim,
+                    ctx.mailer_account,
+                    ctx.mailer_usdc,
+                    ctx.fee_mint_account,
+                    tip_received,
+                )?;
+            }
+            fee_error
+        }
+        _ => {
+            // Standard mode: 10% fee only, no revenue sharing
+            let owner_fee = standard_fee(effective_fee)?; // 10% of effective fee
+            if owner_fee > 0 {
+                match transfer_send_fee(
+                    ctx.program_id,
+                    &mailer_state.usdc_mint,
+                    ctx.mailer_account,
+                    ctx.pays_lamports,
+                    ctx.token_program,
+                    ctx.sender_usdc,
+                    ctx.mailer_usdc,
+                    ctx.sender,
+                    ctx.accounts,
+                    owner_fee,
+                ) {
+                    Ok(amount) => {
+                        // Update owner claimable with the amount actually received
+                        received = amount;
+                        match &treasury {
+                            Some(treasury) if amount > 0 => forward_to_treasury(
+                                treasury,
+                                ctx.mailer_account,
+                                ctx.mailer_usdc,
+                                amount,
+                            )?,
+                            _ if amount > 0 => credit_owner_fee(
+                                ctx.mailer_account,
+                                ctx.fee_mint_account,
+                                ctx.pays_lamports,
+                                amount,
+                            )?,
+                            _ => {}
+                        }
+                        None
+                    }
+                    Err(err) => Some(err),
+                }
+            } else {
+                None // No fee required
+            }
+        }
+    };
+    let paid = fee_error.is_none();
+
+    // Strict senders get an error instead of an unpaid send, others a MailDropped log
+    if let Some(reason) = &fee_error {
+        if policy.strict {
+            return Err(strict_fee_error(reason));
+        }
+        let (recipient, target): (Option<&Pubkey>, &dyn std::fmt::Display) = match &policy.recipient
+        {
+            FeeRecipient::Wallet { to, .. } => (Some(to), to),
+            FeeRecipient::Email { target } => (None, *target),
+        };
+        log_mail_dropped(ctx.sender.key, recipient, target, reason);
+
+        // Under RefundPayer the rent payer may later close the empty claim this send funded
+        if let Some(recipient_claim) = funded_claim {
+            record_claim_rent_payer(
+                ctx.program_id,
+                mailer_state,
+                claim_rent_payer,
+                recipient_claim,
+                ctx.accounts,
+            )?;
+        }
+    }
+
+    // Surcharges of delivered sends accrue to the webhook owner
+    let surcharge = match policy.webhook {
+        Some((webhook_account, surcharge)) => collect_webhook_surcharge(
+            webhook_account,
+            surcharge,
+            ctx.token_program,
+            ctx.sender_usdc,
+            ctx.mailer_usdc,
+            ctx.sender,
+            ctx.accounts,
+            paid,
+        )?,
+        None => 0,
+    };
+
+    // Fees in whitelisted mints or lamports count as sends without adding to the USDC total
+    let usdc_fee = if paid && ctx.fee_mint_account.is_none() && !ctx.pays_lamports {
+        received
+    } else {
+        0
+    };
+    // Volume counts only what the owner keeps: a recipient share can flow back to the sender,
+    // so counting it would let priority sends to oneself buy a discount for 10% of their fees
+    let owner_fee = usdc_fee.saturating_sub(recipient_amount.unwrap_or(0));
+    record_fees_paid(ctx.program_id, ctx.sender, ctx.accounts, owner_fee)?;
+
+    Ok(FeeOutcome {
+        effective_fee,
+        priority,
+        paid,
+        usdc_fee,
+        recipient_amount,
+        surcharge,
+        tip: tip_received,
+    })
+}
+
+/// Transfer a send fee into the mailer's vault, in lamports or USDC, returning the amount received
+#[allow(clippy::too_many_arguments)]
+fn transfer_send_fee<'a>(
+    program_id: &Pubkey,
+    usdc_mint: &Pubkey,
+    mailer_account: &AccountInfo<'a>,
+    pays_lamports: bool,
+    token_program: &AccountInfo<'a>,
+    source: &AccountInfo<'a>,
+    vault: &AccountInfo<'a>,
+    sender: &AccountInfo<'a>,
+    accounts: &[AccountInfo<'a>],
+    amount: u64,
+) -> Result<u64, ProgramError> {
+    if !pays_lamports {
+        return pay_send_fee(
+            program_id,
+            usdc_mint,
+            mailer_account,
+            token_program,
+            source,
+            vault,
+            sender,
+            accounts,
+            amount,
+        );
+    }
+
+    let system_program = accounts
+        .iter()
+        .find(|acc| acc.key == &solana_program::system_program::id())
+        .ok_or(MailerError::InvalidSystemProgram)?;
+    if sender.lamports() < amount {
+        return Err(MailerError::InsufficientFee.into());
+    }
+    invoke(
+        &system_instruction::transfer(sender.key, vault.key, amount),
+        &[sender.clone(), vault.clone(), system_program.clone()],
+    )?;
+    Ok(amount)
+}
+
+/// Pay a USDC send fee from the payer's SendCredits when that account is passed among
+/// `accounts` and covers it, without a token transfer; otherwise transfer the fee from
+/// `source` into the vault. Returns the amount received.
+#[allow(clippy::too_many_arguments)]
+fn pay_send_fee<'a>(
+    program_id: &Pubkey,
+    usdc_mint: &Pubkey,
+    mailer_account: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    source: &AccountInfo<'a>,
+    vault: &AccountInfo<'a>,
+    payer: &AccountInfo<'a>,
+    accounts: &[AccountInfo<'a>],
+    amount: u64,
+) -> Result<u64, ProgramError> {
+    if spend_send_credits(
+        program_id,
+        usdc_mint,
+        payer.key,
+        mailer_account,
+        vault,
+        accounts,
+        amount,
+    )? {
+        return Ok(amount);
+    }
+    if token_balance(source)? < amount {
+        return Err(MailerError::InsufficientFee.into());
+    }
+    transfer_to_vault(token_program, source, vault, payer, accounts, amount)
+}
+
+/// Debit `amount` from the SendCredits of `payer`, returning whether the credits paid for a
+/// fee collected into the USDC `vault`
+/// The amount leaves the tracked credit liabilities of `mailer_account`, to be recorded
+/// again as owner or recipient shares.
+fn spend_send_credits(
+    program_id: &Pubkey,
+    usdc_mint: &Pubkey,
+    payer: &Pubkey,
+    mailer_account: &AccountInfo,
+    vault: &AccountInfo,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> Result<bool, ProgramError> {
+    let credits_account = match created_pda_account(
+        program_id,
+        accounts,
+        "SendCredits",
+        |_| Some(SendCredits::BUMP_OFFSET),
+        |bump| pda::send_credits_with_bump(program_id, payer, bump),
+    ) {
+        Some((account, _)) if account.is_writable => account,
+        _ => return Ok(false),
+    };
+
+    // Credits are held in USDC; fees in whitelisted mints are always transferred
+    if token_mint(vault)? != *usdc_mint {
+        return Ok(false);
+    }
+
+    let mut credits_data = credits_account.try_borrow_mut_data()?;
+    let mut credits: SendCredits = BorshDeserialize::deserialize(&mut &credits_data[8..])?;
+    if credits.balance < amount {
+        return Ok(false);
+    }
+
+    credits.balance -= amount;
+    credits.serialize(&mut &mut credits_data[8..])?;
+
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    mailer_state.release_credit_liability(amount);
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+    msg!(
+        "Send credits of {} debited {}, {} left",
+        payer,
+        amount,
+        credits.balance
+    );
+    Ok(true)
+}
+
+/// Use one message of `sender`'s subscription, returning whether it covers the send fee
+/// The Subscription PDA is optional and must be passed writable; expired or exhausted
+/// subscriptions leave the fee to be paid as usual.
+fn use_subscription(
+    program_id: &Pubkey,
+    sender: &Pubkey,
+    accounts: &[AccountInfo],
+) -> Result<bool, ProgramError> {
+    let subscription_account = match created_pda_account(
+        program_id,
+        accounts,
+        "Subscription",
+        |_| Some(Subscription::BUMP_OFFSET),
+        |bump| pda::subscription_with_bump(program_id, sender, bump),
+    ) {
+        Some((account, _)) if account.is_writable => account,
+        _ => return Ok(false),
+    };
+
+    let mut subscription_data = subscription_account.try_borrow_mut_data()?;
+    let mut subscription: Subscription =
+        BorshDeserialize::deserialize(&mut &subscription_data[8..])?;
+    if !subscription.is_active(Clock::get()?.unix_timestamp) {
+        return Ok(false);
+    }
+
+    subscription.messages_remaining -= 1;
+    subscription.serialize(&mut &mut subscription_data[8..])?;
+    msg!(
+        "Send by {} covered by subscription to plan {}, {} messages left",
+        sender,
+        subscription.plan_id,
+        subscription.messages_remaining
+    );
+    Ok(true)
+}
+
+/// Credit the owner share of a send fee, to the lamport counter when paid in lamports
+fn credit_owner_fee(
+    mailer_account: &AccountInfo,
+    fee_mint_account: Option<&AccountInfo>,
+    pays_lamports: bool,
+    amount: u64,
+) -> ProgramResult {
+    if !pays_lamports {
+        return credit_owner(mailer_account, fee_mint_account, amount);
+    }
+
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    mailer_state.owner_claimable_lamports = mailer_state
+        .owner_claimable_lamports
+        .checked_add(amount)
+        .ok_or(MailerError::MathOverflow)?;
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+    Ok(())
+}
+
+/// Send log detail for lamport fees, empty when fees are paid in tokens
+fn lamport_fee_detail(pays_lamports: bool) -> &'static str {
+    if pays_lamports {
+        ", fee in lamports"
+    } else {
+        ""
+    }
+}
+
+/// Send log detail for the token the fee was paid in, so clients can render the amount:
+/// its mint and decimals for USDC, the mint of a whitelisted fee mint (whose decimals are
+/// not stored) and the decimals of lamport fees
+fn fee_token_detail(
+    mailer_state: &MailerState,
+    pays_lamports: bool,
+    fee_mint_account: Option<&AccountInfo>,
+    fee_mint: &Pubkey,
+) -> String {
+    if pays_lamports {
+        format!(", fee decimals: {}", LAMPORT_DECIMALS)
+    } else if fee_mint_account.is_some() {
+        format!(", fee mint: {}", fee_mint)
+    } else {
+        format!(
+            ", fee mint: {}, fee decimals: {}",
+            mailer_state.usdc_mint, mailer_state.usdc_decimals
+        )
+    }
+}
+
+/// Log detail of a webhook surcharge collected with a send, if any
+fn webhook_surcharge_detail(surcharge: u64) -> String {
+    if surcharge > 0 {
+        format!(", webhook surcharge: {}", surcharge)
+    } else {
+        String::new()
+    }
+}
+
+/// Send log detail for a tip credited to the recipient, empty without one
+fn tip_detail(tip: u64) -> String {
+    if tip > 0 {
+        format!(", tip: {}", tip)
+    } else {
+        String::new()
+    }
+}
+
+/// Send log detail for a spam score, empty when no oracle is registered
+fn spam_score_detail(spam_score: Option<u64>) -> String {
+    spam_score
+        .map(|score| format!(", spam score: {}", score))
+        .unwrap_or_default()
+}
+
+/// Send log detail for the recipient's inbox sequence, empty when no inbox was passed
+fn inbox_sequence_detail(inbox_sequence: Option<u64>) -> String {
+    inbox_sequence
+        .map(|sequence| format!(", inbox sequence: {}", sequence))
+        .unwrap_or_default()
+}
+
+/// Send log detail for the message id and, when given, its thread and parent message
+fn thread_detail(
+    message_id: &[u8; 32],
+    thread_id: Option<[u8; 32]>,
+    in_reply_to: Option<[u8; 32]>,
+) -> String {
+    let mut detail = format!(", message id: {}", Hash::new_from_array(*message_id));
+    if let Some(thread_id) = thread_id {
+        detail.push_str(&format!(", thread: {}", Hash::new_from_array(thread_id)));
+    }
+    if let Some(in_reply_to) = in_reply_to {
+        detail.push_str(&format!(
+            ", in reply to: {}",
+            Hash::new_from_array(in_reply_to)
+        ));
+    }
+    detail
+}
+
+/// Whether a priority send of `fee` to `to` must go out as a standard send instead
+/// The recipient's claim is frozen while they are sanctioned (sanctioned recipients cannot
+/// claim), and capped when its balance plus the recipient share would exceed the claim cap.
+/// With the sender's consent such sends downgrade, logging
+/// `DowngradedToStandard { sender, to, reason }`; without it a capped send fails with
+/// `ClaimCapExceeded` and a frozen claim accrues as before.
+#[allow(clippy::too_many_arguments)]
+fn downgrade_priority_send(
+    program_id: &Pubkey,
+    mailer_state: &MailerState,
+    sender: &Pubkey,
+    to: &Pubkey,
+    recipient_claim: &AccountInfo,
+    accounts: &[AccountInfo],
+    fee: u64,
+    tip: u64,
+    allow_downgrade: bool,
+) -> Result<bool, ProgramError> {
+    let frozen = recipient_claim_frozen(program_id, mailer_state, to, accounts);
+    let capped = !frozen && mailer_state.claim_cap > 0 && {
+        let balance = if recipient_claim.owner == program_id && recipient_claim.data_len() > 8 {
+            let claim_data = recipient_claim.try_borrow_data()?;
+            let claim_state = RecipientClaim::from_account_data(&claim_data[8..])?;
+            claim_state.amount
+        } else {
+            0
+        };
+        balance.saturating_add(fee - fee / 10).saturating_add(tip) > mailer_state.claim_cap
+    };
+    if !frozen && !capped {
+        return Ok(false);
+    }
+
+    if !allow_downgrade {
+        if capped {
+            msg!(
+                "Claim of {} would exceed the claim cap {}",
+                to,
+                mailer_state.claim_cap
+            );
+            return Err(MailerError::ClaimCapExceeded.into());
+        }
+        return Ok(false);
+    }
+
+    let reason = if frozen { "claim frozen" } else { "claim cap" };
+    msg!(
+        "{}DowngradedToStandard {{ sender: {}, to: {}, reason: {} }}",
+        recipient_log_prefix(to),
+        sender,
+        to,
+        reason
+    );
+    Ok(true)
+}
+
+/// Whether sanctions are enabled and `recipient`'s Sanctioned PDA is among the supplied
+/// accounts and active; the PDA is optional here, unlike in `assert_not_sanctioned`
+fn recipient_claim_frozen(
+    program_id: &Pubkey,
+    mailer_state: &MailerState,
+    recipient: &Pubkey,
+    accounts: &[AccountInfo],
+) -> bool {
+    if !mailer_state.sanctions_enabled {
+        return false;
+    }
+
+    let (sanctioned_pda, _) = pda::sanctioned(program_id, recipient);
+    accounts
+        .iter()
+        .any(|acc| acc.key == &sanctioned_pda && acc.owner == program_id && acc.lamports() > 0)
+}
+
+/// Error failing a strict send whose fee payment failed with `reason`: `InsufficientFee`
+/// as is, `FeePaymentFailed` for any other failure
+fn strict_fee_error(reason: &ProgramError) -> ProgramError {
+    if *reason == MailerError::InsufficientFee.into() {
+        return reason.clone();
+    }
+    MailerError::FeePaymentFailed.into()
+}
+
+/// Log a soft-failed send as `MailDropped { sender, to, reason }` for indexers and dApps,
+/// keyed to `recipient` for sends to a wallet
+fn log_mail_dropped(
+    sender: &Pubkey,
+    recipient: Option<&Pubkey>,
+    to: &dyn std::fmt::Display,
+    reason: &ProgramError,
+) {
+    msg!(
+        "{}MailDropped {{ sender: {}, to: {}, reason: {} }}",
+        recipient.map(recipient_log_prefix).unwrap_or_default(),
+        sender,
+        to,
+        reason
+    );
+}
+
+/// Set the return data to the `SendResult` of the message `message_id`, with the compute
+/// units left
+/// Called last, after every CPI of the send, since an invoked program clears return data.
+fn report_send(message_id: [u8; 32]) -> ProgramResult {
+    let result = SendResult {
+        remaining_units: sol_remaining_compute_units(),
+        message_id,
+    };
+    set_return_data(&borsh::to_vec(&result)?);
+    Ok(())
+}
+
+/// Update the MailerStats account passed among `accounts`, required while stats are enabled
+fn update_stats(
+    program_id: &Pubkey,
+    mailer_state: &MailerState,
+    accounts: &[AccountInfo],
+    update: impl FnOnce(&mut MailerStats),
+) -> ProgramResult {
+    if !mailer_state.stats_enabled {
+        return Ok(());
+    }
+
+    let (stats_pda, _) = pda::stats(program_id);
+    let stats_account = accounts
+        .iter()
+        .find(|account| account.key == &stats_pda)
+        .ok_or(MailerError::InvalidPDA)?;
+
+    if stats_account.owner != program_id || !stats_account.is_writable {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    let mut stats_data = stats_account.try_borrow_mut_data()?;
+    let mut stats: MailerStats = BorshDeserialize::deserialize(&mut &stats_data[8..])?;
+    update(&mut stats);
+    stats.serialize(&mut &mut stats_data[8..])?;
+    Ok(())
+}
+
+/// Count a send in the stats; `usdc_fee` is the fee received in USDC, 0 for other fee tokens
+fn record_send_stats(
+    program_id: &Pubkey,
+    mailer_state: &MailerState,
+    accounts: &[AccountInfo],
+    priority: bool,
+    usdc_fee: u64,
+) -> ProgramResult {
+    update_stats(program_id, mailer_state, accounts, |stats| {
+        stats.record_send(priority, usdc_fee)
+    })
+}
+
+/// Count a claim payout in USDC in the stats
+fn record_claim_stats(
+    program_id: &Pubkey,
+    mailer_state: &MailerState,
+    accounts: &[AccountInfo],
+    usdc_amount: u64,
+) -> ProgramResult {
+    update_stats(program_id, mailer_state, accounts, |stats| {
+        stats.record_claim(usdc_amount)
+    })
+}
+
+/// Add the rent locked in a newly created account to its family's reserve in the mailer state
+fn track_rent_reserve(
+    mailer_account: &AccountInfo,
+    reserve: RentReserve,
+    lamports: u64,
+) -> ProgramResult {
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    mailer_state.add_rent_reserve(reserve, lamports);
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+    Ok(())
+}
+
+/// Remove the rent of a closed account from its family's reserve in the mailer state
+fn release_rent_reserve(
+    mailer_account: &AccountInfo,
+    reserve: RentReserve,
+    lamports: u64,
+) -> ProgramResult {
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    mailer_state.release_rent_reserve(reserve, lamports);
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+    Ok(())
+}
+
+/// Append the final state hash of `account` to the closure log before it is closed
+fn record_closure(
+    program_id: &Pubkey,
+    closure_log: &AccountInfo,
+    account: &AccountInfo,
+) -> ProgramResult {
+    if closure_log.key != &pda::closure_log(program_id).0 {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    if closure_log.owner != program_id {
+        return Err(MailerError::NotInitialized.into());
+    }
+
+    let record = ClosureRecord {
+        account: *account.key,
+        state_hash: hashv(&[&account.try_borrow_data()?]).to_bytes(),
+        closed_at: Clock::get()?.unix_timestamp,
+    };
+
+    let mut log_data = closure_log.try_borrow_mut_data()?;
+    let mut log: ClosureLog = BorshDeserialize::deserialize(&mut &log_data[8..])?;
+    let slot = (log.total % CLOSURE_LOG_CAPACITY as u64) as usize;
+    if slot < log.records.len() {
+        log.records[slot] = record;
+    } else {
+        log.records.push(record);
+    }
+    log.total = log.total.checked_add(1).ok_or(MailerError::MathOverflow)?;
+    log.serialize(&mut &mut log_data[8..])?;
+
+    msg!(
+        "Closure of {} recorded (state hash: {}, index: {})",
+        account.key,
+        Hash::new_from_array(record.state_hash),
+        log.total - 1
+    );
+    Ok(())
+}
+
+/// Close a program-owned account: zero its data and move all its lamports to `destination`
+/// The runtime garbage-collects the zero-lamport account at the end of the transaction.
+fn close_account(account: &AccountInfo, destination: &AccountInfo) -> Result<u64, ProgramError> {
+    let lamports = account.lamports();
+    let refunded = destination
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(MailerError::MathOverflow)?;
+
+    **destination.try_borrow_mut_lamports()? = refunded;
+    **account.try_borrow_mut_lamports()? = 0;
+    account.try_borrow_mut_data()?.fill(0);
+    Ok(lamports)
+}
+
+/// Record revenue shares for priority messages, returning the recipient's share
+/// USDC shares fail with `TemporarilyInsolvent` when the vault no longer covers the
+/// tracked liabilities.
+fn record_shares<'a>(
+    recipient_claim: &AccountInfo<'a>,
+    mailer_account: &AccountInfo<'a>,
+    mailer_usdc: &AccountInfo<'a>,
+    fee_mint_account: Option<&AccountInfo<'a>>,
+    treasury: Option<&Treasury<'_, 'a>>,
+    recipient: Pubkey,
+    total_amount: u64,
+) -> Result<u64, ProgramError> {
+    let owner_amount = total_amount / 10; // 10% of total_amount
+    let recipient_amount = total_amount - owner_amount;
+
+    // Update owner's claimable amount, or pay it out to the treasury
+    match treasury {
+        Some(treasury) if owner_amount > 0 => {
+            forward_to_treasury(treasury, mailer_account, mailer_usdc, owner_amount)?
+        }
+        Some(_) => {}
+        None => credit_owner(mailer_account, fee_mint_account, owner_amount)?,
+    }
+    let mailer_state = track_recipient_liabilities(
+        mailer_account,
+        mailer_usdc,
+        fee_mint_account,
+        recipient_amount,
+    )?;
+
+    // Update recipient's claimable amount and refresh the timestamp to extend the 60-day window
+    let current_time = Clock::get()?.unix_timestamp;
+    let mut claim_data = recipient_claim.try_borrow_mut_data()?;
+    credit_claim_data(
+        &mut claim_data,
+        recipient_amount,
+        current_time,
+        mailer_state.paused_duration_at(current_time),
+    )?;
+    if claim_data.len() == 8 + RecipientClaim::LEN {
+        let messages_received =
+            fields::read_u64(&claim_data, RecipientClaim::MESSAGES_RECEIVED_OFFSET)?;
+        fields::write_u64(
+            &mut claim_data,
+            RecipientClaim::MESSAGES_RECEIVED_OFFSET,
+            messages_received.saturating_add(1),
+        )?;
+    }
+    let surcharge = if claim_data[RecipientClaim::NO_EXPIRY_OFFSET] != 0 {
+        let mut claim_state = RecipientClaim::from_account_data(&claim_data[8..])?;
+        let surcharge = mailer_state.settle_no_expiry_surcharge(&mut claim_state, current_time);
+        claim_state.write_account_data(&mut claim_data[8..])?;
+        surcharge
+    } else {
+        0
+    };
+    drop(claim_data);
+
+    if surcharge > 0 {
+        match fee_mint_account {
+            Some(_) => credit_owner(mailer_account, fee_mint_account, surcharge)?,
+            None => move_claim_to_owner(mailer_account, surcharge)?,
+        }
+        msg!(
+            "No-expiry surcharge debited from {}: {}",
+            recipient,
+            surcharge
+        );
+    }
+
+    msg!(
+        "Shares recorded: recipient {}, owner {}",
+        recipient_amount,
+        owner_amount
+    );
+    Ok(recipient_amount)
+}
+
+/// Credit a priority send's tip to the recipient's claim, refreshing its claim window
+/// Tips in USDC count toward the recipient liabilities the vault must cover.
+fn credit_tip<'a>(
+    recipient_claim: &AccountInfo<'a>,
+    mailer_account: &AccountInfo<'a>,
+    mailer_usdc: &AccountInfo<'a>,
+    fee_mint_account: Option<&AccountInfo<'a>>,
+    amount: u64,
+) -> ProgramResult {
+    let mailer_state =
+        track_recipient_liabilities(mailer_account, mailer_usdc, fee_mint_account, amount)?;
+
+    let current_time = Clock::get()?.unix_timestamp;
+    let mut claim_data = recipient_claim.try_borrow_mut_data()?;
+    credit_claim_data(
+        &mut claim_data,
+        amount,
+        current_time,
+        mailer_state.paused_duration_at(current_time),
+    )
+}
+
+/// Add `amount` of USDC recipient balances to the mailer's liabilities in place, returning
+/// the updated mailer state; fee mint balances are not tracked
+/// Fails with `TemporarilyInsolvent` when the vault no longer covers the liabilities.
+fn track_recipient_liabilities(
+    mailer_account: &AccountInfo,
+    mailer_usdc: &AccountInfo,
+    fee_mint_account: Option<&AccountInfo>,
+    amount: u64,
+) -> Result<MailerState, ProgramError> {
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    if fee_mint_account.is_none() {
+        let offset = fields::recipient_liabilities_offset(&mailer_data)?;
+        fields::add_u64(&mut mailer_data, offset, amount)?;
+    }
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    if fee_mint_account.is_none() {
+        assert_solvent(&mailer_state, mailer_usdc)?;
+    }
+    Ok(mailer_state)
+}
+
+/// Credit `amount` to claim account data in place, restarting its claim window at `now`
+fn credit_claim_data(data: &mut [u8], amount: u64, now: i64, paused_offset: i64) -> ProgramResult {
+    fields::add_u64(data, RecipientClaim::AMOUNT_OFFSET, amount)?;
+    fields::write_i64(data, RecipientClaim::TIMESTAMP_OFFSET, now)?;
+    fields::write_i64(data, RecipientClaim::PAUSED_OFFSET_OFFSET, paused_offset)?;
+    *data
+        .get_mut(RecipientClaim::EXTENSIONS_OFFSET)
+        .ok_or(ProgramError::InvalidAccountData)? = 0;
+    Ok(())
+}
+
+/// Treasury token account the owner share of USDC send fees is forwarded to, with the
+/// token program and mailer bump signing the transfer out of the vault
+struct Treasury<'b, 'a> {
+    account: &'b AccountInfo<'a>,
+    token_program: &'b AccountInfo<'a>,
+    mailer_bump: u8,
+}
+
+/// Treasury token account set on the mailer, which must be among `accounts`, writable;
+/// `None` while no treasury is set
+fn treasury_account<'b, 'a>(
+    mailer_state: &MailerState,
+    accounts: &'b [AccountInfo<'a>],
+) -> Result<Option<&'b AccountInfo<'a>>, ProgramError> {
+    let treasury = match mailer_state.treasury {
+        Some(treasury) => treasury,
+        None => return Ok(None),
+    };
+    accounts
+        .iter()
+        .find(|acc| acc.key == &treasury && acc.is_writable)
+        .map(Some)
+        .ok_or_else(|| MailerError::InvalidTreasury.into())
+}
+
+/// Move the owner share of a USDC fee from the mailer vault to the treasury
+fn forward_to_treasury<'a>(
+    treasury: &Treasury<'_, 'a>,
+    mailer_account: &AccountInfo<'a>,
+    mailer_usdc: &AccountInfo<'a>,
+    amount: u64,
+) -> ProgramResult {
+    invoke_signed(
+        &spl_token::instruction::transfer(
+            treasury.token_program.key,
+            mailer_usdc.key,
+            treasury.account.key,
+            mailer_account.key,
+            &[],
+            amount,
+        )?,
+        &[
+            mailer_usdc.clone(),
+            treasury.account.clone(),
+            mailer_account.clone(),
+            treasury.token_program.clone(),
+        ],
+        &[&[seeds::MAILER, &[treasury.mailer_bump]]],
+    )?;
+    msg!("Owner share {} forwarded to treasury", amount);
+    Ok(())
+}
+
+/// Fail with `TemporarilyInsolvent` when the mailer USDC vault covers less than
+/// `min_coverage_bps` of the tracked liabilities, alerting operators in the log
+fn assert_solvent(mailer_state: &MailerState, mailer_usdc: &AccountInfo) -> ProgramResult {
+    if mailer_state.min_coverage_bps == 0 {
+        return Ok(());
+    }
+
+    let vault_balance = token_balance(mailer_usdc)?;
+    if !mailer_state.is_solvent(vault_balance) {
+        msg!(
+            "SolvencyAlert {{ vault: {}, liabilities: {}, min_coverage_bps: {} }}",
+            vault_balance,
+            mailer_state.tracked_liabilities(),
+            mailer_state.min_coverage_bps
+        );
+        return Err(MailerError::TemporarilyInsolvent.into());
+    }
+    Ok(())
+}
+
+/// Propagate `TemporarilyInsolvent` from `record_shares`, which fails a send even where
+/// other fee failures only drop the fee
+fn fail_if_insolvent<T>(recorded: &Result<T, ProgramError>) -> ProgramResult {
+    match recorded {
+        Err(err) if *err == MailerError::TemporarilyInsolvent.into() => Err(err.clone()),
+        _ => Ok(()),
+    }
+}
+
+/// Move `amount` of USDC recipient balances to the owner's claimable balance
+fn move_claim_to_owner(mailer_account: &AccountInfo, amount: u64) -> ProgramResult {
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    mailer_state.move_claim_to_owner(amount)?;
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+    Ok(())
+}
+
+/// Drop paid-out or forfeited USDC recipient balances from the tracked liabilities
+fn release_recipient_liability(mailer_account: &AccountInfo, amount: u64) -> ProgramResult {
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    mailer_state.release_recipient_liability(amount);
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+    Ok(())
+}
+
+/// Record an accrued share and memo in the (recipient, sender) claim history entry
+fn record_claim_history<'a>(
+    program_id: &Pubkey,
+    sender: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    recipient: &Pubkey,
+    amount: u64,
+    memo: String,
+    accounts: &[AccountInfo<'a>],
+) -> ProgramResult {
+    let (history_pda, history_bump) = pda::claim_history(program_id, recipient, sender.key);
+
+    let history_account = accounts
+        .iter()
+        .find(|acc| acc.key == &history_pda)
+        .ok_or(MailerError::InvalidPDA)?;
+
+    // Create history entry if needed
+    if history_account.lamports() == 0 {
+        let rent = Rent::get()?;
+        let space = 8 + ClaimHistoryEntry::LEN;
+        let lamports = rent.minimum_balance(space);
+
+        invoke_signed(
+            &system_instruction::create_account(
+                sender.key,
+                history_account.key,
+                lamports,
+                space as u64,
+                program_id,
+            ),
+            &[
+                sender.clone(),
+                history_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                seeds::HISTORY,
+                &[PDA_VERSION],
+                recipient.as_ref(),
+                sender.key.as_ref(),
+                &[history_bump],
+            ]],
+        )?;
+
+        let mut history_data = history_account.try_borrow_mut_data()?;
+        history_data[0..8].copy_from_slice(&discriminator::account("ClaimHistoryEntry"));
+
+        let entry = ClaimHistoryEntry {
+            recipient: *recipient,
+            sender: *sender.key,
+            total_amount: 0,
+            last_amount: 0,
+            last_memo: String::new(),
+            last_timestamp: 0,
+            bump: history_bump,
+        };
+        entry.serialize(&mut &mut history_data[8..])?;
+    }
+
+    let mut history_data = history_account.try_borrow_mut_data()?;
+    let mut entry: ClaimHistoryEntry = BorshDeserialize::deserialize(&mut &history_data[8..])?;
+
+    entry.total_amount = entry
+        .total_amount
+        .checked_add(amount)
+        .ok_or(MailerError::MathOverflow)?;
+    entry.last_amount = amount;
+    entry.last_memo = memo;
+    entry.last_timestamp = Clock::get()?.unix_timestamp;
+    entry.serialize(&mut &mut history_data[8..])?;
+
+    msg!(
+        "Claim history for {} from {}: amount {}, memo {}",
+        recipient,
+        sender.key,
+        amount,
+        entry.last_memo
+    );
+    Ok(())
+}
+
+/// Calculate the effective fee for an account based on custom discount
+/// The larger of the account's FeeDiscount and its volume discount applies.
+/// Optimized with early returns for common cases (no discount, full discount)
+fn calculate_fee_with_discount(
+    program_id: &Pubkey,
+    account: &Pubkey,
+    accounts: &[AccountInfo],
+    base_fee: u64,
+) -> Result<u64, ProgramError> {
+    // Discount accounts hold their subject and bump, so a supplied one is recognized with a
+    // single create_program_address instead of searching for the discount PDA on every send
+    let discount_account = accounts.iter().find(|acc| {
+        acc.owner == program_id
+            && match acc.try_borrow_data() {
+                Ok(data) => {
+                    data.len() >= 8 + FeeDiscount::LEN
+                        && data[8..40] == account.as_ref()[..]
+                        && pda::fee_discount_with_bump(
+                            program_id,
+                            account,
+                            data[FeeDiscount::BUMP_OFFSET],
+                        )
+                        .as_ref()
+                            == Some(acc.key)
+                }
+                Err(_) => false,
+            }
+    });
+
+    // No discount account or uninitialized - no manual discount (default behavior)
+    let mut discount = 0;
+    if let Some(discount_acc) = discount_account {
+        // Account exists and has lamports - load the discount
+        if discount_acc.lamports() > 0 {
+            let discount_data = discount_acc.try_borrow_data()?;
+            if discount_data.len() >= 8 + FeeDiscount::LEN {
+                let fee_discount: FeeDiscount =
+                    BorshDeserialize::deserialize(&mut &discount_data[8..])?;
+                discount = fee_discount.discount;
+            }
+        }
+    }
+    discount = discount.max(volume_discount(program_id, account, accounts)?);
+
+    // Early return for no discount (most common case - saves computation)
+    if discount == 0 {
+        return Ok(base_fee);
+    }
+
+    // Early return for full discount (free)
+    if discount == 100 {
+        return Ok(0);
+    }
+
+    // Apply discount: fee = base_fee * (100 - discount) / 100
+    // Examples: discount=50 → 50% fee, discount=25 → 75% fee
+    let effective_fee = base_fee as u128 * (100 - discount as u128) / 100;
+    u64::try_from(effective_fee).map_err(|_| MailerError::MathOverflow.into())
+}
+
+/// The owner fee of a standard-mode send: 10% of `fee`, computed in u128 so it cannot
+/// overflow for any fee the owner configures
+fn standard_fee(fee: u64) -> Result<u64, ProgramError> {
+    u64::try_from(fee as u128 * 10 / 100).map_err(|_| MailerError::MathOverflow.into())
+}
+
+/// Discount schedule passed among `accounts`, if any
+/// The schedule is recognized by its size, discriminator and stored bump, so sends without it
+/// pay no PDA derivation.
+fn load_discount_schedule(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> Result<Option<DiscountSchedule>, ProgramError> {
+    let schedule_account = accounts.iter().find(|acc| {
+        acc.owner == program_id
+            && acc.data_len() == 8 + DiscountSchedule::LEN
+            && match acc.try_borrow_data() {
+                Ok(data) => {
+                    data[..8] == discriminator::account("DiscountSchedule")
+                        && pda::discount_schedule_with_bump(
+                            program_id,
+                            data[DiscountSchedule::BUMP_OFFSET],
+                        )
+                        .as_ref()
+                            == Some(acc.key)
+                }
+                Err(_) => false,
+            }
+    });
+    match schedule_account {
+        Some(acc) => Ok(Some(BorshDeserialize::deserialize(
+            &mut &acc.try_borrow_data()?[8..],
+        )?)),
+        None => Ok(None),
+    }
+}
+
+/// Volume discount `account` has reached under the discount schedule, 0 unless the schedule
+/// is passed among `accounts`; without its SenderStats the account has paid nothing yet
+fn volume_discount(
+    program_id: &Pubkey,
+    account: &Pubkey,
+    accounts: &[AccountInfo],
+) -> Result<u8, ProgramError> {
+    let schedule = match load_discount_schedule(program_id, accounts)? {
+        Some(schedule) => schedule,
+        None => return Ok(0),
+    };
+    let (stats_pda, _) = pda::sender_stats(program_id, account);
+    let fees_paid = match accounts.iter().find(|acc| acc.key == &stats_pda) {
+        Some(acc) if acc.owner == program_id && acc.data_len() >= 8 + SenderStats::LEN => {
+            let stats: SenderStats =
+                BorshDeserialize::deserialize(&mut &acc.try_borrow_data()?[8..])?;
+            stats.fees_paid
+        }
+        _ => 0,
+    };
+    Ok(schedule.discount(fees_paid))
+}
+
+/// Add `amount`, the owner's share of a USDC fee, to the fees `sender` has paid, when the
+/// discount schedule and the sender's SenderStats account are passed among `accounts`
+/// The stats account is created on first use, with `sender` funding the rent through the
+/// system program.
+fn record_fees_paid<'a>(
+    program_id: &Pubkey,
+    sender: &AccountInfo<'a>,
+    accounts: &[AccountInfo<'a>],
+    amount: u64,
+) -> ProgramResult {
+    if amount == 0 || load_discount_schedule(program_id, accounts)?.is_none() {
+        return Ok(());
+    }
+    let (stats_pda, stats_bump) = pda::sender_stats(program_id, sender.key);
+    let stats_account = match accounts.iter().find(|acc| acc.key == &stats_pda) {
+        Some(acc) => acc,
+        None => return Ok(()),
+    };
+
+    if stats_account.lamports() == 0 {
+        let system_program = accounts
+            .iter()
+            .find(|acc| acc.key == &solana_program::system_program::id())
+            .ok_or(MailerError::InvalidSystemProgram)?;
+        let space = 8 + SenderStats::LEN;
+
+        invoke_signed(
+            &system_instruction::create_account(
+                sender.key,
+                stats_account.key,
+                Rent::get()?.minimum_balance(space),
+                space as u64,
+                program_id,
+            ),
+            &[
+                sender.clone(),
+                stats_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                seeds::SENDER_STATS,
+                &[PDA_VERSION],
+                sender.key.as_ref(),
+                &[stats_bump],
+            ]],
+        )?;
+
+        let mut stats_data = stats_account.try_borrow_mut_data()?;
+        stats_data[0..8].copy_from_slice(&discriminator::account("SenderStats"));
+        SenderStats {
+            sender: *sender.key,
+            fees_paid: 0,
+            bump: stats_bump,
+        }
+        .serialize(&mut &mut stats_data[8..])?;
+    } else if stats_account.owner != program_id {
+        return Err(MailerError::InvalidAccountOwner.into());
+    }
+
+    let mut stats_data = stats_account.try_borrow_mut_data()?;
+    let mut stats: SenderStats = BorshDeserialize::deserialize(&mut &stats_data[8..])?;
+    stats.fees_paid = stats
+        .fees_paid
+        .checked_add(amount)
+        .ok_or(MailerError::MathOverflow)?;
+    stats.serialize(&mut &mut stats_data[8..])?;
+
+    msg!("Fees paid by {}: {}", sender.key, stats.fees_paid);
+    Ok(())
+}
+
+/// Apply the recipient's inbox policy to a send
+/// Returns the fee to charge, or RecipientBlocked when a blocklist rejects the sender and
+/// SenderNotAllowed when an allowlist does.
+fn apply_inbox_policy(
+    program_id: &Pubkey,
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    accounts: &[AccountInfo],
+    fee: u64,
+) -> Result<u64, ProgramError> {
+    let policy = match inbox_policy(program_id, recipient, accounts)? {
+        Some(policy) => policy,
+        None => return Ok(fee),
+    };
+
+    if policy.allows(sender) {
+        return Ok(fee);
+    }
+
+    if policy.penalty_multiplier == 0 {
+        msg!(
+            "Mail from {} to {} rejected by inbox policy",
+            sender,
+            recipient
+        );
+        return Err(match policy.mode {
+            InboxMode::Blocklist => MailerError::RecipientBlocked,
+            _ => MailerError::SenderNotAllowed,
+        }
+        .into());
+    }
+
+    let penalized_fee = fee
+        .checked_mul(policy.penalty_multiplier as u64)
+        .ok_or(MailerError::MathOverflow)?;
+    msg!(
+        "Inbox policy surcharge for {} to {}: fee {} -> {}",
+        sender,
+        recipient,
+        fee,
+        penalized_fee
+    );
+    Ok(penalized_fee)
+}
+
+/// Reject a priority send paying `paid` to a recipient whose inbox policy sets a higher
+/// minimum fee; the policy account is required as for [`apply_inbox_policy`]
+fn assert_recipient_min_fee(
+    program_id: &Pubkey,
+    recipient: &Pubkey,
+    accounts: &[AccountInfo],
+    paid: u64,
+) -> ProgramResult {
+    match inbox_policy(program_id, recipient, accounts)? {
+        Some(policy) if paid < policy.min_fee => {
+            msg!(
+                "Priority send to {} pays {}, below its minimum fee {}",
+                recipient,
+                paid,
+                policy.min_fee
+            );
+            Err(MailerError::RecipientFeeTooLow.into())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The recipient's inbox policy, `None` while the recipient has not created one
+/// The policy PDA must be among the supplied accounts even when it has not been created, so
+/// a sender cannot leave it out; without it the send fails with `MissingInboxPolicyAccount`.
+fn inbox_policy(
+    program_id: &Pubkey,
+    recipient: &Pubkey,
+    accounts: &[AccountInfo],
+) -> Result<Option<InboxPolicy>, ProgramError> {
+    let (policy_account, _) = trailing_pda_account(
+        program_id,
+        accounts,
+        "InboxPolicy",
+        InboxPolicy::bump_offset,
+        |bump| pda::inbox_policy_with_bump(program_id, recipient, bump),
+        || pda::inbox_policy(program_id, recipient),
+    )
+    .ok_or(MailerError::MissingInboxPolicyAccount)?;
+
+    if policy_account.owner != program_id
+        || policy_account.lamports() == 0
+        || policy_account.data_len() < 8 + InboxPolicy::V1_LEN
+    {
+        return Ok(None);
+    }
+    InboxPolicy::from_account_data(&policy_account.try_borrow_data()?[8..]).map(Some)
+}
+
+/// Pause the contract and distribute owner claimable funds
+fn process_pause(_program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let owner_usdc = next_account_info(account_iter)?;
+    let mailer_usdc = next_account_info(account_iter)?;
+    let token_program = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (mailer_pda, _) = assert_mailer_account(_program_id, accounts, mailer_account)?;
+
+    // Load and update mailer state
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    // Verify owner
+    assert_owner_authority(_program_id, &mailer_state, owner, accounts)?;
+
+    // Check if already paused
+    if mailer_state.paused {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    // Set paused state
+    let pause_flags = mailer_state.pause_flags;
+    mailer_state.set_pause(true, pause_flags, Clock::get()?.unix_timestamp);
+
+    assert_token_program(token_program)?;
+
+    // Distribute owner claimable funds if any
+    if mailer_state.owner_claimable > 0 {
+        let amount = mailer_state.owner_claimable;
+        mailer_state.owner_claimable = 0;
+
+        assert_token_account(
+            accounts,
+            "owner_usdc",
+            owner_usdc,
+            &mailer_state.owner,
+            &mailer_state.usdc_mint,
+        )?;
+        assert_token_account(
+            accounts,
+            "mailer_usdc",
+            mailer_usdc,
+            &mailer_pda,
+            &mailer_state.usdc_mint,
+        )?;
+
+        // Save updated state BEFORE external call (CEI pattern)
+        mailer_state.serialize(&mut &mut mailer_data[8..])?;
+        drop(mailer_data); // Release borrow before external call
+
+        // Transfer USDC from mailer to owner
+        invoke_signed(
+            &spl_token::instruction::transfer(
+                token_program.key,
+                mailer_usdc.key,
+                owner_usdc.key,
+                &mailer_pda,
+                &[],
+                amount,
+            )?,
+            &[
+                mailer_usdc.clone(),
+                owner_usdc.clone(),
+                mailer_account.clone(),
+                token_program.clone(),
+            ],
+            &[&[seeds::MAILER, &[mailer_state.bump]]],
+        )?;
+
+        msg!("Distributed owner funds during pause: {}", amount);
+    } else {
+        // Save updated state even if no distribution
+        mailer_state.serialize(&mut &mut mailer_data[8..])?;
+    }
+
+    msg!("Contract paused by owner: {}", owner.key);
+    Ok(())
+}
+
+/// Unpause the contract
+fn process_unpause(_program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_mailer_account(_program_id, accounts, mailer_account)?;
+
+    // Load and update mailer state
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    // Verify owner
+    assert_owner_authority(_program_id, &mailer_state, owner, accounts)?;
+
+    // Check if not paused
+    if !mailer_state.paused {
+        return Err(MailerError::ContractNotPaused.into());
+    }
+
+    // Set unpaused state, crediting the pause to outstanding claim windows
+    let pause_flags = mailer_state.pause_flags;
+    mailer_state.set_pause(false, pause_flags, Clock::get()?.unix_timestamp);
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+
+    msg!("Contract unpaused by owner: {}", owner.key);
+    Ok(())
+}
+
+/// Distribute claimable funds when contract is paused
+fn process_distribute_claimable_funds(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    recipient: Pubkey,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let _caller = next_account_info(account_iter)?; // Anyone can call
+    let mailer_account = next_account_info(account_iter)?;
+    let recipient_claim_account = next_account_info(account_iter)?;
+    let recipient_usdc = next_account_info(account_iter)?;
+    let mailer_usdc = next_account_info(account_iter)?;
+    let token_program = next_account_info(account_iter)?;
+
+    let (mailer_pda, _) = assert_mailer_account(_program_id, accounts, mailer_account)?;
+
+    // Load mailer state to check if paused
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    // Check if contract is paused
+    if !mailer_state.paused {
+        return Err(MailerError::ContractNotPaused.into());
+    }
+
+    // Verify recipient claim PDA
+    let (claim_pda, _) = pda::recipient_claim(_program_id, &recipient);
+    if recipient_claim_account.key != &claim_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    assert_token_program(token_program)?;
+
+    // Load and update recipient claim
+    let mut claim_data = recipient_claim_account.try_borrow_mut_data()?;
+    let mut claim_state = RecipientClaim::from_account_data(&claim_data[8..])?;
+
+    if claim_state.amount == 0 {
+        return Err(MailerError::NoClaimableAmount.into());
+    }
+
+    let amount = claim_state.amount;
+    claim_state.amount = 0;
+    claim_state.timestamp = 0;
+    claim_state.total_claimed = claim_state.total_claimed.saturating_add(amount);
+
+    assert_payout_account(
+        _program_id,
+        &recipient,
+        recipient_usdc,
+        &mailer_state.usdc_mint,
+        accounts,
+    )?;
+    assert_token_account(
+        accounts,
+        "mailer_usdc",
+        mailer_usdc,
+        &mailer_pda,
+        &mailer_state.usdc_mint,
+    )?;
+
+    // Save updated state BEFORE external call (CEI pattern)
+    claim_state.write_account_data(&mut claim_data[8..])?;
+    drop(claim_data); // Release borrow before external call
+
+    // Transfer USDC from mailer to recipient
+    invoke_signed(
+        &spl_token::instruction::transfer(
+            token_program.key,
+            mailer_usdc.key,
+            recipient_usdc.key,
+            &mailer_pda,
+            &[],
+            amount,
+        )?,
+        &[
+            mailer_usdc.clone(),
+            recipient_usdc.clone(),
+            mailer_account.clone(),
+            token_program.clone(),
+        ],
+        &[&[seeds::MAILER, &[mailer_state.bump]]],
+    )?;
+
+    release_recipient_liability(mailer_account, amount)?;
+    msg!("Distributed claimable funds to {}: {}", recipient, amount);
+    Ok(())
+}
+
+/// Claim expired shares and move them under owner control (owner only)
+fn process_claim_expired_shares(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    recipient: Pubkey,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let recipient_claim_account = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (_mailer_pda, _) = assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    // Load and verify mailer state
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    if mailer_state.is_paused(PAUSE_CLAIM) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+
+    // Verify recipient claim PDA
+    let (claim_pda, _) = pda::recipient_claim(program_id, &recipient);
+    if recipient_claim_account.key != &claim_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    // Load and validate claim state
+    let mut claim_data = recipient_claim_account.try_borrow_mut_data()?;
+    let mut claim_state = RecipientClaim::from_account_data(&claim_data[8..])?;
+
+    if claim_state.recipient != recipient {
+        return Err(MailerError::InvalidRecipient.into());
+    }
+    if claim_state.amount == 0 {
+        return Err(MailerError::NoClaimableAmount.into());
+    }
+
+    let current_time = Clock::get()?.unix_timestamp;
+    if current_time <= mailer_state.claim_expiry(&claim_state, current_time) {
+        return Err(MailerError::ClaimPeriodNotExpired.into());
+    }
+    if current_time
+        <= owner_sweep_time(
+            program_id,
+            &mailer_state,
+            &claim_state,
+            current_time,
+            accounts,
+        )?
+    {
+        return Err(MailerError::BeneficiaryGracePeriodActive.into());
+    }
+
+    let amount = claim_state.amount;
+    claim_state.amount = 0;
+    claim_state.timestamp = 0;
+    claim_state.write_account_data(&mut claim_data[8..])?;
+    drop(claim_data);
+
+    mailer_state.move_claim_to_owner(amount)?;
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+
+    msg!("Expired shares claimed for {}: {}", recipient, amount);
+    Ok(())
+}
+
+/// Time after which the owner may sweep a claim: its expiry, plus the grace period of a
+/// registered beneficiary
+/// The recipient's claim beneficiary PDA must be among `accounts`, registered or not, so a
+/// sweep cannot pass over the beneficiary by leaving it out.
+fn owner_sweep_time(
+    program_id: &Pubkey,
+    mailer_state: &MailerState,
+    claim_state: &RecipientClaim,
+    now: i64,
+    accounts: &[AccountInfo],
+) -> Result<i64, ProgramError> {
+    let (beneficiary_pda, _) = pda::claim_beneficiary(program_id, &claim_state.recipient);
+    let beneficiary_account = accounts
+        .iter()
+        .find(|acc| acc.key == &beneficiary_pda)
+        .ok_or(MailerError::InvalidPDA)?;
+
+    let expiry = mailer_state.claim_expiry(claim_state, now);
+    if beneficiary_account.owner == program_id && beneficiary_account.lamports() > 0 {
+        Ok(expiry.saturating_add(BENEFICIARY_GRACE_PERIOD))
+    } else {
+        Ok(expiry)
+    }
+}
+
+/// Claim the expired shares of a batch of recipients (owner only)
+/// Entries that are not expired canonical claims are skipped rather than failing the batch.
+fn process_claim_expired_shares_batch(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    if mailer_state.is_paused(PAUSE_CLAIM) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+
+    let claim_accounts = account_iter.as_slice();
+    if claim_accounts.len() > 2 * MAX_EXPIRED_BATCH {
+        return Err(MailerError::BatchTooLarge.into());
+    }
+
+    let current_time = Clock::get()?.unix_timestamp;
+    let mut claimed: u64 = 0;
+    let mut total: u64 = 0;
+
+    for claim_account in claim_accounts {
+        if claim_account.owner != program_id
+            || !claim_account.is_writable
+            || !RecipientClaim::is_account_len(claim_account.data_len())
+        {
+            continue;
+        }
+
+        let mut claim_data = claim_account.try_borrow_mut_data()?;
+        if claim_data[..8] != discriminator::account("RecipientClaim") {
+            continue;
+        }
+        let mut claim_state = RecipientClaim::from_account_data(&claim_data[8..])?;
+
+        // Only the canonical default-mint claim of its recipient is eligible
+        let (claim_pda, _) = pda::recipient_claim(program_id, &claim_state.recipient);
+        if claim_account.key != &claim_pda
+            || claim_state.amount == 0
+            || current_time <= mailer_state.claim_expiry(&claim_state, current_time)
+        {
+            continue;
+        }
+
+        // Claims without their beneficiary account, or still in its grace period, wait
+        match owner_sweep_time(
+            program_id,
+            &mailer_state,
+            &claim_state,
+            current_time,
+            claim_accounts,
+        ) {
+            Ok(sweep_time) if current_time > sweep_time => {}
+            _ => continue,
+        }
+
+        let amount = claim_state.amount;
+        claim_state.amount = 0;
+        claim_state.timestamp = 0;
+        claim_state.write_account_data(&mut claim_data[8..])?;
+
+        total = total.checked_add(amount).ok_or(MailerError::MathOverflow)?;
+        claimed += 1;
+
+        msg!(
+            "Expired shares claimed for {}: {}",
+            claim_state.recipient,
+            amount
+        );
+    }
+
+    mailer_state.move_claim_to_owner(total)?;
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+
+    msg!(
+        "Expired shares batch: claimed {} of {} accounts, {} in total",
+        claimed,
+        claim_accounts.len(),
+        total
+    );
+    Ok(())
+}
+
+/// Emergency unpause without fund distribution (owner only)
+fn process_emergency_unpause(_program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_mailer_account(_program_id, accounts, mailer_account)?;
+
+    // Load and update mailer state
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    // Verify owner
+    assert_owner_authority(_program_id, &mailer_state, owner, accounts)?;
+
+    // Check if not paused
+    if !mailer_state.paused {
+        return Err(MailerError::ContractNotPaused.into());
+    }
+
+    // Set unpaused state without fund distribution
+    let pause_flags = mailer_state.pause_flags;
+    mailer_state.set_pause(false, pause_flags, Clock::get()?.unix_timestamp);
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+
+    msg!(
+        "Contract emergency unpaused by owner: {} - funds can be claimed manually",
+        owner.key
+    );
+    Ok(())
+}
+
+/// Set the subsystem pause mask (owner only)
+fn process_set_pause_flags(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    flags: u8,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+
+    if flags & !(PAUSE_SEND | PAUSE_DELEGATE | PAUSE_CLAIM | PAUSE_ADMIN) != 0 {
+        return Err(MailerError::InvalidPauseFlags.into());
+    }
+
+    let paused = mailer_state.paused;
+    mailer_state.set_pause(paused, flags, Clock::get()?.unix_timestamp);
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+
+    msg!("Pause flags set to {:#06b} by owner: {}", flags, owner.key);
+    Ok(())
+}
+
+/// Register or clear the KYC verifier program required for priority sends (owner only)
+fn process_set_kyc_verifier(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    verifier: Option<Pubkey>,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+
+    mailer_state.kyc_verifier = verifier;
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+
+    msg!("KYC verifier set to {:?}", verifier);
+    Ok(())
+}
+
+/// Set the delegation fee refund window (owner only)
+fn process_set_delegation_refund_window(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    window: i64,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+
+    if !(0..=MAX_DELEGATION_REFUND_WINDOW).contains(&window) {
+        return Err(MailerError::InvalidDelegationRefundWindow.into());
+    }
+
+    mailer_state.delegation_refund_window = window;
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+
+    msg!("Delegation refund window set to {}", window);
+    Ok(())
+}
+
+/// Set the claim cap (owner only)
+fn process_set_claim_cap(program_id: &Pubkey, accounts: &[AccountInfo], cap: u64) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+
+    mailer_state.claim_cap = cap;
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+
+    msg!("Claim cap set to {}", cap);
+    Ok(())
+}
+
+/// Set the vault solvency threshold (owner only)
+fn process_set_solvency_threshold(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    min_coverage_bps: u16,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+
+    mailer_state.min_coverage_bps = min_coverage_bps;
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+
+    msg!("Solvency threshold set to {} bps", min_coverage_bps);
+    Ok(())
+}
+
+/// Check the mailer USDC vault against its liabilities and a batch of recipient claims
+fn process_verify_solvency(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let mailer_account = next_account_info(account_iter)?;
+    let mailer_usdc = next_account_info(account_iter)?;
+
+    let (mailer_pda, _) = assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    assert_token_account(
+        accounts,
+        "mailer_usdc",
+        mailer_usdc,
+        &mailer_pda,
+        &mailer_state.usdc_mint,
+    )?;
+
+    let claim_accounts = account_iter.as_slice();
+    if claim_accounts.len() > MAX_SOLVENCY_BATCH {
+        return Err(MailerError::BatchTooLarge.into());
+    }
+
+    // Ascending order keeps any claim from being counted twice
+    let mut claims_total: u64 = 0;
+    let mut previous: Option<&Pubkey> = None;
+    for claim_account in claim_accounts {
+        if previous.is_some_and(|previous| claim_account.key.to_bytes() <= previous.to_bytes()) {
+            return Err(MailerError::UnsortedClaimBatch.into());
+        }
+        previous = Some(claim_account.key);
+
+        if claim_account.owner != program_id
+            || !RecipientClaim::is_account_len(claim_account.data_len())
+        {
+            return Err(MailerError::InvalidPDA.into());
+        }
+        let claim_data = claim_account.try_borrow_data()?;
+        let claim = RecipientClaim::from_account_data(&claim_data[8..])?;
+        drop(claim_data);
+
+        // Only claims in the default mint are paid from this vault
+        if pda::recipient_claim_with_bump(program_id, &claim.recipient, claim.bump)
+            != Some(*claim_account.key)
+        {
+            return Err(MailerError::InvalidPDA.into());
+        }
+        claims_total = claims_total
+            .checked_add(claim.amount)
+            .ok_or(MailerError::MathOverflow)?;
+    }
+
+    let vault_balance = token_balance(mailer_usdc)?;
+    let liabilities = mailer_state.owner_claimable as i128
+        + mailer_state.credit_liabilities as i128
+        + mailer_state.recipient_liabilities.max(claims_total) as i128;
+    let report = SolvencyReport {
+        vault_balance,
+        owner_claimable: mailer_state.owner_claimable,
+        recipient_liabilities: mailer_state.recipient_liabilities,
+        credit_liabilities: mailer_state.credit_liabilities,
+        claims_checked: claim_accounts.len() as u64,
+        claims_total,
+        delta: vault_balance as i128 - liabilities,
+    };
+
+    msg!(
+        "SolvencyCheck {{ vault: {}, owner_claimable: {}, recipient_liabilities: {}, credit_liabilities: {}, claims: {}, claims_total: {}, delta: {} }}",
+        report.vault_balance,
+        report.owner_claimable,
+        report.recipient_liabilities,
+        report.credit_liabilities,
+        report.claims_checked,
+        report.claims_total,
+        report.delta
+    );
+    if report.delta < 0 {
+        return Err(MailerError::TemporarilyInsolvent.into());
+    }
+
+    set_return_data(&borsh::to_vec(&report)?);
+    Ok(())
+}
+
+/// Fund a merkle airdrop of claimable USDC shares (owner only)
+fn process_set_claim_merkle_root(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    distribution_id: u64,
+    merkle_root: [u8; 32],
+    leaf_count: u64,
+    total: u64,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let distribution_account = next_account_info(account_iter)?;
+    let owner_usdc = next_account_info(account_iter)?;
+    let mailer_usdc = next_account_info(account_iter)?;
+    let token_program = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_token_program(token_program)?;
+    assert_system_program(system_program)?;
+    let (mailer_pda, _) = assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+
+    if mailer_state.is_paused(PAUSE_ADMIN) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    if leaf_count == 0 || leaf_count > MAX_DISTRIBUTION_LEAVES as u64 || total == 0 {
+        return Err(MailerError::InvalidDistribution.into());
+    }
+
+    let (distribution_pda, distribution_bump) =
+        pda::claim_distribution(program_id, distribution_id);
+    if distribution_account.key != &distribution_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+    if distribution_account.lamports() > 0 {
+        return Err(MailerError::AlreadyInitialized.into());
+    }
+
+    assert_token_account(
+        accounts,
+        "mailer_usdc",
+        mailer_usdc,
+        &mailer_pda,
+        &mailer_state.usdc_mint,
+    )?;
+
+    let space = 8 + ClaimDistribution::LEN;
+    invoke_signed(
+        &system_instruction::create_account(
+            owner.key,
+            distribution_account.key,
+            Rent::get()?.minimum_balance(space),
+            space as u64,
+            program_id,
+        ),
+        &[
+            owner.clone(),
+            distribution_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            seeds::DISTRIBUTION,
+            &[PDA_VERSION],
+            &distribution_id.to_le_bytes(),
+            &[distribution_bump],
+        ]],
+    )?;
+
+    // The airdrop is held in the vault until its leaves are credited to recipient claims
+    let received = transfer_to_vault(
+        token_program,
+        owner_usdc,
+        mailer_usdc,
+        owner,
+        accounts,
+        total,
+    )?;
+
+    let mut distribution_data = distribution_account.try_borrow_mut_data()?;
+    distribution_data[0..8].copy_from_slice(&discriminator::account("ClaimDistribution"));
+    ClaimDistribution {
+        distribution_id,
+        merkle_root,
+        leaf_count,
+        total: received,
+        remaining: received,
+        bump: distribution_bump,
+        claimed: vec![0; leaf_count.div_ceil(8) as usize],
+    }
+    .serialize(&mut &mut distribution_data[8..])?;
+
+    msg!(
+        "Claim distribution {} funded with {} for {} leaves",
+        distribution_id,
+        received,
+        leaf_count
+    );
+    Ok(())
+}
+
+/// Credit a recipient's claim with their leaf of a merkle airdrop
+fn process_claim_from_merkle(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    distribution_id: u64,
+    index: u64,
+    amount: u64,
+    proof: Vec<[u8; 32]>,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let recipient = next_account_info(account_iter)?;
+    let distribution_account = next_account_info(account_iter)?;
+    let recipient_claim = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !recipient.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_system_program(system_program)?;
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    if mailer_state.is_paused(PAUSE_CLAIM) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    assert_not_sanctioned(program_id, &mailer_state, recipient.key, accounts)?;
+
+    let (distribution_pda, _) = pda::claim_distribution(program_id, distribution_id);
+    if distribution_account.key != &distribution_pda || distribution_account.owner != program_id {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    let mut distribution_data = distribution_account.try_borrow_mut_data()?;
+    let mut distribution: ClaimDistribution =
+        BorshDeserialize::deserialize(&mut &distribution_data[8..])?;
+
+    let leaf = merkle::leaf(index, recipient.key, amount);
+    if index >= distribution.leaf_count
+        || proof.len() > merkle::MAX_PROOF_LEN
+        || !merkle::verify(&distribution.merkle_root, &leaf, &proof)
+    {
+        return Err(MailerError::InvalidMerkleProof.into());
+    }
+    if distribution.is_claimed(index) {
+        return Err(MailerError::DistributionLeafClaimed.into());
+    }
+    if amount == 0 || amount > distribution.remaining {
+        return Err(MailerError::InvalidClaimAmount.into());
+    }
+
+    distribution.claimed[(index / 8) as usize] |= 1 << (index % 8);
+    distribution.remaining -= amount;
+    distribution.serialize(&mut &mut distribution_data[8..])?;
+    drop(distribution_data);
+
+    let (claim_pda, claim_bump) = pda::recipient_claim(program_id, recipient.key);
+    if recipient_claim.key != &claim_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+    create_recipient_claim(
+        program_id,
+        recipient,
+        recipient_claim,
+        system_program,
+        mailer_account,
+        *recipient.key,
+        claim_bump,
+        &[
+            seeds::CLAIM,
+            &[PDA_VERSION],
+            recipient.key.as_ref(),
+            &[claim_bump],
+        ],
+    )?;
+
+    // Credit the claim as a received share, refreshing its claim window
+    let current_time = Clock::get()?.unix_timestamp;
+    let mut claim_data = recipient_claim.try_borrow_mut_data()?;
+    let mut claim = RecipientClaim::from_account_data(&claim_data[8..])?;
+    claim.amount = claim
+        .amount
+        .checked_add(amount)
+        .ok_or(MailerError::MathOverflow)?;
+    claim.timestamp = current_time;
+    claim.paused_offset = mailer_state.paused_duration_at(current_time);
+    claim.extensions = 0;
+    claim.write_account_data(&mut claim_data[8..])?;
+    drop(claim_data);
+
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    mailer_state.recipient_liabilities = mailer_state
+        .recipient_liabilities
+        .checked_add(amount)
+        .ok_or(MailerError::MathOverflow)?;
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+
+    msg!(
+        "Recipient {} credited {} from claim distribution {} (leaf {})",
+        recipient.key,
+        amount,
+        distribution_id,
+        index
+    );
+    Ok(())
+}
+
+/// Send a priority message with its fee held in escrow until the recipient accepts it
+fn process_send_escrowed(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    to: Pubkey,
+    subject: String,
+    _body: String,
+    nonce: u64,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let sender = next_account_info(account_iter)?;
+    let escrow_account = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let sender_usdc = next_account_info(account_iter)?;
+    let mailer_usdc = next_account_info(account_iter)?;
+    let token_program = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !sender.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_token_program(token_program)?;
+    assert_system_program(system_program)?;
+    let (mailer_pda, _) = assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    if mailer_state.is_paused(PAUSE_SEND) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    assert_payload_len(&subject, mailer_state.payload_limits.max_subject_len)?;
+    assert_payload_len(&_body, mailer_state.payload_limits.max_body_len)?;
+    assert_not_sanctioned(program_id, &mailer_state, sender.key, accounts)?;
+    assert_not_sanctioned(program_id, &mailer_state, &to, accounts)?;
+
+    assert_token_account(
+        accounts,
+        "sender_usdc",
+        sender_usdc,
+        sender.key,
+        &mailer_state.usdc_mint,
+    )?;
+    assert_token_account(
+        accounts,
+        "mailer_usdc",
+        mailer_usdc,
+        &mailer_pda,
+        &mailer_state.usdc_mint,
+    )?;
+
+    let (escrow_pda, escrow_bump) = pda::escrow(program_id, sender.key, nonce);
+    if escrow_account.key != &escrow_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+    if escrow_account.lamports() > 0 {
+        return Err(MailerError::AlreadyInitialized.into());
+    }
+
+    // Escrowed sends are priced as priority sends
+    let current_time = Clock::get()?.unix_timestamp;
+    let fee = if mailer_state.fee_paused {
+        0
+    } else {
+        let current_fee = mailer_state.effective_send_fee(current_time);
+        let current_fee = mailer_state.payload_fee(current_fee, subject.len() + _body.len())?;
+        calculate_fee_with_discount(program_id, sender.key, accounts, current_fee)?
+    };
+    let fee = apply_inbox_policy(program_id, sender.key, &to, accounts, fee)?;
+    assert_recipient_min_fee(program_id, &to, accounts, fee)?;
+
+    let space = 8 + Escrow::LEN;
+    invoke_signed(
+        &system_instruction::create_account(
+            sender.key,
+            escrow_account.key,
+            Rent::get()?.minimum_balance(space),
+            space as u64,
+            program_id,
+        ),
+        &[
+            sender.clone(),
+            escrow_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            seeds::ESCROW,
+            &[PDA_VERSION],
+            sender.key.as_ref(),
+            &nonce.to_le_bytes(),
+            &[escrow_bump],
+        ]],
+    )?;
+
+    let received = if fee > 0 {
+        transfer_to_vault(
+            token_program,
+            sender_usdc,
+            mailer_usdc,
+            sender,
+            accounts,
+            fee,
+        )?
+    } else {
+        0
+    };
+
+    let expires_at = current_time.saturating_add(ESCROW_PERIOD);
+    let mut escrow_data = escrow_account.try_borrow_mut_data()?;
+    escrow_data[0..8].copy_from_slice(&discriminator::account("Escrow"));
+    Escrow {
+        sender: *sender.key,
+        recipient: to,
+        nonce,
+        amount: received,
+        created_at: current_time,
+        expires_at,
+        bump: escrow_bump,
+    }
+    .serialize(&mut &mut escrow_data[8..])?;
+    drop(escrow_data);
+
+    let message_nonce = next_message_nonce(program_id, sender.key, sender, accounts)?;
+    let payload_hash = hash_message_body(&subject, &_body);
+    let message_id = derive_message_id(
+        sender.key,
+        to.as_ref(),
+        &payload_hash,
+        Clock::get()?.slot,
+        message_nonce,
+    );
+
+    msg!(
+        "Escrowed mail from {} to {}: {} (escrow {}, amount {}, expires {}{})",
+        sender.key,
+        to,
+        subject,
+        nonce,
+        received,
+        expires_at,
+        thread_detail(&message_id, None, None)
+    );
+    report_send(message_id)
+}
+
+/// Accept an escrowed message, recording its fee as the shares of a priority send
+fn process_accept_message(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    sender: Pubkey,
+    nonce: u64,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let recipient = next_account_info(account_iter)?;
+    let escrow_account = next_account_info(account_iter)?;
+    let recipient_claim = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let mailer_usdc = next_account_info(account_iter)?;
+    let token_program = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+    let sender_account = next_account_info(account_iter)?;
+
+    if !recipient.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_token_program(token_program)?;
+    assert_system_program(system_program)?;
+    let (mailer_pda, _) = assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    assert_token_account(
+        accounts,
+        "mailer_usdc",
+        mailer_usdc,
+        &mailer_pda,
+        &mailer_state.usdc_mint,
+    )?;
+
+    let (escrow_pda, _) = pda::escrow(program_id, &sender, nonce);
+    if escrow_account.key != &escrow_pda || escrow_account.owner != program_id {
+        return Err(MailerError::InvalidPDA.into());
+    }
+    if sender_account.key != &sender {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    let escrow_data = escrow_account.try_borrow_data()?;
+    let escrow: Escrow = BorshDeserialize::deserialize(&mut &escrow_data[8..])?;
+    drop(escrow_data);
+
+    if escrow.recipient != *recipient.key {
+        return Err(MailerError::InvalidRecipient.into());
+    }
+    if Clock::get()?.unix_timestamp >= escrow.expires_at {
+        return Err(MailerError::EscrowExpired.into());
+    }
+
+    let (claim_pda, claim_bump) = pda::recipient_claim(program_id, recipient.key);
+    if recipient_claim.key != &claim_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+    create_recipient_claim(
+        program_id,
+        recipient,
+        recipient_claim,
+        system_program,
+        mailer_account,
+        *recipient.key,
+        claim_bump,
+        &[
+            seeds::CLAIM,
+            &[PDA_VERSION],
+            recipient.key.as_ref(),
+            &[claim_bump],
+        ],
+    )?;
+
+    let treasury = treasury_account(&mailer_state, accounts)?.map(|account| Treasury {
+        account,
+        token_program,
+        mailer_bump: mailer_state.bump,
+    });
+    record_shares(
+        recipient_claim,
+        mailer_account,
+        mailer_usdc,
+        None,
+        treasury.as_ref(),
+        *recipient.key,
+        escrow.amount,
+    )?;
+
+    close_account(escrow_account, sender_account)?;
+
+    msg!(
+        "Escrowed mail from {} accepted by {}: escrow {}, amount {}",
+        sender,
+        recipient.key,
+        nonce,
+        escrow.amount
+    );
+    Ok(())
+}
+
+/// Return the fee of an expired, unaccepted escrow to its sender
+fn process_reclaim_escrow(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    nonce: u64,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let sender = next_account_info(account_iter)?;
+    let escrow_account = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let sender_usdc = next_account_info(account_iter)?;
+    let mailer_usdc = next_account_info(account_iter)?;
+    let token_program = next_account_info(account_iter)?;
+
+    if !sender.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_token_program(token_program)?;
+    let (mailer_pda, _) = assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    if mailer_state.is_paused(PAUSE_CLAIM) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    assert_token_account(
+        accounts,
+        "sender_usdc",
+        sender_usdc,
+        sender.key,
+        &mailer_state.usdc_mint,
+    )?;
+    assert_token_account(
+        accounts,
+        "mailer_usdc",
+        mailer_usdc,
+        &mailer_pda,
+        &mailer_state.usdc_mint,
+    )?;
+
+    let (escrow_pda, _) = pda::escrow(program_id, sender.key, nonce);
+    if escrow_account.key != &escrow_pda || escrow_account.owner != program_id {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    let escrow_data = escrow_account.try_borrow_data()?;
+    let escrow: Escrow = BorshDeserialize::deserialize(&mut &escrow_data[8..])?;
+    drop(escrow_data);
+
+    if Clock::get()?.unix_timestamp < escrow.expires_at {
+        return Err(MailerError::EscrowNotExpired.into());
+    }
+
+    if escrow.amount > 0 {
+        invoke_signed(
+            &spl_token::instruction::transfer(
+                token_program.key,
+                mailer_usdc.key,
+                sender_usdc.key,
+                mailer_account.key,
+                &[],
+                escrow.amount,
+            )?,
+            &[
+                mailer_usdc.clone(),
+                sender_usdc.clone(),
+                mailer_account.clone(),
+                token_program.clone(),
+            ],
+            &[&[seeds::MAILER, &[mailer_state.bump]]],
+        )?;
+    }
+
+    close_account(escrow_account, sender)?;
+
+    msg!(
+        "Escrow {} of {} reclaimed: {}",
+        nonce,
+        sender.key,
+        escrow.amount
+    );
+    Ok(())
+}
+
+/// Create an empty mailing list owned by the signer
+fn process_create_list(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    list_id: u64,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let list_account = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_system_program(system_program)?;
+
+    let (list_pda, list_bump) = pda::mailing_list(program_id, owner.key, list_id);
+    if list_account.key != &list_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+    if list_account.lamports() > 0 {
+        return Err(MailerError::AlreadyInitialized.into());
+    }
+
+    let space = 8 + MailingList::LEN;
+    invoke_signed(
+        &system_instruction::create_account(
+            owner.key,
+            list_account.key,
+            Rent::get()?.minimum_balance(space),
+            space as u64,
+            program_id,
+        ),
+        &[owner.clone(), list_account.clone(), system_program.clone()],
+        &[&[
+            seeds::LIST,
+            &[PDA_VERSION],
+            owner.key.as_ref(),
+            &list_id.to_le_bytes(),
+            &[list_bump],
+        ]],
+    )?;
+
+    let mut list_data = list_account.try_borrow_mut_data()?;
+    list_data[0..8].copy_from_slice(&discriminator::account("MailingList"));
+    MailingList {
+        owner: *owner.key,
+        list_id,
+        member_count: 0,
+        chunk_count: 0,
+        bump: list_bump,
+    }
+    .serialize(&mut &mut list_data[8..])?;
+
+    msg!("Mailing list {} created by {}", list_id, owner.key);
+    Ok(())
+}
+
+/// Load the signer's mailing list `list_id`
+fn load_mailing_list(
+    program_id: &Pubkey,
+    owner: &AccountInfo,
+    list_account: &AccountInfo,
+    list_id: u64,
+) -> Result<MailingList, ProgramError> {
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (list_pda, _) = pda::mailing_list(program_id, owner.key, list_id);
+    if list_account.key != &list_pda || list_account.owner != program_id {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    let list_data = list_account.try_borrow_data()?;
+    Ok(BorshDeserialize::deserialize(&mut &list_data[8..])?)
+}
+
+/// Add a member to a chunk of the signer's mailing list, creating the next chunk if needed
+fn process_add_list_member(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    list_id: u64,
+    chunk: u64,
+    member: Pubkey,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let list_account = next_account_info(account_iter)?;
+    let chunk_account = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    assert_system_program(system_program)?;
+    let mut list = load_mailing_list(program_id, owner, list_account, list_id)?;
+
+    let (chunk_pda, chunk_bump) = pda::list_chunk(program_id, list_account.key, chunk);
+    if chunk_account.key != &chunk_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    // Chunks are created in order, so `chunk_count` is always the next one
+    if chunk == list.chunk_count {
+        let space = 8 + ListChunk::LEN;
+        invoke_signed(
+            &system_instruction::create_account(
+                owner.key,
+                chunk_account.key,
+                Rent::get()?.minimum_balance(space),
+                space as u64,
+                program_id,
+            ),
+            &[owner.clone(), chunk_account.clone(), system_program.clone()],
+            &[&[
+                seeds::LIST_CHUNK,
+                &[PDA_VERSION],
+                list_account.key.as_ref(),
+                &chunk.to_le_bytes(),
+                &[chunk_bump],
+            ]],
+        )?;
+
+        let mut chunk_data = chunk_account.try_borrow_mut_data()?;
+        chunk_data[0..8].copy_from_slice(&discriminator::account("ListChunk"));
+        ListChunk {
+            list: *list_account.key,
+            index: chunk,
+            bump: chunk_bump,
+            members: Vec::new(),
+        }
+        .serialize(&mut &mut chunk_data[8..])?;
+        list.chunk_count += 1;
+    } else if chunk > list.chunk_count || chunk_account.owner != program_id {
+        return Err(MailerError::InvalidListChunk.into());
+    }
+
+    let mut chunk_data = chunk_account.try_borrow_mut_data()?;
+    let mut list_chunk: ListChunk = BorshDeserialize::deserialize(&mut &chunk_data[8..])?;
+    if list_chunk.members.contains(&member) {
+        return Err(MailerError::ListMemberExists.into());
+    }
+    if list_chunk.members.len() >= MAX_LIST_CHUNK_MEMBERS {
+        return Err(MailerError::InvalidListChunk.into());
+    }
+    list_chunk.members.push(member);
+    list_chunk.serialize(&mut &mut chunk_data[8..])?;
+    drop(chunk_data);
+
+    list.member_count += 1;
+    let mut list_data = list_account.try_borrow_mut_data()?;
+    list.serialize(&mut &mut list_data[8..])?;
+
+    msg!(
+        "Member {} added to mailing list {} of {} (chunk {})",
+        member,
+        list_id,
+        owner.key,
+        chunk
+    );
+    Ok(())
+}
+
+/// Remove a member from a chunk of the signer's mailing list
+fn process_remove_list_member(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    list_id: u64,
+    chunk: u64,
+    member: Pubkey,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let list_account = next_account_info(account_iter)?;
+    let chunk_account = next_account_info(account_iter)?;
+
+    let mut list = load_mailing_list(program_id, owner, list_account, list_id)?;
+
+    let (chunk_pda, _) = pda::list_chunk(program_id, list_account.key, chunk);
+    if chunk_account.key != &chunk_pda || chunk_account.owner != program_id {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    let mut chunk_data = chunk_account.try_borrow_mut_data()?;
+    let mut list_chunk: ListChunk = BorshDeserialize::deserialize(&mut &chunk_data[8..])?;
+    let position = list_chunk
+        .members
+        .iter()
+        .position(|m| m == &member)
+        .ok_or(MailerError::NotListMember)?;
+    list_chunk.members.swap_remove(position);
+    // Clear the stale tail left by the shorter member list
+    chunk_data[8..].fill(0);
+    list_chunk.serialize(&mut &mut chunk_data[8..])?;
+    drop(chunk_data);
+
+    list.member_count -= 1;
+    let mut list_data = list_account.try_borrow_mut_data()?;
+    list.serialize(&mut &mut list_data[8..])?;
+
+    msg!(
+        "Member {} removed from mailing list {} of {} (chunk {})",
+        member,
+        list_id,
+        owner.key,
+        chunk
+    );
+    Ok(())
+}
+
+/// Broadcast a message to the signer's mailing list, charging the standard fee per member
+fn process_send_to_list(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    list_id: u64,
+    subject: String,
+    _body: String,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let list_account = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let owner_usdc = next_account_info(account_iter)?;
+    let mailer_usdc = next_account_info(account_iter)?;
+    let token_program = next_account_info(account_iter)?;
+
+    let list = load_mailing_list(program_id, owner, list_account, list_id)?;
+
+    assert_token_program(token_program)?;
+    let (mailer_pda, _) = assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    if mailer_state.is_paused(PAUSE_SEND) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    assert_payload_len(&subject, mailer_state.payload_limits.max_subject_len)?;
+    assert_payload_len(&_body, mailer_state.payload_limits.max_body_len)?;
+    assert_not_sanctioned(program_id, &mailer_state, owner.key, accounts)?;
+
+    assert_token_account(
+        accounts,
+        "sender_usdc",
+        owner_usdc,
+        owner.key,
+        &mailer_state.usdc_mint,
+    )?;
+    assert_token_account(
+        accounts,
+        "mailer_usdc",
+        mailer_usdc,
+        &mailer_pda,
+        &mailer_state.usdc_mint,
+    )?;
+
+    if list.member_count == 0 {
+        return Err(MailerError::EmptyList.into());
+    }
+
+    // Each member costs the owner share of a standard send
+    let member_fee = if mailer_state.fee_paused {
+        0
+    } else {
+        let current_fee = mailer_state.effective_send_fee(Clock::get()?.unix_timestamp);
+        let current_fee = mailer_state.payload_fee(current_fee, subject.len() + _body.len())?;
+        standard_fee(calculate_fee_with_discount(
+            program_id,
+            owner.key,
+            accounts,
+            current_fee,
+        )?)?
+    };
+    let fee = member_fee
+        .checked_mul(list.member_count)
+        .ok_or(MailerError::MathOverflow)?;
+
+    if fee > 0 {
+        let received =
+            transfer_to_vault(token_program, owner_usdc, mailer_usdc, owner, accounts, fee)?;
+        match treasury_account(&mailer_state, accounts)? {
+            Some(account) if received > 0 => {
+                let treasury = Treasury {
+                    account,
+                    token_program,
+                    mailer_bump: mailer_state.bump,
+                };
+                forward_to_treasury(&treasury, mailer_account, mailer_usdc, received)?
+            }
+            _ if received > 0 => credit_owner(mailer_account, None, received)?,
+            _ => {}
+        }
+    }
+
+    for chunk in 0..list.chunk_count {
+        msg!(
+            "ListMailSent {{ list: {}, owner: {}, chunk: {}, subject: {} }}",
+            list_account.key,
+            owner.key,
+            chunk,
+            subject
+        );
+    }
+    // One message id covers the whole list
+    let message_nonce = next_message_nonce(program_id, owner.key, owner, accounts)?;
+    let payload_hash = hash_message_body(&subject, &_body);
+    let message_id = derive_message_id(
+        owner.key,
+        list_account.key.as_ref(),
+        &payload_hash,
+        Clock::get()?.slot,
+        message_nonce,
+    );
+
+    msg!(
+        "Mail sent to mailing list {} of {}: {} members, fee {}{}",
+        list_id,
+        owner.key,
+        list.member_count,
+        fee,
+        thread_detail(&message_id, None, None)
+    );
+    report_send(message_id)
+}
+
+/// Send to the owner of a `.sol` domain, resolved from its name account
+fn process_send_to_domain(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    domain: String,
+    subject: String,
+    _body: String,
+    revenue_share_to_receiver: bool,
+    strict: bool,
+) -> ProgramResult {
+    let name_account = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    if name_account.owner != &sns::NAME_PROGRAM_ID || name_account.key != &sns::domain_key(&domain)
+    {
+        return Err(MailerError::InvalidDomain.into());
+    }
+    let to =
+        sns::record_owner(&name_account.try_borrow_data()?).ok_or(MailerError::InvalidDomain)?;
+
+    msg!(
+        "Domain {}.sol resolved to {}",
+        sns::domain_name(&domain),
+        to
+    );
+
+    // The Send handler sees its own account list, without the name account
+    let mut send_accounts = vec![accounts[0].clone()];
+    send_accounts.extend_from_slice(&accounts[2..]);
+    process_send(
+        program_id,
+        &send_accounts,
+        to,
+        subject,
+        _body,
+        revenue_share_to_receiver,
+        false,
+        false,
+        None,
+        None,
+        strict,
+        None,
+        None,
+        false,
+        0,
+        false,
+        None,
+    )
+}
+
+/// Bind an EVM address to the signing wallet, proven by the preceding secp256k1 instruction
+fn process_bind_evm_address(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    evm_address: [u8; 20],
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let wallet = next_account_info(account_iter)?;
+    let mapping_account = next_account_info(account_iter)?;
+    let instructions_sysvar = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !wallet.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_system_program(system_program)?;
+    if instructions_sysvar.key != &sysvar::instructions::id() {
+        return Err(MailerError::InvalidInstructionsSysvar.into());
+    }
+
+    // The runtime has already checked the signature of the secp256k1 instruction; what it
+    // signed must be this wallet's binding message, by this address
+    let current_index = sysvar::instructions::load_current_index_checked(instructions_sysvar)?;
+    let proof_index = current_index
+        .checked_sub(1)
+        .ok_or(MailerError::InvalidEvmSignature)?;
+    let proof = sysvar::instructions::load_instruction_at_checked(
+        proof_index as usize,
+        instructions_sysvar,
+    )?;
+    let verified = proof.program_id == secp256k1_program::id()
+        && matches!(
+            evm::verified_signer(&proof.data, proof_index as u8),
+            Some((address, message)) if address == evm_address && message == evm::binding_message(program_id, wallet.key)
+        );
+    if !verified {
+        return Err(MailerError::InvalidEvmSignature.into());
+    }
+
+    let (mapping_pda, mapping_bump) = pda::evm_mapping(program_id, &evm_address);
+    if mapping_account.key != &mapping_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    if mapping_account.lamports() == 0 {
+        let space = 8 + EvmMapping::LEN;
+        invoke_signed(
+            &system_instruction::create_account(
+                wallet.key,
+                mapping_account.key,
+                Rent::get()?.minimum_balance(space),
+                space as u64,
+                program_id,
+            ),
+            &[
+                wallet.clone(),
+                mapping_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                seeds::EVM_MAP,
+                &[PDA_VERSION],
+                &evm_address,
+                &[mapping_bump],
+            ]],
+        )?;
+    } else if mapping_account.owner != program_id {
+        return Err(MailerError::InvalidAccountOwner.into());
+    }
+
+    let mut mapping_data = mapping_account.try_borrow_mut_data()?;
+    mapping_data[0..8].copy_from_slice(&discriminator::account("EvmMapping"));
+    EvmMapping {
+        evm_address,
+        wallet: *wallet.key,
+        bound_at: Clock::get()?.unix_timestamp,
+        bump: mapping_bump,
+    }
+    .serialize(&mut &mut mapping_data[8..])?;
+
+    msg!(
+        "EVM address {} bound to {}",
+        evm::to_hex(&evm_address),
+        wallet.key
+    );
+    Ok(())
+}
+
+/// Send to the wallet an EVM address is bound to
+fn process_send_to_evm_address(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    evm_address: [u8; 20],
+    subject: String,
+    _body: String,
+    revenue_share_to_receiver: bool,
+    strict: bool,
+) -> ProgramResult {
+    let mapping_account = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    let (mapping_pda, _) = pda::evm_mapping(program_id, &evm_address);
+    if mapping_account.key != &mapping_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+    if mapping_account.owner != program_id || mapping_account.lamports() == 0 {
+        return Err(MailerError::EvmAddressNotBound.into());
+    }
+    let mapping: EvmMapping =
+        BorshDeserialize::deserialize(&mut &mapping_account.try_borrow_data()?[8..])?;
+
+    msg!(
+        "EVM address {} resolved to {}",
+        evm::to_hex(&evm_address),
+        mapping.wallet
+    );
+
+    // The Send handler sees its own account list, without the mapping account
+    let mut send_accounts = vec![accounts[0].clone()];
+    send_accounts.extend_from_slice(&accounts[2..]);
+    process_send(
+        program_id,
+        &send_accounts,
+        mapping.wallet,
+        subject,
+        _body,
+        revenue_share_to_receiver,
+        false,
+        false,
+        None,
+        None,
+        strict,
+        None,
+        None,
+        false,
+        0,
+        false,
+        None,
+    )
+}
+
+/// Point bridged sends at a Wormhole core bridge, or switch bridging off (owner only)
+fn process_set_wormhole_bridge(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    core_bridge: Option<Pubkey>,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let config_account = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_system_program(system_program)?;
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+
+    let (config_pda, config_bump) = pda::wormhole_config(program_id);
+    if config_account.key != &config_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    let (emitter, emitter_bump) = pda::wormhole_emitter(program_id);
+    let message_count = if config_account.lamports() == 0 {
+        let space = 8 + WormholeConfig::LEN;
+        invoke_signed(
+            &system_instruction::create_account(
+                owner.key,
+                config_account.key,
+                Rent::get()?.minimum_balance(space),
+                space as u64,
+                program_id,
+            ),
+            &[
+                owner.clone(),
+                config_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[seeds::WORMHOLE, &[PDA_VERSION], &[config_bump]]],
+        )?;
+        config_account.try_borrow_mut_data()?[0..8]
+            .copy_from_slice(&discriminator::account("WormholeConfig"));
+        0
+    } else {
+        if config_account.owner != program_id {
+            return Err(MailerError::InvalidAccountOwner.into());
+        }
+        let config: WormholeConfig =
+            BorshDeserialize::deserialize(&mut &config_account.try_borrow_data()?[8..])?;
+        config.message_count
+    };
+
+    let addresses = match core_bridge {
+        Some(core_bridge) => wormhole::BridgeAddresses::derive(&core_bridge, &emitter),
+        None => wormhole::BridgeAddresses {
+            core_bridge: Pubkey::default(),
+            bridge: Pubkey::default(),
+            fee_collector: Pubkey::default(),
+            sequence: Pubkey::default(),
+        },
+    };
+    let mut config_data = config_account.try_borrow_mut_data()?;
+    WormholeConfig {
+        core_bridge: addresses.core_bridge,
+        bridge: addresses.bridge,
+        fee_collector: addresses.fee_collector,
+        sequence: addresses.sequence,
+        emitter_bump,
+        message_count,
+        bump: config_bump,
+    }
+    .serialize(&mut &mut config_data[8..])?;
+
+    msg!(
+        "Wormhole bridge set to {:?} (emitter: {})",
+        core_bridge,
+        emitter
+    );
+    Ok(())
+}
+
+/// Return the fees charged now as return data
+fn process_get_fee(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let mailer_account = next_account_info(account_iter)?;
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    let fees = mailer_state.fee_view(Clock::get()?.unix_timestamp)?;
+    set_return_data(&borsh::to_vec(&fees)?);
+    Ok(())
+}
+
+/// Return the recipient share `account` could claim now as return data
+fn process_get_claimable(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    account: Pubkey,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let mailer_account = next_account_info(account_iter)?;
+    let recipient_claim = next_account_info(account_iter)?;
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    if recipient_claim.key != &pda::recipient_claim(program_id, &account).0 {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    let claimable = if recipient_claim.lamports() == 0 {
+        ClaimableView::default()
+    } else {
+        if recipient_claim.owner != program_id {
+            return Err(MailerError::InvalidAccountOwner.into());
+        }
+        let claim = RecipientClaim::from_account_data(&recipient_claim.try_borrow_data()?[8..])?;
+        mailer_state.claimable_view(&claim, Clock::get()?.unix_timestamp)
+    };
+    set_return_data(&borsh::to_vec(&claimable)?);
+    Ok(())
+}
+
+/// Return the delegation of `delegator` as return data
+fn process_get_delegation(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    delegator: Pubkey,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let delegation_account = next_account_info(account_iter)?;
+
+    if delegation_account.key != &pda::delegation(program_id, &delegator).0 {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    let delegation = if delegation_account.lamports() == 0 {
+        DelegationView::default()
+    } else {
+        if delegation_account.owner != program_id {
+            return Err(MailerError::InvalidAccountOwner.into());
+        }
+        let delegation: Delegation =
+            BorshDeserialize::deserialize(&mut &delegation_account.try_borrow_data()?[8..])?;
+        DelegationView::from(&delegation)
+    };
+    set_return_data(&borsh::to_vec(&delegation)?);
+    Ok(())
+}
+
+/// Replace the volume discount tiers
+fn process_set_discount_schedule(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    tiers: Vec<VolumeTier>,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let schedule_account = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_system_program(system_program)?;
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+    assert_fee_not_timelocked(&mailer_state)?;
+
+    let ascending = tiers.windows(2).all(|pair| {
+        pair[0].min_fees_paid < pair[1].min_fees_paid && pair[0].discount <= pair[1].discount
+    });
+    if tiers.len() > MAX_DISCOUNT_TIERS
+        || !ascending
+        || tiers.iter().any(|tier| tier.discount > 100)
+    {
+        return Err(MailerError::InvalidDiscountSchedule.into());
+    }
+
+    let (schedule_pda, schedule_bump) = pda::discount_schedule(program_id);
+    if schedule_account.key != &schedule_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    if schedule_account.lamports() == 0 {
+        let space = 8 + DiscountSchedule::LEN;
+        invoke_signed(
+            &system_instruction::create_account(
+                owner.key,
+                schedule_account.key,
+                Rent::get()?.minimum_balance(space),
+                space as u64,
+                program_id,
+            ),
+            &[
+                owner.clone(),
+                schedule_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[seeds::DISCOUNT_SCHEDULE, &[PDA_VERSION], &[schedule_bump]]],
+        )?;
+        schedule_account.try_borrow_mut_data()?[0..8]
+            .copy_from_slice(&discriminator::account("DiscountSchedule"));
+    } else if schedule_account.owner != program_id {
+        return Err(MailerError::InvalidAccountOwner.into());
+    }
+
+    let tier_count = tiers.len();
+    let mut schedule_data = schedule_account.try_borrow_mut_data()?;
+    DiscountSchedule {
+        bump: schedule_bump,
+        tiers,
+    }
+    .serialize(&mut &mut schedule_data[8..])?;
+
+    msg!("Discount schedule set with {} tiers", tier_count);
+    Ok(())
+}
+
+/// Post `send` through the configured Wormhole core bridge, paying its message fee from
+/// `payer`
+/// The wormhole config, the bridge accounts it names, the next message account, the emitter
+/// and the sysvars must be among the supplied accounts.
+#[cfg(feature = "wormhole")]
+fn post_bridge_message<'a>(
+    program_id: &Pubkey,
+    payer: &AccountInfo<'a>,
+    send: &wormhole::MirroredSend,
+    accounts: &[AccountInfo<'a>],
+) -> ProgramResult {
+    let find = |key: &Pubkey| {
+        accounts
+            .iter()
+            .find(|acc| acc.key == key)
+            .ok_or(ProgramError::NotEnoughAccountKeys)
+    };
+
+    let (config_pda, _) = pda::wormhole_config(program_id);
+    let config_account = find(&config_pda).map_err(|_| MailerError::WormholeNotConfigured)?;
+    if config_account.owner != program_id {
+        return Err(MailerError::WormholeNotConfigured.into());
+    }
+    let mut config: WormholeConfig =
+        BorshDeserialize::deserialize(&mut &config_account.try_borrow_data()?[8..])?;
+    if config.core_bridge == Pubkey::default() {
+        return Err(MailerError::WormholeNotConfigured.into());
+    }
+
+    let addresses = wormhole::BridgeAddresses {
+        core_bridge: config.core_bridge,
+        bridge: config.bridge,
+        fee_collector: config.fee_collector,
+        sequence: config.sequence,
+    };
+    let emitter_seeds: &[&[u8]] = &[
+        seeds::WORMHOLE_EMITTER,
+        &[PDA_VERSION],
+        &[config.emitter_bump],
+    ];
+    let emitter = Pubkey::create_program_address(emitter_seeds, program_id)
+        .map_err(|_| MailerError::InvalidPDA)?;
+    let (message, message_bump) = pda::wormhole_message(program_id, config.message_count);
+    let count = config.message_count.to_le_bytes();
+    let message_seeds: &[&[u8]] = &[
+        seeds::WORMHOLE_MESSAGE,
+        &[PDA_VERSION],
+        &count,
+        &[message_bump],
+    ];
+
+    // The core bridge only accepts a message once its fee is in the fee collector
+    let bridge = find(&addresses.bridge)?;
+    if bridge.owner != &addresses.core_bridge {
+        return Err(MailerError::InvalidAccountOwner.into());
+    }
+    let fee = wormhole::message_fee(&bridge.try_borrow_data()?)
+        .ok_or(MailerError::InvalidBridgeConfig)?;
+    if fee > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, &addresses.fee_collector, fee),
+            &[
+                payer.clone(),
+                find(&addresses.fee_collector)?.clone(),
+                find(&solana_program::system_program::id())?.clone(),
+            ],
+        )?;
+    }
+
+    let instruction = wormhole::post_message_instruction(
+        &addresses,
+        payer.key,
+        &emitter,
+        &message,
+        0,
+        wormhole::encode_send_payload(send),
+    );
+    let mut infos = vec![find(&addresses.core_bridge)?.clone()];
+    for meta in &instruction.accounts {
+        infos.push(find(&meta.pubkey)?.clone());
+    }
+    invoke_signed(&instruction, &infos, &[emitter_seeds, message_seeds])?;
+
+    msg!(
+        "Wormhole message {} posted for mail from {} to {}",
+        config.message_count,
+        send.sender,
+        send.recipient
+    );
+
+    config.message_count = config
+        .message_count
+        .checked_add(1)
+        .ok_or(MailerError::MathOverflow)?;
+    config.serialize(&mut &mut config_account.try_borrow_mut_data()?[8..])?;
+    Ok(())
+}
+
+/// Bridged sends need the `wormhole` feature
+#[cfg(not(feature = "wormhole"))]
+fn post_bridge_message(
+    _program_id: &Pubkey,
+    _payer: &AccountInfo,
+    _send: &wormhole::MirroredSend,
+    _accounts: &[AccountInfo],
+) -> ProgramResult {
+    Err(MailerError::WormholeDisabled.into())
+}
+
+/// Set payload size limits and fee tiers (owner only)
+fn process_set_payload_limits(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    limits: PayloadLimits,
+    fee_tier_len: u16,
+    fee_tier_percent: u16,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+    if (fee_tier_len, fee_tier_percent)
+        != (
+            mailer_state.payload_fee_tier_len,
+            mailer_state.payload_fee_tier_percent,
+        )
+    {
+        assert_fee_not_timelocked(&mailer_state)?;
+    }
+
+    mailer_state.payload_limits = limits;
+    mailer_state.payload_fee_tier_len = fee_tier_len;
+    mailer_state.payload_fee_tier_percent = fee_tier_percent;
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+
+    msg!(
+        "Payload limits set to {:?}, fee tiers of {} bytes adding {}%",
+        limits,
+        fee_tier_len,
+        fee_tier_percent
+    );
+    Ok(())
+}
+
+/// Set fee paused state (owner only)
+fn process_set_fee_paused(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fee_paused: bool,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_mailer_account(_program_id, accounts, mailer_account)?;
+
+    // Load and update mailer state
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    assert_owner_authority(_program_id, &mailer_state, owner, accounts)?;
+
+    mailer_state.fee_paused = fee_paused;
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+
+    msg!("Fee paused state set to: {}", fee_paused);
+    Ok(())
+}
+
+/// Set recipient messaging preferences (recipient only)
+fn process_set_recipient_prefs(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    read_receipts_opt_out: bool,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let authority = next_account_info(account_iter)?;
+    let prefs_account = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+    let session_account = account_iter.next();
+
+    let recipient = resolve_recipient_authority(
+        program_id,
+        authority,
+        session_account,
+        SessionKey::SCOPE_PREFS,
+    )?;
+
+    assert_system_program(system_program)?;
+
+    // Verify recipient preferences PDA
+    let (prefs_pda, prefs_bump) = pda::recipient_prefs(program_id, &recipient);
+
+    if prefs_account.key != &prefs_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    // Create preferences account if needed
+    if prefs_account.lamports() == 0 {
+        let rent = Rent::get()?;
+        let space = 8 + RecipientPrefs::LEN;
+        let lamports = rent.minimum_balance(space);
+
+        invoke_signed(
+            &system_instruction::create_account(
+                authority.key,
+                prefs_account.key,
+                lamports,
+                space as u64,
+                program_id,
+            ),
+            &[
+                authority.clone(),
+                prefs_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                seeds::PREFS,
+                &[PDA_VERSION],
+                recipient.as_ref(),
+                &[prefs_bump],
+            ]],
+        )?;
+
+        let mut prefs_data = prefs_account.try_borrow_mut_data()?;
+        prefs_data[0..8].copy_from_slice(&discriminator::account("RecipientPrefs"));
+        drop(prefs_data);
+    }
+
+    let prefs = RecipientPrefs {
+        recipient,
+        read_receipts_opt_out,
+        bump: prefs_bump,
+    };
+
+    let mut prefs_data = prefs_account.try_borrow_mut_data()?;
+    prefs.serialize(&mut &mut prefs_data[8..])?;
+
+    msg!(
+        "Recipient prefs updated for {}: read receipts opt-out {}",
+        recipient,
+        read_receipts_opt_out
+    );
+    Ok(())
+}
+
+/// Set a recipient's inbox policy
+fn process_set_inbox_policy(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    mode: InboxMode,
+    senders: Vec<Pubkey>,
+    penalty_multiplier: u8,
+    min_fee: u64,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let authority = next_account_info(account_iter)?;
+    let policy_account = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+    let session_account = account_iter.next();
+
+    let recipient = resolve_recipient_authority(
+        program_id,
+        authority,
+        session_account,
+        SessionKey::SCOPE_INBOX_POLICY,
+    )?;
+
+    assert_system_program(system_program)?;
+
+    if senders.len() > MAX_POLICY_SENDERS {
+        return Err(MailerError::InvalidInboxPolicy.into());
+    }
+
+    // Verify inbox policy PDA
+    let (policy_pda, policy_bump) = pda::inbox_policy(program_id, &recipient);
+
+    if policy_account.key != &policy_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    // Create policy account if needed
+    if policy_account.lamports() == 0 {
+        let rent = Rent::get()?;
+        let space = 8 + InboxPolicy::LEN;
+        let lamports = rent.minimum_balance(space);
+
+        invoke_signed(
+            &system_instruction::create_account(
+                authority.key,
+                policy_account.key,
+                lamports,
+                space as u64,
+                program_id,
+            ),
+            &[
+                authority.clone(),
+                policy_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                seeds::POLICY,
+                &[PDA_VERSION],
+                recipient.as_ref(),
+                &[policy_bump],
+            ]],
+        )?;
+
+        let mut policy_data = policy_account.try_borrow_mut_data()?;
+        policy_data[0..8].copy_from_slice(&discriminator::account("InboxPolicy"));
+        drop(policy_data);
+    } else if policy_account.data_len() < 8 + InboxPolicy::LEN {
+        // Make room for the minimum fee in a policy created before it existed
+        let space = 8 + InboxPolicy::LEN;
+        let shortfall = Rent::get()?
+            .minimum_balance(space)
+            .saturating_sub(policy_account.lamports());
+        if shortfall > 0 {
+            invoke(
+                &system_instruction::transfer(authority.key, policy_account.key, shortfall),
+                &[
+                    authority.clone(),
+                    policy_account.clone(),
+                    system_program.clone(),
+                ],
+            )?;
+        }
+        policy_account.realloc(space, true)?;
+    }
+
+    let sender_count = senders.len();
+    let policy = InboxPolicy {
+        recipient,
+        mode,
+        senders,
+        penalty_multiplier,
+        bump: policy_bump,
+        min_fee,
+    };
+
+    let mut policy_data = policy_account.try_borrow_mut_data()?;
+    policy.serialize(&mut &mut policy_data[8..])?;
+
+    msg!(
+        "Inbox policy updated for {}: mode {:?}, {} senders, penalty multiplier {}, min fee {}",
+        recipient,
+        mode,
+        sender_count,
+        penalty_multiplier,
+        min_fee
+    );
+    Ok(())
+}
+
+/// Register or replace a recipient session key
+fn process_register_session_key(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    session_key: Pubkey,
+    scope: u8,
+    expires_at: i64,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let recipient = next_account_info(account_iter)?;
+    let session_account = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !recipient.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_system_program(system_program)?;
+
+    if scope == 0 || scope & !SessionKey::SCOPE_ALL != 0 || session_key == *recipient.key {
+        return Err(MailerError::InvalidSessionKey.into());
+    }
+
+    let current_time = Clock::get()?.unix_timestamp;
+    if expires_at <= current_time || expires_at - current_time > MAX_SESSION_DURATION {
+        return Err(MailerError::InvalidSessionKey.into());
+    }
+
+    // Verify session key PDA
+    let (session_pda, session_bump) = pda::session_key(program_id, recipient.key, &session_key);
+
+    if session_account.key != &session_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    // Create session key account if needed
+    if session_account.lamports() == 0 {
+        let rent = Rent::get()?;
+        let space = 8 + SessionKey::LEN;
+        let lamports = rent.minimum_balance(space);
+
+        invoke_signed(
+            &system_instruction::create_account(
+                recipient.key,
+                session_account.key,
+                lamports,
+                space as u64,
+                program_id,
+            ),
+            &[
+                recipient.clone(),
+                session_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                seeds::SESSION,
+                &[PDA_VERSION],
+                recipient.key.as_ref(),
+                session_key.as_ref(),
+                &[session_bump],
+            ]],
+        )?;
+
+        let mut session_data = session_account.try_borrow_mut_data()?;
+        session_data[0..8].copy_from_slice(&discriminator::account("SessionKey"));
+        drop(session_data);
+    }
+
+    let session = SessionKey {
+        recipient: *recipient.key,
+        session_key,
+        scope,
+        expires_at,
+        bump: session_bump,
+    };
+
+    let mut session_data = session_account.try_borrow_mut_data()?;
+    session.serialize(&mut &mut session_data[8..])?;
+
+    msg!(
+        "Session key {} registered for {}: scope {}, expires at {}",
+        session_key,
+        recipient.key,
+        scope,
+        expires_at
+    );
+    Ok(())
+}
+
+/// Revoke a recipient session key
+fn process_revoke_session_key(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    session_key: Pubkey,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let recipient = next_account_info(account_iter)?;
+    let session_account = next_account_info(account_iter)?;
+
+    if !recipient.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (session_pda, _) = pda::session_key(program_id, recipient.key, &session_key);
+
+    if session_account.key != &session_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    let mut session_data = session_account.try_borrow_mut_data()?;
+    let mut session: SessionKey = BorshDeserialize::deserialize(&mut &session_data[8..])?;
+    session.scope = 0;
+    session.expires_at = 0;
+    session.serialize(&mut &mut session_data[8..])?;
+
+    msg!("Session key {} revoked for {}", session_key, recipient.key);
+    Ok(())
+}
+
+/// Create or replace a send pre-authorization
+fn process_create_preauthorization(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    relayer: Pubkey,
+    webhook_id: String,
+    max_sends: u64,
+    max_fee_per_send: u64,
+    expires_at: i64,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let user = next_account_info(account_iter)?;
+    let preauth_account = next_account_info(account_iter)?;
+    let user_usdc = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let token_program = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !user.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_system_program(system_program)?;
+    assert_token_program(token_program)?;
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    assert_token_account(
+        accounts,
+        "user_usdc",
+        user_usdc,
+        user.key,
+        &mailer_state.usdc_mint,
+    )?;
+    assert_payload_len(&webhook_id, mailer_state.payload_limits.max_webhook_id_len)?;
+
+    if relayer == *user.key || max_sends == 0 || webhook_id.len() > MAX_WEBHOOK_ID_LEN {
+        return Err(MailerError::InvalidPreauthorization.into());
+    }
+
+    if expires_at <= Clock::get()?.unix_timestamp {
+        return Err(MailerError::InvalidPreauthorization.into());
+    }
+
+    let allowance = max_sends
+        .checked_mul(max_fee_per_send)
+        .ok_or(MailerError::MathOverflow)?;
+
+    // Verify pre-authorization PDA
+    let (preauth_pda, preauth_bump) = pda::preauthorization(program_id, user.key, &relayer);
+
+    if preauth_account.key != &preauth_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    // Create pre-authorization account if needed
+    if preauth_account.lamports() == 0 {
+        let rent = Rent::get()?;
+        let space = 8 + Preauthorization::LEN;
+        let lamports = rent.minimum_balance(space);
+
+        invoke_signed(
+            &system_instruction::create_account(
+                user.key,
+                preauth_account.key,
+                lamports,
+                space as u64,
+                program_id,
+            ),
+            &[
+                user.clone(),
+                preauth_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                seeds::PREAUTH,
+                &[PDA_VERSION],
+                user.key.as_ref(),
+                relayer.as_ref(),
+                &[preauth_bump],
+            ]],
+        )?;
+
+        let mut preauth_data = preauth_account.try_borrow_mut_data()?;
+        preauth_data[0..8].copy_from_slice(&discriminator::account("Preauthorization"));
+        drop(preauth_data);
+    }
+
+    let preauth = Preauthorization {
+        user: *user.key,
+        relayer,
+        webhook_id,
+        max_sends,
+        sends_used: 0,
+        max_fee_per_send,
+        expires_at,
+        bump: preauth_bump,
+    };
+
+    let mut preauth_data = preauth_account.try_borrow_mut_data()?;
+    preauth.serialize(&mut &mut preauth_data[8..])?;
+    drop(preauth_data);
+
+    // Let the pre-authorization PDA pull fees from the user's USDC account
+    invoke(
+        &spl_token::instruction::approve(
+            token_program.key,
+            user_usdc.key,
+            preauth_account.key,
+            user.key,
+            &[],
+            allowance,
+        )?,
+        &[
+            user_usdc.clone(),
+            preauth_account.clone(),
+            user.clone(),
+            token_program.clone(),
+        ],
+    )?;
+
+    msg!(
+        "Preauthorization created by {} for relayer {} (webhookId: {}): {} sends, max fee {}, expires at {}",
+        user.key,
+        relayer,
+        preauth.webhook_id,
+        max_sends,
+        max_fee_per_send,
+        expires_at
+    );
+    Ok(())
+}
+
+/// Revoke a send pre-authorization and its token delegation
+fn process_revoke_preauthorization(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    relayer: Pubkey,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let user = next_account_info(account_iter)?;
+    let preauth_account = next_account_info(account_iter)?;
+    let user_usdc = next_account_info(account_iter)?;
+    let token_program = next_account_info(account_iter)?;
+
+    if !user.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_token_program(token_program)?;
+
+    let (preauth_pda, _) = pda::preauthorization(program_id, user.key, &relayer);
+
+    if preauth_account.key != &preauth_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    let mut preauth_data = preauth_account.try_borrow_mut_data()?;
+    let mut preauth: Preauthorization = BorshDeserialize::deserialize(&mut &preauth_data[8..])?;
+    preauth.max_sends = preauth.sends_used;
+    preauth.expires_at = 0;
+    preauth.serialize(&mut &mut preauth_data[8..])?;
+    drop(preauth_data);
+
+    let token_data = user_usdc.try_borrow_data()?;
+    let token_account = unpack_token_account(&token_data)?;
+    drop(token_data);
+
+    if token_account.owner != *user.key {
+        return Err(MailerError::InvalidAccountOwner.into());
+    }
+
+    // Leave delegations the user has since granted to someone else untouched
+    if token_account.delegate == COption::Some(preauth_pda) {
+        invoke(
+            &spl_token::instruction::revoke(token_program.key, user_usdc.key, user.key, &[])?,
+            &[user_usdc.clone(), user.clone(), token_program.clone()],
+        )?;
+    }
+
+    msg!(
+        "Preauthorization for relayer {} revoked by {}",
+        relayer,
+        user.key
+    );
+    Ok(())
+}
+
+/// Set or clear a recipient's payout token account
+fn process_set_payout_address(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    payout_token_account: Option<Pubkey>,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let recipient = next_account_info(account_iter)?;
+    let payout_account = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !recipient.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_system_program(system_program)?;
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    // Verify payout config PDA
+    let (payout_pda, payout_bump) = pda::payout_config(program_id, recipient.key);
+
+    if payout_account.key != &payout_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    // The payout destination must be a USDC token account
+    if let Some(payout_token_account) = payout_token_account {
+        let payout_usdc = next_account_info(account_iter)?;
+        if payout_usdc.key != &payout_token_account {
+            return Err(MailerError::InvalidPayoutAccount.into());
+        }
+
+        let mailer_data = mailer_account.try_borrow_data()?;
+        let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+        drop(mailer_data);
+
+        if payout_usdc.owner != &spl_token::id() && payout_usdc.owner != &spl_token_2022::id() {
+            return Err(MailerError::InvalidPayoutAccount.into());
+        }
+        let data = payout_usdc.try_borrow_data()?;
+        let token_account = unpack_token_account(&data)?;
+        if token_account.mint != mailer_state.usdc_mint {
+            return Err(MailerError::InvalidMint.into());
+        }
+    }
+
+    // Create payout config account if needed
+    if payout_account.lamports() == 0 {
+        let rent = Rent::get()?;
+        let space = 8 + PayoutConfig::LEN;
+        let lamports = rent.minimum_balance(space);
+
+        invoke_signed(
+            &system_instruction::create_account(
+                recipient.key,
+                payout_account.key,
+                lamports,
+                space as u64,
+                program_id,
+            ),
+            &[
+                recipient.clone(),
+                payout_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                seeds::PAYOUT,
+                &[PDA_VERSION],
+                recipient.key.as_ref(),
+                &[payout_bump],
+            ]],
+        )?;
+
+        let mut payout_data = payout_account.try_borrow_mut_data()?;
+        payout_data[0..8].copy_from_slice(&discriminator::account("PayoutConfig"));
+        drop(payout_data);
+    }
+
+    let config = PayoutConfig {
+        recipient: *recipient.key,
+        payout_token_account,
+        bump: payout_bump,
+    };
+
+    let mut payout_data = payout_account.try_borrow_mut_data()?;
+    config.serialize(&mut &mut payout_data[8..])?;
+
+    msg!(
+        "Payout address for {} set to {:?}",
+        recipient.key,
+        payout_token_account
+    );
+    Ok(())
+}
+
+/// Register or toggle a conversion vault for an alternate payout mint (owner only)
+fn process_set_conversion_vault(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    mint: Pubkey,
+    enabled: bool,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let conversion_account = next_account_info(account_iter)?;
+    let vault_token = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_system_program(system_program)?;
+
+    let (mailer_pda, _) = assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+
+    if mint == mailer_state.usdc_mint {
+        return Err(MailerError::InvalidMint.into());
+    }
+
+    assert_token_account(accounts, "vault_token", vault_token, &mailer_pda, &mint)?;
+
+    // Verify conversion vault PDA
+    let (conversion_pda, conversion_bump) = pda::conversion_vault(program_id, &mint);
+
+    if conversion_account.key != &conversion_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    // Create conversion vault account if needed
+    let mut total_converted = 0;
+    if conversion_account.lamports() == 0 {
+        let rent = Rent::get()?;
+        let space = 8 + ConversionVault::LEN;
+        let lamports = rent.minimum_balance(space);
+
+        invoke_signed(
+            &system_instruction::create_account(
+                owner.key,
+                conversion_account.key,
+                lamports,
+                space as u64,
+                program_id,
+            ),
+            &[
+                owner.clone(),
+                conversion_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                seeds::CONVERSION,
+                &[PDA_VERSION],
+                mint.as_ref(),
+                &[conversion_bump],
+            ]],
+        )?;
+
+        let mut conversion_data = conversion_account.try_borrow_mut_data()?;
+        conversion_data[0..8].copy_from_slice(&discriminator::account("ConversionVault"));
+        drop(conversion_data);
+    } else {
+        let conversion_data = conversion_account.try_borrow_data()?;
+        let existing: ConversionVault = BorshDeserialize::deserialize(&mut &conversion_data[8..])?;
+        total_converted = existing.total_converted;
+    }
+
+    let vault = ConversionVault {
+        mint,
+        vault: *vault_token.key,
+        enabled,
+        total_converted,
+        bump: conversion_bump,
+    };
+
+    let mut conversion_data = conversion_account.try_borrow_mut_data()?;
+    vault.serialize(&mut &mut conversion_data[8..])?;
+
+    msg!(
+        "Conversion vault for {} set to {} (enabled: {})",
+        mint,
+        vault_token.key,
+        enabled
+    );
+    Ok(())
+}
+
+/// Acknowledge a message, emitting a read receipt unless the recipient opted out
+fn process_acknowledge_message(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    sender: Pubkey,
+    receipt_nonce: Option<u64>,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let recipient = next_account_info(account_iter)?;
+    let prefs_account = next_account_info(account_iter)?;
+
+    if !recipient.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (prefs_pda, _) = pda::recipient_prefs(program_id, recipient.key);
+    if prefs_account.key != &prefs_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    if !read_receipts_allowed(program_id, recipient.key, accounts)? {
+        return Err(MailerError::ReadReceiptsDisabled.into());
+    }
+
+    // Mark the on-chain message receipt as read
+    if let Some(nonce) = receipt_nonce {
+        let receipt_account = next_account_info(account_iter)?;
+
+        let (receipt_pda, _) = pda::message_receipt(program_id, recipient.key, nonce);
+        if receipt_account.key != &receipt_pda || receipt_account.owner != program_id {
+            return Err(MailerError::InvalidPDA.into());
+        }
+
+        let mut receipt_data = receipt_account.try_borrow_mut_data()?;
+        let mut receipt: MessageReceipt = BorshDeserialize::deserialize(&mut &receipt_data[8..])?;
+        if receipt.sender != sender {
+            return Err(MailerError::InvalidRecipient.into());
+        }
+        if receipt.read_at == 0 {
+            receipt.read_at = Clock::get()?.unix_timestamp;
+            receipt.serialize(&mut &mut receipt_data[8..])?;
+        }
+
+        msg!(
+            "Message receipt {} for {} marked read at {}",
+            nonce,
+            recipient.key,
+            receipt.read_at
+        );
+    }
+
+    msg!(
+        "Read receipt: {} acknowledged mail from {}",
+        recipient.key,
+        sender
+    );
+    Ok(())
+}
+
+/// Create a MessageReceipt PDA for a send, paid for by the sender
+fn create_message_receipt<'a>(
+    program_id: &Pubkey,
+    sender: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    recipient: &Pubkey,
+    nonce: u64,
+    accounts: &[AccountInfo<'a>],
+) -> ProgramResult {
+    let nonce_bytes = nonce.to_le_bytes();
+    let (receipt_pda, receipt_bump) = pda::message_receipt(program_id, recipient, nonce);
+
+    let receipt_account = accounts
+        .iter()
+        .find(|acc| acc.key == &receipt_pda)
+        .ok_or(MailerError::InvalidPDA)?;
+
+    if receipt_account.lamports() > 0 {
+        return Err(MailerError::ReceiptAlreadyExists.into());
+    }
+
+    let rent = Rent::get()?;
+    let space = 8 + MessageReceipt::LEN;
+    let lamports = rent.minimum_balance(space);
+
+    invoke_signed(
+        &system_instruction::create_account(
+            sender.key,
+            receipt_account.key,
+            lamports,
+            space as u64,
+            program_id,
+        ),
+        &[
+            sender.clone(),
+            receipt_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            seeds::RECEIPT,
+            &[PDA_VERSION],
+            recipient.as_ref(),
+            &nonce_bytes,
+            &[receipt_bump],
+        ]],
+    )?;
+
+    let receipt = MessageReceipt {
+        sender: *sender.key,
+        recipient: *recipient,
+        nonce,
+        sent_at: Clock::get()?.unix_timestamp,
+        read_at: 0,
+        bump: receipt_bump,
+    };
+
+    let mut receipt_data = receipt_account.try_borrow_mut_data()?;
+    receipt_data[0..8].copy_from_slice(&discriminator::account("MessageReceipt"));
+    receipt.serialize(&mut &mut receipt_data[8..])?;
+
+    msg!(
+        "Message receipt {} created from {} to {}",
+        nonce,
+        sender.key,
+        recipient
+    );
+    Ok(())
+}
+
+/// Return the program configuration, including rent reserve counters, as return data
+fn process_get_config(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let mailer_account = next_account_info(account_iter)?;
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    set_return_data(&borsh::to_vec(&mailer_state)?);
+
+    msg!(
+        "Rent reserves: claims {}, delegations {}, discounts {} (total {})",
+        mailer_state.claim_rent_reserve,
+        mailer_state.delegation_rent_reserve,
+        mailer_state.discount_rent_reserve,
+        mailer_state.total_rent_reserve()
+    );
+    Ok(())
+}
+
+/// Disable or re-enable one instruction variant (owner only)
+fn process_set_instruction_disabled(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    tag: u8,
+    disabled: bool,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    // Instructions that must stay available, the setter among them, cannot be disabled
+    let switchable = instruction_versions::decode(&[instruction_versions::FORMAT_V2, tag])
+        .is_ok_and(|decoded| is_switchable(&decoded.instruction));
+    if tag >= 128 || !switchable {
+        return Err(MailerError::InvalidInstructionTag.into());
+    }
+
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+
+    mailer_state.set_instruction_disabled(tag, disabled);
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+
+    msg!(
+        "Instruction {} {}",
+        tag,
+        if disabled { "disabled" } else { "enabled" }
+    );
+    Ok(())
+}
+
+/// Register or clear the spam oracle and its rejection threshold (owner only)
+fn process_set_spam_oracle(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    oracle: Option<Pubkey>,
+    threshold: Option<u64>,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    // A threshold without an oracle could never be checked
+    if oracle.is_none() && threshold.is_some() {
+        return Err(MailerError::InvalidSpamOracleConfig.into());
+    }
+
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+
+    mailer_state.spam_oracle = oracle;
+    mailer_state.spam_threshold = threshold;
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+
+    msg!(
+        "Spam oracle set to {:?} (threshold: {:?})",
+        oracle,
+        threshold
+    );
+    Ok(())
+}
+
+/// Set the per-sender send rate limit, or disable it with `max_sends = 0` (owner only)
+fn process_set_rate_limit(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    max_sends: u64,
+    window: i64,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    if max_sends > 0 && window <= 0 {
+        return Err(MailerError::InvalidRateLimit.into());
+    }
+
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+
+    mailer_state.rate_limit_max_sends = max_sends;
+    mailer_state.rate_limit_window = window;
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+
+    msg!(
+        "Rate limit set to {} sends per {} seconds",
+        max_sends,
+        window
+    );
+    Ok(())
+}
+
+/// Create a promo code account (owner only)
+fn process_create_promo(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    code_hash: [u8; 32],
+    discount: u8,
+    max_redemptions: u64,
+    expiry: i64,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let promo_account = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_system_program(system_program)?;
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+
+    if discount == 0 || discount > 100 {
+        return Err(MailerError::InvalidPercentage.into());
+    }
+    if max_redemptions == 0 || expiry <= Clock::get()?.unix_timestamp {
+        return Err(MailerError::InvalidPromoConfig.into());
+    }
+
+    let (promo_pda, promo_bump) = pda::promo(program_id, &code_hash);
+
+    if promo_account.key != &promo_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    if promo_account.lamports() > 0 {
+        return Err(MailerError::AlreadyInitialized.into());
+    }
+
+    let rent = Rent::get()?;
+    let space = 8 + Promo::LEN;
+
+    invoke_signed(
+        &system_instruction::create_account(
+            owner.key,
+            promo_account.key,
+            rent.minimum_balance(space),
+            space as u64,
+            program_id,
+        ),
+        &[owner.clone(), promo_account.clone(), system_program.clone()],
+        &[&[seeds::PROMO, &[PDA_VERSION], &code_hash, &[promo_bump]]],
+    )?;
+
+    let mut promo_data = promo_account.try_borrow_mut_data()?;
+    promo_data[0..8].copy_from_slice(&discriminator::account("Promo"));
+
+    let promo = Promo {
+        code_hash,
+        discount,
+        remaining_redemptions: max_redemptions,
+        expiry,
+        bump: promo_bump,
+    };
+    promo.serialize(&mut &mut promo_data[8..])?;
+
+    msg!(
+        "Promo {} created: {}% discount, {} redemptions, expires at {}",
+        promo_account.key,
+        discount,
+        max_redemptions,
+        expiry
+    );
+    Ok(())
+}
+
+/// Redeem a promo code into the caller's fee discount
+fn process_redeem_promo(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    code: String,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let user = next_account_info(account_iter)?;
+    let promo_account = next_account_info(account_iter)?;
+    let fee_discount_account = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !user.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_system_program(system_program)?;
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    // Check if contract is paused
+    if mailer_state.is_paused(PAUSE_SEND) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    // Load the promo addressed by the code's hash
+    let code_hash = solana_program::hash::hash(code.as_bytes()).to_bytes();
+    let (promo_pda, _) = pda::promo(program_id, &code_hash);
+
+    if promo_account.key != &promo_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    if promo_account.owner != program_id || promo_account.data_len() < 8 + Promo::LEN {
+        return Err(MailerError::InvalidPromoCode.into());
+    }
+
+    let mut promo_data = promo_account.try_borrow_mut_data()?;
+    let mut promo: Promo = BorshDeserialize::deserialize(&mut &promo_data[8..])?;
+
+    if !promo.is_redeemable(Clock::get()?.unix_timestamp) {
+        return Err(MailerError::PromoExhausted.into());
+    }
+
+    let (discount_pda, discount_bump) = pda::fee_discount(program_id, user.key);
+
+    if fee_discount_account.key != &discount_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    // Create or raise the user's fee discount
+    if fee_discount_account.lamports() == 0 {
+        let rent = Rent::get()?;
+        let space = 8 + FeeDiscount::LEN;
+
+        invoke_signed(
+            &system_instruction::create_account(
+                user.key,
+                fee_discount_account.key,
+                rent.minimum_balance(space),
+                space as u64,
+                program_id,
+            ),
+            &[
+                user.clone(),
+                fee_discount_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                seeds::DISCOUNT,
+                &[PDA_VERSION],
+                user.key.as_ref(),
+                &[discount_bump],
+            ]],
+        )?;
+        track_rent_reserve(
+            mailer_account,
+            RentReserve::Discounts,
+            fee_discount_account.lamports(),
+        )?;
+
+        let mut discount_data = fee_discount_account.try_borrow_mut_data()?;
+        discount_data[0..8].copy_from_slice(&discriminator::account("FeeDiscount"));
+
+        let fee_discount = FeeDiscount {
+            account: *user.key,
+            discount: promo.discount,
+            bump: discount_bump,
+        };
+        fee_discount.serialize(&mut &mut discount_data[8..])?;
+    } else {
+        let mut discount_data = fee_discount_account.try_borrow_mut_data()?;
+        let mut fee_discount: FeeDiscount =
+            BorshDeserialize::deserialize(&mut &discount_data[8..])?;
+        if fee_discount.discount >= promo.discount {
+            return Err(MailerError::DiscountAlreadyApplied.into());
+        }
+        fee_discount.discount = promo.discount;
+        fee_discount.serialize(&mut &mut discount_data[8..])?;
+    }
+
+    promo.remaining_redemptions -= 1;
+    promo.serialize(&mut &mut promo_data[8..])?;
+
+    msg!(
+        "Promo {} redeemed by {}: {}% discount ({} redemptions left)",
+        promo_account.key,
+        user.key,
+        promo.discount,
+        promo.remaining_redemptions
+    );
+    Ok(())
+}
+
+/// Whitelist a fee mint, or update the fees of a whitelisted one (owner only)
+fn process_add_fee_mint(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    mint: Pubkey,
+    send_fee: u64,
+    delegation_fee: u64,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let fee_mint_account = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_system_program(system_program)?;
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+
+    // USDC fees are configured on the mailer state itself
+    if mint == mailer_state.usdc_mint {
+        return Err(MailerError::InvalidMint.into());
+    }
+
+    let (fee_mint_pda, fee_mint_bump) = pda::fee_mint(program_id, &mint);
+
+    if fee_mint_account.key != &fee_mint_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    // Whitelisting a mint offers a new way to pay; changing its fees is a fee change
+    if fee_mint_account.lamports() != 0 {
+        assert_fee_not_timelocked(&mailer_state)?;
+    }
+
+    let owner_claimable = if fee_mint_account.lamports() == 0 {
+        let rent = Rent::get()?;
+        let space = 8 + FeeMint::LEN;
+
+        invoke_signed(
+            &system_instruction::create_account(
+                owner.key,
+                fee_mint_account.key,
+                rent.minimum_balance(space),
+                space as u64,
+                program_id,
+            ),
+            &[
+                owner.clone(),
+                fee_mint_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                seeds::FEE_MINT,
+                &[PDA_VERSION],
+                mint.as_ref(),
+                &[fee_mint_bump],
+            ]],
+        )?;
+
+        fee_mint_account.try_borrow_mut_data()?[0..8]
+            .copy_from_slice(&discriminator::account("FeeMint"));
+        0
+    } else {
+        load_fee_mint(fee_mint_account)?.owner_claimable
+    };
+
+    let fee_mint = FeeMint {
+        mint,
+        send_fee,
+        delegation_fee,
+        owner_claimable,
+        bump: fee_mint_bump,
+    };
+    fee_mint.serialize(&mut &mut fee_mint_account.try_borrow_mut_data()?[8..])?;
+
+    msg!(
+        "Fee mint {} added: send fee {}, delegation fee {}",
+        mint,
+        send_fee,
+        delegation_fee
+    );
+    Ok(())
+}
+
+/// Remove a fee mint from the whitelist once its owner share is claimed (owner only)
+fn process_remove_fee_mint(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    mint: Pubkey,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let fee_mint_account = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+
+    let (fee_mint_pda, _) = pda::fee_mint(program_id, &mint);
+
+    if fee_mint_account.key != &fee_mint_pda || fee_mint_account.owner != program_id {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    if load_fee_mint(fee_mint_account)?.owner_claimable > 0 {
+        return Err(MailerError::AccountNotEmpty.into());
+    }
+
+    let lamports = close_account(fee_mint_account, owner)?;
+
+    msg!(
+        "Fee mint {} removed, {} lamports refunded to {}",
+        mint,
+        lamports,
+        owner.key
+    );
+    Ok(())
+}
+
+/// Set the lamport send fee, creating the lamport vault on first use (owner only)
+fn process_set_lamport_fee(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    lamport_fee: u64,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let vault_account = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_system_program(system_program)?;
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+    assert_fee_not_timelocked(&mailer_state)?;
+
+    let (vault_pda, vault_bump) = pda::lamport_vault(program_id);
+
+    if vault_account.key != &vault_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    // The vault holds no data; its rent-exempt minimum stays behind every owner claim
+    if vault_account.lamports() == 0 {
+        invoke_signed(
+            &system_instruction::create_account(
+                owner.key,
+                vault_account.key,
+                Rent::get()?.minimum_balance(0),
+                0,
+                program_id,
+            ),
+            &[owner.clone(), vault_account.clone(), system_program.clone()],
+            &[&[seeds::LAMPORT_VAULT, &[PDA_VERSION], &[vault_bump]]],
+        )?;
+    }
+
+    let old_fee = mailer_state.lamport_send_fee;
+    mailer_state.lamport_send_fee = lamport_fee;
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+
+    msg!("Lamport fee updated from {} to {}", old_fee, lamport_fee);
+    Ok(())
+}
+
+/// Claim the owner's lamport fees from the lamport vault
+fn process_claim_owner_lamports(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let vault_account = next_account_info(account_iter)?;
+    let owner_wallet = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    if mailer_state.is_paused(PAUSE_CLAIM) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+
+    let (vault_pda, _) = pda::lamport_vault(program_id);
+
+    if vault_account.key != &vault_pda || vault_account.owner != program_id {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    if owner_wallet.key != &mailer_state.owner {
+        return Err(MailerError::OnlyOwner.into());
+    }
+
+    if mailer_state.owner_claimable_lamports == 0 {
+        return Err(MailerError::NoClaimableAmount.into());
+    }
+
+    let amount = mailer_state.owner_claimable_lamports;
+    mailer_state.owner_claimable_lamports = 0;
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+    drop(mailer_data);
+
+    // The program owns the vault, so it moves the lamports without a system transfer
+    let vault_balance = vault_account
+        .lamports()
+        .checked_sub(amount)
+        .ok_or(MailerError::MathOverflow)?;
+    let owner_balance = owner_wallet
+        .lamports()
+        .checked_add(amount)
+        .ok_or(MailerError::MathOverflow)?;
+    **vault_account.try_borrow_mut_lamports()? = vault_balance;
+    **owner_wallet.try_borrow_mut_lamports()? = owner_balance;
+
+    msg!("Owner {} claimed {} lamports", owner_wallet.key, amount);
+    Ok(())
+}
+
+/// Peg the lamport send fee to a Pyth price account, or clear the peg (owner only)
+fn process_set_fee_oracle(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    oracle: Option<Pubkey>,
+    target_fee: u64,
+    max_age: i64,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    // A peg needs a fee to target and room for the oracle to publish
+    if oracle.is_some() && (target_fee == 0 || max_age <= 0) {
+        return Err(MailerError::InvalidFeePeg.into());
+    }
+
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+    assert_fee_not_timelocked(&mailer_state)?;
+
+    mailer_state.fee_oracle = oracle;
+    mailer_state.fee_oracle_target = if oracle.is_some() { target_fee } else { 0 };
+    mailer_state.fee_oracle_max_age = if oracle.is_some() { max_age } else { 0 };
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+
+    msg!(
+        "Fee oracle set to {:?} (target: {} micro-USD, max age: {}s)",
+        oracle,
+        mailer_state.fee_oracle_target,
+        mailer_state.fee_oracle_max_age
+    );
+    Ok(())
+}
+
+/// Set when priority sends create recipient claim accounts (owner only)
+fn process_set_claim_creation_policy(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    policy: ClaimCreationPolicy,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+
+    mailer_state.claim_creation_policy = policy;
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+
+    msg!("Claim creation policy set to {:?}", policy);
+    Ok(())
+}
+
+/// Close a never-credited claim and its rent payer record, refunding the recorded payer
+fn process_close_funded_claim(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let payer = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let recipient_claim = next_account_info(account_iter)?;
+    let record_account = next_account_info(account_iter)?;
+    let closure_log = next_account_info(account_iter)?;
+
+    if !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    if mailer_state.is_paused(PAUSE_ADMIN) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    let (record_pda, _) = pda::claim_rent_payer(program_id, recipient_claim.key);
+
+    if record_account.key != &record_pda || record_account.owner != program_id {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    let record_data = record_account.try_borrow_data()?;
+    let record: ClaimRentPayer = BorshDeserialize::deserialize(&mut &record_data[8..])?;
+    drop(record_data);
+
+    if record.payer != *payer.key {
+        return Err(MailerError::NotClaimRentPayer.into());
+    }
+
+    // The owner may already have closed the claim; only the record is left then
+    let mut claim_refund = 0;
+    if recipient_claim.lamports() > 0 {
+        if recipient_claim.owner != program_id {
+            return Err(MailerError::InvalidAccountOwner.into());
+        }
+
+        let claim_data = recipient_claim.try_borrow_data()?;
+        let claim_state = RecipientClaim::from_account_data(&claim_data[8..])?;
+        drop(claim_data);
+
+        // A claim credited since its creation is in use by the recipient
+        if claim_state.amount > 0 || claim_state.timestamp != 0 {
+            return Err(MailerError::AccountNotEmpty.into());
+        }
+
+        record_closure(program_id, closure_log, recipient_claim)?;
+        claim_refund = close_account(recipient_claim, payer)?;
+        release_rent_reserve(mailer_account, RentReserve::Claims, claim_refund)?;
+    }
+
+    let record_refund = close_account(record_account, payer)?;
+
+    msg!(
+        "Funded claim {} closed, {} lamports refunded to {}",
+        recipient_claim.key,
+        claim_refund + record_refund,
+        payer.key
+    );
+    Ok(())
+}
+
+/// Send an encrypted envelope to a wallet, charged and logged like a Send without a subject
+#[allow(clippy::too_many_arguments)]
+fn process_send_encrypted(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    to: Pubkey,
+    ephemeral_key: [u8; 32],
+    nonce: [u8; 12],
+    ciphertext: Vec<u8>,
+    revenue_share_to_receiver: bool,
+    strict: bool,
+) -> ProgramResult {
+    if !crypto::is_well_formed(&ephemeral_key, &ciphertext) {
+        return Err(MailerError::InvalidEnvelope.into());
+    }
+
+    process_send(
+        program_id,
+        accounts,
+        to,
+        String::new(),
+        String::new(),
+        revenue_share_to_receiver,
+        false,
+        false,
+        None,
+        None,
+        strict,
+        None,
+        None,
+        false,
+        0,
+        false,
+        None,
+    )?;
+
+    let sender = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    msg!(
+        "{}EncryptedMail {{ sender: {}, to: {}, ephemeral key: {}, ciphertext: {} bytes, digest: {} }}",
+        recipient_log_prefix(&to),
+        sender.key,
+        to,
+        Hash::new_from_array(ephemeral_key),
+        ciphertext.len(),
+        Hash::new_from_array(crypto::envelope_digest(&ephemeral_key, &nonce, &ciphertext))
+    );
+    Ok(())
+}
+
+/// Register a webhook id to the signer
+fn process_register_webhook(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    webhook_id: String,
+    endpoint_hash: [u8; 32],
+    surcharge: u64,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let webhook_account = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_system_program(system_program)?;
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    // Reject payloads over the owner's size limits
+    assert_payload_len(&webhook_id, mailer_state.payload_limits.max_webhook_id_len)?;
+
+    // Check if contract is paused
+    if mailer_state.is_paused(PAUSE_SEND) {
+        return Err(MailerError::ContractPaused.into());
+    }
 
-    /// Claim recipient share
-    /// TIMESTAMP DEPENDENCY: Uses Clock::get()?.unix_timestamp for expiration checks (60 days).
-    /// Validators can manipulate timestamps by ±30 seconds or more. Claims near the deadline
-    /// have a small risk of denial. Recommended: Claim well before the 60-day deadline.
-    /// Accounts:
-    /// 0. `[signer]` Recipient
-    /// 1. `[writable]` Recipient claim account (PDA)
-    /// 2. `[]` Mailer state account (PDA)
-    /// 3. `[writable]` Recipient USDC account
-    /// 4. `[writable]` Mailer USDC account
-    /// 5. `[]` Token program
-    ClaimRecipientShare,
+    if webhook_id.is_empty() {
+        return Err(MailerError::EmptyWebhookId.into());
+    }
 
-    /// Claim owner share
-    /// Accounts:
-    /// 0. `[signer]` Owner
-    /// 1. `[writable]` Mailer state account (PDA)
-    /// 2. `[writable]` Owner USDC account
-    /// 3. `[writable]` Mailer USDC account
-    /// 4. `[]` Token program
-    ClaimOwnerShare,
+    let id_hash = solana_program::hash::hash(webhook_id.as_bytes()).to_bytes();
+    let (webhook_pda, webhook_bump) = pda::webhook(program_id, &id_hash);
 
-    /// Set send fee (owner only)
-    /// WARNING: Fee changes take effect IMMEDIATELY with no time delay or notification.
-    /// This allows quick response to market conditions but requires user trust.
-    ///   - No maximum fee cap enforced
-    ///   - Users with pending transactions may pay different fees than expected
-    ///   - Monitor program logs for FeeUpdated events
-    ///
-    /// Accounts:
-    /// 0. `[signer]` Owner
-    /// 1. `[writable]` Mailer state account (PDA)
-    SetFee { new_fee: u64 },
+    if webhook_account.key != &webhook_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
 
-    /// Delegate to another address
-    /// WARNING: Delegation fee is NON-REFUNDABLE, even if the delegate rejects the delegation.
-    /// The fee is an anti-spam measure and goes to the contract owner regardless of delegation outcome.
-    /// Accounts:
-    /// 0. `[signer]` Delegator
-    /// 1. `[writable]` Delegation account (PDA)
-    /// 2. `[]` Mailer state account (PDA)
-    /// 3. `[writable]` Delegator USDC account
-    /// 4. `[writable]` Mailer USDC account
-    /// 5. `[]` Token program
-    /// 6. `[]` System program
-    DelegateTo { delegate: Option<Pubkey> },
+    if webhook_account.lamports() > 0 {
+        return Err(MailerError::AlreadyInitialized.into());
+    }
 
-    /// Reject delegation
-    /// NOTE: Rejecting a delegation does NOT refund the delegation fee paid by the delegator.
-    /// The fee is an anti-spam measure and is non-refundable by design.
-    /// Accounts:
-    /// 0. `[signer]` Rejector
-    /// 1. `[writable]` Delegation account (PDA)
-    /// 2. `[]` Mailer state account (PDA)
-    RejectDelegation,
+    let rent = Rent::get()?;
+    let space = 8 + Webhook::LEN;
 
-    /// Set delegation fee (owner only)
-    /// WARNING: Fee changes take effect IMMEDIATELY with no time delay.
-    /// See SetFee instruction for detailed implications of instant fee changes.
-    /// Accounts:
-    /// 0. `[signer]` Owner
-    /// 1. `[writable]` Mailer state account (PDA)
-    SetDelegationFee { new_fee: u64 },
+    invoke_signed(
+        &system_instruction::create_account(
+            owner.key,
+            webhook_account.key,
+            rent.minimum_balance(space),
+            space as u64,
+            program_id,
+        ),
+        &[
+            owner.clone(),
+            webhook_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[seeds::WEBHOOK, &[PDA_VERSION], &id_hash, &[webhook_bump]]],
+    )?;
 
-    /// Set custom fee percentage for a specific address (owner only)
-    /// Accounts:
-    /// 0. `[signer]` Owner
-    /// 1. `[]` Mailer state account (PDA)
-    /// 2. `[writable]` Fee discount account (PDA)
-    /// 3. `[]` Account to set custom fee for
-    /// 4. `[signer]` Payer for account creation
-    /// 5. `[]` System program
-    SetCustomFeePercentage {
-        account: Pubkey,
-        percentage: u8, // 0-100: 0 = free, 100 = full fee
-    },
+    let mut webhook_data = webhook_account.try_borrow_mut_data()?;
+    webhook_data[0..8].copy_from_slice(&discriminator::account("Webhook"));
 
-    /// Clear custom fee percentage for a specific address (owner only)
-    /// Accounts:
-    /// 0. `[signer]` Owner
-    /// 1. `[]` Mailer state account (PDA)
-    /// 2. `[writable]` Fee discount account (PDA)
-    ClearCustomFeePercentage { account: Pubkey },
+    let webhook = Webhook {
+        owner: *owner.key,
+        id_hash,
+        endpoint_hash,
+        active: true,
+        surcharge,
+        claimable: 0,
+        bump: webhook_bump,
+        sends_routed: 0,
+        statuses_recorded: 0,
+        deliveries_failed: 0,
+        total_ack_latency_slots: 0,
+    };
+    webhook.serialize(&mut &mut webhook_data[8..])?;
 
-    /// Pause the contract (owner only)
-    /// Accounts:
-    /// 0. `[signer]` Owner
-    /// 1. `[writable]` Mailer state account (PDA)
-    /// 2. `[writable]` Owner USDC account
-    /// 3. `[writable]` Mailer USDC account  
-    /// 4. `[]` Token program
-    Pause,
+    msg!(
+        "Webhook {} registered by {} (webhookId: {}, surcharge: {})",
+        webhook_account.key,
+        owner.key,
+        webhook_id,
+        surcharge
+    );
+    Ok(())
+}
 
-    /// Unpause the contract (owner only)
-    /// Accounts:
-    /// 0. `[signer]` Owner
-    /// 1. `[writable]` Mailer state account (PDA)
-    Unpause,
+/// Update a webhook's endpoint and surcharge and reactivate it, or revoke it when `update`
+/// is `None`
+fn process_update_webhook(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    update: Option<([u8; 32], u64)>,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let webhook_account = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if webhook_account.owner != program_id || webhook_account.data_len() < 8 + Webhook::LEN {
+        return Err(MailerError::InvalidWebhook.into());
+    }
+
+    let mut webhook_data = webhook_account.try_borrow_mut_data()?;
+    let mut webhook: Webhook = BorshDeserialize::deserialize(&mut &webhook_data[8..])?;
+
+    if webhook_account.key != &pda::webhook(program_id, &webhook.id_hash).0 {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    if webhook.owner != *owner.key {
+        return Err(MailerError::InvalidWebhook.into());
+    }
+
+    match update {
+        Some((endpoint_hash, surcharge)) => {
+            webhook.endpoint_hash = endpoint_hash;
+            webhook.surcharge = surcharge;
+            webhook.active = true;
+            webhook.serialize(&mut &mut webhook_data[8..])?;
+            msg!(
+                "Webhook {} updated by {} (surcharge: {})",
+                webhook_account.key,
+                owner.key,
+                surcharge
+            );
+        }
+        None => {
+            webhook.active = false;
+            webhook.serialize(&mut &mut webhook_data[8..])?;
+            msg!("Webhook {} revoked by {}", webhook_account.key, owner.key);
+        }
+    }
+    Ok(())
+}
+
+/// Pay a webhook's accrued surcharges to its owner
+fn process_claim_webhook_revenue(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let webhook_account = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let owner_usdc = next_account_info(account_iter)?;
+    let mailer_usdc = next_account_info(account_iter)?;
+    let token_program = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (mailer_pda, _) = assert_mailer_account(program_id, accounts, mailer_account)?;
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    if mailer_state.is_paused(PAUSE_CLAIM) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    assert_not_sanctioned(program_id, &mailer_state, owner.key, accounts)?;
+
+    if webhook_account.owner != program_id || webhook_account.data_len() < 8 + Webhook::LEN {
+        return Err(MailerError::InvalidWebhook.into());
+    }
+
+    let mut webhook_data = webhook_account.try_borrow_mut_data()?;
+    let mut webhook: Webhook = BorshDeserialize::deserialize(&mut &webhook_data[8..])?;
+
+    if webhook_account.key != &pda::webhook(program_id, &webhook.id_hash).0 {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    if webhook.owner != *owner.key {
+        return Err(MailerError::InvalidWebhook.into());
+    }
+
+    if webhook.claimable == 0 {
+        return Err(MailerError::NoClaimableAmount.into());
+    }
+
+    let amount = webhook.claimable;
+    webhook.claimable = 0;
+    webhook.serialize(&mut &mut webhook_data[8..])?;
+    drop(webhook_data);
+
+    assert_token_program(token_program)?;
+    assert_token_account(
+        accounts,
+        "owner_usdc",
+        owner_usdc,
+        owner.key,
+        &mailer_state.usdc_mint,
+    )?;
+    assert_token_account(
+        accounts,
+        "mailer_usdc",
+        mailer_usdc,
+        &mailer_pda,
+        &mailer_state.usdc_mint,
+    )?;
+
+    invoke_signed(
+        &spl_token::instruction::transfer(
+            token_program.key,
+            mailer_usdc.key,
+            owner_usdc.key,
+            mailer_account.key,
+            &[],
+            amount,
+        )?,
+        &[
+            mailer_usdc.clone(),
+            owner_usdc.clone(),
+            mailer_account.clone(),
+            token_program.clone(),
+        ],
+        &[&[seeds::MAILER, &[mailer_state.bump]]],
+    )?;
+
+    msg!(
+        "Webhook owner {} claimed {} from {}",
+        owner.key,
+        amount,
+        webhook_account.key
+    );
+
+    record_claim_stats(program_id, &mailer_state, accounts, amount)?;
+    Ok(())
+}
+
+/// Record the delivery status of a routed send and its ack latency
+fn process_record_delivery_status(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    to: Pubkey,
+    nonce: u64,
+    sent_slot: u64,
+    delivered: bool,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let webhook_account = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if webhook_account.owner != program_id || webhook_account.data_len() < 8 + Webhook::LEN {
+        return Err(MailerError::InvalidWebhook.into());
+    }
+
+    let mut webhook_data = webhook_account.try_borrow_mut_data()?;
+    let mut webhook: Webhook = BorshDeserialize::deserialize(&mut &webhook_data[8..])?;
+
+    if webhook_account.key != &pda::webhook(program_id, &webhook.id_hash).0 {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    if webhook.owner != *owner.key {
+        return Err(MailerError::InvalidWebhook.into());
+    }
+
+    if webhook.statuses_recorded >= webhook.sends_routed {
+        return Err(MailerError::NoPendingDelivery.into());
+    }
+
+    let slot = Clock::get()?.slot;
+    let latency = slot
+        .checked_sub(sent_slot)
+        .ok_or(MailerError::InvalidDeliverySlot)?;
+
+    webhook.statuses_recorded += 1;
+    if !delivered {
+        webhook.deliveries_failed += 1;
+    }
+    webhook.total_ack_latency_slots = webhook
+        .total_ack_latency_slots
+        .checked_add(latency)
+        .ok_or(MailerError::MathOverflow)?;
+    webhook.serialize(&mut &mut webhook_data[8..])?;
+
+    // Webhook sends are made by the webhook owner, with the webhook id as their payload
+    let message_id = derive_message_id(owner.key, to.as_ref(), &webhook.id_hash, sent_slot, nonce);
+    msg!(
+        "DeliveryStatus {{ webhook: {}, message id: {}, delivered: {}, latency slots: {} }}",
+        webhook_account.key,
+        Hash::new_from_array(message_id),
+        delivered,
+        latency
+    );
+    Ok(())
+}
+
+/// Return a webhook's SLA counters as return data
+fn process_get_webhook_stats(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let webhook_account = next_account_info(account_iter)?;
+
+    if webhook_account.owner != program_id || webhook_account.data_len() < 8 + Webhook::LEN {
+        return Err(MailerError::InvalidWebhook.into());
+    }
+
+    let webhook_data = webhook_account.try_borrow_data()?;
+    let webhook: Webhook = BorshDeserialize::deserialize(&mut &webhook_data[8..])?;
+    drop(webhook_data);
+
+    if webhook_account.key != &pda::webhook(program_id, &webhook.id_hash).0 {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    let stats = webhook.stats();
+    set_return_data(&borsh::to_vec(&stats)?);
+
+    msg!(
+        "Webhook {} stats: {} routed, {} statuses, {} failed, {} slots average ack latency",
+        webhook_account.key,
+        stats.sends_routed,
+        stats.statuses_recorded,
+        stats.deliveries_failed,
+        stats.average_ack_latency_slots
+    );
+    Ok(())
+}
+
+/// Deposit USDC into a sender's prepaid send credits
+fn process_deposit_send_credits(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let sender = next_account_info(account_iter)?;
+    let credits_account = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let sender_usdc = next_account_info(account_iter)?;
+    let mailer_usdc = next_account_info(account_iter)?;
+    let token_program = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !sender.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_system_program(system_program)?;
+
+    let (mailer_pda, _) = assert_mailer_account(program_id, accounts, mailer_account)?;
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    if mailer_state.is_paused(PAUSE_SEND) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    assert_not_sanctioned(program_id, &mailer_state, sender.key, accounts)?;
+
+    if amount == 0 {
+        return Err(MailerError::ZeroAmount.into());
+    }
+
+    assert_token_program(token_program)?;
+    assert_token_account(
+        accounts,
+        "sender_usdc",
+        sender_usdc,
+        sender.key,
+        &mailer_state.usdc_mint,
+    )?;
+    assert_token_account(
+        accounts,
+        "mailer_usdc",
+        mailer_usdc,
+        &mailer_pda,
+        &mailer_state.usdc_mint,
+    )?;
 
-    /// Distribute claimable funds (when paused)
-    /// Accounts:
-    /// 0. `[signer]` Anyone can call
-    /// 1. `[]` Mailer state account (PDA)
-    /// 2. `[writable]` Recipient claim account (PDA)
-    /// 3. `[writable]` Recipient USDC account
-    /// 4. `[writable]` Mailer USDC account
-    /// 5. `[]` Token program
-    DistributeClaimableFunds { recipient: Pubkey },
+    let (credits_pda, credits_bump) = pda::send_credits(program_id, sender.key);
+    if credits_account.key != &credits_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
 
-    /// Claim expired recipient shares (owner only)
-    /// Accounts:
-    /// 0. `[signer]` Owner
-    /// 1. `[writable]` Mailer state account (PDA)
-    /// 2. `[writable]` Recipient claim account (PDA)
-    ClaimExpiredShares { recipient: Pubkey },
+    if credits_account.lamports() == 0 {
+        let rent = Rent::get()?;
+        let space = 8 + SendCredits::LEN;
 
-    /// Emergency unpause without fund distribution (owner only)
-    /// Accounts:
-    /// 0. `[signer]` Owner
-    /// 1. `[writable]` Mailer state account (PDA)
-    EmergencyUnpause,
+        invoke_signed(
+            &system_instruction::create_account(
+                sender.key,
+                credits_account.key,
+                rent.minimum_balance(space),
+                space as u64,
+                program_id,
+            ),
+            &[
+                sender.clone(),
+                credits_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                seeds::SEND_CREDITS,
+                &[PDA_VERSION],
+                sender.key.as_ref(),
+                &[credits_bump],
+            ]],
+        )?;
 
-    /// Toggle fee collection on or off (owner only)
-    /// Accounts:
-    /// 0. `[signer]` Owner
-    /// 1. `[writable]` Mailer state account (PDA)
-    SetFeePaused { fee_paused: bool },
-}
+        let mut credits_data = credits_account.try_borrow_mut_data()?;
+        credits_data[0..8].copy_from_slice(&discriminator::account("SendCredits"));
 
-/// Custom program errors
-#[derive(Error, Debug, Copy, Clone)]
-pub enum MailerError {
-    #[error("Only the owner can perform this action")]
-    OnlyOwner,
-    #[error("No claimable amount available")]
-    NoClaimableAmount,
-    #[error("Claim period has expired")]
-    ClaimPeriodExpired,
-    #[error("Claim period has not expired yet")]
-    ClaimPeriodNotExpired,
-    #[error("Invalid recipient")]
-    InvalidRecipient,
-    #[error("No delegation to reject")]
-    NoDelegationToReject,
-    #[error("Invalid delegator")]
-    InvalidDelegator,
-    #[error("Account already initialized")]
-    AlreadyInitialized,
-    #[error("Account not initialized")]
-    NotInitialized,
-    #[error("Invalid PDA")]
-    InvalidPDA,
-    #[error("Invalid account owner")]
-    InvalidAccountOwner,
-    #[error("Invalid token mint")]
-    InvalidMint,
-    #[error("Invalid token program")]
-    InvalidTokenProgram,
-    #[error("Contract is paused")]
-    ContractPaused,
-    #[error("Contract is not paused")]
-    ContractNotPaused,
-    #[error("Invalid percentage (must be 0-100)")]
-    InvalidPercentage,
-    #[error("Math overflow")]
-    MathOverflow,
-}
-
-impl From<MailerError> for ProgramError {
-    fn from(e: MailerError) -> Self {
-        ProgramError::Custom(e as u32)
+        let credits = SendCredits {
+            sender: *sender.key,
+            balance: 0,
+            bump: credits_bump,
+        };
+        credits.serialize(&mut &mut credits_data[8..])?;
+    } else if credits_account.owner != program_id {
+        return Err(MailerError::InvalidPDA.into());
     }
+
+    let received = transfer_to_vault(
+        token_program,
+        sender_usdc,
+        mailer_usdc,
+        sender,
+        accounts,
+        amount,
+    )?;
+
+    let mut credits_data = credits_account.try_borrow_mut_data()?;
+    let mut credits: SendCredits = BorshDeserialize::deserialize(&mut &credits_data[8..])?;
+    credits.balance = credits
+        .balance
+        .checked_add(received)
+        .ok_or(MailerError::MathOverflow)?;
+    credits.serialize(&mut &mut credits_data[8..])?;
+
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    mailer_state.credit_liabilities = mailer_state
+        .credit_liabilities
+        .checked_add(received)
+        .ok_or(MailerError::MathOverflow)?;
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+
+    msg!(
+        "Send credits of {} topped up by {} to {}",
+        sender.key,
+        received,
+        credits.balance
+    );
+    Ok(())
 }
 
-/// Main instruction processor
-pub fn process_instruction(
+/// Withdraw unspent send credits back to the sender
+fn process_withdraw_send_credits(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
-    instruction_data: &[u8],
+    amount: Option<u64>,
 ) -> ProgramResult {
-    let instruction = MailerInstruction::try_from_slice(instruction_data)?;
+    let account_iter = &mut accounts.iter();
+    let sender = next_account_info(account_iter)?;
+    let credits_account = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let sender_usdc = next_account_info(account_iter)?;
+    let mailer_usdc = next_account_info(account_iter)?;
+    let token_program = next_account_info(account_iter)?;
 
-    match instruction {
-        MailerInstruction::Initialize { usdc_mint } => {
-            process_initialize(program_id, accounts, usdc_mint)
-        }
-        MailerInstruction::Send {
-            to,
-            subject,
-            _body,
-            revenue_share_to_receiver,
-            resolve_sender_to_name,
-        } => process_send(
-            program_id,
-            accounts,
-            to,
-            subject,
-            _body,
-            revenue_share_to_receiver,
-            resolve_sender_to_name,
-        ),
-        MailerInstruction::SendPrepared {
-            to,
-            mail_id,
-            revenue_share_to_receiver,
-            resolve_sender_to_name,
-        } => process_send_prepared(
-            program_id,
-            accounts,
-            to,
-            mail_id,
-            revenue_share_to_receiver,
-            resolve_sender_to_name,
-        ),
-        MailerInstruction::SendToEmail {
-            to_email,
-            subject,
-            _body,
-        } => process_send_to_email(program_id, accounts, to_email, subject, _body),
-        MailerInstruction::SendPreparedToEmail { to_email, mail_id } => {
-            process_send_prepared_to_email(program_id, accounts, to_email, mail_id)
-        }
-        MailerInstruction::SendThroughWebhook {
-            to,
-            webhook_id,
-            revenue_share_to_receiver,
-            resolve_sender_to_name,
-        } => process_send_through_webhook(
-            program_id,
-            accounts,
-            to,
-            webhook_id,
-            revenue_share_to_receiver,
-            resolve_sender_to_name,
-        ),
-        MailerInstruction::ClaimRecipientShare => {
-            process_claim_recipient_share(program_id, accounts)
-        }
-        MailerInstruction::ClaimOwnerShare => process_claim_owner_share(program_id, accounts),
-        MailerInstruction::SetFee { new_fee } => process_set_fee(program_id, accounts, new_fee),
-        MailerInstruction::DelegateTo { delegate } => {
-            process_delegate_to(program_id, accounts, delegate)
-        }
-        MailerInstruction::RejectDelegation => process_reject_delegation(program_id, accounts),
-        MailerInstruction::SetDelegationFee { new_fee } => {
-            process_set_delegation_fee(program_id, accounts, new_fee)
-        }
-        MailerInstruction::SetCustomFeePercentage {
-            account,
-            percentage,
-        } => process_set_custom_fee_percentage(program_id, accounts, account, percentage),
-        MailerInstruction::ClearCustomFeePercentage { account } => {
-            process_clear_custom_fee_percentage(program_id, accounts, account)
-        }
-        MailerInstruction::Pause => process_pause(program_id, accounts),
-        MailerInstruction::Unpause => process_unpause(program_id, accounts),
-        MailerInstruction::DistributeClaimableFunds { recipient } => {
-            process_distribute_claimable_funds(program_id, accounts, recipient)
-        }
-        MailerInstruction::ClaimExpiredShares { recipient } => {
-            process_claim_expired_shares(program_id, accounts, recipient)
-        }
-        MailerInstruction::EmergencyUnpause => process_emergency_unpause(program_id, accounts),
-        MailerInstruction::SetFeePaused { fee_paused } => {
-            process_set_fee_paused(program_id, accounts, fee_paused)
-        }
+    if !sender.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (mailer_pda, _) = assert_mailer_account(program_id, accounts, mailer_account)?;
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    if mailer_state.is_paused(PAUSE_CLAIM) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    let (credits_pda, _) = pda::send_credits(program_id, sender.key);
+    if credits_account.key != &credits_pda || credits_account.owner != program_id {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    let mut credits_data = credits_account.try_borrow_mut_data()?;
+    let mut credits: SendCredits = BorshDeserialize::deserialize(&mut &credits_data[8..])?;
+
+    if credits.balance == 0 {
+        return Err(MailerError::NoClaimableAmount.into());
+    }
+
+    let amount = amount.unwrap_or(credits.balance);
+    if amount == 0 || amount > credits.balance {
+        return Err(MailerError::InvalidClaimAmount.into());
     }
+
+    credits.balance -= amount;
+    credits.serialize(&mut &mut credits_data[8..])?;
+    drop(credits_data);
+
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    mailer_state.release_credit_liability(amount);
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+    drop(mailer_data);
+
+    assert_token_program(token_program)?;
+    assert_token_account(
+        accounts,
+        "sender_usdc",
+        sender_usdc,
+        sender.key,
+        &mailer_state.usdc_mint,
+    )?;
+    assert_token_account(
+        accounts,
+        "mailer_usdc",
+        mailer_usdc,
+        &mailer_pda,
+        &mailer_state.usdc_mint,
+    )?;
+
+    invoke_signed(
+        &spl_token::instruction::transfer(
+            token_program.key,
+            mailer_usdc.key,
+            sender_usdc.key,
+            mailer_account.key,
+            &[],
+            amount,
+        )?,
+        &[
+            mailer_usdc.clone(),
+            sender_usdc.clone(),
+            mailer_account.clone(),
+            token_program.clone(),
+        ],
+        &[&[seeds::MAILER, &[mailer_state.bump]]],
+    )?;
+
+    msg!(
+        "Send credits of {} withdrawn: {}, {} left",
+        sender.key,
+        amount,
+        credits.balance
+    );
+    Ok(())
 }
 
-/// Initialize the program
-fn process_initialize(
+/// Create or update a subscription plan (owner only)
+#[allow(clippy::too_many_arguments)]
+fn process_set_plan(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
-    usdc_mint: Pubkey,
+    plan_id: u64,
+    price: u64,
+    duration: i64,
+    max_messages: u64,
+    active: bool,
 ) -> ProgramResult {
     let account_iter = &mut accounts.iter();
     let owner = next_account_info(account_iter)?;
     let mailer_account = next_account_info(account_iter)?;
+    let plan_account = next_account_info(account_iter)?;
     let system_program = next_account_info(account_iter)?;
 
     if !owner.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    // Verify mailer account PDA
-    let (mailer_pda, bump) = Pubkey::find_program_address(&[b"mailer"], program_id);
-    if mailer_account.key != &mailer_pda {
+    assert_system_program(system_program)?;
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+
+    if duration <= 0 || max_messages == 0 {
+        return Err(MailerError::InvalidPlan.into());
+    }
+
+    let (plan_pda, plan_bump) = pda::plan(program_id, plan_id);
+    if plan_account.key != &plan_pda {
         return Err(MailerError::InvalidPDA.into());
     }
 
-    // Create mailer account
-    let rent = Rent::get()?;
-    let space = 8 + MailerState::LEN; // 8 bytes for discriminator
-    let lamports = rent.minimum_balance(space);
+    if plan_account.lamports() == 0 {
+        let rent = Rent::get()?;
+        let space = 8 + Plan::LEN;
 
-    invoke_signed(
-        &system_instruction::create_account(
-            owner.key,
-            mailer_account.key,
-            lamports,
-            space as u64,
-            program_id,
-        ),
-        &[
-            owner.clone(),
-            mailer_account.clone(),
-            system_program.clone(),
-        ],
-        &[&[b"mailer", &[bump]]],
-    )?;
+        invoke_signed(
+            &system_instruction::create_account(
+                owner.key,
+                plan_account.key,
+                rent.minimum_balance(space),
+                space as u64,
+                program_id,
+            ),
+            &[owner.clone(), plan_account.clone(), system_program.clone()],
+            &[&[
+                seeds::PLAN,
+                &[PDA_VERSION],
+                &plan_id.to_le_bytes(),
+                &[plan_bump],
+            ]],
+        )?;
 
-    // Initialize state
-    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
-    mailer_data[0..8].copy_from_slice(&hash_discriminator("account:MailerState").to_le_bytes());
+        plan_account.try_borrow_mut_data()?[0..8].copy_from_slice(&discriminator::account("Plan"));
+    } else if plan_account.owner != program_id {
+        return Err(MailerError::InvalidPDA.into());
+    }
 
-    let mailer_state = MailerState {
-        owner: *owner.key,
-        usdc_mint,
-        send_fee: SEND_FEE,
-        delegation_fee: DELEGATION_FEE,
-        owner_claimable: 0,
-        paused: false,
-        fee_paused: false,
-        bump,
+    let plan = Plan {
+        plan_id,
+        price,
+        duration,
+        max_messages,
+        active,
+        bump: plan_bump,
     };
+    plan.serialize(&mut &mut plan_account.try_borrow_mut_data()?[8..])?;
 
-    mailer_state.serialize(&mut &mut mailer_data[8..])?;
-
-    msg!("Mailer initialized with owner: {}", owner.key);
+    msg!(
+        "Plan {} set: price {}, duration {}, max messages {}, active: {}",
+        plan_id,
+        price,
+        duration,
+        max_messages,
+        active
+    );
     Ok(())
 }
 
-/// Send message with optional revenue sharing
-fn process_send(
-    program_id: &Pubkey,
-    accounts: &[AccountInfo],
-    to: Pubkey,
-    subject: String,
-    _body: String,
-    revenue_share_to_receiver: bool,
-    _resolve_sender_to_name: bool,
-) -> ProgramResult {
+/// Load an active plan, checking its account is the PDA for `plan_id`
+fn load_active_plan(
+    program_id: &Pubkey,
+    plan_account: &AccountInfo,
+    plan_id: u64,
+) -> Result<Plan, ProgramError> {
+    let (plan_pda, _) = pda::plan(program_id, plan_id);
+    if plan_account.key != &plan_pda || plan_account.owner != program_id {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    let plan_data = plan_account.try_borrow_data()?;
+    let plan: Plan = BorshDeserialize::deserialize(&mut &plan_data[8..])?;
+    if !plan.active {
+        return Err(MailerError::PlanInactive.into());
+    }
+    Ok(plan)
+}
+
+/// Pay a plan's price into the mailer vault, crediting the owner with the amount received
+fn pay_plan_price<'a>(
+    mailer_account: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    subscriber_usdc: &AccountInfo<'a>,
+    mailer_usdc: &AccountInfo<'a>,
+    subscriber: &AccountInfo<'a>,
+    accounts: &[AccountInfo<'a>],
+    plan: &Plan,
+) -> Result<u64, ProgramError> {
+    if plan.price == 0 {
+        return Ok(0);
+    }
+    let received = transfer_to_vault(
+        token_program,
+        subscriber_usdc,
+        mailer_usdc,
+        subscriber,
+        accounts,
+        plan.price,
+    )?;
+    credit_owner(mailer_account, None, received)?;
+    Ok(received)
+}
+
+/// Subscribe to a plan for one period from now
+fn process_subscribe(program_id: &Pubkey, accounts: &[AccountInfo], plan_id: u64) -> ProgramResult {
     let account_iter = &mut accounts.iter();
-    let sender = next_account_info(account_iter)?;
-    let recipient_claim = next_account_info(account_iter)?;
+    let subscriber = next_account_info(account_iter)?;
+    let plan_account = next_account_info(account_iter)?;
+    let subscription_account = next_account_info(account_iter)?;
     let mailer_account = next_account_info(account_iter)?;
-    let sender_usdc = next_account_info(account_iter)?;
+    let subscriber_usdc = next_account_info(account_iter)?;
     let mailer_usdc = next_account_info(account_iter)?;
     let token_program = next_account_info(account_iter)?;
     let system_program = next_account_info(account_iter)?;
 
-    if !sender.is_signer {
+    if !subscriber.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    // Load mailer state
-    let (mailer_pda, _) = assert_mailer_account(program_id, mailer_account)?;
+    assert_system_program(system_program)?;
+
+    let (mailer_pda, _) = assert_mailer_account(program_id, accounts, mailer_account)?;
     let mailer_data = mailer_account.try_borrow_data()?;
     let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
     drop(mailer_data);
 
-    assert_token_program(token_program)?;
-    assert_token_account(sender_usdc, sender.key, &mailer_state.usdc_mint)?;
-    assert_token_account(mailer_usdc, &mailer_pda, &mailer_state.usdc_mint)?;
-
-    // Check if contract is paused
-    if mailer_state.paused {
+    if mailer_state.is_paused(PAUSE_SEND) {
         return Err(MailerError::ContractPaused.into());
     }
 
-    // Calculate effective fee based on custom discount (if any), or skip if fee_paused
-    let effective_fee = if mailer_state.fee_paused {
-        0 // Skip fee collection when fee_paused is true
-    } else {
-        calculate_fee_with_discount(program_id, sender.key, accounts, mailer_state.send_fee)?
-    };
-
-    // Track whether fee was paid successfully
-    let fee_paid: bool;
-
-    if revenue_share_to_receiver {
-        // Priority mode: full fee with revenue sharing
-
-        // Create or load recipient claim account
-        let (claim_pda, claim_bump) =
-            Pubkey::find_program_address(&[b"claim", &[PDA_VERSION], to.as_ref()], program_id);
-
-        if recipient_claim.key != &claim_pda {
-            return Err(MailerError::InvalidPDA.into());
-        }
-
-        // Create claim account if needed
-        if recipient_claim.lamports() == 0 {
-            let rent = Rent::get()?;
-            let space = 8 + RecipientClaim::LEN;
-            let lamports = rent.minimum_balance(space);
+    assert_not_sanctioned(program_id, &mailer_state, subscriber.key, accounts)?;
 
-            invoke_signed(
-                &system_instruction::create_account(
-                    sender.key,
-                    recipient_claim.key,
-                    lamports,
-                    space as u64,
-                    program_id,
-                ),
-                &[
-                    sender.clone(),
-                    recipient_claim.clone(),
-                    system_program.clone(),
-                ],
-                &[&[b"claim", &[PDA_VERSION], to.as_ref(), &[claim_bump]]],
-            )?;
+    assert_token_program(token_program)?;
+    assert_token_account(
+        accounts,
+        "subscriber_usdc",
+        subscriber_usdc,
+        subscriber.key,
+        &mailer_state.usdc_mint,
+    )?;
+    assert_token_account(
+        accounts,
+        "mailer_usdc",
+        mailer_usdc,
+        &mailer_pda,
+        &mailer_state.usdc_mint,
+    )?;
 
-            // Verify account is rent-exempt
-            let account_lamports = recipient_claim.lamports();
-            if !rent.is_exempt(account_lamports, space) {
-                msg!("ERROR: Recipient claim account not rent-exempt! {} lamports for {} bytes",
-                     account_lamports, space);
-                return Err(ProgramError::InsufficientFunds);
-            }
-            msg!("Created rent-exempt recipient claim account: {} lamports for {} bytes",
-                 account_lamports, space);
-
-            // Initialize claim account
-            let mut claim_data = recipient_claim.try_borrow_mut_data()?;
-            claim_data[0..8]
-                .copy_from_slice(&hash_discriminator("account:RecipientClaim").to_le_bytes());
-
-            let claim_state = RecipientClaim {
-                recipient: to,
-                amount: 0,
-                timestamp: 0,
-                bump: claim_bump,
-            };
+    let plan = load_active_plan(program_id, plan_account, plan_id)?;
 
-            claim_state.serialize(&mut &mut claim_data[8..])?;
-            drop(claim_data);
-        }
+    let (subscription_pda, subscription_bump) = pda::subscription(program_id, subscriber.key);
+    if subscription_account.key != &subscription_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
 
-        // Transfer effective fee (may be discounted) and track success
-        if effective_fee > 0 {
-            let transfer_result = invoke(
-                &spl_token::instruction::transfer(
-                    token_program.key,
-                    sender_usdc.key,
-                    mailer_usdc.key,
-                    sender.key,
-                    &[],
-                    effective_fee,
-                )?,
-                &[
-                    sender_usdc.clone(),
-                    mailer_usdc.clone(),
-                    sender.clone(),
-                    token_program.clone(),
-                ],
-            );
+    let now = Clock::get()?.unix_timestamp;
+    if subscription_account.lamports() == 0 {
+        let rent = Rent::get()?;
+        let space = 8 + Subscription::LEN;
 
-            // Check if transfer succeeded
-            if transfer_result.is_err() {
-                fee_paid = false;
-            } else {
-                // Record revenue shares (only if fee > 0 and transfer succeeded)
-                fee_paid = record_shares(recipient_claim, mailer_account, to, effective_fee).is_ok();
-            }
-        } else {
-            fee_paid = true; // No fee required
-        }
+        invoke_signed(
+            &system_instruction::create_account(
+                subscriber.key,
+                subscription_account.key,
+                rent.minimum_balance(space),
+                space as u64,
+                program_id,
+            ),
+            &[
+                subscriber.clone(),
+                subscription_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                seeds::SUBSCRIPTION,
+                &[PDA_VERSION],
+                subscriber.key.as_ref(),
+                &[subscription_bump],
+            ]],
+        )?;
 
-        // Always log the message with fee_paid status (payer = sender in Solana)
-        msg!("Priority mail sent from {} payer {} to {}: {} (revenue share enabled, resolve sender: {}, effective fee: {}, fee paid: {})", sender.key, sender.key, to, subject, _resolve_sender_to_name, effective_fee, fee_paid);
+        subscription_account.try_borrow_mut_data()?[0..8]
+            .copy_from_slice(&discriminator::account("Subscription"));
+    } else if subscription_account.owner != program_id {
+        return Err(MailerError::InvalidPDA.into());
     } else {
-        // Standard mode: 10% fee only, no revenue sharing
-        let owner_fee = (effective_fee * 10) / 100; // 10% of effective fee
-
-        // Transfer only owner fee (10%) and track success
-        if owner_fee > 0 {
-            let transfer_result = invoke(
-                &spl_token::instruction::transfer(
-                    token_program.key,
-                    sender_usdc.key,
-                    mailer_usdc.key,
-                    sender.key,
-                    &[],
-                    owner_fee,
-                )?,
-                &[
-                    sender_usdc.clone(),
-                    mailer_usdc.clone(),
-                    sender.clone(),
-                    token_program.clone(),
-                ],
-            );
-
-            // Check if transfer succeeded
-            fee_paid = transfer_result.is_ok();
-        } else {
-            fee_paid = true; // No fee required
+        let subscription_data = subscription_account.try_borrow_data()?;
+        let current: Subscription = BorshDeserialize::deserialize(&mut &subscription_data[8..])?;
+        if current.is_active(now) {
+            return Err(MailerError::SubscriptionActive.into());
         }
+    }
 
-        // Update owner claimable only if fee was paid
-        if fee_paid && owner_fee > 0 {
-            let mut mailer_data = mailer_account.try_borrow_mut_data()?;
-            let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
-            mailer_state.increase_owner_claimable(owner_fee)?;
-            mailer_state.serialize(&mut &mut mailer_data[8..])?;
-        }
+    let paid = pay_plan_price(
+        mailer_account,
+        token_program,
+        subscriber_usdc,
+        mailer_usdc,
+        subscriber,
+        accounts,
+        &plan,
+    )?;
 
-        // Always log the message with fee_paid status (payer = sender in Solana)
-        msg!(
-            "Standard mail sent from {} payer {} to {}: {} (resolve sender: {}, effective fee: {}, fee paid: {})",
-            sender.key,
-            sender.key,
-            to,
-            subject,
-            _resolve_sender_to_name,
-            effective_fee,
-            fee_paid
-        );
-    }
+    let subscription = Subscription {
+        subscriber: *subscriber.key,
+        plan_id,
+        expires_at: now
+            .checked_add(plan.duration)
+            .ok_or(MailerError::MathOverflow)?,
+        messages_remaining: plan.max_messages,
+        bump: subscription_bump,
+    };
+    subscription.serialize(&mut &mut subscription_account.try_borrow_mut_data()?[8..])?;
 
+    msg!(
+        "Subscribed {} to plan {} for {} until {} ({} messages)",
+        subscriber.key,
+        plan_id,
+        paid,
+        subscription.expires_at,
+        subscription.messages_remaining
+    );
     Ok(())
 }
 
-/// Send prepared message with optional revenue sharing (references off-chain content via mailId)
-fn process_send_prepared(
-    program_id: &Pubkey,
-    accounts: &[AccountInfo],
-    to: Pubkey,
-    mail_id: String,
-    revenue_share_to_receiver: bool,
-    _resolve_sender_to_name: bool,
-) -> ProgramResult {
+/// Renew a subscription for another period of its plan
+fn process_renew_subscription(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
     let account_iter = &mut accounts.iter();
-    let sender = next_account_info(account_iter)?;
-    let recipient_claim = next_account_info(account_iter)?;
+    let subscriber = next_account_info(account_iter)?;
+    let plan_account = next_account_info(account_iter)?;
+    let subscription_account = next_account_info(account_iter)?;
     let mailer_account = next_account_info(account_iter)?;
-    let sender_usdc = next_account_info(account_iter)?;
+    let subscriber_usdc = next_account_info(account_iter)?;
     let mailer_usdc = next_account_info(account_iter)?;
     let token_program = next_account_info(account_iter)?;
-    let system_program = next_account_info(account_iter)?;
 
-    if !sender.is_signer {
+    if !subscriber.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    // Load mailer state
-    let (mailer_pda, _) = assert_mailer_account(program_id, mailer_account)?;
+    let (mailer_pda, _) = assert_mailer_account(program_id, accounts, mailer_account)?;
     let mailer_data = mailer_account.try_borrow_data()?;
     let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
     drop(mailer_data);
 
-    assert_token_program(token_program)?;
-    assert_token_account(sender_usdc, sender.key, &mailer_state.usdc_mint)?;
-    assert_token_account(mailer_usdc, &mailer_pda, &mailer_state.usdc_mint)?;
-
-    // Check if contract is paused
-    if mailer_state.paused {
+    if mailer_state.is_paused(PAUSE_SEND) {
         return Err(MailerError::ContractPaused.into());
     }
 
-    // Calculate effective fee based on custom discount (if any), or skip if fee_paused
-    let effective_fee = if mailer_state.fee_paused {
-        0 // Skip fee collection when fee_paused is true
-    } else {
-        calculate_fee_with_discount(program_id, sender.key, accounts, mailer_state.send_fee)?
-    };
-
-    // Track whether fee was paid successfully
-    let fee_paid: bool;
-
-    if revenue_share_to_receiver {
-        // Priority mode: full fee with revenue sharing
-
-        // Create or load recipient claim account
-        let (claim_pda, claim_bump) =
-            Pubkey::find_program_address(&[b"claim", &[PDA_VERSION], to.as_ref()], program_id);
-
-        if recipient_claim.key != &claim_pda {
-            return Err(MailerError::InvalidPDA.into());
-        }
-
-        // Create claim account if needed
-        if recipient_claim.lamports() == 0 {
-            let rent = Rent::get()?;
-            let space = 8 + RecipientClaim::LEN;
-            let lamports = rent.minimum_balance(space);
-
-            invoke_signed(
-                &system_instruction::create_account(
-                    sender.key,
-                    recipient_claim.key,
-                    lamports,
-                    space as u64,
-                    program_id,
-                ),
-                &[
-                    sender.clone(),
-                    recipient_claim.clone(),
-                    system_program.clone(),
-                ],
-                &[&[b"claim", &[PDA_VERSION], to.as_ref(), &[claim_bump]]],
-            )?;
-
-            // Verify account is rent-exempt
-            let account_lamports = recipient_claim.lamports();
-            if !rent.is_exempt(account_lamports, space) {
-                msg!("ERROR: Recipient claim account not rent-exempt! {} lamports for {} bytes",
-                     account_lamports, space);
-                return Err(ProgramError::InsufficientFunds);
-            }
-            msg!("Created rent-exempt recipient claim account: {} lamports for {} bytes",
-                 account_lamports, space);
-
-            // Initialize claim account
-            let mut claim_data = recipient_claim.try_borrow_mut_data()?;
-            claim_data[0..8]
-                .copy_from_slice(&hash_discriminator("account:RecipientClaim").to_le_bytes());
-
-            let claim_state = RecipientClaim {
-                recipient: to,
-                amount: 0,
-                timestamp: 0,
-                bump: claim_bump,
-            };
+    assert_not_sanctioned(program_id, &mailer_state, subscriber.key, accounts)?;
 
-            claim_state.serialize(&mut &mut claim_data[8..])?;
-            drop(claim_data);
-        }
+    assert_token_program(token_program)?;
+    assert_token_account(
+        accounts,
+        "subscriber_usdc",
+        subscriber_usdc,
+        subscriber.key,
+        &mailer_state.usdc_mint,
+    )?;
+    assert_token_account(
+        accounts,
+        "mailer_usdc",
+        mailer_usdc,
+        &mailer_pda,
+        &mailer_state.usdc_mint,
+    )?;
 
-        // Transfer effective fee (may be discounted) and track success
-        if effective_fee > 0 {
-            let transfer_result = invoke(
-                &spl_token::instruction::transfer(
-                    token_program.key,
-                    sender_usdc.key,
-                    mailer_usdc.key,
-                    sender.key,
-                    &[],
-                    effective_fee,
-                )?,
-                &[
-                    sender_usdc.clone(),
-                    mailer_usdc.clone(),
-                    sender.clone(),
-                    token_program.clone(),
-                ],
-            );
+    let (subscription_pda, _) = pda::subscription(program_id, subscriber.key);
+    if subscription_account.key != &subscription_pda || subscription_account.owner != program_id {
+        return Err(MailerError::InvalidPDA.into());
+    }
 
-            // Check if transfer succeeded
-            if transfer_result.is_err() {
-                fee_paid = false;
-            } else {
-                // Record revenue shares (only if fee > 0 and transfer succeeded)
-                fee_paid = record_shares(recipient_claim, mailer_account, to, effective_fee).is_ok();
-            }
-        } else {
-            fee_paid = true; // No fee required
-        }
+    let subscription_data = subscription_account.try_borrow_data()?;
+    let mut subscription: Subscription =
+        BorshDeserialize::deserialize(&mut &subscription_data[8..])?;
+    drop(subscription_data);
+
+    let plan = load_active_plan(program_id, plan_account, subscription.plan_id)?;
+    let paid = pay_plan_price(
+        mailer_account,
+        token_program,
+        subscriber_usdc,
+        mailer_usdc,
+        subscriber,
+        accounts,
+        &plan,
+    )?;
 
-        // Always log the message with fee_paid status (payer = sender in Solana)
-        msg!("Priority prepared mail sent from {} payer {} to {} (mailId: {}, revenue share enabled, resolve sender: {}, effective fee: {}, fee paid: {})", sender.key, sender.key, to, mail_id, _resolve_sender_to_name, effective_fee, fee_paid);
+    // Extend an unexpired period, or start a new one from now
+    let now = Clock::get()?.unix_timestamp;
+    if now < subscription.expires_at {
+        subscription.messages_remaining = subscription
+            .messages_remaining
+            .saturating_add(plan.max_messages);
     } else {
-        // Standard mode: 10% fee only, no revenue sharing
-        let owner_fee = (effective_fee * 10) / 100; // 10% of effective fee
-
-        // Transfer only owner fee (10%) and track success
-        if owner_fee > 0 {
-            let transfer_result = invoke(
-                &spl_token::instruction::transfer(
-                    token_program.key,
-                    sender_usdc.key,
-                    mailer_usdc.key,
-                    sender.key,
-                    &[],
-                    owner_fee,
-                )?,
-                &[
-                    sender_usdc.clone(),
-                    mailer_usdc.clone(),
-                    sender.clone(),
-                    token_program.clone(),
-                ],
-            );
-
-            // Check if transfer succeeded
-            fee_paid = transfer_result.is_ok();
-        } else {
-            fee_paid = true; // No fee required
-        }
-
-        // Update owner claimable only if fee was paid
-        if fee_paid && owner_fee > 0 {
-            let mut mailer_data = mailer_account.try_borrow_mut_data()?;
-            let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
-            mailer_state.increase_owner_claimable(owner_fee)?;
-            mailer_state.serialize(&mut &mut mailer_data[8..])?;
-        }
-
-        // Always log the message with fee_paid status (payer = sender in Solana)
-        msg!(
-            "Standard prepared mail sent from {} payer {} to {} (mailId: {}, resolve sender: {}, effective fee: {}, fee paid: {})",
-            sender.key,
-            sender.key,
-            to,
-            mail_id,
-            _resolve_sender_to_name,
-            effective_fee,
-            fee_paid
-        );
+        subscription.expires_at = now;
+        subscription.messages_remaining = plan.max_messages;
     }
+    subscription.expires_at = subscription
+        .expires_at
+        .checked_add(plan.duration)
+        .ok_or(MailerError::MathOverflow)?;
+    subscription.serialize(&mut &mut subscription_account.try_borrow_mut_data()?[8..])?;
 
+    msg!(
+        "Subscription of {} to plan {} renewed for {} until {} ({} messages left)",
+        subscriber.key,
+        subscription.plan_id,
+        paid,
+        subscription.expires_at,
+        subscription.messages_remaining
+    );
     Ok(())
 }
 
-/// Process send to email address (no wallet known, only owner fee)
-fn process_send_to_email(
-    _program_id: &Pubkey,
-    accounts: &[AccountInfo],
-    to_email: String,
-    subject: String,
-    _body: String,
-) -> ProgramResult {
+/// Mint devnet fee tokens to the caller, at most once per cooldown
+#[cfg(feature = "devnet-faucet")]
+fn process_dev_faucet(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> ProgramResult {
     let account_iter = &mut accounts.iter();
-    let sender = next_account_info(account_iter)?;
+    let caller = next_account_info(account_iter)?;
+    let drip_account = next_account_info(account_iter)?;
     let mailer_account = next_account_info(account_iter)?;
-    let sender_usdc = next_account_info(account_iter)?;
-    let mailer_usdc = next_account_info(account_iter)?;
+    let usdc_mint = next_account_info(account_iter)?;
+    let caller_usdc = next_account_info(account_iter)?;
     let token_program = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
 
-    if !sender.is_signer {
+    if !caller.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    // Load mailer state
-    let (mailer_pda, _) = assert_mailer_account(_program_id, mailer_account)?;
+    assert_system_program(system_program)?;
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
     let mailer_data = mailer_account.try_borrow_data()?;
     let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
     drop(mailer_data);
 
-    assert_token_program(token_program)?;
-    assert_token_account(sender_usdc, sender.key, &mailer_state.usdc_mint)?;
-    assert_token_account(mailer_usdc, &mailer_pda, &mailer_state.usdc_mint)?;
-
-    // Check if contract is paused
-    if mailer_state.paused {
+    if mailer_state.is_paused(PAUSE_SEND) {
         return Err(MailerError::ContractPaused.into());
     }
 
-    // Calculate effective fee based on custom discount (if any), or skip if fee_paused
-    let effective_fee = if mailer_state.fee_paused {
-        0 // Skip fee collection when fee_paused is true
-    } else {
-        calculate_fee_with_discount(_program_id, sender.key, accounts, mailer_state.send_fee)?
-    };
+    if amount == 0 || amount > DEV_FAUCET_MAX_AMOUNT {
+        return Err(MailerError::InvalidClaimAmount.into());
+    }
+
+    assert_token_program(token_program)?;
+    if usdc_mint.key != &mailer_state.usdc_mint {
+        return Err(MailerError::InvalidMint.into());
+    }
+    assert_token_account(
+        accounts,
+        "caller_usdc",
+        caller_usdc,
+        caller.key,
+        &mailer_state.usdc_mint,
+    )?;
 
-    // Calculate 10% owner fee (no revenue share since no wallet address)
-    let owner_fee = (effective_fee * 10) / 100;
+    let (drip_pda, drip_bump) = pda::faucet_drip(program_id, caller.key);
+    if drip_account.key != &drip_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
 
-    // Transfer fee from sender to mailer and track success
-    let fee_paid: bool = if owner_fee > 0 {
-        let transfer_ix = spl_token::instruction::transfer(
-            token_program.key,
-            sender_usdc.key,
-            mailer_usdc.key,
-            sender.key,
-            &[],
-            owner_fee,
-        )?;
+    let now = Clock::get()?.unix_timestamp;
+    if drip_account.lamports() == 0 {
+        let rent = Rent::get()?;
+        let space = 8 + FaucetDrip::LEN;
 
-        let transfer_result = invoke(
-            &transfer_ix,
-            &[
-                sender_usdc.clone(),
-                mailer_usdc.clone(),
-                sender.clone(),
-                token_program.clone(),
-            ],
-        );
+        invoke_signed(
+            &system_instruction::create_account(
+                caller.key,
+                drip_account.key,
+                rent.minimum_balance(space),
+                space as u64,
+                program_id,
+            ),
+            &[caller.clone(), drip_account.clone(), system_program.clone()],
+            &[&[
+                seeds::FAUCET,
+                &[PDA_VERSION],
+                caller.key.as_ref(),
+                &[drip_bump],
+            ]],
+        )?;
 
-        // Check if transfer succeeded
-        transfer_result.is_ok()
+        drip_account.try_borrow_mut_data()?[0..8]
+            .copy_from_slice(&discriminator::account("FaucetDrip"));
+    } else if drip_account.owner != program_id {
+        return Err(MailerError::InvalidPDA.into());
     } else {
-        true // No fee required
-    };
-
-    // Update owner claimable only if fee was paid
-    if fee_paid && owner_fee > 0 {
-        let mut mailer_data = mailer_account.try_borrow_mut_data()?;
-        let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
-        mailer_state.increase_owner_claimable(owner_fee)?;
-        mailer_state.serialize(&mut &mut mailer_data[8..])?;
+        let drip_data = drip_account.try_borrow_data()?;
+        let drip: FaucetDrip = BorshDeserialize::deserialize(&mut &drip_data[8..])?;
+        if !drip.cooled_down(now) {
+            msg!(
+                "Faucet drip to {} rejected: next drip allowed at {}",
+                caller.key,
+                drip.last_drip_at.saturating_add(DEV_FAUCET_COOLDOWN)
+            );
+            return Err(MailerError::RateLimitExceeded.into());
+        }
     }
 
-    // Always log the message with fee_paid status (payer = sender in Solana)
-    msg!(
-        "Mail sent from {} payer {} to email {}: {} (effective fee: {}, fee paid: {})",
-        sender.key,
-        sender.key,
-        to_email,
-        subject,
-        effective_fee,
-        fee_paid
-    );
+    invoke_signed(
+        &spl_token::instruction::mint_to(
+            token_program.key,
+            usdc_mint.key,
+            caller_usdc.key,
+            mailer_account.key,
+            &[],
+            amount,
+        )?,
+        &[
+            usdc_mint.clone(),
+            caller_usdc.clone(),
+            mailer_account.clone(),
+            token_program.clone(),
+        ],
+        &[&[seeds::MAILER, &[mailer_state.bump]]],
+    )?;
+
+    FaucetDrip {
+        caller: *caller.key,
+        last_drip_at: now,
+        bump: drip_bump,
+    }
+    .serialize(&mut &mut drip_account.try_borrow_mut_data()?[8..])?;
 
+    msg!("Faucet minted {} to {}", amount, caller.key);
     Ok(())
 }
 
-/// Process send prepared to email address (no wallet known, only owner fee)
-fn process_send_prepared_to_email(
-    _program_id: &Pubkey,
-    accounts: &[AccountInfo],
-    to_email: String,
-    mail_id: String,
-) -> ProgramResult {
+/// Create the closure log ring buffer
+fn process_initialize_closure_log(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
     let account_iter = &mut accounts.iter();
-    let sender = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
     let mailer_account = next_account_info(account_iter)?;
-    let sender_usdc = next_account_info(account_iter)?;
-    let mailer_usdc = next_account_info(account_iter)?;
-    let token_program = next_account_info(account_iter)?;
+    let closure_log = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
 
-    if !sender.is_signer {
+    if !owner.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    // Load mailer state
-    let (mailer_pda, _) = assert_mailer_account(_program_id, mailer_account)?;
+    assert_system_program(system_program)?;
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
     let mailer_data = mailer_account.try_borrow_data()?;
     let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
     drop(mailer_data);
 
-    assert_token_program(token_program)?;
-    assert_token_account(sender_usdc, sender.key, &mailer_state.usdc_mint)?;
-    assert_token_account(mailer_usdc, &mailer_pda, &mailer_state.usdc_mint)?;
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
 
-    // Check if contract is paused
-    if mailer_state.paused {
-        return Err(MailerError::ContractPaused.into());
+    let (log_pda, log_bump) = pda::closure_log(program_id);
+
+    if closure_log.key != &log_pda {
+        return Err(MailerError::InvalidPDA.into());
     }
 
-    // Calculate effective fee based on custom discount (if any), or skip if fee_paused
-    let effective_fee = if mailer_state.fee_paused {
-        0 // Skip fee collection when fee_paused is true
-    } else {
-        calculate_fee_with_discount(_program_id, sender.key, accounts, mailer_state.send_fee)?
-    };
+    if closure_log.lamports() > 0 {
+        return Err(MailerError::AlreadyInitialized.into());
+    }
 
-    // Calculate 10% owner fee (no revenue share since no wallet address)
-    let owner_fee = (effective_fee * 10) / 100;
+    let rent = Rent::get()?;
+    let space = 8 + ClosureLog::LEN;
+    let lamports = rent.minimum_balance(space);
 
-    // Transfer fee from sender to mailer and track success
-    let fee_paid: bool = if owner_fee > 0 {
-        let transfer_ix = spl_token::instruction::transfer(
-            token_program.key,
-            sender_usdc.key,
-            mailer_usdc.key,
-            sender.key,
-            &[],
-            owner_fee,
-        )?;
+    invoke_signed(
+        &system_instruction::create_account(
+            owner.key,
+            closure_log.key,
+            lamports,
+            space as u64,
+            program_id,
+        ),
+        &[owner.clone(), closure_log.clone(), system_program.clone()],
+        &[&[seeds::CLOSURE_LOG, &[PDA_VERSION], &[log_bump]]],
+    )?;
 
-        let transfer_result = invoke(
-            &transfer_ix,
-            &[
-                sender_usdc.clone(),
-                mailer_usdc.clone(),
-                sender.clone(),
-                token_program.clone(),
-            ],
-        );
+    let mut log_data = closure_log.try_borrow_mut_data()?;
+    log_data[0..8].copy_from_slice(&discriminator::account("ClosureLog"));
 
-        // Check if transfer succeeded
-        transfer_result.is_ok()
-    } else {
-        true // No fee required
+    let log = ClosureLog {
+        total: 0,
+        records: Vec::new(),
+        bump: log_bump,
     };
+    log.serialize(&mut &mut log_data[8..])?;
 
-    // Update owner claimable only if fee was paid
-    if fee_paid && owner_fee > 0 {
-        let mut mailer_data = mailer_account.try_borrow_mut_data()?;
-        let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
-        mailer_state.increase_owner_claimable(owner_fee)?;
-        mailer_state.serialize(&mut &mut mailer_data[8..])?;
-    }
-
-    // Always log the message with fee_paid status (payer = sender in Solana)
-    msg!(
-        "Prepared mail sent from {} payer {} to email {} (mailId: {}, effective fee: {}, fee paid: {})",
-        sender.key,
-        sender.key,
-        to_email,
-        mail_id,
-        effective_fee,
-        fee_paid
-    );
-
+    msg!("Closure log {} initialized", closure_log.key);
     Ok(())
 }
 
-/// Send message through webhook (references webhook by webhookId)
-fn process_send_through_webhook(
+/// Send a message as a delegator, paid by their current delegate
+fn process_send_as_delegator(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     to: Pubkey,
-    webhook_id: String,
+    subject: String,
+    _body: String,
     revenue_share_to_receiver: bool,
-    _resolve_sender_to_name: bool,
+    strict: bool,
 ) -> ProgramResult {
     let account_iter = &mut accounts.iter();
-    let sender = next_account_info(account_iter)?;
+    let delegate = next_account_info(account_iter)?;
+    let delegator = next_account_info(account_iter)?;
+    let delegation_account = next_account_info(account_iter)?;
     let recipient_claim = next_account_info(account_iter)?;
     let mailer_account = next_account_info(account_iter)?;
-    let sender_usdc = next_account_info(account_iter)?;
+    let delegate_usdc = next_account_info(account_iter)?;
     let mailer_usdc = next_account_info(account_iter)?;
     let token_program = next_account_info(account_iter)?;
     let system_program = next_account_info(account_iter)?;
 
-    if !sender.is_signer {
+    if !delegate.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
+    assert_system_program(system_program)?;
+
+    // Only the delegate currently recorded in the delegator's delegation may act for them
+    let (delegation_pda, _) = pda::delegation(program_id, delegator.key);
+
+    if delegation_account.key != &delegation_pda || delegation_account.owner != program_id {
+        return Err(MailerError::InvalidPDA.into());
+    }
+
+    let delegation_data = delegation_account.try_borrow_data()?;
+    let delegation_state: Delegation = BorshDeserialize::deserialize(&mut &delegation_data[8..])?;
+    drop(delegation_data);
+
+    if delegation_state.delegate != Some(*delegate.key) {
+        return Err(MailerError::NotDelegate.into());
+    }
+
     // Load mailer state
-    let (mailer_pda, _) = assert_mailer_account(program_id, mailer_account)?;
+    let (mailer_pda, _) = assert_mailer_account(program_id, accounts, mailer_account)?;
     let mailer_data = mailer_account.try_borrow_data()?;
     let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
     drop(mailer_data);
 
     assert_token_program(token_program)?;
-    assert_token_account(sender_usdc, sender.key, &mailer_state.usdc_mint)?;
-    assert_token_account(mailer_usdc, &mailer_pda, &mailer_state.usdc_mint)?;
+    assert_token_account(
+        accounts,
+        "delegate_usdc",
+        delegate_usdc,
+        delegate.key,
+        &mailer_state.usdc_mint,
+    )?;
+    assert_token_account(
+        accounts,
+        "mailer_usdc",
+        mailer_usdc,
+        &mailer_pda,
+        &mailer_state.usdc_mint,
+    )?;
+
+    // Reject payloads over the owner's size limits
+    assert_payload_len(&subject, mailer_state.payload_limits.max_subject_len)?;
+    assert_payload_len(&_body, mailer_state.payload_limits.max_body_len)?;
 
     // Check if contract is paused
-    if mailer_state.paused {
+    if mailer_state.is_paused(PAUSE_SEND) {
         return Err(MailerError::ContractPaused.into());
     }
 
-    // Calculate effective fee based on custom discount (if any), or skip if fee_paused
-    let effective_fee = if mailer_state.fee_paused {
-        0 // Skip fee collection when fee_paused is true
-    } else {
-        calculate_fee_with_discount(program_id, sender.key, accounts, mailer_state.send_fee)?
-    };
+    // Refuse service when either party is sanctioned
+    assert_not_sanctioned(program_id, &mailer_state, delegator.key, accounts)?;
+    assert_not_sanctioned(program_id, &mailer_state, delegate.key, accounts)?;
+
+    // Priority sends require the sender to be attested by the KYC verifier, if registered
+    if revenue_share_to_receiver {
+        assert_verified(&mailer_state, delegator.key, accounts)?;
+    }
 
-    // Track whether fee was paid successfully
-    let fee_paid: bool;
+    // The delegator is the sender: their spam score and rate limit apply, funded by the delegate
+    let spam_score = check_spam_score(&mailer_state, delegator.key, &to, accounts)?;
+    enforce_rate_limit(program_id, &mailer_state, delegator.key, delegate, accounts)?;
+
+    // Number the send in the recipient's inbox, if its inbox account was passed
+    let inbox_sequence = next_inbox_sequence(program_id, &to, delegate, accounts)?;
+
+    // Identify the message for the caller and threaded conversations
+    let message_nonce = next_message_nonce(program_id, delegator.key, delegate, accounts)?;
+    let payload_hash = hash_message_body(&subject, &_body);
+    let message_id = derive_message_id(
+        delegator.key,
+        to.as_ref(),
+        &payload_hash,
+        Clock::get()?.slot,
+        message_nonce,
+    );
 
+    // The delegator's discount and the recipient's policy for the delegator apply
+    let effective_fee =
+        if mailer_state.fee_paused || use_subscription(program_id, delegator.key, accounts)? {
+            0
+        } else {
+            let current_fee = mailer_state.effective_send_fee(Clock::get()?.unix_timestamp);
+            let current_fee = mailer_state.payload_fee(current_fee, subject.len() + _body.len())?;
+            calculate_fee_with_discount(program_id, delegator.key, accounts, current_fee)?
+        };
+    let effective_fee =
+        apply_inbox_policy(program_id, delegator.key, &to, accounts, effective_fee)?;
     if revenue_share_to_receiver {
-        // Priority mode: full fee with revenue sharing
+        assert_recipient_min_fee(program_id, &to, accounts, effective_fee)?;
+    }
 
-        // Create or load recipient claim account
-        let (claim_pda, claim_bump) =
-            Pubkey::find_program_address(&[b"claim", &[PDA_VERSION], to.as_ref()], program_id);
+    let mut fee_received = 0;
+    let (fee_paid, fee_error) = if revenue_share_to_receiver {
+        // Priority mode: full fee with revenue sharing
+        let (claim_pda, claim_bump) = cached_pda(
+            program_id,
+            recipient_claim,
+            RecipientClaim::BUMP_OFFSET,
+            |bump| pda::recipient_claim_with_bump(program_id, &to, bump),
+            || pda::recipient_claim(program_id, &to),
+        );
 
         if recipient_claim.key != &claim_pda {
             return Err(MailerError::InvalidPDA.into());
         }
 
-        // Create claim account if needed
-        if recipient_claim.lamports() == 0 {
-            let rent = Rent::get()?;
-            let space = 8 + RecipientClaim::LEN;
-            let lamports = rent.minimum_balance(space);
+        let claim_seeds: &[&[u8]] = &[seeds::CLAIM, &[PDA_VERSION], to.as_ref(), &[claim_bump]];
 
-            invoke_signed(
-                &system_instruction::create_account(
-                    sender.key,
-                    recipient_claim.key,
-                    lamports,
-                    space as u64,
-                    program_id,
-                ),
-                &[
-                    sender.clone(),
-                    recipient_claim.clone(),
-                    system_program.clone(),
-                ],
-                &[&[b"claim", &[PDA_VERSION], to.as_ref(), &[claim_bump]]],
+        // Create the claim account up front (paid by the delegate), or once the fee has
+        // arrived under the AfterTransfer policy
+        let defer_claim = mailer_state.claim_creation_policy == ClaimCreationPolicy::AfterTransfer;
+        let claim_created = !defer_claim
+            && create_recipient_claim(
+                program_id,
+                delegate,
+                recipient_claim,
+                system_program,
+                mailer_account,
+                to,
+                claim_bump,
+                claim_seeds,
             )?;
 
-            // Verify account is rent-exempt
-            let account_lamports = recipient_claim.lamports();
-            if !rent.is_exempt(account_lamports, space) {
-                msg!("ERROR: Recipient claim account not rent-exempt! {} lamports for {} bytes",
-                     account_lamports, space);
-                return Err(ProgramError::InsufficientFunds);
-            }
-            msg!("Created rent-exempt recipient claim account: {} lamports for {} bytes",
-                 account_lamports, space);
-
-            // Initialize claim account
-            let mut claim_data = recipient_claim.try_borrow_mut_data()?;
-            claim_data[0..8]
-                .copy_from_slice(&hash_discriminator("account:RecipientClaim").to_le_bytes());
-
-            let claim_state = RecipientClaim {
-                recipient: to,
-                amount: 0,
-                timestamp: 0,
-                bump: claim_bump,
-            };
-
-            claim_state.serialize(&mut &mut claim_data[8..])?;
-            drop(claim_data);
-        }
-
-        // Transfer effective fee (may be discounted) and track success
-        if effective_fee > 0 {
-            let transfer_result = invoke(
-                &spl_token::instruction::transfer(
-                    token_program.key,
-                    sender_usdc.key,
-                    mailer_usdc.key,
-                    sender.key,
-                    &[],
-                    effective_fee,
-                )?,
-                &[
-                    sender_usdc.clone(),
-                    mailer_usdc.clone(),
-                    sender.clone(),
-                    token_program.clone(),
-                ],
+        let fee_error = if effective_fee > 0 {
+            let transfer_result = pay_send_fee(
+                program_id,
+                &mailer_state.usdc_mint,
+                mailer_account,
+                token_program,
+                delegate_usdc,
+                mailer_usdc,
+                delegate,
+                accounts,
+                effective_fee,
             );
 
-            // Check if transfer succeeded
-            if transfer_result.is_err() {
-                fee_paid = false;
-            } else {
-                // Record revenue shares (only if fee > 0 and transfer succeeded)
-                fee_paid = record_shares(recipient_claim, mailer_account, to, effective_fee).is_ok();
+            // Under AfterTransfer the claim account is created only once the fee has arrived
+            if defer_claim && transfer_result.is_ok() {
+                create_recipient_claim(
+                    program_id,
+                    delegate,
+                    recipient_claim,
+                    system_program,
+                    mailer_account,
+                    to,
+                    claim_bump,
+                    claim_seeds,
+                )?;
             }
+
+            // Record revenue shares on the amount actually received (only if transfer succeeded)
+            let recorded = transfer_result.and_then(|received| {
+                fee_received = received;
+                record_shares(
+                    recipient_claim,
+                    mailer_account,
+                    mailer_usdc,
+                    None,
+                    None,
+                    to,
+                    received,
+                )
+            });
+            fail_if_insolvent(&recorded)?;
+            recorded.err()
         } else {
-            fee_paid = true; // No fee required
-        }
+            None // No fee required
+        };
 
-        // Always log the message with fee_paid status (payer = sender in Solana)
-        msg!("Webhook mail sent from {} payer {} to {} (webhookId: {}, revenue share enabled, resolve sender: {}, effective fee: {}, fee paid: {})", sender.key, sender.key, to, webhook_id, _resolve_sender_to_name, effective_fee, fee_paid);
+        // Under RefundPayer the delegate may later close the empty claim this send funded
+        if claim_created && fee_error.is_some() && !strict {
+            record_claim_rent_payer(
+                program_id,
+                &mailer_state,
+                delegate,
+                recipient_claim,
+                accounts,
+            )?;
+        }
+        (fee_error.is_none(), fee_error)
     } else {
         // Standard mode: 10% fee only, no revenue sharing
-        let owner_fee = (effective_fee * 10) / 100; // 10% of effective fee
-
-        // Transfer only owner fee (10%) and track success
-        if owner_fee > 0 {
-            let transfer_result = invoke(
-                &spl_token::instruction::transfer(
-                    token_program.key,
-                    sender_usdc.key,
-                    mailer_usdc.key,
-                    sender.key,
-                    &[],
-                    owner_fee,
-                )?,
-                &[
-                    sender_usdc.clone(),
-                    mailer_usdc.clone(),
-                    sender.clone(),
-                    token_program.clone(),
-                ],
-            );
+        let owner_fee = standard_fee(effective_fee)?;
 
-            // Check if transfer succeeded
-            fee_paid = transfer_result.is_ok();
+        let fee_error = if owner_fee > 0 {
+            match pay_send_fee(
+                program_id,
+                &mailer_state.usdc_mint,
+                mailer_account,
+                token_program,
+                delegate_usdc,
+                mailer_usdc,
+                delegate,
+                accounts,
+                owner_fee,
+            ) {
+                Ok(owner_received) => {
+                    credit_owner_fee(mailer_account, None, false, owner_received)?;
+                    fee_received = owner_received;
+                    None
+                }
+                Err(err) => Some(err),
+            }
         } else {
-            fee_paid = true; // No fee required
-        }
+            None // No fee required
+        };
+        (fee_error.is_none(), fee_error)
+    };
 
-        // Update owner claimable only if fee was paid
-        if fee_paid && owner_fee > 0 {
-            let mut mailer_data = mailer_account.try_borrow_mut_data()?;
-            let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
-            mailer_state.increase_owner_claimable(owner_fee)?;
-            mailer_state.serialize(&mut &mut mailer_data[8..])?;
+    // Strict senders get an error instead of an unpaid send, others a MailDropped log
+    if let Some(reason) = &fee_error {
+        if strict {
+            return Err(strict_fee_error(reason));
         }
-
-        // Always log the message with fee_paid status (payer = sender in Solana)
-        msg!(
-            "Webhook mail sent from {} payer {} to {} (webhookId: {}, resolve sender: {}, effective fee: {}, fee paid: {})",
-            sender.key,
-            sender.key,
-            to,
-            webhook_id,
-            _resolve_sender_to_name,
-            effective_fee,
-            fee_paid
-        );
+        log_mail_dropped(delegator.key, Some(&to), &to, reason);
     }
 
-    Ok(())
+    msg!(
+        "{}{} mail sent from {} payer {} to {}: {} ({}delegate: {}, effective fee: {}, fee paid: {}{}{}{}{})",
+        recipient_log_prefix(&to),
+        if revenue_share_to_receiver { "Priority" } else { "Standard" },
+        delegator.key,
+        delegate.key,
+        to,
+        subject,
+        if revenue_share_to_receiver { "revenue share enabled, " } else { "" },
+        delegate.key,
+        effective_fee,
+        fee_paid,
+        fee_token_detail(&mailer_state, false, None, &mailer_state.usdc_mint),
+        spam_score_detail(spam_score),
+        inbox_sequence_detail(inbox_sequence),
+        thread_detail(&message_id, None, None)
+    );
+
+    let usdc_fee = if fee_paid { fee_received } else { 0 };
+    record_send_stats(
+        program_id,
+        &mailer_state,
+        accounts,
+        revenue_share_to_receiver,
+        usdc_fee,
+    )?;
+
+    report_send(message_id)
 }
 
-/// Process claim recipient share
-fn process_claim_recipient_share(_program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+/// Add an address to the sanctions registry (owner only)
+fn process_sanction_address(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    address: Pubkey,
+) -> ProgramResult {
     let account_iter = &mut accounts.iter();
-    let recipient = next_account_info(account_iter)?;
-    let recipient_claim = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
     let mailer_account = next_account_info(account_iter)?;
-    let recipient_usdc = next_account_info(account_iter)?;
-    let mailer_usdc = next_account_info(account_iter)?;
-    let token_program = next_account_info(account_iter)?;
+    let sanctioned_account = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
 
-    if !recipient.is_signer {
+    if !owner.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    let (mailer_pda, _) = assert_mailer_account(_program_id, mailer_account)?;
-    let (claim_pda, _) =
-        Pubkey::find_program_address(&[b"claim", &[PDA_VERSION], recipient.key.as_ref()], _program_id);
-    if recipient_claim.key != &claim_pda {
-        return Err(MailerError::InvalidPDA.into());
-    }
+    assert_system_program(system_program)?;
 
-    // Load claim state
-    let mut claim_data = recipient_claim.try_borrow_mut_data()?;
-    let mut claim_state: RecipientClaim = BorshDeserialize::deserialize(&mut &claim_data[8..])?;
+    assert_mailer_account(program_id, accounts, mailer_account)?;
 
-    if claim_state.recipient != *recipient.key {
-        return Err(MailerError::InvalidRecipient.into());
-    }
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
 
-    if claim_state.amount == 0 {
-        return Err(MailerError::NoClaimableAmount.into());
-    }
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
 
-    // Check if claim period has expired
-    let current_time = Clock::get()?.unix_timestamp;
-    if current_time > claim_state.timestamp + CLAIM_PERIOD {
-        return Err(MailerError::ClaimPeriodExpired.into());
-    }
+    let (sanctioned_pda, sanctioned_bump) = pda::sanctioned(program_id, &address);
 
-    let amount = claim_state.amount;
-    claim_state.amount = 0;
-    claim_state.timestamp = 0;
-    claim_state.serialize(&mut &mut claim_data[8..])?;
+    if sanctioned_account.key != &sanctioned_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
 
-    // Load mailer state for PDA signing
-    let mailer_data = mailer_account.try_borrow_data()?;
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
-    drop(mailer_data);
+    if sanctioned_account.lamports() > 0 {
+        return Err(MailerError::AlreadyInitialized.into());
+    }
 
-    assert_token_program(token_program)?;
-    assert_token_account(recipient_usdc, recipient.key, &mailer_state.usdc_mint)?;
-    assert_token_account(mailer_usdc, &mailer_pda, &mailer_state.usdc_mint)?;
+    let rent = Rent::get()?;
+    let space = 8 + Sanctioned::LEN;
+    let lamports = rent.minimum_balance(space);
 
-    // Transfer USDC from mailer to recipient
     invoke_signed(
-        &spl_token::instruction::transfer(
-            token_program.key,
-            mailer_usdc.key,
-            recipient_usdc.key,
-            mailer_account.key,
-            &[],
-            amount,
-        )?,
+        &system_instruction::create_account(
+            owner.key,
+            sanctioned_account.key,
+            lamports,
+            space as u64,
+            program_id,
+        ),
         &[
-            mailer_usdc.clone(),
-            recipient_usdc.clone(),
-            mailer_account.clone(),
-            token_program.clone(),
+            owner.clone(),
+            sanctioned_account.clone(),
+            system_program.clone(),
         ],
-        &[&[b"mailer", &[mailer_state.bump]]],
+        &[&[
+            seeds::SANCTIONED,
+            &[PDA_VERSION],
+            address.as_ref(),
+            &[sanctioned_bump],
+        ]],
     )?;
 
-    msg!("Recipient {} claimed {}", recipient.key, amount);
+    let mut sanctioned_data = sanctioned_account.try_borrow_mut_data()?;
+    sanctioned_data[0..8].copy_from_slice(&discriminator::account("Sanctioned"));
+
+    let sanctioned = Sanctioned {
+        address,
+        sanctioned_at: Clock::get()?.unix_timestamp,
+        bump: sanctioned_bump,
+    };
+    sanctioned.serialize(&mut &mut sanctioned_data[8..])?;
+
+    msg!("Address {} sanctioned by {}", address, owner.key);
     Ok(())
 }
 
-/// Process claim owner share
-fn process_claim_owner_share(_program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+/// Remove an address from the sanctions registry, refunding the entry's rent (owner only)
+fn process_unsanction_address(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    address: Pubkey,
+) -> ProgramResult {
     let account_iter = &mut accounts.iter();
     let owner = next_account_info(account_iter)?;
     let mailer_account = next_account_info(account_iter)?;
-    let owner_usdc = next_account_info(account_iter)?;
-    let mailer_usdc = next_account_info(account_iter)?;
-    let token_program = next_account_info(account_iter)?;
+    let sanctioned_account = next_account_info(account_iter)?;
 
     if !owner.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    let (mailer_pda, _) = assert_mailer_account(_program_id, mailer_account)?;
+    assert_mailer_account(program_id, accounts, mailer_account)?;
 
-    // Load and update mailer state
-    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
-    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
 
-    if mailer_state.owner != *owner.key {
-        return Err(MailerError::OnlyOwner.into());
-    }
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
 
-    if mailer_state.owner_claimable == 0 {
-        return Err(MailerError::NoClaimableAmount.into());
+    let (sanctioned_pda, _) = pda::sanctioned(program_id, &address);
+
+    if sanctioned_account.key != &sanctioned_pda || sanctioned_account.owner != program_id {
+        return Err(MailerError::InvalidPDA.into());
     }
 
-    let amount = mailer_state.owner_claimable;
-    mailer_state.owner_claimable = 0;
-    mailer_state.serialize(&mut &mut mailer_data[8..])?;
-    drop(mailer_data);
+    let refunded = close_account(sanctioned_account, owner)?;
 
-    assert_token_program(token_program)?;
-    assert_token_account(owner_usdc, owner.key, &mailer_state.usdc_mint)?;
-    assert_token_account(mailer_usdc, &mailer_pda, &mailer_state.usdc_mint)?;
+    msg!(
+        "Address {} unsanctioned by {}, {} lamports refunded",
+        address,
+        owner.key,
+        refunded
+    );
+    Ok(())
+}
 
-    // Transfer USDC from mailer to owner
-    invoke_signed(
-        &spl_token::instruction::transfer(
-            token_program.key,
-            mailer_usdc.key,
-            owner_usdc.key,
-            mailer_account.key,
-            &[],
-            amount,
-        )?,
-        &[
-            mailer_usdc.clone(),
-            owner_usdc.clone(),
-            mailer_account.clone(),
-            token_program.clone(),
-        ],
-        &[&[b"mailer", &[mailer_state.bump]]],
-    )?;
+/// Enable or disable sanctions checks on sends and claims (owner only)
+fn process_set_sanctions_enabled(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    enabled: bool,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
 
-    msg!("Owner {} claimed {}", owner.key, amount);
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+
+    mailer_state.sanctions_enabled = enabled;
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+
+    msg!("Sanctions enabled set to: {}", enabled);
     Ok(())
 }
 
-/// Set send fee (owner only)
-fn process_set_fee(_program_id: &Pubkey, accounts: &[AccountInfo], new_fee: u64) -> ProgramResult {
+/// Enter contingency mode with a backup mint, or leave it (owner only)
+fn process_set_backup_mint(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    mint: Option<Pubkey>,
+) -> ProgramResult {
     let account_iter = &mut accounts.iter();
     let owner = next_account_info(account_iter)?;
     let mailer_account = next_account_info(account_iter)?;
@@ -1462,591 +14850,712 @@ fn process_set_fee(_program_id: &Pubkey, accounts: &[AccountInfo], new_fee: u64)
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    assert_mailer_account(_program_id, mailer_account)?;
+    assert_mailer_account(program_id, accounts, mailer_account)?;
 
-    // Load and update mailer state
     let mut mailer_data = mailer_account.try_borrow_mut_data()?;
     let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
 
-    if mailer_state.owner != *owner.key {
-        return Err(MailerError::OnlyOwner.into());
-    }
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
 
-    // Check if contract is paused
-    if mailer_state.paused {
-        return Err(MailerError::ContractPaused.into());
+    // The backup mint must already have an enabled conversion vault to pay claims from
+    if let Some(mint) = mint {
+        let conversion_account = next_account_info(account_iter)?;
+        let (conversion_pda, _) = pda::conversion_vault(program_id, &mint);
+        if conversion_account.key != &conversion_pda || conversion_account.owner != program_id {
+            return Err(MailerError::InvalidPDA.into());
+        }
+
+        let conversion_data = conversion_account.try_borrow_data()?;
+        let vault: ConversionVault = BorshDeserialize::deserialize(&mut &conversion_data[8..])?;
+        if !vault.enabled {
+            return Err(MailerError::BackupVaultUnavailable.into());
+        }
+
+        msg!(
+            "Contingency mode entered: backup mint {} paid from {}",
+            mint,
+            vault.vault
+        );
+    } else {
+        msg!("Contingency mode left");
     }
 
-    let old_fee = mailer_state.send_fee;
-    mailer_state.send_fee = new_fee;
+    mailer_state.backup_mint = mint;
     mailer_state.serialize(&mut &mut mailer_data[8..])?;
-
-    msg!("Fee updated from {} to {}", old_fee, new_fee);
     Ok(())
 }
 
-/// Delegate to another address
-fn process_delegate_to(
-    program_id: &Pubkey,
-    accounts: &[AccountInfo],
-    delegate: Option<Pubkey>,
-) -> ProgramResult {
+/// Create the program-wide stats account and require it on sends and claims (owner only)
+fn process_initialize_stats(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
     let account_iter = &mut accounts.iter();
-    let delegator = next_account_info(account_iter)?;
-    let delegation_account = next_account_info(account_iter)?;
+    let owner = next_account_info(account_iter)?;
     let mailer_account = next_account_info(account_iter)?;
-    let delegator_usdc = next_account_info(account_iter)?;
-    let mailer_usdc = next_account_info(account_iter)?;
-    let token_program = next_account_info(account_iter)?;
+    let stats_account = next_account_info(account_iter)?;
     let system_program = next_account_info(account_iter)?;
 
-    if !delegator.is_signer {
+    if !owner.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    let (mailer_pda, _) = assert_mailer_account(program_id, mailer_account)?;
+    assert_system_program(system_program)?;
+
+    assert_mailer_account(program_id, accounts, mailer_account)?;
 
-    // Load mailer state
     let mailer_data = mailer_account.try_borrow_data()?;
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
     drop(mailer_data);
 
-    assert_token_program(token_program)?;
-    assert_token_account(delegator_usdc, delegator.key, &mailer_state.usdc_mint)?;
-    assert_token_account(mailer_usdc, &mailer_pda, &mailer_state.usdc_mint)?;
-
-    // Check if contract is paused
-    if mailer_state.paused {
-        return Err(MailerError::ContractPaused.into());
-    }
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
 
-    // Verify delegation account PDA
-    let (delegation_pda, delegation_bump) =
-        Pubkey::find_program_address(&[b"delegation", &[PDA_VERSION], delegator.key.as_ref()], program_id);
+    let (stats_pda, stats_bump) = pda::stats(program_id);
 
-    if delegation_account.key != &delegation_pda {
+    if stats_account.key != &stats_pda {
         return Err(MailerError::InvalidPDA.into());
     }
 
-    // Create delegation account if needed
-    if delegation_account.lamports() == 0 {
-        let rent = Rent::get()?;
-        let space = 8 + Delegation::LEN;
-        let lamports = rent.minimum_balance(space);
-
-        invoke_signed(
-            &system_instruction::create_account(
-                delegator.key,
-                delegation_account.key,
-                lamports,
-                space as u64,
-                program_id,
-            ),
-            &[
-                delegator.clone(),
-                delegation_account.clone(),
-                system_program.clone(),
-            ],
-            &[&[b"delegation", &[PDA_VERSION], delegator.key.as_ref(), &[delegation_bump]]],
-        )?;
-
-        // Verify account is rent-exempt
-        let account_lamports = delegation_account.lamports();
-        if !rent.is_exempt(account_lamports, space) {
-            msg!("ERROR: Delegation account not rent-exempt! {} lamports for {} bytes",
-                 account_lamports, space);
-            return Err(ProgramError::InsufficientFunds);
-        }
-        msg!("Created rent-exempt delegation account: {} lamports for {} bytes",
-             account_lamports, space);
-
-        // Initialize delegation account
-        let mut delegation_data = delegation_account.try_borrow_mut_data()?;
-        delegation_data[0..8]
-            .copy_from_slice(&hash_discriminator("account:Delegation").to_le_bytes());
+    if stats_account.lamports() > 0 {
+        return Err(MailerError::AlreadyInitialized.into());
+    }
 
-        let delegation_state = Delegation {
-            delegator: *delegator.key,
-            delegate: None,
-            bump: delegation_bump,
-        };
+    let rent = Rent::get()?;
+    let space = 8 + MailerStats::LEN;
+    let lamports = rent.minimum_balance(space);
 
-        delegation_state.serialize(&mut &mut delegation_data[8..])?;
-        drop(delegation_data);
-    }
+    invoke_signed(
+        &system_instruction::create_account(
+            owner.key,
+            stats_account.key,
+            lamports,
+            space as u64,
+            program_id,
+        ),
+        &[owner.clone(), stats_account.clone(), system_program.clone()],
+        &[&[seeds::STATS, &[PDA_VERSION], &[stats_bump]]],
+    )?;
 
-    // If setting delegation (not clearing), charge fee (unless fee_paused)
-    if let Some(delegate_key) = delegate {
-        if delegate_key != Pubkey::default() && !mailer_state.fee_paused {
-            invoke(
-                &spl_token::instruction::transfer(
-                    token_program.key,
-                    delegator_usdc.key,
-                    mailer_usdc.key,
-                    delegator.key,
-                    &[],
-                    mailer_state.delegation_fee,
-                )?,
-                &[
-                    delegator_usdc.clone(),
-                    mailer_usdc.clone(),
-                    delegator.clone(),
-                    token_program.clone(),
-                ],
-            )?;
+    let mut stats_data = stats_account.try_borrow_mut_data()?;
+    stats_data[0..8].copy_from_slice(&discriminator::account("MailerStats"));
 
-            // Mirror EVM behavior: delegation fees become owner-claimable
-            let mut mailer_data_mut = mailer_account.try_borrow_mut_data()?;
-            let mut mailer_state_mut: MailerState =
-                BorshDeserialize::deserialize(&mut &mailer_data_mut[8..])?;
-            mailer_state_mut.increase_owner_claimable(mailer_state.delegation_fee)?;
-            mailer_state_mut.serialize(&mut &mut mailer_data_mut[8..])?;
-            drop(mailer_data_mut);
-        }
-    }
+    let stats = MailerStats {
+        bump: stats_bump,
+        ..MailerStats::default()
+    };
+    stats.serialize(&mut &mut stats_data[8..])?;
+    drop(stats_data);
 
-    // Update delegation
-    let mut delegation_data = delegation_account.try_borrow_mut_data()?;
-    let mut delegation_state: Delegation =
-        BorshDeserialize::deserialize(&mut &delegation_data[8..])?;
-    delegation_state.delegate = delegate;
-    delegation_state.serialize(&mut &mut delegation_data[8..])?;
+    mailer_state.stats_enabled = true;
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
 
-    msg!("Delegation set from {} to {:?}", delegator.key, delegate);
+    msg!("Mailer stats {} initialized", stats_account.key);
     Ok(())
 }
 
-/// Reject delegation
-fn process_reject_delegation(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+/// Opt a recipient's USDC claim in or out of no-expiry status
+fn process_set_claim_no_expiry(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    enabled: bool,
+) -> ProgramResult {
     let account_iter = &mut accounts.iter();
-    let rejector = next_account_info(account_iter)?;
-    let delegation_account = next_account_info(account_iter)?;
+    let recipient = next_account_info(account_iter)?;
+    let recipient_claim = next_account_info(account_iter)?;
     let mailer_account = next_account_info(account_iter)?;
 
-    if !rejector.is_signer {
+    if !recipient.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    // Verify mailer state PDA and ensure contract is not paused
-    let (_mailer_pda, _) = assert_mailer_account(program_id, mailer_account)?;
+    assert_mailer_account(program_id, accounts, mailer_account)?;
 
-    let mailer_data = mailer_account.try_borrow_data()?;
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
-    drop(mailer_data);
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
 
-    if mailer_state.paused {
-        return Err(MailerError::ContractPaused.into());
+    let (claim_pda, _) = pda::recipient_claim(program_id, recipient.key);
+    if recipient_claim.key != &claim_pda || recipient_claim.owner != program_id {
+        return Err(MailerError::InvalidPDA.into());
     }
 
-    // Load and update delegation state
-    let mut delegation_data = delegation_account.try_borrow_mut_data()?;
-    let mut delegation_state: Delegation =
-        BorshDeserialize::deserialize(&mut &delegation_data[8..])?;
+    let mut claim_data = recipient_claim.try_borrow_mut_data()?;
+    let mut claim_state = RecipientClaim::from_account_data(&claim_data[8..])?;
 
-    // Verify the rejector is the current delegate
-    if delegation_state.delegate != Some(*rejector.key) {
-        return Err(MailerError::NoDelegationToReject.into());
+    if claim_state.recipient != *recipient.key {
+        return Err(MailerError::InvalidRecipient.into());
     }
 
-    delegation_state.delegate = None;
-    delegation_state.serialize(&mut &mut delegation_data[8..])?;
+    // Periods already started are owed whichever way the status changes
+    let current_time = Clock::get()?.unix_timestamp;
+    let mut surcharge = mailer_state.settle_no_expiry_surcharge(&mut claim_state, current_time);
+
+    if enabled && !claim_state.no_expiry {
+        // An expired balance belongs to the owner and cannot be insured back
+        if claim_state.amount > 0
+            && current_time > mailer_state.claim_expiry(&claim_state, current_time)
+        {
+            return Err(MailerError::ClaimPeriodExpired.into());
+        }
+        if claim_state.amount < NO_EXPIRY_SURCHARGE {
+            return Err(MailerError::NoExpirySurchargeUnpaid.into());
+        }
 
-    msg!("Delegation rejected by {}", rejector.key);
+        claim_state.amount -= NO_EXPIRY_SURCHARGE;
+        surcharge += NO_EXPIRY_SURCHARGE;
+        claim_state.no_expiry = true;
+        claim_state.surcharge_paid_until = current_time.saturating_add(NO_EXPIRY_PERIOD);
+    } else if !enabled && claim_state.no_expiry {
+        claim_state.no_expiry = false;
+        if claim_state.amount > 0 {
+            claim_state.timestamp = current_time;
+            claim_state.paused_offset = mailer_state.paused_duration_at(current_time);
+        }
+    }
+
+    claim_state.write_account_data(&mut claim_data[8..])?;
+    drop(claim_data);
+
+    mailer_state.move_claim_to_owner(surcharge)?;
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+
+    msg!(
+        "Claim no-expiry for {}: {} (surcharge {}, paid until {})",
+        recipient.key,
+        claim_state.no_expiry,
+        surcharge,
+        claim_state.surcharge_paid_until
+    );
     Ok(())
 }
 
-/// Set delegation fee (owner only)
-fn process_set_delegation_fee(
-    _program_id: &Pubkey,
-    accounts: &[AccountInfo],
-    new_fee: u64,
-) -> ProgramResult {
+/// Restart the caller's USDC claim window for a fee debited from the balance
+fn process_extend_claim_window(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
     let account_iter = &mut accounts.iter();
-    let owner = next_account_info(account_iter)?;
+    let recipient = next_account_info(account_iter)?;
+    let recipient_claim = next_account_info(account_iter)?;
     let mailer_account = next_account_info(account_iter)?;
 
-    if !owner.is_signer {
+    if !recipient.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    assert_mailer_account(_program_id, mailer_account)?;
+    assert_mailer_account(program_id, accounts, mailer_account)?;
 
-    // Load and update mailer state
     let mut mailer_data = mailer_account.try_borrow_mut_data()?;
     let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
 
-    if mailer_state.owner != *owner.key {
-        return Err(MailerError::OnlyOwner.into());
+    if mailer_state.is_paused(PAUSE_CLAIM) {
+        return Err(MailerError::ContractPaused.into());
     }
 
-    // Check if contract is paused
-    if mailer_state.paused {
-        return Err(MailerError::ContractPaused.into());
+    let (claim_pda, _) = pda::recipient_claim(program_id, recipient.key);
+    if recipient_claim.key != &claim_pda || recipient_claim.owner != program_id {
+        return Err(MailerError::InvalidPDA.into());
     }
 
-    let old_fee = mailer_state.delegation_fee;
-    mailer_state.delegation_fee = new_fee;
+    let mut claim_data = recipient_claim.try_borrow_mut_data()?;
+    let mut claim_state = RecipientClaim::from_account_data(&claim_data[8..])?;
+
+    if claim_state.recipient != *recipient.key {
+        return Err(MailerError::InvalidRecipient.into());
+    }
+
+    // No-expiry surcharges due are settled before the window moves
+    let current_time = Clock::get()?.unix_timestamp;
+    let surcharge = mailer_state.settle_no_expiry_surcharge(&mut claim_state, current_time);
+
+    if claim_state.amount == 0 {
+        return Err(MailerError::NoClaimableAmount.into());
+    }
+    if current_time > mailer_state.claim_expiry(&claim_state, current_time) {
+        return Err(MailerError::ClaimPeriodExpired.into());
+    }
+    if claim_state.extensions >= MAX_CLAIM_EXTENSIONS {
+        return Err(MailerError::ClaimExtensionLimitReached.into());
+    }
+    if claim_state.amount < CLAIM_EXTENSION_FEE {
+        return Err(MailerError::ClaimExtensionFeeUnpaid.into());
+    }
+
+    claim_state.amount -= CLAIM_EXTENSION_FEE;
+    claim_state.timestamp = current_time;
+    claim_state.paused_offset = mailer_state.paused_duration_at(current_time);
+    claim_state.extensions += 1;
+    claim_state.write_account_data(&mut claim_data[8..])?;
+    drop(claim_data);
+
+    mailer_state.move_claim_to_owner(surcharge + CLAIM_EXTENSION_FEE)?;
     mailer_state.serialize(&mut &mut mailer_data[8..])?;
 
-    msg!("Delegation fee updated from {} to {}", old_fee, new_fee);
+    msg!(
+        "Claim window extended for {}: {} remaining (fee {}, extension {} of {})",
+        recipient.key,
+        claim_state.amount,
+        CLAIM_EXTENSION_FEE,
+        claim_state.extensions,
+        MAX_CLAIM_EXTENSIONS
+    );
     Ok(())
 }
 
-/// Set custom fee percentage for a specific address (owner only)
-fn process_set_custom_fee_percentage(
+/// Register, replace or remove the backup beneficiary of the caller's USDC claim
+fn process_set_claim_beneficiary(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
-    account: Pubkey,
-    percentage: u8,
+    beneficiary: Option<Pubkey>,
 ) -> ProgramResult {
     let account_iter = &mut accounts.iter();
-    let owner = next_account_info(account_iter)?;
+    let recipient = next_account_info(account_iter)?;
+    let beneficiary_account = next_account_info(account_iter)?;
     let mailer_account = next_account_info(account_iter)?;
-    let fee_discount_account = next_account_info(account_iter)?;
-    let _target_account = next_account_info(account_iter)?;
-    let payer = next_account_info(account_iter)?;
     let system_program = next_account_info(account_iter)?;
 
-    if !owner.is_signer {
+    if !recipient.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    assert_mailer_account(program_id, mailer_account)?;
-
-    // Load mailer state and verify owner
-    let mailer_data = mailer_account.try_borrow_data()?;
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
-    drop(mailer_data);
-
-    if mailer_state.owner != *owner.key {
-        return Err(MailerError::OnlyOwner.into());
-    }
-
-    // Check if contract is paused
-    if mailer_state.paused {
-        return Err(MailerError::ContractPaused.into());
-    }
-
-    // Validate percentage
-    if percentage > 100 {
-        return Err(MailerError::InvalidPercentage.into());
-    }
+    assert_system_program(system_program)?;
 
-    // Verify fee discount account PDA
-    let (discount_pda, bump) =
-        Pubkey::find_program_address(&[b"discount", &[PDA_VERSION], account.as_ref()], program_id);
+    assert_mailer_account(program_id, accounts, mailer_account)?;
 
-    if fee_discount_account.key != &discount_pda {
+    let (beneficiary_pda, beneficiary_bump) = pda::claim_beneficiary(program_id, recipient.key);
+    if beneficiary_account.key != &beneficiary_pda {
         return Err(MailerError::InvalidPDA.into());
     }
 
-    // Create or update fee discount account
-    if fee_discount_account.lamports() == 0 {
+    let beneficiary = match beneficiary {
+        Some(beneficiary) if beneficiary == *recipient.key => {
+            return Err(MailerError::InvalidRecipient.into());
+        }
+        Some(beneficiary) => beneficiary,
+        None => {
+            if beneficiary_account.owner == program_id && beneficiary_account.lamports() > 0 {
+                close_account(beneficiary_account, recipient)?;
+            }
+            msg!("Claim beneficiary for {} removed", recipient.key);
+            return Ok(());
+        }
+    };
+
+    // Create claim beneficiary account if needed
+    if beneficiary_account.lamports() == 0 {
         let rent = Rent::get()?;
-        let space = 8 + FeeDiscount::LEN;
+        let space = 8 + ClaimBeneficiary::LEN;
         let lamports = rent.minimum_balance(space);
 
         invoke_signed(
             &system_instruction::create_account(
-                payer.key,
-                fee_discount_account.key,
+                recipient.key,
+                beneficiary_account.key,
                 lamports,
                 space as u64,
                 program_id,
             ),
             &[
-                payer.clone(),
-                fee_discount_account.clone(),
+                recipient.clone(),
+                beneficiary_account.clone(),
                 system_program.clone(),
             ],
-            &[&[b"discount", &[PDA_VERSION], account.as_ref(), &[bump]]],
+            &[&[
+                seeds::BENEFICIARY,
+                &[PDA_VERSION],
+                recipient.key.as_ref(),
+                &[beneficiary_bump],
+            ]],
         )?;
 
-        // Verify account is rent-exempt
-        let account_lamports = fee_discount_account.lamports();
-        if !rent.is_exempt(account_lamports, space) {
-            msg!("ERROR: Fee discount account not rent-exempt! {} lamports for {} bytes",
-                 account_lamports, space);
-            return Err(ProgramError::InsufficientFunds);
-        }
-        msg!("Created rent-exempt fee discount account: {} lamports for {} bytes",
-             account_lamports, space);
-
-        // Initialize discount account
-        let mut discount_data = fee_discount_account.try_borrow_mut_data()?;
-        discount_data[0..8]
-            .copy_from_slice(&hash_discriminator("account:FeeDiscount").to_le_bytes());
+        let mut beneficiary_data = beneficiary_account.try_borrow_mut_data()?;
+        beneficiary_data[0..8].copy_from_slice(&discriminator::account("ClaimBeneficiary"));
+    }
 
-        let fee_discount = FeeDiscount {
-            account,
-            discount: 100 - percentage, // Store as discount: 0% fee = 100 discount, 100% fee = 0 discount
-            bump,
-        };
+    let record = ClaimBeneficiary {
+        recipient: *recipient.key,
+        beneficiary,
+        bump: beneficiary_bump,
+    };
 
-        fee_discount.serialize(&mut &mut discount_data[8..])?;
-    } else {
-        // Update existing discount account
-        let mut discount_data = fee_discount_account.try_borrow_mut_data()?;
-        let mut fee_discount: FeeDiscount =
-            BorshDeserialize::deserialize(&mut &discount_data[8..])?;
-        fee_discount.discount = 100 - percentage; // Store as discount
-        fee_discount.serialize(&mut &mut discount_data[8..])?;
-    }
+    let mut beneficiary_data = beneficiary_account.try_borrow_mut_data()?;
+    record.serialize(&mut &mut beneficiary_data[8..])?;
 
-    msg!("Custom fee percentage set for {}: {}%", account, percentage);
+    msg!(
+        "Claim beneficiary for {} set to {}",
+        recipient.key,
+        beneficiary
+    );
     Ok(())
 }
 
-/// Clear custom fee percentage for a specific address (owner only)
-fn process_clear_custom_fee_percentage(
+/// Collect an expired USDC claim as the recipient's registered beneficiary
+fn process_claim_as_beneficiary(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
-    account: Pubkey,
+    recipient: Pubkey,
 ) -> ProgramResult {
     let account_iter = &mut accounts.iter();
-    let owner = next_account_info(account_iter)?;
+    let beneficiary = next_account_info(account_iter)?;
+    let beneficiary_account = next_account_info(account_iter)?;
+    let recipient_claim = next_account_info(account_iter)?;
     let mailer_account = next_account_info(account_iter)?;
-    let fee_discount_account = next_account_info(account_iter)?;
+    let beneficiary_usdc = next_account_info(account_iter)?;
+    let mailer_usdc = next_account_info(account_iter)?;
+    let token_program = next_account_info(account_iter)?;
 
-    if !owner.is_signer {
+    if !beneficiary.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    assert_mailer_account(program_id, mailer_account)?;
+    let (mailer_pda, _) = assert_mailer_account(program_id, accounts, mailer_account)?;
+    assert_token_program(token_program)?;
 
-    // Load mailer state and verify owner
-    let mailer_data = mailer_account.try_borrow_data()?;
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
-    drop(mailer_data);
+    // Verify the signer is the recipient's registered beneficiary
+    let (beneficiary_pda, _) = pda::claim_beneficiary(program_id, &recipient);
+    if beneficiary_account.key != &beneficiary_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
+    if beneficiary_account.owner != program_id || beneficiary_account.lamports() == 0 {
+        return Err(MailerError::NotClaimBeneficiary.into());
+    }
+    let beneficiary_data = beneficiary_account.try_borrow_data()?;
+    let record: ClaimBeneficiary = BorshDeserialize::deserialize(&mut &beneficiary_data[8..])?;
+    drop(beneficiary_data);
+    if record.recipient != recipient || record.beneficiary != *beneficiary.key {
+        return Err(MailerError::NotClaimBeneficiary.into());
+    }
 
-    if mailer_state.owner != *owner.key {
-        return Err(MailerError::OnlyOwner.into());
+    let (claim_pda, _) = pda::recipient_claim(program_id, &recipient);
+    if recipient_claim.key != &claim_pda || recipient_claim.owner != program_id {
+        return Err(MailerError::InvalidPDA.into());
     }
 
-    // Check if contract is paused
-    if mailer_state.paused {
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    if mailer_state.is_paused(PAUSE_CLAIM) {
         return Err(MailerError::ContractPaused.into());
     }
 
-    // Verify fee discount account PDA
-    let (discount_pda, _) =
-        Pubkey::find_program_address(&[b"discount", &[PDA_VERSION], account.as_ref()], program_id);
+    assert_not_sanctioned(program_id, &mailer_state, beneficiary.key, accounts)?;
+    assert_token_account(
+        accounts,
+        "beneficiary_usdc",
+        beneficiary_usdc,
+        beneficiary.key,
+        &mailer_state.usdc_mint,
+    )?;
+    assert_token_account(
+        accounts,
+        "mailer_usdc",
+        mailer_usdc,
+        &mailer_pda,
+        &mailer_state.usdc_mint,
+    )?;
 
-    if fee_discount_account.key != &discount_pda {
-        return Err(MailerError::InvalidPDA.into());
+    let mut claim_data = recipient_claim.try_borrow_mut_data()?;
+    let mut claim_state = RecipientClaim::from_account_data(&claim_data[8..])?;
+
+    if claim_state.recipient != recipient {
+        return Err(MailerError::InvalidRecipient.into());
     }
 
-    // Clear by setting discount to 0 (no discount = 100% fee = default behavior)
-    if fee_discount_account.lamports() > 0 {
-        let mut discount_data = fee_discount_account.try_borrow_mut_data()?;
-        let mut fee_discount: FeeDiscount =
-            BorshDeserialize::deserialize(&mut &discount_data[8..])?;
-        fee_discount.discount = 0; // 0 discount = 100% fee = default
-        fee_discount.serialize(&mut &mut discount_data[8..])?;
+    // No-expiry surcharges due are settled before the expiry is checked
+    let current_time = Clock::get()?.unix_timestamp;
+    let surcharge = mailer_state.settle_no_expiry_surcharge(&mut claim_state, current_time);
+
+    if claim_state.amount == 0 {
+        return Err(MailerError::NoClaimableAmount.into());
+    }
+    let expiry = mailer_state.claim_expiry(&claim_state, current_time);
+    if current_time <= expiry {
+        return Err(MailerError::ClaimPeriodNotExpired.into());
+    }
+    if current_time > expiry.saturating_add(BENEFICIARY_GRACE_PERIOD) {
+        return Err(MailerError::BeneficiaryGracePeriodEnded.into());
     }
 
+    let amount = claim_state.amount;
+    claim_state.amount = 0;
+    claim_state.timestamp = 0;
+    claim_state.write_account_data(&mut claim_data[8..])?;
+    drop(claim_data);
+
+    mailer_state.move_claim_to_owner(surcharge)?;
+    mailer_state.release_recipient_liability(amount);
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+    drop(mailer_data);
+
+    invoke_signed(
+        &spl_token::instruction::transfer(
+            token_program.key,
+            mailer_usdc.key,
+            beneficiary_usdc.key,
+            mailer_account.key,
+            &[],
+            amount,
+        )?,
+        &[
+            mailer_usdc.clone(),
+            beneficiary_usdc.clone(),
+            mailer_account.clone(),
+            token_program.clone(),
+        ],
+        &[&[seeds::MAILER, &[mailer_state.bump]]],
+    )?;
+
     msg!(
-        "Custom fee percentage cleared for {} (reset to 100%)",
-        account
+        "Beneficiary {} claimed expired shares of {}: {}",
+        beneficiary.key,
+        recipient,
+        amount
     );
+    record_claim_stats(program_id, &mailer_state, accounts, amount)?;
     Ok(())
 }
 
-fn assert_token_program(token_program: &AccountInfo) -> Result<(), ProgramError> {
-    if token_program.key != &spl_token::id() {
-        return Err(MailerError::InvalidTokenProgram.into());
+/// Rewrite legacy discriminators of the supplied accounts
+fn process_migrate_discriminator(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    if accounts.is_empty() {
+        return Err(ProgramError::NotEnoughAccountKeys);
     }
-    Ok(())
-}
 
-fn assert_token_account(
-    token_account_info: &AccountInfo,
-    expected_owner: &Pubkey,
-    expected_mint: &Pubkey,
-) -> Result<(), ProgramError> {
-    let data = token_account_info.try_borrow_data()?;
-    let token_account = TokenAccount::unpack(&data)?;
-    drop(data);
+    for account in accounts {
+        if account.owner != program_id {
+            return Err(MailerError::InvalidAccountOwner.into());
+        }
 
-    if token_account.owner != *expected_owner {
-        return Err(MailerError::InvalidAccountOwner.into());
-    }
+        let mut data = account.try_borrow_mut_data()?;
+        let identified = data.get(..8).and_then(discriminator::identify);
 
-    if token_account.mint != *expected_mint {
-        return Err(MailerError::InvalidMint.into());
+        match identified {
+            Some((name, discriminator::Version::Legacy)) => {
+                data[..8].copy_from_slice(&discriminator::account(name));
+                msg!("Migrated {} discriminator of {}", name, account.key);
+            }
+            Some((name, discriminator::Version::Sha256)) => {
+                msg!("{} discriminator of {} already migrated", name, account.key);
+            }
+            None => return Err(MailerError::UnknownDiscriminator.into()),
+        }
     }
 
     Ok(())
 }
 
-fn assert_mailer_account(
-    program_id: &Pubkey,
-    mailer_account: &AccountInfo,
-) -> Result<(Pubkey, u8), ProgramError> {
-    let (mailer_pda, bump) = Pubkey::find_program_address(&[b"mailer"], program_id);
-    if mailer_account.key != &mailer_pda {
+/// Upgrade the mailer state account to the next layout version (owner only)
+fn process_migrate_state(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    assert_system_program(system_program)?;
+
+    // assert_mailer_account rejects earlier layouts, so the PDA is verified here
+    let (mailer_pda, _) = pda::mailer_state(program_id);
+    if mailer_account.key != &mailer_pda || mailer_account.owner != program_id {
         return Err(MailerError::InvalidPDA.into());
     }
-    Ok((mailer_pda, bump))
-}
 
-/// Record revenue shares for priority messages
-fn record_shares(
-    recipient_claim: &AccountInfo,
-    mailer_account: &AccountInfo,
-    recipient: Pubkey,
-    total_amount: u64,
-) -> ProgramResult {
-    let owner_amount = total_amount / 10; // 10% of total_amount
-    let recipient_amount = total_amount - owner_amount;
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let state = VersionedMailerState::deserialize(&mailer_data[8..])?;
+    drop(mailer_data);
 
-    // Update recipient's claimable amount and refresh the timestamp to extend the 60-day window
-    let mut claim_data = recipient_claim.try_borrow_mut_data()?;
-    let mut claim_state: RecipientClaim = BorshDeserialize::deserialize(&mut &claim_data[8..])?;
+    let from_version = state.version();
+    let upgraded = state.upgrade().ok_or(MailerError::StateAlreadyCurrent)?;
 
-    claim_state.recipient = recipient;
-    claim_state.amount += recipient_amount;
-    claim_state.timestamp = Clock::get()?.unix_timestamp;
-    claim_state.serialize(&mut &mut claim_data[8..])?;
-    drop(claim_data);
+    // Every layout keeps the owner, so authority is checked against the current form
+    assert_owner_authority(
+        program_id,
+        &upgraded.clone().into_current(),
+        owner,
+        accounts,
+    )?;
+
+    // Top up the rent for the larger account before growing it
+    let space = 8 + upgraded.data_len();
+    let shortfall = Rent::get()?
+        .minimum_balance(space)
+        .saturating_sub(mailer_account.lamports());
+    if shortfall > 0 {
+        invoke(
+            &system_instruction::transfer(owner.key, mailer_account.key, shortfall),
+            &[
+                owner.clone(),
+                mailer_account.clone(),
+                system_program.clone(),
+            ],
+        )?;
+    }
+    mailer_account.realloc(space, true)?;
 
-    // Update owner's claimable amount
     let mut mailer_data = mailer_account.try_borrow_mut_data()?;
-    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
-    mailer_state.increase_owner_claimable(owner_amount)?;
-    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+    upgraded.serialize(&mut mailer_data[8..])?;
 
     msg!(
-        "Shares recorded: recipient {}, owner {}",
-        recipient_amount,
-        owner_amount
+        "Mailer state migrated from version {} to {} ({} bytes)",
+        from_version,
+        upgraded.version(),
+        space
     );
     Ok(())
 }
 
-/// Calculate the effective fee for an account based on custom discount
-/// Optimized with early returns for common cases (no discount, full discount)
-fn calculate_fee_with_discount(
+/// Set or clear the treasury token account receiving the owner share of USDC send fees (owner only)
+fn process_set_treasury(
     program_id: &Pubkey,
-    account: &Pubkey,
     accounts: &[AccountInfo],
-    base_fee: u64,
-) -> Result<u64, ProgramError> {
-    // Try to find fee discount account
-    let (discount_pda, _) =
-        Pubkey::find_program_address(&[b"discount", &[PDA_VERSION], account.as_ref()], program_id);
+    token_account: Option<Pubkey>,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
 
-    // Check if any account in the accounts slice matches the discount PDA
-    let discount_account = accounts.iter().find(|acc| acc.key == &discount_pda);
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
 
-    if let Some(discount_acc) = discount_account {
-        // Account exists and has lamports - load the discount
-        if discount_acc.lamports() > 0 {
-            let discount_data = discount_acc.try_borrow_data()?;
-            if discount_data.len() >= 8 + FeeDiscount::LEN {
-                let fee_discount: FeeDiscount =
-                    BorshDeserialize::deserialize(&mut &discount_data[8..])?;
-                let discount = fee_discount.discount;
+    assert_mailer_account(program_id, accounts, mailer_account)?;
 
-                // Early return for no discount (most common case - saves computation)
-                if discount == 0 {
-                    return Ok(base_fee);
-                }
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
 
-                // Early return for full discount (free)
-                if discount == 100 {
-                    return Ok(0);
-                }
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
 
-                // Apply discount: fee = base_fee * (100 - discount) / 100
-                // Examples: discount=50 → 50% fee, discount=25 → 75% fee
-                let effective_fee = (base_fee * (100 - discount as u64)) / 100;
-                return Ok(effective_fee);
-            }
+    if mailer_state.is_paused(PAUSE_ADMIN) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    // Only the treasury PDA may move funds out of the treasury account
+    if let Some(token_account) = token_account {
+        let treasury_usdc = next_account_info(account_iter)?;
+        if treasury_usdc.key != &token_account {
+            return Err(MailerError::InvalidTreasury.into());
         }
+        let (treasury_pda, _) = pda::treasury(program_id);
+        assert_token_account(
+            accounts,
+            "treasury_usdc",
+            treasury_usdc,
+            &treasury_pda,
+            &mailer_state.usdc_mint,
+        )
+        .map_err(|_| MailerError::InvalidTreasury)?;
+
+        msg!("Treasury set to {}", token_account);
+    } else {
+        msg!("Treasury cleared; owner share accrues in the mailer vault");
     }
 
-    // No discount account or uninitialized - use full fee (default behavior)
-    Ok(base_fee)
+    mailer_state.treasury = token_account;
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+    Ok(())
 }
 
-/// Pause the contract and distribute owner claimable funds
-fn process_pause(_program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+/// Enable or disable protocol-internal sends (owner only)
+fn process_set_protocol_sends_enabled(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    enabled: bool,
+) -> ProgramResult {
     let account_iter = &mut accounts.iter();
     let owner = next_account_info(account_iter)?;
     let mailer_account = next_account_info(account_iter)?;
-    let owner_usdc = next_account_info(account_iter)?;
-    let mailer_usdc = next_account_info(account_iter)?;
-    let token_program = next_account_info(account_iter)?;
 
     if !owner.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    let (mailer_pda, _) = assert_mailer_account(_program_id, mailer_account)?;
+    assert_mailer_account(_program_id, accounts, mailer_account)?;
 
     // Load and update mailer state
     let mut mailer_data = mailer_account.try_borrow_mut_data()?;
     let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
 
-    // Verify owner
-    if mailer_state.owner != *owner.key {
-        return Err(MailerError::OnlyOwner.into());
-    }
-
-    // Check if already paused
-    if mailer_state.paused {
-        return Err(MailerError::ContractPaused.into());
-    }
+    assert_owner_authority(_program_id, &mailer_state, owner, accounts)?;
 
-    // Set paused state
-    mailer_state.paused = true;
+    mailer_state.protocol_sends_enabled = enabled;
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
 
-    assert_token_program(token_program)?;
+    msg!("Protocol sends enabled set to: {}", enabled);
+    Ok(())
+}
 
-    // Distribute owner claimable funds if any
-    if mailer_state.owner_claimable > 0 {
-        let amount = mailer_state.owner_claimable;
-        mailer_state.owner_claimable = 0;
+/// Send a fee-exempt protocol-origin message (owner only)
+fn process_send_protocol_message(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    to: Pubkey,
+    subject: String,
+    _body: String,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
 
-        assert_token_account(owner_usdc, owner.key, &mailer_state.usdc_mint)?;
-        assert_token_account(mailer_usdc, &mailer_pda, &mailer_state.usdc_mint)?;
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
 
-        // Save updated state BEFORE external call (CEI pattern)
-        mailer_state.serialize(&mut &mut mailer_data[8..])?;
-        drop(mailer_data); // Release borrow before external call
+    let (mailer_pda, _) = assert_mailer_account(_program_id, accounts, mailer_account)?;
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
 
-        // Transfer USDC from mailer to owner
-        invoke_signed(
-            &spl_token::instruction::transfer(
-                token_program.key,
-                mailer_usdc.key,
-                owner_usdc.key,
-                &mailer_pda,
-                &[],
-                amount,
-            )?,
-            &[
-                mailer_usdc.clone(),
-                owner_usdc.clone(),
-                mailer_account.clone(),
-                token_program.clone(),
-            ],
-            &[&[b"mailer", &[mailer_state.bump]]],
-        )?;
+    assert_owner_authority(_program_id, &mailer_state, owner, accounts)?;
 
-        msg!("Distributed owner funds during pause: {}", amount);
-    } else {
-        // Save updated state even if no distribution
-        mailer_state.serialize(&mut &mut mailer_data[8..])?;
+    // Check if contract is paused
+    if mailer_state.is_paused(PAUSE_SEND) {
+        return Err(MailerError::ContractPaused.into());
+    }
+
+    if !mailer_state.protocol_sends_enabled {
+        return Err(MailerError::ProtocolSendsDisabled.into());
     }
 
-    msg!("Contract paused by owner: {}", owner.key);
-    Ok(())
+    // The mailer PDA is the sender; the owner funds its nonce account
+    let message_nonce = next_message_nonce(_program_id, &mailer_pda, owner, accounts)?;
+    let payload_hash = hash_message_body(&subject, &_body);
+    let message_id = derive_message_id(
+        &mailer_pda,
+        to.as_ref(),
+        &payload_hash,
+        Clock::get()?.slot,
+        message_nonce,
+    );
+
+    log_protocol_message(&mailer_pda, &to, &subject, Some(&message_id));
+
+    report_send(message_id)
 }
 
-/// Unpause the contract
-fn process_unpause(_program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+/// Emit a protocol-origin message log, with the message id of a SendProtocolMessage
+/// Protocol messages are sent by the mailer PDA itself: no fee is charged and no
+/// revenue share is recorded, so owner fees are never routed back to the owner.
+fn log_protocol_message(
+    mailer_pda: &Pubkey,
+    to: &Pubkey,
+    subject: &str,
+    message_id: Option<&[u8; 32]>,
+) {
+    msg!(
+        "{}Protocol mail sent from {} to {}: {} (protocol origin, effective fee: 0, fee paid: true{})",
+        recipient_log_prefix(to),
+        mailer_pda,
+        to,
+        subject,
+        message_id
+            .map(|message_id| thread_detail(message_id, None, None))
+            .unwrap_or_default()
+    );
+}
+
+/// Propose a new owner (owner only)
+fn process_transfer_ownership(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    new_owner: Pubkey,
+) -> ProgramResult {
     let account_iter = &mut accounts.iter();
     let owner = next_account_info(account_iter)?;
     let mailer_account = next_account_info(account_iter)?;
@@ -2055,240 +15564,508 @@ fn process_unpause(_program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramRes
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    assert_mailer_account(_program_id, mailer_account)?;
+    assert_mailer_account(_program_id, accounts, mailer_account)?;
 
     // Load and update mailer state
     let mut mailer_data = mailer_account.try_borrow_mut_data()?;
     let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
 
-    // Verify owner
-    if mailer_state.owner != *owner.key {
-        return Err(MailerError::OnlyOwner.into());
+    assert_owner_authority(_program_id, &mailer_state, owner, accounts)?;
+
+    mailer_state.pending_owner = Some(new_owner);
+    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+
+    msg!(
+        "Ownership transfer proposed from {} to {}",
+        owner.key,
+        new_owner
+    );
+    Ok(())
+}
+
+/// Accept a pending ownership transfer (pending owner only)
+fn process_accept_ownership(_program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    let new_owner = next_account_info(account_iter)?;
+    let mailer_account = next_account_info(account_iter)?;
+
+    if !new_owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
     }
 
-    // Check if not paused
-    if !mailer_state.paused {
-        return Err(MailerError::ContractNotPaused.into());
+    assert_mailer_account(_program_id, accounts, mailer_account)?;
+
+    // Load and update mailer state
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+
+    if mailer_state.pending_owner != Some(*new_owner.key) {
+        return Err(MailerError::NotPendingOwner.into());
     }
 
-    // Set unpaused state
-    mailer_state.paused = false;
+    let previous_owner = mailer_state.owner;
+    mailer_state.owner = *new_owner.key;
+    mailer_state.pending_owner = None;
     mailer_state.serialize(&mut &mut mailer_data[8..])?;
 
-    msg!("Contract unpaused by owner: {}", owner.key);
+    msg!(
+        "Ownership transferred from {} to {}",
+        previous_owner,
+        new_owner.key
+    );
     Ok(())
 }
 
-/// Distribute claimable funds when contract is paused
-fn process_distribute_claimable_funds(
-    _program_id: &Pubkey,
+/// Configure the admin set (owner or admin quorum)
+fn process_set_admins(
+    program_id: &Pubkey,
     accounts: &[AccountInfo],
-    recipient: Pubkey,
+    admins: Vec<Pubkey>,
+    threshold: u8,
 ) -> ProgramResult {
     let account_iter = &mut accounts.iter();
-    let _caller = next_account_info(account_iter)?; // Anyone can call
+    let owner = next_account_info(account_iter)?;
     let mailer_account = next_account_info(account_iter)?;
-    let recipient_claim_account = next_account_info(account_iter)?;
-    let recipient_usdc = next_account_info(account_iter)?;
-    let mailer_usdc = next_account_info(account_iter)?;
-    let token_program = next_account_info(account_iter)?;
+    let admins_account = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
 
-    let (mailer_pda, _) = assert_mailer_account(_program_id, mailer_account)?;
+    assert_system_program(system_program)?;
 
-    // Load mailer state to check if paused
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    // Load mailer state and verify authority
     let mailer_data = mailer_account.try_borrow_data()?;
     let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
     drop(mailer_data);
 
-    // Check if contract is paused
-    if !mailer_state.paused {
-        return Err(MailerError::ContractNotPaused.into());
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+
+    // Validate configuration: bounded size, no duplicates, reachable threshold
+    if admins.len() > MAX_ADMINS || threshold as usize > admins.len() {
+        return Err(MailerError::InvalidAdminConfig.into());
+    }
+    if !admins.is_empty() && threshold == 0 {
+        return Err(MailerError::InvalidAdminConfig.into());
+    }
+    for (i, admin) in admins.iter().enumerate() {
+        if admins[..i].contains(admin) {
+            return Err(MailerError::InvalidAdminConfig.into());
+        }
     }
 
-    // Verify recipient claim PDA
-    let (claim_pda, _) = Pubkey::find_program_address(&[b"claim", &[PDA_VERSION], recipient.as_ref()], _program_id);
-    if recipient_claim_account.key != &claim_pda {
+    // Verify admin set PDA
+    let (admins_pda, admins_bump) = pda::admin_set(program_id);
+
+    if admins_account.key != &admins_pda {
         return Err(MailerError::InvalidPDA.into());
     }
 
-    assert_token_program(token_program)?;
+    // Create admin set account if needed
+    if admins_account.lamports() == 0 {
+        let rent = Rent::get()?;
+        let space = 8 + AdminSet::LEN;
+        let lamports = rent.minimum_balance(space);
 
-    // Load and update recipient claim
-    let mut claim_data = recipient_claim_account.try_borrow_mut_data()?;
-    let mut claim_state: RecipientClaim = BorshDeserialize::deserialize(&mut &claim_data[8..])?;
+        invoke_signed(
+            &system_instruction::create_account(
+                owner.key,
+                admins_account.key,
+                lamports,
+                space as u64,
+                program_id,
+            ),
+            &[
+                owner.clone(),
+                admins_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[seeds::ADMINS, &[PDA_VERSION], &[admins_bump]]],
+        )?;
 
-    if claim_state.amount == 0 {
-        return Err(MailerError::NoClaimableAmount.into());
+        let mut admins_data = admins_account.try_borrow_mut_data()?;
+        admins_data[0..8].copy_from_slice(&discriminator::account("AdminSet"));
+        drop(admins_data);
     }
 
-    let amount = claim_state.amount;
-    claim_state.amount = 0;
-    claim_state.timestamp = 0;
-
-    assert_token_account(recipient_usdc, &recipient, &mailer_state.usdc_mint)?;
-    assert_token_account(mailer_usdc, &mailer_pda, &mailer_state.usdc_mint)?;
-
-    // Save updated state BEFORE external call (CEI pattern)
-    claim_state.serialize(&mut &mut claim_data[8..])?;
-    drop(claim_data); // Release borrow before external call
+    let admin_count = admins.len();
+    let admin_set = AdminSet {
+        admins,
+        threshold,
+        bump: admins_bump,
+    };
 
-    // Transfer USDC from mailer to recipient
-    invoke_signed(
-        &spl_token::instruction::transfer(
-            token_program.key,
-            mailer_usdc.key,
-            recipient_usdc.key,
-            &mailer_pda,
-            &[],
-            amount,
-        )?,
-        &[
-            mailer_usdc.clone(),
-            recipient_usdc.clone(),
-            mailer_account.clone(),
-            token_program.clone(),
-        ],
-        &[&[b"mailer", &[mailer_state.bump]]],
-    )?;
+    let mut admins_data = admins_account.try_borrow_mut_data()?;
+    admin_set.serialize(&mut &mut admins_data[8..])?;
 
-    msg!("Distributed claimable funds to {}: {}", recipient, amount);
+    msg!(
+        "Admin set updated: {} admins, threshold {}",
+        admin_count,
+        threshold
+    );
     Ok(())
 }
 
-/// Claim expired shares and move them under owner control (owner only)
-fn process_claim_expired_shares(
+/// Sweep a page of expired recipient claims (owner only)
+fn process_sweep_expired_page(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
-    recipient: Pubkey,
+    max_items: u8,
 ) -> ProgramResult {
     let account_iter = &mut accounts.iter();
     let owner = next_account_info(account_iter)?;
     let mailer_account = next_account_info(account_iter)?;
-    let recipient_claim_account = next_account_info(account_iter)?;
+    let cursor_account = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
 
     if !owner.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    let (_mailer_pda, _) = assert_mailer_account(program_id, mailer_account)?;
+    assert_system_program(system_program)?;
 
-    // Load and verify mailer state
-    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
+    assert_mailer_account(program_id, accounts, mailer_account)?;
+
+    let mailer_data = mailer_account.try_borrow_data()?;
     let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
 
-    if mailer_state.owner != *owner.key {
-        return Err(MailerError::OnlyOwner.into());
+    if mailer_state.is_paused(PAUSE_CLAIM) {
+        return Err(MailerError::ContractPaused.into());
     }
 
-    // Verify recipient claim PDA
-    let (claim_pda, _) = Pubkey::find_program_address(&[b"claim", &[PDA_VERSION], recipient.as_ref()], program_id);
-    if recipient_claim_account.key != &claim_pda {
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
+
+    // Verify sweep cursor PDA
+    let (cursor_pda, cursor_bump) = pda::sweep_cursor(program_id);
+
+    if cursor_account.key != &cursor_pda {
         return Err(MailerError::InvalidPDA.into());
     }
 
-    // Load and validate claim state
-    let mut claim_data = recipient_claim_account.try_borrow_mut_data()?;
-    let mut claim_state: RecipientClaim = BorshDeserialize::deserialize(&mut &claim_data[8..])?;
+    // Create cursor account if needed
+    if cursor_account.lamports() == 0 {
+        let rent = Rent::get()?;
+        let space = 8 + SweepCursor::LEN;
+        let lamports = rent.minimum_balance(space);
 
-    if claim_state.recipient != recipient {
-        return Err(MailerError::InvalidRecipient.into());
-    }
-    if claim_state.amount == 0 {
-        return Err(MailerError::NoClaimableAmount.into());
+        invoke_signed(
+            &system_instruction::create_account(
+                owner.key,
+                cursor_account.key,
+                lamports,
+                space as u64,
+                program_id,
+            ),
+            &[
+                owner.clone(),
+                cursor_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[seeds::SWEEP_CURSOR, &[PDA_VERSION], &[cursor_bump]]],
+        )?;
+
+        let mut cursor_data = cursor_account.try_borrow_mut_data()?;
+        cursor_data[0..8].copy_from_slice(&discriminator::account("SweepCursor"));
+
+        let cursor = SweepCursor {
+            last_processed: Pubkey::default(),
+            processed_total: 0,
+            swept_total: 0,
+            bump: cursor_bump,
+        };
+        cursor.serialize(&mut &mut cursor_data[8..])?;
     }
 
+    let cursor_data = cursor_account.try_borrow_data()?;
+    let mut cursor: SweepCursor = BorshDeserialize::deserialize(&mut &cursor_data[8..])?;
+    drop(cursor_data);
+
     let current_time = Clock::get()?.unix_timestamp;
-    if current_time <= claim_state.timestamp + CLAIM_PERIOD {
-        return Err(MailerError::ClaimPeriodNotExpired.into());
+    let mut processed: u64 = 0;
+    let mut swept_amount: u64 = 0;
+
+    // Remaining program-owned claim accounts, in the order supplied
+    let claim_accounts = account_iter
+        .filter(|acc| acc.owner == program_id && RecipientClaim::is_account_len(acc.data_len()));
+
+    for claim_account in claim_accounts.take(max_items as usize) {
+        if claim_account.key.to_bytes() <= cursor.last_processed.to_bytes() {
+            return Err(MailerError::InvalidSweepOrder.into());
+        }
+
+        let mut claim_data = claim_account.try_borrow_mut_data()?;
+        let mut claim_state = RecipientClaim::from_account_data(&claim_data[8..])?;
+
+        // Verify the account is the canonical claim PDA for its recipient
+        let expected_claim = Pubkey::create_program_address(
+            &[
+                seeds::CLAIM,
+                &[PDA_VERSION],
+                claim_state.recipient.as_ref(),
+                &[claim_state.bump],
+            ],
+            program_id,
+        )
+        .map_err(|_| MailerError::InvalidPDA)?;
+        if claim_account.key != &expected_claim {
+            return Err(MailerError::InvalidPDA.into());
+        }
+
+        // Collect no-expiry surcharges due; a lapsed claim starts a fresh window
+        let surcharge = mailer_state.settle_no_expiry_surcharge(&mut claim_state, current_time);
+        if surcharge > 0 {
+            claim_state.write_account_data(&mut claim_data[8..])?;
+            mailer_state.move_claim_to_owner(surcharge)?;
+            msg!(
+                "No-expiry surcharge debited from {}: {}",
+                claim_state.recipient,
+                surcharge
+            );
+        }
+
+        if claim_state.amount > 0
+            && current_time > mailer_state.claim_expiry(&claim_state, current_time)
+            && current_time
+                > owner_sweep_time(
+                    program_id,
+                    &mailer_state,
+                    &claim_state,
+                    current_time,
+                    accounts,
+                )?
+        {
+            let amount = claim_state.amount;
+            claim_state.amount = 0;
+            claim_state.timestamp = 0;
+            claim_state.write_account_data(&mut claim_data[8..])?;
+
+            mailer_state.move_claim_to_owner(amount)?;
+            swept_amount = swept_amount
+                .checked_add(amount)
+                .ok_or(MailerError::MathOverflow)?;
+
+            msg!(
+                "Expired shares claimed for {}: {}",
+                claim_state.recipient,
+                amount
+            );
+        }
+
+        cursor.last_processed = *claim_account.key;
+        processed += 1;
     }
 
-    let amount = claim_state.amount;
-    claim_state.amount = 0;
-    claim_state.timestamp = 0;
-    claim_state.serialize(&mut &mut claim_data[8..])?;
-    drop(claim_data);
+    cursor.processed_total = cursor.processed_total.saturating_add(processed);
+    cursor.swept_total = cursor.swept_total.saturating_add(swept_amount);
 
-    mailer_state.increase_owner_claimable(amount)?;
+    let mut cursor_data = cursor_account.try_borrow_mut_data()?;
+    cursor.serialize(&mut &mut cursor_data[8..])?;
+    drop(cursor_data);
+
+    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
     mailer_state.serialize(&mut &mut mailer_data[8..])?;
 
-    msg!("Expired shares claimed for {}: {}", recipient, amount);
+    msg!(
+        "Sweep progress: processed {}, swept {} (totals: processed {}, swept {}), cursor {}",
+        processed,
+        swept_amount,
+        cursor.processed_total,
+        cursor.swept_total,
+        cursor.last_processed
+    );
     Ok(())
 }
 
-/// Emergency unpause without fund distribution (owner only)
-fn process_emergency_unpause(_program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+/// Reset the sweep cursor (owner only)
+fn process_reset_sweep_cursor(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
     let account_iter = &mut accounts.iter();
     let owner = next_account_info(account_iter)?;
     let mailer_account = next_account_info(account_iter)?;
+    let cursor_account = next_account_info(account_iter)?;
 
     if !owner.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    assert_mailer_account(_program_id, mailer_account)?;
+    assert_mailer_account(program_id, accounts, mailer_account)?;
 
-    // Load and update mailer state
-    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
-    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    let mailer_data = mailer_account.try_borrow_data()?;
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    drop(mailer_data);
 
-    // Verify owner
-    if mailer_state.owner != *owner.key {
-        return Err(MailerError::OnlyOwner.into());
-    }
+    assert_owner_authority(program_id, &mailer_state, owner, accounts)?;
 
-    // Check if not paused
-    if !mailer_state.paused {
-        return Err(MailerError::ContractNotPaused.into());
+    let (cursor_pda, _) = pda::sweep_cursor(program_id);
+    if cursor_account.key != &cursor_pda {
+        return Err(MailerError::InvalidPDA.into());
     }
 
-    // Set unpaused state without fund distribution
-    mailer_state.paused = false;
-    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+    let mut cursor_data = cursor_account.try_borrow_mut_data()?;
+    let mut cursor: SweepCursor = BorshDeserialize::deserialize(&mut &cursor_data[8..])?;
+    cursor.last_processed = Pubkey::default();
+    cursor.serialize(&mut &mut cursor_data[8..])?;
 
     msg!(
-        "Contract emergency unpaused by owner: {} - funds can be claimed manually",
-        owner.key
+        "Sweep cursor reset (totals: processed {}, swept {})",
+        cursor.processed_total,
+        cursor.swept_total
     );
     Ok(())
 }
 
-/// Set fee paused state (owner only)
-fn process_set_fee_paused(
-    _program_id: &Pubkey,
+/// Verify owner-level authority for an instruction
+/// Passes when `authority` is the owner and signed, or when at least `threshold` distinct
+/// admins from the admin set PDA (passed as a trailing account) signed the transaction.
+fn assert_owner_authority(
+    program_id: &Pubkey,
+    mailer_state: &MailerState,
+    authority: &AccountInfo,
     accounts: &[AccountInfo],
-    fee_paused: bool,
 ) -> ProgramResult {
-    let account_iter = &mut accounts.iter();
-    let owner = next_account_info(account_iter)?;
-    let mailer_account = next_account_info(account_iter)?;
+    if authority.is_signer && mailer_state.owner == *authority.key {
+        return Ok(());
+    }
 
-    if !owner.is_signer {
+    let (admins_pda, _) = pda::admin_set(program_id);
+
+    if let Some(admins_acc) = accounts.iter().find(|acc| acc.key == &admins_pda) {
+        if admins_acc.lamports() > 0 && admins_acc.owner == program_id {
+            let admins_data = admins_acc.try_borrow_data()?;
+            let admin_set: AdminSet = BorshDeserialize::deserialize(&mut &admins_data[8..])?;
+
+            if admin_set.threshold > 0 {
+                let approvals = admin_set
+                    .admins
+                    .iter()
+                    .filter(|admin| {
+                        accounts
+                            .iter()
+                            .any(|acc| acc.is_signer && acc.key == *admin)
+                    })
+                    .count();
+
+                if approvals >= admin_set.threshold as usize {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    Err(MailerError::OnlyOwner.into())
+}
+
+/// Resolve the recipient a preference-mutation instruction acts for
+/// Without a session key account the signer is the recipient. With one, the signer must be
+/// that account's session key, unexpired and granted `scope`; the stored recipient is returned.
+fn resolve_recipient_authority(
+    program_id: &Pubkey,
+    signer: &AccountInfo,
+    session_account: Option<&AccountInfo>,
+    scope: u8,
+) -> Result<Pubkey, ProgramError> {
+    if !signer.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    assert_mailer_account(_program_id, mailer_account)?;
+    let session_account = match session_account {
+        Some(session_account) => session_account,
+        None => return Ok(*signer.key),
+    };
 
-    // Load and update mailer state
-    let mut mailer_data = mailer_account.try_borrow_mut_data()?;
-    let mut mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_data[8..])?;
+    if session_account.owner != program_id || session_account.data_len() < 8 + SessionKey::LEN {
+        return Err(MailerError::InvalidSessionKey.into());
+    }
 
-    if mailer_state.owner != *owner.key {
-        return Err(MailerError::OnlyOwner.into());
+    let session_data = session_account.try_borrow_data()?;
+    let session: SessionKey = BorshDeserialize::deserialize(&mut &session_data[8..])?;
+
+    if session.session_key != *signer.key {
+        return Err(MailerError::InvalidSessionKey.into());
     }
 
-    mailer_state.fee_paused = fee_paused;
-    mailer_state.serialize(&mut &mut mailer_data[8..])?;
+    let session_pda = Pubkey::create_program_address(
+        &[
+            seeds::SESSION,
+            &[PDA_VERSION],
+            session.recipient.as_ref(),
+            session.session_key.as_ref(),
+            &[session.bump],
+        ],
+        program_id,
+    )
+    .map_err(|_| MailerError::InvalidPDA)?;
+    if session_account.key != &session_pda {
+        return Err(MailerError::InvalidPDA.into());
+    }
 
-    msg!("Fee paused state set to: {}", fee_paused);
-    Ok(())
+    if session.scope & scope == 0 {
+        return Err(MailerError::SessionScopeDenied.into());
+    }
+
+    if Clock::get()?.unix_timestamp >= session.expires_at {
+        return Err(MailerError::SessionKeyExpired.into());
+    }
+
+    Ok(session.recipient)
+}
+
+/// Verify the destination token account of a recipient payout
+/// Honors the recipient's payout config PDA when it is in the accounts slice and set;
+/// otherwise the destination must be the recipient's own USDC account.
+fn assert_payout_account(
+    program_id: &Pubkey,
+    recipient: &Pubkey,
+    destination: &AccountInfo,
+    usdc_mint: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let (payout_pda, _) = pda::payout_config(program_id, recipient);
+
+    if let Some(payout_acc) = accounts.iter().find(|acc| acc.key == &payout_pda) {
+        if payout_acc.lamports() > 0 && payout_acc.owner == program_id {
+            let payout_data = payout_acc.try_borrow_data()?;
+            let config: PayoutConfig = BorshDeserialize::deserialize(&mut &payout_data[8..])?;
+
+            if let Some(payout_token_account) = config.payout_token_account {
+                if destination.key != &payout_token_account {
+                    return Err(MailerError::InvalidPayoutAccount.into());
+                }
+
+                let data = destination.try_borrow_data()?;
+                let token_account = unpack_token_account(&data)?;
+                if token_account.mint != *usdc_mint {
+                    return Err(MailerError::InvalidMint.into());
+                }
+                return Ok(());
+            }
+        }
+    }
+
+    assert_token_account(accounts, "destination", destination, recipient, usdc_mint)
 }
 
-/// Simple hash function for account discriminators
-fn hash_discriminator(name: &str) -> u64 {
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::{Hash, Hasher};
+/// Check whether a recipient accepts read receipts
+/// Looks for the recipient preferences PDA in the accounts slice; defaults to allowed
+fn read_receipts_allowed(
+    program_id: &Pubkey,
+    recipient: &Pubkey,
+    accounts: &[AccountInfo],
+) -> Result<bool, ProgramError> {
+    let (prefs_pda, _) = pda::recipient_prefs(program_id, recipient);
+
+    if let Some(prefs_acc) = accounts.iter().find(|acc| acc.key == &prefs_pda) {
+        if prefs_acc.lamports() > 0 && prefs_acc.owner == program_id {
+            let prefs_data = prefs_acc.try_borrow_data()?;
+            if prefs_data.len() >= 8 + RecipientPrefs::LEN {
+                let prefs: RecipientPrefs = BorshDeserialize::deserialize(&mut &prefs_data[8..])?;
+                return Ok(!prefs.read_receipts_opt_out);
+            }
+        }
+    }
 
-    let mut hasher = DefaultHasher::new();
-    name.hash(&mut hasher);
-    hasher.finish()
+    Ok(true)
 }
@@ -0,0 +1,98 @@
+//! # Claim Distribution Merkle Trees
+//!
+//! `SetClaimMerkleRoot` funds an airdrop of claimable USDC shares with a single merkle root
+//! instead of one transaction per recipient. Leaf `index` commits to a recipient and the
+//! amount credited to their claim with `ClaimFromMerkle`; the recipient supplies the sibling
+//! hashes from the leaf up to the root.
+//!
+//! Leaves and inner nodes are SHA-256 hashes with distinct prefix bytes, so a node can never
+//! be passed off as a leaf. Each inner node hashes its two children in ascending byte order,
+//! so proofs need no left/right flags. [`root`] and [`proof`] build a tree off-chain, pairing
+//! an odd node at the end of a level with itself.
+//!
+//! ```rust
+//! use mailer::merkle;
+//! use solana_program::pubkey::Pubkey;
+//!
+//! let recipients = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+//! let leaves: Vec<[u8; 32]> = recipients
+//!     .iter()
+//!     .enumerate()
+//!     .map(|(index, recipient)| merkle::leaf(index as u64, recipient, 1_000_000))
+//!     .collect();
+//!
+//! let root = merkle::root(&leaves);
+//! let proof = merkle::proof(&leaves, 2);
+//! assert!(merkle::verify(&root, &leaves[2], &proof));
+//! assert!(!merkle::verify(&root, &leaves[1], &proof));
+//! ```
+
+use solana_program::{hash::hashv, pubkey::Pubkey};
+
+/// Prefix byte of leaf hashes
+const LEAF_PREFIX: &[u8] = &[0];
+
+/// Prefix byte of inner node hashes
+const NODE_PREFIX: &[u8] = &[1];
+
+/// Longest proof `ClaimFromMerkle` accepts, enough for a distribution of the maximum
+/// 32,768 leaves
+pub const MAX_PROOF_LEN: usize = 15;
+
+/// Leaf crediting `amount` to `recipient`'s claim at position `index` of a distribution
+pub fn leaf(index: u64, recipient: &Pubkey, amount: u64) -> [u8; 32] {
+    hashv(&[
+        LEAF_PREFIX,
+        &index.to_le_bytes(),
+        recipient.as_ref(),
+        &amount.to_le_bytes(),
+    ])
+    .to_bytes()
+}
+
+/// Parent of two nodes, hashed in ascending byte order
+fn node(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (low, high) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[NODE_PREFIX, low, high]).to_bytes()
+}
+
+/// Whether `proof` leads from `leaf` to `root`
+pub fn verify(root: &[u8; 32], leaf: &[u8; 32], proof: &[[u8; 32]]) -> bool {
+    let computed = proof
+        .iter()
+        .fold(*leaf, |hash, sibling| node(&hash, sibling));
+    computed == *root
+}
+
+/// Root of the tree over `leaves`; all zeroes for an empty tree
+pub fn root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    if leaves.is_empty() {
+        return [0; 32];
+    }
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = parent_level(&level);
+    }
+    level[0]
+}
+
+/// Sibling hashes from leaf `index` up to the root of the tree over `leaves`
+pub fn proof(leaves: &[[u8; 32]], mut index: usize) -> Vec<[u8; 32]> {
+    let mut proof = Vec::new();
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        let sibling = index ^ 1;
+        proof.push(*level.get(sibling).unwrap_or(&level[index]));
+        level = parent_level(&level);
+        index /= 2;
+    }
+    proof
+}
+
+/// Next level up, an odd last node paired with itself
+fn parent_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level
+        .chunks(2)
+        .map(|pair| node(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+        .collect()
+}
@@ -0,0 +1,921 @@
+//! # PDA Seeds and Derivation
+//!
+//! Seed prefixes and derivation helpers for every program derived address the Mailer
+//! program uses. The program itself signs and verifies with the constants in [`seeds`],
+//! so clients deriving addresses through this module cannot drift from the program.
+//!
+//! Versioned PDAs are laid out as `[prefix, &[PDA_VERSION], ..keys]`; the mailer state
+//! is a global singleton without a version byte.
+//!
+//! The `*_with_bump` variants rebuild an address from the bump stored in its account with a
+//! single `create_program_address`, where the plain helpers search down from bump 255 with
+//! `find_program_address`. The program checks existing hot-path accounts that way and
+//! searches only for accounts it has yet to create.
+//!
+//! The runtime meters each derivation attempt at [`DERIVATION_UNITS`], but not in a program
+//! running as a native builtin under solana-program-test; off-chain, this module counts the
+//! attempts instead, read with [`derivations`].
+//!
+//! ```rust
+//! use mailer::pda;
+//!
+//! let recipient = solana_program::pubkey::Pubkey::new_unique();
+//! let (claim, _bump) = pda::recipient_claim(&mailer::id(), &recipient);
+//! ```
+
+#[cfg(not(target_os = "solana"))]
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use solana_program::pubkey::{Pubkey, PubkeyError};
+
+pub use crate::PDA_VERSION;
+
+use crate::MigrationKind;
+
+/// Version byte of the seeds `MigrateAccountVersion` moves claim, delegation and
+/// discount accounts from, after `PDA_VERSION` is incremented
+pub const PREVIOUS_PDA_VERSION: u8 = PDA_VERSION - 1;
+
+/// Seed prefixes, one per account type
+pub mod seeds {
+    pub const MAILER: &[u8] = b"mailer";
+    pub const CLAIM: &[u8] = b"claim";
+    pub const DELEGATION: &[u8] = b"delegation";
+    pub const DISCOUNT: &[u8] = b"discount";
+    pub const PREFS: &[u8] = b"prefs";
+    pub const POLICY: &[u8] = b"policy";
+    pub const RECEIPT: &[u8] = b"receipt";
+    pub const HISTORY: &[u8] = b"history";
+    pub const ADMINS: &[u8] = b"admins";
+    pub const SWEEP_CURSOR: &[u8] = b"sweep_cursor";
+    pub const SESSION: &[u8] = b"session";
+    pub const PAYOUT: &[u8] = b"payout";
+    pub const CONVERSION: &[u8] = b"conversion";
+    pub const PREAUTH: &[u8] = b"preauth";
+    pub const RATE_LIMIT: &[u8] = b"rate_limit";
+    pub const PROMO: &[u8] = b"promo";
+    pub const FEE_MINT: &[u8] = b"fee_mint";
+    pub const LAMPORT_VAULT: &[u8] = b"lamport_vault";
+    pub const INBOX: &[u8] = b"inbox";
+    pub const CLAIM_RENT: &[u8] = b"claim_rent";
+    pub const WEBHOOK: &[u8] = b"webhook";
+    pub const CLOSURE_LOG: &[u8] = b"closure_log";
+    pub const SANCTIONED: &[u8] = b"sanctioned";
+    pub const DELEGATE_INDEX: &[u8] = b"delegate_index";
+    pub const STATS: &[u8] = b"stats";
+    pub const SEND_CREDITS: &[u8] = b"send_credits";
+    pub const PLAN: &[u8] = b"plan";
+    pub const SUBSCRIPTION: &[u8] = b"subscription";
+    pub const BENEFICIARY: &[u8] = b"beneficiary";
+    pub const TREASURY: &[u8] = b"treasury";
+    pub const DISTRIBUTION: &[u8] = b"distribution";
+    pub const ESCROW: &[u8] = b"escrow";
+    pub const LIST: &[u8] = b"list";
+    pub const LIST_CHUNK: &[u8] = b"list_chunk";
+    pub const EVM_MAP: &[u8] = b"evm_map";
+    pub const WORMHOLE: &[u8] = b"wormhole";
+    pub const WORMHOLE_EMITTER: &[u8] = b"emitter";
+    pub const WORMHOLE_MESSAGE: &[u8] = b"wormhole_message";
+    pub const MESSAGE_NONCE: &[u8] = b"message_nonce";
+    pub const SEND_NONCE: &[u8] = b"send_nonce";
+    pub const DISCOUNT_SCHEDULE: &[u8] = b"discount_schedule";
+    pub const SENDER_STATS: &[u8] = b"sender_stats";
+    #[cfg(feature = "devnet-faucet")]
+    pub const FAUCET: &[u8] = b"faucet";
+}
+
+/// One component of a PDA seed list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeedComponent {
+    /// Fixed byte-string prefix
+    Literal(&'static [u8]),
+    /// The `PDA_VERSION` byte
+    Version,
+    /// A 32-byte public key input, named after the instruction argument or account
+    Pubkey(&'static str),
+    /// A little-endian u64 input
+    U64Le(&'static str),
+    /// A 32-byte hash input
+    Hash(&'static str),
+    /// A 20-byte EVM address input
+    EvmAddress(&'static str),
+}
+
+/// Seed layout of one PDA account type
+#[derive(Debug, Clone, Copy)]
+pub struct PdaLayout {
+    pub account: &'static str,
+    pub seeds: &'static [SeedComponent],
+}
+
+/// Value for a `Pubkey`, `U64Le`, `Hash` or `EvmAddress` seed component
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeedInput {
+    Pubkey(Pubkey),
+    U64(u64),
+    Hash([u8; 32]),
+    EvmAddress([u8; 20]),
+}
+
+impl PdaLayout {
+    /// Derive the address from inputs given in seed order, returning `None` when the
+    /// inputs do not match the layout's `Pubkey`/`U64Le`/`Hash`/`EvmAddress` components
+    pub fn find_address(&self, program_id: &Pubkey, inputs: &[SeedInput]) -> Option<(Pubkey, u8)> {
+        let mut inputs = inputs.iter();
+        let mut seed_bytes: Vec<Vec<u8>> = Vec::with_capacity(self.seeds.len());
+        for component in self.seeds {
+            seed_bytes.push(match component {
+                SeedComponent::Literal(bytes) => bytes.to_vec(),
+                SeedComponent::Version => vec![PDA_VERSION],
+                SeedComponent::Pubkey(_) => match inputs.next()? {
+                    SeedInput::Pubkey(key) => key.to_bytes().to_vec(),
+                    _ => return None,
+                },
+                SeedComponent::U64Le(_) => match inputs.next()? {
+                    SeedInput::U64(value) => value.to_le_bytes().to_vec(),
+                    _ => return None,
+                },
+                SeedComponent::Hash(_) => match inputs.next()? {
+                    SeedInput::Hash(hash) => hash.to_vec(),
+                    _ => return None,
+                },
+                SeedComponent::EvmAddress(_) => match inputs.next()? {
+                    SeedInput::EvmAddress(address) => address.to_vec(),
+                    _ => return None,
+                },
+            });
+        }
+        if inputs.next().is_some() {
+            return None;
+        }
+        let seeds: Vec<&[u8]> = seed_bytes.iter().map(Vec::as_slice).collect();
+        Some(Pubkey::find_program_address(&seeds, program_id))
+    }
+}
+
+/// Seed layouts for every PDA, in account declaration order
+pub const LAYOUTS: &[PdaLayout] = {
+    use SeedComponent::*;
+    &[
+        PdaLayout {
+            account: "MailerState",
+            seeds: &[Literal(seeds::MAILER)],
+        },
+        PdaLayout {
+            account: "RecipientClaim",
+            seeds: &[Literal(seeds::CLAIM), Version, Pubkey("recipient")],
+        },
+        PdaLayout {
+            account: "Delegation",
+            seeds: &[Literal(seeds::DELEGATION), Version, Pubkey("delegator")],
+        },
+        PdaLayout {
+            account: "FeeDiscount",
+            seeds: &[Literal(seeds::DISCOUNT), Version, Pubkey("account")],
+        },
+        PdaLayout {
+            account: "RecipientPrefs",
+            seeds: &[Literal(seeds::PREFS), Version, Pubkey("recipient")],
+        },
+        PdaLayout {
+            account: "PayoutConfig",
+            seeds: &[Literal(seeds::PAYOUT), Version, Pubkey("recipient")],
+        },
+        PdaLayout {
+            account: "ConversionVault",
+            seeds: &[Literal(seeds::CONVERSION), Version, Pubkey("mint")],
+        },
+        PdaLayout {
+            account: "MessageReceipt",
+            seeds: &[
+                Literal(seeds::RECEIPT),
+                Version,
+                Pubkey("recipient"),
+                U64Le("nonce"),
+            ],
+        },
+        PdaLayout {
+            account: "ClaimHistoryEntry",
+            seeds: &[
+                Literal(seeds::HISTORY),
+                Version,
+                Pubkey("recipient"),
+                Pubkey("sender"),
+            ],
+        },
+        PdaLayout {
+            account: "InboxPolicy",
+            seeds: &[Literal(seeds::POLICY), Version, Pubkey("recipient")],
+        },
+        PdaLayout {
+            account: "SessionKey",
+            seeds: &[
+                Literal(seeds::SESSION),
+                Version,
+                Pubkey("recipient"),
+                Pubkey("session_key"),
+            ],
+        },
+        PdaLayout {
+            account: "Preauthorization",
+            seeds: &[
+                Literal(seeds::PREAUTH),
+                Version,
+                Pubkey("user"),
+                Pubkey("relayer"),
+            ],
+        },
+        PdaLayout {
+            account: "AdminSet",
+            seeds: &[Literal(seeds::ADMINS), Version],
+        },
+        PdaLayout {
+            account: "SweepCursor",
+            seeds: &[Literal(seeds::SWEEP_CURSOR), Version],
+        },
+        PdaLayout {
+            account: "RateLimit",
+            seeds: &[Literal(seeds::RATE_LIMIT), Version, Pubkey("sender")],
+        },
+        PdaLayout {
+            account: "Promo",
+            seeds: &[Literal(seeds::PROMO), Version, Hash("code_hash")],
+        },
+        PdaLayout {
+            account: "FeeMint",
+            seeds: &[Literal(seeds::FEE_MINT), Version, Pubkey("mint")],
+        },
+        PdaLayout {
+            account: "RecipientClaim",
+            seeds: &[
+                Literal(seeds::CLAIM),
+                Version,
+                Pubkey("recipient"),
+                Pubkey("mint"),
+            ],
+        },
+        PdaLayout {
+            account: "LamportVault",
+            seeds: &[Literal(seeds::LAMPORT_VAULT), Version],
+        },
+        PdaLayout {
+            account: "RecipientInbox",
+            seeds: &[Literal(seeds::INBOX), Version, Pubkey("recipient")],
+        },
+        PdaLayout {
+            account: "ClaimRentPayer",
+            seeds: &[Literal(seeds::CLAIM_RENT), Version, Pubkey("claim")],
+        },
+        PdaLayout {
+            account: "Webhook",
+            seeds: &[Literal(seeds::WEBHOOK), Version, Hash("id_hash")],
+        },
+        PdaLayout {
+            account: "ClosureLog",
+            seeds: &[Literal(seeds::CLOSURE_LOG), Version],
+        },
+        PdaLayout {
+            account: "Sanctioned",
+            seeds: &[Literal(seeds::SANCTIONED), Version, Pubkey("address")],
+        },
+        PdaLayout {
+            account: "DelegateIndex",
+            seeds: &[Literal(seeds::DELEGATE_INDEX), Version, Pubkey("delegate")],
+        },
+        PdaLayout {
+            account: "MailerStats",
+            seeds: &[Literal(seeds::STATS), Version],
+        },
+        PdaLayout {
+            account: "SendCredits",
+            seeds: &[Literal(seeds::SEND_CREDITS), Version, Pubkey("sender")],
+        },
+        PdaLayout {
+            account: "Plan",
+            seeds: &[Literal(seeds::PLAN), Version, U64Le("plan_id")],
+        },
+        PdaLayout {
+            account: "Subscription",
+            seeds: &[Literal(seeds::SUBSCRIPTION), Version, Pubkey("subscriber")],
+        },
+        PdaLayout {
+            account: "ClaimBeneficiary",
+            seeds: &[Literal(seeds::BENEFICIARY), Version, Pubkey("recipient")],
+        },
+        PdaLayout {
+            account: "Treasury",
+            seeds: &[Literal(seeds::TREASURY), Version],
+        },
+        PdaLayout {
+            account: "ClaimDistribution",
+            seeds: &[
+                Literal(seeds::DISTRIBUTION),
+                Version,
+                U64Le("distribution_id"),
+            ],
+        },
+        PdaLayout {
+            account: "Escrow",
+            seeds: &[
+                Literal(seeds::ESCROW),
+                Version,
+                Pubkey("sender"),
+                U64Le("nonce"),
+            ],
+        },
+        PdaLayout {
+            account: "MailingList",
+            seeds: &[
+                Literal(seeds::LIST),
+                Version,
+                Pubkey("owner"),
+                U64Le("list_id"),
+            ],
+        },
+        PdaLayout {
+            account: "ListChunk",
+            seeds: &[
+                Literal(seeds::LIST_CHUNK),
+                Version,
+                Pubkey("list"),
+                U64Le("chunk"),
+            ],
+        },
+        PdaLayout {
+            account: "EvmMapping",
+            seeds: &[Literal(seeds::EVM_MAP), Version, EvmAddress("evm_address")],
+        },
+        PdaLayout {
+            account: "WormholeConfig",
+            seeds: &[Literal(seeds::WORMHOLE), Version],
+        },
+        PdaLayout {
+            account: "WormholeEmitter",
+            seeds: &[Literal(seeds::WORMHOLE_EMITTER), Version],
+        },
+        PdaLayout {
+            account: "WormholeMessage",
+            seeds: &[Literal(seeds::WORMHOLE_MESSAGE), Version, U64Le("message")],
+        },
+        PdaLayout {
+            account: "MessageNonce",
+            seeds: &[Literal(seeds::MESSAGE_NONCE), Version, Pubkey("sender")],
+        },
+        PdaLayout {
+            account: "SendNonce",
+            seeds: &[Literal(seeds::SEND_NONCE), Version, Pubkey("sender")],
+        },
+        PdaLayout {
+            account: "DiscountSchedule",
+            seeds: &[Literal(seeds::DISCOUNT_SCHEDULE), Version],
+        },
+        PdaLayout {
+            account: "SenderStats",
+            seeds: &[Literal(seeds::SENDER_STATS), Version, Pubkey("sender")],
+        },
+    ]
+};
+
+/// Compute units the runtime charges per `create_program_address`; `find_program_address`
+/// pays them for every bump it tries from 255 down to the canonical one
+pub const DERIVATION_UNITS: u64 = 1_500;
+
+/// Derivation attempts made by this process through this module
+#[cfg(not(target_os = "solana"))]
+static DERIVATIONS: AtomicU64 = AtomicU64::new(0);
+
+/// Derivation attempts made by this process through this module (off-chain only)
+#[cfg(not(target_os = "solana"))]
+pub fn derivations() -> u64 {
+    DERIVATIONS.load(Ordering::Relaxed)
+}
+
+#[cfg(not(target_os = "solana"))]
+fn meter(attempts: u64) {
+    DERIVATIONS.fetch_add(attempts, Ordering::Relaxed);
+}
+
+#[cfg(target_os = "solana")]
+fn meter(_attempts: u64) {}
+
+/// `Pubkey::find_program_address`, counting the bumps it tries
+fn find(seeds: &[&[u8]], program_id: &Pubkey) -> (Pubkey, u8) {
+    let (address, bump) = Pubkey::find_program_address(seeds, program_id);
+    meter((u8::MAX - bump) as u64 + 1);
+    (address, bump)
+}
+
+/// `Pubkey::create_program_address`, counting the attempt
+fn create(seeds: &[&[u8]], program_id: &Pubkey) -> Result<Pubkey, PubkeyError> {
+    meter(1);
+    Pubkey::create_program_address(seeds, program_id)
+}
+
+/// Claim, delegation or discount account of `subject` under the seeds of `version`
+pub fn versioned(
+    program_id: &Pubkey,
+    kind: MigrationKind,
+    subject: &Pubkey,
+    version: u8,
+) -> (Pubkey, u8) {
+    find(&[kind.seed(), &[version], subject.as_ref()], program_id)
+}
+
+/// Global mailer state account
+pub fn mailer_state(program_id: &Pubkey) -> (Pubkey, u8) {
+    find(&[seeds::MAILER], program_id)
+}
+
+/// Global mailer state account with the bump stored in it
+pub fn mailer_state_with_bump(program_id: &Pubkey, bump: u8) -> Option<Pubkey> {
+    create(&[seeds::MAILER, &[bump]], program_id).ok()
+}
+
+/// Recipient claim account
+pub fn recipient_claim(program_id: &Pubkey, recipient: &Pubkey) -> (Pubkey, u8) {
+    find(
+        &[seeds::CLAIM, &[PDA_VERSION], recipient.as_ref()],
+        program_id,
+    )
+}
+
+/// Recipient claim account with the bump stored in it
+pub fn recipient_claim_with_bump(
+    program_id: &Pubkey,
+    recipient: &Pubkey,
+    bump: u8,
+) -> Option<Pubkey> {
+    create(
+        &[seeds::CLAIM, &[PDA_VERSION], recipient.as_ref(), &[bump]],
+        program_id,
+    )
+    .ok()
+}
+
+/// Recipient claim account for shares paid in a whitelisted fee mint other than USDC
+pub fn recipient_claim_in_mint(
+    program_id: &Pubkey,
+    recipient: &Pubkey,
+    mint: &Pubkey,
+) -> (Pubkey, u8) {
+    find(
+        &[
+            seeds::CLAIM,
+            &[PDA_VERSION],
+            recipient.as_ref(),
+            mint.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+/// Recipient claim account in a whitelisted fee mint with the bump stored in it
+pub fn recipient_claim_in_mint_with_bump(
+    program_id: &Pubkey,
+    recipient: &Pubkey,
+    mint: &Pubkey,
+    bump: u8,
+) -> Option<Pubkey> {
+    create(
+        &[
+            seeds::CLAIM,
+            &[PDA_VERSION],
+            recipient.as_ref(),
+            mint.as_ref(),
+            &[bump],
+        ],
+        program_id,
+    )
+    .ok()
+}
+
+/// Delegation account
+pub fn delegation(program_id: &Pubkey, delegator: &Pubkey) -> (Pubkey, u8) {
+    find(
+        &[seeds::DELEGATION, &[PDA_VERSION], delegator.as_ref()],
+        program_id,
+    )
+}
+
+/// Fee discount account
+pub fn fee_discount(program_id: &Pubkey, account: &Pubkey) -> (Pubkey, u8) {
+    find(
+        &[seeds::DISCOUNT, &[PDA_VERSION], account.as_ref()],
+        program_id,
+    )
+}
+
+/// Fee discount account with the bump stored in it
+pub fn fee_discount_with_bump(program_id: &Pubkey, account: &Pubkey, bump: u8) -> Option<Pubkey> {
+    create(
+        &[seeds::DISCOUNT, &[PDA_VERSION], account.as_ref(), &[bump]],
+        program_id,
+    )
+    .ok()
+}
+
+/// Recipient preferences account
+pub fn recipient_prefs(program_id: &Pubkey, recipient: &Pubkey) -> (Pubkey, u8) {
+    find(
+        &[seeds::PREFS, &[PDA_VERSION], recipient.as_ref()],
+        program_id,
+    )
+}
+
+/// Recipient payout config account
+pub fn payout_config(program_id: &Pubkey, recipient: &Pubkey) -> (Pubkey, u8) {
+    find(
+        &[seeds::PAYOUT, &[PDA_VERSION], recipient.as_ref()],
+        program_id,
+    )
+}
+
+/// Conversion vault account for an alternate payout mint
+pub fn conversion_vault(program_id: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    find(
+        &[seeds::CONVERSION, &[PDA_VERSION], mint.as_ref()],
+        program_id,
+    )
+}
+
+/// Message receipt account
+pub fn message_receipt(program_id: &Pubkey, recipient: &Pubkey, nonce: u64) -> (Pubkey, u8) {
+    find(
+        &[
+            seeds::RECEIPT,
+            &[PDA_VERSION],
+            recipient.as_ref(),
+            &nonce.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
+/// Per-sender claim history entry
+pub fn claim_history(program_id: &Pubkey, recipient: &Pubkey, sender: &Pubkey) -> (Pubkey, u8) {
+    find(
+        &[
+            seeds::HISTORY,
+            &[PDA_VERSION],
+            recipient.as_ref(),
+            sender.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+/// Inbox policy account
+pub fn inbox_policy(program_id: &Pubkey, recipient: &Pubkey) -> (Pubkey, u8) {
+    find(
+        &[seeds::POLICY, &[PDA_VERSION], recipient.as_ref()],
+        program_id,
+    )
+}
+
+/// Inbox policy account with the bump stored in it
+pub fn inbox_policy_with_bump(program_id: &Pubkey, recipient: &Pubkey, bump: u8) -> Option<Pubkey> {
+    create(
+        &[seeds::POLICY, &[PDA_VERSION], recipient.as_ref(), &[bump]],
+        program_id,
+    )
+    .ok()
+}
+
+/// Recipient session key account
+pub fn session_key(program_id: &Pubkey, recipient: &Pubkey, session_key: &Pubkey) -> (Pubkey, u8) {
+    find(
+        &[
+            seeds::SESSION,
+            &[PDA_VERSION],
+            recipient.as_ref(),
+            session_key.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+/// Send pre-authorization account
+pub fn preauthorization(program_id: &Pubkey, user: &Pubkey, relayer: &Pubkey) -> (Pubkey, u8) {
+    find(
+        &[
+            seeds::PREAUTH,
+            &[PDA_VERSION],
+            user.as_ref(),
+            relayer.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+/// Global admin set account
+pub fn admin_set(program_id: &Pubkey) -> (Pubkey, u8) {
+    find(&[seeds::ADMINS, &[PDA_VERSION]], program_id)
+}
+
+/// Global sweep cursor account
+pub fn sweep_cursor(program_id: &Pubkey) -> (Pubkey, u8) {
+    find(&[seeds::SWEEP_CURSOR, &[PDA_VERSION]], program_id)
+}
+
+/// Per-sender rate limit account
+pub fn rate_limit(program_id: &Pubkey, sender: &Pubkey) -> (Pubkey, u8) {
+    find(
+        &[seeds::RATE_LIMIT, &[PDA_VERSION], sender.as_ref()],
+        program_id,
+    )
+}
+
+/// Promo code account, addressed by the SHA-256 hash of the code
+pub fn promo(program_id: &Pubkey, code_hash: &[u8; 32]) -> (Pubkey, u8) {
+    find(&[seeds::PROMO, &[PDA_VERSION], code_hash], program_id)
+}
+
+/// Whitelisted fee mint account
+pub fn fee_mint(program_id: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    find(
+        &[seeds::FEE_MINT, &[PDA_VERSION], mint.as_ref()],
+        program_id,
+    )
+}
+
+/// Program-owned vault holding lamport send fees
+pub fn lamport_vault(program_id: &Pubkey) -> (Pubkey, u8) {
+    find(&[seeds::LAMPORT_VAULT, &[PDA_VERSION]], program_id)
+}
+
+/// Per-recipient inbox account numbering sends
+pub fn recipient_inbox(program_id: &Pubkey, recipient: &Pubkey) -> (Pubkey, u8) {
+    find(
+        &[seeds::INBOX, &[PDA_VERSION], recipient.as_ref()],
+        program_id,
+    )
+}
+
+/// Per-recipient inbox account with the bump stored in it
+pub fn recipient_inbox_with_bump(
+    program_id: &Pubkey,
+    recipient: &Pubkey,
+    bump: u8,
+) -> Option<Pubkey> {
+    create(
+        &[seeds::INBOX, &[PDA_VERSION], recipient.as_ref(), &[bump]],
+        program_id,
+    )
+    .ok()
+}
+
+/// Rent payer record of a recipient claim account
+pub fn claim_rent_payer(program_id: &Pubkey, claim: &Pubkey) -> (Pubkey, u8) {
+    find(
+        &[seeds::CLAIM_RENT, &[PDA_VERSION], claim.as_ref()],
+        program_id,
+    )
+}
+
+/// Registered webhook account, addressed by the SHA-256 hash of the webhook id
+pub fn webhook(program_id: &Pubkey, id_hash: &[u8; 32]) -> (Pubkey, u8) {
+    find(&[seeds::WEBHOOK, &[PDA_VERSION], id_hash], program_id)
+}
+
+/// Global closure log account
+pub fn closure_log(program_id: &Pubkey) -> (Pubkey, u8) {
+    find(&[seeds::CLOSURE_LOG, &[PDA_VERSION]], program_id)
+}
+
+/// Sanctions registry entry of an address
+pub fn sanctioned(program_id: &Pubkey, address: &Pubkey) -> (Pubkey, u8) {
+    find(
+        &[seeds::SANCTIONED, &[PDA_VERSION], address.as_ref()],
+        program_id,
+    )
+}
+
+/// Reverse index of the delegators of a delegate
+pub fn delegate_index(program_id: &Pubkey, delegate: &Pubkey) -> (Pubkey, u8) {
+    find(
+        &[seeds::DELEGATE_INDEX, &[PDA_VERSION], delegate.as_ref()],
+        program_id,
+    )
+}
+
+/// Global program statistics account
+pub fn stats(program_id: &Pubkey) -> (Pubkey, u8) {
+    find(&[seeds::STATS, &[PDA_VERSION]], program_id)
+}
+
+/// Prepaid send credits of a sender
+pub fn send_credits(program_id: &Pubkey, sender: &Pubkey) -> (Pubkey, u8) {
+    find(
+        &[seeds::SEND_CREDITS, &[PDA_VERSION], sender.as_ref()],
+        program_id,
+    )
+}
+
+/// Prepaid send credits of a sender with the bump stored in it
+pub fn send_credits_with_bump(program_id: &Pubkey, sender: &Pubkey, bump: u8) -> Option<Pubkey> {
+    create(
+        &[
+            seeds::SEND_CREDITS,
+            &[PDA_VERSION],
+            sender.as_ref(),
+            &[bump],
+        ],
+        program_id,
+    )
+    .ok()
+}
+
+/// Subscription plan with the given id
+pub fn plan(program_id: &Pubkey, plan_id: u64) -> (Pubkey, u8) {
+    find(
+        &[seeds::PLAN, &[PDA_VERSION], &plan_id.to_le_bytes()],
+        program_id,
+    )
+}
+
+/// Subscription of a subscriber
+pub fn subscription(program_id: &Pubkey, subscriber: &Pubkey) -> (Pubkey, u8) {
+    find(
+        &[seeds::SUBSCRIPTION, &[PDA_VERSION], subscriber.as_ref()],
+        program_id,
+    )
+}
+
+/// Subscription of a subscriber with the bump stored in it
+pub fn subscription_with_bump(
+    program_id: &Pubkey,
+    subscriber: &Pubkey,
+    bump: u8,
+) -> Option<Pubkey> {
+    create(
+        &[
+            seeds::SUBSCRIPTION,
+            &[PDA_VERSION],
+            subscriber.as_ref(),
+            &[bump],
+        ],
+        program_id,
+    )
+    .ok()
+}
+
+/// Backup beneficiary of a recipient's claim
+pub fn claim_beneficiary(program_id: &Pubkey, recipient: &Pubkey) -> (Pubkey, u8) {
+    find(
+        &[seeds::BENEFICIARY, &[PDA_VERSION], recipient.as_ref()],
+        program_id,
+    )
+}
+
+/// Treasury authority owning the token account that receives the owner's share of fees
+pub fn treasury(program_id: &Pubkey) -> (Pubkey, u8) {
+    find(&[seeds::TREASURY, &[PDA_VERSION]], program_id)
+}
+
+/// Merkle airdrop distribution with the given id
+pub fn claim_distribution(program_id: &Pubkey, distribution_id: u64) -> (Pubkey, u8) {
+    find(
+        &[
+            seeds::DISTRIBUTION,
+            &[PDA_VERSION],
+            &distribution_id.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
+/// Escrow holding the fee of a sender's escrowed message `nonce`
+pub fn escrow(program_id: &Pubkey, sender: &Pubkey, nonce: u64) -> (Pubkey, u8) {
+    find(
+        &[
+            seeds::ESCROW,
+            &[PDA_VERSION],
+            sender.as_ref(),
+            &nonce.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
+/// Mailing list `list_id` of `owner`
+pub fn mailing_list(program_id: &Pubkey, owner: &Pubkey, list_id: u64) -> (Pubkey, u8) {
+    find(
+        &[
+            seeds::LIST,
+            &[PDA_VERSION],
+            owner.as_ref(),
+            &list_id.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
+/// Member chunk `chunk` of a mailing list
+pub fn list_chunk(program_id: &Pubkey, list: &Pubkey, chunk: u64) -> (Pubkey, u8) {
+    find(
+        &[
+            seeds::LIST_CHUNK,
+            &[PDA_VERSION],
+            list.as_ref(),
+            &chunk.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
+/// Binding of an EVM address to a Solana wallet
+pub fn evm_mapping(program_id: &Pubkey, evm_address: &[u8; 20]) -> (Pubkey, u8) {
+    find(&[seeds::EVM_MAP, &[PDA_VERSION], evm_address], program_id)
+}
+
+/// Wormhole core bridge config of bridged sends (global singleton)
+pub fn wormhole_config(program_id: &Pubkey) -> (Pubkey, u8) {
+    find(&[seeds::WORMHOLE, &[PDA_VERSION]], program_id)
+}
+
+/// Emitter of the program's Wormhole messages (holds no data)
+pub fn wormhole_emitter(program_id: &Pubkey) -> (Pubkey, u8) {
+    find(&[seeds::WORMHOLE_EMITTER, &[PDA_VERSION]], program_id)
+}
+
+/// Account of the `message`-th Wormhole message, created and owned by the core bridge
+pub fn wormhole_message(program_id: &Pubkey, message: u64) -> (Pubkey, u8) {
+    find(
+        &[
+            seeds::WORMHOLE_MESSAGE,
+            &[PDA_VERSION],
+            &message.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
+/// Per-sender account numbering the messages a wallet sends
+pub fn message_nonce(program_id: &Pubkey, sender: &Pubkey) -> (Pubkey, u8) {
+    find(
+        &[seeds::MESSAGE_NONCE, &[PDA_VERSION], sender.as_ref()],
+        program_id,
+    )
+}
+
+/// Message nonce account of a sender with the bump stored in it
+pub fn message_nonce_with_bump(program_id: &Pubkey, sender: &Pubkey, bump: u8) -> Option<Pubkey> {
+    create(
+        &[
+            seeds::MESSAGE_NONCE,
+            &[PDA_VERSION],
+            sender.as_ref(),
+            &[bump],
+        ],
+        program_id,
+    )
+    .ok()
+}
+
+/// Per-sender ring buffer of the idempotency nonces of a wallet's latest sends
+pub fn send_nonce(program_id: &Pubkey, sender: &Pubkey) -> (Pubkey, u8) {
+    find(
+        &[seeds::SEND_NONCE, &[PDA_VERSION], sender.as_ref()],
+        program_id,
+    )
+}
+
+/// Idempotency nonce ring buffer of a sender with the bump stored in it
+pub fn send_nonce_with_bump(program_id: &Pubkey, sender: &Pubkey, bump: u8) -> Option<Pubkey> {
+    create(
+        &[seeds::SEND_NONCE, &[PDA_VERSION], sender.as_ref(), &[bump]],
+        program_id,
+    )
+    .ok()
+}
+
+/// Global volume discount tiers set by the owner
+pub fn discount_schedule(program_id: &Pubkey) -> (Pubkey, u8) {
+    find(&[seeds::DISCOUNT_SCHEDULE, &[PDA_VERSION]], program_id)
+}
+
+/// Discount schedule account with the bump stored in it
+pub fn discount_schedule_with_bump(program_id: &Pubkey, bump: u8) -> Option<Pubkey> {
+    create(
+        &[seeds::DISCOUNT_SCHEDULE, &[PDA_VERSION], &[bump]],
+        program_id,
+    )
+    .ok()
+}
+
+/// Per-sender USDC fees paid, counted toward volume discounts
+pub fn sender_stats(program_id: &Pubkey, sender: &Pubkey) -> (Pubkey, u8) {
+    find(
+        &[seeds::SENDER_STATS, &[PDA_VERSION], sender.as_ref()],
+        program_id,
+    )
+}
+
+/// Devnet faucet drip record of a caller
+#[cfg(feature = "devnet-faucet")]
+pub fn faucet_drip(program_id: &Pubkey, caller: &Pubkey) -> (Pubkey, u8) {
+    find(
+        &[seeds::FAUCET, &[PDA_VERSION], caller.as_ref()],
+        program_id,
+    )
+}
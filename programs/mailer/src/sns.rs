@@ -0,0 +1,76 @@
+//! # Solana Name Service Domains
+//!
+//! `SendToDomain` addresses a message to the current owner of a `.sol` domain. The sender
+//! passes the domain's name account, which must be the Name Service account derived for the
+//! domain under the `.sol` top-level domain; the owner is read from its record header and
+//! the send is then routed, claim PDA included, exactly like a `Send` to that wallet. A
+//! transferred domain therefore reaches its new owner without the sender looking them up.
+//!
+//! Only the 96-byte record header (parent, owner, class) is read; [`encode_name_record`]
+//! builds a minimal name account with the same layout for tests and local validators.
+//!
+//! ```rust
+//! use mailer::sns;
+//! use solana_program::pubkey::Pubkey;
+//!
+//! let owner = Pubkey::new_unique();
+//! let data = sns::encode_name_record(&sns::SOL_TLD, &owner);
+//! assert_eq!(sns::record_owner(&data), Some(owner));
+//!
+//! // "bonfida" and "bonfida.sol" name the same account
+//! let key = sns::domain_key("bonfida.sol");
+//! assert_eq!(key, sns::domain_key("bonfida"));
+//! assert_eq!(key.to_string(), "Crf8hzfthWGbGbLTVCiqRqV5MVnbpHB1L9KQMd6gsinb");
+//! ```
+
+use solana_program::{hash::hashv, pubkey::Pubkey};
+
+/// Solana Name Service program
+pub const NAME_PROGRAM_ID: Pubkey =
+    solana_program::pubkey!("namesLPneVptA9Z5rqUDD9tMTWEJwofgaYwp8cawRkX");
+
+/// Name account of the `.sol` top-level domain, the parent of every `.sol` domain
+pub const SOL_TLD: Pubkey = solana_program::pubkey!("58PwtjSDuFHuUkYjH9BYnnQKHfwo9reZhC2zMJv9JPkx");
+
+/// Prefix hashed with a name to derive its name account
+const HASH_PREFIX: &str = "SPL Name Service";
+
+/// Size of the record header opening every name account
+pub const NAME_RECORD_HEADER_LEN: usize = 96;
+
+const OWNER_OFFSET: usize = 32;
+
+/// `domain` without its `.sol` suffix, if any
+pub fn domain_name(domain: &str) -> &str {
+    domain.strip_suffix(".sol").unwrap_or(domain)
+}
+
+/// Hash of a name as used in its name account seeds
+pub fn hashed_name(name: &str) -> [u8; 32] {
+    hashv(&[HASH_PREFIX.as_bytes(), name.as_bytes()]).to_bytes()
+}
+
+/// Name account of the `.sol` domain `domain`, with or without its suffix
+pub fn domain_key(domain: &str) -> Pubkey {
+    let hashed = hashed_name(domain_name(domain));
+    Pubkey::find_program_address(&[&hashed, &[0; 32], SOL_TLD.as_ref()], &NAME_PROGRAM_ID).0
+}
+
+/// Owner recorded in the header of a `.sol` domain's name account, `None` unless the
+/// account is a `.sol` domain with an owner
+pub fn record_owner(data: &[u8]) -> Option<Pubkey> {
+    let header = data.get(..NAME_RECORD_HEADER_LEN)?;
+    if header[..OWNER_OFFSET] != SOL_TLD.to_bytes() {
+        return None;
+    }
+    let owner = Pubkey::try_from(&header[OWNER_OFFSET..OWNER_OFFSET + 32]).ok()?;
+    (owner != Pubkey::default()).then_some(owner)
+}
+
+/// Minimal name account under `parent` owned by `owner`, with no class and no data
+pub fn encode_name_record(parent: &Pubkey, owner: &Pubkey) -> Vec<u8> {
+    let mut data = vec![0u8; NAME_RECORD_HEADER_LEN];
+    data[..OWNER_OFFSET].copy_from_slice(parent.as_ref());
+    data[OWNER_OFFSET..OWNER_OFFSET + 32].copy_from_slice(owner.as_ref());
+    data
+}
@@ -0,0 +1,54 @@
+//! # Spam Oracle Interface
+//!
+//! The owner may register a spam-oracle program with `SetSpamOracle`. Sends to a wallet
+//! then invoke the oracle's `score(sender, recipient)` view and record the returned score
+//! in the send log; when a threshold is configured, sends scoring above it are rejected
+//! with `SpamScoreTooHigh`.
+//!
+//! An oracle is any program that handles the instruction built by [`score_instruction`]
+//! (no accounts, data `[SCORE_TAG, sender, recipient]`) and sets its return data to the
+//! score as a little-endian `u64`. Senders pass the oracle program as a trailing account.
+//!
+//! ```rust
+//! use mailer::spam_oracle;
+//! use solana_program::pubkey::Pubkey;
+//!
+//! let (oracle, sender, recipient) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+//! let instruction = spam_oracle::score_instruction(&oracle, &sender, &recipient);
+//! assert_eq!(spam_oracle::decode_score_request(&instruction.data), Some((sender, recipient)));
+//! assert_eq!(spam_oracle::decode_score(&7u64.to_le_bytes()), Some(7));
+//! ```
+
+use solana_program::{instruction::Instruction, pubkey::Pubkey};
+
+/// First byte of a score request
+pub const SCORE_TAG: u8 = 0;
+
+/// Build the `score(sender, recipient)` request sent to an oracle
+pub fn score_instruction(oracle: &Pubkey, sender: &Pubkey, recipient: &Pubkey) -> Instruction {
+    let mut data = Vec::with_capacity(1 + 32 + 32);
+    data.push(SCORE_TAG);
+    data.extend_from_slice(sender.as_ref());
+    data.extend_from_slice(recipient.as_ref());
+    Instruction {
+        program_id: *oracle,
+        accounts: vec![],
+        data,
+    }
+}
+
+/// Sender and recipient of a score request, for oracle implementations
+pub fn decode_score_request(data: &[u8]) -> Option<(Pubkey, Pubkey)> {
+    match data {
+        [SCORE_TAG, keys @ ..] if keys.len() == 64 => Some((
+            Pubkey::new_from_array(keys[..32].try_into().ok()?),
+            Pubkey::new_from_array(keys[32..].try_into().ok()?),
+        )),
+        _ => None,
+    }
+}
+
+/// Score from an oracle's return data
+pub fn decode_score(return_data: &[u8]) -> Option<u64> {
+    Some(u64::from_le_bytes(return_data.try_into().ok()?))
+}
@@ -0,0 +1,595 @@
+//! # Mailer State Versions
+//!
+//! The mailer state account is allocated at [`MailerState::LEN`], so adding a field after
+//! deployment changes the account size. Each layout therefore gets a version, and the
+//! owner upgrades the deployed account one version at a time with `MigrateState`, which
+//! reallocs it to the next layout's size.
+//!
+//! The layout deployed at launch is frozen here as [`MailerStateV0`], and the last layout
+//! before versioning as [`MailerStateV1`]; since version 2 the layout records its version
+//! in [`MailerState::version`]. Because Borsh options make the encoded length vary, the
+//! layout of an account is identified by its allocated size rather than by reading the
+//! version byte.
+//!
+//! To add fields in a new layout version:
+//! 1. Freeze the current [`MailerState`] here as `MailerStateV<N>` with its `LEN`.
+//! 2. Append the new fields to [`MailerState`], grow its `LEN` and bump
+//!    [`MAILER_STATE_VERSION`].
+//! 3. Add the `V<N>` variant to [`VersionedMailerState`] with its size and upgrade step.
+//!
+//! Instructions other than `MigrateState` reject a mailer account in an earlier layout
+//! with `StateMigrationRequired`.
+//!
+//! Recipient claims are versioned the same way, but stay usable in their earlier layout:
+//! [`RecipientClaimV1`] accounts decode with zeroed counters, and the next priority send
+//! to the recipient reallocs them to the current [`RecipientClaim`] layout.
+//!
+//! ```rust
+//! use mailer::state_versions::VersionedMailerState;
+//! use mailer::MAILER_STATE_VERSION;
+//!
+//! let data = vec![0u8; mailer::state_versions::MailerStateV1::LEN];
+//! let state = VersionedMailerState::deserialize(&data).unwrap();
+//! assert_eq!(state.version(), 1);
+//! assert_eq!(state.into_current().version, MAILER_STATE_VERSION);
+//! ```
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+
+use crate::{
+    ClaimCreationPolicy, MailerState, PayloadLimits, PendingFeeChange, RecipientClaim,
+    MAILER_STATE_VERSION, USDC_DECIMALS,
+};
+
+/// Mailer state layout deployed at launch, before any configuration was added to it
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct MailerStateV0 {
+    pub owner: Pubkey,
+    pub usdc_mint: Pubkey,
+    pub send_fee: u64,
+    pub delegation_fee: u64,
+    pub owner_claimable: u64,
+    pub paused: bool,
+    pub fee_paused: bool,
+    pub bump: u8,
+}
+
+impl MailerStateV0 {
+    pub const LEN: usize = 91; // frozen at the launch MailerState::LEN
+}
+
+/// Fields added since launch take the values Initialize gives them, so an upgraded mailer
+/// behaves like a freshly initialized one with the launch fees and owner balance
+impl From<MailerStateV0> for MailerStateV1 {
+    fn from(state: MailerStateV0) -> Self {
+        MailerStateV1 {
+            owner: state.owner,
+            usdc_mint: state.usdc_mint,
+            send_fee: state.send_fee,
+            delegation_fee: state.delegation_fee,
+            owner_claimable: state.owner_claimable,
+            paused: state.paused,
+            fee_paused: state.fee_paused,
+            bump: state.bump,
+            protocol_sends_enabled: false,
+            pending_owner: None,
+            fee_timelock: 0,
+            pending_fee: None,
+            paused_at: 0,
+            total_paused_duration: 0,
+            claim_rent_reserve: 0,
+            delegation_rent_reserve: 0,
+            discount_rent_reserve: 0,
+            disabled_instructions: 0,
+            spam_oracle: None,
+            spam_threshold: None,
+            rate_limit_max_sends: 0,
+            rate_limit_window: 0,
+            lamport_send_fee: 0,
+            owner_claimable_lamports: 0,
+            fee_oracle: None,
+            fee_oracle_target: 0,
+            fee_oracle_max_age: 0,
+            claim_creation_policy: ClaimCreationPolicy::Eager,
+            usdc_decimals: USDC_DECIMALS,
+            sanctions_enabled: false,
+            backup_mint: None,
+            stats_enabled: false,
+            pause_flags: 0,
+            kyc_verifier: None,
+            delegation_refund_window: 0,
+            claim_cap: 0,
+            payload_limits: PayloadLimits::default(),
+            payload_fee_tier_len: 0,
+            payload_fee_tier_percent: 0,
+            recipient_liabilities: 0,
+            min_coverage_bps: 0,
+        }
+    }
+}
+
+/// Last unversioned mailer state layout, before `version` was added
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct MailerStateV1 {
+    pub owner: Pubkey,
+    pub usdc_mint: Pubkey,
+    pub send_fee: u64,
+    pub delegation_fee: u64,
+    pub owner_claimable: u64,
+    pub paused: bool,
+    pub fee_paused: bool,
+    pub bump: u8,
+    pub protocol_sends_enabled: bool,
+    pub pending_owner: Option<Pubkey>,
+    pub fee_timelock: i64,
+    pub pending_fee: Option<PendingFeeChange>,
+    pub paused_at: i64,
+    pub total_paused_duration: i64,
+    pub claim_rent_reserve: u64,
+    pub delegation_rent_reserve: u64,
+    pub discount_rent_reserve: u64,
+    pub disabled_instructions: u128,
+    pub spam_oracle: Option<Pubkey>,
+    pub spam_threshold: Option<u64>,
+    pub rate_limit_max_sends: u64,
+    pub rate_limit_window: i64,
+    pub lamport_send_fee: u64,
+    pub owner_claimable_lamports: u64,
+    pub fee_oracle: Option<Pubkey>,
+    pub fee_oracle_target: u64,
+    pub fee_oracle_max_age: i64,
+    pub claim_creation_policy: ClaimCreationPolicy,
+    pub usdc_decimals: u8,
+    pub sanctions_enabled: bool,
+    pub backup_mint: Option<Pubkey>,
+    pub stats_enabled: bool,
+    pub pause_flags: u8,
+    pub kyc_verifier: Option<Pubkey>,
+    pub delegation_refund_window: i64,
+    pub claim_cap: u64,
+    pub payload_limits: PayloadLimits,
+    pub payload_fee_tier_len: u16,
+    pub payload_fee_tier_percent: u16,
+    pub recipient_liabilities: u64,
+    pub min_coverage_bps: u16,
+}
+
+impl MailerStateV1 {
+    pub const LEN: usize = 440; // frozen at the last unversioned MailerState::LEN
+}
+
+/// Mailer state layout version 2, as deployed before the treasury was added
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct MailerStateV2 {
+    pub owner: Pubkey,
+    pub usdc_mint: Pubkey,
+    pub send_fee: u64,
+    pub delegation_fee: u64,
+    pub owner_claimable: u64,
+    pub paused: bool,
+    pub fee_paused: bool,
+    pub bump: u8,
+    pub protocol_sends_enabled: bool,
+    pub pending_owner: Option<Pubkey>,
+    pub fee_timelock: i64,
+    pub pending_fee: Option<PendingFeeChange>,
+    pub paused_at: i64,
+    pub total_paused_duration: i64,
+    pub claim_rent_reserve: u64,
+    pub delegation_rent_reserve: u64,
+    pub discount_rent_reserve: u64,
+    pub disabled_instructions: u128,
+    pub spam_oracle: Option<Pubkey>,
+    pub spam_threshold: Option<u64>,
+    pub rate_limit_max_sends: u64,
+    pub rate_limit_window: i64,
+    pub lamport_send_fee: u64,
+    pub owner_claimable_lamports: u64,
+    pub fee_oracle: Option<Pubkey>,
+    pub fee_oracle_target: u64,
+    pub fee_oracle_max_age: i64,
+    pub claim_creation_policy: ClaimCreationPolicy,
+    pub usdc_decimals: u8,
+    pub sanctions_enabled: bool,
+    pub backup_mint: Option<Pubkey>,
+    pub stats_enabled: bool,
+    pub pause_flags: u8,
+    pub kyc_verifier: Option<Pubkey>,
+    pub delegation_refund_window: i64,
+    pub claim_cap: u64,
+    pub payload_limits: PayloadLimits,
+    pub payload_fee_tier_len: u16,
+    pub payload_fee_tier_percent: u16,
+    pub recipient_liabilities: u64,
+    pub min_coverage_bps: u16,
+    pub version: u8,
+}
+
+impl MailerStateV2 {
+    pub const LEN: usize = 441; // frozen at the last version 2 MailerState::LEN
+}
+
+impl From<MailerStateV1> for MailerStateV2 {
+    fn from(state: MailerStateV1) -> Self {
+        MailerStateV2 {
+            owner: state.owner,
+            usdc_mint: state.usdc_mint,
+            send_fee: state.send_fee,
+            delegation_fee: state.delegation_fee,
+            owner_claimable: state.owner_claimable,
+            paused: state.paused,
+            fee_paused: state.fee_paused,
+            bump: state.bump,
+            protocol_sends_enabled: state.protocol_sends_enabled,
+            pending_owner: state.pending_owner,
+            fee_timelock: state.fee_timelock,
+            pending_fee: state.pending_fee,
+            paused_at: state.paused_at,
+            total_paused_duration: state.total_paused_duration,
+            claim_rent_reserve: state.claim_rent_reserve,
+            delegation_rent_reserve: state.delegation_rent_reserve,
+            discount_rent_reserve: state.discount_rent_reserve,
+            disabled_instructions: state.disabled_instructions,
+            spam_oracle: state.spam_oracle,
+            spam_threshold: state.spam_threshold,
+            rate_limit_max_sends: state.rate_limit_max_sends,
+            rate_limit_window: state.rate_limit_window,
+            lamport_send_fee: state.lamport_send_fee,
+            owner_claimable_lamports: state.owner_claimable_lamports,
+            fee_oracle: state.fee_oracle,
+            fee_oracle_target: state.fee_oracle_target,
+            fee_oracle_max_age: state.fee_oracle_max_age,
+            claim_creation_policy: state.claim_creation_policy,
+            usdc_decimals: state.usdc_decimals,
+            sanctions_enabled: state.sanctions_enabled,
+            backup_mint: state.backup_mint,
+            stats_enabled: state.stats_enabled,
+            pause_flags: state.pause_flags,
+            kyc_verifier: state.kyc_verifier,
+            delegation_refund_window: state.delegation_refund_window,
+            claim_cap: state.claim_cap,
+            payload_limits: state.payload_limits,
+            payload_fee_tier_len: state.payload_fee_tier_len,
+            payload_fee_tier_percent: state.payload_fee_tier_percent,
+            recipient_liabilities: state.recipient_liabilities,
+            min_coverage_bps: state.min_coverage_bps,
+            version: 2,
+        }
+    }
+}
+
+/// Mailer state layout version 3, as deployed before send credit liabilities were tracked
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct MailerStateV3 {
+    pub owner: Pubkey,
+    pub usdc_mint: Pubkey,
+    pub send_fee: u64,
+    pub delegation_fee: u64,
+    pub owner_claimable: u64,
+    pub paused: bool,
+    pub fee_paused: bool,
+    pub bump: u8,
+    pub protocol_sends_enabled: bool,
+    pub pending_owner: Option<Pubkey>,
+    pub fee_timelock: i64,
+    pub pending_fee: Option<PendingFeeChange>,
+    pub paused_at: i64,
+    pub total_paused_duration: i64,
+    pub claim_rent_reserve: u64,
+    pub delegation_rent_reserve: u64,
+    pub discount_rent_reserve: u64,
+    pub disabled_instructions: u128,
+    pub spam_oracle: Option<Pubkey>,
+    pub spam_threshold: Option<u64>,
+    pub rate_limit_max_sends: u64,
+    pub rate_limit_window: i64,
+    pub lamport_send_fee: u64,
+    pub owner_claimable_lamports: u64,
+    pub fee_oracle: Option<Pubkey>,
+    pub fee_oracle_target: u64,
+    pub fee_oracle_max_age: i64,
+    pub claim_creation_policy: ClaimCreationPolicy,
+    pub usdc_decimals: u8,
+    pub sanctions_enabled: bool,
+    pub backup_mint: Option<Pubkey>,
+    pub stats_enabled: bool,
+    pub pause_flags: u8,
+    pub kyc_verifier: Option<Pubkey>,
+    pub delegation_refund_window: i64,
+    pub claim_cap: u64,
+    pub payload_limits: PayloadLimits,
+    pub payload_fee_tier_len: u16,
+    pub payload_fee_tier_percent: u16,
+    pub recipient_liabilities: u64,
+    pub min_coverage_bps: u16,
+    pub version: u8,
+    pub treasury: Option<Pubkey>,
+}
+
+impl MailerStateV3 {
+    pub const LEN: usize = 474; // frozen at the last version 3 MailerState::LEN
+}
+
+impl From<MailerStateV2> for MailerStateV3 {
+    fn from(state: MailerStateV2) -> Self {
+        MailerStateV3 {
+            owner: state.owner,
+            usdc_mint: state.usdc_mint,
+            send_fee: state.send_fee,
+            delegation_fee: state.delegation_fee,
+            owner_claimable: state.owner_claimable,
+            paused: state.paused,
+            fee_paused: state.fee_paused,
+            bump: state.bump,
+            protocol_sends_enabled: state.protocol_sends_enabled,
+            pending_owner: state.pending_owner,
+            fee_timelock: state.fee_timelock,
+            pending_fee: state.pending_fee,
+            paused_at: state.paused_at,
+            total_paused_duration: state.total_paused_duration,
+            claim_rent_reserve: state.claim_rent_reserve,
+            delegation_rent_reserve: state.delegation_rent_reserve,
+            discount_rent_reserve: state.discount_rent_reserve,
+            disabled_instructions: state.disabled_instructions,
+            spam_oracle: state.spam_oracle,
+            spam_threshold: state.spam_threshold,
+            rate_limit_max_sends: state.rate_limit_max_sends,
+            rate_limit_window: state.rate_limit_window,
+            lamport_send_fee: state.lamport_send_fee,
+            owner_claimable_lamports: state.owner_claimable_lamports,
+            fee_oracle: state.fee_oracle,
+            fee_oracle_target: state.fee_oracle_target,
+            fee_oracle_max_age: state.fee_oracle_max_age,
+            claim_creation_policy: state.claim_creation_policy,
+            usdc_decimals: state.usdc_decimals,
+            sanctions_enabled: state.sanctions_enabled,
+            backup_mint: state.backup_mint,
+            stats_enabled: state.stats_enabled,
+            pause_flags: state.pause_flags,
+            kyc_verifier: state.kyc_verifier,
+            delegation_refund_window: state.delegation_refund_window,
+            claim_cap: state.claim_cap,
+            payload_limits: state.payload_limits,
+            payload_fee_tier_len: state.payload_fee_tier_len,
+            payload_fee_tier_percent: state.payload_fee_tier_percent,
+            recipient_liabilities: state.recipient_liabilities,
+            min_coverage_bps: state.min_coverage_bps,
+            version: 3,
+            treasury: None,
+        }
+    }
+}
+
+impl From<MailerStateV3> for MailerState {
+    fn from(state: MailerStateV3) -> Self {
+        MailerState {
+            owner: state.owner,
+            usdc_mint: state.usdc_mint,
+            send_fee: state.send_fee,
+            delegation_fee: state.delegation_fee,
+            owner_claimable: state.owner_claimable,
+            paused: state.paused,
+            fee_paused: state.fee_paused,
+            bump: state.bump,
+            protocol_sends_enabled: state.protocol_sends_enabled,
+            pending_owner: state.pending_owner,
+            fee_timelock: state.fee_timelock,
+            pending_fee: state.pending_fee,
+            paused_at: state.paused_at,
+            total_paused_duration: state.total_paused_duration,
+            claim_rent_reserve: state.claim_rent_reserve,
+            delegation_rent_reserve: state.delegation_rent_reserve,
+            discount_rent_reserve: state.discount_rent_reserve,
+            disabled_instructions: state.disabled_instructions,
+            spam_oracle: state.spam_oracle,
+            spam_threshold: state.spam_threshold,
+            rate_limit_max_sends: state.rate_limit_max_sends,
+            rate_limit_window: state.rate_limit_window,
+            lamport_send_fee: state.lamport_send_fee,
+            owner_claimable_lamports: state.owner_claimable_lamports,
+            fee_oracle: state.fee_oracle,
+            fee_oracle_target: state.fee_oracle_target,
+            fee_oracle_max_age: state.fee_oracle_max_age,
+            claim_creation_policy: state.claim_creation_policy,
+            usdc_decimals: state.usdc_decimals,
+            sanctions_enabled: state.sanctions_enabled,
+            backup_mint: state.backup_mint,
+            stats_enabled: state.stats_enabled,
+            pause_flags: state.pause_flags,
+            kyc_verifier: state.kyc_verifier,
+            delegation_refund_window: state.delegation_refund_window,
+            claim_cap: state.claim_cap,
+            payload_limits: state.payload_limits,
+            payload_fee_tier_len: state.payload_fee_tier_len,
+            payload_fee_tier_percent: state.payload_fee_tier_percent,
+            recipient_liabilities: state.recipient_liabilities,
+            min_coverage_bps: state.min_coverage_bps,
+            version: 4,
+            treasury: state.treasury,
+            credit_liabilities: 0,
+        }
+    }
+}
+
+/// Mailer state in any layout this program can read
+#[derive(Debug, Clone)]
+pub enum VersionedMailerState {
+    V0(MailerStateV0),
+    V1(MailerStateV1),
+    V2(MailerStateV2),
+    V3(MailerStateV3),
+    V4(MailerState),
+}
+
+impl VersionedMailerState {
+    /// Decode mailer state account data following the discriminator, in whichever layout
+    /// its size identifies
+    pub fn deserialize(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() == MailerStateV0::LEN {
+            return Ok(Self::V0(MailerStateV0::deserialize(&mut &data[..])?));
+        }
+        if data.len() == MailerStateV1::LEN {
+            return Ok(Self::V1(MailerStateV1::deserialize(&mut &data[..])?));
+        }
+        if data.len() == MailerStateV2::LEN {
+            return Ok(Self::V2(MailerStateV2::deserialize(&mut &data[..])?));
+        }
+        if data.len() == MailerStateV3::LEN {
+            return Ok(Self::V3(MailerStateV3::deserialize(&mut &data[..])?));
+        }
+        if data.len() < MailerState::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let state = MailerState::deserialize(&mut &data[..])?;
+        if state.version != MAILER_STATE_VERSION {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(Self::V4(state))
+    }
+
+    /// Layout version of the state
+    pub fn version(&self) -> u8 {
+        match self {
+            Self::V0(_) => 0,
+            Self::V1(_) => 1,
+            Self::V2(state) => state.version,
+            Self::V3(state) => state.version,
+            Self::V4(state) => state.version,
+        }
+    }
+
+    /// Account data size of the state's layout, excluding the discriminator
+    pub fn data_len(&self) -> usize {
+        match self {
+            Self::V0(_) => MailerStateV0::LEN,
+            Self::V1(_) => MailerStateV1::LEN,
+            Self::V2(_) => MailerStateV2::LEN,
+            Self::V3(_) => MailerStateV3::LEN,
+            Self::V4(_) => MailerState::LEN,
+        }
+    }
+
+    /// Encode the state in its own layout into account data following the discriminator
+    pub fn serialize(&self, data: &mut [u8]) -> Result<(), ProgramError> {
+        match self {
+            Self::V0(state) => state.serialize(&mut &mut data[..])?,
+            Self::V1(state) => state.serialize(&mut &mut data[..])?,
+            Self::V2(state) => state.serialize(&mut &mut data[..])?,
+            Self::V3(state) => state.serialize(&mut &mut data[..])?,
+            Self::V4(state) => state.serialize(&mut &mut data[..])?,
+        }
+        Ok(())
+    }
+
+    /// Whether the state is in the current layout
+    pub fn is_current(&self) -> bool {
+        self.version() == MAILER_STATE_VERSION
+    }
+
+    /// The state in the next layout version, `None` if it is already current
+    pub fn upgrade(self) -> Option<Self> {
+        match self {
+            Self::V0(state) => Some(Self::V1(state.into())),
+            Self::V1(state) => Some(Self::V2(state.into())),
+            Self::V2(state) => Some(Self::V3(state.into())),
+            Self::V3(state) => Some(Self::V4(state.into())),
+            Self::V4(_) => None,
+        }
+    }
+
+    /// The state upgraded through every later layout to the current one
+    pub fn into_current(self) -> MailerState {
+        match self {
+            Self::V0(state) => Self::V1(state.into()).into_current(),
+            Self::V1(state) => Self::V2(state.into()).into_current(),
+            Self::V2(state) => Self::V3(state.into()).into_current(),
+            Self::V3(state) => state.into(),
+            Self::V4(state) => state,
+        }
+    }
+}
+
+/// Recipient claim layout before the received and claimed counters were added
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct RecipientClaimV1 {
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+    pub bump: u8,
+    pub paused_offset: i64,
+    pub no_expiry: bool,
+    pub surcharge_paid_until: i64,
+    pub extensions: u8,
+}
+
+impl RecipientClaimV1 {
+    pub const LEN: usize = 67; // frozen at the last RecipientClaim::LEN without counters
+}
+
+impl From<RecipientClaimV1> for RecipientClaim {
+    fn from(claim: RecipientClaimV1) -> Self {
+        RecipientClaim {
+            recipient: claim.recipient,
+            amount: claim.amount,
+            timestamp: claim.timestamp,
+            bump: claim.bump,
+            paused_offset: claim.paused_offset,
+            no_expiry: claim.no_expiry,
+            surcharge_paid_until: claim.surcharge_paid_until,
+            extensions: claim.extensions,
+            messages_received: 0,
+            total_claimed: 0,
+        }
+    }
+}
+
+impl From<&RecipientClaim> for RecipientClaimV1 {
+    fn from(claim: &RecipientClaim) -> Self {
+        RecipientClaimV1 {
+            recipient: claim.recipient,
+            amount: claim.amount,
+            timestamp: claim.timestamp,
+            bump: claim.bump,
+            paused_offset: claim.paused_offset,
+            no_expiry: claim.no_expiry,
+            surcharge_paid_until: claim.surcharge_paid_until,
+            extensions: claim.extensions,
+        }
+    }
+}
+
+/// Recipient claim in any layout this program can read
+#[derive(Debug, Clone)]
+pub enum VersionedRecipientClaim {
+    V1(RecipientClaimV1),
+    V2(RecipientClaim),
+}
+
+impl VersionedRecipientClaim {
+    /// Decode recipient claim account data following the discriminator, in whichever
+    /// layout its size identifies
+    pub fn deserialize(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() == RecipientClaimV1::LEN {
+            return Ok(Self::V1(RecipientClaimV1::deserialize(&mut &data[..])?));
+        }
+        Ok(Self::V2(RecipientClaim::deserialize(&mut &data[..])?))
+    }
+
+    /// Layout version of the claim
+    pub fn version(&self) -> u8 {
+        match self {
+            Self::V1(_) => 1,
+            Self::V2(_) => 2,
+        }
+    }
+
+    /// The claim in the current layout, with zeroed counters if they were not yet kept
+    pub fn into_current(self) -> RecipientClaim {
+        match self {
+            Self::V1(claim) => claim.into(),
+            Self::V2(claim) => claim,
+        }
+    }
+}
@@ -0,0 +1,1221 @@
+//! # Canonical Serialization Test Vectors
+//!
+//! Canonical Borsh bytes for every instruction and account type, built from fixed,
+//! well-known field values. Cross-language clients (TypeScript, Python) can assert
+//! byte-for-byte compatibility against the generated fixture in
+//! `test-vectors/vectors.json` instead of discovering encoding mismatches on-chain.
+//!
+//! Known values:
+//! - Pubkeys are `[n; 32]` for a small `n` (see [`key`])
+//! - Strings are short ASCII literals
+//! - Account vectors include the 8-byte discriminator prefix
+//!
+//! Regenerate the fixture with:
+//! ```text
+//! UPDATE_TEST_VECTORS=1 cargo test -p mailer --test integration_tests test_vectors
+//! ```
+
+use borsh::BorshSerialize;
+use solana_program::pubkey::Pubkey;
+
+use crate::instruction_versions::Reserved;
+use crate::{
+    discriminator, hash_email, AdminSet, ClaimBeneficiary, ClaimCreationPolicy, ClaimDistribution,
+    ClaimHistoryEntry, ClaimRentPayer, ClaimSplit, ClosureLog, ClosureRecord, ConversionVault,
+    DelegateIndex, Delegation, DiscountSchedule, Escrow, EvmMapping, FeeDiscount, FeeMint,
+    InboxMode, InboxPolicy, ListChunk, MailerInstruction, MailerState, MailerStats, MailingList,
+    MessageNonce, MessageReceipt, MigrationKind, PayloadLimits, PayoutConfig, PendingFeeChange,
+    Plan, Preauthorization, Promo, RateLimit, RecipientClaim, RecipientInbox, RecipientPrefs,
+    Sanctioned, SendCredits, SendNonce, SenderStats, SessionKey, Subscription, SweepCursor,
+    VolumeTier, Webhook, WormholeConfig,
+};
+
+/// A single named test vector
+#[derive(Debug, Clone)]
+pub struct TestVector {
+    pub name: &'static str,
+    /// Debug rendering of the value, documenting the field values used
+    pub value: String,
+    pub bytes: Vec<u8>,
+}
+
+/// Deterministic pubkey used throughout the vectors
+pub fn key(n: u8) -> Pubkey {
+    Pubkey::new_from_array([n; 32])
+}
+
+fn instruction(name: &'static str, ix: MailerInstruction) -> TestVector {
+    TestVector {
+        name,
+        value: format!("{:?}", ix),
+        bytes: borsh::to_vec(&ix).expect("instruction serializes"),
+    }
+}
+
+fn account<T: BorshSerialize + std::fmt::Debug>(name: &'static str, state: T) -> TestVector {
+    let mut bytes = discriminator::account(name).to_vec();
+    bytes.extend(borsh::to_vec(&state).expect("account serializes"));
+    TestVector {
+        name,
+        value: format!("{:?}", state),
+        bytes,
+    }
+}
+
+/// Canonical vectors for every instruction variant, in discriminant order
+pub fn instruction_vectors() -> Vec<TestVector> {
+    vec![
+        instruction(
+            "Initialize",
+            MailerInstruction::Initialize { usdc_mint: key(1) },
+        ),
+        instruction(
+            "Send",
+            MailerInstruction::Send {
+                to: key(2),
+                subject: "Hello".to_string(),
+                _body: "World".to_string(),
+                revenue_share_to_receiver: true,
+                resolve_sender_to_name: false,
+                request_read_receipt: true,
+                receipt_nonce: Some(7),
+                memo: Some("INV-001".to_string()),
+                strict: true,
+                thread_id: Some([0x11; 32]),
+                in_reply_to: Some([0x22; 32]),
+                allow_downgrade: true,
+                tip: 25_000,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+        ),
+        instruction(
+            "SendPrepared",
+            MailerInstruction::SendPrepared {
+                to: key(2),
+                mail_id: "mail-1".to_string(),
+                revenue_share_to_receiver: false,
+                resolve_sender_to_name: true,
+                strict: false,
+                thread_id: Some([0x11; 32]),
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+            },
+        ),
+        instruction(
+            "SendToEmail",
+            MailerInstruction::SendToEmail {
+                to_email: "a@b.co".to_string(),
+                subject: "Hello".to_string(),
+                _body: "World".to_string(),
+                strict: false,
+            },
+        ),
+        instruction(
+            "SendPreparedToEmail",
+            MailerInstruction::SendPreparedToEmail {
+                to_email: "a@b.co".to_string(),
+                mail_id: "mail-1".to_string(),
+                strict: false,
+            },
+        ),
+        instruction(
+            "SendThroughWebhook",
+            MailerInstruction::SendThroughWebhook {
+                to: key(2),
+                webhook_id: "hook-1".to_string(),
+                revenue_share_to_receiver: true,
+                resolve_sender_to_name: true,
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+            },
+        ),
+        instruction(
+            "ClaimRecipientShare",
+            MailerInstruction::ClaimRecipientShare {
+                amount: Some(50_000),
+                split: Some(vec![
+                    ClaimSplit {
+                        destination: key(26),
+                        bps: 7_500,
+                    },
+                    ClaimSplit {
+                        destination: key(27),
+                        bps: 2_500,
+                    },
+                ]),
+                close_to: Some(key(28)),
+            },
+        ),
+        instruction("ClaimOwnerShare", MailerInstruction::ClaimOwnerShare),
+        instruction(
+            "SetFee",
+            MailerInstruction::SetFee {
+                new_fee: 100_000,
+                expected_current_value: Some(50_000),
+            },
+        ),
+        instruction(
+            "DelegateTo",
+            MailerInstruction::DelegateTo {
+                delegate: Some(key(3)),
+            },
+        ),
+        instruction("RejectDelegation", MailerInstruction::RejectDelegation),
+        instruction(
+            "SetDelegationFee",
+            MailerInstruction::SetDelegationFee {
+                new_fee: 10_000_000,
+                expected_current_value: Some(5_000_000),
+            },
+        ),
+        instruction(
+            "SetCustomFeePercentage",
+            MailerInstruction::SetCustomFeePercentage {
+                account: key(4),
+                percentage: 25,
+            },
+        ),
+        instruction(
+            "ClearCustomFeePercentage",
+            MailerInstruction::ClearCustomFeePercentage { account: key(4) },
+        ),
+        instruction("Pause", MailerInstruction::Pause),
+        instruction("Unpause", MailerInstruction::Unpause),
+        instruction(
+            "DistributeClaimableFunds",
+            MailerInstruction::DistributeClaimableFunds { recipient: key(2) },
+        ),
+        instruction(
+            "ClaimExpiredShares",
+            MailerInstruction::ClaimExpiredShares { recipient: key(2) },
+        ),
+        instruction("EmergencyUnpause", MailerInstruction::EmergencyUnpause),
+        instruction(
+            "SetFeePaused",
+            MailerInstruction::SetFeePaused { fee_paused: true },
+        ),
+        instruction(
+            "SetRecipientPrefs",
+            MailerInstruction::SetRecipientPrefs {
+                read_receipts_opt_out: true,
+            },
+        ),
+        instruction(
+            "AcknowledgeMessage",
+            MailerInstruction::AcknowledgeMessage {
+                sender: key(5),
+                receipt_nonce: None,
+            },
+        ),
+        instruction(
+            "SetProtocolSendsEnabled",
+            MailerInstruction::SetProtocolSendsEnabled { enabled: true },
+        ),
+        instruction(
+            "SendProtocolMessage",
+            MailerInstruction::SendProtocolMessage {
+                to: key(2),
+                subject: "Hello".to_string(),
+                _body: "World".to_string(),
+            },
+        ),
+        instruction(
+            "TransferOwnership",
+            MailerInstruction::TransferOwnership { new_owner: key(6) },
+        ),
+        instruction("AcceptOwnership", MailerInstruction::AcceptOwnership),
+        instruction(
+            "SetAdmins",
+            MailerInstruction::SetAdmins {
+                admins: vec![key(7), key(8)],
+                threshold: 2,
+            },
+        ),
+        instruction(
+            "SweepExpiredPage",
+            MailerInstruction::SweepExpiredPage { max_items: 10 },
+        ),
+        instruction("ResetSweepCursor", MailerInstruction::ResetSweepCursor),
+        instruction(
+            "ProposeFee",
+            MailerInstruction::ProposeFee { new_fee: 200_000 },
+        ),
+        instruction(
+            "SetFeeTimelock",
+            MailerInstruction::SetFeeTimelock { delay: 86_400 },
+        ),
+        instruction(
+            "SetInboxPolicy",
+            MailerInstruction::SetInboxPolicy {
+                mode: InboxMode::Allowlist,
+                senders: vec![key(5)],
+                penalty_multiplier: 3,
+                min_fee: 500_000,
+            },
+        ),
+        instruction(
+            "ClaimRecipientShareToAta",
+            MailerInstruction::ClaimRecipientShareToAta,
+        ),
+        instruction(
+            "RegisterSessionKey",
+            MailerInstruction::RegisterSessionKey {
+                session_key: key(12),
+                scope: SessionKey::SCOPE_ALL,
+                expires_at: 1_700_086_400,
+            },
+        ),
+        instruction(
+            "RevokeSessionKey",
+            MailerInstruction::RevokeSessionKey {
+                session_key: key(12),
+            },
+        ),
+        instruction(
+            "SetPayoutAddress",
+            MailerInstruction::SetPayoutAddress {
+                payout_token_account: Some(key(13)),
+            },
+        ),
+        instruction(
+            "SetConversionVault",
+            MailerInstruction::SetConversionVault {
+                mint: key(14),
+                enabled: true,
+            },
+        ),
+        instruction(
+            "ClaimRecipientShareAs",
+            MailerInstruction::ClaimRecipientShareAs { mint: key(14) },
+        ),
+        instruction("GetConfig", MailerInstruction::GetConfig),
+        instruction(
+            "CreatePreauthorization",
+            MailerInstruction::CreatePreauthorization {
+                relayer: key(16),
+                webhook_id: "webhook-123".to_string(),
+                max_sends: 5,
+                max_fee_per_send: 100_000,
+                expires_at: 1_700_086_400,
+            },
+        ),
+        instruction(
+            "RevokePreauthorization",
+            MailerInstruction::RevokePreauthorization { relayer: key(16) },
+        ),
+        instruction(
+            "SendPreauthorized",
+            MailerInstruction::SendPreauthorized {
+                to: key(2),
+                webhook_id: "webhook-123".to_string(),
+                revenue_share_to_receiver: true,
+            },
+        ),
+        instruction(
+            "SetInstructionDisabled",
+            MailerInstruction::SetInstructionDisabled {
+                tag: 3,
+                disabled: true,
+            },
+        ),
+        instruction(
+            "MigrateDiscriminator",
+            MailerInstruction::MigrateDiscriminator,
+        ),
+        instruction(
+            "CloseRecipientClaim",
+            MailerInstruction::CloseRecipientClaim { recipient: key(2) },
+        ),
+        instruction("CloseDelegation", MailerInstruction::CloseDelegation),
+        instruction(
+            "CloseFeeDiscount",
+            MailerInstruction::CloseFeeDiscount { account: key(3) },
+        ),
+        instruction(
+            "MigrateAccountVersion",
+            MailerInstruction::MigrateAccountVersion {
+                kind: MigrationKind::Delegation,
+            },
+        ),
+        instruction(
+            "SetSpamOracle",
+            MailerInstruction::SetSpamOracle {
+                oracle: Some(key(17)),
+                threshold: Some(80),
+            },
+        ),
+        instruction(
+            "SetRateLimit",
+            MailerInstruction::SetRateLimit {
+                max_sends: 100,
+                window: 86_400,
+            },
+        ),
+        instruction(
+            "CreatePromo",
+            MailerInstruction::CreatePromo {
+                code_hash: [0xab; 32],
+                discount: 25,
+                max_redemptions: 100,
+                expiry: 1_700_086_400,
+            },
+        ),
+        instruction(
+            "RedeemPromo",
+            MailerInstruction::RedeemPromo {
+                code: "WELCOME10".to_string(),
+            },
+        ),
+        instruction(
+            "ClaimAndSend",
+            MailerInstruction::ClaimAndSend {
+                amount: Some(50_000),
+                to: key(2),
+                subject: "Re: Hello".to_string(),
+                _body: "Thanks".to_string(),
+                revenue_share_to_receiver: true,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: true,
+            },
+        ),
+        instruction(
+            "AddFeeMint",
+            MailerInstruction::AddFeeMint {
+                mint: key(14),
+                send_fee: 250_000,
+                delegation_fee: 25_000_000,
+            },
+        ),
+        instruction(
+            "RemoveFeeMint",
+            MailerInstruction::RemoveFeeMint { mint: key(14) },
+        ),
+        instruction(
+            "SetLamportFee",
+            MailerInstruction::SetLamportFee {
+                lamport_fee: 1_000_000,
+            },
+        ),
+        instruction("ClaimOwnerLamports", MailerInstruction::ClaimOwnerLamports),
+        instruction(
+            "SetFeeOracle",
+            MailerInstruction::SetFeeOracle {
+                oracle: Some(key(18)),
+                target_fee: 100_000,
+                max_age: 60,
+            },
+        ),
+        instruction(
+            "SetClaimCreationPolicy",
+            MailerInstruction::SetClaimCreationPolicy {
+                policy: ClaimCreationPolicy::RefundPayer,
+            },
+        ),
+        instruction("CloseFundedClaim", MailerInstruction::CloseFundedClaim),
+        instruction(
+            "SendEncrypted",
+            MailerInstruction::SendEncrypted {
+                to: key(2),
+                ephemeral_key: [0x33; 32],
+                nonce: [0x44; 12],
+                ciphertext: vec![0x55; 21],
+                revenue_share_to_receiver: true,
+                strict: false,
+            },
+        ),
+        instruction(
+            "RegisterWebhook",
+            MailerInstruction::RegisterWebhook {
+                webhook_id: "webhook-123".to_string(),
+                endpoint_hash: [0x66; 32],
+                surcharge: 25_000,
+            },
+        ),
+        instruction(
+            "UpdateWebhook",
+            MailerInstruction::UpdateWebhook {
+                endpoint_hash: [0x77; 32],
+                surcharge: 30_000,
+            },
+        ),
+        instruction("RevokeWebhook", MailerInstruction::RevokeWebhook),
+        instruction(
+            "ClaimWebhookRevenue",
+            MailerInstruction::ClaimWebhookRevenue,
+        ),
+        instruction(
+            "InitializeClosureLog",
+            MailerInstruction::InitializeClosureLog,
+        ),
+        instruction(
+            "SendAsDelegator",
+            MailerInstruction::SendAsDelegator {
+                to: key(2),
+                subject: "On behalf".to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: true,
+                strict: true,
+            },
+        ),
+        instruction(
+            "SanctionAddress",
+            MailerInstruction::SanctionAddress { address: key(22) },
+        ),
+        instruction(
+            "UnsanctionAddress",
+            MailerInstruction::UnsanctionAddress { address: key(22) },
+        ),
+        instruction(
+            "SetSanctionsEnabled",
+            MailerInstruction::SetSanctionsEnabled { enabled: true },
+        ),
+        instruction(
+            "SetBackupMint",
+            MailerInstruction::SetBackupMint {
+                mint: Some(key(23)),
+            },
+        ),
+        instruction("InitializeStats", MailerInstruction::InitializeStats),
+        instruction(
+            "SetClaimNoExpiry",
+            MailerInstruction::SetClaimNoExpiry { enabled: true },
+        ),
+        instruction(
+            "ClaimExpiredSharesBatch",
+            MailerInstruction::ClaimExpiredSharesBatch,
+        ),
+        instruction(
+            "SetPauseFlags",
+            MailerInstruction::SetPauseFlags { flags: 0b0101 },
+        ),
+        instruction(
+            "SetKycVerifier",
+            MailerInstruction::SetKycVerifier {
+                verifier: Some(key(25)),
+            },
+        ),
+        instruction(
+            "SetDelegationRefundWindow",
+            MailerInstruction::SetDelegationRefundWindow { window: 172_800 },
+        ),
+        instruction("AcceptDelegation", MailerInstruction::AcceptDelegation),
+        instruction(
+            "RefundDelegationFee",
+            MailerInstruction::RefundDelegationFee,
+        ),
+        instruction(
+            "RecordDeliveryStatus",
+            MailerInstruction::RecordDeliveryStatus {
+                to: key(2),
+                nonce: 7,
+                sent_slot: 250_000_000,
+                delivered: true,
+            },
+        ),
+        instruction("GetWebhookStats", MailerInstruction::GetWebhookStats),
+        instruction(
+            "DepositSendCredits",
+            MailerInstruction::DepositSendCredits { amount: 5_000_000 },
+        ),
+        instruction(
+            "WithdrawSendCredits",
+            MailerInstruction::WithdrawSendCredits {
+                amount: Some(1_000_000),
+            },
+        ),
+        instruction(
+            "SetClaimCap",
+            MailerInstruction::SetClaimCap { cap: 5_000_000 },
+        ),
+        instruction(
+            "SetPlan",
+            MailerInstruction::SetPlan {
+                plan_id: 7,
+                price: 5_000_000,
+                duration: 2_592_000,
+                max_messages: 500,
+                active: true,
+            },
+        ),
+        instruction("Subscribe", MailerInstruction::Subscribe { plan_id: 7 }),
+        instruction("RenewSubscription", MailerInstruction::RenewSubscription),
+        instruction(
+            "SetPayloadLimits",
+            MailerInstruction::SetPayloadLimits {
+                limits: PayloadLimits {
+                    max_subject_len: 200,
+                    max_body_len: 1_000,
+                    max_mail_id_len: 64,
+                    max_email_len: 254,
+                    max_webhook_id_len: 64,
+                },
+                fee_tier_len: 256,
+                fee_tier_percent: 25,
+            },
+        ),
+        instruction(
+            "SendToEmailHash",
+            MailerInstruction::SendToEmailHash {
+                email_hash: hash_email(b"salt", "a@b.co").to_vec(),
+                hint_id: 42,
+                subject: "Hello".to_string(),
+                _body: "World".to_string(),
+                strict: false,
+            },
+        ),
+        instruction(
+            "SetSolvencyThreshold",
+            MailerInstruction::SetSolvencyThreshold {
+                min_coverage_bps: 9_000,
+            },
+        ),
+        instruction("ExtendClaimWindow", MailerInstruction::ExtendClaimWindow),
+        instruction(
+            "SetClaimBeneficiary",
+            MailerInstruction::SetClaimBeneficiary {
+                beneficiary: Some(key(3)),
+            },
+        ),
+        instruction(
+            "ClaimAsBeneficiary",
+            MailerInstruction::ClaimAsBeneficiary { recipient: key(2) },
+        ),
+        instruction("MigrateState", MailerInstruction::MigrateState),
+        instruction(
+            "SetTreasury",
+            MailerInstruction::SetTreasury {
+                token_account: Some(key(29)),
+            },
+        ),
+        instruction("VerifySolvency", MailerInstruction::VerifySolvency),
+        instruction(
+            "SetClaimMerkleRoot",
+            MailerInstruction::SetClaimMerkleRoot {
+                distribution_id: 7,
+                merkle_root: [0x5a; 32],
+                leaf_count: 3,
+                total: 3_000_000,
+            },
+        ),
+        instruction(
+            "ClaimFromMerkle",
+            MailerInstruction::ClaimFromMerkle {
+                distribution_id: 7,
+                index: 2,
+                amount: 1_000_000,
+                proof: vec![[0x11; 32], [0x22; 32]],
+            },
+        ),
+        instruction(
+            "SendEscrowed",
+            MailerInstruction::SendEscrowed {
+                to: key(2),
+                subject: "Intro".to_string(),
+                _body: "Worth your time".to_string(),
+                nonce: 7,
+            },
+        ),
+        instruction(
+            "AcceptMessage",
+            MailerInstruction::AcceptMessage {
+                sender: key(1),
+                nonce: 7,
+            },
+        ),
+        instruction(
+            "ReclaimEscrow",
+            MailerInstruction::ReclaimEscrow { nonce: 7 },
+        ),
+        instruction("CreateList", MailerInstruction::CreateList { list_id: 7 }),
+        instruction(
+            "AddListMember",
+            MailerInstruction::AddListMember {
+                list_id: 7,
+                chunk: 0,
+                member: key(2),
+            },
+        ),
+        instruction(
+            "RemoveListMember",
+            MailerInstruction::RemoveListMember {
+                list_id: 7,
+                chunk: 0,
+                member: key(2),
+            },
+        ),
+        instruction(
+            "SendToList",
+            MailerInstruction::SendToList {
+                list_id: 7,
+                subject: "Release notes".to_string(),
+                _body: "Version 2 is live".to_string(),
+            },
+        ),
+        instruction(
+            "SendToDomain",
+            MailerInstruction::SendToDomain {
+                domain: "bonfida.sol".to_string(),
+                subject: "Hello".to_string(),
+                _body: "World".to_string(),
+                revenue_share_to_receiver: true,
+                strict: false,
+            },
+        ),
+        instruction(
+            "BindEvmAddress",
+            MailerInstruction::BindEvmAddress {
+                evm_address: [0xab; 20],
+            },
+        ),
+        instruction(
+            "SendToEvmAddress",
+            MailerInstruction::SendToEvmAddress {
+                evm_address: [0xab; 20],
+                subject: "Hello".to_string(),
+                _body: "World".to_string(),
+                revenue_share_to_receiver: true,
+                strict: false,
+            },
+        ),
+        instruction(
+            "SetWormholeBridge",
+            MailerInstruction::SetWormholeBridge {
+                core_bridge: Some(key(31)),
+            },
+        ),
+        instruction(
+            "GetFee",
+            MailerInstruction::GetFee {
+                reserved: Reserved::default(),
+            },
+        ),
+        instruction(
+            "GetClaimable",
+            MailerInstruction::GetClaimable {
+                account: key(3),
+                reserved: Reserved::default(),
+            },
+        ),
+        instruction(
+            "GetDelegation",
+            MailerInstruction::GetDelegation {
+                delegator: key(1),
+                reserved: Reserved::default(),
+            },
+        ),
+        instruction(
+            "SetDiscountSchedule",
+            MailerInstruction::SetDiscountSchedule {
+                tiers: vec![
+                    VolumeTier {
+                        min_fees_paid: 1_000_000,
+                        discount: 10,
+                    },
+                    VolumeTier {
+                        min_fees_paid: 10_000_000,
+                        discount: 25,
+                    },
+                ],
+                reserved: Reserved::default(),
+            },
+        ),
+    ]
+}
+
+/// Canonical vectors for every account type, including discriminators
+pub fn account_vectors() -> Vec<TestVector> {
+    vec![
+        account(
+            "MailerState",
+            MailerState {
+                owner: key(6),
+                usdc_mint: key(1),
+                send_fee: 100_000,
+                delegation_fee: 10_000_000,
+                owner_claimable: 42,
+                paused: false,
+                fee_paused: true,
+                bump: 255,
+                protocol_sends_enabled: true,
+                pending_owner: Some(key(9)),
+                fee_timelock: 86_400,
+                pending_fee: Some(PendingFeeChange {
+                    new_fee: 200_000,
+                    effective_at: 1_700_086_400,
+                }),
+                paused_at: 0,
+                total_paused_duration: 604_800,
+                claim_rent_reserve: 1_343_280,
+                delegation_rent_reserve: 1_405_920,
+                discount_rent_reserve: 1_183_200,
+                disabled_instructions: 1 << 3,
+                spam_oracle: Some(key(17)),
+                spam_threshold: Some(80),
+                rate_limit_max_sends: 100,
+                rate_limit_window: 86_400,
+                lamport_send_fee: 1_000_000,
+                owner_claimable_lamports: 300_000,
+                fee_oracle: Some(key(18)),
+                fee_oracle_target: 100_000,
+                fee_oracle_max_age: 60,
+                claim_creation_policy: ClaimCreationPolicy::AfterTransfer,
+                usdc_decimals: 6,
+                sanctions_enabled: true,
+                backup_mint: Some(key(23)),
+                stats_enabled: true,
+                pause_flags: 0b0100,
+                kyc_verifier: Some(key(25)),
+                delegation_refund_window: 172_800,
+                claim_cap: 5_000_000,
+                payload_limits: PayloadLimits {
+                    max_subject_len: 200,
+                    max_body_len: 1_000,
+                    max_mail_id_len: 64,
+                    max_email_len: 254,
+                    max_webhook_id_len: 64,
+                },
+                payload_fee_tier_len: 256,
+                payload_fee_tier_percent: 25,
+                recipient_liabilities: 12_000_000,
+                min_coverage_bps: 9_000,
+                version: 4,
+                treasury: Some(key(29)),
+                credit_liabilities: 5_000_000,
+            },
+        ),
+        account(
+            "RecipientClaim",
+            RecipientClaim {
+                recipient: key(2),
+                amount: 90_000,
+                timestamp: 1_700_000_000,
+                bump: 254,
+                paused_offset: 3_600,
+                no_expiry: true,
+                surcharge_paid_until: 1_702_592_000,
+                extensions: 1,
+                messages_received: 12,
+                total_claimed: 450_000,
+            },
+        ),
+        account(
+            "Delegation",
+            Delegation {
+                delegator: key(2),
+                delegate: Some(key(3)),
+                bump: 253,
+                escrowed_fee: 10_000_000,
+                refund_deadline: 1_700_172_800,
+                fee_refundable: false,
+            },
+        ),
+        account(
+            "FeeDiscount",
+            FeeDiscount {
+                account: key(4),
+                discount: 75,
+                bump: 252,
+            },
+        ),
+        account(
+            "RecipientPrefs",
+            RecipientPrefs {
+                recipient: key(2),
+                read_receipts_opt_out: true,
+                bump: 251,
+            },
+        ),
+        account(
+            "MessageReceipt",
+            MessageReceipt {
+                sender: key(5),
+                recipient: key(2),
+                nonce: 7,
+                sent_at: 1_700_000_000,
+                read_at: 1_700_000_600,
+                bump: 247,
+            },
+        ),
+        account(
+            "ClaimHistoryEntry",
+            ClaimHistoryEntry {
+                recipient: key(2),
+                sender: key(5),
+                total_amount: 180_000,
+                last_amount: 90_000,
+                last_memo: "INV-001".to_string(),
+                last_timestamp: 1_700_000_000,
+                bump: 246,
+            },
+        ),
+        account(
+            "InboxPolicy",
+            InboxPolicy {
+                recipient: key(2),
+                mode: InboxMode::Blocklist,
+                senders: vec![key(5), key(11)],
+                penalty_multiplier: 0,
+                bump: 248,
+                min_fee: 250_000,
+            },
+        ),
+        account(
+            "AdminSet",
+            AdminSet {
+                admins: vec![key(7), key(8)],
+                threshold: 2,
+                bump: 250,
+            },
+        ),
+        account(
+            "SweepCursor",
+            SweepCursor {
+                last_processed: key(10),
+                processed_total: 3,
+                swept_total: 270_000,
+                bump: 249,
+            },
+        ),
+        account(
+            "SessionKey",
+            SessionKey {
+                recipient: key(2),
+                session_key: key(12),
+                scope: SessionKey::SCOPE_PREFS,
+                expires_at: 1_700_086_400,
+                bump: 245,
+            },
+        ),
+        account(
+            "PayoutConfig",
+            PayoutConfig {
+                recipient: key(2),
+                payout_token_account: Some(key(13)),
+                bump: 244,
+            },
+        ),
+        account(
+            "ConversionVault",
+            ConversionVault {
+                mint: key(14),
+                vault: key(15),
+                enabled: true,
+                total_converted: 90_000,
+                bump: 243,
+            },
+        ),
+        account(
+            "Preauthorization",
+            Preauthorization {
+                user: key(1),
+                relayer: key(16),
+                webhook_id: "webhook-123".to_string(),
+                max_sends: 5,
+                sends_used: 2,
+                max_fee_per_send: 100_000,
+                expires_at: 1_700_086_400,
+                bump: 242,
+            },
+        ),
+        account(
+            "RateLimit",
+            RateLimit {
+                sender: key(1),
+                window_start: 1_700_000_000,
+                count: 12,
+                bump: 241,
+            },
+        ),
+        account(
+            "Promo",
+            Promo {
+                code_hash: [0xab; 32],
+                discount: 25,
+                remaining_redemptions: 99,
+                expiry: 1_700_086_400,
+                bump: 240,
+            },
+        ),
+        account(
+            "FeeMint",
+            FeeMint {
+                mint: key(14),
+                send_fee: 250_000,
+                delegation_fee: 25_000_000,
+                owner_claimable: 4_200_000,
+                bump: 239,
+            },
+        ),
+        account(
+            "RecipientInbox",
+            RecipientInbox {
+                recipient: key(3),
+                sequence: 42,
+                bump: 238,
+            },
+        ),
+        account(
+            "ClaimRentPayer",
+            ClaimRentPayer {
+                claim: key(19),
+                payer: key(1),
+                bump: 237,
+            },
+        ),
+        account(
+            "Webhook",
+            Webhook {
+                owner: key(1),
+                id_hash: [0x88; 32],
+                endpoint_hash: [0x66; 32],
+                active: true,
+                surcharge: 25_000,
+                claimable: 75_000,
+                bump: 236,
+                sends_routed: 40,
+                statuses_recorded: 38,
+                deliveries_failed: 2,
+                total_ack_latency_slots: 570,
+            },
+        ),
+        account(
+            "ClosureLog",
+            ClosureLog {
+                total: 66,
+                records: vec![
+                    ClosureRecord {
+                        account: key(20),
+                        state_hash: [0x99; 32],
+                        closed_at: 1_700_000_000,
+                    },
+                    ClosureRecord {
+                        account: key(21),
+                        state_hash: [0xaa; 32],
+                        closed_at: 1_700_000_100,
+                    },
+                ],
+                bump: 235,
+            },
+        ),
+        account(
+            "Sanctioned",
+            Sanctioned {
+                address: key(22),
+                sanctioned_at: 1_700_000_200,
+                bump: 234,
+            },
+        ),
+        account(
+            "DelegateIndex",
+            DelegateIndex {
+                delegate: key(24),
+                count: 2,
+                accumulator: DelegateIndex::accumulate(&[key(1), key(2)]),
+                bump: 233,
+            },
+        ),
+        account(
+            "MailerStats",
+            MailerStats {
+                messages_sent: 1_250,
+                priority_sent: 400,
+                standard_sent: 850,
+                fees_collected: 48_500_000,
+                total_claimed: 31_000_000,
+                bump: 232,
+            },
+        ),
+        account(
+            "SendCredits",
+            SendCredits {
+                sender: key(1),
+                balance: 5_000_000,
+                bump: 231,
+            },
+        ),
+        account(
+            "Plan",
+            Plan {
+                plan_id: 7,
+                price: 5_000_000,
+                duration: 2_592_000,
+                max_messages: 500,
+                active: true,
+                bump: 230,
+            },
+        ),
+        account(
+            "Subscription",
+            Subscription {
+                subscriber: key(1),
+                plan_id: 7,
+                expires_at: 1_700_000_000,
+                messages_remaining: 499,
+                bump: 229,
+            },
+        ),
+        account(
+            "ClaimBeneficiary",
+            ClaimBeneficiary {
+                recipient: key(2),
+                beneficiary: key(3),
+                bump: 228,
+            },
+        ),
+        account(
+            "ClaimDistribution",
+            ClaimDistribution {
+                distribution_id: 7,
+                merkle_root: [0x5a; 32],
+                leaf_count: 3,
+                total: 3_000_000,
+                remaining: 2_000_000,
+                bump: 227,
+                claimed: vec![0b100],
+            },
+        ),
+        account(
+            "Escrow",
+            Escrow {
+                sender: key(1),
+                recipient: key(2),
+                nonce: 7,
+                amount: 100_000,
+                created_at: 1_700_000_000,
+                expires_at: 1_700_604_800,
+                bump: 226,
+            },
+        ),
+        account(
+            "MailingList",
+            MailingList {
+                owner: key(1),
+                list_id: 7,
+                member_count: 2,
+                chunk_count: 1,
+                bump: 225,
+            },
+        ),
+        account(
+            "ListChunk",
+            ListChunk {
+                list: key(30),
+                index: 0,
+                bump: 224,
+                members: vec![key(2), key(3)],
+            },
+        ),
+        account(
+            "EvmMapping",
+            EvmMapping {
+                evm_address: [0xab; 20],
+                wallet: key(2),
+                bound_at: 1_700_000_000,
+                bump: 223,
+            },
+        ),
+        account(
+            "WormholeConfig",
+            WormholeConfig {
+                core_bridge: key(31),
+                bridge: key(32),
+                fee_collector: key(33),
+                sequence: key(34),
+                emitter_bump: 254,
+                message_count: 7,
+                bump: 222,
+            },
+        ),
+        account(
+            "MessageNonce",
+            MessageNonce {
+                sender: key(1),
+                nonce: 42,
+                bump: 221,
+            },
+        ),
+        account(
+            "SendNonce",
+            SendNonce {
+                sender: key(1),
+                total: 3,
+                nonces: vec![7, 42, u64::MAX],
+                bump: 220,
+            },
+        ),
+        account(
+            "DiscountSchedule",
+            DiscountSchedule {
+                bump: 219,
+                tiers: vec![
+                    VolumeTier {
+                        min_fees_paid: 1_000_000,
+                        discount: 10,
+                    },
+                    VolumeTier {
+                        min_fees_paid: 10_000_000,
+                        discount: 25,
+                    },
+                ],
+            },
+        ),
+        account(
+            "SenderStats",
+            SenderStats {
+                sender: key(1),
+                fees_paid: 12_345_678,
+                bump: 218,
+            },
+        ),
+    ]
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub(crate) fn json_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn vectors_json(vectors: &[TestVector]) -> String {
+    vectors
+        .iter()
+        .map(|v| {
+            format!(
+                "    {{\n      \"name\": \"{}\",\n      \"value\": \"{}\",\n      \"hex\": \"{}\"\n    }}",
+                v.name,
+                json_escape(&v.value),
+                hex(&v.bytes)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",\n")
+}
+
+/// Render all vectors as the JSON fixture checked into `test-vectors/vectors.json`
+pub fn to_json() -> String {
+    format!(
+        "{{\n  \"instructions\": [\n{}\n  ],\n  \"accounts\": [\n{}\n  ]\n}}\n",
+        vectors_json(&instruction_vectors()),
+        vectors_json(&account_vectors())
+    )
+}
@@ -0,0 +1,172 @@
+//! # Wormhole Send Mirroring
+//!
+//! The same mailer runs on EVM chains. A `Send` with `bridge` set also posts a Wormhole
+//! message describing the send, so the EVM deployment can mirror the notification once the
+//! guardians sign it. The owner points the program at the Wormhole core bridge with
+//! `SetWormholeBridge`; the posting itself is compiled in only with the `wormhole` feature,
+//! and builds without it reject bridged sends with `WormholeDisabled`.
+//!
+//! Messages are emitted by the program's emitter PDA, numbered by the wormhole config's
+//! message count, and carry the payload built by [`encode_send_payload`]: a version byte,
+//! the sender and recipient, a priority flag and the subject, with big-endian lengths for
+//! EVM decoders. The message fee is read from the core bridge's config account and paid by
+//! the sender.
+//!
+//! ```rust
+//! use mailer::wormhole::{self, MirroredSend};
+//! use solana_program::pubkey::Pubkey;
+//!
+//! let send = MirroredSend {
+//!     sender: Pubkey::new_unique(),
+//!     recipient: Pubkey::new_unique(),
+//!     priority: true,
+//!     subject: "Hello".to_string(),
+//! };
+//! let payload = wormhole::encode_send_payload(&send);
+//! assert_eq!(wormhole::decode_send_payload(&payload), Some(send));
+//! ```
+
+use borsh::BorshSerialize;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program, sysvar,
+};
+
+/// Core bridge instruction index of `post_message`
+pub const POST_MESSAGE_TAG: u8 = 1;
+
+/// Consistency level asking the guardians to wait for finality
+pub const CONSISTENCY_FINALIZED: u8 = 1;
+
+/// Version byte opening every mirrored send payload
+pub const PAYLOAD_VERSION: u8 = 1;
+
+/// Offset of the message fee in the core bridge's config account
+const MESSAGE_FEE_OFFSET: usize = 16;
+
+/// Core bridge accounts a mailer emitter posts through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BridgeAddresses {
+    pub core_bridge: Pubkey,
+    pub bridge: Pubkey,
+    pub fee_collector: Pubkey,
+    pub sequence: Pubkey,
+}
+
+impl BridgeAddresses {
+    /// Accounts of the core bridge `core_bridge` for `emitter`
+    pub fn derive(core_bridge: &Pubkey, emitter: &Pubkey) -> Self {
+        BridgeAddresses {
+            core_bridge: *core_bridge,
+            bridge: bridge_config(core_bridge),
+            fee_collector: fee_collector(core_bridge),
+            sequence: sequence(core_bridge, emitter),
+        }
+    }
+}
+
+/// A send as mirrored to other chains
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MirroredSend {
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub priority: bool,
+    pub subject: String,
+}
+
+/// Config account of the core bridge
+pub fn bridge_config(core_bridge: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"Bridge"], core_bridge).0
+}
+
+/// Account collecting the core bridge's message fees
+pub fn fee_collector(core_bridge: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"fee_collector"], core_bridge).0
+}
+
+/// Sequence account of `emitter` in the core bridge
+pub fn sequence(core_bridge: &Pubkey, emitter: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"Sequence", emitter.as_ref()], core_bridge).0
+}
+
+/// Message fee in lamports recorded in the core bridge's config account data
+pub fn message_fee(bridge_data: &[u8]) -> Option<u64> {
+    let fee = bridge_data.get(MESSAGE_FEE_OFFSET..MESSAGE_FEE_OFFSET + 8)?;
+    Some(u64::from_le_bytes(fee.try_into().ok()?))
+}
+
+/// `post_message` of `payload` by `emitter` into the new account `message`, paid by `payer`
+pub fn post_message_instruction(
+    addresses: &BridgeAddresses,
+    payer: &Pubkey,
+    emitter: &Pubkey,
+    message: &Pubkey,
+    nonce: u32,
+    payload: Vec<u8>,
+) -> Instruction {
+    let mut data = vec![POST_MESSAGE_TAG];
+    (nonce, payload, CONSISTENCY_FINALIZED)
+        .serialize(&mut data)
+        .expect("serializing into a Vec cannot fail");
+    Instruction {
+        program_id: addresses.core_bridge,
+        accounts: vec![
+            AccountMeta::new(addresses.bridge, false),
+            AccountMeta::new(*message, true),
+            AccountMeta::new_readonly(*emitter, true),
+            AccountMeta::new(addresses.sequence, false),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(addresses.fee_collector, false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data,
+    }
+}
+
+/// Nonce, payload and consistency level of `post_message` instruction data, for bridge
+/// mocks and indexers
+pub fn decode_post_message(data: &[u8]) -> Option<(u32, Vec<u8>, u8)> {
+    match data.split_first()? {
+        (&POST_MESSAGE_TAG, mut args) => borsh::BorshDeserialize::deserialize(&mut args).ok(),
+        _ => None,
+    }
+}
+
+/// Payload of the Wormhole message mirroring `send`
+pub fn encode_send_payload(send: &MirroredSend) -> Vec<u8> {
+    let subject = send.subject.as_bytes();
+    let mut payload = Vec::with_capacity(1 + 32 + 32 + 1 + 2 + subject.len());
+    payload.push(PAYLOAD_VERSION);
+    payload.extend_from_slice(send.sender.as_ref());
+    payload.extend_from_slice(send.recipient.as_ref());
+    payload.push(send.priority as u8);
+    payload.extend_from_slice(&(subject.len() as u16).to_be_bytes());
+    payload.extend_from_slice(subject);
+    payload
+}
+
+/// Send mirrored by a Wormhole message payload
+pub fn decode_send_payload(payload: &[u8]) -> Option<MirroredSend> {
+    let (&version, rest) = payload.split_first()?;
+    if version != PAYLOAD_VERSION || rest.len() < 67 {
+        return None;
+    }
+    let subject_len = u16::from_be_bytes([rest[65], rest[66]]) as usize;
+    let subject = rest.get(67..)?;
+    if subject.len() != subject_len {
+        return None;
+    }
+    Some(MirroredSend {
+        sender: Pubkey::try_from(&rest[..32]).ok()?,
+        recipient: Pubkey::try_from(&rest[32..64]).ok()?,
+        priority: match rest[64] {
+            0 => false,
+            1 => true,
+            _ => return None,
+        },
+        subject: String::from_utf8(subject.to_vec()).ok()?,
+    })
+}
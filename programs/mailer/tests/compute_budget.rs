@@ -0,0 +1,211 @@
+//! Compute budget of the send hot path
+//!
+//! A program running as a native builtin under solana-program-test is not metered by the
+//! runtime, which then only charges the token and system program CPIs it makes. The PDA
+//! derivations the mailer makes are counted by [`mailer::pda::derivations`] instead and
+//! charged at [`mailer::pda::DERIVATION_UNITS`] each, so the measured figure covers the
+//! lookups that dominate a send. Run with `SBF_OUT_DIR=target/deploy` after
+//! `cargo build-sbf` to measure the on-chain program, which the runtime meters in full.
+//!
+//! The counter is process-wide, so this file holds a single test and runs in its own
+//! process.
+
+use mailer::{pda, MailerInstruction};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    system_program,
+};
+use solana_program_test::*;
+use solana_sdk::{
+    signature::{Keypair, Signer},
+    system_instruction,
+    transaction::Transaction,
+};
+use spl_token::state::{Account as TokenAccount, Mint};
+use std::str::FromStr;
+
+const PROGRAM_ID_STR: &str = "9FLkBDGpZBcR8LMsQ7MwwV6X9P4TDFgN3DeRh5qYyHJF";
+
+/// Compute units of a priority send to an existing claim, measured before existing
+/// accounts were checked against their stored bumps
+const PRIORITY_SEND_BASELINE: u64 = 33_566;
+
+/// Compute units a priority send to an existing claim may use
+const PRIORITY_SEND_BUDGET: u64 = 25_000;
+
+const _: () = assert!(PRIORITY_SEND_BUDGET < PRIORITY_SEND_BASELINE);
+
+fn program_id() -> Pubkey {
+    Pubkey::from_str(PROGRAM_ID_STR).unwrap()
+}
+
+async fn process(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) {
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend(signers);
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+}
+
+/// Create a token account of `mint` owned by `owner`
+async fn create_token_account(
+    context: &mut ProgramTestContext,
+    mint: &Pubkey,
+    owner: &Pubkey,
+) -> Pubkey {
+    let account = Keypair::new();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let instructions = [
+        system_instruction::create_account(
+            &context.payer.pubkey(),
+            &account.pubkey(),
+            rent.minimum_balance(TokenAccount::LEN),
+            TokenAccount::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_account(
+            &spl_token::id(),
+            &account.pubkey(),
+            mint,
+            owner,
+        )
+        .unwrap(),
+    ];
+    process(context, &instructions, &[&account]).await;
+    account.pubkey()
+}
+
+#[tokio::test]
+async fn test_priority_send_stays_within_compute_budget() {
+    let mut program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    program_test.prefer_bpf(std::env::var("SBF_OUT_DIR").is_ok());
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.pubkey();
+
+    let mint = Keypair::new();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let instructions = [
+        system_instruction::create_account(
+            &payer,
+            &mint.pubkey(),
+            rent.minimum_balance(Mint::LEN),
+            Mint::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_mint(&spl_token::id(), &mint.pubkey(), &payer, None, 6)
+            .unwrap(),
+    ];
+    process(&mut context, &instructions, &[&mint]).await;
+    let usdc_mint = mint.pubkey();
+
+    let (mailer_pda, _) = pda::mailer_state(&program_id());
+    let initialize = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    process(&mut context, &[initialize], &[]).await;
+
+    let sender_usdc = create_token_account(&mut context, &usdc_mint, &payer).await;
+    let mailer_usdc = create_token_account(&mut context, &usdc_mint, &mailer_pda).await;
+    let mint_to = spl_token::instruction::mint_to(
+        &spl_token::id(),
+        &usdc_mint,
+        &sender_usdc,
+        &payer,
+        &[],
+        10_000_000,
+    )
+    .unwrap();
+    process(&mut context, &[mint_to], &[]).await;
+
+    let recipient = Pubkey::new_unique();
+    let accounts = vec![
+        AccountMeta::new(payer, true),
+        AccountMeta::new(pda::recipient_claim(&program_id(), &recipient).0, false),
+        AccountMeta::new(mailer_pda, false),
+        AccountMeta::new(sender_usdc, false),
+        AccountMeta::new(mailer_usdc, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new(payer, true),
+        AccountMeta::new_readonly(pda::inbox_policy(&program_id(), &recipient).0, false),
+        AccountMeta::new(pda::message_nonce(&program_id(), &payer).0, false),
+    ];
+    let send = |subject: &str| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient,
+                subject: subject.to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: true,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: true,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            accounts.clone(),
+        )
+    };
+
+    // The first send creates the claim and message nonce accounts
+    process(&mut context, &[send("First")], &[]).await;
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[send("Second")],
+        Some(&payer),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    let derivations_before = pda::derivations();
+    let result = context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    let derivations = pda::derivations() - derivations_before;
+    assert!(result.result.is_ok(), "send failed: {:?}", result.result);
+
+    let compute_units =
+        result.metadata.unwrap().compute_units_consumed + derivations * pda::DERIVATION_UNITS;
+    println!(
+        "Priority send: {} CU ({} PDA derivations), baseline {} CU, budget {} CU",
+        compute_units, derivations, PRIORITY_SEND_BASELINE, PRIORITY_SEND_BUDGET
+    );
+    assert!(
+        compute_units <= PRIORITY_SEND_BUDGET,
+        "priority send used {} CU, over its budget of {}",
+        compute_units,
+        PRIORITY_SEND_BUDGET
+    );
+}
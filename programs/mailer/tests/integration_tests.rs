@@ -1,5 +1,6 @@
 use borsh::BorshDeserialize;
 use solana_program::{
+    account_info::AccountInfo,
     instruction::{AccountMeta, Instruction},
     program_pack::Pack,
     pubkey::Pubkey,
@@ -17,7 +18,14 @@ use spl_token::{
 use std::str::FromStr;
 
 // Import our program
-use mailer::{Delegation, FeeDiscount, MailerInstruction, MailerState, RecipientClaim};
+use mailer::cpi;
+use mailer::indexer::{self, ClaimKind, MailerActivity, SendTarget, SentMail};
+use mailer::{
+    ClaimHistoryEntry, ClaimRentPayer, ClosureLog, ConversionVault, Delegation, FeeDiscount,
+    FeeMint, MailerInstruction, MailerState, MessageReceipt, PayloadLimits, PayoutConfig,
+    Preauthorization, Promo, RateLimit, RecipientClaim, RecipientInbox, RecipientPrefs, SessionKey,
+    SweepCursor, Webhook,
+};
 
 // Program ID for tests
 const PROGRAM_ID_STR: &str = "9FLkBDGpZBcR8LMsQ7MwwV6X9P4TDFgN3DeRh5qYyHJF";
@@ -34,6 +42,16 @@ async fn create_usdc_mint(
     banks_client: &mut BanksClient,
     payer: &Keypair,
     recent_blockhash: solana_program::hash::Hash,
+) -> Pubkey {
+    create_mint(banks_client, payer, recent_blockhash, 6).await // USDC has 6 decimals
+}
+
+/// Test helper to create a mint with the given decimals
+async fn create_mint(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    recent_blockhash: solana_program::hash::Hash,
+    decimals: u8,
 ) -> Pubkey {
     let mint = Keypair::new();
     let rent = banks_client.get_rent().await.unwrap();
@@ -53,7 +71,7 @@ async fn create_usdc_mint(
                 &mint.pubkey(),
                 &payer.pubkey(),
                 None,
-                6, // USDC has 6 decimals
+                decimals,
             )
             .unwrap(),
         ],
@@ -122,6 +140,62 @@ async fn mint_to(
     banks_client.process_transaction(transaction).await.unwrap();
 }
 
+/// Test helper registering `webhook_id` to the payer, returning its webhook PDA
+async fn register_webhook(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    recent_blockhash: solana_program::hash::Hash,
+    webhook_id: &str,
+) -> Pubkey {
+    let id_hash = solana_program::hash::hash(webhook_id.as_bytes()).to_bytes();
+    let (webhook_pda, _) = mailer::pda::webhook(&program_id(), &id_hash);
+    let mut transaction = Transaction::new_with_payer(
+        &[Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::RegisterWebhook {
+                webhook_id: webhook_id.to_string(),
+                endpoint_hash: [0u8; 32],
+                surcharge: 0,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(get_mailer_pda().0, false),
+                AccountMeta::new(webhook_pda, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    webhook_pda
+}
+
+/// Test helper to create the closure log, signed by the mailer owner
+async fn initialize_closure_log(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    recent_blockhash: solana_program::hash::Hash,
+) -> Pubkey {
+    let (closure_log_pda, _) = mailer::pda::closure_log(&program_id());
+    let mut transaction = Transaction::new_with_payer(
+        &[Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::InitializeClosureLog,
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(get_mailer_pda().0, false),
+                AccountMeta::new(closure_log_pda, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    closure_log_pda
+}
+
 /// Test helper to get mailer state PDA
 fn get_mailer_pda() -> (Pubkey, u8) {
     Pubkey::find_program_address(&[b"mailer"], &program_id())
@@ -129,17 +203,55 @@ fn get_mailer_pda() -> (Pubkey, u8) {
 
 /// Test helper to get recipient claim PDA
 fn get_claim_pda(recipient: &Pubkey) -> (Pubkey, u8) {
-    Pubkey::find_program_address(&[b"claim", &[PDA_VERSION], recipient.as_ref()], &program_id())
+    Pubkey::find_program_address(
+        &[b"claim", &[PDA_VERSION], recipient.as_ref()],
+        &program_id(),
+    )
 }
 
 /// Test helper to get delegation PDA
 fn get_delegation_pda(delegator: &Pubkey) -> (Pubkey, u8) {
-    Pubkey::find_program_address(&[b"delegation", &[PDA_VERSION], delegator.as_ref()], &program_id())
+    Pubkey::find_program_address(
+        &[b"delegation", &[PDA_VERSION], delegator.as_ref()],
+        &program_id(),
+    )
+}
+
+/// Test helper to get delegate index PDA
+fn get_delegate_index_pda(delegate: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"delegate_index", &[PDA_VERSION], delegate.as_ref()],
+        &program_id(),
+    )
 }
 
 /// Test helper to get fee discount PDA
 fn get_fee_discount_pda(account: &Pubkey) -> (Pubkey, u8) {
-    Pubkey::find_program_address(&[b"discount", &[PDA_VERSION], account.as_ref()], &program_id())
+    Pubkey::find_program_address(
+        &[b"discount", &[PDA_VERSION], account.as_ref()],
+        &program_id(),
+    )
+}
+
+/// Test helper to get admin set PDA
+fn get_admins_pda() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"admins", &[PDA_VERSION]], &program_id())
+}
+
+/// Test helper to get recipient preferences PDA
+fn get_prefs_pda(recipient: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"prefs", &[PDA_VERSION], recipient.as_ref()],
+        &program_id(),
+    )
+}
+
+/// Test helper to get claim beneficiary PDA
+fn get_beneficiary_pda(recipient: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"beneficiary", &[PDA_VERSION], recipient.as_ref()],
+        &program_id(),
+    )
 }
 
 #[tokio::test]
@@ -251,6 +363,16 @@ async fn test_send_priority_message() {
         _body: "Test message body".to_string(),
         revenue_share_to_receiver: true,
         resolve_sender_to_name: false,
+        request_read_receipt: false,
+        receipt_nonce: None,
+        memo: None,
+        strict: false,
+        thread_id: None,
+        in_reply_to: None,
+        allow_downgrade: false,
+        tip: 0,
+        bridge: false,
+        idempotency_nonce: None,
     };
 
     let instruction = Instruction::new_with_borsh(
@@ -264,6 +386,9 @@ async fn test_send_priority_message() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&payer.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -294,6 +419,182 @@ async fn test_send_priority_message() {
     assert_eq!(mailer_state.owner_claimable, 10_000); // 10% of send_fee
 }
 
+#[tokio::test]
+async fn test_send_priority_with_transfer_fee_mint_credits_amount_received() {
+    use spl_token_2022::{
+        extension::{transfer_fee::instruction::initialize_transfer_fee_config, ExtensionType},
+        state::{Account as Token2022Account, Mint as Token2022Mint},
+    };
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+    let rent = banks_client.get_rent().await.unwrap();
+
+    // Token-2022 mint withholding 1% of every transfer
+    let mint = Keypair::new();
+    let mint_len = ExtensionType::try_calculate_account_len::<Token2022Mint>(&[
+        ExtensionType::TransferFeeConfig,
+    ])
+    .unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            solana_sdk::system_instruction::create_account(
+                &payer.pubkey(),
+                &mint.pubkey(),
+                rent.minimum_balance(mint_len),
+                mint_len as u64,
+                &spl_token_2022::id(),
+            ),
+            initialize_transfer_fee_config(
+                &spl_token_2022::id(),
+                &mint.pubkey(),
+                None,
+                None,
+                100,
+                1_000_000,
+            )
+            .unwrap(),
+            spl_token_2022::instruction::initialize_mint(
+                &spl_token_2022::id(),
+                &mint.pubkey(),
+                &payer.pubkey(),
+                None,
+                6,
+            )
+            .unwrap(),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &mint], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    let usdc_mint = mint.pubkey();
+
+    let (mailer_pda, _) = get_mailer_pda();
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(usdc_mint, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Token accounts of a transfer-fee mint carry the withheld amount extension
+    let account_len = ExtensionType::try_calculate_account_len::<Token2022Account>(&[
+        ExtensionType::TransferFeeAmount,
+    ])
+    .unwrap();
+    let sender_usdc = Keypair::new();
+    let mailer_usdc = Keypair::new();
+    let mut instructions = Vec::new();
+    for (account, owner) in [(&sender_usdc, payer.pubkey()), (&mailer_usdc, mailer_pda)] {
+        instructions.push(solana_sdk::system_instruction::create_account(
+            &payer.pubkey(),
+            &account.pubkey(),
+            rent.minimum_balance(account_len),
+            account_len as u64,
+            &spl_token_2022::id(),
+        ));
+        instructions.push(
+            spl_token_2022::instruction::initialize_account3(
+                &spl_token_2022::id(),
+                &account.pubkey(),
+                &usdc_mint,
+                &owner,
+            )
+            .unwrap(),
+        );
+    }
+    instructions.push(
+        spl_token_2022::instruction::mint_to(
+            &spl_token_2022::id(),
+            &usdc_mint,
+            &sender_usdc.pubkey(),
+            &payer.pubkey(),
+            &[],
+            1_000_000,
+        )
+        .unwrap(),
+    );
+    let mut transaction = Transaction::new_with_payer(&instructions, Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &sender_usdc, &mailer_usdc], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let recipient = Pubkey::new_unique();
+    let (recipient_claim_pda, _) = get_claim_pda(&recipient);
+    let instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Send {
+            to: recipient,
+            subject: "Test Subject".to_string(),
+            _body: "Test message body".to_string(),
+            revenue_share_to_receiver: true,
+            resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: true,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(recipient_claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc.pubkey(), false),
+            AccountMeta::new(mailer_usdc.pubkey(), false),
+            AccountMeta::new_readonly(spl_token_2022::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            AccountMeta::new_readonly(usdc_mint, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert!(result.result.is_ok(), "send failed: {:?}", result.result);
+    let logs = result.metadata.unwrap().log_messages;
+    assert!(
+        logs.iter().any(|log| log
+            == "Program log: Transfer skim detected: sent 100000, received 99000, skimmed 1000"),
+        "missing skim log: {:?}",
+        logs
+    );
+
+    // Shares split the 99,000 that arrived, not the 100,000 fee
+    let claim_account = banks_client
+        .get_account(recipient_claim_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let recipient_claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    assert_eq!(recipient_claim.amount, 89_100);
+
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.owner_claimable, 9_900);
+}
+
 #[tokio::test]
 async fn test_send_standard_message() {
     let program_test = ProgramTest::new(
@@ -361,6 +662,16 @@ async fn test_send_standard_message() {
         _body: "Standard body".to_string(),
         revenue_share_to_receiver: false,
         resolve_sender_to_name: false,
+        request_read_receipt: false,
+        receipt_nonce: None,
+        memo: None,
+        strict: false,
+        thread_id: None,
+        in_reply_to: None,
+        allow_downgrade: false,
+        tip: 0,
+        bridge: false,
+        idempotency_nonce: None,
     };
 
     let instruction = Instruction::new_with_borsh(
@@ -374,6 +685,9 @@ async fn test_send_standard_message() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient_keypair.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -447,11 +761,18 @@ async fn test_send_through_webhook_priority() {
     let (recipient_claim_pda, _) = get_claim_pda(&payer.pubkey());
 
     // Send webhook message with revenue sharing
+    let webhook_pda =
+        register_webhook(&mut banks_client, &payer, recent_blockhash, "webhook-123").await;
     let instruction_data = MailerInstruction::SendThroughWebhook {
         to: payer.pubkey(),
         webhook_id: "webhook-123".to_string(),
         revenue_share_to_receiver: true,
         resolve_sender_to_name: false,
+        strict: false,
+        thread_id: None,
+        in_reply_to: None,
+        allow_downgrade: false,
+        tip: 0,
     };
 
     let instruction = Instruction::new_with_borsh(
@@ -465,6 +786,10 @@ async fn test_send_through_webhook_priority() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(webhook_pda, false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&payer.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -543,11 +868,18 @@ async fn test_send_through_webhook_standard() {
     let (recipient_claim_pda, _) = get_claim_pda(&recipient_keypair.pubkey());
 
     // Send webhook message without revenue sharing
+    let webhook_pda =
+        register_webhook(&mut banks_client, &payer, recent_blockhash, "webhook-456").await;
     let instruction_data = MailerInstruction::SendThroughWebhook {
         to: recipient_keypair.pubkey(),
         webhook_id: "webhook-456".to_string(),
         revenue_share_to_receiver: false,
         resolve_sender_to_name: false,
+        strict: false,
+        thread_id: None,
+        in_reply_to: None,
+        allow_downgrade: false,
+        tip: 0,
     };
 
     let instruction = Instruction::new_with_borsh(
@@ -561,6 +893,10 @@ async fn test_send_through_webhook_standard() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(webhook_pda, false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient_keypair.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -653,6 +989,16 @@ async fn test_claim_recipient_share() {
             _body: "Body".to_string(),
             revenue_share_to_receiver: true,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -662,6 +1008,9 @@ async fn test_claim_recipient_share() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -672,7 +1021,11 @@ async fn test_claim_recipient_share() {
     // Claim recipient share
     let claim_instruction = Instruction::new_with_borsh(
         program_id(),
-        &MailerInstruction::ClaimRecipientShare,
+        &MailerInstruction::ClaimRecipientShare {
+            amount: None,
+            split: None,
+            close_to: None,
+        },
         vec![
             AccountMeta::new(recipient.pubkey(), true),
             AccountMeta::new(recipient_claim_pda, false),
@@ -693,8 +1046,7 @@ async fn test_claim_recipient_share() {
         .await
         .unwrap()
         .unwrap();
-    let recipient_token_data =
-        TokenAccount::unpack(&recipient_token_account.data[..]).unwrap();
+    let recipient_token_data = TokenAccount::unpack(&recipient_token_account.data[..]).unwrap();
 
     assert_eq!(recipient_token_data.amount, 90_000);
 
@@ -704,7 +1056,8 @@ async fn test_claim_recipient_share() {
         .await
         .unwrap()
         .unwrap();
-    let claim: RecipientClaim = BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    let claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
 
     assert_eq!(claim.amount, 0);
 }
@@ -784,6 +1137,16 @@ async fn test_claim_owner_share() {
             _body: "Body".to_string(),
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -793,6 +1156,9 @@ async fn test_claim_owner_share() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -864,6 +1230,7 @@ async fn test_set_fees() {
         program_id(),
         &MailerInstruction::SetFee {
             new_fee: new_send_fee,
+            expected_current_value: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -871,7 +1238,8 @@ async fn test_set_fees() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
@@ -888,6 +1256,7 @@ async fn test_set_fees() {
         program_id(),
         &MailerInstruction::SetDelegationFee {
             new_fee: new_delegation_fee,
+            expected_current_value: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -908,6 +1277,98 @@ async fn test_set_fees() {
     assert_eq!(mailer_state.delegation_fee, new_delegation_fee);
 }
 
+#[tokio::test]
+async fn test_fee_setters_reject_stale_expected_values() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let owner_setter = |instruction: MailerInstruction| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &instruction,
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(mailer_pda, false),
+            ],
+        )
+    };
+
+    // Another tool already moved the send fee away from the value this one read
+    let stale_fee = owner_setter(MailerInstruction::SetFee {
+        new_fee: 200_000,
+        expected_current_value: Some(50_000),
+    });
+    let mut transaction = Transaction::new_with_payer(&[stale_fee], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = banks_client.process_transaction(transaction).await;
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::StaleState as u32)
+        )
+    );
+
+    let stale_delegation_fee = owner_setter(MailerInstruction::SetDelegationFee {
+        new_fee: 20_000_000,
+        expected_current_value: Some(1),
+    });
+    let mut transaction =
+        Transaction::new_with_payer(&[stale_delegation_fee], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = banks_client.process_transaction(transaction).await;
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::StaleState as u32)
+        )
+    );
+
+    // Matching expectations apply the change
+    let fresh_fees = [
+        owner_setter(MailerInstruction::SetFee {
+            new_fee: 200_000,
+            expected_current_value: Some(100_000),
+        }),
+        owner_setter(MailerInstruction::SetDelegationFee {
+            new_fee: 20_000_000,
+            expected_current_value: Some(10_000_000),
+        }),
+    ];
+    let mut transaction = Transaction::new_with_payer(&fresh_fees, Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.send_fee, 200_000);
+    assert_eq!(mailer_state.delegation_fee, 20_000_000);
+}
+
 #[tokio::test]
 async fn test_delegation_functionality() {
     let program_test = ProgramTest::new(
@@ -980,10 +1441,12 @@ async fn test_delegation_functionality() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(get_delegate_index_pda(&delegate.pubkey()).0, false),
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[delegate_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[delegate_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
@@ -1151,6 +1614,16 @@ async fn test_claim_expired_shares_moves_funds_to_owner() {
             _body: "Body".to_string(),
             revenue_share_to_receiver: true,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(context.payer.pubkey(), true),
@@ -1160,6 +1633,9 @@ async fn test_claim_expired_shares_moves_funds_to_owner() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(context.payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&context.payer.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&context.payer.pubkey()).0, false),
         ],
     );
 
@@ -1191,6 +1667,7 @@ async fn test_claim_expired_shares_moves_funds_to_owner() {
             AccountMeta::new(context.payer.pubkey(), true),
             AccountMeta::new(mailer_pda, false),
             AccountMeta::new(recipient_claim_pda, false),
+            AccountMeta::new_readonly(get_beneficiary_pda(&context.payer.pubkey()).0, false),
         ],
     );
 
@@ -1294,6 +1771,11 @@ async fn test_send_prepared_priority() {
         mail_id: "mail-123".to_string(),
         revenue_share_to_receiver: true,
         resolve_sender_to_name: false,
+        strict: false,
+        thread_id: None,
+        in_reply_to: None,
+        allow_downgrade: false,
+        tip: 0,
     };
 
     let instruction = Instruction::new_with_borsh(
@@ -1307,6 +1789,9 @@ async fn test_send_prepared_priority() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&payer.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -1396,6 +1881,11 @@ async fn test_send_prepared_standard() {
         mail_id: "mail-456".to_string(),
         revenue_share_to_receiver: false,
         resolve_sender_to_name: false,
+        strict: false,
+        thread_id: None,
+        in_reply_to: None,
+        allow_downgrade: false,
+        tip: 0,
     };
 
     let instruction = Instruction::new_with_borsh(
@@ -1409,6 +1899,9 @@ async fn test_send_prepared_standard() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient_keypair.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -1484,6 +1977,7 @@ async fn test_send_to_email() {
         to_email: "test@example.com".to_string(),
         subject: "Test Subject".to_string(),
         _body: "Test body".to_string(),
+        strict: false,
     };
 
     let instruction = Instruction::new_with_borsh(
@@ -1495,6 +1989,8 @@ async fn test_send_to_email() {
             AccountMeta::new(sender_usdc, false),
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
         ],
     );
 
@@ -1569,6 +2065,7 @@ async fn test_send_prepared_to_email() {
     let instruction_data = MailerInstruction::SendPreparedToEmail {
         to_email: "test@example.com".to_string(),
         mail_id: "email-mail-789".to_string(),
+        strict: false,
     };
 
     let instruction = Instruction::new_with_borsh(
@@ -1580,6 +2077,8 @@ async fn test_send_prepared_to_email() {
             AccountMeta::new(sender_usdc, false),
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
         ],
     );
 
@@ -1674,7 +2173,8 @@ async fn test_pause_functionality() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[unpause_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[unpause_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
@@ -1714,6 +2214,13 @@ async fn test_custom_fee_percentage() {
     banks_client.process_transaction(transaction).await.unwrap();
 
     let test_user = Keypair::new();
+
+    // The sender pays for its message nonce account
+    let fund_user =
+        solana_sdk::system_instruction::transfer(&payer.pubkey(), &test_user.pubkey(), 10_000_000);
+    let mut transaction = Transaction::new_with_payer(&[fund_user], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
     let (fee_discount_pda, _) = get_fee_discount_pda(&test_user.pubkey());
 
     // Set custom fee percentage (50% = pay 50% of normal fee)
@@ -1725,7 +2232,7 @@ async fn test_custom_fee_percentage() {
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
-            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(mailer_pda, false),
             AccountMeta::new(fee_discount_pda, false),
             AccountMeta::new_readonly(test_user.pubkey(), false),
             AccountMeta::new(payer.pubkey(), true), // payer for account creation
@@ -1739,10 +2246,7 @@ async fn test_custom_fee_percentage() {
     banks_client.process_transaction(transaction).await.unwrap();
 
     // Verify fee discount account was created
-    let fee_discount_account = banks_client
-        .get_account(fee_discount_pda)
-        .await
-        .unwrap();
+    let fee_discount_account = banks_client.get_account(fee_discount_pda).await.unwrap();
 
     assert!(fee_discount_account.is_some());
 
@@ -1786,6 +2290,16 @@ async fn test_custom_fee_percentage() {
             _body: "Body".to_string(),
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(test_user.pubkey(), true),
@@ -1795,8 +2309,11 @@ async fn test_custom_fee_percentage() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(test_user.pubkey(), true),
             // Include fee discount PDA for custom fee calculation
             AccountMeta::new_readonly(fee_discount_pda, false),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&test_user.pubkey()).0, false),
         ],
     );
 
@@ -1893,6 +2410,16 @@ async fn test_fee_paused() {
             _body: "No fee".to_string(),
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -1902,6 +2429,9 @@ async fn test_fee_paused() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -1949,10 +2479,32 @@ async fn test_send_with_empty_strings() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
-    
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 1_000_000).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
 
     let recipient = Keypair::new();
     let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
@@ -1966,6 +2518,16 @@ async fn test_send_with_empty_strings() {
             _body: "".to_string(),
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -1975,6 +2537,9 @@ async fn test_send_with_empty_strings() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -1984,7 +2549,8 @@ async fn test_send_with_empty_strings() {
 
     // Verify transaction succeeded
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.owner_claimable, 10_000);
 }
 
@@ -2014,11 +2580,33 @@ async fn test_send_with_long_strings() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
-    
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 1_000_000).await;
-
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
     let recipient = Keypair::new();
     let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
 
@@ -2034,6 +2622,16 @@ async fn test_send_with_long_strings() {
             _body: long_body,
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -2043,6 +2641,9 @@ async fn test_send_with_long_strings() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -2077,10 +2678,32 @@ async fn test_send_prepared_with_special_characters() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
-    
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 1_000_000).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
 
     let recipient = Keypair::new();
     let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
@@ -2095,6 +2718,11 @@ async fn test_send_prepared_with_special_characters() {
             mail_id,
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -2104,6 +2732,9 @@ async fn test_send_prepared_with_special_characters() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -2138,10 +2769,32 @@ async fn test_send_to_email_with_various_formats() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
-    
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 10_000_000).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
 
     // Test various email formats
     let emails = vec![
@@ -2158,6 +2811,7 @@ async fn test_send_to_email_with_various_formats() {
                 to_email: email.to_string(),
                 subject: "Test".to_string(),
                 _body: "Body".to_string(),
+                strict: false,
             },
             vec![
                 AccountMeta::new(payer.pubkey(), true),
@@ -2165,15 +2819,151 @@ async fn test_send_to_email_with_various_formats() {
                 AccountMeta::new(sender_usdc, false),
                 AccountMeta::new(mailer_usdc, false),
                 AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+                AccountMeta::new_readonly(system_program::id(), false),
             ],
         );
 
-        let mut transaction = Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
+        let mut transaction =
+            Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
         transaction.sign(&[&payer], recent_blockhash);
         banks_client.process_transaction(transaction).await.unwrap();
     }
 }
 
+#[tokio::test]
+async fn test_send_to_email_rejects_malformed_addresses_and_lowercases_valid_ones() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let send = |email: &str| {
+        let instruction = Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SendToEmail {
+                to_email: email.to_string(),
+                subject: "Test".to_string(),
+                _body: "Body".to_string(),
+                strict: false,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer], recent_blockhash);
+        transaction
+    };
+
+    let too_long = format!("{}@example.com", "a".repeat(250));
+    let malformed = [
+        "",
+        "a@b.c",
+        "no-at-sign.com",
+        "two@at@example.com",
+        "@example.com",
+        "user@localhost",
+        "user@.example.com",
+        "user@example.com.",
+        "user name@example.com",
+        "usér@example.com",
+        &too_long,
+    ];
+    for email in malformed {
+        assert!(matches!(
+            mailer::normalize_email(email),
+            Err(mailer::MailerError::InvalidEmailAddress)
+        ));
+        assert_eq!(
+            banks_client
+                .process_transaction(send(email))
+                .await
+                .unwrap_err()
+                .unwrap(),
+            TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(mailer::MailerError::InvalidEmailAddress as u32)
+            ),
+            "{email}"
+        );
+    }
+
+    // Nothing is charged for rejected addresses; valid ones are logged lowercased
+    let sender_account = banks_client
+        .get_account(sender_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&sender_account.data).unwrap().amount,
+        1_000_000
+    );
+
+    let transaction = send("User.Name+Tag@Example.COM");
+    let simulation = banks_client
+        .simulate_transaction(transaction.clone())
+        .await
+        .unwrap();
+    let logs = simulation.simulation_details.unwrap().logs;
+    assert!(logs
+        .iter()
+        .any(|log| log.contains("to email user.name+tag@example.com: Test")));
+    banks_client.process_transaction(transaction).await.unwrap();
+}
+
 // ============================================================================
 // Comprehensive Pause State Tests
 // ============================================================================
@@ -2204,11 +2994,40 @@ async fn test_send_fails_when_paused() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
-    let owner_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    let owner_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 1_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
 
     // Pause contract
     let pause_instruction = Instruction::new_with_borsh(
@@ -2239,6 +3058,16 @@ async fn test_send_fails_when_paused() {
             _body: "Body".to_string(),
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -2248,12 +3077,15 @@ async fn test_send_fails_when_paused() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
     let mut transaction = Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
-    
+
     let result = banks_client.process_transaction(transaction).await;
     assert!(result.is_err());
 }
@@ -2284,9 +3116,30 @@ async fn test_send_prepared_fails_when_paused() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
-    let owner_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    let owner_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
 
     // Pause contract
     let pause_instruction = Instruction::new_with_borsh(
@@ -2316,6 +3169,11 @@ async fn test_send_prepared_fails_when_paused() {
             mail_id: "test-123".to_string(),
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -2325,12 +3183,15 @@ async fn test_send_prepared_fails_when_paused() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
     let mut transaction = Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
-    
+
     let result = banks_client.process_transaction(transaction).await;
     assert!(result.is_err());
 }
@@ -2361,9 +3222,30 @@ async fn test_send_to_email_fails_when_paused() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
-    let owner_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    let owner_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
 
     // Pause contract
     let pause_instruction = Instruction::new_with_borsh(
@@ -2389,6 +3271,7 @@ async fn test_send_to_email_fails_when_paused() {
             to_email: "test@example.com".to_string(),
             subject: "Test".to_string(),
             _body: "Body".to_string(),
+            strict: false,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -2396,12 +3279,14 @@ async fn test_send_to_email_fails_when_paused() {
             AccountMeta::new(sender_usdc, false),
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
         ],
     );
 
     let mut transaction = Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
-    
+
     let result = banks_client.process_transaction(transaction).await;
     assert!(result.is_err());
 }
@@ -2432,11 +3317,32 @@ async fn test_delegation_fails_when_paused() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let delegator_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
-    let owner_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-
-    // Pause contract
+    let delegator_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    let owner_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+
+    // Pause contract
     let pause_instruction = Instruction::new_with_borsh(
         program_id(),
         &MailerInstruction::Pause,
@@ -2470,17 +3376,19 @@ async fn test_delegation_fails_when_paused() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(get_delegate_index_pda(&delegate.pubkey()).0, false),
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[delegate_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[delegate_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
-    
+
     let result = banks_client.process_transaction(transaction).await;
     assert!(result.is_err());
 }
 
-// ============================================================================  
+// ============================================================================
 // More Custom Fee Percentage Tests
 // ============================================================================
 
@@ -2511,6 +3419,13 @@ async fn test_custom_fee_0_percent() {
     banks_client.process_transaction(transaction).await.unwrap();
 
     let test_user = Keypair::new();
+
+    // The sender pays for its message nonce account
+    let fund_user =
+        solana_sdk::system_instruction::transfer(&payer.pubkey(), &test_user.pubkey(), 10_000_000);
+    let mut transaction = Transaction::new_with_payer(&[fund_user], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
     let (fee_discount_pda, _) = get_fee_discount_pda(&test_user.pubkey());
 
     // Set 0% fee (free)
@@ -2522,7 +3437,7 @@ async fn test_custom_fee_0_percent() {
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
-            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(mailer_pda, false),
             AccountMeta::new(fee_discount_pda, false),
             AccountMeta::new_readonly(test_user.pubkey(), false),
             AccountMeta::new(payer.pubkey(), true),
@@ -2530,12 +3445,27 @@ async fn test_custom_fee_0_percent() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_custom_fee_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_custom_fee_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let test_user_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &test_user.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let test_user_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &test_user.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     // Don't mint any USDC - if fees were charged, this would fail
     let recipient = Keypair::new();
@@ -2549,6 +3479,16 @@ async fn test_custom_fee_0_percent() {
             _body: "No fee".to_string(),
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(test_user.pubkey(), true),
@@ -2558,7 +3498,10 @@ async fn test_custom_fee_0_percent() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(test_user.pubkey(), true),
             AccountMeta::new_readonly(fee_discount_pda, false),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&test_user.pubkey()).0, false),
         ],
     );
 
@@ -2568,7 +3511,8 @@ async fn test_custom_fee_0_percent() {
 
     // Verify no fees collected
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.owner_claimable, 0);
 }
 
@@ -2599,6 +3543,13 @@ async fn test_custom_fee_25_percent() {
     banks_client.process_transaction(transaction).await.unwrap();
 
     let test_user = Keypair::new();
+
+    // The sender pays for its message nonce account
+    let fund_user =
+        solana_sdk::system_instruction::transfer(&payer.pubkey(), &test_user.pubkey(), 10_000_000);
+    let mut transaction = Transaction::new_with_payer(&[fund_user], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
     let (fee_discount_pda, _) = get_fee_discount_pda(&test_user.pubkey());
 
     let set_custom_fee_instruction = Instruction::new_with_borsh(
@@ -2609,7 +3560,7 @@ async fn test_custom_fee_25_percent() {
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
-            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(mailer_pda, false),
             AccountMeta::new(fee_discount_pda, false),
             AccountMeta::new_readonly(test_user.pubkey(), false),
             AccountMeta::new(payer.pubkey(), true),
@@ -2617,14 +3568,37 @@ async fn test_custom_fee_25_percent() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_custom_fee_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_custom_fee_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let test_user_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &test_user.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let test_user_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &test_user.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &test_user_usdc, 1_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &test_user_usdc,
+        1_000_000,
+    )
+    .await;
 
     let recipient = Keypair::new();
     let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
@@ -2636,6 +3610,11 @@ async fn test_custom_fee_25_percent() {
             mail_id: "test-25".to_string(),
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
         },
         vec![
             AccountMeta::new(test_user.pubkey(), true),
@@ -2645,7 +3624,10 @@ async fn test_custom_fee_25_percent() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(test_user.pubkey(), true),
             AccountMeta::new_readonly(fee_discount_pda, false),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&test_user.pubkey()).0, false),
         ],
     );
 
@@ -2655,7 +3637,8 @@ async fn test_custom_fee_25_percent() {
 
     // 25% of 100,000 = 25,000, then 10% of that = 2,500
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.owner_claimable, 2_500);
 }
 
@@ -2686,6 +3669,13 @@ async fn test_custom_fee_100_percent() {
     banks_client.process_transaction(transaction).await.unwrap();
 
     let test_user = Keypair::new();
+
+    // The sender pays for its message nonce account
+    let fund_user =
+        solana_sdk::system_instruction::transfer(&payer.pubkey(), &test_user.pubkey(), 10_000_000);
+    let mut transaction = Transaction::new_with_payer(&[fund_user], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
     let (fee_discount_pda, _) = get_fee_discount_pda(&test_user.pubkey());
 
     let set_custom_fee_instruction = Instruction::new_with_borsh(
@@ -2696,7 +3686,7 @@ async fn test_custom_fee_100_percent() {
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
-            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(mailer_pda, false),
             AccountMeta::new(fee_discount_pda, false),
             AccountMeta::new_readonly(test_user.pubkey(), false),
             AccountMeta::new(payer.pubkey(), true),
@@ -2704,14 +3694,37 @@ async fn test_custom_fee_100_percent() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_custom_fee_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_custom_fee_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let test_user_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &test_user.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let test_user_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &test_user.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &test_user_usdc, 1_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &test_user_usdc,
+        1_000_000,
+    )
+    .await;
 
     let recipient = Keypair::new();
     let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
@@ -2724,6 +3737,16 @@ async fn test_custom_fee_100_percent() {
             _body: "Body".to_string(),
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(test_user.pubkey(), true),
@@ -2733,7 +3756,10 @@ async fn test_custom_fee_100_percent() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(test_user.pubkey(), true),
             AccountMeta::new_readonly(fee_discount_pda, false),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&test_user.pubkey()).0, false),
         ],
     );
 
@@ -2743,7 +3769,8 @@ async fn test_custom_fee_100_percent() {
 
     // 100% of 100,000 = 100,000, then 10% of that = 10,000 (same as normal)
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.owner_claimable, 10_000);
 }
 
@@ -2777,10 +3804,32 @@ async fn test_clear_delegation() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let delegator_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let delegator_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &delegator_usdc, 100_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &delegator_usdc,
+        100_000_000,
+    )
+    .await;
 
     let delegate = Keypair::new();
     let (delegation_pda, _) = get_delegation_pda(&payer.pubkey());
@@ -2799,19 +3848,19 @@ async fn test_clear_delegation() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(get_delegate_index_pda(&delegate.pubkey()).0, false),
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[delegate_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[delegate_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
     // Clear delegation (set to None) - should not charge fee
     let clear_instruction = Instruction::new_with_borsh(
         program_id(),
-        &MailerInstruction::DelegateTo {
-            delegate: None,
-        },
+        &MailerInstruction::DelegateTo { delegate: None },
         vec![
             AccountMeta::new(payer.pubkey(), true),
             AccountMeta::new(delegation_pda, false),
@@ -2820,6 +3869,7 @@ async fn test_clear_delegation() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(get_delegate_index_pda(&delegate.pubkey()).0, false),
         ],
     );
 
@@ -2828,13 +3878,19 @@ async fn test_clear_delegation() {
     banks_client.process_transaction(transaction).await.unwrap();
 
     // Verify delegation cleared
-    let delegation_account = banks_client.get_account(delegation_pda).await.unwrap().unwrap();
-    let delegation: Delegation = BorshDeserialize::deserialize(&mut &delegation_account.data[8..]).unwrap();
+    let delegation_account = banks_client
+        .get_account(delegation_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let delegation: Delegation =
+        BorshDeserialize::deserialize(&mut &delegation_account.data[8..]).unwrap();
     assert_eq!(delegation.delegate, None);
 
     // Verify only one delegation fee was charged (not for clearing)
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.owner_claimable, 10_000_000);
 }
 
@@ -2864,10 +3920,32 @@ async fn test_reject_delegation() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let delegator_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let delegator_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &delegator_usdc, 100_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &delegator_usdc,
+        100_000_000,
+    )
+    .await;
 
     let delegate = Keypair::new();
     let (delegation_pda, _) = get_delegation_pda(&payer.pubkey());
@@ -2886,10 +3964,12 @@ async fn test_reject_delegation() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(get_delegate_index_pda(&delegate.pubkey()).0, false),
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[delegate_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[delegate_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
@@ -2901,6 +3981,7 @@ async fn test_reject_delegation() {
             AccountMeta::new(delegate.pubkey(), true),
             AccountMeta::new(delegation_pda, false),
             AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(get_delegate_index_pda(&delegate.pubkey()).0, false),
         ],
     );
 
@@ -2909,8 +3990,13 @@ async fn test_reject_delegation() {
     banks_client.process_transaction(transaction).await.unwrap();
 
     // Verify delegation was cleared
-    let delegation_account = banks_client.get_account(delegation_pda).await.unwrap().unwrap();
-    let delegation: Delegation = BorshDeserialize::deserialize(&mut &delegation_account.data[8..]).unwrap();
+    let delegation_account = banks_client
+        .get_account(delegation_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let delegation: Delegation =
+        BorshDeserialize::deserialize(&mut &delegation_account.data[8..]).unwrap();
     assert_eq!(delegation.delegate, None);
 }
 
@@ -2944,10 +4030,32 @@ async fn test_multiple_messages_accumulate_shares() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 10_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
 
     let recipient = Keypair::new();
     let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
@@ -2965,6 +4073,16 @@ async fn test_multiple_messages_accumulate_shares() {
                 _body: "Body".to_string(),
                 revenue_share_to_receiver: true,
                 resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
             },
             vec![
                 AccountMeta::new(payer.pubkey(), true),
@@ -2974,22 +4092,32 @@ async fn test_multiple_messages_accumulate_shares() {
                 AccountMeta::new(mailer_usdc, false),
                 AccountMeta::new_readonly(spl_token::id(), false),
                 AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
             ],
         );
 
-        let mut transaction = Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
+        let mut transaction =
+            Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
         transaction.sign(&[&payer], recent_blockhash);
         banks_client.process_transaction(transaction).await.unwrap();
     }
 
     // Verify accumulated shares (3 * 90,000 = 270,000)
-    let claim_account = banks_client.get_account(recipient_claim_pda).await.unwrap().unwrap();
-    let recipient_claim: RecipientClaim = BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    let claim_account = banks_client
+        .get_account(recipient_claim_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let recipient_claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
     assert_eq!(recipient_claim.amount, 270_000);
 
     // Verify owner claimable (3 * 10,000 = 30,000)
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.owner_claimable, 30_000);
 }
 
@@ -3030,6 +4158,7 @@ async fn test_only_owner_can_update_send_fee() {
         program_id(),
         &MailerInstruction::SetFee {
             new_fee: 200_000,
+            expected_current_value: None,
         },
         vec![
             AccountMeta::new(non_owner.pubkey(), true),
@@ -3037,9 +4166,10 @@ async fn test_only_owner_can_update_send_fee() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer, &non_owner], recent_blockhash);
-    
+
     let result = banks_client.process_transaction(transaction).await;
     assert!(result.is_err());
 }
@@ -3077,6 +4207,7 @@ async fn test_only_owner_can_update_delegation_fee() {
         program_id(),
         &MailerInstruction::SetDelegationFee {
             new_fee: 20_000_000,
+            expected_current_value: None,
         },
         vec![
             AccountMeta::new(non_owner.pubkey(), true),
@@ -3084,9 +4215,10 @@ async fn test_only_owner_can_update_delegation_fee() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_delegation_fee_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_delegation_fee_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer, &non_owner], recent_blockhash);
-    
+
     let result = banks_client.process_transaction(transaction).await;
     assert!(result.is_err());
 }
@@ -3117,15 +4249,37 @@ async fn test_fee_changes_affect_subsequent_sends() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
-
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 10_000_000).await;
-
-    let recipient = Keypair::new();
-    let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    // Send with default fee
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
+
+    let recipient = Keypair::new();
+    let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
+
+    // Send with default fee
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let send_instruction = Instruction::new_with_borsh(
         program_id(),
@@ -3135,6 +4289,16 @@ async fn test_fee_changes_affect_subsequent_sends() {
             _body: "Body".to_string(),
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -3144,6 +4308,9 @@ async fn test_fee_changes_affect_subsequent_sends() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -3152,7 +4319,8 @@ async fn test_fee_changes_affect_subsequent_sends() {
     banks_client.process_transaction(transaction).await.unwrap();
 
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.owner_claimable, 10_000); // 10% of 100,000
 
     // Update fee to 200,000 (0.2 USDC)
@@ -3161,6 +4329,7 @@ async fn test_fee_changes_affect_subsequent_sends() {
         program_id(),
         &MailerInstruction::SetFee {
             new_fee: 200_000,
+            expected_current_value: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -3168,7 +4337,8 @@ async fn test_fee_changes_affect_subsequent_sends() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
@@ -3182,6 +4352,16 @@ async fn test_fee_changes_affect_subsequent_sends() {
             _body: "Body".to_string(),
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -3191,6 +4371,9 @@ async fn test_fee_changes_affect_subsequent_sends() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -3199,7 +4382,8 @@ async fn test_fee_changes_affect_subsequent_sends() {
     banks_client.process_transaction(transaction).await.unwrap();
 
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.owner_claimable, 30_000); // 10,000 + 20,000 (10% of 200,000)
 }
 
@@ -3235,7 +4419,8 @@ async fn test_initialization_sets_usdc_mint_correctly() {
 
     // Verify USDC mint was set correctly
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.usdc_mint, usdc_mint);
 }
 
@@ -3267,7 +4452,8 @@ async fn test_initialization_sets_default_send_fee() {
 
     // Verify default send fee is 100,000 (0.1 USDC)
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.send_fee, 100_000);
 }
 
@@ -3299,7 +4485,8 @@ async fn test_initialization_sets_owner_correctly() {
 
     // Verify owner is set to payer
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.owner, payer.pubkey());
 }
 
@@ -3331,7 +4518,8 @@ async fn test_initialization_sets_default_delegation_fee() {
 
     // Verify default delegation fee is 10,000,000 (10 USDC)
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.delegation_fee, 10_000_000);
 }
 
@@ -3365,8 +4553,22 @@ async fn test_send_priority_with_insufficient_balance() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     // Don't mint any USDC - balance is 0
     let recipient = Keypair::new();
@@ -3381,6 +4583,16 @@ async fn test_send_priority_with_insufficient_balance() {
             _body: "Body".to_string(),
             revenue_share_to_receiver: true,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -3390,18 +4602,22 @@ async fn test_send_priority_with_insufficient_balance() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
     let mut transaction = Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
-    
+
     // Transaction should succeed (soft fail - logs with feePaid=false)
     banks_client.process_transaction(transaction).await.unwrap();
-    
+
     // Verify no shares were recorded
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.owner_claimable, 0);
 }
 
@@ -3431,8 +4647,22 @@ async fn test_send_standard_with_insufficient_balance() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     // Don't mint any USDC
     let recipient = Keypair::new();
@@ -3447,6 +4677,16 @@ async fn test_send_standard_with_insufficient_balance() {
             _body: "Body".to_string(),
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -3456,18 +4696,22 @@ async fn test_send_standard_with_insufficient_balance() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
     let mut transaction = Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
-    
+
     // Should succeed with feePaid=false
     banks_client.process_transaction(transaction).await.unwrap();
-    
+
     // Verify no owner claimable
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.owner_claimable, 0);
 }
 
@@ -3497,8 +4741,22 @@ async fn test_send_prepared_priority_with_insufficient_balance() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     let recipient = Keypair::new();
     let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
@@ -3511,6 +4769,11 @@ async fn test_send_prepared_priority_with_insufficient_balance() {
             mail_id: "test123".to_string(),
             revenue_share_to_receiver: true,
             resolve_sender_to_name: false,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -3520,6 +4783,9 @@ async fn test_send_prepared_priority_with_insufficient_balance() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -3554,8 +4820,22 @@ async fn test_send_prepared_standard_with_insufficient_balance() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     let recipient = Keypair::new();
     let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
@@ -3568,6 +4848,11 @@ async fn test_send_prepared_standard_with_insufficient_balance() {
             mail_id: "test123".to_string(),
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -3577,6 +4862,9 @@ async fn test_send_prepared_standard_with_insufficient_balance() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -3611,8 +4899,22 @@ async fn test_send_to_email_with_insufficient_balance() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let send_instruction = Instruction::new_with_borsh(
@@ -3621,6 +4923,7 @@ async fn test_send_to_email_with_insufficient_balance() {
             to_email: "test@example.com".to_string(),
             subject: "Test".to_string(),
             _body: "Body".to_string(),
+            strict: false,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -3628,6 +4931,8 @@ async fn test_send_to_email_with_insufficient_balance() {
             AccountMeta::new(sender_usdc, false),
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
         ],
     );
 
@@ -3662,8 +4967,22 @@ async fn test_send_prepared_to_email_with_insufficient_balance() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let send_instruction = Instruction::new_with_borsh(
@@ -3671,6 +4990,7 @@ async fn test_send_prepared_to_email_with_insufficient_balance() {
         &MailerInstruction::SendPreparedToEmail {
             to_email: "test@example.com".to_string(),
             mail_id: "test123".to_string(),
+            strict: false,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -3678,6 +4998,8 @@ async fn test_send_prepared_to_email_with_insufficient_balance() {
             AccountMeta::new(sender_usdc, false),
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
         ],
     );
 
@@ -3712,13 +5034,29 @@ async fn test_send_through_webhook_priority_with_insufficient_balance() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     let recipient = Keypair::new();
     let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
 
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let webhook_pda =
+        register_webhook(&mut banks_client, &payer, recent_blockhash, "webhook123").await;
     let send_instruction = Instruction::new_with_borsh(
         program_id(),
         &MailerInstruction::SendThroughWebhook {
@@ -3726,6 +5064,11 @@ async fn test_send_through_webhook_priority_with_insufficient_balance() {
             webhook_id: "webhook123".to_string(),
             revenue_share_to_receiver: true,
             resolve_sender_to_name: false,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -3735,6 +5078,10 @@ async fn test_send_through_webhook_priority_with_insufficient_balance() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(webhook_pda, false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -3769,13 +5116,29 @@ async fn test_send_through_webhook_standard_with_insufficient_balance() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     let recipient = Keypair::new();
     let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
 
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let webhook_pda =
+        register_webhook(&mut banks_client, &payer, recent_blockhash, "webhook123").await;
     let send_instruction = Instruction::new_with_borsh(
         program_id(),
         &MailerInstruction::SendThroughWebhook {
@@ -3783,6 +5146,11 @@ async fn test_send_through_webhook_standard_with_insufficient_balance() {
             webhook_id: "webhook123".to_string(),
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -3792,6 +5160,10 @@ async fn test_send_through_webhook_standard_with_insufficient_balance() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(webhook_pda, false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -3834,20 +5206,25 @@ async fn test_set_fee_allows_zero() {
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let set_fee_instruction = Instruction::new_with_borsh(
         program_id(),
-        &MailerInstruction::SetFee { new_fee: 0 },
+        &MailerInstruction::SetFee {
+            new_fee: 0,
+            expected_current_value: None,
+        },
         vec![
             AccountMeta::new(payer.pubkey(), true),
             AccountMeta::new(mailer_pda, false),
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
     // Verify fee is zero
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.send_fee, 0);
 }
 
@@ -3882,20 +5259,25 @@ async fn test_set_fee_allows_very_high_fee() {
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let set_fee_instruction = Instruction::new_with_borsh(
         program_id(),
-        &MailerInstruction::SetFee { new_fee: very_high_fee },
+        &MailerInstruction::SetFee {
+            new_fee: very_high_fee,
+            expected_current_value: None,
+        },
         vec![
             AccountMeta::new(payer.pubkey(), true),
             AccountMeta::new(mailer_pda, false),
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
     // Verify fee is set
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.send_fee, very_high_fee);
 }
 
@@ -3929,20 +5311,25 @@ async fn test_set_delegation_fee_allows_zero() {
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let set_delegation_fee_instruction = Instruction::new_with_borsh(
         program_id(),
-        &MailerInstruction::SetDelegationFee { new_fee: 0 },
+        &MailerInstruction::SetDelegationFee {
+            new_fee: 0,
+            expected_current_value: None,
+        },
         vec![
             AccountMeta::new(payer.pubkey(), true),
             AccountMeta::new(mailer_pda, false),
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_delegation_fee_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_delegation_fee_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
     // Verify delegation fee is zero
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.delegation_fee, 0);
 }
 
@@ -3977,20 +5364,25 @@ async fn test_set_delegation_fee_allows_very_high_fee() {
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let set_delegation_fee_instruction = Instruction::new_with_borsh(
         program_id(),
-        &MailerInstruction::SetDelegationFee { new_fee: very_high_fee },
+        &MailerInstruction::SetDelegationFee {
+            new_fee: very_high_fee,
+            expected_current_value: None,
+        },
         vec![
             AccountMeta::new(payer.pubkey(), true),
             AccountMeta::new(mailer_pda, false),
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_delegation_fee_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_delegation_fee_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
     // Verify delegation fee is set
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.delegation_fee, very_high_fee);
 }
 
@@ -4024,19 +5416,37 @@ async fn test_send_with_zero_fee() {
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let set_fee_instruction = Instruction::new_with_borsh(
         program_id(),
-        &MailerInstruction::SetFee { new_fee: 0 },
+        &MailerInstruction::SetFee {
+            new_fee: 0,
+            expected_current_value: None,
+        },
         vec![
             AccountMeta::new(payer.pubkey(), true),
             AccountMeta::new(mailer_pda, false),
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     let recipient = Keypair::new();
     let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
@@ -4051,6 +5461,16 @@ async fn test_send_with_zero_fee() {
             _body: "Body".to_string(),
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -4060,6 +5480,9 @@ async fn test_send_with_zero_fee() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -4069,7 +5492,8 @@ async fn test_send_with_zero_fee() {
 
     // Verify no fees collected
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.owner_claimable, 0);
 }
 
@@ -4103,14 +5527,25 @@ async fn test_claim_recipient_share_reverts_with_no_claimable_amount() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let payer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
+    let payer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
     let (claim_pda, _) = get_claim_pda(&payer.pubkey());
 
     // Try to claim without having any claimable amount (account doesn't exist)
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let claim_instruction = Instruction::new_with_borsh(
         program_id(),
-        &MailerInstruction::ClaimRecipientShare,
+        &MailerInstruction::ClaimRecipientShare {
+            amount: None,
+            split: None,
+            close_to: None,
+        },
         vec![
             AccountMeta::new(payer.pubkey(), true),
             AccountMeta::new(claim_pda, false),
@@ -4122,7 +5557,7 @@ async fn test_claim_recipient_share_reverts_with_no_claimable_amount() {
 
     let mut transaction = Transaction::new_with_payer(&[claim_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
-    
+
     // Should fail because claim account doesn't exist
     let result = banks_client.process_transaction(transaction).await;
     assert!(result.is_err());
@@ -4154,8 +5589,22 @@ async fn test_claim_owner_share_reverts_with_no_claimable_amount() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let owner_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let owner_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     // Try to claim without having any claimable amount
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
@@ -4173,18 +5622,20 @@ async fn test_claim_owner_share_reverts_with_no_claimable_amount() {
 
     let mut transaction = Transaction::new_with_payer(&[claim_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
-    
+
     // Should fail (no claimable amount)
     let result = banks_client.process_transaction(transaction).await;
     assert!(result.is_err());
 }
 
 // ============================================================================
-// Webhook Variant Tests  
+// Webhook Variant Tests
 // ============================================================================
 
 #[tokio::test]
 async fn test_send_through_webhook_with_empty_webhook_id() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
     let program_test = ProgramTest::new(
         "mailer",
         program_id(),
@@ -4209,22 +5660,53 @@ async fn test_send_through_webhook_with_empty_webhook_id() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
-
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 10_000_000).await;
-
-    let recipient = Keypair::new();
-    let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
-
-    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
-    let send_instruction = Instruction::new_with_borsh(
-        program_id(),
-        &MailerInstruction::SendThroughWebhook {
-            to: recipient.pubkey(),
-            webhook_id: "".to_string(), // Empty webhook_id
-            revenue_share_to_receiver: false,
-            resolve_sender_to_name: false,
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
+
+    let recipient = Keypair::new();
+    let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
+
+    // An empty id cannot be registered, so no webhook account exists for it
+    let (webhook_pda, _) =
+        mailer::pda::webhook(&program_id(), &solana_program::hash::hash(b"").to_bytes());
+
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let send_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SendThroughWebhook {
+            to: recipient.pubkey(),
+            webhook_id: "".to_string(), // Empty webhook_id
+            revenue_share_to_receiver: false,
+            resolve_sender_to_name: false,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -4234,12 +5716,27 @@ async fn test_send_through_webhook_with_empty_webhook_id() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(webhook_pda, false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
     let mut transaction = Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
-    banks_client.process_transaction(transaction).await.unwrap();
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::InvalidWebhook as u32)
+        )
+    );
 }
 
 #[tokio::test]
@@ -4268,10 +5765,32 @@ async fn test_send_through_webhook_with_long_webhook_id() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 10_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
 
     let recipient = Keypair::new();
     let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
@@ -4279,6 +5798,13 @@ async fn test_send_through_webhook_with_long_webhook_id() {
     let long_webhook_id = "A".repeat(200); // Long webhook_id
 
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let webhook_pda = register_webhook(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &long_webhook_id,
+    )
+    .await;
     let send_instruction = Instruction::new_with_borsh(
         program_id(),
         &MailerInstruction::SendThroughWebhook {
@@ -4286,6 +5812,11 @@ async fn test_send_through_webhook_with_long_webhook_id() {
             webhook_id: long_webhook_id,
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -4295,6 +5826,10 @@ async fn test_send_through_webhook_with_long_webhook_id() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(webhook_pda, false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -4329,15 +5864,44 @@ async fn test_send_through_webhook_with_special_characters() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 10_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
 
     let recipient = Keypair::new();
     let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
 
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let webhook_pda = register_webhook(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        "webhook-123!@#$%^&*()",
+    )
+    .await;
     let send_instruction = Instruction::new_with_borsh(
         program_id(),
         &MailerInstruction::SendThroughWebhook {
@@ -4345,6 +5909,11 @@ async fn test_send_through_webhook_with_special_characters() {
             webhook_id: "webhook-123!@#$%^&*()".to_string(),
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -4354,6 +5923,10 @@ async fn test_send_through_webhook_with_special_characters() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(webhook_pda, false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -4403,9 +5976,10 @@ async fn test_unpause_non_paused_contract_fails() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[unpause_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[unpause_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
-    
+
     // Should fail
     let result = banks_client.process_transaction(transaction).await;
     assert!(result.is_err());
@@ -4437,8 +6011,22 @@ async fn test_resume_normal_operations_after_unpause() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let owner_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let owner_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     // Pause
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
@@ -4469,13 +6057,29 @@ async fn test_resume_normal_operations_after_unpause() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[unpause_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[unpause_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
     // Verify can send messages after unpause
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 10_000_000).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
 
     let recipient = Keypair::new();
     let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
@@ -4489,6 +6093,16 @@ async fn test_resume_normal_operations_after_unpause() {
             _body: "Body".to_string(),
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -4498,6 +6112,9 @@ async fn test_resume_normal_operations_after_unpause() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -4532,8 +6149,22 @@ async fn test_emergency_unpause_success() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let owner_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let owner_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     // Pause
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
@@ -4564,13 +6195,15 @@ async fn test_emergency_unpause_success() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[emergency_unpause_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[emergency_unpause_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
     // Verify contract is unpaused
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert!(!mailer_state.paused);
 }
 
@@ -4611,9 +6244,10 @@ async fn test_emergency_unpause_when_not_paused_fails() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[emergency_unpause_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[emergency_unpause_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
-    
+
     // Should fail
     let result = banks_client.process_transaction(transaction).await;
     assert!(result.is_err());
@@ -4649,10 +6283,32 @@ async fn test_custom_fee_50_percent_with_revenue_sharing() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 10_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
 
     // Set custom fee percentage to 50%
     let (custom_fee_pda, _) = get_fee_discount_pda(&payer.pubkey());
@@ -4666,7 +6322,7 @@ async fn test_custom_fee_50_percent_with_revenue_sharing() {
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
-            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(mailer_pda, false),
             AccountMeta::new(custom_fee_pda, false),
             AccountMeta::new_readonly(payer.pubkey(), false),
             AccountMeta::new(payer.pubkey(), true),
@@ -4674,7 +6330,8 @@ async fn test_custom_fee_50_percent_with_revenue_sharing() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
@@ -4691,6 +6348,16 @@ async fn test_custom_fee_50_percent_with_revenue_sharing() {
             _body: "Body".to_string(),
             revenue_share_to_receiver: true,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -4700,7 +6367,10 @@ async fn test_custom_fee_50_percent_with_revenue_sharing() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
             AccountMeta::new_readonly(custom_fee_pda, false),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -4711,12 +6381,18 @@ async fn test_custom_fee_50_percent_with_revenue_sharing() {
     // Verify shares: 50% of 100,000 = 50,000
     // Recipient gets 90% of 50,000 = 45,000
     // Owner gets 10% of 50,000 = 5,000
-    let claim_account = banks_client.get_account(recipient_claim_pda).await.unwrap().unwrap();
-    let recipient_claim: RecipientClaim = BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    let claim_account = banks_client
+        .get_account(recipient_claim_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let recipient_claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
     assert_eq!(recipient_claim.amount, 45_000);
 
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.owner_claimable, 5_000);
 }
 
@@ -4758,7 +6434,7 @@ async fn test_clear_custom_fee_percentage() {
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
-            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(mailer_pda, false),
             AccountMeta::new(custom_fee_pda, false),
             AccountMeta::new_readonly(payer.pubkey(), false),
             AccountMeta::new(payer.pubkey(), true),
@@ -4766,7 +6442,8 @@ async fn test_clear_custom_fee_percentage() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
@@ -4785,13 +6462,19 @@ async fn test_clear_custom_fee_percentage() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[clear_percentage_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[clear_percentage_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
     // Verify percentage is cleared (discount reset to 0 = 100% fee = default)
-    let custom_fee_account = banks_client.get_account(custom_fee_pda).await.unwrap().unwrap();
-    let fee_discount: FeeDiscount = BorshDeserialize::deserialize(&mut &custom_fee_account.data[8..]).unwrap();
+    let custom_fee_account = banks_client
+        .get_account(custom_fee_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let fee_discount: FeeDiscount =
+        BorshDeserialize::deserialize(&mut &custom_fee_account.data[8..]).unwrap();
     assert_eq!(fee_discount.discount, 0); // discount 0 = no discount = 100% fee
 }
 
@@ -4821,10 +6504,32 @@ async fn test_send_prepared_with_custom_fee_25_percent() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 10_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
 
     // Set custom fee percentage to 25%
     let (custom_fee_pda, _) = get_fee_discount_pda(&payer.pubkey());
@@ -4838,7 +6543,7 @@ async fn test_send_prepared_with_custom_fee_25_percent() {
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
-            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(mailer_pda, false),
             AccountMeta::new(custom_fee_pda, false),
             AccountMeta::new_readonly(payer.pubkey(), false),
             AccountMeta::new(payer.pubkey(), true),
@@ -4846,7 +6551,8 @@ async fn test_send_prepared_with_custom_fee_25_percent() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
@@ -4862,6 +6568,11 @@ async fn test_send_prepared_with_custom_fee_25_percent() {
             mail_id: "test123".to_string(),
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -4871,7 +6582,10 @@ async fn test_send_prepared_with_custom_fee_25_percent() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
             AccountMeta::new_readonly(custom_fee_pda, false),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -4881,7 +6595,8 @@ async fn test_send_prepared_with_custom_fee_25_percent() {
 
     // Verify owner claimable: 25% of 100,000 = 25,000, then 10% of that = 2,500
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.owner_claimable, 2_500);
 }
 
@@ -4911,13 +6626,35 @@ async fn test_send_to_email_with_custom_fee() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
-
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 10_000_000).await;
-
-    // Set custom fee percentage to 75%
-    let (custom_fee_pda, _) = get_fee_discount_pda(&payer.pubkey());
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
+
+    // Set custom fee percentage to 75%
+    let (custom_fee_pda, _) = get_fee_discount_pda(&payer.pubkey());
 
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let set_percentage_instruction = Instruction::new_with_borsh(
@@ -4928,7 +6665,7 @@ async fn test_send_to_email_with_custom_fee() {
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
-            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(mailer_pda, false),
             AccountMeta::new(custom_fee_pda, false),
             AccountMeta::new_readonly(payer.pubkey(), false),
             AccountMeta::new(payer.pubkey(), true),
@@ -4936,7 +6673,8 @@ async fn test_send_to_email_with_custom_fee() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
@@ -4948,6 +6686,7 @@ async fn test_send_to_email_with_custom_fee() {
             to_email: "test@example.com".to_string(),
             subject: "Test".to_string(),
             _body: "Body".to_string(),
+            strict: false,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -4956,6 +6695,8 @@ async fn test_send_to_email_with_custom_fee() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(custom_fee_pda, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
         ],
     );
 
@@ -4965,7 +6706,8 @@ async fn test_send_to_email_with_custom_fee() {
 
     // Verify owner claimable: 75% of 100,000 = 75,000, then 10% of that = 7,500
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.owner_claimable, 7_500);
 }
 
@@ -4995,10 +6737,32 @@ async fn test_send_prepared_to_email_with_custom_fee() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 10_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
 
     // Set custom fee percentage to 10%
     let (custom_fee_pda, _) = get_fee_discount_pda(&payer.pubkey());
@@ -5012,7 +6776,7 @@ async fn test_send_prepared_to_email_with_custom_fee() {
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
-            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(mailer_pda, false),
             AccountMeta::new(custom_fee_pda, false),
             AccountMeta::new_readonly(payer.pubkey(), false),
             AccountMeta::new(payer.pubkey(), true),
@@ -5020,7 +6784,8 @@ async fn test_send_prepared_to_email_with_custom_fee() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
@@ -5031,6 +6796,7 @@ async fn test_send_prepared_to_email_with_custom_fee() {
         &MailerInstruction::SendPreparedToEmail {
             to_email: "test@example.com".to_string(),
             mail_id: "test123".to_string(),
+            strict: false,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -5039,6 +6805,8 @@ async fn test_send_prepared_to_email_with_custom_fee() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(custom_fee_pda, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
         ],
     );
 
@@ -5048,7 +6816,8 @@ async fn test_send_prepared_to_email_with_custom_fee() {
 
     // Verify owner claimable: 10% of 100,000 = 10,000, then 10% of that = 1,000
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.owner_claimable, 1_000);
 }
 
@@ -5078,10 +6847,32 @@ async fn test_send_through_webhook_with_custom_fee() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 10_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
 
     // Set custom fee percentage to 20%
     let (custom_fee_pda, _) = get_fee_discount_pda(&payer.pubkey());
@@ -5095,7 +6886,7 @@ async fn test_send_through_webhook_with_custom_fee() {
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
-            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(mailer_pda, false),
             AccountMeta::new(custom_fee_pda, false),
             AccountMeta::new_readonly(payer.pubkey(), false),
             AccountMeta::new(payer.pubkey(), true),
@@ -5103,7 +6894,8 @@ async fn test_send_through_webhook_with_custom_fee() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
@@ -5112,6 +6904,8 @@ async fn test_send_through_webhook_with_custom_fee() {
     let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
 
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let webhook_pda =
+        register_webhook(&mut banks_client, &payer, recent_blockhash, "webhook123").await;
     let send_instruction = Instruction::new_with_borsh(
         program_id(),
         &MailerInstruction::SendThroughWebhook {
@@ -5119,6 +6913,11 @@ async fn test_send_through_webhook_with_custom_fee() {
             webhook_id: "webhook123".to_string(),
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -5128,7 +6927,11 @@ async fn test_send_through_webhook_with_custom_fee() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(webhook_pda, false),
+            AccountMeta::new(payer.pubkey(), true),
             AccountMeta::new_readonly(custom_fee_pda, false),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -5138,7 +6941,8 @@ async fn test_send_through_webhook_with_custom_fee() {
 
     // Verify owner claimable: 20% of 100,000 = 20,000, then 10% of that = 2,000
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.owner_claimable, 2_000);
 }
 
@@ -5172,14 +6976,41 @@ async fn test_send_to_email_transfer_correct_usdc_amount() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 1_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
 
     // Get initial balance
-    let initial_sender_account = banks_client.get_account(sender_usdc).await.unwrap().unwrap();
-    let initial_sender_token: TokenAccount = TokenAccount::unpack(&initial_sender_account.data).unwrap();
+    let initial_sender_account = banks_client
+        .get_account(sender_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    let initial_sender_token: TokenAccount =
+        TokenAccount::unpack(&initial_sender_account.data).unwrap();
     let initial_balance = initial_sender_token.amount;
 
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
@@ -5189,6 +7020,7 @@ async fn test_send_to_email_transfer_correct_usdc_amount() {
             to_email: "test@example.com".to_string(),
             subject: "Test".to_string(),
             _body: "Body".to_string(),
+            strict: false,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -5196,6 +7028,8 @@ async fn test_send_to_email_transfer_correct_usdc_amount() {
             AccountMeta::new(sender_usdc, false),
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
         ],
     );
 
@@ -5204,8 +7038,13 @@ async fn test_send_to_email_transfer_correct_usdc_amount() {
     banks_client.process_transaction(transaction).await.unwrap();
 
     // Verify correct amount was transferred (10% of send_fee = 10,000)
-    let final_sender_account = banks_client.get_account(sender_usdc).await.unwrap().unwrap();
-    let final_sender_token: TokenAccount = TokenAccount::unpack(&final_sender_account.data).unwrap();
+    let final_sender_account = banks_client
+        .get_account(sender_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    let final_sender_token: TokenAccount =
+        TokenAccount::unpack(&final_sender_account.data).unwrap();
 
     assert_eq!(initial_balance - final_sender_token.amount, 10_000);
 }
@@ -5237,8 +7076,22 @@ async fn test_send_to_email_insufficient_allowance() {
     banks_client.process_transaction(transaction).await.unwrap();
 
     // Create token account but don't mint - insufficient balance scenario
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let send_instruction = Instruction::new_with_borsh(
@@ -5247,6 +7100,7 @@ async fn test_send_to_email_insufficient_allowance() {
             to_email: "test@example.com".to_string(),
             subject: "Test".to_string(),
             _body: "Body".to_string(),
+            strict: false,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -5254,6 +7108,8 @@ async fn test_send_to_email_insufficient_allowance() {
             AccountMeta::new(sender_usdc, false),
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
         ],
     );
 
@@ -5265,7 +7121,8 @@ async fn test_send_to_email_insufficient_allowance() {
 
     // Verify no fees were collected
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.owner_claimable, 0);
 }
 
@@ -5299,14 +7156,41 @@ async fn test_send_prepared_to_email_transfer_correct_usdc_amount() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 1_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
 
     // Get initial balance
-    let initial_sender_account = banks_client.get_account(sender_usdc).await.unwrap().unwrap();
-    let initial_sender_token: TokenAccount = TokenAccount::unpack(&initial_sender_account.data).unwrap();
+    let initial_sender_account = banks_client
+        .get_account(sender_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    let initial_sender_token: TokenAccount =
+        TokenAccount::unpack(&initial_sender_account.data).unwrap();
     let initial_balance = initial_sender_token.amount;
 
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
@@ -5315,6 +7199,7 @@ async fn test_send_prepared_to_email_transfer_correct_usdc_amount() {
         &MailerInstruction::SendPreparedToEmail {
             to_email: "test@example.com".to_string(),
             mail_id: "mail-123".to_string(),
+            strict: false,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -5322,6 +7207,8 @@ async fn test_send_prepared_to_email_transfer_correct_usdc_amount() {
             AccountMeta::new(sender_usdc, false),
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
         ],
     );
 
@@ -5330,8 +7217,13 @@ async fn test_send_prepared_to_email_transfer_correct_usdc_amount() {
     banks_client.process_transaction(transaction).await.unwrap();
 
     // Verify correct amount was transferred
-    let final_sender_account = banks_client.get_account(sender_usdc).await.unwrap().unwrap();
-    let final_sender_token: TokenAccount = TokenAccount::unpack(&final_sender_account.data).unwrap();
+    let final_sender_account = banks_client
+        .get_account(sender_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    let final_sender_token: TokenAccount =
+        TokenAccount::unpack(&final_sender_account.data).unwrap();
 
     assert_eq!(initial_balance - final_sender_token.amount, 10_000);
 }
@@ -5362,8 +7254,22 @@ async fn test_send_prepared_to_email_insufficient_balance() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     // Don't mint any USDC
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
@@ -5372,6 +7278,7 @@ async fn test_send_prepared_to_email_insufficient_balance() {
         &MailerInstruction::SendPreparedToEmail {
             to_email: "test@example.com".to_string(),
             mail_id: "mail-123".to_string(),
+            strict: false,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -5379,6 +7286,8 @@ async fn test_send_prepared_to_email_insufficient_balance() {
             AccountMeta::new(sender_usdc, false),
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
         ],
     );
 
@@ -5390,7 +7299,8 @@ async fn test_send_prepared_to_email_insufficient_balance() {
 
     // Verify no fees were collected
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.owner_claimable, 0);
 }
 
@@ -5424,11 +7334,25 @@ async fn test_pause_non_owner_fails() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     // Create non-owner
     let non_owner = Keypair::new();
-    let non_owner_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &non_owner.pubkey()).await;
+    let non_owner_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &non_owner.pubkey(),
+    )
+    .await;
 
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let pause_instruction = Instruction::new_with_borsh(
@@ -5476,8 +7400,22 @@ async fn test_pause_already_paused_fails() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let owner_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let owner_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     // Pause once
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
@@ -5499,12 +7437,20 @@ async fn test_pause_already_paused_fails() {
 
     // Verify contract is paused
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
-    assert_eq!(mailer_state.paused, true);
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert!(mailer_state.paused);
 
     // Try any operation while paused - should fail (test SendToEmail as example)
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
 
     let send_instruction = Instruction::new_with_borsh(
         program_id(),
@@ -5512,6 +7458,7 @@ async fn test_pause_already_paused_fails() {
             to_email: "test@example.com".to_string(),
             subject: "Test".to_string(),
             _body: "Body".to_string(),
+            strict: false,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -5519,6 +7466,8 @@ async fn test_pause_already_paused_fails() {
             AccountMeta::new(sender_usdc, false),
             AccountMeta::new(owner_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
         ],
     );
 
@@ -5554,11 +7503,40 @@ async fn test_pause_distributes_owner_claimable() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
-    let owner_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    let owner_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 1_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
 
     // Send a message to accumulate owner fees
     let recipient = Keypair::new();
@@ -5573,6 +7551,16 @@ async fn test_pause_distributes_owner_claimable() {
             _body: "Body".to_string(),
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -5582,6 +7570,9 @@ async fn test_pause_distributes_owner_claimable() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -5591,7 +7582,8 @@ async fn test_pause_distributes_owner_claimable() {
 
     // Get owner balance before pause
     let owner_account_before = banks_client.get_account(owner_usdc).await.unwrap().unwrap();
-    let owner_token_before: TokenAccount = TokenAccount::unpack(&owner_account_before.data).unwrap();
+    let owner_token_before: TokenAccount =
+        TokenAccount::unpack(&owner_account_before.data).unwrap();
 
     // Pause and distribute
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
@@ -5619,7 +7611,8 @@ async fn test_pause_distributes_owner_claimable() {
 
     // Verify owner_claimable is now 0
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.owner_claimable, 0);
 }
 
@@ -5649,8 +7642,22 @@ async fn test_unpause_non_owner_fails() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let owner_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let owner_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     // Pause first
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
@@ -5682,7 +7689,8 @@ async fn test_unpause_non_owner_fails() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[unpause_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[unpause_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer, &non_owner], recent_blockhash);
 
     let result = banks_client.process_transaction(transaction).await;
@@ -5715,8 +7723,22 @@ async fn test_distribute_when_not_paused_fails() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let _owner_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let _mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let _owner_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let _mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     // Try to distribute without pausing - this should fail
     // Note: Solana doesn't have a separate Distribute instruction, distribution happens during Pause
@@ -5749,8 +7771,22 @@ async fn test_set_fee_when_paused_fails() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let owner_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let owner_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     // Pause contract
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
@@ -5774,14 +7810,18 @@ async fn test_set_fee_when_paused_fails() {
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let set_fee_instruction = Instruction::new_with_borsh(
         program_id(),
-        &MailerInstruction::SetFee { new_fee: 200_000 },
+        &MailerInstruction::SetFee {
+            new_fee: 200_000,
+            expected_current_value: None,
+        },
         vec![
             AccountMeta::new(payer.pubkey(), true),
             AccountMeta::new(mailer_pda, false),
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
 
     let result = banks_client.process_transaction(transaction).await;
@@ -5837,7 +7877,8 @@ async fn test_set_custom_fee_percentage_non_owner_fails() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer, &non_owner], recent_blockhash);
 
     let result = banks_client.process_transaction(transaction).await;
@@ -5888,7 +7929,8 @@ async fn test_set_custom_fee_percentage_over_100_fails() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
 
     let result = banks_client.process_transaction(transaction).await;
@@ -5934,7 +7976,7 @@ async fn test_clear_custom_fee_percentage_non_owner_fails() {
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
-            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(mailer_pda, false),
             AccountMeta::new(custom_fee_pda, false),
             AccountMeta::new_readonly(test_user.pubkey(), false),
             AccountMeta::new(payer.pubkey(), true),
@@ -5942,7 +7984,8 @@ async fn test_clear_custom_fee_percentage_non_owner_fails() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
@@ -6043,7 +8086,7 @@ async fn test_get_custom_fee_percentage_returns_correct_value() {
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
-            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(mailer_pda, false),
             AccountMeta::new(custom_fee_pda, false),
             AccountMeta::new_readonly(test_user.pubkey(), false),
             AccountMeta::new(payer.pubkey(), true),
@@ -6051,12 +8094,17 @@ async fn test_get_custom_fee_percentage_returns_correct_value() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
     // Verify the percentage was stored correctly (discount = 100 - percentage)
-    let account = banks_client.get_account(custom_fee_pda).await.unwrap().unwrap();
+    let account = banks_client
+        .get_account(custom_fee_pda)
+        .await
+        .unwrap()
+        .unwrap();
     let fee_discount: FeeDiscount = BorshDeserialize::deserialize(&mut &account.data[8..]).unwrap();
     assert_eq!(fee_discount.discount, 25); // 100 - 75 = 25% discount
 }
@@ -6096,20 +8144,25 @@ async fn test_set_send_fee_updates_correctly() {
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let set_fee_instruction = Instruction::new_with_borsh(
         program_id(),
-        &MailerInstruction::SetFee { new_fee: new_fee },
+        &MailerInstruction::SetFee {
+            new_fee,
+            expected_current_value: None,
+        },
         vec![
             AccountMeta::new(payer.pubkey(), true),
             AccountMeta::new(mailer_pda, false),
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
     // Verify fee was updated
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.send_fee, new_fee);
 }
 
@@ -6139,24 +8192,50 @@ async fn test_send_uses_updated_fee() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 10_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
 
     // Set new higher fee
     let new_fee = 500_000u64;
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let set_fee_instruction = Instruction::new_with_borsh(
         program_id(),
-        &MailerInstruction::SetFee { new_fee: new_fee },
+        &MailerInstruction::SetFee {
+            new_fee,
+            expected_current_value: None,
+        },
         vec![
             AccountMeta::new(payer.pubkey(), true),
             AccountMeta::new(mailer_pda, false),
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
@@ -6173,6 +8252,16 @@ async fn test_send_uses_updated_fee() {
             _body: "Body".to_string(),
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -6182,6 +8271,9 @@ async fn test_send_uses_updated_fee() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -6191,7 +8283,8 @@ async fn test_send_uses_updated_fee() {
 
     // Verify owner got 10% of new fee
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.owner_claimable, 50_000); // 10% of 500,000
 }
 
@@ -6221,24 +8314,50 @@ async fn test_send_prepared_uses_updated_fee() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 10_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
 
     // Set new higher fee
     let new_fee = 300_000u64;
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let set_fee_instruction = Instruction::new_with_borsh(
         program_id(),
-        &MailerInstruction::SetFee { new_fee: new_fee },
+        &MailerInstruction::SetFee {
+            new_fee,
+            expected_current_value: None,
+        },
         vec![
             AccountMeta::new(payer.pubkey(), true),
             AccountMeta::new(mailer_pda, false),
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
@@ -6254,6 +8373,11 @@ async fn test_send_prepared_uses_updated_fee() {
             mail_id: "mail-123".to_string(),
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -6263,6 +8387,9 @@ async fn test_send_prepared_uses_updated_fee() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -6272,7 +8399,8 @@ async fn test_send_prepared_uses_updated_fee() {
 
     // Verify owner got 10% of new fee
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.owner_claimable, 30_000); // 10% of 300,000
 }
 
@@ -6302,25 +8430,51 @@ async fn test_send_with_insufficient_balance_for_new_fee() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     // Only mint 50,000 (not enough for new fee)
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 50_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        50_000,
+    )
+    .await;
 
     // Set very high fee
     let new_fee = 1_000_000u64;
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let set_fee_instruction = Instruction::new_with_borsh(
         program_id(),
-        &MailerInstruction::SetFee { new_fee: new_fee },
+        &MailerInstruction::SetFee {
+            new_fee,
+            expected_current_value: None,
+        },
         vec![
             AccountMeta::new(payer.pubkey(), true),
             AccountMeta::new(mailer_pda, false),
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
@@ -6337,6 +8491,16 @@ async fn test_send_with_insufficient_balance_for_new_fee() {
             _body: "Body".to_string(),
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -6346,6 +8510,9 @@ async fn test_send_with_insufficient_balance_for_new_fee() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -6357,7 +8524,8 @@ async fn test_send_with_insufficient_balance_for_new_fee() {
 
     // Verify no fees were collected
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.owner_claimable, 0);
 }
 
@@ -6391,10 +8559,32 @@ async fn test_get_recipient_claimable_info() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 1_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
 
     let recipient = Keypair::new();
     let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
@@ -6409,6 +8599,16 @@ async fn test_get_recipient_claimable_info() {
             _body: "Body".to_string(),
             revenue_share_to_receiver: true,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -6418,6 +8618,9 @@ async fn test_get_recipient_claimable_info() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -6426,8 +8629,13 @@ async fn test_get_recipient_claimable_info() {
     banks_client.process_transaction(transaction).await.unwrap();
 
     // Get claim info
-    let claim_account = banks_client.get_account(recipient_claim_pda).await.unwrap().unwrap();
-    let recipient_claim: RecipientClaim = BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    let claim_account = banks_client
+        .get_account(recipient_claim_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let recipient_claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
 
     assert_eq!(recipient_claim.recipient, recipient.pubkey());
     assert_eq!(recipient_claim.amount, 90_000);
@@ -6460,10 +8668,32 @@ async fn test_get_owner_claimable_amount() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 10_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
 
     // Send multiple messages to accumulate fees
     for i in 0..5 {
@@ -6479,6 +8709,16 @@ async fn test_get_owner_claimable_amount() {
                 _body: "Body".to_string(),
                 revenue_share_to_receiver: false,
                 resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
             },
             vec![
                 AccountMeta::new(payer.pubkey(), true),
@@ -6488,17 +8728,22 @@ async fn test_get_owner_claimable_amount() {
                 AccountMeta::new(mailer_usdc, false),
                 AccountMeta::new_readonly(spl_token::id(), false),
                 AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
             ],
         );
 
-        let mut transaction = Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
+        let mut transaction =
+            Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
         transaction.sign(&[&payer], recent_blockhash);
         banks_client.process_transaction(transaction).await.unwrap();
     }
 
     // Get owner claimable amount
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
 
     assert_eq!(mailer_state.owner_claimable, 50_000); // 5 * 10,000
 }
@@ -6533,11 +8778,40 @@ async fn test_only_owner_can_claim_expired_shares() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
-    let owner_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    let owner_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 1_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
 
     let recipient = Keypair::new();
     let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
@@ -6552,6 +8826,16 @@ async fn test_only_owner_can_claim_expired_shares() {
             _body: "Body".to_string(),
             revenue_share_to_receiver: true,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -6561,6 +8845,9 @@ async fn test_only_owner_can_claim_expired_shares() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -6580,6 +8867,7 @@ async fn test_only_owner_can_claim_expired_shares() {
             AccountMeta::new(non_owner.pubkey(), true),
             AccountMeta::new(recipient_claim_pda, false),
             AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(get_beneficiary_pda(&recipient.pubkey()).0, false),
             AccountMeta::new_readonly(payer.pubkey(), false),
             AccountMeta::new(owner_usdc, false),
             AccountMeta::new(mailer_usdc, false),
@@ -6623,12 +8911,34 @@ async fn test_send_priority_records_90_percent_for_recipient() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
-
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 1_000_000).await;
-
-    let recipient = Keypair::new();
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let recipient = Keypair::new();
     let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
 
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
@@ -6640,6 +8950,16 @@ async fn test_send_priority_records_90_percent_for_recipient() {
             _body: "Test".to_string(),
             revenue_share_to_receiver: true,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -6649,6 +8969,9 @@ async fn test_send_priority_records_90_percent_for_recipient() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -6656,8 +8979,13 @@ async fn test_send_priority_records_90_percent_for_recipient() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let claim_account = banks_client.get_account(recipient_claim_pda).await.unwrap().unwrap();
-    let recipient_claim: RecipientClaim = BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    let claim_account = banks_client
+        .get_account(recipient_claim_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let recipient_claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
 
     assert_eq!(recipient_claim.amount, 90_000);
 }
@@ -6688,10 +9016,32 @@ async fn test_send_prepared_priority_records_90_percent_for_recipient() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 1_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
 
     let recipient = Keypair::new();
     let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
@@ -6704,6 +9054,11 @@ async fn test_send_prepared_priority_records_90_percent_for_recipient() {
             mail_id: "mail-456".to_string(),
             revenue_share_to_receiver: true,
             resolve_sender_to_name: false,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -6713,6 +9068,9 @@ async fn test_send_prepared_priority_records_90_percent_for_recipient() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -6720,8 +9078,13 @@ async fn test_send_prepared_priority_records_90_percent_for_recipient() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let claim_account = banks_client.get_account(recipient_claim_pda).await.unwrap().unwrap();
-    let recipient_claim: RecipientClaim = BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    let claim_account = banks_client
+        .get_account(recipient_claim_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let recipient_claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
 
     assert_eq!(recipient_claim.amount, 90_000);
 }
@@ -6752,15 +9115,39 @@ async fn test_webhook_priority_records_90_percent_for_recipient() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 1_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
 
     let recipient = Keypair::new();
     let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
 
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let webhook_pda =
+        register_webhook(&mut banks_client, &payer, recent_blockhash, "webhook-789").await;
     let send_instruction = Instruction::new_with_borsh(
         program_id(),
         &MailerInstruction::SendThroughWebhook {
@@ -6768,6 +9155,11 @@ async fn test_webhook_priority_records_90_percent_for_recipient() {
             webhook_id: "webhook-789".to_string(),
             revenue_share_to_receiver: true,
             resolve_sender_to_name: false,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -6777,6 +9169,10 @@ async fn test_webhook_priority_records_90_percent_for_recipient() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(webhook_pda, false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -6784,13 +9180,18 @@ async fn test_webhook_priority_records_90_percent_for_recipient() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let claim_account = banks_client.get_account(recipient_claim_pda).await.unwrap().unwrap();
-    let recipient_claim: RecipientClaim = BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    let claim_account = banks_client
+        .get_account(recipient_claim_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let recipient_claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
 
     assert_eq!(recipient_claim.amount, 90_000);
 }
 
-// Additional Delegation Tests  
+// Additional Delegation Tests
 // ============================================================================
 
 #[tokio::test]
@@ -6819,10 +9220,32 @@ async fn test_delegation_credits_owner_claimable() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 20_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        20_000_000,
+    )
+    .await;
 
     let delegate = Keypair::new();
     let (delegation_pda, _) = get_delegation_pda(&payer.pubkey());
@@ -6841,15 +9264,18 @@ async fn test_delegation_credits_owner_claimable() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(get_delegate_index_pda(&delegate.pubkey()).0, false),
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[delegate_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[delegate_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
 
     assert_eq!(mailer_state.owner_claimable, 10_000_000);
 }
@@ -6880,10 +9306,32 @@ async fn test_delegation_clears_successfully() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 20_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        20_000_000,
+    )
+    .await;
 
     let delegate = Keypair::new();
     let (delegation_pda, _) = get_delegation_pda(&payer.pubkey());
@@ -6903,10 +9351,12 @@ async fn test_delegation_clears_successfully() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(get_delegate_index_pda(&delegate.pubkey()).0, false),
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[delegate_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[delegate_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
@@ -6914,9 +9364,7 @@ async fn test_delegation_clears_successfully() {
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let clear_instruction = Instruction::new_with_borsh(
         program_id(),
-        &MailerInstruction::DelegateTo {
-            delegate: None,
-        },
+        &MailerInstruction::DelegateTo { delegate: None },
         vec![
             AccountMeta::new(payer.pubkey(), true),
             AccountMeta::new(delegation_pda, false),
@@ -6925,6 +9373,7 @@ async fn test_delegation_clears_successfully() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(get_delegate_index_pda(&delegate.pubkey()).0, false),
         ],
     );
 
@@ -6932,8 +9381,13 @@ async fn test_delegation_clears_successfully() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let delegation_account = banks_client.get_account(delegation_pda).await.unwrap().unwrap();
-    let delegation: Delegation = BorshDeserialize::deserialize(&mut &delegation_account.data[8..]).unwrap();
+    let delegation_account = banks_client
+        .get_account(delegation_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let delegation: Delegation =
+        BorshDeserialize::deserialize(&mut &delegation_account.data[8..]).unwrap();
 
     assert_eq!(delegation.delegate, None);
 }
@@ -6968,19 +9422,24 @@ async fn test_delegation_fee_can_be_updated() {
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let set_fee_instruction = Instruction::new_with_borsh(
         program_id(),
-        &MailerInstruction::SetDelegationFee { new_fee },
+        &MailerInstruction::SetDelegationFee {
+            new_fee,
+            expected_current_value: None,
+        },
         vec![
             AccountMeta::new(payer.pubkey(), true),
             AccountMeta::new(mailer_pda, false),
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
 
     assert_eq!(mailer_state.delegation_fee, new_fee);
 }
@@ -7014,19 +9473,24 @@ async fn test_delegation_fee_allows_zero() {
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let set_fee_instruction = Instruction::new_with_borsh(
         program_id(),
-        &MailerInstruction::SetDelegationFee { new_fee: 0 },
+        &MailerInstruction::SetDelegationFee {
+            new_fee: 0,
+            expected_current_value: None,
+        },
         vec![
             AccountMeta::new(payer.pubkey(), true),
             AccountMeta::new(mailer_pda, false),
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
 
     assert_eq!(mailer_state.delegation_fee, 0);
 }
@@ -7061,19 +9525,24 @@ async fn test_delegation_fee_allows_very_high_fee() {
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let set_fee_instruction = Instruction::new_with_borsh(
         program_id(),
-        &MailerInstruction::SetDelegationFee { new_fee },
+        &MailerInstruction::SetDelegationFee {
+            new_fee,
+            expected_current_value: None,
+        },
         vec![
             AccountMeta::new(payer.pubkey(), true),
             AccountMeta::new(mailer_pda, false),
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
 
     assert_eq!(mailer_state.delegation_fee, new_fee);
 }
@@ -7111,19 +9580,24 @@ async fn test_send_fee_can_be_set_to_zero() {
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let set_fee_instruction = Instruction::new_with_borsh(
         program_id(),
-        &MailerInstruction::SetFee { new_fee: 0 },
+        &MailerInstruction::SetFee {
+            new_fee: 0,
+            expected_current_value: None,
+        },
         vec![
             AccountMeta::new(payer.pubkey(), true),
             AccountMeta::new(mailer_pda, false),
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
 
     assert_eq!(mailer_state.send_fee, 0);
 }
@@ -7158,19 +9632,24 @@ async fn test_send_fee_allows_very_high_fee() {
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let set_fee_instruction = Instruction::new_with_borsh(
         program_id(),
-        &MailerInstruction::SetFee { new_fee },
+        &MailerInstruction::SetFee {
+            new_fee,
+            expected_current_value: None,
+        },
         vec![
             AccountMeta::new(payer.pubkey(), true),
             AccountMeta::new(mailer_pda, false),
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
 
     assert_eq!(mailer_state.send_fee, new_fee);
 }
@@ -7202,7 +9681,8 @@ async fn test_get_send_fee_returns_current_fee() {
     banks_client.process_transaction(transaction).await.unwrap();
 
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
 
     assert_eq!(mailer_state.send_fee, 100_000);
 }
@@ -7237,19 +9717,24 @@ async fn test_get_send_fee_returns_updated_fee() {
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let set_fee_instruction = Instruction::new_with_borsh(
         program_id(),
-        &MailerInstruction::SetFee { new_fee },
+        &MailerInstruction::SetFee {
+            new_fee,
+            expected_current_value: None,
+        },
         vec![
             AccountMeta::new(payer.pubkey(), true),
             AccountMeta::new(mailer_pda, false),
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
 
     assert_eq!(mailer_state.send_fee, new_fee);
 }
@@ -7281,7 +9766,8 @@ async fn test_get_delegation_fee_returns_current_fee() {
     banks_client.process_transaction(transaction).await.unwrap();
 
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
 
     assert_eq!(mailer_state.delegation_fee, 10_000_000);
 }
@@ -7316,13 +9802,42 @@ async fn test_claim_recipient_share_transfers_correct_amount() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 1_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
 
     let recipient = Keypair::new();
-    let recipient_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &recipient.pubkey()).await;
+    let recipient_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &recipient.pubkey(),
+    )
+    .await;
     let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
 
     // Send with revenue sharing
@@ -7335,6 +9850,16 @@ async fn test_claim_recipient_share_transfers_correct_amount() {
             _body: "Body".to_string(),
             revenue_share_to_receiver: true,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -7344,6 +9869,9 @@ async fn test_claim_recipient_share_transfers_correct_amount() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -7352,14 +9880,22 @@ async fn test_claim_recipient_share_transfers_correct_amount() {
     banks_client.process_transaction(transaction).await.unwrap();
 
     // Get initial balance
-    let initial_account = banks_client.get_account(recipient_usdc).await.unwrap().unwrap();
+    let initial_account = banks_client
+        .get_account(recipient_usdc)
+        .await
+        .unwrap()
+        .unwrap();
     let initial_token: TokenAccount = TokenAccount::unpack(&initial_account.data).unwrap();
 
     // Claim
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let claim_instruction = Instruction::new_with_borsh(
         program_id(),
-        &MailerInstruction::ClaimRecipientShare,
+        &MailerInstruction::ClaimRecipientShare {
+            amount: None,
+            split: None,
+            close_to: None,
+        },
         vec![
             AccountMeta::new(recipient.pubkey(), true),
             AccountMeta::new(recipient_claim_pda, false),
@@ -7375,7 +9911,11 @@ async fn test_claim_recipient_share_transfers_correct_amount() {
     banks_client.process_transaction(transaction).await.unwrap();
 
     // Verify amount transferred
-    let final_account = banks_client.get_account(recipient_usdc).await.unwrap().unwrap();
+    let final_account = banks_client
+        .get_account(recipient_usdc)
+        .await
+        .unwrap()
+        .unwrap();
     let final_token: TokenAccount = TokenAccount::unpack(&final_account.data).unwrap();
 
     assert_eq!(final_token.amount - initial_token.amount, 90_000);
@@ -7407,25 +9947,64 @@ async fn test_claim_owner_share_transfers_correct_amount() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
-    let owner_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 1_000_000).await;
-
-    let recipient = Keypair::new();
-    let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
-
-    // Send message to accumulate owner fees
-    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
-    let send_instruction = Instruction::new_with_borsh(
-        program_id(),
-        &MailerInstruction::Send {
-            to: recipient.pubkey(),
-            subject: "Test".to_string(),
-            _body: "Body".to_string(),
-            revenue_share_to_receiver: false,
-            resolve_sender_to_name: false,
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    let owner_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let recipient = Keypair::new();
+    let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
+
+    // Send message to accumulate owner fees
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let send_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Send {
+            to: recipient.pubkey(),
+            subject: "Test".to_string(),
+            _body: "Body".to_string(),
+            revenue_share_to_receiver: false,
+            resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -7435,6 +10014,9 @@ async fn test_claim_owner_share_transfers_correct_amount() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -7501,10 +10083,32 @@ async fn test_custom_fee_percentage_applies_to_standard_send() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 10_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
 
     // Set custom fee percentage to 50%
     let (custom_fee_pda, _) = get_fee_discount_pda(&payer.pubkey());
@@ -7518,7 +10122,7 @@ async fn test_custom_fee_percentage_applies_to_standard_send() {
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
-            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(mailer_pda, false),
             AccountMeta::new(custom_fee_pda, false),
             AccountMeta::new_readonly(payer.pubkey(), false),
             AccountMeta::new(payer.pubkey(), true),
@@ -7526,7 +10130,8 @@ async fn test_custom_fee_percentage_applies_to_standard_send() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
@@ -7543,6 +10148,16 @@ async fn test_custom_fee_percentage_applies_to_standard_send() {
             _body: "Body".to_string(),
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -7552,7 +10167,10 @@ async fn test_custom_fee_percentage_applies_to_standard_send() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
             AccountMeta::new_readonly(custom_fee_pda, false),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -7563,7 +10181,8 @@ async fn test_custom_fee_percentage_applies_to_standard_send() {
     // Verify owner fee is 10% of 50% of send_fee
     // 50% of 100,000 = 50,000, then 10% of that = 5,000
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
 
     assert_eq!(mailer_state.owner_claimable, 5_000);
 }
@@ -7594,8 +10213,22 @@ async fn test_custom_fee_percentage_no_charge_when_zero() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     // Don't mint any USDC - if fees were charged, this would fail
 
@@ -7611,7 +10244,7 @@ async fn test_custom_fee_percentage_no_charge_when_zero() {
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
-            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(mailer_pda, false),
             AccountMeta::new(custom_fee_pda, false),
             AccountMeta::new_readonly(payer.pubkey(), false),
             AccountMeta::new(payer.pubkey(), true),
@@ -7619,7 +10252,8 @@ async fn test_custom_fee_percentage_no_charge_when_zero() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
@@ -7636,6 +10270,16 @@ async fn test_custom_fee_percentage_no_charge_when_zero() {
             _body: "Body".to_string(),
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -7645,6 +10289,9 @@ async fn test_custom_fee_percentage_no_charge_when_zero() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -7656,13 +10303,14 @@ async fn test_custom_fee_percentage_no_charge_when_zero() {
 
     // Verify no fees were collected
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
 
     assert_eq!(mailer_state.owner_claimable, 0);
 }
 
 // ============================================================================
-// Final Batch - Comprehensive Coverage Tests  
+// Final Batch - Comprehensive Coverage Tests
 // ============================================================================
 
 #[tokio::test]
@@ -7691,10 +10339,32 @@ async fn test_send_to_email_with_empty_subject_and_body() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 1_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
 
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let send_instruction = Instruction::new_with_borsh(
@@ -7703,6 +10373,7 @@ async fn test_send_to_email_with_empty_subject_and_body() {
             to_email: "test@example.com".to_string(),
             subject: "".to_string(),
             _body: "".to_string(),
+            strict: false,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -7710,6 +10381,8 @@ async fn test_send_to_email_with_empty_subject_and_body() {
             AccountMeta::new(sender_usdc, false),
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
         ],
     );
 
@@ -7744,10 +10417,32 @@ async fn test_send_to_email_with_long_subject_and_body() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 1_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
 
     let long_subject = "S".repeat(300);
     let long_body = "B".repeat(2000);
@@ -7759,6 +10454,7 @@ async fn test_send_to_email_with_long_subject_and_body() {
             to_email: "test@example.com".to_string(),
             subject: long_subject,
             _body: long_body,
+            strict: false,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -7766,6 +10462,8 @@ async fn test_send_to_email_with_long_subject_and_body() {
             AccountMeta::new(sender_usdc, false),
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
         ],
     );
 
@@ -7800,10 +10498,32 @@ async fn test_send_prepared_to_email_with_empty_mail_id() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 1_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
 
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let send_instruction = Instruction::new_with_borsh(
@@ -7811,6 +10531,7 @@ async fn test_send_prepared_to_email_with_empty_mail_id() {
         &MailerInstruction::SendPreparedToEmail {
             to_email: "test@example.com".to_string(),
             mail_id: "".to_string(),
+            strict: false,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -7818,6 +10539,8 @@ async fn test_send_prepared_to_email_with_empty_mail_id() {
             AccountMeta::new(sender_usdc, false),
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
         ],
     );
 
@@ -7852,10 +10575,32 @@ async fn test_send_prepared_to_email_with_long_mail_id() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 1_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
 
     let long_mail_id = "M".repeat(200);
 
@@ -7865,6 +10610,7 @@ async fn test_send_prepared_to_email_with_long_mail_id() {
         &MailerInstruction::SendPreparedToEmail {
             to_email: "test@example.com".to_string(),
             mail_id: long_mail_id,
+            strict: false,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -7872,6 +10618,8 @@ async fn test_send_prepared_to_email_with_long_mail_id() {
             AccountMeta::new(sender_usdc, false),
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
         ],
     );
 
@@ -7906,10 +10654,32 @@ async fn test_send_prepared_to_email_with_special_characters() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 1_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
 
     let special_mail_id = "mail-!@#$%^&*()_+-=[]{}|;':\",./<>?".to_string();
 
@@ -7919,6 +10689,7 @@ async fn test_send_prepared_to_email_with_special_characters() {
         &MailerInstruction::SendPreparedToEmail {
             to_email: "test@example.com".to_string(),
             mail_id: special_mail_id,
+            strict: false,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -7926,6 +10697,8 @@ async fn test_send_prepared_to_email_with_special_characters() {
             AccountMeta::new(sender_usdc, false),
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
         ],
     );
 
@@ -7960,10 +10733,32 @@ async fn test_send_prepared_to_email_with_complex_email_formats() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 10_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
 
     let emails = vec![
         "user+tag@example.com",
@@ -7979,6 +10774,7 @@ async fn test_send_prepared_to_email_with_complex_email_formats() {
             &MailerInstruction::SendPreparedToEmail {
                 to_email: email.to_string(),
                 mail_id: "mail-001".to_string(),
+                strict: false,
             },
             vec![
                 AccountMeta::new(payer.pubkey(), true),
@@ -7986,10 +10782,13 @@ async fn test_send_prepared_to_email_with_complex_email_formats() {
                 AccountMeta::new(sender_usdc, false),
                 AccountMeta::new(mailer_usdc, false),
                 AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+                AccountMeta::new_readonly(system_program::id(), false),
             ],
         );
 
-        let mut transaction = Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
+        let mut transaction =
+            Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
         transaction.sign(&[&payer], recent_blockhash);
         banks_client.process_transaction(transaction).await.unwrap();
     }
@@ -8021,10 +10820,32 @@ async fn test_claim_accumulates_from_multiple_priority_sends() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 10_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
 
     let recipient = Keypair::new();
     let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
@@ -8040,6 +10861,16 @@ async fn test_claim_accumulates_from_multiple_priority_sends() {
                 _body: "Body".to_string(),
                 revenue_share_to_receiver: true,
                 resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
             },
             vec![
                 AccountMeta::new(payer.pubkey(), true),
@@ -8049,17 +10880,26 @@ async fn test_claim_accumulates_from_multiple_priority_sends() {
                 AccountMeta::new(mailer_usdc, false),
                 AccountMeta::new_readonly(spl_token::id(), false),
                 AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
             ],
         );
 
-        let mut transaction = Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
+        let mut transaction =
+            Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
         transaction.sign(&[&payer], recent_blockhash);
         banks_client.process_transaction(transaction).await.unwrap();
     }
 
     // Verify accumulated claim
-    let claim_account = banks_client.get_account(recipient_claim_pda).await.unwrap().unwrap();
-    let recipient_claim: RecipientClaim = BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    let claim_account = banks_client
+        .get_account(recipient_claim_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let recipient_claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
 
     assert_eq!(recipient_claim.amount, 270_000); // 3 * 90,000
 }
@@ -8090,10 +10930,32 @@ async fn test_owner_accumulates_from_multiple_standard_sends() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 10_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
 
     // Send 4 standard messages
     for i in 0..4 {
@@ -8109,6 +10971,16 @@ async fn test_owner_accumulates_from_multiple_standard_sends() {
                 _body: "Body".to_string(),
                 revenue_share_to_receiver: false,
                 resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
             },
             vec![
                 AccountMeta::new(payer.pubkey(), true),
@@ -8118,16 +10990,21 @@ async fn test_owner_accumulates_from_multiple_standard_sends() {
                 AccountMeta::new(mailer_usdc, false),
                 AccountMeta::new_readonly(spl_token::id(), false),
                 AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
             ],
         );
 
-        let mut transaction = Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
+        let mut transaction =
+            Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
         transaction.sign(&[&payer], recent_blockhash);
         banks_client.process_transaction(transaction).await.unwrap();
     }
 
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
 
     assert_eq!(mailer_state.owner_claimable, 40_000); // 4 * 10,000
 }
@@ -8158,10 +11035,32 @@ async fn test_mixed_priority_and_standard_sends_accumulate_correctly() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 10_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
 
     let recipient = Keypair::new();
     let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
@@ -8177,6 +11076,16 @@ async fn test_mixed_priority_and_standard_sends_accumulate_correctly() {
                 _body: "Body".to_string(),
                 revenue_share_to_receiver: true,
                 resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
             },
             vec![
                 AccountMeta::new(payer.pubkey(), true),
@@ -8186,10 +11095,14 @@ async fn test_mixed_priority_and_standard_sends_accumulate_correctly() {
                 AccountMeta::new(mailer_usdc, false),
                 AccountMeta::new_readonly(spl_token::id(), false),
                 AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
             ],
         );
 
-        let mut transaction = Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
+        let mut transaction =
+            Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
         transaction.sign(&[&payer], recent_blockhash);
         banks_client.process_transaction(transaction).await.unwrap();
     }
@@ -8205,6 +11118,16 @@ async fn test_mixed_priority_and_standard_sends_accumulate_correctly() {
                 _body: "Body".to_string(),
                 revenue_share_to_receiver: false,
                 resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
             },
             vec![
                 AccountMeta::new(payer.pubkey(), true),
@@ -8214,22 +11137,32 @@ async fn test_mixed_priority_and_standard_sends_accumulate_correctly() {
                 AccountMeta::new(mailer_usdc, false),
                 AccountMeta::new_readonly(spl_token::id(), false),
                 AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
             ],
         );
 
-        let mut transaction = Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
+        let mut transaction =
+            Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
         transaction.sign(&[&payer], recent_blockhash);
         banks_client.process_transaction(transaction).await.unwrap();
     }
 
     // Verify recipient claim: 2 * 90,000 = 180,000
-    let claim_account = banks_client.get_account(recipient_claim_pda).await.unwrap().unwrap();
-    let recipient_claim: RecipientClaim = BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    let claim_account = banks_client
+        .get_account(recipient_claim_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let recipient_claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
     assert_eq!(recipient_claim.amount, 180_000);
 
     // Verify owner claimable: (2 * 10,000) + (3 * 10,000) = 50,000
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.owner_claimable, 50_000);
 }
 
@@ -8259,8 +11192,22 @@ async fn test_delegation_with_insufficient_balance_fails() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     // Don't mint any USDC
 
@@ -8281,10 +11228,12 @@ async fn test_delegation_with_insufficient_balance_fails() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(get_delegate_index_pda(&delegate.pubkey()).0, false),
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[delegate_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[delegate_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
 
     let result = banks_client.process_transaction(transaction).await;
@@ -8317,10 +11266,32 @@ async fn test_reject_delegation_from_non_delegate_fails() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 20_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        20_000_000,
+    )
+    .await;
 
     let delegate = Keypair::new();
     let (delegation_pda, _) = get_delegation_pda(&payer.pubkey());
@@ -8340,10 +11311,12 @@ async fn test_reject_delegation_from_non_delegate_fails() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(get_delegate_index_pda(&delegate.pubkey()).0, false),
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[delegate_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[delegate_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
@@ -8357,6 +11330,7 @@ async fn test_reject_delegation_from_non_delegate_fails() {
             AccountMeta::new(wrong_delegate.pubkey(), true),
             AccountMeta::new(delegation_pda, false),
             AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(get_delegate_index_pda(&wrong_delegate.pubkey()).0, false),
         ],
     );
 
@@ -8393,8 +11367,22 @@ async fn test_pause_and_unpause_cycle() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let owner_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let owner_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     // Pause
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
@@ -8416,7 +11404,8 @@ async fn test_pause_and_unpause_cycle() {
 
     // Verify paused
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert!(mailer_state.paused);
 
     // Unpause
@@ -8430,13 +11419,15 @@ async fn test_pause_and_unpause_cycle() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[unpause_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[unpause_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
     // Verify unpaused
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert!(!mailer_state.paused);
 }
 
@@ -8466,8 +11457,22 @@ async fn test_emergency_unpause_by_owner() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let owner_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let owner_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     // Pause
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
@@ -8498,13 +11503,15 @@ async fn test_emergency_unpause_by_owner() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[emergency_unpause_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[emergency_unpause_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
     // Verify unpaused
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert!(!mailer_state.paused);
 }
 
@@ -8534,8 +11541,22 @@ async fn test_emergency_unpause_by_non_owner_fails() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let owner_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let owner_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     // Pause
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
@@ -8567,7 +11588,8 @@ async fn test_emergency_unpause_by_non_owner_fails() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[emergency_unpause_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[emergency_unpause_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer, &non_owner], recent_blockhash);
 
     let result = banks_client.process_transaction(transaction).await;
@@ -8600,8 +11622,22 @@ async fn test_set_custom_fee_percentage_when_paused_fails() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let owner_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let owner_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     // Pause
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
@@ -8640,7 +11676,8 @@ async fn test_set_custom_fee_percentage_when_paused_fails() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
 
     let result = banks_client.process_transaction(transaction).await;
@@ -8686,7 +11723,7 @@ async fn test_clear_custom_fee_percentage_when_paused_fails() {
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
-            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(mailer_pda, false),
             AccountMeta::new(custom_fee_pda, false),
             AccountMeta::new_readonly(test_user.pubkey(), false),
             AccountMeta::new(payer.pubkey(), true),
@@ -8694,12 +11731,27 @@ async fn test_clear_custom_fee_percentage_when_paused_fails() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let owner_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let owner_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     // Pause
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
@@ -8771,14 +11823,18 @@ async fn test_delegation_fee_update_by_non_owner_fails() {
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let set_fee_instruction = Instruction::new_with_borsh(
         program_id(),
-        &MailerInstruction::SetDelegationFee { new_fee: 20_000_000 },
+        &MailerInstruction::SetDelegationFee {
+            new_fee: 20_000_000,
+            expected_current_value: None,
+        },
         vec![
             AccountMeta::new(non_owner.pubkey(), true),
             AccountMeta::new(mailer_pda, false),
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer, &non_owner], recent_blockhash);
 
     let result = banks_client.process_transaction(transaction).await;
@@ -8815,14 +11871,18 @@ async fn test_send_fee_update_by_non_owner_fails() {
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let set_fee_instruction = Instruction::new_with_borsh(
         program_id(),
-        &MailerInstruction::SetFee { new_fee: 200_000 },
+        &MailerInstruction::SetFee {
+            new_fee: 200_000,
+            expected_current_value: None,
+        },
         vec![
             AccountMeta::new(non_owner.pubkey(), true),
             AccountMeta::new(mailer_pda, false),
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer, &non_owner], recent_blockhash);
 
     let result = banks_client.process_transaction(transaction).await;
@@ -8859,13 +11919,35 @@ async fn test_send_with_different_recipients_accumulates_owner_fees() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
-
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 10_000_000).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    // Send to 10 different recipients
-    for i in 0..10 {
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
+
+    // Send to 10 different recipients
+    for i in 0..10 {
         let recipient = Keypair::new();
         let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
 
@@ -8878,6 +11960,16 @@ async fn test_send_with_different_recipients_accumulates_owner_fees() {
                 _body: "Test".to_string(),
                 revenue_share_to_receiver: false,
                 resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
             },
             vec![
                 AccountMeta::new(payer.pubkey(), true),
@@ -8887,16 +11979,21 @@ async fn test_send_with_different_recipients_accumulates_owner_fees() {
                 AccountMeta::new(mailer_usdc, false),
                 AccountMeta::new_readonly(spl_token::id(), false),
                 AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
             ],
         );
 
-        let mut transaction = Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
+        let mut transaction =
+            Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
         transaction.sign(&[&payer], recent_blockhash);
         banks_client.process_transaction(transaction).await.unwrap();
     }
 
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
 
     assert_eq!(mailer_state.owner_claimable, 100_000); // 10 * 10,000
 }
@@ -8927,15 +12024,39 @@ async fn test_webhook_standard_mode_charges_owner_fee_only() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 1_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
 
     let recipient = Keypair::new();
     let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
 
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let webhook_pda =
+        register_webhook(&mut banks_client, &payer, recent_blockhash, "webhook-std").await;
     let send_instruction = Instruction::new_with_borsh(
         program_id(),
         &MailerInstruction::SendThroughWebhook {
@@ -8943,6 +12064,11 @@ async fn test_webhook_standard_mode_charges_owner_fee_only() {
             webhook_id: "webhook-std".to_string(),
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -8952,6 +12078,10 @@ async fn test_webhook_standard_mode_charges_owner_fee_only() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(webhook_pda, false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -8960,7 +12090,8 @@ async fn test_webhook_standard_mode_charges_owner_fee_only() {
     banks_client.process_transaction(transaction).await.unwrap();
 
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
 
     assert_eq!(mailer_state.owner_claimable, 10_000);
 }
@@ -8995,19 +12126,37 @@ async fn test_send_prepared_priority_with_zero_fee() {
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let set_fee_instruction = Instruction::new_with_borsh(
         program_id(),
-        &MailerInstruction::SetFee { new_fee: 0 },
+        &MailerInstruction::SetFee {
+            new_fee: 0,
+            expected_current_value: None,
+        },
         vec![
             AccountMeta::new(payer.pubkey(), true),
             AccountMeta::new(mailer_pda, false),
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
     // Don't mint any USDC - should still work with zero fee
 
@@ -9022,6 +12171,11 @@ async fn test_send_prepared_priority_with_zero_fee() {
             mail_id: "mail-zero".to_string(),
             revenue_share_to_receiver: true,
             resolve_sender_to_name: false,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -9031,6 +12185,9 @@ async fn test_send_prepared_priority_with_zero_fee() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -9065,10 +12222,32 @@ async fn test_send_to_email_standard_mode_charges_owner_fee_only() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 1_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
 
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let send_instruction = Instruction::new_with_borsh(
@@ -9077,6 +12256,7 @@ async fn test_send_to_email_standard_mode_charges_owner_fee_only() {
             to_email: "user@example.com".to_string(),
             subject: "Test".to_string(),
             _body: "Body".to_string(),
+            strict: false,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -9084,6 +12264,8 @@ async fn test_send_to_email_standard_mode_charges_owner_fee_only() {
             AccountMeta::new(sender_usdc, false),
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
         ],
     );
 
@@ -9092,7 +12274,8 @@ async fn test_send_to_email_standard_mode_charges_owner_fee_only() {
     banks_client.process_transaction(transaction).await.unwrap();
 
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
 
     assert_eq!(mailer_state.owner_claimable, 10_000);
 }
@@ -9123,10 +12306,32 @@ async fn test_send_prepared_to_email_standard_mode_charges_owner_fee_only() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 1_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
 
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let send_instruction = Instruction::new_with_borsh(
@@ -9134,6 +12339,7 @@ async fn test_send_prepared_to_email_standard_mode_charges_owner_fee_only() {
         &MailerInstruction::SendPreparedToEmail {
             to_email: "user@example.com".to_string(),
             mail_id: "mail-email".to_string(),
+            strict: false,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -9141,6 +12347,8 @@ async fn test_send_prepared_to_email_standard_mode_charges_owner_fee_only() {
             AccountMeta::new(sender_usdc, false),
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
         ],
     );
 
@@ -9149,7 +12357,8 @@ async fn test_send_prepared_to_email_standard_mode_charges_owner_fee_only() {
     banks_client.process_transaction(transaction).await.unwrap();
 
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
 
     assert_eq!(mailer_state.owner_claimable, 10_000);
 }
@@ -9180,10 +12389,32 @@ async fn test_custom_fee_splits_revenue_correctly_in_priority_mode() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 10_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
 
     // Set custom fee percentage to 25%
     let (custom_fee_pda, _) = get_fee_discount_pda(&payer.pubkey());
@@ -9197,7 +12428,7 @@ async fn test_custom_fee_splits_revenue_correctly_in_priority_mode() {
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
-            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(mailer_pda, false),
             AccountMeta::new(custom_fee_pda, false),
             AccountMeta::new_readonly(payer.pubkey(), false),
             AccountMeta::new(payer.pubkey(), true),
@@ -9205,7 +12436,8 @@ async fn test_custom_fee_splits_revenue_correctly_in_priority_mode() {
         ],
     );
 
-    let mut transaction = Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
+    let mut transaction =
+        Transaction::new_with_payer(&[set_percentage_instruction], Some(&payer.pubkey()));
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
@@ -9222,6 +12454,16 @@ async fn test_custom_fee_splits_revenue_correctly_in_priority_mode() {
             _body: "Test".to_string(),
             revenue_share_to_receiver: true,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -9231,7 +12473,10 @@ async fn test_custom_fee_splits_revenue_correctly_in_priority_mode() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
             AccountMeta::new_readonly(custom_fee_pda, false),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -9242,12 +12487,18 @@ async fn test_custom_fee_splits_revenue_correctly_in_priority_mode() {
     // 25% of 100,000 = 25,000 total charged
     // Recipient gets 90% of 25,000 = 22,500
     // Owner gets 10% of 25,000 = 2,500
-    let claim_account = banks_client.get_account(recipient_claim_pda).await.unwrap().unwrap();
-    let recipient_claim: RecipientClaim = BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    let claim_account = banks_client
+        .get_account(recipient_claim_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let recipient_claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
     assert_eq!(recipient_claim.amount, 22_500);
 
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
     assert_eq!(mailer_state.owner_claimable, 2_500);
 }
 
@@ -9277,40 +12528,71 @@ async fn test_multiple_delegations_accumulate_owner_fees() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 100_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        100_000_000,
+    )
+    .await;
 
-    // Delegate 3 times
+    // Delegate 3 times, each to a new delegate
+    let mut previous_delegate: Option<Pubkey> = None;
     for _i in 0..3 {
         let delegate = Keypair::new();
         let (delegation_pda, _) = get_delegation_pda(&payer.pubkey());
 
         let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+        let mut accounts = vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(delegation_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(get_delegate_index_pda(&delegate.pubkey()).0, false),
+        ];
+        if let Some(previous) = previous_delegate {
+            accounts.push(AccountMeta::new(get_delegate_index_pda(&previous).0, false));
+        }
         let delegate_instruction = Instruction::new_with_borsh(
             program_id(),
             &MailerInstruction::DelegateTo {
                 delegate: Some(delegate.pubkey()),
             },
-            vec![
-                AccountMeta::new(payer.pubkey(), true),
-                AccountMeta::new(delegation_pda, false),
-                AccountMeta::new(mailer_pda, false),
-                AccountMeta::new(sender_usdc, false),
-                AccountMeta::new(mailer_usdc, false),
-                AccountMeta::new_readonly(spl_token::id(), false),
-                AccountMeta::new_readonly(system_program::id(), false),
-            ],
+            accounts,
         );
 
-        let mut transaction = Transaction::new_with_payer(&[delegate_instruction], Some(&payer.pubkey()));
+        let mut transaction =
+            Transaction::new_with_payer(&[delegate_instruction], Some(&payer.pubkey()));
         transaction.sign(&[&payer], recent_blockhash);
         banks_client.process_transaction(transaction).await.unwrap();
+        previous_delegate = Some(delegate.pubkey());
     }
 
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
 
     assert_eq!(mailer_state.owner_claimable, 30_000_000); // 3 * 10,000,000
 }
@@ -9341,11 +12623,40 @@ async fn test_claim_owner_share_resets_owner_claimable_to_zero() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
-    let owner_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    let owner_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
 
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 1_000_000).await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
 
     let recipient = Keypair::new();
     let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
@@ -9360,6 +12671,16 @@ async fn test_claim_owner_share_resets_owner_claimable_to_zero() {
             _body: "Body".to_string(),
             revenue_share_to_receiver: false,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -9369,6 +12690,9 @@ async fn test_claim_owner_share_resets_owner_claimable_to_zero() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -9396,7 +12720,8 @@ async fn test_claim_owner_share_resets_owner_claimable_to_zero() {
 
     // Verify owner_claimable is now 0
     let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
-    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
 
     assert_eq!(mailer_state.owner_claimable, 0);
 }
@@ -9427,17 +12752,46 @@ async fn test_claim_recipient_share_clears_claim_amount() {
     transaction.sign(&[&payer], recent_blockhash);
     banks_client.process_transaction(transaction).await.unwrap();
 
-    let sender_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &payer.pubkey()).await;
-    let mailer_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &mailer_pda).await;
-
-    mint_to(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &sender_usdc, 1_000_000).await;
-
-    let recipient = Keypair::new();
-    let recipient_usdc = create_token_account(&mut banks_client, &payer, recent_blockhash, &usdc_mint, &recipient.pubkey()).await;
-    let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
 
-    // Send with revenue sharing
-    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let recipient = Keypair::new();
+    let recipient_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &recipient.pubkey(),
+    )
+    .await;
+    let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
+
+    // Send with revenue sharing
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let send_instruction = Instruction::new_with_borsh(
         program_id(),
         &MailerInstruction::Send {
@@ -9446,6 +12800,16 @@ async fn test_claim_recipient_share_clears_claim_amount() {
             _body: "Body".to_string(),
             revenue_share_to_receiver: true,
             resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
         },
         vec![
             AccountMeta::new(payer.pubkey(), true),
@@ -9455,6 +12819,9 @@ async fn test_claim_recipient_share_clears_claim_amount() {
             AccountMeta::new(mailer_usdc, false),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
         ],
     );
 
@@ -9466,7 +12833,11 @@ async fn test_claim_recipient_share_clears_claim_amount() {
     let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
     let claim_instruction = Instruction::new_with_borsh(
         program_id(),
-        &MailerInstruction::ClaimRecipientShare,
+        &MailerInstruction::ClaimRecipientShare {
+            amount: None,
+            split: None,
+            close_to: None,
+        },
         vec![
             AccountMeta::new(recipient.pubkey(), true),
             AccountMeta::new(recipient_claim_pda, false),
@@ -9482,8 +12853,18737 @@ async fn test_claim_recipient_share_clears_claim_amount() {
     banks_client.process_transaction(transaction).await.unwrap();
 
     // Verify claim amount is now 0
-    let claim_account = banks_client.get_account(recipient_claim_pda).await.unwrap().unwrap();
-    let recipient_claim: RecipientClaim = BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    let claim_account = banks_client
+        .get_account(recipient_claim_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let recipient_claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
 
     assert_eq!(recipient_claim.amount, 0);
 }
+
+// ============================================================================
+// Read Receipt Negotiation Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_acknowledge_message_respects_read_receipt_opt_out() {
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let (prefs_pda, _) = get_prefs_pda(&payer.pubkey());
+
+    // Each acknowledgment names a fresh sender so transactions are distinct
+    let ack_instruction = || {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::AcknowledgeMessage {
+                sender: Keypair::new().pubkey(),
+                receipt_nonce: None,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(prefs_pda, false),
+            ],
+        )
+    };
+
+    // Without a prefs account, read receipts default to allowed
+    let mut transaction = Transaction::new_with_payer(&[ack_instruction()], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Opt out of read receipts
+    let set_prefs_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetRecipientPrefs {
+            read_receipts_opt_out: true,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(prefs_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[set_prefs_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let prefs_account = banks_client.get_account(prefs_pda).await.unwrap().unwrap();
+    let prefs: RecipientPrefs =
+        BorshDeserialize::deserialize(&mut &prefs_account.data[8..]).unwrap();
+    assert_eq!(prefs.recipient, payer.pubkey());
+    assert!(prefs.read_receipts_opt_out);
+
+    // Acknowledgment must now be refused
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(&[ack_instruction()], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+
+    // Opting back in re-enables acknowledgments
+    let set_prefs_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetRecipientPrefs {
+            read_receipts_opt_out: false,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(prefs_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(
+        &[set_prefs_instruction, ack_instruction()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_send_read_receipt_request_declined_when_recipient_opted_out() {
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    // Recipient opts out of read receipts
+    let recipient = Keypair::new();
+    let (prefs_pda, _) = get_prefs_pda(&recipient.pubkey());
+    let set_prefs_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetRecipientPrefs {
+            read_receipts_opt_out: true,
+        },
+        vec![
+            AccountMeta::new(recipient.pubkey(), true),
+            AccountMeta::new(prefs_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let fund_recipient =
+        solana_sdk::system_instruction::transfer(&payer.pubkey(), &recipient.pubkey(), 10_000_000);
+    let mut transaction = Transaction::new_with_payer(
+        &[fund_recipient, set_prefs_instruction],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
+    let send_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Send {
+            to: recipient.pubkey(),
+            subject: "Receipt please".to_string(),
+            _body: "Body".to_string(),
+            revenue_share_to_receiver: false,
+            resolve_sender_to_name: false,
+            request_read_receipt: true,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(recipient_claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            // Include recipient prefs PDA for read receipt negotiation
+            AccountMeta::new_readonly(prefs_pda, false),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ],
+    );
+
+    let mut transaction = Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert!(result.result.is_ok());
+
+    let logs = result.metadata.unwrap().log_messages;
+    assert!(logs
+        .iter()
+        .any(|log| log.contains("Read receipt requested") && log.contains("declined")));
+}
+
+// ============================================================================
+// Protocol-Origin Send Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_send_protocol_message_is_fee_exempt_and_flag_gated() {
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let recipient = Keypair::new();
+    let protocol_send = |subject: &str| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SendProtocolMessage {
+                to: recipient.pubkey(),
+                subject: subject.to_string(),
+                _body: "Your claim expires soon".to_string(),
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(mailer_pda, false),
+                AccountMeta::new(get_message_nonce_pda(&mailer_pda).0, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    };
+
+    // Disabled by default
+    let mut transaction =
+        Transaction::new_with_payer(&[protocol_send("Reminder 1")], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+
+    // Enable protocol sends
+    let enable_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetProtocolSendsEnabled { enabled: true },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[enable_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mut transaction =
+        Transaction::new_with_payer(&[protocol_send("Reminder 2")], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert!(result.result.is_ok());
+    let logs = result.metadata.unwrap().log_messages;
+    assert!(logs
+        .iter()
+        .any(|log| log.contains("Protocol mail sent") && log.contains("protocol origin")));
+
+    // No fee is credited back to the owner
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert!(mailer_state.protocol_sends_enabled);
+    assert_eq!(mailer_state.owner_claimable, 0);
+}
+
+#[tokio::test]
+async fn test_send_protocol_message_by_non_owner_fails() {
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let enable_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetProtocolSendsEnabled { enabled: true },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+        ],
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[init_instruction, enable_instruction],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let attacker = Keypair::new();
+    let protocol_send = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SendProtocolMessage {
+            to: payer.pubkey(),
+            subject: "Spoofed".to_string(),
+            _body: "Body".to_string(),
+        },
+        vec![
+            AccountMeta::new(attacker.pubkey(), true),
+            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(get_message_nonce_pda(&mailer_pda).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+
+    let mut transaction = Transaction::new_with_payer(&[protocol_send], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &attacker], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+}
+
+// ============================================================================
+// Ownership Transfer Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_two_step_ownership_transfer() {
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let new_owner = Keypair::new();
+    let transfer_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::TransferOwnership {
+            new_owner: new_owner.pubkey(),
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+        ],
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[transfer_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Proposal alone does not move ownership
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.owner, payer.pubkey());
+    assert_eq!(mailer_state.pending_owner, Some(new_owner.pubkey()));
+
+    // A third party cannot accept
+    let intruder = Keypair::new();
+    let intruder_accept = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::AcceptOwnership,
+        vec![
+            AccountMeta::new(intruder.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[intruder_accept], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &intruder], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+
+    // The pending owner accepts
+    let accept_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::AcceptOwnership,
+        vec![
+            AccountMeta::new(new_owner.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[accept_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &new_owner], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.owner, new_owner.pubkey());
+    assert_eq!(mailer_state.pending_owner, None);
+
+    // The previous owner has lost owner-only access
+    let set_fee_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetFee {
+            new_fee: 1,
+            expected_current_value: None,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+        ],
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[set_fee_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+}
+
+// ============================================================================
+// Threshold Admin Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_admin_quorum_can_perform_owner_actions() {
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+    let (admins_pda, _) = get_admins_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Configure a 2-of-3 admin set
+    let admin_a = Keypair::new();
+    let admin_b = Keypair::new();
+    let admin_c = Keypair::new();
+    let set_admins_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetAdmins {
+            admins: vec![admin_a.pubkey(), admin_b.pubkey(), admin_c.pubkey()],
+            threshold: 2,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(admins_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[set_admins_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // A single admin signature is not enough
+    let single_admin_set_fee = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetFee {
+            new_fee: 123,
+            expected_current_value: None,
+        },
+        vec![
+            AccountMeta::new(admin_a.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(admins_pda, false),
+        ],
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[single_admin_set_fee], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &admin_a], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+
+    // Two admin signatures meet the threshold
+    let quorum_set_fee = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetFee {
+            new_fee: 456,
+            expected_current_value: None,
+        },
+        vec![
+            AccountMeta::new(admin_a.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(admins_pda, false),
+            AccountMeta::new_readonly(admin_c.pubkey(), true),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[quorum_set_fee], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &admin_a, &admin_c], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.send_fee, 456);
+
+    // The legacy owner keeps working on its own
+    let owner_set_fee = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetFee {
+            new_fee: 789,
+            expected_current_value: None,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[owner_set_fee], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.send_fee, 789);
+}
+
+#[tokio::test]
+async fn test_set_admins_rejects_invalid_configuration() {
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+    let (admins_pda, _) = get_admins_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let admin = Keypair::new().pubkey();
+    let invalid_configs = vec![
+        (vec![admin], 2),        // threshold above admin count
+        (vec![admin], 0),        // zero threshold with admins
+        (vec![admin, admin], 1), // duplicate admins
+    ];
+
+    for (admins, threshold) in invalid_configs {
+        let set_admins_instruction = Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SetAdmins { admins, threshold },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(mailer_pda, false),
+                AccountMeta::new(admins_pda, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        let mut transaction =
+            Transaction::new_with_payer(&[set_admins_instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer], recent_blockhash);
+        assert!(banks_client.process_transaction(transaction).await.is_err());
+    }
+}
+
+// ============================================================================
+// Sweep Cursor Tests
+// ============================================================================
+
+fn get_sweep_cursor_pda() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"sweep_cursor", &[1]], &program_id())
+}
+
+#[tokio::test]
+async fn test_sweep_expired_page_advances_cursor() {
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let mut recent_blockhash = context.last_blockhash;
+
+    let usdc_mint =
+        create_usdc_mint(&mut context.banks_client, &context.payer, recent_blockhash).await;
+    recent_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+
+    let (mailer_pda, _) = get_mailer_pda();
+    let (cursor_pda, _) = get_sweep_cursor_pda();
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(context.payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[init_instruction], Some(&context.payer.pubkey()));
+    transaction.sign(&[&context.payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut context.banks_client,
+        &context.payer,
+        recent_blockhash,
+        &usdc_mint,
+        &context.payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut context.banks_client,
+        &context.payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut context.banks_client,
+        &context.payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    // Priority messages to two recipients create two claims of 90,000 each
+    let recipients = [Keypair::new().pubkey(), Keypair::new().pubkey()];
+    for recipient in recipients.iter() {
+        let (claim_pda, _) = get_claim_pda(recipient);
+        let send_instruction = Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: *recipient,
+                subject: "Sweep".to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: true,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            vec![
+                AccountMeta::new(context.payer.pubkey(), true),
+                AccountMeta::new(claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(context.payer.pubkey(), true),
+                AccountMeta::new_readonly(get_policy_pda(recipient).0, false),
+                AccountMeta::new(get_message_nonce_pda(&context.payer.pubkey()).0, false),
+            ],
+        );
+        let mut transaction =
+            Transaction::new_with_payer(&[send_instruction], Some(&context.payer.pubkey()));
+        transaction.sign(&[&context.payer], recent_blockhash);
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap();
+    }
+
+    use solana_sdk::clock::Clock;
+    let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    clock.unix_timestamp += 60 * 24 * 60 * 60 + 1; // 60 days + 1 second
+    context.set_sysvar(&clock);
+    recent_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+
+    // Crankers supply claims in ascending key order
+    let mut claim_pdas: Vec<Pubkey> = recipients.iter().map(|r| get_claim_pda(r).0).collect();
+    claim_pdas.sort_by_key(|k| k.to_bytes());
+
+    let sweep_page =
+        |max_items: u8, claims: &[Pubkey]| {
+            let mut accounts = vec![
+                AccountMeta::new(context.payer.pubkey(), true),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(cursor_pda, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ];
+            accounts.extend(claims.iter().map(|claim| AccountMeta::new(*claim, false)));
+            accounts.extend(recipients.iter().map(|recipient| {
+                AccountMeta::new_readonly(get_beneficiary_pda(recipient).0, false)
+            }));
+            Instruction::new_with_borsh(
+                program_id(),
+                &MailerInstruction::SweepExpiredPage { max_items },
+                accounts,
+            )
+        };
+
+    // First page processes only one claim even though both are supplied
+    let mut transaction =
+        Transaction::new_with_payer(&[sweep_page(1, &claim_pdas)], Some(&context.payer.pubkey()));
+    transaction.sign(&[&context.payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let cursor_account = context
+        .banks_client
+        .get_account(cursor_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let cursor: SweepCursor =
+        BorshDeserialize::deserialize(&mut &cursor_account.data[8..]).unwrap();
+    assert_eq!(cursor.last_processed, claim_pdas[0]);
+    assert_eq!(cursor.processed_total, 1);
+    assert_eq!(cursor.swept_total, 90_000);
+
+    // Replaying a key at or before the cursor is rejected
+    let mut transaction =
+        Transaction::new_with_payer(&[sweep_page(5, &claim_pdas)], Some(&context.payer.pubkey()));
+    transaction.sign(&[&context.payer], recent_blockhash);
+    assert!(context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_err());
+
+    // Second page resumes after the cursor
+    let mut transaction = Transaction::new_with_payer(
+        &[sweep_page(5, &claim_pdas[1..])],
+        Some(&context.payer.pubkey()),
+    );
+    transaction.sign(&[&context.payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let cursor_account = context
+        .banks_client
+        .get_account(cursor_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let cursor: SweepCursor =
+        BorshDeserialize::deserialize(&mut &cursor_account.data[8..]).unwrap();
+    assert_eq!(cursor.last_processed, claim_pdas[1]);
+    assert_eq!(cursor.processed_total, 2);
+    assert_eq!(cursor.swept_total, 180_000);
+
+    // Owner share (2 x 10,000) plus both expired claims
+    let mailer_account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.owner_claimable, 200_000);
+
+    // Resetting the cursor allows a new full sweep
+    let reset_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::ResetSweepCursor,
+        vec![
+            AccountMeta::new(context.payer.pubkey(), true),
+            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(cursor_pda, false),
+        ],
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[reset_instruction], Some(&context.payer.pubkey()));
+    transaction.sign(&[&context.payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let cursor_account = context
+        .banks_client
+        .get_account(cursor_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let cursor: SweepCursor =
+        BorshDeserialize::deserialize(&mut &cursor_account.data[8..]).unwrap();
+    assert_eq!(cursor.last_processed, Pubkey::default());
+    assert_eq!(cursor.processed_total, 2);
+}
+
+// ============================================================================
+// Test Vector Tests
+// ============================================================================
+
+#[test]
+fn test_vectors_match_fixture() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/test-vectors/vectors.json");
+    let generated = mailer::test_vectors::to_json();
+
+    if std::env::var("UPDATE_TEST_VECTORS").is_ok() {
+        std::fs::create_dir_all(concat!(env!("CARGO_MANIFEST_DIR"), "/test-vectors")).unwrap();
+        std::fs::write(path, &generated).unwrap();
+    }
+
+    let fixture = std::fs::read_to_string(path).expect("missing test-vectors/vectors.json");
+    assert_eq!(
+        fixture, generated,
+        "serialization changed; regenerate with UPDATE_TEST_VECTORS=1"
+    );
+}
+
+#[test]
+fn test_vectors_roundtrip() {
+    for vector in mailer::test_vectors::instruction_vectors() {
+        let decoded = MailerInstruction::try_from_slice(&vector.bytes).unwrap();
+        assert_eq!(format!("{:?}", decoded), vector.value, "{}", vector.name);
+    }
+
+    let accounts = mailer::test_vectors::account_vectors();
+    let state = accounts.iter().find(|v| v.name == "MailerState").unwrap();
+    assert_eq!(state.bytes.len(), 8 + MailerState::LEN);
+}
+
+#[test]
+fn test_conformance_matches_golden_file() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/test-vectors/conformance.json");
+    let generated = mailer::conformance::to_json();
+
+    if std::env::var("UPDATE_TEST_VECTORS").is_ok() {
+        std::fs::write(path, &generated).unwrap();
+    }
+
+    let fixture = std::fs::read_to_string(path).expect("missing test-vectors/conformance.json");
+    assert_eq!(
+        fixture, generated,
+        "seeds or layouts changed; regenerate with UPDATE_TEST_VECTORS=1"
+    );
+}
+
+#[test]
+fn test_conformance_pdas_match_derivation_helpers() {
+    use mailer::conformance::{input_key, EVM_ADDRESS, NONCE, PROMO_CODE};
+    use mailer::pda;
+
+    let id = program_id();
+    let recipient = input_key("recipient");
+    let sender = input_key("sender");
+    let expected = [
+        ("MailerState", pda::mailer_state(&id)),
+        ("RecipientClaim", pda::recipient_claim(&id, &recipient)),
+        ("Delegation", pda::delegation(&id, &input_key("delegator"))),
+        ("FeeDiscount", pda::fee_discount(&id, &input_key("account"))),
+        ("RecipientPrefs", pda::recipient_prefs(&id, &recipient)),
+        ("PayoutConfig", pda::payout_config(&id, &recipient)),
+        (
+            "ConversionVault",
+            pda::conversion_vault(&id, &input_key("mint")),
+        ),
+        (
+            "MessageReceipt",
+            pda::message_receipt(&id, &recipient, NONCE),
+        ),
+        (
+            "ClaimHistoryEntry",
+            pda::claim_history(&id, &recipient, &sender),
+        ),
+        ("InboxPolicy", pda::inbox_policy(&id, &recipient)),
+        (
+            "SessionKey",
+            pda::session_key(&id, &recipient, &input_key("session_key")),
+        ),
+        (
+            "Preauthorization",
+            pda::preauthorization(&id, &input_key("user"), &input_key("relayer")),
+        ),
+        ("AdminSet", pda::admin_set(&id)),
+        ("SweepCursor", pda::sweep_cursor(&id)),
+        ("RateLimit", pda::rate_limit(&id, &sender)),
+        (
+            "Promo",
+            pda::promo(
+                &id,
+                &solana_program::hash::hash(PROMO_CODE.as_bytes()).to_bytes(),
+            ),
+        ),
+        ("FeeMint", pda::fee_mint(&id, &input_key("mint"))),
+        (
+            "RecipientClaim",
+            pda::recipient_claim_in_mint(&id, &recipient, &input_key("mint")),
+        ),
+        ("LamportVault", pda::lamport_vault(&id)),
+        ("RecipientInbox", pda::recipient_inbox(&id, &recipient)),
+        (
+            "ClaimRentPayer",
+            pda::claim_rent_payer(&id, &input_key("claim")),
+        ),
+        (
+            "Webhook",
+            pda::webhook(
+                &id,
+                &solana_program::hash::hash(PROMO_CODE.as_bytes()).to_bytes(),
+            ),
+        ),
+        ("ClosureLog", pda::closure_log(&id)),
+        ("Sanctioned", pda::sanctioned(&id, &input_key("address"))),
+        (
+            "DelegateIndex",
+            pda::delegate_index(&id, &input_key("delegate")),
+        ),
+        ("MailerStats", pda::stats(&id)),
+        ("SendCredits", pda::send_credits(&id, &sender)),
+        ("Plan", pda::plan(&id, NONCE)),
+        ("Subscription", pda::subscription(&id, &sender)),
+        ("ClaimBeneficiary", pda::claim_beneficiary(&id, &recipient)),
+        ("Treasury", pda::treasury(&id)),
+        ("ClaimDistribution", pda::claim_distribution(&id, NONCE)),
+        ("Escrow", pda::escrow(&id, &sender, NONCE)),
+        (
+            "MailingList",
+            pda::mailing_list(&id, &input_key("owner"), NONCE),
+        ),
+        ("ListChunk", pda::list_chunk(&id, &input_key("list"), NONCE)),
+        ("EvmMapping", pda::evm_mapping(&id, &EVM_ADDRESS)),
+        ("WormholeConfig", pda::wormhole_config(&id)),
+        ("WormholeEmitter", pda::wormhole_emitter(&id)),
+        ("WormholeMessage", pda::wormhole_message(&id, NONCE)),
+        ("MessageNonce", pda::message_nonce(&id, &sender)),
+        ("SendNonce", pda::send_nonce(&id, &sender)),
+        ("DiscountSchedule", pda::discount_schedule(&id)),
+        ("SenderStats", pda::sender_stats(&id, &sender)),
+    ];
+
+    let vectors = mailer::conformance::pda_vectors();
+    assert_eq!(vectors.len(), expected.len());
+    for (vector, (account, (address, bump))) in vectors.iter().zip(expected) {
+        assert_eq!(vector.account, account);
+        assert_eq!(
+            (vector.address, vector.bump),
+            (address, bump),
+            "{}",
+            account
+        );
+    }
+}
+
+#[test]
+fn test_account_layouts_match_struct_sizes() {
+    let layouts = mailer::layout::accounts();
+    let samples = mailer::test_vectors::account_vectors();
+    assert_eq!(layouts.len(), samples.len());
+
+    for layout in &layouts {
+        assert_eq!(8 + layout.data_len(), layout.space, "{}", layout.name);
+        let sample = samples.iter().find(|v| v.name == layout.name).unwrap();
+        assert_eq!(&sample.bytes[..8], &layout.discriminator, "{}", layout.name);
+        assert!(sample.bytes.len() <= layout.space, "{}", layout.name);
+    }
+
+    let claim = layouts.iter().find(|l| l.name == "RecipientClaim").unwrap();
+    assert_eq!(
+        claim.fixed_offsets(),
+        vec![
+            Some(8),
+            Some(40),
+            Some(48),
+            Some(56),
+            Some(57),
+            Some(65),
+            Some(66),
+            Some(74),
+            Some(75),
+            Some(83)
+        ]
+    );
+
+    // PDA checks and in-place updates address these fields by offset
+    for (name, field, offset) in [
+        ("MailerState", "bump", MailerState::BUMP_OFFSET),
+        (
+            "MailerState",
+            "owner_claimable",
+            MailerState::OWNER_CLAIMABLE_OFFSET,
+        ),
+        ("RecipientClaim", "bump", RecipientClaim::BUMP_OFFSET),
+        ("RecipientClaim", "amount", RecipientClaim::AMOUNT_OFFSET),
+        (
+            "RecipientClaim",
+            "timestamp",
+            RecipientClaim::TIMESTAMP_OFFSET,
+        ),
+        (
+            "RecipientClaim",
+            "paused_offset",
+            RecipientClaim::PAUSED_OFFSET_OFFSET,
+        ),
+        (
+            "RecipientClaim",
+            "no_expiry",
+            RecipientClaim::NO_EXPIRY_OFFSET,
+        ),
+        (
+            "RecipientClaim",
+            "extensions",
+            RecipientClaim::EXTENSIONS_OFFSET,
+        ),
+        (
+            "RecipientClaim",
+            "messages_received",
+            RecipientClaim::MESSAGES_RECEIVED_OFFSET,
+        ),
+        ("FeeDiscount", "bump", FeeDiscount::BUMP_OFFSET),
+        (
+            "FeeMint",
+            "owner_claimable",
+            FeeMint::OWNER_CLAIMABLE_OFFSET,
+        ),
+    ] {
+        let layout = layouts.iter().find(|l| l.name == name).unwrap();
+        let index = layout.fields.iter().position(|f| f.name == field).unwrap();
+        assert_eq!(
+            layout.fixed_offsets()[index],
+            Some(offset),
+            "{}.{}",
+            name,
+            field
+        );
+    }
+}
+
+#[test]
+fn test_idl_matches_checked_in_file() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/idl/mailer.json");
+    let generated = mailer::idl::to_json();
+
+    if std::env::var("UPDATE_IDL").is_ok() {
+        std::fs::write(path, &generated).unwrap();
+    }
+
+    let idl = std::fs::read_to_string(path).expect("missing idl/mailer.json");
+    assert_eq!(idl, generated, "IDL changed; regenerate with UPDATE_IDL=1");
+}
+
+#[test]
+fn test_idl_covers_every_instruction_and_error() {
+    // Every instruction vector has an IDL entry whose discriminator is its Borsh tag
+    let vectors = mailer::test_vectors::instruction_vectors();
+    assert_eq!(cpi::INSTRUCTIONS.len(), vectors.len());
+    for (tag, spec) in cpi::INSTRUCTIONS.iter().enumerate() {
+        let vector = vectors.iter().find(|v| v.name == spec.name).unwrap();
+        assert_eq!(vector.bytes[0] as usize, tag, "{}", spec.name);
+    }
+
+    // Error codes are the declaration index past the offset, and map back to their error
+    for (index, error) in mailer::idl::ERRORS.iter().enumerate() {
+        let code = mailer::error::ERROR_CODE_OFFSET + index as u32;
+        assert_eq!(error.code(), code, "{:?}", error);
+        assert_eq!(mailer::MailerError::from_code(code), Some(*error));
+    }
+    let past_last = mailer::error::ERROR_CODE_OFFSET + mailer::idl::ERRORS.len() as u32;
+    assert_eq!(mailer::MailerError::from_code(past_last), None);
+    assert_eq!(mailer::MailerError::from_code(0), None);
+}
+
+// ============================================================================
+// Fee Timelock Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_proposed_fee_applies_after_timelock() {
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let mut recent_blockhash = context.last_blockhash;
+
+    let usdc_mint =
+        create_usdc_mint(&mut context.banks_client, &context.payer, recent_blockhash).await;
+    recent_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+
+    let (mailer_pda, _) = get_mailer_pda();
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(context.payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[init_instruction], Some(&context.payer.pubkey()));
+    transaction.sign(&[&context.payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut context.banks_client,
+        &context.payer,
+        recent_blockhash,
+        &usdc_mint,
+        &context.payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut context.banks_client,
+        &context.payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut context.banks_client,
+        &context.payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let owner_ix = |ix: MailerInstruction| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &ix,
+            vec![
+                AccountMeta::new(context.payer.pubkey(), true),
+                AccountMeta::new(mailer_pda, false),
+            ],
+        )
+    };
+
+    // Configure a one-day timelock
+    let mut transaction = Transaction::new_with_payer(
+        &[owner_ix(MailerInstruction::SetFeeTimelock {
+            delay: 86_400,
+        })],
+        Some(&context.payer.pubkey()),
+    );
+    transaction.sign(&[&context.payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // Immediate fee changes and shorter timelocks are now rejected
+    for ix in [
+        MailerInstruction::SetFee {
+            new_fee: 1,
+            expected_current_value: None,
+        },
+        MailerInstruction::SetFeeTimelock { delay: 60 },
+    ] {
+        let mut transaction =
+            Transaction::new_with_payer(&[owner_ix(ix)], Some(&context.payer.pubkey()));
+        transaction.sign(&[&context.payer], recent_blockhash);
+        assert!(context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .is_err());
+    }
+
+    let mut transaction = Transaction::new_with_payer(
+        &[owner_ix(MailerInstruction::ProposeFee { new_fee: 200_000 })],
+        Some(&context.payer.pubkey()),
+    );
+    transaction.sign(&[&context.payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let send_standard = |subject: &str| {
+        let recipient = Pubkey::new_unique();
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient,
+                subject: subject.to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: false,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            vec![
+                AccountMeta::new(context.payer.pubkey(), true),
+                AccountMeta::new(get_claim_pda(&recipient).0, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(context.payer.pubkey(), true),
+                AccountMeta::new_readonly(get_policy_pda(&recipient).0, false),
+                AccountMeta::new(get_message_nonce_pda(&context.payer.pubkey()).0, false),
+            ],
+        )
+    };
+
+    // Before the timelock elapses the old fee still applies (10% of 100,000)
+    let mut transaction =
+        Transaction::new_with_payer(&[send_standard("Before")], Some(&context.payer.pubkey()));
+    transaction.sign(&[&context.payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let mailer_account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.owner_claimable, 10_000);
+    assert_eq!(mailer_state.pending_fee.unwrap().new_fee, 200_000);
+
+    use solana_sdk::clock::Clock;
+    let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    clock.unix_timestamp += 86_400;
+    context.set_sysvar(&clock);
+    recent_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+
+    // After the timelock the proposed fee applies (10% of 200,000)
+    let mut transaction =
+        Transaction::new_with_payer(&[send_standard("After")], Some(&context.payer.pubkey()));
+    transaction.sign(&[&context.payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let mailer_account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.owner_claimable, 30_000);
+}
+
+// ============================================================================
+// Inbox Policy Tests
+// ============================================================================
+
+fn get_policy_pda(recipient: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"policy", &[1], recipient.as_ref()], &program_id())
+}
+
+fn get_message_nonce_pda(sender: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"message_nonce", &[1], sender.as_ref()], &program_id())
+}
+
+#[tokio::test]
+async fn test_inbox_policy_rejects_or_surcharges_disallowed_senders() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    // Recipient only accepts mail from a single friend
+    let recipient = Keypair::new();
+    let (policy_pda, _) = get_policy_pda(&recipient.pubkey());
+    let set_policy = |mode: mailer::InboxMode, sender: Pubkey, penalty_multiplier: u8| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SetInboxPolicy {
+                mode,
+                senders: vec![sender],
+                penalty_multiplier,
+                min_fee: 0,
+            },
+            vec![
+                AccountMeta::new(recipient.pubkey(), true),
+                AccountMeta::new(policy_pda, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    };
+    let fund_recipient =
+        solana_sdk::system_instruction::transfer(&payer.pubkey(), &recipient.pubkey(), 100_000_000);
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            fund_recipient,
+            set_policy(mailer::InboxMode::Allowlist, Pubkey::new_unique(), 0),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let send_instruction = |subject: &str| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient.pubkey(),
+                subject: subject.to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: false,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(get_claim_pda(&recipient.pubkey()).0, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(policy_pda, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            ],
+        )
+    };
+
+    // Unknown sender is rejected outright, and the log names the error
+    let mut transaction =
+        Transaction::new_with_payer(&[send_instruction("Rejected")], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let simulation = banks_client
+        .simulate_transaction(transaction.clone())
+        .await
+        .unwrap();
+    let logs = simulation.simulation_details.unwrap().logs;
+    assert!(logs
+        .iter()
+        .any(|log| log.contains("Error: SenderNotAllowed (6024)")));
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::SenderNotAllowed as u32)
+        )
+    );
+
+    // A blocklist naming the sender blocks them
+    let mut transaction = Transaction::new_with_payer(
+        &[set_policy(mailer::InboxMode::Blocklist, payer.pubkey(), 0)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mut transaction =
+        Transaction::new_with_payer(&[send_instruction("Blocked")], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::RecipientBlocked as u32)
+        )
+    );
+
+    // With a 5x penalty the unknown sender gets through but pays more (5 x 10,000)
+    let mut transaction = Transaction::new_with_payer(
+        &[set_policy(
+            mailer::InboxMode::Allowlist,
+            Pubkey::new_unique(),
+            5,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mut transaction =
+        Transaction::new_with_payer(&[send_instruction("Surcharged")], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.owner_claimable, 50_000);
+}
+
+#[tokio::test]
+async fn test_blocked_sender_cannot_skip_the_inbox_policy_account() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    // Recipient blocks the sender
+    let recipient = Keypair::new();
+    let (policy_pda, _) = get_policy_pda(&recipient.pubkey());
+    let fund_recipient =
+        solana_sdk::system_instruction::transfer(&payer.pubkey(), &recipient.pubkey(), 100_000_000);
+    let set_policy = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetInboxPolicy {
+            mode: mailer::InboxMode::Blocklist,
+            senders: vec![payer.pubkey()],
+            penalty_multiplier: 0,
+            min_fee: 0,
+        },
+        vec![
+            AccountMeta::new(recipient.pubkey(), true),
+            AccountMeta::new(policy_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[fund_recipient, set_policy], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let send_instruction = |with_policy: bool| {
+        let mut accounts = vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(get_claim_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ];
+        if with_policy {
+            accounts.push(AccountMeta::new_readonly(policy_pda, false));
+        }
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient.pubkey(),
+                subject: "Hello".to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: false,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            accounts,
+        )
+    };
+
+    // Leaving the policy out no longer skips it
+    let mut transaction =
+        Transaction::new_with_payer(&[send_instruction(false)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::MissingInboxPolicyAccount as u32)
+        )
+    );
+
+    // Passing it applies the blocklist
+    let mut transaction =
+        Transaction::new_with_payer(&[send_instruction(true)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::RecipientBlocked as u32)
+        )
+    );
+
+    // Nothing was charged
+    let sender_token = banks_client
+        .get_account(sender_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    let sender_token = spl_token::state::Account::unpack(&sender_token.data).unwrap();
+    assert_eq!(sender_token.amount, 1_000_000);
+}
+
+// ============================================================================
+// Message Receipt Tests
+// ============================================================================
+
+fn get_receipt_pda(recipient: &Pubkey, nonce: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"receipt", &[1], recipient.as_ref(), &nonce.to_le_bytes()],
+        &program_id(),
+    )
+}
+
+#[tokio::test]
+async fn test_message_receipt_created_on_send_and_marked_read() {
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let recipient = Keypair::new();
+    let (receipt_pda, _) = get_receipt_pda(&recipient.pubkey(), 42);
+
+    let send_instruction = |subject: &str| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient.pubkey(),
+                subject: subject.to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: false,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: Some(42),
+                memo: None,
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(get_claim_pda(&recipient.pubkey()).0, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(receipt_pda, false),
+                AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            ],
+        )
+    };
+
+    let mut transaction =
+        Transaction::new_with_payer(&[send_instruction("First")], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let receipt_account = banks_client
+        .get_account(receipt_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let receipt: MessageReceipt =
+        BorshDeserialize::deserialize(&mut &receipt_account.data[8..]).unwrap();
+    assert_eq!(receipt.sender, payer.pubkey());
+    assert_eq!(receipt.recipient, recipient.pubkey());
+    assert_eq!(receipt.nonce, 42);
+    assert_eq!(receipt.read_at, 0);
+
+    // Reusing a nonce for the same recipient fails
+    let mut transaction =
+        Transaction::new_with_payer(&[send_instruction("Second")], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+
+    // Recipient acknowledges the message, marking the receipt read
+    let ack_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::AcknowledgeMessage {
+            sender: payer.pubkey(),
+            receipt_nonce: Some(42),
+        },
+        vec![
+            AccountMeta::new_readonly(recipient.pubkey(), true),
+            AccountMeta::new_readonly(get_prefs_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(receipt_pda, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[ack_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let receipt_account = banks_client
+        .get_account(receipt_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let receipt: MessageReceipt =
+        BorshDeserialize::deserialize(&mut &receipt_account.data[8..]).unwrap();
+    assert!(receipt.read_at > 0);
+}
+
+// ============================================================================
+// Sender Memo Tests
+// ============================================================================
+
+fn get_history_pda(recipient: &Pubkey, sender: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"history", &[1], recipient.as_ref(), sender.as_ref()],
+        &program_id(),
+    )
+}
+
+#[tokio::test]
+async fn test_priority_send_memo_recorded_in_claim_history() {
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let recipient = Keypair::new().pubkey();
+    let (history_pda, _) = get_history_pda(&recipient, &payer.pubkey());
+
+    let send_instruction = |memo: String| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient,
+                subject: "Invoice".to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: true,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: Some(memo),
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(get_claim_pda(&recipient).0, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(history_pda, false),
+                AccountMeta::new_readonly(get_policy_pda(&recipient).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            ],
+        )
+    };
+
+    // Memos over 64 bytes are rejected
+    let mut transaction =
+        Transaction::new_with_payer(&[send_instruction("x".repeat(65))], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+
+    for memo in ["INV-001", "INV-002"] {
+        let mut transaction = Transaction::new_with_payer(
+            &[send_instruction(memo.to_string())],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer], recent_blockhash);
+        banks_client.process_transaction(transaction).await.unwrap();
+    }
+
+    let history_account = banks_client
+        .get_account(history_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let entry: ClaimHistoryEntry =
+        BorshDeserialize::deserialize(&mut &history_account.data[8..]).unwrap();
+    assert_eq!(entry.sender, payer.pubkey());
+    assert_eq!(entry.recipient, recipient);
+    assert_eq!(entry.last_amount, 90_000);
+    assert_eq!(entry.total_amount, 180_000);
+    assert_eq!(entry.last_memo, "INV-002");
+}
+
+// ============================================================================
+// Paused Interval Grace Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_pause_extends_claim_window() {
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let mut recent_blockhash = context.last_blockhash;
+
+    let usdc_mint =
+        create_usdc_mint(&mut context.banks_client, &context.payer, recent_blockhash).await;
+    recent_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+
+    let (mailer_pda, _) = get_mailer_pda();
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(context.payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[init_instruction], Some(&context.payer.pubkey()));
+    transaction.sign(&[&context.payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let payer_usdc = create_token_account(
+        &mut context.banks_client,
+        &context.payer,
+        recent_blockhash,
+        &usdc_mint,
+        &context.payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut context.banks_client,
+        &context.payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut context.banks_client,
+        &context.payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer_usdc,
+        1_000_000,
+    )
+    .await;
+
+    // Priority message to self creates a 90,000 claim
+    let (claim_pda, _) = get_claim_pda(&context.payer.pubkey());
+    let send_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Send {
+            to: context.payer.pubkey(),
+            subject: "Grace".to_string(),
+            _body: "Body".to_string(),
+            revenue_share_to_receiver: true,
+            resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
+        },
+        vec![
+            AccountMeta::new(context.payer.pubkey(), true),
+            AccountMeta::new(claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(payer_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(context.payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&context.payer.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&context.payer.pubkey()).0, false),
+        ],
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[send_instruction], Some(&context.payer.pubkey()));
+    transaction.sign(&[&context.payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    use solana_sdk::clock::Clock;
+    let start = context
+        .banks_client
+        .get_sysvar::<Clock>()
+        .await
+        .unwrap()
+        .unix_timestamp;
+    let set_time = |context: &mut ProgramTestContext, unix_timestamp: i64| {
+        context.set_sysvar(&Clock {
+            unix_timestamp,
+            ..Clock::default()
+        });
+    };
+
+    // Pause for ten days
+    let pause_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Pause,
+        vec![
+            AccountMeta::new(context.payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(payer_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[pause_instruction], Some(&context.payer.pubkey()));
+    transaction.sign(&[&context.payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    set_time(&mut context, start + 10 * 24 * 60 * 60);
+    recent_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+
+    let unpause_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Unpause,
+        vec![
+            AccountMeta::new(context.payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+        ],
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[unpause_instruction], Some(&context.payer.pubkey()));
+    transaction.sign(&[&context.payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // Past the nominal 60-day window but within the pause-extended one
+    set_time(&mut context, start + 65 * 24 * 60 * 60);
+    recent_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+
+    let claim_expired_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::ClaimExpiredShares {
+            recipient: context.payer.pubkey(),
+        },
+        vec![
+            AccountMeta::new(context.payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(claim_pda, false),
+            AccountMeta::new_readonly(get_beneficiary_pda(&context.payer.pubkey()).0, false),
+        ],
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[claim_expired_instruction], Some(&context.payer.pubkey()));
+    transaction.sign(&[&context.payer], recent_blockhash);
+    assert!(context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_err());
+
+    let claim_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::ClaimRecipientShare {
+            amount: None,
+            split: None,
+            close_to: None,
+        },
+        vec![
+            AccountMeta::new(context.payer.pubkey(), true),
+            AccountMeta::new(claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(payer_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[claim_instruction], Some(&context.payer.pubkey()));
+    transaction.sign(&[&context.payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let mailer_account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.total_paused_duration, 10 * 24 * 60 * 60);
+}
+
+// ============================================================================
+// Associated Token Account Claim Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_claim_recipient_share_creates_associated_token_account() {
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    // First-time recipient with SOL but no token account
+    let recipient = Keypair::new();
+    let (claim_pda, _) = get_claim_pda(&recipient.pubkey());
+    let fund_recipient =
+        solana_sdk::system_instruction::transfer(&payer.pubkey(), &recipient.pubkey(), 100_000_000);
+    let send_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Send {
+            to: recipient.pubkey(),
+            subject: "Welcome".to_string(),
+            _body: "Body".to_string(),
+            revenue_share_to_receiver: true,
+            resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ],
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[fund_recipient, send_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let ata_program_id = Pubkey::from_str("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL").unwrap();
+    let (recipient_ata, _) = Pubkey::find_program_address(
+        &[
+            recipient.pubkey().as_ref(),
+            spl_token::id().as_ref(),
+            usdc_mint.as_ref(),
+        ],
+        &ata_program_id,
+    );
+    assert!(banks_client
+        .get_account(recipient_ata)
+        .await
+        .unwrap()
+        .is_none());
+
+    let claim_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::ClaimRecipientShareToAta,
+        vec![
+            AccountMeta::new(recipient.pubkey(), true),
+            AccountMeta::new(claim_pda, false),
+            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(recipient_ata, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(usdc_mint, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(ata_program_id, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[claim_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let ata_account = banks_client
+        .get_account(recipient_ata)
+        .await
+        .unwrap()
+        .unwrap();
+    let token_account = TokenAccount::unpack(&ata_account.data).unwrap();
+    assert_eq!(token_account.owner, recipient.pubkey());
+    assert_eq!(token_account.amount, 90_000);
+}
+
+// ============================================================================
+// Rent Cost Tests
+// ============================================================================
+
+#[test]
+fn test_rent_costs_match_account_sizes() {
+    use mailer::{costs, AdminSet, InboxMode, InboxPolicy, PendingFeeChange, SweepCursor};
+    use solana_program::rent::Rent;
+
+    let key = Pubkey::new_unique();
+    let rent = Rent::default();
+
+    // Serialize maximal instances so every LEN constant is checked against real Borsh sizes
+    let check = |data: Vec<u8>, len: usize, cost: u64| {
+        assert_eq!(data.len(), len);
+        assert_eq!(cost, rent.minimum_balance(8 + data.len()));
+    };
+
+    check(
+        borsh::to_vec(&MailerState {
+            owner: key,
+            usdc_mint: key,
+            send_fee: 0,
+            delegation_fee: 0,
+            owner_claimable: 0,
+            paused: false,
+            fee_paused: false,
+            bump: 0,
+            protocol_sends_enabled: false,
+            pending_owner: Some(key),
+            fee_timelock: 0,
+            pending_fee: Some(PendingFeeChange {
+                new_fee: 0,
+                effective_at: 0,
+            }),
+            paused_at: 0,
+            total_paused_duration: 0,
+            claim_rent_reserve: 0,
+            delegation_rent_reserve: 0,
+            discount_rent_reserve: 0,
+            disabled_instructions: 0,
+            spam_oracle: Some(key),
+            spam_threshold: Some(u64::MAX),
+            rate_limit_max_sends: 0,
+            rate_limit_window: 0,
+            lamport_send_fee: 0,
+            owner_claimable_lamports: 0,
+            fee_oracle: Some(key),
+            fee_oracle_target: 0,
+            fee_oracle_max_age: 0,
+            claim_creation_policy: mailer::ClaimCreationPolicy::Eager,
+            usdc_decimals: 6,
+            sanctions_enabled: false,
+            backup_mint: Some(key),
+            stats_enabled: true,
+            pause_flags: 0,
+            kyc_verifier: Some(key),
+            delegation_refund_window: 0,
+            claim_cap: 0,
+            payload_limits: PayloadLimits::default(),
+            payload_fee_tier_len: 0,
+            payload_fee_tier_percent: 0,
+            recipient_liabilities: 0,
+            min_coverage_bps: 0,
+            version: mailer::MAILER_STATE_VERSION,
+            treasury: Some(key),
+            credit_liabilities: 0,
+        })
+        .unwrap(),
+        MailerState::LEN,
+        costs::mailer_state(&rent),
+    );
+    check(
+        borsh::to_vec(&RecipientClaim {
+            recipient: key,
+            amount: 0,
+            timestamp: 0,
+            bump: 0,
+            paused_offset: 0,
+            no_expiry: false,
+            surcharge_paid_until: 0,
+            extensions: 0,
+            messages_received: 0,
+            total_claimed: 0,
+        })
+        .unwrap(),
+        RecipientClaim::LEN,
+        costs::recipient_claim(&rent),
+    );
+    check(
+        borsh::to_vec(&Delegation {
+            delegator: key,
+            delegate: Some(key),
+            bump: 0,
+            escrowed_fee: u64::MAX,
+            refund_deadline: i64::MAX,
+            fee_refundable: true,
+        })
+        .unwrap(),
+        Delegation::LEN,
+        costs::delegation(&rent),
+    );
+    check(
+        borsh::to_vec(&FeeDiscount {
+            account: key,
+            discount: 0,
+            bump: 0,
+        })
+        .unwrap(),
+        FeeDiscount::LEN,
+        costs::fee_discount(&rent),
+    );
+    check(
+        borsh::to_vec(&RecipientPrefs {
+            recipient: key,
+            read_receipts_opt_out: false,
+            bump: 0,
+        })
+        .unwrap(),
+        RecipientPrefs::LEN,
+        costs::recipient_prefs(&rent),
+    );
+    check(
+        borsh::to_vec(&AdminSet {
+            admins: vec![key; 10],
+            threshold: 0,
+            bump: 0,
+        })
+        .unwrap(),
+        AdminSet::LEN,
+        costs::admin_set(&rent),
+    );
+    check(
+        borsh::to_vec(&SweepCursor {
+            last_processed: key,
+            processed_total: 0,
+            swept_total: 0,
+            bump: 0,
+        })
+        .unwrap(),
+        SweepCursor::LEN,
+        costs::sweep_cursor(&rent),
+    );
+    check(
+        borsh::to_vec(&InboxPolicy {
+            recipient: key,
+            mode: InboxMode::Allowlist,
+            senders: vec![key; 16],
+            penalty_multiplier: 0,
+            bump: 0,
+            min_fee: 0,
+        })
+        .unwrap(),
+        InboxPolicy::LEN,
+        costs::inbox_policy(&rent),
+    );
+    check(
+        borsh::to_vec(&MessageReceipt {
+            sender: key,
+            recipient: key,
+            nonce: 0,
+            sent_at: 0,
+            read_at: 0,
+            bump: 0,
+        })
+        .unwrap(),
+        MessageReceipt::LEN,
+        costs::message_receipt(&rent),
+    );
+    check(
+        borsh::to_vec(&ClaimHistoryEntry {
+            recipient: key,
+            sender: key,
+            total_amount: 0,
+            last_amount: 0,
+            last_memo: "m".repeat(64),
+            last_timestamp: 0,
+            bump: 0,
+        })
+        .unwrap(),
+        ClaimHistoryEntry::LEN,
+        costs::claim_history_entry(&rent),
+    );
+    check(
+        borsh::to_vec(&SessionKey {
+            recipient: key,
+            session_key: key,
+            scope: 0,
+            expires_at: 0,
+            bump: 0,
+        })
+        .unwrap(),
+        SessionKey::LEN,
+        costs::session_key(&rent),
+    );
+    check(
+        borsh::to_vec(&Preauthorization {
+            user: key,
+            relayer: key,
+            webhook_id: "w".repeat(64),
+            max_sends: 0,
+            sends_used: 0,
+            max_fee_per_send: 0,
+            expires_at: 0,
+            bump: 0,
+        })
+        .unwrap(),
+        Preauthorization::LEN,
+        costs::preauthorization(&rent),
+    );
+    check(
+        borsh::to_vec(&PayoutConfig {
+            recipient: key,
+            payout_token_account: Some(key),
+            bump: 0,
+        })
+        .unwrap(),
+        PayoutConfig::LEN,
+        costs::payout_config(&rent),
+    );
+    check(
+        borsh::to_vec(&ConversionVault {
+            mint: key,
+            vault: key,
+            enabled: false,
+            total_converted: 0,
+            bump: 0,
+        })
+        .unwrap(),
+        ConversionVault::LEN,
+        costs::conversion_vault(&rent),
+    );
+    check(
+        borsh::to_vec(&RateLimit {
+            sender: key,
+            window_start: 0,
+            count: 0,
+            bump: 0,
+        })
+        .unwrap(),
+        RateLimit::LEN,
+        costs::rate_limit(&rent),
+    );
+    check(
+        borsh::to_vec(&Promo {
+            code_hash: [0; 32],
+            discount: 0,
+            remaining_redemptions: 0,
+            expiry: 0,
+            bump: 0,
+        })
+        .unwrap(),
+        Promo::LEN,
+        costs::promo(&rent),
+    );
+    check(
+        borsh::to_vec(&FeeMint {
+            mint: key,
+            send_fee: 0,
+            delegation_fee: 0,
+            owner_claimable: 0,
+            bump: 0,
+        })
+        .unwrap(),
+        FeeMint::LEN,
+        costs::fee_mint(&rent),
+    );
+    check(
+        borsh::to_vec(&RecipientInbox {
+            recipient: key,
+            sequence: 0,
+            bump: 0,
+        })
+        .unwrap(),
+        RecipientInbox::LEN,
+        costs::recipient_inbox(&rent),
+    );
+    check(
+        borsh::to_vec(&ClaimRentPayer {
+            claim: key,
+            payer: key,
+            bump: 0,
+        })
+        .unwrap(),
+        ClaimRentPayer::LEN,
+        costs::claim_rent_payer(&rent),
+    );
+}
+
+// ============================================================================
+// Partial Claim Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_partial_claim_keeps_remainder_and_timestamp() {
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    // Setup
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Create token accounts
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    // Create a separate recipient
+    let recipient = Keypair::new();
+    let recipient_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &recipient.pubkey(),
+    )
+    .await;
+
+    let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
+
+    // Send priority message to create claimable share
+    let send_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Send {
+            to: recipient.pubkey(),
+            subject: "Test".to_string(),
+            _body: "Body".to_string(),
+            revenue_share_to_receiver: true,
+            resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(recipient_claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ],
+    );
+
+    let mut transaction = Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let claim_ix = |amount: Option<u64>| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::ClaimRecipientShare {
+                amount,
+                split: None,
+                close_to: None,
+            },
+            vec![
+                AccountMeta::new(recipient.pubkey(), true),
+                AccountMeta::new(recipient_claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(recipient_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+            ],
+        )
+    };
+
+    let claim_account = banks_client
+        .get_account(recipient_claim_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let original: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+
+    // Withdraw part of the share
+    let mut transaction =
+        Transaction::new_with_payer(&[claim_ix(Some(30_000))], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let claim_account = banks_client
+        .get_account(recipient_claim_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    assert_eq!(claim.amount, 60_000);
+    assert_eq!(claim.timestamp, original.timestamp);
+
+    // Claiming more than the remaining balance fails
+    let mut transaction =
+        Transaction::new_with_payer(&[claim_ix(Some(60_001))], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+
+    // Claim the remainder
+    let mut transaction = Transaction::new_with_payer(&[claim_ix(None)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let recipient_token_account = banks_client
+        .get_account(recipient_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    let recipient_token_data = TokenAccount::unpack(&recipient_token_account.data[..]).unwrap();
+    assert_eq!(recipient_token_data.amount, 90_000);
+
+    let claim_account = banks_client
+        .get_account(recipient_claim_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    assert_eq!(claim.amount, 0);
+    assert_eq!(claim.timestamp, 0);
+}
+
+// ============================================================================
+// Session Key Tests
+// ============================================================================
+
+fn get_session_pda(recipient: &Pubkey, session_key: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"session",
+            &[PDA_VERSION],
+            recipient.as_ref(),
+            session_key.as_ref(),
+        ],
+        &program_id(),
+    )
+}
+
+#[tokio::test]
+async fn test_session_key_updates_prefs_within_scope_and_expiry() {
+    use solana_program::clock::Clock;
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+
+    let start: i64 = 1_700_000_000;
+    let set_time = |context: &mut ProgramTestContext, unix_timestamp: i64| {
+        context.set_sysvar(&Clock {
+            unix_timestamp,
+            ..Clock::default()
+        });
+    };
+    set_time(&mut context, start);
+    let mut recent_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+
+    let recipient = context.payer.pubkey();
+    let session = Keypair::new();
+    let (session_pda, _) = get_session_pda(&recipient, &session.pubkey());
+    let (prefs_pda, _) = get_prefs_pda(&recipient);
+    let (policy_pda, _) = get_policy_pda(&recipient);
+
+    // Fund the session key so it can pay for preference account creation
+    let fund_instruction =
+        solana_sdk::system_instruction::transfer(&recipient, &session.pubkey(), 100_000_000);
+    let mut transaction = Transaction::new_with_payer(&[fund_instruction], Some(&recipient));
+    transaction.sign(&[&context.payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // Register a prefs-only session key valid for one day
+    let register_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::RegisterSessionKey {
+            session_key: session.pubkey(),
+            scope: SessionKey::SCOPE_PREFS,
+            expires_at: start + 24 * 60 * 60,
+        },
+        vec![
+            AccountMeta::new(recipient, true),
+            AccountMeta::new(session_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[register_instruction], Some(&recipient));
+    transaction.sign(&[&context.payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let session_account = context
+        .banks_client
+        .get_account(session_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let session_state: SessionKey =
+        BorshDeserialize::deserialize(&mut &session_account.data[8..]).unwrap();
+    assert_eq!(session_state.recipient, recipient);
+    assert_eq!(session_state.session_key, session.pubkey());
+
+    let prefs_instruction = |opt_out: bool| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SetRecipientPrefs {
+                read_receipts_opt_out: opt_out,
+            },
+            vec![
+                AccountMeta::new(session.pubkey(), true),
+                AccountMeta::new(prefs_pda, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new_readonly(session_pda, false),
+            ],
+        )
+    };
+
+    // The session key updates the recipient's prefs without the recipient signing
+    let mut transaction = Transaction::new_with_payer(&[prefs_instruction(true)], Some(&recipient));
+    transaction.sign(&[&context.payer, &session], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let prefs_account = context
+        .banks_client
+        .get_account(prefs_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let prefs: RecipientPrefs =
+        BorshDeserialize::deserialize(&mut &prefs_account.data[8..]).unwrap();
+    assert_eq!(prefs.recipient, recipient);
+    assert!(prefs.read_receipts_opt_out);
+
+    // Inbox policy is outside the granted scope
+    let policy_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetInboxPolicy {
+            mode: mailer::InboxMode::Blocklist,
+            senders: vec![Pubkey::new_unique()],
+            penalty_multiplier: 0,
+            min_fee: 0,
+        },
+        vec![
+            AccountMeta::new(session.pubkey(), true),
+            AccountMeta::new(policy_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(session_pda, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[policy_instruction], Some(&recipient));
+    transaction.sign(&[&context.payer, &session], recent_blockhash);
+    assert!(context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_err());
+
+    // Expired session keys are rejected
+    set_time(&mut context, start + 24 * 60 * 60);
+    recent_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+
+    let mut transaction =
+        Transaction::new_with_payer(&[prefs_instruction(false)], Some(&recipient));
+    transaction.sign(&[&context.payer, &session], recent_blockhash);
+    assert!(context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_err());
+
+    // Re-register, then revoke
+    let register_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::RegisterSessionKey {
+            session_key: session.pubkey(),
+            scope: SessionKey::SCOPE_ALL,
+            expires_at: start + 2 * 24 * 60 * 60,
+        },
+        vec![
+            AccountMeta::new(recipient, true),
+            AccountMeta::new(session_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let revoke_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::RevokeSessionKey {
+            session_key: session.pubkey(),
+        },
+        vec![
+            AccountMeta::new(recipient, true),
+            AccountMeta::new(session_pda, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(
+        &[register_instruction, revoke_instruction],
+        Some(&recipient),
+    );
+    transaction.sign(&[&context.payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let mut transaction = Transaction::new_with_payer(&[prefs_instruction(true)], Some(&recipient));
+    transaction.sign(&[&context.payer, &session], recent_blockhash);
+    assert!(context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_err());
+}
+
+// ============================================================================
+// Payout Address Tests
+// ============================================================================
+
+fn get_payout_pda(recipient: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"payout", &[PDA_VERSION], recipient.as_ref()],
+        &program_id(),
+    )
+}
+
+#[tokio::test]
+async fn test_payout_address_redirects_recipient_share() {
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    // Setup
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Create token accounts
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    // Create a separate recipient
+    let recipient = Keypair::new();
+    let recipient_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &recipient.pubkey(),
+    )
+    .await;
+
+    let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
+
+    // Send priority message to create claimable share
+    let send_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Send {
+            to: recipient.pubkey(),
+            subject: "Test".to_string(),
+            _body: "Body".to_string(),
+            revenue_share_to_receiver: true,
+            resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(recipient_claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ],
+    );
+
+    let mut transaction = Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Route payouts to a cold wallet's USDC account
+    let cold_wallet = Keypair::new();
+    let cold_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &cold_wallet.pubkey(),
+    )
+    .await;
+    let (payout_pda, _) = get_payout_pda(&recipient.pubkey());
+
+    let fund_instruction =
+        solana_sdk::system_instruction::transfer(&payer.pubkey(), &recipient.pubkey(), 100_000_000);
+    let set_payout_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetPayoutAddress {
+            payout_token_account: Some(cold_usdc),
+        },
+        vec![
+            AccountMeta::new(recipient.pubkey(), true),
+            AccountMeta::new(payout_pda, false),
+            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(cold_usdc, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(
+        &[fund_instruction, set_payout_instruction],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let payout_account = banks_client.get_account(payout_pda).await.unwrap().unwrap();
+    let config: PayoutConfig =
+        BorshDeserialize::deserialize(&mut &payout_account.data[8..]).unwrap();
+    assert_eq!(config.payout_token_account, Some(cold_usdc));
+
+    let claim_ix = |destination: Pubkey| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::ClaimRecipientShare {
+                amount: None,
+                split: None,
+                close_to: None,
+            },
+            vec![
+                AccountMeta::new(recipient.pubkey(), true),
+                AccountMeta::new(recipient_claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(destination, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(payout_pda, false),
+            ],
+        )
+    };
+
+    // The recipient's own account is rejected once a payout address is configured
+    let mut transaction =
+        Transaction::new_with_payer(&[claim_ix(recipient_usdc)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+
+    let mut transaction =
+        Transaction::new_with_payer(&[claim_ix(cold_usdc)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let cold_token_account = banks_client.get_account(cold_usdc).await.unwrap().unwrap();
+    let cold_token_data = TokenAccount::unpack(&cold_token_account.data[..]).unwrap();
+    assert_eq!(cold_token_data.amount, 90_000);
+
+    let recipient_token_account = banks_client
+        .get_account(recipient_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    let recipient_token_data = TokenAccount::unpack(&recipient_token_account.data[..]).unwrap();
+    assert_eq!(recipient_token_data.amount, 0);
+
+    // Distribution while paused honors the payout address too
+    let send_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Send {
+            to: recipient.pubkey(),
+            subject: "Again".to_string(),
+            _body: "Body".to_string(),
+            revenue_share_to_receiver: true,
+            resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(recipient_claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ],
+    );
+    let pause_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Pause,
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+    );
+    let distribute_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::DistributeClaimableFunds {
+            recipient: recipient.pubkey(),
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(recipient_claim_pda, false),
+            AccountMeta::new(cold_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(payout_pda, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(
+        &[send_instruction, pause_instruction, distribute_instruction],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let cold_token_account = banks_client.get_account(cold_usdc).await.unwrap().unwrap();
+    let cold_token_data = TokenAccount::unpack(&cold_token_account.data[..]).unwrap();
+    assert_eq!(cold_token_data.amount, 180_000);
+}
+
+// ============================================================================
+// System Program Validation Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_account_creation_rejects_fake_system_program() {
+    use solana_program::instruction::InstructionError;
+    use solana_sdk::transaction::TransactionError;
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(Pubkey::new_unique(), false),
+        ],
+    );
+
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::InvalidSystemProgram as u32)
+        )
+    );
+    assert!(banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+// ============================================================================
+// Conversion Vault Tests
+// ============================================================================
+
+fn get_conversion_pda(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"conversion", &[PDA_VERSION], mint.as_ref()],
+        &program_id(),
+    )
+}
+
+#[tokio::test]
+async fn test_claim_recipient_share_as_alternate_mint() {
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    // Setup
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Create token accounts
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    // Create a separate recipient
+    let recipient = Keypair::new();
+    let recipient_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &recipient.pubkey(),
+    )
+    .await;
+
+    let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
+
+    let send_ix = |subject: &str| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient.pubkey(),
+                subject: subject.to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: true,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(recipient_claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            ],
+        )
+    };
+
+    // Owner registers an alternate stable mint with an (initially empty) conversion vault
+    let alt_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let vault_token = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &alt_mint,
+        &mailer_pda,
+    )
+    .await;
+    let recipient_alt = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &alt_mint,
+        &recipient.pubkey(),
+    )
+    .await;
+    let (conversion_pda, _) = get_conversion_pda(&alt_mint);
+
+    let set_vault_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetConversionVault {
+            mint: alt_mint,
+            enabled: true,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(conversion_pda, false),
+            AccountMeta::new_readonly(vault_token, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(
+        &[send_ix("First"), set_vault_instruction],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let claim_as_ix = || {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::ClaimRecipientShareAs { mint: alt_mint },
+            vec![
+                AccountMeta::new(recipient.pubkey(), true),
+                AccountMeta::new(recipient_claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(recipient_alt, false),
+                AccountMeta::new(conversion_pda, false),
+                AccountMeta::new(vault_token, false),
+                AccountMeta::new(recipient_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+            ],
+        )
+    };
+
+    // Without liquidity the claim falls back to the default mint
+    let mut transaction = Transaction::new_with_payer(&[claim_as_ix()], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let recipient_token_account = banks_client
+        .get_account(recipient_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    let recipient_token_data = TokenAccount::unpack(&recipient_token_account.data[..]).unwrap();
+    assert_eq!(recipient_token_data.amount, 90_000);
+
+    // With liquidity the share is paid 1:1 in the alternate mint
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &alt_mint,
+        &vault_token,
+        100_000,
+    )
+    .await;
+
+    let mut transaction =
+        Transaction::new_with_payer(&[send_ix("Second"), claim_as_ix()], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let recipient_alt_account = banks_client
+        .get_account(recipient_alt)
+        .await
+        .unwrap()
+        .unwrap();
+    let recipient_alt_data = TokenAccount::unpack(&recipient_alt_account.data[..]).unwrap();
+    assert_eq!(recipient_alt_data.amount, 90_000);
+
+    let vault_account = banks_client
+        .get_account(vault_token)
+        .await
+        .unwrap()
+        .unwrap();
+    let vault_data = TokenAccount::unpack(&vault_account.data[..]).unwrap();
+    assert_eq!(vault_data.amount, 10_000);
+
+    // The retained default-mint share reimburses the owner
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.owner_claimable, 10_000 + 10_000 + 90_000);
+
+    let conversion_account = banks_client
+        .get_account(conversion_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let conversion: ConversionVault =
+        BorshDeserialize::deserialize(&mut &conversion_account.data[8..]).unwrap();
+    assert_eq!(conversion.total_converted, 90_000);
+
+    let claim_account = banks_client
+        .get_account(recipient_claim_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    assert_eq!(claim.amount, 0);
+}
+
+#[tokio::test]
+async fn test_claim_recipient_share_as_counts_conversion_payouts_in_stats() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+    let (stats_pda, _) = mailer::pda::stats(&program_id());
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let init_stats = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::InitializeStats,
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(stats_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[init_instruction, init_stats], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let recipient = Keypair::new();
+    let recipient_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &recipient.pubkey(),
+    )
+    .await;
+    let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
+
+    // The conversion vault holds enough of the alternate mint for the whole share
+    let alt_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let vault_token = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &alt_mint,
+        &mailer_pda,
+    )
+    .await;
+    let recipient_alt = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &alt_mint,
+        &recipient.pubkey(),
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &alt_mint,
+        &vault_token,
+        100_000,
+    )
+    .await;
+    let (conversion_pda, _) = get_conversion_pda(&alt_mint);
+
+    let send_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Send {
+            to: recipient.pubkey(),
+            subject: "Priority".to_string(),
+            _body: "Body".to_string(),
+            revenue_share_to_receiver: true,
+            resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: true,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(recipient_claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            AccountMeta::new(stats_pda, false),
+        ],
+    );
+    let set_vault_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetConversionVault {
+            mint: alt_mint,
+            enabled: true,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(conversion_pda, false),
+            AccountMeta::new_readonly(vault_token, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(
+        &[send_instruction, set_vault_instruction],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let claim_as = |with_stats: bool| {
+        let mut accounts = vec![
+            AccountMeta::new(recipient.pubkey(), true),
+            AccountMeta::new(recipient_claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(recipient_alt, false),
+            AccountMeta::new(conversion_pda, false),
+            AccountMeta::new(vault_token, false),
+            AccountMeta::new(recipient_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ];
+        if with_stats {
+            accounts.push(AccountMeta::new(stats_pda, false));
+        }
+        let mut transaction = Transaction::new_with_payer(
+            &[Instruction::new_with_borsh(
+                program_id(),
+                &MailerInstruction::ClaimRecipientShareAs { mint: alt_mint },
+                accounts,
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &recipient], recent_blockhash);
+        transaction
+    };
+
+    // Conversion payouts need the stats account like any other claim
+    assert_eq!(
+        banks_client
+            .process_transaction(claim_as(false))
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::InvalidPDA as u32)
+        )
+    );
+    banks_client
+        .process_transaction(claim_as(true))
+        .await
+        .unwrap();
+
+    let recipient_alt_account = banks_client
+        .get_account(recipient_alt)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&recipient_alt_account.data[..])
+            .unwrap()
+            .amount,
+        90_000
+    );
+    let stats_account = banks_client.get_account(stats_pda).await.unwrap().unwrap();
+    let stats: mailer::MailerStats =
+        BorshDeserialize::deserialize(&mut &stats_account.data[8..]).unwrap();
+    assert_eq!(stats.messages_sent, 1);
+    assert_eq!(stats.total_claimed, 90_000);
+}
+
+// ============================================================================
+// Indexer Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_indexer_decodes_transaction_logs() {
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    // Setup
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Create token accounts
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    // Create a separate recipient
+    let recipient = Keypair::new();
+    let recipient_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &recipient.pubkey(),
+    )
+    .await;
+
+    let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
+
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        20_000_000,
+    )
+    .await;
+
+    // A sender with an empty USDC account, so the fee transfer fails
+    let broke_sender = Keypair::new();
+    let broke_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &broke_sender.pubkey(),
+    )
+    .await;
+
+    // It still pays for its message nonce account
+    let fund_broke_sender = solana_sdk::system_instruction::transfer(
+        &payer.pubkey(),
+        &broke_sender.pubkey(),
+        10_000_000,
+    );
+    let mut transaction = Transaction::new_with_payer(&[fund_broke_sender], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let delegate = Keypair::new();
+    let (delegation_pda, _) = get_delegation_pda(&payer.pubkey());
+
+    let instructions = vec![
+        (
+            Instruction::new_with_borsh(
+                program_id(),
+                &MailerInstruction::Send {
+                    to: recipient.pubkey(),
+                    subject: "Invoice (March)".to_string(),
+                    _body: "Body".to_string(),
+                    revenue_share_to_receiver: true,
+                    resolve_sender_to_name: false,
+                    request_read_receipt: false,
+                    receipt_nonce: None,
+                    memo: None,
+                    strict: false,
+                    thread_id: None,
+                    in_reply_to: None,
+                    allow_downgrade: false,
+                    tip: 0,
+                    bridge: false,
+                    idempotency_nonce: None,
+                },
+                vec![
+                    AccountMeta::new(payer.pubkey(), true),
+                    AccountMeta::new(recipient_claim_pda, false),
+                    AccountMeta::new(mailer_pda, false),
+                    AccountMeta::new(sender_usdc, false),
+                    AccountMeta::new(mailer_usdc, false),
+                    AccountMeta::new_readonly(spl_token::id(), false),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                    AccountMeta::new(payer.pubkey(), true),
+                    AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+                    AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+                ],
+            ),
+            None,
+        ),
+        (
+            Instruction::new_with_borsh(
+                program_id(),
+                &MailerInstruction::Send {
+                    to: recipient.pubkey(),
+                    subject: "Unpaid".to_string(),
+                    _body: "Body".to_string(),
+                    revenue_share_to_receiver: false,
+                    resolve_sender_to_name: true,
+                    request_read_receipt: false,
+                    receipt_nonce: None,
+                    memo: None,
+                    strict: false,
+                    thread_id: None,
+                    in_reply_to: None,
+                    allow_downgrade: false,
+                    tip: 0,
+                    bridge: false,
+                    idempotency_nonce: None,
+                },
+                vec![
+                    AccountMeta::new(broke_sender.pubkey(), true),
+                    AccountMeta::new(recipient_claim_pda, false),
+                    AccountMeta::new(mailer_pda, false),
+                    AccountMeta::new(broke_usdc, false),
+                    AccountMeta::new(mailer_usdc, false),
+                    AccountMeta::new_readonly(spl_token::id(), false),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                    AccountMeta::new(broke_sender.pubkey(), true),
+                    AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+                    AccountMeta::new(get_message_nonce_pda(&broke_sender.pubkey()).0, false),
+                ],
+            ),
+            Some(&broke_sender),
+        ),
+        (
+            Instruction::new_with_borsh(
+                program_id(),
+                &MailerInstruction::ClaimRecipientShare {
+                    amount: None,
+                    split: None,
+                    close_to: None,
+                },
+                vec![
+                    AccountMeta::new(recipient.pubkey(), true),
+                    AccountMeta::new(recipient_claim_pda, false),
+                    AccountMeta::new(mailer_pda, false),
+                    AccountMeta::new(recipient_usdc, false),
+                    AccountMeta::new(mailer_usdc, false),
+                    AccountMeta::new_readonly(spl_token::id(), false),
+                ],
+            ),
+            Some(&recipient),
+        ),
+        (
+            Instruction::new_with_borsh(
+                program_id(),
+                &MailerInstruction::DelegateTo {
+                    delegate: Some(delegate.pubkey()),
+                },
+                vec![
+                    AccountMeta::new(payer.pubkey(), true),
+                    AccountMeta::new(delegation_pda, false),
+                    AccountMeta::new(mailer_pda, false),
+                    AccountMeta::new(sender_usdc, false),
+                    AccountMeta::new(mailer_usdc, false),
+                    AccountMeta::new_readonly(spl_token::id(), false),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                    AccountMeta::new(get_delegate_index_pda(&delegate.pubkey()).0, false),
+                ],
+            ),
+            None,
+        ),
+    ];
+
+    let mut activity = Vec::new();
+    for (instruction, signer) in instructions {
+        assert!(indexer::decode_instruction(&instruction.data).is_some());
+
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        match signer {
+            Some(signer) => transaction.sign(&[&payer, signer], recent_blockhash),
+            None => transaction.sign(&[&payer], recent_blockhash),
+        }
+        let result = banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+            .unwrap();
+        assert!(result.result.is_ok());
+        activity.extend(indexer::decode_logs(&result.metadata.unwrap().log_messages));
+    }
+
+    // Message ids depend on the slot, so take them from the decoded sends
+    let message_ids: Vec<_> = activity
+        .iter()
+        .filter_map(|entry| match entry {
+            MailerActivity::Sent(mail) | MailerActivity::FeeFailed(mail) => mail.message_id,
+            _ => None,
+        })
+        .collect();
+    assert_eq!(message_ids.len(), 2);
+
+    assert_eq!(
+        activity,
+        vec![
+            MailerActivity::Sent(SentMail {
+                sender: payer.pubkey(),
+                payer: payer.pubkey(),
+                to: SendTarget::Wallet(recipient.pubkey()),
+                subject: Some("Invoice (March)".to_string()),
+                mail_id: None,
+                webhook_id: None,
+                revenue_share: true,
+                protocol: false,
+                effective_fee: 100_000,
+                spam_score: None,
+                fee_mint: Some(usdc_mint),
+                fee_decimals: Some(6),
+                fee_in_lamports: false,
+                webhook_surcharge: None,
+                tip: None,
+                inbox_sequence: None,
+                message_id: Some(message_ids[0]),
+                thread_id: None,
+                in_reply_to: None,
+            }),
+            MailerActivity::MailDropped {
+                sender: broke_sender.pubkey(),
+                to: SendTarget::Wallet(recipient.pubkey()),
+                reason: format!(
+                    "{}",
+                    solana_program::program_error::ProgramError::from(
+                        mailer::MailerError::InsufficientFee
+                    )
+                ),
+            },
+            MailerActivity::FeeFailed(SentMail {
+                sender: broke_sender.pubkey(),
+                payer: broke_sender.pubkey(),
+                to: SendTarget::Wallet(recipient.pubkey()),
+                subject: Some("Unpaid".to_string()),
+                mail_id: None,
+                webhook_id: None,
+                revenue_share: false,
+                protocol: false,
+                effective_fee: 100_000,
+                spam_score: None,
+                fee_mint: Some(usdc_mint),
+                fee_decimals: Some(6),
+                fee_in_lamports: false,
+                webhook_surcharge: None,
+                tip: None,
+                inbox_sequence: None,
+                message_id: Some(message_ids[1]),
+                thread_id: None,
+                in_reply_to: None,
+            }),
+            MailerActivity::Claimed {
+                account: recipient.pubkey(),
+                amount: 90_000,
+                kind: ClaimKind::Recipient,
+            },
+            MailerActivity::Delegated {
+                delegator: payer.pubkey(),
+                delegate: Some(delegate.pubkey()),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_indexer_decodes_log_variants() {
+    let sender = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+
+    assert_eq!(
+        indexer::decode_log(&format!(
+            "Prepared mail sent from {} payer {} to email a@b.co (mailId: mail-1, effective fee: 100000, fee paid: true)",
+            sender, sender
+        )),
+        Some(MailerActivity::Sent(SentMail {
+            sender,
+            payer: sender,
+            to: SendTarget::Email("a@b.co".to_string()),
+            subject: None,
+            mail_id: Some("mail-1".to_string()),
+            webhook_id: None,
+            revenue_share: false,
+            protocol: false,
+            effective_fee: 100_000,
+            spam_score: None,
+            fee_mint: None,
+            fee_decimals: None,
+            fee_in_lamports: false,
+            webhook_surcharge: None,
+            tip: None,
+            inbox_sequence: None,
+            message_id: None,
+            thread_id: None,
+            in_reply_to: None,
+        }))
+    );
+    assert_eq!(
+        indexer::decode_log(&format!(
+            "Recipient {} claimed 90000 as {} from conversion vault; 90000 credited to owner",
+            sender, mint
+        )),
+        Some(MailerActivity::Claimed {
+            account: sender,
+            amount: 90_000,
+            kind: ClaimKind::Converted { mint },
+        })
+    );
+    assert_eq!(
+        indexer::decode_log(&format!("Delegation set from {} to None", sender)),
+        Some(MailerActivity::Delegated {
+            delegator: sender,
+            delegate: None,
+        })
+    );
+    assert_eq!(
+        indexer::decode_log(&format!(
+            "MailDropped {{ sender: {}, to: email a@b.co, reason: insufficient funds, retry later }}",
+            sender
+        )),
+        Some(MailerActivity::MailDropped {
+            sender,
+            to: SendTarget::Email("a@b.co".to_string()),
+            reason: "insufficient funds, retry later".to_string(),
+        })
+    );
+    assert_eq!(indexer::decode_log("Fee updated from 1 to 2"), None);
+
+    // Logs from other programs are ignored even when they look like Mailer logs
+    let other = Pubkey::new_unique();
+    let logs = vec![
+        format!("Program {} invoke [1]", other),
+        format!("Program log: Delegation rejected by {}", sender),
+        format!("Program {} success", other),
+        format!("Program {} invoke [1]", program_id()),
+        format!("Program log: Delegation rejected by {}", sender),
+        format!("Program {} success", program_id()),
+    ];
+    assert_eq!(
+        indexer::decode_logs(&logs),
+        vec![MailerActivity::DelegationRejected { delegate: sender }]
+    );
+}
+
+#[tokio::test]
+async fn test_recipient_keyed_event_logs() {
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let recent_blockhash = context.last_blockhash;
+    let payer = context.payer.insecure_clone();
+
+    let usdc_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let recipient = Pubkey::new_unique();
+    let prefix = mailer::recipient_log_prefix(&recipient);
+    let digits: String = recipient.to_bytes()[..8]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    assert_eq!(prefix, format!("[rcpt:{}] ", digits));
+
+    let send_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Send {
+            to: recipient,
+            subject: "Keyed".to_string(),
+            _body: "Body".to_string(),
+            revenue_share_to_receiver: true,
+            resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(get_claim_pda(&recipient).0, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert!(result.result.is_ok(), "{:?}", result.result);
+    let logs = result.metadata.unwrap().log_messages;
+
+    // Filtering on the prefix keeps the send log, which still decodes
+    let keyed: Vec<&String> = logs.iter().filter(|log| log.contains(&prefix)).collect();
+    assert_eq!(keyed.len(), 1);
+    let event = keyed[0].strip_prefix("Program log: ").unwrap();
+    let (key, rest) = indexer::split_recipient_key(event).unwrap();
+    assert_eq!(key, recipient.to_bytes()[..8]);
+    assert!(rest.starts_with("Priority mail sent from "));
+    match indexer::decode_log(event) {
+        Some(MailerActivity::Sent(mail)) => assert_eq!(mail.to, SendTarget::Wallet(recipient)),
+        other => panic!("unexpected activity {:?}", other),
+    }
+    assert!(!logs
+        .iter()
+        .any(|log| log.contains(&mailer::recipient_log_prefix(&Pubkey::new_unique()))));
+
+    assert_eq!(indexer::split_recipient_key("[rcpt:0a1b] Recipient"), None);
+    assert_eq!(
+        indexer::split_recipient_key("Fee updated from 1 to 2"),
+        None
+    );
+}
+
+// ============================================================================
+// CPI BUILDER TESTS
+// ============================================================================
+
+#[tokio::test]
+async fn test_cpi_builder_matches_send_layout() {
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    // Create USDC mint and accounts
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    // Initialize the program first
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Create token accounts
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+
+    // Mint USDC to sender
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await; // 1 USDC
+
+    // Get recipient claim PDA
+    let (recipient_claim_pda, _) = get_claim_pda(&payer.pubkey());
+
+    // Off-chain stand-ins for the AccountInfos a calling program would pass
+    let keys = [
+        payer.pubkey(),
+        recipient_claim_pda,
+        mailer_pda,
+        sender_usdc,
+        mailer_usdc,
+        spl_token::id(),
+        system_program::id(),
+    ];
+    let owner = Pubkey::default();
+    let mut lamports = [0u64; 7];
+    let mut data = [[0u8; 0]; 7];
+    let infos: Vec<AccountInfo> = keys
+        .iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .map(|((key, lamports), data)| {
+            AccountInfo::new(key, false, false, lamports, &mut data[..], &owner, false, 0)
+        })
+        .collect();
+
+    let instruction = cpi::accounts::Send {
+        sender: infos[0].clone(),
+        recipient_claim: infos[1].clone(),
+        mailer_account: infos[2].clone(),
+        sender_usdc: infos[3].clone(),
+        mailer_usdc: infos[4].clone(),
+        token_program: infos[5].clone(),
+        system_program: infos[6].clone(),
+    }
+    .instruction(
+        &program_id(),
+        payer.pubkey(),
+        "Test Subject".to_string(),
+        "Test message body".to_string(),
+        true,
+        false,
+        false,
+        None,
+        None,
+        false,
+        None,
+        None,
+        false,
+        0,
+        false,
+        None,
+    );
+    let policy_pda = get_policy_pda(&payer.pubkey()).0;
+
+    // Signer and writable flags come from the instruction layout, not the AccountInfos
+    assert_eq!(
+        instruction.accounts,
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(recipient_claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ]
+    );
+
+    // The rent payer, inbox policy and message nonce are trailing accounts a caller passes
+    // with `invoke_with_remaining`
+    let mut instruction = instruction;
+    instruction.accounts.extend([
+        AccountMeta::new(payer.pubkey(), true),
+        AccountMeta::new_readonly(policy_pda, false),
+        AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+    ]);
+    let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let claim_account = banks_client
+        .get_account(recipient_claim_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let recipient_claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    assert_eq!(recipient_claim.amount, 90_000);
+}
+
+// ============================================================================
+// Rent Reserve Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_rent_reserves_tracked_and_exposed_by_get_config() {
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        20_000_000,
+    )
+    .await;
+
+    // Priority send creates a claim account, delegation and discount create one each
+    let recipient = Keypair::new();
+    let (claim_pda, _) = get_claim_pda(&recipient.pubkey());
+    let (delegation_pda, _) = get_delegation_pda(&payer.pubkey());
+    let delegate = Pubkey::new_unique();
+    let discounted = Keypair::new();
+    let (discount_pda, _) = get_fee_discount_pda(&discounted.pubkey());
+
+    let instructions = [
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient.pubkey(),
+                subject: "Reserve".to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: true,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            ],
+        ),
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::DelegateTo {
+                delegate: Some(delegate),
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(delegation_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(get_delegate_index_pda(&delegate).0, false),
+            ],
+        ),
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SetCustomFeePercentage {
+                account: discounted.pubkey(),
+                percentage: 50,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(discount_pda, false),
+                AccountMeta::new_readonly(discounted.pubkey(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        ),
+    ];
+
+    let mut transaction = Transaction::new_with_payer(&instructions, Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let rent = banks_client.get_rent().await.unwrap();
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+
+    assert_eq!(
+        mailer_state.claim_rent_reserve,
+        mailer::costs::recipient_claim(&rent)
+    );
+    assert_eq!(
+        mailer_state.delegation_rent_reserve,
+        mailer::costs::delegation(&rent)
+    );
+    assert_eq!(
+        mailer_state.discount_rent_reserve,
+        mailer::costs::fee_discount(&rent)
+    );
+
+    // A second send to the same recipient reuses the claim account
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(&instructions[..1], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // GetConfig returns the state, counters included, as return data
+    let get_config_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::GetConfig,
+        vec![AccountMeta::new_readonly(mailer_pda, false)],
+    );
+
+    let mut transaction =
+        Transaction::new_with_payer(&[get_config_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let simulation = banks_client
+        .simulate_transaction(transaction)
+        .await
+        .unwrap();
+    let return_data = simulation.simulation_details.unwrap().return_data.unwrap();
+
+    assert_eq!(return_data.program_id, program_id());
+    let config: MailerState = BorshDeserialize::deserialize(&mut &return_data.data[..]).unwrap();
+    assert_eq!(config.owner, payer.pubkey());
+    assert_eq!(
+        config.claim_rent_reserve,
+        mailer::costs::recipient_claim(&rent)
+    );
+    assert_eq!(
+        config.delegation_rent_reserve,
+        mailer::costs::delegation(&rent)
+    );
+    assert_eq!(
+        config.discount_rent_reserve,
+        mailer::costs::fee_discount(&rent)
+    );
+    assert_eq!(
+        config.total_rent_reserve(),
+        mailer::costs::recipient_claim(&rent)
+            + mailer::costs::delegation(&rent)
+            + mailer::costs::fee_discount(&rent)
+    );
+}
+
+// ============================================================================
+// Pre-authorized Send Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_send_preauthorized_within_bounds() {
+    use solana_program::clock::Clock;
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+
+    let start: i64 = 1_700_000_000;
+    context.set_sysvar(&Clock {
+        unix_timestamp: start,
+        ..Clock::default()
+    });
+    let recent_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+    let banks_client = &mut context.banks_client;
+    let payer = &context.payer;
+
+    let usdc_mint = create_usdc_mint(banks_client, payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let user = payer.pubkey();
+    let relayer = Keypair::new();
+    let user_usdc =
+        create_token_account(banks_client, payer, recent_blockhash, &usdc_mint, &user).await;
+    let mailer_usdc = create_token_account(
+        banks_client,
+        payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        banks_client,
+        payer,
+        recent_blockhash,
+        &usdc_mint,
+        &user_usdc,
+        1_000_000,
+    )
+    .await;
+
+    // Fund the relayer so it can pay for claim account creation
+    let fund_instruction =
+        solana_sdk::system_instruction::transfer(&user, &relayer.pubkey(), 100_000_000);
+    let mut transaction = Transaction::new_with_payer(&[fund_instruction], Some(&user));
+    transaction.sign(&[payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let (preauth_pda, _) = mailer::pda::preauthorization(&program_id(), &user, &relayer.pubkey());
+
+    // Two priority sends of at most the full 0.1 USDC fee, scoped to one webhook
+    let create_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::CreatePreauthorization {
+            relayer: relayer.pubkey(),
+            webhook_id: "webhook-123".to_string(),
+            max_sends: 2,
+            max_fee_per_send: 100_000,
+            expires_at: start + 24 * 60 * 60,
+        },
+        vec![
+            AccountMeta::new(user, true),
+            AccountMeta::new(preauth_pda, false),
+            AccountMeta::new(user_usdc, false),
+            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[create_instruction], Some(&user));
+    transaction.sign(&[payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let token_account = banks_client.get_account(user_usdc).await.unwrap().unwrap();
+    let token_state = TokenAccount::unpack(&token_account.data).unwrap();
+    assert_eq!(
+        token_state.delegate,
+        solana_program::program_option::COption::Some(preauth_pda)
+    );
+    assert_eq!(token_state.delegated_amount, 200_000);
+
+    let recipient = Pubkey::new_unique();
+    let (claim_pda, _) = get_claim_pda(&recipient);
+    let send_instruction = |webhook_id: &str, nonce: u64| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SendPreauthorized {
+                to: recipient,
+                webhook_id: webhook_id.to_string(),
+                revenue_share_to_receiver: true,
+            },
+            vec![
+                AccountMeta::new(relayer.pubkey(), true),
+                AccountMeta::new(preauth_pda, false),
+                AccountMeta::new_readonly(user, false),
+                AccountMeta::new(claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(user_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                // Distinguishes otherwise identical transactions
+                AccountMeta::new_readonly(Pubkey::new_from_array([nonce as u8; 32]), false),
+                AccountMeta::new_readonly(get_policy_pda(&recipient).0, false),
+                AccountMeta::new(get_message_nonce_pda(&user).0, false),
+            ],
+        )
+    };
+
+    // Only the pre-authorized webhook may be used
+    let mut transaction =
+        Transaction::new_with_payer(&[send_instruction("other", 0)], Some(&relayer.pubkey()));
+    transaction.sign(&[&relayer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+
+    // The relayer sends twice without the user signing
+    for nonce in 1..=2 {
+        let mut transaction = Transaction::new_with_payer(
+            &[send_instruction("webhook-123", nonce)],
+            Some(&relayer.pubkey()),
+        );
+        transaction.sign(&[&relayer], recent_blockhash);
+        banks_client.process_transaction(transaction).await.unwrap();
+    }
+
+    let token_account = banks_client.get_account(user_usdc).await.unwrap().unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&token_account.data).unwrap().amount,
+        800_000
+    );
+
+    let claim_account = banks_client.get_account(claim_pda).await.unwrap().unwrap();
+    let claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    assert_eq!(claim.amount, 180_000);
+
+    let preauth_account = banks_client
+        .get_account(preauth_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let preauth: Preauthorization =
+        BorshDeserialize::deserialize(&mut &preauth_account.data[8..]).unwrap();
+    assert_eq!(preauth.sends_used, 2);
+
+    // The send limit is exhausted
+    let mut transaction = Transaction::new_with_payer(
+        &[send_instruction("webhook-123", 3)],
+        Some(&relayer.pubkey()),
+    );
+    transaction.sign(&[&relayer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+
+    // Recreating with a lower fee cap rejects the full priority fee, then revoking clears the delegation
+    let recreate_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::CreatePreauthorization {
+            relayer: relayer.pubkey(),
+            webhook_id: String::new(),
+            max_sends: 5,
+            max_fee_per_send: 50_000,
+            expires_at: start + 24 * 60 * 60,
+        },
+        vec![
+            AccountMeta::new(user, true),
+            AccountMeta::new(preauth_pda, false),
+            AccountMeta::new(user_usdc, false),
+            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[recreate_instruction], Some(&user));
+    transaction.sign(&[payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mut transaction =
+        Transaction::new_with_payer(&[send_instruction("any", 4)], Some(&relayer.pubkey()));
+    transaction.sign(&[&relayer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+
+    let revoke_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::RevokePreauthorization {
+            relayer: relayer.pubkey(),
+        },
+        vec![
+            AccountMeta::new(user, true),
+            AccountMeta::new(preauth_pda, false),
+            AccountMeta::new(user_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[revoke_instruction], Some(&user));
+    transaction.sign(&[payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let token_account = banks_client.get_account(user_usdc).await.unwrap().unwrap();
+    let token_state = TokenAccount::unpack(&token_account.data).unwrap();
+    assert_eq!(
+        token_state.delegate,
+        solana_program::program_option::COption::None
+    );
+
+    let preauth_account = banks_client
+        .get_account(preauth_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let preauth: Preauthorization =
+        BorshDeserialize::deserialize(&mut &preauth_account.data[8..]).unwrap();
+    assert!(!preauth.is_active(start));
+}
+
+// ============================================================================
+// Instruction Kill-Switch Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_disabled_instruction_is_rejected_until_reenabled() {
+    use solana_program::instruction::InstructionError;
+    use solana_sdk::transaction::TransactionError;
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let send_to_email = |subject: &str| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SendToEmail {
+                to_email: "ops@example.com".to_string(),
+                subject: subject.to_string(),
+                _body: "Body".to_string(),
+                strict: false,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    };
+    let set_disabled = |tag: u8, disabled: bool, owner: Pubkey| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SetInstructionDisabled { tag, disabled },
+            vec![
+                AccountMeta::new_readonly(owner, true),
+                AccountMeta::new(mailer_pda, false),
+            ],
+        )
+    };
+    let send_tag = send_to_email("tag").data[0];
+    let setter_tag = set_disabled(0, false, payer.pubkey()).data[0];
+
+    // Only the owner can flip the switch
+    let stranger = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[set_disabled(send_tag, true, stranger.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &stranger], recent_blockhash);
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::OnlyOwner as u32)
+        )
+    );
+
+    // The setter cannot disable itself
+    let mut transaction = Transaction::new_with_payer(
+        &[set_disabled(setter_tag, true, payer.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::InvalidInstructionTag as u32)
+        )
+    );
+
+    // Disable SendToEmail: it fails while other instructions keep working
+    let mut transaction = Transaction::new_with_payer(
+        &[set_disabled(send_tag, true, payer.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &account.data[8..]).unwrap();
+    assert!(mailer_state.is_instruction_disabled(send_tag));
+
+    let mut transaction =
+        Transaction::new_with_payer(&[send_to_email("blocked")], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::InstructionDisabled as u32)
+        )
+    );
+
+    let set_fee = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetFee {
+            new_fee: 200_000,
+            expected_current_value: None,
+        },
+        vec![
+            AccountMeta::new_readonly(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[set_fee], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Re-enable and send
+    let mut transaction = Transaction::new_with_payer(
+        &[set_disabled(send_tag, false, payer.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mut transaction =
+        Transaction::new_with_payer(&[send_to_email("allowed")], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState = BorshDeserialize::deserialize(&mut &account.data[8..]).unwrap();
+    assert_eq!(mailer_state.disabled_instructions, 0);
+    assert_eq!(mailer_state.owner_claimable, 20_000);
+}
+
+// ============================================================================
+// Discriminator Migration Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_migrate_discriminator_rewrites_legacy_accounts() {
+    use mailer::discriminator;
+    use solana_sdk::account::AccountSharedData;
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let recent_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+    let recipient = context.payer.pubkey();
+    let (prefs_pda, _) = get_prefs_pda(&recipient);
+
+    let prefs_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetRecipientPrefs {
+            read_receipts_opt_out: true,
+        },
+        vec![
+            AccountMeta::new(recipient, true),
+            AccountMeta::new(prefs_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[prefs_instruction], Some(&recipient));
+    transaction.sign(&[&context.payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // New accounts are created with the SHA-256 discriminator
+    let mut account = context
+        .banks_client
+        .get_account(prefs_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.data[..8], discriminator::account("RecipientPrefs"));
+
+    // Simulate an account written by an earlier deployment
+    account.data[..8].copy_from_slice(&discriminator::legacy("RecipientPrefs").unwrap());
+    context.set_account(&prefs_pda, &AccountSharedData::from(account));
+
+    let migrate_instruction = |accounts: Vec<Pubkey>| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::MigrateDiscriminator,
+            accounts
+                .into_iter()
+                .map(|key| AccountMeta::new(key, false))
+                .collect(),
+        )
+    };
+
+    // Anyone may migrate; running it twice is a no-op
+    let cranker = Keypair::new();
+    let fund_instruction =
+        solana_sdk::system_instruction::transfer(&recipient, &cranker.pubkey(), 10_000_000);
+    let mut transaction = Transaction::new_with_payer(
+        &[fund_instruction, migrate_instruction(vec![prefs_pda])],
+        Some(&recipient),
+    );
+    transaction.sign(&[&context.payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let mut transaction = Transaction::new_with_payer(
+        &[migrate_instruction(vec![prefs_pda])],
+        Some(&cranker.pubkey()),
+    );
+    transaction.sign(&[&cranker], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let account = context
+        .banks_client
+        .get_account(prefs_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.data[..8], discriminator::account("RecipientPrefs"));
+    let prefs: RecipientPrefs = BorshDeserialize::deserialize(&mut &account.data[8..]).unwrap();
+    assert_eq!(prefs.recipient, recipient);
+    assert!(prefs.read_receipts_opt_out);
+
+    // Accounts owned by other programs are rejected
+    let mut transaction = Transaction::new_with_payer(
+        &[migrate_instruction(vec![cranker.pubkey()])],
+        Some(&cranker.pubkey()),
+    );
+    transaction.sign(&[&cranker], recent_blockhash);
+    assert!(context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_err());
+}
+
+// ============================================================================
+// Account Close Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_close_accounts_refund_rent_once_cleared() {
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let payer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer_usdc,
+        20_000_000,
+    )
+    .await;
+
+    // The payer sends to itself (creating a claim), delegates and gets a discount
+    let (claim_pda, _) = get_claim_pda(&payer.pubkey());
+    let (delegation_pda, _) = get_delegation_pda(&payer.pubkey());
+    let (discount_pda, _) = get_fee_discount_pda(&payer.pubkey());
+    let delegate = Pubkey::new_unique();
+    let (delegate_index_pda, _) = get_delegate_index_pda(&delegate);
+    let delegate_instruction = |delegate: Option<Pubkey>| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::DelegateTo { delegate },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(delegation_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(payer_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(delegate_index_pda, false),
+            ],
+        )
+    };
+    let setup = [
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: payer.pubkey(),
+                subject: "Close".to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: true,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(payer_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(get_policy_pda(&payer.pubkey()).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            ],
+        ),
+        delegate_instruction(Some(delegate)),
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SetCustomFeePercentage {
+                account: payer.pubkey(),
+                percentage: 50,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(discount_pda, false),
+                AccountMeta::new_readonly(payer.pubkey(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        ),
+    ];
+    let mut transaction = Transaction::new_with_payer(&setup, Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    let closure_log_pda = initialize_closure_log(&mut banks_client, &payer, recent_blockhash).await;
+
+    let close_instructions = [
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::CloseRecipientClaim {
+                recipient: payer.pubkey(),
+            },
+            vec![
+                AccountMeta::new_readonly(payer.pubkey(), true),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(claim_pda, false),
+                AccountMeta::new(payer.pubkey(), false),
+                AccountMeta::new(closure_log_pda, false),
+            ],
+        ),
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::CloseDelegation,
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(delegation_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(closure_log_pda, false),
+            ],
+        ),
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::CloseFeeDiscount {
+                account: payer.pubkey(),
+            },
+            vec![
+                AccountMeta::new_readonly(payer.pubkey(), true),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(discount_pda, false),
+                AccountMeta::new(payer.pubkey(), false),
+            ],
+        ),
+    ];
+
+    // Nothing closes while it still holds a balance or an active setting
+    for instruction in &close_instructions {
+        let mut transaction =
+            Transaction::new_with_payer(std::slice::from_ref(instruction), Some(&payer.pubkey()));
+        transaction.sign(&[&payer], recent_blockhash);
+        assert!(banks_client.process_transaction(transaction).await.is_err());
+    }
+
+    // Claim the share, clear the delegation and the discount
+    let clear = [
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::ClaimRecipientShare {
+                amount: None,
+                split: None,
+                close_to: None,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(payer_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+            ],
+        ),
+        delegate_instruction(None),
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::ClearCustomFeePercentage {
+                account: payer.pubkey(),
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(mailer_pda, false),
+                AccountMeta::new(discount_pda, false),
+                AccountMeta::new(payer.pubkey(), true),
+            ],
+        ),
+    ];
+    let mut transaction = Transaction::new_with_payer(&clear, Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // A refund destination other than the owner is rejected
+    let mut misdirected = close_instructions[0].clone();
+    misdirected.accounts[3] = AccountMeta::new(Pubkey::new_unique(), false);
+    let mut transaction = Transaction::new_with_payer(&[misdirected], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+
+    let rent = banks_client.get_rent().await.unwrap();
+    let reclaimed = mailer::costs::recipient_claim(&rent)
+        + mailer::costs::delegation(&rent)
+        + mailer::costs::fee_discount(&rent);
+    let balance_before = banks_client.get_balance(payer.pubkey()).await.unwrap();
+    let mut final_states = Vec::new();
+    for address in [claim_pda, delegation_pda] {
+        let account = banks_client.get_account(address).await.unwrap().unwrap();
+        final_states.push((
+            address,
+            solana_program::hash::hash(&account.data).to_bytes(),
+        ));
+    }
+
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(&close_instructions, Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // All three accounts are gone and their rent is back with the payer, less the tx fee
+    for address in [claim_pda, delegation_pda, discount_pda] {
+        assert!(banks_client.get_account(address).await.unwrap().is_none());
+    }
+    let balance_after = banks_client.get_balance(payer.pubkey()).await.unwrap();
+    assert_eq!(balance_after, balance_before + reclaimed - 5_000);
+
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.total_rent_reserve(), 0);
+
+    // The closure log commits to the final state of the claim and the delegation
+    let log_account = banks_client
+        .get_account(closure_log_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        &log_account.data[..8],
+        &mailer::discriminator::account("ClosureLog")
+    );
+    let log: ClosureLog = BorshDeserialize::deserialize(&mut &log_account.data[8..]).unwrap();
+    assert_eq!(log.total, 2);
+    let recorded: Vec<_> = log
+        .records
+        .iter()
+        .map(|r| (r.account, r.state_hash))
+        .collect();
+    assert_eq!(recorded, final_states);
+}
+
+// ============================================================================
+// Compute Report Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_send_instructions_report_remaining_compute() {
+    use mailer::ComputeReport;
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let recipient = Pubkey::new_unique();
+    let (claim_pda, _) = get_claim_pda(&recipient);
+    let sends = [
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient,
+                subject: "Compute".to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: true,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(get_policy_pda(&recipient).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            ],
+        ),
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SendToEmail {
+                to_email: "user@example.com".to_string(),
+                subject: "Compute".to_string(),
+                _body: "Body".to_string(),
+                strict: false,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        ),
+    ];
+
+    for send in sends {
+        let mut transaction = Transaction::new_with_payer(&[send], Some(&payer.pubkey()));
+        transaction.sign(&[&payer], recent_blockhash);
+        let simulation = banks_client
+            .simulate_transaction(transaction)
+            .await
+            .unwrap();
+        let return_data = simulation.simulation_details.unwrap().return_data.unwrap();
+
+        assert_eq!(return_data.program_id, program_id());
+        // Builtin test processors report 0 remaining units; on-chain the syscall measures them
+        let report: ComputeReport =
+            BorshDeserialize::deserialize(&mut &return_data.data[..]).unwrap();
+        assert!(report.remaining_units <= 1_400_000);
+    }
+
+    let report = ComputeReport {
+        remaining_units: 150_000,
+    };
+    assert_eq!(report.consumed_units(200_000), 50_000);
+    assert_eq!(report.recommended_limit(200_000), 55_000);
+    assert_eq!(report.consumed_units(100_000), 0);
+}
+
+// ============================================================================
+// Account Version Migration Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_migrate_account_version_moves_previous_version_accounts() {
+    use mailer::{discriminator, pda, MigrationKind};
+
+    let mut program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+
+    // Accounts created under the previous seed version
+    let user = Keypair::new();
+    let delegate = Pubkey::new_unique();
+    let (old_claim, old_claim_bump) = pda::versioned(
+        &program_id(),
+        MigrationKind::RecipientClaim,
+        &user.pubkey(),
+        pda::PREVIOUS_PDA_VERSION,
+    );
+    let (old_delegation, old_delegation_bump) = pda::versioned(
+        &program_id(),
+        MigrationKind::Delegation,
+        &user.pubkey(),
+        pda::PREVIOUS_PDA_VERSION,
+    );
+    let mut claim_data = discriminator::account("RecipientClaim").to_vec();
+    claim_data.extend(
+        borsh::to_vec(&RecipientClaim {
+            recipient: user.pubkey(),
+            amount: 70_000,
+            timestamp: 1,
+            bump: old_claim_bump,
+            paused_offset: 0,
+            no_expiry: false,
+            surcharge_paid_until: 0,
+            extensions: 0,
+            messages_received: 0,
+            total_claimed: 0,
+        })
+        .unwrap(),
+    );
+    // Not yet migrated to SHA-256 discriminators either
+    let mut delegation_data = discriminator::legacy("Delegation").unwrap().to_vec();
+    delegation_data.extend(
+        borsh::to_vec(&Delegation {
+            delegator: user.pubkey(),
+            delegate: Some(delegate),
+            bump: old_delegation_bump,
+            escrowed_fee: 0,
+            refund_deadline: 0,
+            fee_refundable: false,
+        })
+        .unwrap(),
+    );
+    for (address, data) in [(old_claim, claim_data), (old_delegation, delegation_data)] {
+        program_test.add_account(
+            address,
+            solana_sdk::account::Account {
+                lamports: 5_000_000,
+                data,
+                owner: program_id(),
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+    }
+    program_test.add_account(
+        user.pubkey(),
+        solana_sdk::account::Account::new(1_000_000_000, 0, &system_program::id()),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // A priority send after the version bump credits the current claim
+    let payer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let (claim_pda, claim_bump) = get_claim_pda(&user.pubkey());
+    let (delegation_pda, delegation_bump) = get_delegation_pda(&user.pubkey());
+    let send_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Send {
+            to: user.pubkey(),
+            subject: "Migrate".to_string(),
+            _body: "Body".to_string(),
+            revenue_share_to_receiver: true,
+            resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(payer_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&user.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let migrate_instruction = |kind: MigrationKind, old: Pubkey, new: Pubkey| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::MigrateAccountVersion { kind },
+            vec![
+                AccountMeta::new(user.pubkey(), true),
+                AccountMeta::new(old, false),
+                AccountMeta::new(new, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    };
+
+    // Only the subject can move its accounts
+    let mut wrong_subject =
+        migrate_instruction(MigrationKind::RecipientClaim, old_claim, claim_pda);
+    wrong_subject.accounts[0] = AccountMeta::new(payer.pubkey(), true);
+    let mut transaction = Transaction::new_with_payer(&[wrong_subject], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+
+    let user_lamports = banks_client.get_balance(user.pubkey()).await.unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            migrate_instruction(MigrationKind::RecipientClaim, old_claim, claim_pda),
+            migrate_instruction(MigrationKind::Delegation, old_delegation, delegation_pda),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // The old balance joins the one credited under the current seeds
+    let claim_account = banks_client.get_account(claim_pda).await.unwrap().unwrap();
+    let claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    assert_eq!(claim.amount, 70_000 + 90_000);
+    assert_eq!(claim.bump, claim_bump);
+    assert!(claim.timestamp > 1);
+
+    let delegation_account = banks_client
+        .get_account(delegation_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        &delegation_account.data[..8],
+        &discriminator::account("Delegation")
+    );
+    let delegation: Delegation =
+        BorshDeserialize::deserialize(&mut &delegation_account.data[8..]).unwrap();
+    assert_eq!(delegation.delegator, user.pubkey());
+    assert_eq!(delegation.delegate, Some(delegate));
+    assert_eq!(delegation.bump, delegation_bump);
+
+    // Old accounts are closed and their rent refunded; the user paid only the new delegation
+    assert!(banks_client.get_account(old_claim).await.unwrap().is_none());
+    assert!(banks_client
+        .get_account(old_delegation)
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        banks_client.get_balance(user.pubkey()).await.unwrap(),
+        user_lamports + 2 * 5_000_000 - delegation_account.lamports
+    );
+
+    // Nothing is left to migrate
+    let mut transaction = Transaction::new_with_payer(
+        &[migrate_instruction(
+            MigrationKind::RecipientClaim,
+            old_claim,
+            claim_pda,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+}
+
+// ============================================================================
+// Strict Send Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_strict_sends_fail_when_fee_cannot_be_paid() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // No USDC is minted, so every fee transfer fails
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    let webhook_pda =
+        register_webhook(&mut banks_client, &payer, recent_blockhash, "webhook-1").await;
+
+    let recipient = Pubkey::new_unique();
+    let (claim_pda, _) = get_claim_pda(&recipient);
+    let wallet_accounts = vec![
+        AccountMeta::new(payer.pubkey(), true),
+        AccountMeta::new(claim_pda, false),
+        AccountMeta::new(mailer_pda, false),
+        AccountMeta::new(sender_usdc, false),
+        AccountMeta::new(mailer_usdc, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new(payer.pubkey(), true),
+        AccountMeta::new_readonly(get_policy_pda(&recipient).0, false),
+        AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+    ];
+    // The webhook is the last fixed account, ahead of the rent payer and inbox policy
+    let mut webhook_accounts = wallet_accounts.clone();
+    webhook_accounts.insert(7, AccountMeta::new(webhook_pda, false));
+    let email_accounts = vec![
+        AccountMeta::new(payer.pubkey(), true),
+        AccountMeta::new(mailer_pda, false),
+        AccountMeta::new(sender_usdc, false),
+        AccountMeta::new(mailer_usdc, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    let sends = |strict: bool| {
+        vec![
+            (
+                MailerInstruction::Send {
+                    to: recipient,
+                    subject: "Strict".to_string(),
+                    _body: "Body".to_string(),
+                    revenue_share_to_receiver: true,
+                    resolve_sender_to_name: false,
+                    request_read_receipt: false,
+                    receipt_nonce: None,
+                    memo: None,
+                    strict,
+                    thread_id: None,
+                    in_reply_to: None,
+                    allow_downgrade: false,
+                    tip: 0,
+                    bridge: false,
+                    idempotency_nonce: None,
+                },
+                &wallet_accounts,
+            ),
+            (
+                MailerInstruction::SendPrepared {
+                    to: recipient,
+                    mail_id: "mail-1".to_string(),
+                    revenue_share_to_receiver: false,
+                    resolve_sender_to_name: false,
+                    strict,
+                    thread_id: None,
+                    in_reply_to: None,
+                    allow_downgrade: false,
+                    tip: 0,
+                },
+                &wallet_accounts,
+            ),
+            (
+                MailerInstruction::SendThroughWebhook {
+                    to: recipient,
+                    webhook_id: "webhook-1".to_string(),
+                    revenue_share_to_receiver: true,
+                    resolve_sender_to_name: false,
+                    strict,
+                    thread_id: None,
+                    in_reply_to: None,
+                    allow_downgrade: false,
+                    tip: 0,
+                },
+                &webhook_accounts,
+            ),
+            (
+                MailerInstruction::SendToEmail {
+                    to_email: "user@example.com".to_string(),
+                    subject: "Strict".to_string(),
+                    _body: "Body".to_string(),
+                    strict,
+                },
+                &email_accounts,
+            ),
+            (
+                MailerInstruction::SendPreparedToEmail {
+                    to_email: "user@example.com".to_string(),
+                    mail_id: "mail-2".to_string(),
+                    strict,
+                },
+                &email_accounts,
+            ),
+        ]
+    };
+
+    for (send, accounts) in sends(true) {
+        let instruction = Instruction::new_with_borsh(program_id(), &send, accounts.clone());
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer], recent_blockhash);
+        let err = banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap();
+        assert_eq!(
+            err,
+            TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(mailer::MailerError::InsufficientFee as u32)
+            )
+        );
+    }
+    assert!(banks_client.get_account(claim_pda).await.unwrap().is_none());
+
+    // Without strict, the same sends go through unpaid
+    for (send, accounts) in sends(false) {
+        let instruction = Instruction::new_with_borsh(program_id(), &send, accounts.clone());
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer], recent_blockhash);
+        banks_client.process_transaction(transaction).await.unwrap();
+    }
+
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.owner_claimable, 0);
+}
+
+// ============================================================================
+// Spam Oracle Tests
+// ============================================================================
+
+/// Test oracle scoring every send 50
+fn fixed_score_oracle(
+    _program_id: &Pubkey,
+    _accounts: &[AccountInfo],
+    data: &[u8],
+) -> solana_program::entrypoint::ProgramResult {
+    mailer::spam_oracle::decode_score_request(data)
+        .ok_or(solana_program::program_error::ProgramError::InvalidInstructionData)?;
+    solana_program::program::set_return_data(&50u64.to_le_bytes());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_spam_oracle_scores_and_rejects_sends() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let oracle = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    program_test.add_program("spam_oracle", oracle, processor!(fixed_score_oracle));
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
+
+    let set_oracle = |oracle: Option<Pubkey>, threshold: Option<u64>| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SetSpamOracle { oracle, threshold },
+            vec![
+                AccountMeta::new_readonly(payer.pubkey(), true),
+                AccountMeta::new(mailer_pda, false),
+            ],
+        )
+    };
+    let recipient = Pubkey::new_unique();
+    let (claim_pda, _) = get_claim_pda(&recipient);
+    let send = |subject: &str, with_oracle: bool| {
+        let mut accounts = vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ];
+        if with_oracle {
+            accounts.push(AccountMeta::new_readonly(oracle, false));
+        }
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient,
+                subject: subject.to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: false,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            accounts,
+        )
+    };
+    let custom_error = |error: mailer::MailerError| {
+        TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+    };
+
+    // A threshold needs an oracle, and only the owner registers one
+    let mut transaction =
+        Transaction::new_with_payer(&[set_oracle(None, Some(10))], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+
+    let stranger = Keypair::new();
+    let mut foreign = set_oracle(Some(oracle), None);
+    foreign.accounts[0] = AccountMeta::new_readonly(stranger.pubkey(), true);
+    let mut transaction = Transaction::new_with_payer(&[foreign], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &stranger], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+
+    // Without a threshold the score is only recorded in the send log
+    let mut transaction = Transaction::new_with_payer(
+        &[set_oracle(Some(oracle), None), send("Scored", true)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert!(result.result.is_ok(), "{:?}", result.result);
+    let activity = indexer::decode_logs(&result.metadata.unwrap().log_messages);
+    assert!(matches!(
+        activity.as_slice(),
+        [MailerActivity::Sent(SentMail {
+            spam_score: Some(50),
+            ..
+        })]
+    ));
+
+    // The oracle cannot be skipped by leaving it out
+    let mut transaction =
+        Transaction::new_with_payer(&[send("Unscored", false)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(err, custom_error(mailer::MailerError::InvalidSpamOracle));
+
+    // Scores above the threshold are rejected
+    let mut transaction =
+        Transaction::new_with_payer(&[set_oracle(Some(oracle), Some(40))], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mut transaction = Transaction::new_with_payer(&[send("Spam", true)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(err, custom_error(mailer::MailerError::SpamScoreTooHigh));
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            set_oracle(Some(oracle), Some(50)),
+            send("At threshold", true),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Clearing the oracle restores sends without it
+    let mut transaction = Transaction::new_with_payer(
+        &[set_oracle(None, None), send("Unchecked", false)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.spam_oracle, None);
+    assert_eq!(mailer_state.spam_threshold, None);
+}
+
+// ============================================================================
+// Rate Limit Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_rate_limit_caps_sends_per_window() {
+    use solana_sdk::{clock::Clock, instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let recent_blockhash = context.last_blockhash;
+    let payer = context.payer.insecure_clone();
+
+    let usdc_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
+
+    let set_rate_limit = |max_sends: u64, window: i64| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SetRateLimit { max_sends, window },
+            vec![
+                AccountMeta::new_readonly(payer.pubkey(), true),
+                AccountMeta::new(mailer_pda, false),
+            ],
+        )
+    };
+    let (rate_limit_pda, _) = mailer::pda::rate_limit(&program_id(), &payer.pubkey());
+    let recipient = Pubkey::new_unique();
+    let (claim_pda, _) = get_claim_pda(&recipient);
+    let send = |subject: &str, with_rate_limit: bool| {
+        let mut accounts = vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ];
+        if with_rate_limit {
+            accounts.push(AccountMeta::new(rate_limit_pda, false));
+        }
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient,
+                subject: subject.to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: false,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            accounts,
+        )
+    };
+    // Email sends take the system program as a trailing account to create the PDA
+    let send_to_email = |subject: &str| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SendToEmail {
+                to_email: "user@example.com".to_string(),
+                subject: subject.to_string(),
+                _body: "Body".to_string(),
+                strict: false,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new(rate_limit_pda, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            ],
+        )
+    };
+    let custom_error = |error: mailer::MailerError| {
+        TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+    };
+
+    // A limit needs a window
+    let mut transaction =
+        Transaction::new_with_payer(&[set_rate_limit(2, 0)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_err());
+
+    let mut transaction =
+        Transaction::new_with_payer(&[set_rate_limit(2, 3_600)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // The rate limit account cannot be skipped by leaving it out
+    let mut transaction =
+        Transaction::new_with_payer(&[send("Unlimited", false)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let err = context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(err, custom_error(mailer::MailerError::InvalidPDA));
+
+    // Wallet and email sends share the sender's window
+    let mut transaction = Transaction::new_with_payer(
+        &[send("First", true), send_to_email("Second")],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let account = context
+        .banks_client
+        .get_account(rate_limit_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.owner, program_id());
+    assert_eq!(
+        account.data[..8],
+        mailer::discriminator::account("RateLimit")
+    );
+    let rate_limit: RateLimit = BorshDeserialize::deserialize(&mut &account.data[8..]).unwrap();
+    assert_eq!(rate_limit.sender, payer.pubkey());
+    assert_eq!(rate_limit.count, 2);
+
+    let mut transaction =
+        Transaction::new_with_payer(&[send_to_email("Third")], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let err = context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(err, custom_error(mailer::MailerError::RateLimitExceeded));
+
+    // The next window starts once the current one has elapsed
+    context.set_sysvar(&Clock {
+        unix_timestamp: rate_limit.window_start + 3_600,
+        ..Clock::default()
+    });
+    let mut transaction =
+        Transaction::new_with_payer(&[send("Next window", true)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let account = context
+        .banks_client
+        .get_account(rate_limit_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let next: RateLimit = BorshDeserialize::deserialize(&mut &account.data[8..]).unwrap();
+    assert_eq!(next.window_start, rate_limit.window_start + 3_600);
+    assert_eq!(next.count, 1);
+
+    // Disabling the limit restores sends without the account
+    let mut transaction = Transaction::new_with_payer(
+        &[set_rate_limit(0, 0), send("Unlimited again", false)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let mailer_account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.rate_limit_max_sends, 0);
+}
+
+// ============================================================================
+// Promo Code Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_promo_codes_grant_discounts_until_exhausted() {
+    use solana_sdk::{clock::Clock, instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let now = banks_client
+        .get_sysvar::<Clock>()
+        .await
+        .unwrap()
+        .unix_timestamp;
+    let code_hash = |code: &str| solana_program::hash::hash(code.as_bytes()).to_bytes();
+    let create_promo = |owner: &Pubkey, discount: u8, expiry: i64| {
+        let code_hash = code_hash("SPRING25");
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::CreatePromo {
+                code_hash,
+                discount,
+                max_redemptions: 2,
+                expiry,
+            },
+            vec![
+                AccountMeta::new(*owner, true),
+                AccountMeta::new_readonly(mailer_pda, false),
+                AccountMeta::new(mailer::pda::promo(&program_id(), &code_hash).0, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    };
+    let redeem = |user: &Pubkey, code: &str| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::RedeemPromo {
+                code: code.to_string(),
+            },
+            vec![
+                AccountMeta::new(*user, true),
+                AccountMeta::new(mailer::pda::promo(&program_id(), &code_hash(code)).0, false),
+                AccountMeta::new(get_fee_discount_pda(user).0, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    };
+    let custom_error = |error: mailer::MailerError| {
+        TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+    };
+
+    // Only the owner creates promos, with a valid discount and a future expiry
+    let stranger = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[create_promo(&stranger.pubkey(), 25, now + 3_600)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &stranger], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+
+    for (discount, expiry) in [(101, now + 3_600), (25, now)] {
+        let mut transaction = Transaction::new_with_payer(
+            &[create_promo(&payer.pubkey(), discount, expiry)],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer], recent_blockhash);
+        assert!(banks_client.process_transaction(transaction).await.is_err());
+    }
+
+    let mut transaction = Transaction::new_with_payer(
+        &[create_promo(&payer.pubkey(), 25, now + 3_600)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Unknown codes are rejected
+    let mut transaction = Transaction::new_with_payer(
+        &[redeem(&payer.pubkey(), "WINTER50")],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(err, custom_error(mailer::MailerError::InvalidPromoCode));
+
+    // Redemption creates the user's discount; redeeming again cannot use up the code
+    let mut transaction = Transaction::new_with_payer(
+        &[redeem(&payer.pubkey(), "SPRING25")],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let discount_account = banks_client
+        .get_account(get_fee_discount_pda(&payer.pubkey()).0)
+        .await
+        .unwrap()
+        .unwrap();
+    let fee_discount: FeeDiscount =
+        BorshDeserialize::deserialize(&mut &discount_account.data[8..]).unwrap();
+    assert_eq!(fee_discount.account, payer.pubkey());
+    assert_eq!(fee_discount.discount, 25);
+
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(
+        mailer_state.discount_rent_reserve,
+        discount_account.lamports
+    );
+
+    // (the transfer keeps this transaction distinct from the first redemption)
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            solana_sdk::system_instruction::transfer(
+                &payer.pubkey(),
+                &stranger.pubkey(),
+                100_000_000,
+            ),
+            redeem(&payer.pubkey(), "SPRING25"),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(mailer::MailerError::DiscountAlreadyApplied as u32)
+        )
+    );
+
+    // The second user takes the last redemption
+    let fund =
+        solana_sdk::system_instruction::transfer(&payer.pubkey(), &stranger.pubkey(), 100_000_000);
+    let mut transaction = Transaction::new_with_payer(&[fund], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mut transaction = Transaction::new_with_payer(
+        &[redeem(&stranger.pubkey(), "SPRING25")],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &stranger], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let (promo_pda, _) = mailer::pda::promo(&program_id(), &code_hash("SPRING25"));
+    let promo_account = banks_client.get_account(promo_pda).await.unwrap().unwrap();
+    let promo: Promo = BorshDeserialize::deserialize(&mut &promo_account.data[8..]).unwrap();
+    assert_eq!(promo.discount, 25);
+    assert_eq!(promo.remaining_redemptions, 0);
+
+    let late = Keypair::new();
+    let mut transaction =
+        Transaction::new_with_payer(&[redeem(&late.pubkey(), "SPRING25")], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &late], recent_blockhash);
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(err, custom_error(mailer::MailerError::PromoExhausted));
+}
+
+// ============================================================================
+// Claim And Send Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_claim_and_send_funds_reply_from_claimed_share() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let payer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer_usdc,
+        1_000_000,
+    )
+    .await;
+
+    // The recipient holds no USDC, only a 90_000 share from a priority message
+    let recipient = Keypair::new();
+    let recipient_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &recipient.pubkey(),
+    )
+    .await;
+
+    // Lamports only fund the recipient's message nonce account for the reply
+    let fund_recipient =
+        solana_sdk::system_instruction::transfer(&payer.pubkey(), &recipient.pubkey(), 10_000_000);
+    let mut transaction = Transaction::new_with_payer(&[fund_recipient], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
+    let (payer_claim_pda, _) = get_claim_pda(&payer.pubkey());
+
+    let send_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Send {
+            to: recipient.pubkey(),
+            subject: "Hello".to_string(),
+            _body: "Body".to_string(),
+            revenue_share_to_receiver: true,
+            resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(recipient_claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(payer_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let claim_and_send = |subject: &str| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::ClaimAndSend {
+                amount: None,
+                to: payer.pubkey(),
+                subject: subject.to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: false,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: true,
+            },
+            vec![
+                AccountMeta::new(recipient.pubkey(), true),
+                AccountMeta::new(recipient_claim_pda, false),
+                AccountMeta::new(payer_claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(recipient_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(recipient.pubkey(), true),
+                AccountMeta::new_readonly(get_policy_pda(&payer.pubkey()).0, false),
+                AccountMeta::new(get_message_nonce_pda(&recipient.pubkey()).0, false),
+            ],
+        )
+    };
+
+    // The claimed share pays the reply's strict fee in the same instruction
+    let mut transaction =
+        Transaction::new_with_payer(&[claim_and_send("Re: Hello")], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    let result = banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert!(result.result.is_ok(), "{:?}", result.result);
+    let activity = indexer::decode_logs(&result.metadata.unwrap().log_messages);
+    assert!(matches!(
+        activity.as_slice(),
+        [
+            MailerActivity::Claimed {
+                amount: 90_000,
+                kind: ClaimKind::Recipient,
+                ..
+            },
+            MailerActivity::Sent(SentMail {
+                effective_fee: 100_000,
+                ..
+            }),
+        ]
+    ));
+
+    let recipient_token = banks_client
+        .get_account(recipient_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&recipient_token.data).unwrap().amount,
+        80_000
+    );
+
+    let claim_account = banks_client
+        .get_account(recipient_claim_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    assert_eq!(claim.amount, 0);
+
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.owner_claimable, 20_000);
+
+    // Nothing left to claim fails the whole instruction, send included
+    let mut transaction =
+        Transaction::new_with_payer(&[claim_and_send("Re: Re: Hello")], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::NoClaimableAmount as u32)
+        )
+    );
+}
+
+// ============================================================================
+// Fee Mint Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_fee_mint_sends_accrue_and_claim_in_their_own_mint() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // A second stablecoin, whitelisted with its own fees
+    let alt_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let payer_alt = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &alt_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_alt = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &alt_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &alt_mint,
+        &payer_alt,
+        1_000_000,
+    )
+    .await;
+
+    let (fee_mint_pda, _) = mailer::pda::fee_mint(&program_id(), &alt_mint);
+    let add_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::AddFeeMint {
+            mint: alt_mint,
+            send_fee: 250_000,
+            delegation_fee: 2_000_000,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(fee_mint_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[add_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let custom_error = |error: mailer::MailerError| {
+        TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+    };
+
+    let recipient = Keypair::new();
+    let (claim_pda, _) =
+        mailer::pda::recipient_claim_in_mint(&program_id(), &recipient.pubkey(), &alt_mint);
+    let send = |subject: &str, fee_mint_accounts: Vec<AccountMeta>| {
+        let mut accounts = vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(payer_alt, false),
+            AccountMeta::new(mailer_alt, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ];
+        accounts.extend(fee_mint_accounts);
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient.pubkey(),
+                subject: subject.to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: true,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: true,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            accounts,
+        )
+    };
+
+    // Paying in a mint without its FeeMint account is rejected
+    let mut transaction =
+        Transaction::new_with_payer(&[send("Hello", vec![])], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(err, custom_error(mailer::MailerError::InvalidMint));
+
+    // The mint's own send fee is charged and split into the per-mint claim account
+    let mut transaction = Transaction::new_with_payer(
+        &[send("Hello", vec![AccountMeta::new(fee_mint_pda, false)])],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert!(result.result.is_ok(), "{:?}", result.result);
+    let activity = indexer::decode_logs(&result.metadata.unwrap().log_messages);
+    assert!(matches!(
+        activity.as_slice(),
+        [MailerActivity::Sent(SentMail { effective_fee: 250_000, fee_mint: Some(mint), .. })] if *mint == alt_mint
+    ));
+
+    let claim_account = banks_client.get_account(claim_pda).await.unwrap().unwrap();
+    let claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    assert_eq!(claim.amount, 225_000);
+
+    let fee_mint_account = banks_client
+        .get_account(fee_mint_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let fee_mint: FeeMint =
+        BorshDeserialize::deserialize(&mut &fee_mint_account.data[8..]).unwrap();
+    assert_eq!(fee_mint.owner_claimable, 25_000);
+
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.owner_claimable, 0);
+
+    // The mint cannot be removed while the owner share in it is unclaimed
+    let remove_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::RemoveFeeMint { mint: alt_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(fee_mint_pda, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(
+        std::slice::from_ref(&remove_instruction),
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(err, custom_error(mailer::MailerError::AccountNotEmpty));
+
+    let claim_owner_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::ClaimOwnerShare,
+        vec![
+            AccountMeta::new_readonly(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(payer_alt, false),
+            AccountMeta::new(mailer_alt, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(fee_mint_pda, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(
+        &[claim_owner_instruction, remove_instruction],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let payer_token = banks_client.get_account(payer_alt).await.unwrap().unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&payer_token.data).unwrap().amount,
+        775_000
+    );
+    assert!(banks_client
+        .get_account(fee_mint_pda)
+        .await
+        .unwrap()
+        .is_none());
+
+    // Shares already accrued stay claimable in their mint after removal
+    let recipient_alt = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &alt_mint,
+        &recipient.pubkey(),
+    )
+    .await;
+    let claim_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::ClaimRecipientShare {
+            amount: None,
+            split: None,
+            close_to: None,
+        },
+        vec![
+            AccountMeta::new_readonly(recipient.pubkey(), true),
+            AccountMeta::new(claim_pda, false),
+            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(recipient_alt, false),
+            AccountMeta::new(mailer_alt, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[claim_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let recipient_token = banks_client
+        .get_account(recipient_alt)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&recipient_token.data).unwrap().amount,
+        225_000
+    );
+
+    // Once removed, the mint is no longer accepted for fees
+    let mut transaction = Transaction::new_with_payer(
+        &[send(
+            "Hello again",
+            vec![AccountMeta::new(fee_mint_pda, false)],
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(err, custom_error(mailer::MailerError::InvalidMint));
+}
+
+#[tokio::test]
+async fn test_fee_mint_claim_surcharge_accrues_to_its_fee_mint() {
+    use solana_sdk::{
+        account::AccountSharedData, clock::Clock, instruction::InstructionError,
+        transaction::TransactionError,
+    };
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+    let recent_blockhash = context.last_blockhash;
+
+    let usdc_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let alt_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let payer_alt = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &alt_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_alt = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &alt_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &alt_mint,
+        &payer_alt,
+        1_000_000,
+    )
+    .await;
+
+    let (fee_mint_pda, _) = mailer::pda::fee_mint(&program_id(), &alt_mint);
+    let add_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::AddFeeMint {
+            mint: alt_mint,
+            send_fee: 250_000,
+            delegation_fee: 2_000_000,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(fee_mint_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[add_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // A priority send in the mint: 225,000 to the recipient, 25,000 to the FeeMint account
+    let recipient = Keypair::new();
+    let (claim_pda, _) =
+        mailer::pda::recipient_claim_in_mint(&program_id(), &recipient.pubkey(), &alt_mint);
+    let send_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Send {
+            to: recipient.pubkey(),
+            subject: "Hello".to_string(),
+            _body: "Body".to_string(),
+            revenue_share_to_receiver: true,
+            resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: true,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(payer_alt, false),
+            AccountMeta::new(mailer_alt, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(fee_mint_pda, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // Give the claim no-expiry status with one period due now
+    let now = context
+        .banks_client
+        .get_sysvar::<Clock>()
+        .await
+        .unwrap()
+        .unix_timestamp;
+    let mut account = context
+        .banks_client
+        .get_account(claim_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let mut claim = RecipientClaim::from_account_data(&account.data[8..]).unwrap();
+    assert_eq!(claim.amount, 225_000);
+    claim.no_expiry = true;
+    claim.surcharge_paid_until = now;
+    claim.write_account_data(&mut account.data[8..]).unwrap();
+    context.set_account(&claim_pda, &AccountSharedData::from(account));
+
+    let recipient_alt = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &alt_mint,
+        &recipient.pubkey(),
+    )
+    .await;
+    let claim_instruction = |fee_mint_accounts: Vec<AccountMeta>| {
+        let mut accounts = vec![
+            AccountMeta::new_readonly(recipient.pubkey(), true),
+            AccountMeta::new(claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(recipient_alt, false),
+            AccountMeta::new(mailer_alt, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ];
+        accounts.extend(fee_mint_accounts);
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::ClaimRecipientShare {
+                amount: None,
+                split: None,
+                close_to: None,
+            },
+            accounts,
+        )
+    };
+
+    // The surcharge is owed in the mint, so its FeeMint account must take it
+    let mut transaction =
+        Transaction::new_with_payer(&[claim_instruction(vec![])], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::InvalidMint as u32)
+        )
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[claim_instruction(vec![AccountMeta::new(
+            fee_mint_pda,
+            false,
+        )])],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let recipient_token = context
+        .banks_client
+        .get_account(recipient_alt)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&recipient_token.data).unwrap().amount,
+        175_000
+    );
+
+    // The FeeMint account holds the send's owner share and the surcharge; the USDC owner
+    // balance is untouched
+    let fee_mint_account = context
+        .banks_client
+        .get_account(fee_mint_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let fee_mint: FeeMint =
+        BorshDeserialize::deserialize(&mut &fee_mint_account.data[8..]).unwrap();
+    assert_eq!(fee_mint.owner_claimable, 25_000 + 50_000);
+
+    let mailer_account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.owner_claimable, 0);
+}
+
+// ============================================================================
+// Lamport Fee Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_lamport_fees_accrue_to_owner_and_claim_from_vault() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+    let (vault_pda, _) = mailer::pda::lamport_vault(&program_id());
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let set_lamport_fee = |lamport_fee: u64| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SetLamportFee { lamport_fee },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(vault_pda, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    };
+    let mut transaction =
+        Transaction::new_with_payer(&[set_lamport_fee(1_000_000)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // A sender holding only SOL pays the lamport fee
+    let sender = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[solana_sdk::system_instruction::transfer(
+            &payer.pubkey(),
+            &sender.pubkey(),
+            100_000_000,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let recipient = Pubkey::new_unique();
+    let (claim_pda, _) = get_claim_pda(&recipient);
+    let send = |subject: &str, revenue_share_to_receiver: bool| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient,
+                subject: subject.to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: true,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            vec![
+                AccountMeta::new(sender.pubkey(), true),
+                AccountMeta::new(claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender.pubkey(), false),
+                AccountMeta::new(vault_pda, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(sender.pubkey(), true),
+                AccountMeta::new_readonly(get_policy_pda(&recipient).0, false),
+                AccountMeta::new(get_message_nonce_pda(&sender.pubkey()).0, false),
+            ],
+        )
+    };
+    let custom_error = |error: mailer::MailerError| {
+        TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+    };
+
+    let mut transaction =
+        Transaction::new_with_payer(&[send("Hello", false)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &sender], recent_blockhash);
+    let result = banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert!(result.result.is_ok(), "{:?}", result.result);
+    let activity = indexer::decode_logs(&result.metadata.unwrap().log_messages);
+    assert!(matches!(
+        activity.as_slice(),
+        [MailerActivity::Sent(SentMail {
+            effective_fee: 1_000_000,
+            fee_in_lamports: true,
+            ..
+        })]
+    ));
+
+    // Email sends pay the same way with a writable sender and the system program trailing
+    let email_send = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SendToEmail {
+            to_email: "user@example.com".to_string(),
+            subject: "Hello".to_string(),
+            _body: "Body".to_string(),
+            strict: true,
+        },
+        vec![
+            AccountMeta::new(sender.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender.pubkey(), false),
+            AccountMeta::new(vault_pda, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(get_message_nonce_pda(&sender.pubkey()).0, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[email_send], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &sender], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.owner_claimable_lamports, 200_000);
+    assert_eq!(mailer_state.owner_claimable, 0);
+
+    let rent = banks_client.get_rent().await.unwrap();
+    let vault_rent = rent.minimum_balance(0);
+    let vault = banks_client.get_account(vault_pda).await.unwrap().unwrap();
+    assert_eq!(vault.lamports, vault_rent + 200_000);
+
+    // The owner claims the fees, leaving the vault's rent behind
+    let owner_before = banks_client.get_balance(payer.pubkey()).await.unwrap();
+    let claim_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::ClaimOwnerLamports,
+        vec![
+            AccountMeta::new_readonly(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(vault_pda, false),
+            AccountMeta::new(payer.pubkey(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(
+        std::slice::from_ref(&claim_instruction),
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let owner_after = banks_client.get_balance(payer.pubkey()).await.unwrap();
+    assert_eq!(owner_after, owner_before + 200_000 - 5_000);
+    let vault = banks_client.get_account(vault_pda).await.unwrap().unwrap();
+    assert_eq!(vault.lamports, vault_rent);
+
+    // Revenue-shared sends pay in tokens only
+    let mut transaction =
+        Transaction::new_with_payer(&[send("Priority", true)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &sender], recent_blockhash);
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        custom_error(mailer::MailerError::LamportFeeUnsupported)
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[claim_instruction, set_lamport_fee(0)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(err, custom_error(mailer::MailerError::NoClaimableAmount));
+
+    // A zero lamport fee turns lamport payments off
+    let mut transaction = Transaction::new_with_payer(&[set_lamport_fee(0)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mut transaction =
+        Transaction::new_with_payer(&[send("Hello again", false)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &sender], recent_blockhash);
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(err, custom_error(mailer::MailerError::LamportFeesDisabled));
+}
+
+#[tokio::test]
+async fn test_fee_oracle_pegs_lamport_fee_and_rejects_stale_prices() {
+    use mailer::price_oracle::{self, OraclePrice};
+    use solana_sdk::{
+        account::{Account, AccountSharedData},
+        clock::Clock,
+        instruction::InstructionError,
+        transaction::TransactionError,
+    };
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let mut recent_blockhash = context.last_blockhash;
+    let payer = context.payer.insecure_clone();
+
+    let usdc_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+    let (vault_pda, _) = mailer::pda::lamport_vault(&program_id());
+    let oracle = Pubkey::new_unique();
+
+    let sender = Keypair::new();
+    let setup = [
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Initialize { usdc_mint },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        ),
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SetLamportFee {
+                lamport_fee: 1_000_000,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(vault_pda, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        ),
+        solana_sdk::system_instruction::transfer(&payer.pubkey(), &sender.pubkey(), 100_000_000),
+    ];
+    let mut transaction = Transaction::new_with_payer(&setup, Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let set_fee_oracle = |oracle: Option<Pubkey>, target_fee: u64| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SetFeeOracle {
+                oracle,
+                target_fee,
+                max_age: 60,
+            },
+            vec![
+                AccountMeta::new_readonly(payer.pubkey(), true),
+                AccountMeta::new(mailer_pda, false),
+            ],
+        )
+    };
+    let custom_error = |error: mailer::MailerError| {
+        TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+    };
+
+    // A peg needs a target fee
+    let mut transaction =
+        Transaction::new_with_payer(&[set_fee_oracle(Some(oracle), 0)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let err = context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::InvalidFeePeg as u32)
+        )
+    );
+
+    // Peg the fee to $0.10 with SOL trading at $150
+    let mut transaction = Transaction::new_with_payer(
+        &[set_fee_oracle(Some(oracle), 100_000)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    let price = OraclePrice {
+        price: 15_000_000_000,
+        expo: -8,
+        publish_time: clock.unix_timestamp,
+    };
+    context.set_account(
+        &oracle,
+        &AccountSharedData::from(Account {
+            lamports: 10_000_000,
+            data: price_oracle::encode_price_account(&price),
+            owner: Pubkey::new_unique(),
+            executable: false,
+            rent_epoch: 0,
+        }),
+    );
+
+    let recipient = Pubkey::new_unique();
+    let (claim_pda, _) = get_claim_pda(&recipient);
+    let send = |subject: &str, with_oracle: bool| {
+        let mut accounts = vec![
+            AccountMeta::new(sender.pubkey(), true),
+            AccountMeta::new(claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender.pubkey(), false),
+            AccountMeta::new(vault_pda, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(sender.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient).0, false),
+            AccountMeta::new(get_message_nonce_pda(&sender.pubkey()).0, false),
+        ];
+        if with_oracle {
+            accounts.push(AccountMeta::new_readonly(oracle, false));
+        }
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient,
+                subject: subject.to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: false,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: true,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            accounts,
+        )
+    };
+
+    // The price account must be passed while the peg is set
+    let mut transaction =
+        Transaction::new_with_payer(&[send("No oracle", false)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &sender], recent_blockhash);
+    let err = context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(err, custom_error(mailer::MailerError::InvalidFeeOracle));
+
+    let mut transaction =
+        Transaction::new_with_payer(&[send("Pegged", true)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &sender], recent_blockhash);
+    let result = context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert!(result.result.is_ok(), "{:?}", result.result);
+    let activity = indexer::decode_logs(&result.metadata.unwrap().log_messages);
+    assert!(matches!(
+        activity.as_slice(),
+        [MailerActivity::Sent(SentMail {
+            effective_fee: 666_667,
+            fee_in_lamports: true,
+            ..
+        })]
+    ));
+
+    let mailer_account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.owner_claimable_lamports, 66_666);
+
+    // Prices older than the maximum age are rejected
+    clock.unix_timestamp += 61;
+    context.set_sysvar(&clock);
+    recent_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+
+    let mut transaction =
+        Transaction::new_with_payer(&[send("Stale", true)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &sender], recent_blockhash);
+    let err = context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(err, custom_error(mailer::MailerError::StaleOraclePrice));
+
+    // Clearing the peg falls back to the fixed lamport fee
+    let mut transaction =
+        Transaction::new_with_payer(&[set_fee_oracle(None, 0)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let mut transaction =
+        Transaction::new_with_payer(&[send("Fixed", false)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &sender], recent_blockhash);
+    let result = context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert!(result.result.is_ok(), "{:?}", result.result);
+    let activity = indexer::decode_logs(&result.metadata.unwrap().log_messages);
+    assert!(matches!(
+        activity.as_slice(),
+        [MailerActivity::Sent(SentMail {
+            effective_fee: 1_000_000,
+            fee_in_lamports: true,
+            ..
+        })]
+    ));
+}
+
+// ============================================================================
+// Inbox Sequence Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_inbox_sequence_numbers_targeted_sends() {
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let recipient = Pubkey::new_unique();
+    let (claim_pda, _) = get_claim_pda(&recipient);
+    let (inbox_pda, _) = mailer::pda::recipient_inbox(&program_id(), &recipient);
+    let send_accounts = |with_inbox: bool| {
+        let mut accounts = vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ];
+        if with_inbox {
+            accounts.push(AccountMeta::new(inbox_pda, false));
+        }
+        accounts
+    };
+    let send = |subject: &str, with_inbox: bool| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient,
+                subject: subject.to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: false,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: true,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            send_accounts(with_inbox),
+        )
+    };
+    let send_prepared = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SendPrepared {
+            to: recipient,
+            mail_id: "mail-1".to_string(),
+            revenue_share_to_receiver: false,
+            resolve_sender_to_name: false,
+            strict: true,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+        },
+        send_accounts(true),
+    );
+
+    // The first send creates the inbox; every send passing it takes the next number
+    let mut sequences = Vec::new();
+    for instruction in [
+        send("First", true),
+        send("Unnumbered", false),
+        send_prepared,
+        send("Second", true),
+    ] {
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer], recent_blockhash);
+        let result = banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+            .unwrap();
+        assert!(result.result.is_ok(), "{:?}", result.result);
+        match indexer::decode_logs(&result.metadata.unwrap().log_messages).as_slice() {
+            [MailerActivity::Sent(mail)] => sequences.push(mail.inbox_sequence),
+            activity => panic!("unexpected activity: {:?}", activity),
+        }
+    }
+    assert_eq!(sequences, vec![Some(1), None, Some(2), Some(3)]);
+
+    let inbox_account = banks_client.get_account(inbox_pda).await.unwrap().unwrap();
+    assert_eq!(
+        &inbox_account.data[..8],
+        &mailer::discriminator::account("RecipientInbox")
+    );
+    let inbox: RecipientInbox =
+        BorshDeserialize::deserialize(&mut &inbox_account.data[8..]).unwrap();
+    assert_eq!(inbox.recipient, recipient);
+    assert_eq!(inbox.sequence, 3);
+}
+
+// ============================================================================
+// Claim Creation Policy Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_claim_creation_policies_compensate_unpaid_priority_sends() {
+    use mailer::{costs, ClaimCreationPolicy};
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+
+    let set_policy = |policy: ClaimCreationPolicy| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SetClaimCreationPolicy { policy },
+            vec![
+                AccountMeta::new_readonly(payer.pubkey(), true),
+                AccountMeta::new(mailer_pda, false),
+            ],
+        )
+    };
+    let priority_send = |recipient: Pubkey, subject: &str, trailing: Vec<AccountMeta>| {
+        let mut accounts = vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(get_claim_pda(&recipient).0, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ];
+        accounts.extend(trailing);
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient,
+                subject: subject.to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: true,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            accounts,
+        )
+    };
+
+    // AfterTransfer: an unpaid send leaves no claim behind, a paid one creates it
+    let recipient = Pubkey::new_unique();
+    let (claim_pda, _) = get_claim_pda(&recipient);
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            set_policy(ClaimCreationPolicy::AfterTransfer),
+            priority_send(recipient, "Unpaid", vec![]),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    assert!(banks_client.get_account(claim_pda).await.unwrap().is_none());
+
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        100_000,
+    )
+    .await;
+    let mut transaction = Transaction::new_with_payer(
+        &[priority_send(recipient, "Paid", vec![])],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let claim_account = banks_client.get_account(claim_pda).await.unwrap().unwrap();
+    let claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    assert_eq!(claim.amount, 90_000);
+
+    // RefundPayer: the sender of an unpaid send that created a claim is recorded as its rent payer
+    let recipient = Pubkey::new_unique();
+    let (claim_pda, _) = get_claim_pda(&recipient);
+    let (record_pda, _) = mailer::pda::claim_rent_payer(&program_id(), &claim_pda);
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            set_policy(ClaimCreationPolicy::RefundPayer),
+            priority_send(
+                recipient,
+                "Unpaid",
+                vec![AccountMeta::new(record_pda, false)],
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let record_account = banks_client.get_account(record_pda).await.unwrap().unwrap();
+    let record: ClaimRentPayer =
+        BorshDeserialize::deserialize(&mut &record_account.data[8..]).unwrap();
+    assert_eq!(record.claim, claim_pda);
+    assert_eq!(record.payer, payer.pubkey());
+
+    let closure_log_pda = initialize_closure_log(&mut banks_client, &payer, recent_blockhash).await;
+    let close_funded_claim = |signer: Pubkey| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::CloseFundedClaim,
+            vec![
+                AccountMeta::new(signer, true),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(claim_pda, false),
+                AccountMeta::new(record_pda, false),
+                AccountMeta::new(closure_log_pda, false),
+            ],
+        )
+    };
+
+    // Only the recorded payer may close it
+    let stranger = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[solana_sdk::system_instruction::transfer(
+            &payer.pubkey(),
+            &stranger.pubkey(),
+            10_000_000,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mut transaction = Transaction::new_with_payer(
+        &[close_funded_claim(stranger.pubkey())],
+        Some(&stranger.pubkey()),
+    );
+    transaction.sign(&[&stranger], recent_blockhash);
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::NotClaimRentPayer as u32)
+        )
+    );
+
+    let reserve_before = {
+        let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+        let mailer_state: MailerState =
+            BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+        mailer_state.claim_rent_reserve
+    };
+    let balance_before = banks_client.get_balance(payer.pubkey()).await.unwrap();
+    let mut transaction =
+        Transaction::new_with_payer(&[close_funded_claim(payer.pubkey())], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let rent = banks_client.get_rent().await.unwrap();
+    let balance_after = banks_client.get_balance(payer.pubkey()).await.unwrap();
+    assert_eq!(
+        balance_after,
+        balance_before + costs::recipient_claim(&rent) + costs::claim_rent_payer(&rent) - 5_000
+    );
+    assert!(banks_client.get_account(claim_pda).await.unwrap().is_none());
+    assert!(banks_client
+        .get_account(record_pda)
+        .await
+        .unwrap()
+        .is_none());
+
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(
+        mailer_state.claim_rent_reserve,
+        reserve_before - costs::recipient_claim(&rent)
+    );
+
+    // Only the claim is archived; the rent payer record carries no balance history
+    let log_account = banks_client
+        .get_account(closure_log_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let log: ClosureLog = BorshDeserialize::deserialize(&mut &log_account.data[8..]).unwrap();
+    assert_eq!(log.total, 1);
+    assert_eq!(log.records[0].account, claim_pda);
+}
+
+// ============================================================================
+// Thread Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_thread_ids_and_message_ids_are_logged_with_sends() {
+    use solana_sdk::{clock::Clock, hash::hash};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let recent_blockhash = context.last_blockhash;
+    let payer = context.payer.insecure_clone();
+
+    let usdc_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+    let webhook_pda = register_webhook(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        "webhook-1",
+    )
+    .await;
+
+    // Pin the slot so message ids can be derived independently
+    let slot = 4_242;
+    let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    clock.slot = slot;
+    context.set_sysvar(&clock);
+    let recent_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+
+    let recipient = Pubkey::new_unique();
+    let (claim_pda, _) = get_claim_pda(&recipient);
+    let send_accounts = || {
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ]
+    };
+
+    let send = |instruction: Instruction| {
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer], recent_blockhash);
+        let mut banks_client = context.banks_client.clone();
+        async move {
+            let result = banks_client
+                .process_transaction_with_metadata(transaction)
+                .await
+                .unwrap();
+            assert!(result.result.is_ok(), "{:?}", result.result);
+            match indexer::decode_logs(&result.metadata.unwrap().log_messages).as_slice() {
+                [MailerActivity::Sent(mail)] => mail.clone(),
+                activity => panic!("unexpected activity: {:?}", activity),
+            }
+        }
+    };
+
+    // Opening message of a thread, numbered by the sender's message nonce
+    let thread_id = [0x11; 32];
+    let opening = send(Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Send {
+            to: recipient,
+            subject: "Hello".to_string(),
+            _body: "Body".to_string(),
+            revenue_share_to_receiver: false,
+            resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: true,
+            thread_id: Some(thread_id),
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
+        },
+        send_accounts(),
+    ))
+    .await;
+    assert_eq!(opening.thread_id, Some(thread_id));
+    assert_eq!(opening.in_reply_to, None);
+    assert_eq!(
+        opening.message_id,
+        Some(mailer::derive_message_id(
+            &payer.pubkey(),
+            recipient.as_ref(),
+            &mailer::hash_message_body("Hello", "Body"),
+            slot,
+            1
+        ))
+    );
+
+    // A prepared reply in the same thread references the opening message
+    let reply = send(Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SendPrepared {
+            to: recipient,
+            mail_id: "mail-1".to_string(),
+            revenue_share_to_receiver: false,
+            resolve_sender_to_name: false,
+            strict: true,
+            thread_id: Some(thread_id),
+            in_reply_to: opening.message_id,
+            allow_downgrade: false,
+            tip: 0,
+        },
+        send_accounts(),
+    ))
+    .await;
+    assert_eq!(reply.thread_id, Some(thread_id));
+    assert_eq!(reply.in_reply_to, opening.message_id);
+    assert_eq!(
+        reply.message_id,
+        Some(mailer::derive_message_id(
+            &payer.pubkey(),
+            recipient.as_ref(),
+            &hash(b"mail-1").to_bytes(),
+            slot,
+            2
+        ))
+    );
+
+    // Unthreaded sends log only their id
+    let webhook = send(Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SendThroughWebhook {
+            to: recipient,
+            webhook_id: "webhook-1".to_string(),
+            revenue_share_to_receiver: false,
+            resolve_sender_to_name: false,
+            strict: true,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+        },
+        {
+            let mut accounts = send_accounts();
+            accounts.insert(7, AccountMeta::new(webhook_pda, false));
+            accounts
+        },
+    ))
+    .await;
+    assert_eq!((webhook.thread_id, webhook.in_reply_to), (None, None));
+    assert_eq!(
+        webhook.message_id,
+        Some(mailer::derive_message_id(
+            &payer.pubkey(),
+            recipient.as_ref(),
+            &hash(b"webhook-1").to_bytes(),
+            slot,
+            3
+        ))
+    );
+}
+
+#[tokio::test]
+async fn test_identical_sends_in_one_slot_get_different_ids() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let recipient = Pubkey::new_unique();
+    let send = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Send {
+            to: recipient,
+            subject: "Hello".to_string(),
+            _body: "Body".to_string(),
+            revenue_share_to_receiver: false,
+            resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: true,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(get_claim_pda(&recipient).0, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ],
+    );
+
+    // The same send twice in one transaction shares its slot, sender, recipient and payload
+    let mut transaction =
+        Transaction::new_with_payer(&[send.clone(), send.clone()], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert!(result.result.is_ok(), "{:?}", result.result);
+    match indexer::decode_logs(&result.metadata.unwrap().log_messages).as_slice() {
+        [MailerActivity::Sent(first), MailerActivity::Sent(second)] => {
+            assert!(first.message_id.is_some());
+            assert_ne!(first.message_id, second.message_id);
+        }
+        activity => panic!("unexpected activity: {:?}", activity),
+    }
+
+    // Leaving the nonce account out cannot fall back to a shared nonce
+    let mut unnumbered = send;
+    unnumbered.accounts.pop();
+    let mut transaction = Transaction::new_with_payer(&[unnumbered], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::MissingMessageNonceAccount as u32)
+        )
+    );
+}
+
+// ============================================================================
+// Encrypted Message Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_send_encrypted_logs_envelope_openable_by_recipient() {
+    use mailer::crypto::{self, Envelope, EnvelopeError};
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let recipient = Keypair::new();
+    let (claim_pda, _) = get_claim_pda(&recipient.pubkey());
+    let send_encrypted = |envelope: &Envelope| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SendEncrypted {
+                to: recipient.pubkey(),
+                ephemeral_key: envelope.ephemeral_key,
+                nonce: envelope.nonce,
+                ciphertext: envelope.ciphertext.clone(),
+                revenue_share_to_receiver: false,
+                strict: true,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            ],
+        )
+    };
+
+    // The envelope is charged and logged like a standard send, then as an EncryptedMail event
+    let envelope = crypto::seal(&recipient.pubkey(), b"Meet at noon", [9; 32], [3; 12]).unwrap();
+    let instruction = send_encrypted(&envelope);
+    let mut transaction =
+        Transaction::new_with_payer(std::slice::from_ref(&instruction), Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert!(result.result.is_ok(), "{:?}", result.result);
+    let activity = indexer::decode_logs(&result.metadata.unwrap().log_messages);
+    let digest = crypto::envelope_digest(
+        &envelope.ephemeral_key,
+        &envelope.nonce,
+        &envelope.ciphertext,
+    );
+    match activity.as_slice() {
+        [MailerActivity::Sent(mail), MailerActivity::EncryptedMail {
+            sender,
+            to,
+            ephemeral_key,
+            ciphertext_len,
+            digest: logged_digest,
+        }] => {
+            assert_eq!(
+                (mail.subject.as_deref(), mail.effective_fee),
+                (Some(""), 100_000)
+            );
+            assert_eq!((*sender, *to), (payer.pubkey(), recipient.pubkey()));
+            assert_eq!(*ephemeral_key, envelope.ephemeral_key);
+            assert_eq!(*ciphertext_len, envelope.ciphertext.len());
+            assert_eq!(*logged_digest, digest);
+        }
+        activity => panic!("unexpected activity: {:?}", activity),
+    }
+
+    // Only the recipient's wallet opens the envelope recovered from the instruction data
+    let Some(MailerInstruction::SendEncrypted {
+        ephemeral_key,
+        nonce,
+        ciphertext,
+        ..
+    }) = indexer::decode_instruction(&instruction.data)
+    else {
+        panic!("not a SendEncrypted instruction");
+    };
+    let received = Envelope {
+        ephemeral_key,
+        nonce,
+        ciphertext,
+    };
+    let recipient_seed: [u8; 32] = recipient.to_bytes()[..32].try_into().unwrap();
+    assert_eq!(
+        crypto::open(&recipient_seed, &received).unwrap(),
+        b"Meet at noon"
+    );
+    let stranger_seed: [u8; 32] = Keypair::new().to_bytes()[..32].try_into().unwrap();
+    assert_eq!(
+        crypto::open(&stranger_seed, &received),
+        Err(EnvelopeError::Decryption)
+    );
+
+    // Envelopes without a tag, above the size limit or without an ephemeral key are rejected
+    let malformed = [
+        Envelope {
+            ciphertext: vec![1; crypto::TAG_LEN - 1],
+            ..envelope.clone()
+        },
+        Envelope {
+            ciphertext: vec![1; crypto::MAX_CIPHERTEXT_LEN + 1],
+            ..envelope.clone()
+        },
+        Envelope {
+            ephemeral_key: [0; 32],
+            ..envelope.clone()
+        },
+    ];
+    for envelope in &malformed {
+        let mut transaction =
+            Transaction::new_with_payer(&[send_encrypted(envelope)], Some(&payer.pubkey()));
+        transaction.sign(&[&payer], recent_blockhash);
+        let err = banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap();
+        assert_eq!(
+            err,
+            TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(mailer::MailerError::InvalidEnvelope as u32)
+            )
+        );
+    }
+}
+
+// ============================================================================
+// Fee Display Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_initialize_snapshots_mint_decimals_for_send_logs() {
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    // A fee mint with 2 decimals, passed to Initialize so its decimals are recorded
+    let usdc_mint = create_mint(&mut banks_client, &payer, recent_blockhash, 2).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(usdc_mint, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.usdc_decimals, 2);
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let send_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SendToEmail {
+            to_email: "user@example.com".to_string(),
+            subject: "Display".to_string(),
+            _body: "Body".to_string(),
+            strict: true,
+        },
+        vec![
+            AccountMeta::new_readonly(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert!(result.result.is_ok(), "{:?}", result.result);
+
+    // The send log carries the mint and its decimals next to the raw fee
+    assert!(matches!(
+        indexer::decode_logs(&result.metadata.unwrap().log_messages).as_slice(),
+        [MailerActivity::Sent(SentMail { effective_fee: 100_000, fee_mint: Some(mint), fee_decimals: Some(2), .. })]
+            if *mint == usdc_mint
+    ));
+}
+
+// ============================================================================
+// Webhook Registry Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_webhook_registry_guards_send_through_webhook() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let webhook_pda = register_webhook(&mut banks_client, &payer, recent_blockhash, "alerts").await;
+    let webhook_account = banks_client
+        .get_account(webhook_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        &webhook_account.data[..8],
+        &mailer::discriminator::account("Webhook")
+    );
+    let webhook: Webhook = BorshDeserialize::deserialize(&mut &webhook_account.data[8..]).unwrap();
+    assert_eq!(webhook.owner, payer.pubkey());
+    assert_eq!(
+        webhook.id_hash,
+        solana_program::hash::hash(b"alerts").to_bytes()
+    );
+    assert!(webhook.active);
+
+    let recipient = Pubkey::new_unique();
+    let (claim_pda, _) = get_claim_pda(&recipient);
+    let attacker = Keypair::new();
+    let webhook_send = |sender: &Pubkey, strict: bool| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SendThroughWebhook {
+                to: recipient,
+                webhook_id: "alerts".to_string(),
+                revenue_share_to_receiver: false,
+                resolve_sender_to_name: false,
+                strict,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+            },
+            vec![
+                AccountMeta::new(*sender, true),
+                AccountMeta::new(claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(webhook_pda, false),
+                AccountMeta::new(*sender, true),
+                AccountMeta::new_readonly(get_policy_pda(&recipient).0, false),
+                AccountMeta::new(get_message_nonce_pda(sender).0, false),
+            ],
+        )
+    };
+    let invalid_webhook = TransactionError::InstructionError(
+        0,
+        InstructionError::Custom(mailer::MailerError::InvalidWebhook as u32),
+    );
+
+    // Another wallet can neither take over the id nor send through it
+    let hijack = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::RegisterWebhook {
+            webhook_id: "alerts".to_string(),
+            endpoint_hash: [9u8; 32],
+            surcharge: 0,
+        },
+        vec![
+            AccountMeta::new(attacker.pubkey(), true),
+            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(webhook_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[hijack], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &attacker], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::AlreadyInitialized as u32)
+        )
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[webhook_send(&attacker.pubkey(), true)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &attacker], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        invalid_webhook
+    );
+
+    let update = |owner: &Pubkey, instruction: MailerInstruction| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &instruction,
+            vec![
+                AccountMeta::new_readonly(*owner, true),
+                AccountMeta::new(webhook_pda, false),
+            ],
+        )
+    };
+    let mut transaction = Transaction::new_with_payer(
+        &[update(&attacker.pubkey(), MailerInstruction::RevokeWebhook)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &attacker], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        invalid_webhook
+    );
+
+    // Once revoked, the owner's own sends are rejected too
+    let mut transaction = Transaction::new_with_payer(
+        &[update(&payer.pubkey(), MailerInstruction::RevokeWebhook)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mut transaction = Transaction::new_with_payer(
+        &[webhook_send(&payer.pubkey(), true)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        invalid_webhook
+    );
+
+    // Updating the endpoint reactivates the webhook
+    let mut transaction = Transaction::new_with_payer(
+        &[update(
+            &payer.pubkey(),
+            MailerInstruction::UpdateWebhook {
+                endpoint_hash: [7u8; 32],
+                surcharge: 0,
+            },
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let webhook_account = banks_client
+        .get_account(webhook_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let webhook: Webhook = BorshDeserialize::deserialize(&mut &webhook_account.data[8..]).unwrap();
+    assert_eq!(webhook.endpoint_hash, [7u8; 32]);
+    assert!(webhook.active);
+
+    let mut transaction = Transaction::new_with_payer(
+        &[webhook_send(&payer.pubkey(), false)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_webhook_surcharge_accrues_to_owner_claim() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    // Register "billing" with a 50_000 surcharge
+    let id_hash = solana_program::hash::hash(b"billing").to_bytes();
+    let (webhook_pda, _) = mailer::pda::webhook(&program_id(), &id_hash);
+    let register_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::RegisterWebhook {
+            webhook_id: "billing".to_string(),
+            endpoint_hash: [0u8; 32],
+            surcharge: 50_000,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(webhook_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[register_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let recipient = Pubkey::new_unique();
+    let (claim_pda, _) = get_claim_pda(&recipient);
+    let send_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SendThroughWebhook {
+            to: recipient,
+            webhook_id: "billing".to_string(),
+            revenue_share_to_receiver: false,
+            resolve_sender_to_name: false,
+            strict: true,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(webhook_pda, false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert!(result.result.is_ok(), "{:?}", result.result);
+    assert!(matches!(
+        indexer::decode_logs(&result.metadata.unwrap().log_messages).as_slice(),
+        [MailerActivity::Sent(SentMail {
+            webhook_surcharge: Some(50_000),
+            ..
+        })]
+    ));
+
+    // The surcharge is charged on top of the 10% standard fee and accrues to the owner
+    let sender_account = banks_client
+        .get_account(sender_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&sender_account.data).unwrap().amount,
+        1_000_000 - 10_000 - 50_000
+    );
+    let webhook_account = banks_client
+        .get_account(webhook_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let webhook: Webhook = BorshDeserialize::deserialize(&mut &webhook_account.data[8..]).unwrap();
+    assert_eq!(webhook.surcharge, 50_000);
+    assert_eq!(webhook.claimable, 50_000);
+
+    let claim_instruction = |owner: &Pubkey, owner_usdc: &Pubkey| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::ClaimWebhookRevenue,
+            vec![
+                AccountMeta::new_readonly(*owner, true),
+                AccountMeta::new(webhook_pda, false),
+                AccountMeta::new_readonly(mailer_pda, false),
+                AccountMeta::new(*owner_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+            ],
+        )
+    };
+
+    // Only the registered owner can claim
+    let attacker = Keypair::new();
+    let attacker_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &attacker.pubkey(),
+    )
+    .await;
+    let mut transaction = Transaction::new_with_payer(
+        &[claim_instruction(&attacker.pubkey(), &attacker_usdc)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &attacker], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::InvalidWebhook as u32)
+        )
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[claim_instruction(&payer.pubkey(), &sender_usdc)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert!(result.result.is_ok(), "{:?}", result.result);
+    assert!(matches!(
+        indexer::decode_logs(&result.metadata.unwrap().log_messages).as_slice(),
+        [MailerActivity::Claimed { amount: 50_000, kind: ClaimKind::Webhook { webhook }, .. }]
+            if *webhook == webhook_pda
+    ));
+
+    let sender_account = banks_client
+        .get_account(sender_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&sender_account.data).unwrap().amount,
+        1_000_000 - 10_000
+    );
+    let webhook_account = banks_client
+        .get_account(webhook_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let webhook: Webhook = BorshDeserialize::deserialize(&mut &webhook_account.data[8..]).unwrap();
+    assert_eq!(webhook.claimable, 0);
+
+    // Nothing is left to claim, whichever account it would be paid to
+    let other_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mut transaction = Transaction::new_with_payer(
+        &[claim_instruction(&payer.pubkey(), &other_usdc)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::NoClaimableAmount as u32)
+        )
+    );
+}
+
+// ============================================================================
+// Delegated Send Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_send_as_delegator_requires_current_delegate() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let delegator_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &delegator_usdc,
+        20_000_000,
+    )
+    .await;
+
+    let delegate = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[solana_sdk::system_instruction::transfer(
+            &payer.pubkey(),
+            &delegate.pubkey(),
+            100_000_000,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    let delegate_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &delegate.pubkey(),
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &delegate_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let (delegation_pda, _) = get_delegation_pda(&payer.pubkey());
+    let (delegate_index_pda, _) = get_delegate_index_pda(&delegate.pubkey());
+    let delegate_to = |delegate: Option<Pubkey>| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::DelegateTo { delegate },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(delegation_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(delegator_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(delegate_index_pda, false),
+            ],
+        )
+    };
+    let mut transaction = Transaction::new_with_payer(
+        &[delegate_to(Some(delegate.pubkey()))],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let recipient = Pubkey::new_unique();
+    let (claim_pda, _) = get_claim_pda(&recipient);
+    let send_as_delegator = |signer: &Pubkey, signer_usdc: &Pubkey, subject: &str| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SendAsDelegator {
+                to: recipient,
+                subject: subject.to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: true,
+                strict: true,
+            },
+            vec![
+                AccountMeta::new(*signer, true),
+                AccountMeta::new_readonly(payer.pubkey(), false),
+                AccountMeta::new_readonly(delegation_pda, false),
+                AccountMeta::new(claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(*signer_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new_readonly(get_policy_pda(&recipient).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            ],
+        )
+    };
+    let not_delegate = TransactionError::InstructionError(
+        0,
+        InstructionError::Custom(mailer::MailerError::NotDelegate as u32),
+    );
+
+    // A wallet other than the delegate cannot send as the delegator
+    let stranger = Keypair::new();
+    let stranger_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &stranger.pubkey(),
+    )
+    .await;
+    let mut transaction = Transaction::new_with_payer(
+        &[send_as_delegator(
+            &stranger.pubkey(),
+            &stranger_usdc,
+            "Impostor",
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &stranger], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        not_delegate
+    );
+
+    // The delegate pays and the message is logged as from the delegator
+    let mut transaction = Transaction::new_with_payer(
+        &[send_as_delegator(
+            &delegate.pubkey(),
+            &delegate_usdc,
+            "On behalf",
+        )],
+        Some(&delegate.pubkey()),
+    );
+    transaction.sign(&[&delegate], recent_blockhash);
+    let result = banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert!(result.result.is_ok(), "{:?}", result.result);
+    assert!(matches!(
+        indexer::decode_logs(&result.metadata.unwrap().log_messages).as_slice(),
+        [MailerActivity::Sent(SentMail { sender, payer: fee_payer, revenue_share: true, effective_fee: 100_000, .. })]
+            if *sender == payer.pubkey() && *fee_payer == delegate.pubkey()
+    ));
+
+    let delegate_account = banks_client
+        .get_account(delegate_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&delegate_account.data).unwrap().amount,
+        900_000
+    );
+    let claim_account = banks_client.get_account(claim_pda).await.unwrap().unwrap();
+    let claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    assert_eq!(claim.amount, 90_000);
+
+    // Once the delegation is cleared the former delegate is rejected
+    let mut transaction = Transaction::new_with_payer(&[delegate_to(None)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mut transaction = Transaction::new_with_payer(
+        &[send_as_delegator(
+            &delegate.pubkey(),
+            &delegate_usdc,
+            "After clearing",
+        )],
+        Some(&delegate.pubkey()),
+    );
+    transaction.sign(&[&delegate], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        not_delegate
+    );
+}
+
+// ============================================================================
+// Sanctions Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_sanctioned_sender_is_refused_while_sanctions_enabled() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[solana_sdk::system_instruction::transfer(
+            &payer.pubkey(),
+            &sender.pubkey(),
+            100_000_000,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let (sanctioned_pda, _) = mailer::pda::sanctioned(&program_id(), &sender.pubkey());
+    let owner_instruction = |ix: MailerInstruction, sanctioned: Option<Pubkey>| {
+        let mut accounts = vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+        ];
+        if let Some(sanctioned) = sanctioned {
+            accounts[1] = AccountMeta::new_readonly(mailer_pda, false);
+            accounts.push(AccountMeta::new(sanctioned, false));
+        }
+        if matches!(ix, MailerInstruction::SanctionAddress { .. }) {
+            accounts.push(AccountMeta::new_readonly(system_program::id(), false));
+        }
+        Instruction::new_with_borsh(program_id(), &ix, accounts)
+    };
+    let send = |subject: &str, with_sanctioned: bool| {
+        let recipient = Pubkey::new_unique();
+        let mut accounts = vec![
+            AccountMeta::new(sender.pubkey(), true),
+            AccountMeta::new(get_claim_pda(&recipient).0, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(sender.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient).0, false),
+            AccountMeta::new(get_message_nonce_pda(&sender.pubkey()).0, false),
+        ];
+        if with_sanctioned {
+            accounts.push(AccountMeta::new_readonly(sanctioned_pda, false));
+        }
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient,
+                subject: subject.to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: false,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: true,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            accounts,
+        )
+    };
+    let fails_with = |error: mailer::MailerError| {
+        TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+    };
+
+    // Enable sanctions and add the sender to the registry
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            owner_instruction(
+                MailerInstruction::SetSanctionsEnabled { enabled: true },
+                None,
+            ),
+            owner_instruction(
+                MailerInstruction::SanctionAddress {
+                    address: sender.pubkey(),
+                },
+                Some(sanctioned_pda),
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sanctioned_account = banks_client
+        .get_account(sanctioned_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        &sanctioned_account.data[..8],
+        &mailer::discriminator::account("Sanctioned")
+    );
+    let entry: mailer::Sanctioned =
+        BorshDeserialize::deserialize(&mut &sanctioned_account.data[8..]).unwrap();
+    assert_eq!(entry.address, sender.pubkey());
+
+    // The registry entry cannot be left out, and a sanctioned sender is refused
+    let mut transaction =
+        Transaction::new_with_payer(&[send("Omitted", false)], Some(&sender.pubkey()));
+    transaction.sign(&[&sender], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        fails_with(mailer::MailerError::InvalidPDA)
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[send("Sanctioned", true)], Some(&sender.pubkey()));
+    transaction.sign(&[&sender], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        fails_with(mailer::MailerError::AddressSanctioned)
+    );
+
+    // Removing the entry refunds its rent and restores service
+    let owner_lamports = banks_client.get_balance(payer.pubkey()).await.unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[owner_instruction(
+            MailerInstruction::UnsanctionAddress {
+                address: sender.pubkey(),
+            },
+            Some(sanctioned_pda),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    assert!(banks_client
+        .get_account(sanctioned_pda)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(banks_client.get_balance(payer.pubkey()).await.unwrap() > owner_lamports);
+
+    let mut transaction =
+        Transaction::new_with_payer(&[send("Cleared", true)], Some(&sender.pubkey()));
+    transaction.sign(&[&sender], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // With sanctions disabled the registry is not consulted at all
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            owner_instruction(
+                MailerInstruction::SanctionAddress {
+                    address: sender.pubkey(),
+                },
+                Some(sanctioned_pda),
+            ),
+            owner_instruction(
+                MailerInstruction::SetSanctionsEnabled { enabled: false },
+                None,
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mut transaction =
+        Transaction::new_with_payer(&[send("Unchecked", false)], Some(&sender.pubkey()));
+    transaction.sign(&[&sender], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert!(!mailer_state.sanctions_enabled);
+}
+
+// ============================================================================
+// Contingency Mode Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_contingency_mode_pays_claims_from_backup_vault() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let recipient = Keypair::new();
+    let recipient_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &recipient.pubkey(),
+    )
+    .await;
+    let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
+
+    let backup_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let vault_token = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &backup_mint,
+        &mailer_pda,
+    )
+    .await;
+    let recipient_backup = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &backup_mint,
+        &recipient.pubkey(),
+    )
+    .await;
+    let (conversion_pda, _) = get_conversion_pda(&backup_mint);
+
+    let send = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Send {
+            to: recipient.pubkey(),
+            subject: "Before the freeze".to_string(),
+            _body: "Body".to_string(),
+            revenue_share_to_receiver: true,
+            resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: true,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(recipient_claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ],
+    );
+    let set_vault = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetConversionVault {
+            mint: backup_mint,
+            enabled: true,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(conversion_pda, false),
+            AccountMeta::new_readonly(vault_token, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let set_backup_mint = |mint: Option<Pubkey>| {
+        let mut accounts = vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+        ];
+        if mint.is_some() {
+            accounts.push(AccountMeta::new_readonly(conversion_pda, false));
+        }
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SetBackupMint { mint },
+            accounts,
+        )
+    };
+    let mut transaction = Transaction::new_with_payer(
+        &[send, set_vault, set_backup_mint(Some(backup_mint))],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.backup_mint, Some(backup_mint));
+
+    let claim_from_backup = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::ClaimRecipientShareAs { mint: backup_mint },
+        vec![
+            AccountMeta::new(recipient.pubkey(), true),
+            AccountMeta::new(recipient_claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(recipient_backup, false),
+            AccountMeta::new(conversion_pda, false),
+            AccountMeta::new(vault_token, false),
+            AccountMeta::new(recipient_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+    );
+
+    // An unfunded backup vault fails the claim instead of falling back to the disrupted mint
+    let mut transaction = Transaction::new_with_payer(
+        std::slice::from_ref(&claim_from_backup),
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::BackupVaultUnavailable as u32)
+        )
+    );
+    let recipient_usdc_account = banks_client
+        .get_account(recipient_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&recipient_usdc_account.data)
+            .unwrap()
+            .amount,
+        0
+    );
+
+    // Once the owner funds the backup vault the share is paid 1:1 in the backup mint
+    let fund_vault = spl_instruction::mint_to(
+        &spl_token::id(),
+        &backup_mint,
+        &vault_token,
+        &payer.pubkey(),
+        &[],
+        100_000,
+    )
+    .unwrap();
+    let mut transaction =
+        Transaction::new_with_payer(&[fund_vault, claim_from_backup], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    let result = banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert!(result.result.is_ok(), "{:?}", result.result);
+    assert_eq!(
+        indexer::decode_logs(&result.metadata.unwrap().log_messages),
+        vec![MailerActivity::Claimed {
+            account: recipient.pubkey(),
+            amount: 90_000,
+            kind: ClaimKind::Backup { mint: backup_mint },
+        }]
+    );
+
+    let recipient_backup_account = banks_client
+        .get_account(recipient_backup)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&recipient_backup_account.data)
+            .unwrap()
+            .amount,
+        90_000
+    );
+
+    // Leaving contingency mode clears the backup mint
+    let mut transaction =
+        Transaction::new_with_payer(&[set_backup_mint(None)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.backup_mint, None);
+}
+
+// ============================================================================
+// Delegate Index Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_delegate_index_tracks_delegators_of_a_delegate() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    let payer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer_usdc,
+        50_000_000,
+    )
+    .await;
+
+    let other = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[solana_sdk::system_instruction::transfer(
+            &payer.pubkey(),
+            &other.pubkey(),
+            100_000_000,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    let other_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &other.pubkey(),
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &other_usdc,
+        50_000_000,
+    )
+    .await;
+
+    let first_delegate = Keypair::new();
+    let second_delegate = Pubkey::new_unique();
+    let (first_index_pda, _) = get_delegate_index_pda(&first_delegate.pubkey());
+    let (second_index_pda, _) = get_delegate_index_pda(&second_delegate);
+
+    let delegate_to = |delegator: &Pubkey,
+                       delegator_usdc: &Pubkey,
+                       delegate: Option<Pubkey>,
+                       indexes: &[Pubkey]| {
+        let mut accounts = vec![
+            AccountMeta::new(*delegator, true),
+            AccountMeta::new(get_delegation_pda(delegator).0, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(*delegator_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ];
+        accounts.extend(indexes.iter().map(|index| AccountMeta::new(*index, false)));
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::DelegateTo { delegate },
+            accounts,
+        )
+    };
+
+    // The new delegate's index is required
+    let mut transaction = Transaction::new_with_payer(
+        &[delegate_to(
+            &payer.pubkey(),
+            &payer_usdc,
+            Some(first_delegate.pubkey()),
+            &[],
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::InvalidPDA as u32)
+        )
+    );
+
+    // Both wallets delegate to the first delegate; the first delegation creates its index
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            delegate_to(
+                &payer.pubkey(),
+                &payer_usdc,
+                Some(first_delegate.pubkey()),
+                &[first_index_pda],
+            ),
+            delegate_to(
+                &other.pubkey(),
+                &other_usdc,
+                Some(first_delegate.pubkey()),
+                &[first_index_pda],
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &other], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let read_index = |account: solana_sdk::account::Account| {
+        assert_eq!(
+            &account.data[..8],
+            &mailer::discriminator::account("DelegateIndex")
+        );
+        let index: mailer::DelegateIndex =
+            BorshDeserialize::deserialize(&mut &account.data[8..]).unwrap();
+        index
+    };
+    let index = read_index(
+        banks_client
+            .get_account(first_index_pda)
+            .await
+            .unwrap()
+            .unwrap(),
+    );
+    assert_eq!(index.delegate, first_delegate.pubkey());
+    assert_eq!(index.count, 2);
+    assert_eq!(
+        index.accumulator,
+        mailer::DelegateIndex::accumulate(&[payer.pubkey(), other.pubkey()])
+    );
+
+    // Re-delegating moves the delegator from the previous delegate's index to the new one
+    let mut transaction = Transaction::new_with_payer(
+        &[delegate_to(
+            &payer.pubkey(),
+            &payer_usdc,
+            Some(second_delegate),
+            &[second_index_pda, first_index_pda],
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let index = read_index(
+        banks_client
+            .get_account(first_index_pda)
+            .await
+            .unwrap()
+            .unwrap(),
+    );
+    assert_eq!(index.count, 1);
+    assert_eq!(
+        index.accumulator,
+        mailer::DelegateIndex::accumulate(&[other.pubkey()])
+    );
+    let index = read_index(
+        banks_client
+            .get_account(second_index_pda)
+            .await
+            .unwrap()
+            .unwrap(),
+    );
+    assert_eq!(index.count, 1);
+    assert_eq!(
+        index.accumulator,
+        mailer::DelegateIndex::accumulate(&[payer.pubkey()])
+    );
+
+    // Rejecting removes the delegator from the rejector's index
+    let reject_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::RejectDelegation,
+        vec![
+            AccountMeta::new(first_delegate.pubkey(), true),
+            AccountMeta::new(get_delegation_pda(&other.pubkey()).0, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(first_index_pda, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[reject_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &first_delegate], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let index = read_index(
+        banks_client
+            .get_account(first_index_pda)
+            .await
+            .unwrap()
+            .unwrap(),
+    );
+    assert_eq!(index.count, 0);
+    assert_eq!(index.accumulator, [0u8; 32]);
+
+    // Clearing a delegation empties the index it was recorded in
+    let mut transaction = Transaction::new_with_payer(
+        &[delegate_to(
+            &payer.pubkey(),
+            &payer_usdc,
+            None,
+            &[second_index_pda],
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let index = read_index(
+        banks_client
+            .get_account(second_index_pda)
+            .await
+            .unwrap()
+            .unwrap(),
+    );
+    assert_eq!((index.count, index.accumulator), (0, [0u8; 32]));
+}
+
+// ============================================================================
+// Stats Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_stats_count_sends_fees_and_claims() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+    let (stats_pda, _) = mailer::pda::stats(&program_id());
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let init_stats = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::InitializeStats,
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(stats_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[init_instruction, init_stats], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
+
+    let recipient = Keypair::new();
+    let recipient_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &recipient.pubkey(),
+    )
+    .await;
+    let (claim_pda, _) = get_claim_pda(&recipient.pubkey());
+    let send = |subject: &str, revenue_share_to_receiver: bool, with_stats: bool| {
+        let mut accounts = vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ];
+        if with_stats {
+            accounts.push(AccountMeta::new(stats_pda, false));
+        }
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient.pubkey(),
+                subject: subject.to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: true,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            accounts,
+        )
+    };
+
+    // Once initialized, sends must pass the stats account
+    let mut transaction =
+        Transaction::new_with_payer(&[send("Untracked", true, false)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::InvalidPDA as u32)
+        )
+    );
+
+    // A priority send (full fee) and a standard send (10% of the fee)
+    let mut transaction = Transaction::new_with_payer(
+        &[send("Priority", true, true), send("Standard", false, true)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let claim_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::ClaimRecipientShare {
+            amount: None,
+            split: None,
+            close_to: None,
+        },
+        vec![
+            AccountMeta::new(recipient.pubkey(), true),
+            AccountMeta::new(claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(recipient_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(stats_pda, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[claim_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert!(mailer_state.stats_enabled);
+
+    let stats_account = banks_client.get_account(stats_pda).await.unwrap().unwrap();
+    assert_eq!(
+        &stats_account.data[..8],
+        &mailer::discriminator::account("MailerStats")
+    );
+    let stats: mailer::MailerStats =
+        BorshDeserialize::deserialize(&mut &stats_account.data[8..]).unwrap();
+    assert_eq!(stats.messages_sent, 2);
+    assert_eq!(stats.priority_sent, 1);
+    assert_eq!(stats.standard_sent, 1);
+    assert_eq!(stats.fees_collected, 100_000 + 10_000);
+    assert_eq!(stats.total_claimed, 90_000);
+}
+
+// ============================================================================
+// Account Check Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_token_account_mismatch_logs_offending_account() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Both token accounts belong to the sender, so the vault is the wrong one
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let wrong_vault = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let recipient = Pubkey::new_unique();
+    let (claim_pda, _) = get_claim_pda(&recipient);
+    let send_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Send {
+            to: recipient,
+            subject: "Subject".to_string(),
+            _body: "Body".to_string(),
+            revenue_share_to_receiver: true,
+            resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: true,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(wrong_vault, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+
+    // The error code is unchanged; the log names the account and its position
+    assert_eq!(
+        result.result.unwrap_err(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::InvalidAccountOwner as u32)
+        )
+    );
+    let logs = result.metadata.unwrap().log_messages;
+    let expected = format!(
+        "AccountMismatch {{ account: mailer_usdc, index: 4, key: {}, expected: owner {}, found: owner {} }}",
+        wrong_vault,
+        mailer_pda,
+        payer.pubkey()
+    );
+    assert!(
+        logs.iter().any(|log| log.ends_with(&expected)),
+        "{:?}",
+        logs
+    );
+}
+
+// ============================================================================
+// No-Expiry Claim Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_no_expiry_claim_pays_surcharge_instead_of_expiring() {
+    use solana_sdk::{clock::Clock, instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+    let mut recent_blockhash = context.last_blockhash;
+
+    let usdc_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
+
+    let recipient = Keypair::new();
+    let recipient_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &recipient.pubkey(),
+    )
+    .await;
+    let (claim_pda, _) = get_claim_pda(&recipient.pubkey());
+    let send = |subject: &str| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient.pubkey(),
+                subject: subject.to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: true,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: true,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            ],
+        )
+    };
+    let set_no_expiry = |enabled: bool| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SetClaimNoExpiry { enabled },
+            vec![
+                AccountMeta::new_readonly(recipient.pubkey(), true),
+                AccountMeta::new(claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+            ],
+        )
+    };
+
+    // Two priority sends credit 90,000 each; opting in prepays the first period
+    let mut transaction = Transaction::new_with_payer(
+        &[send("First"), send("Second"), set_no_expiry(true)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let claim_account = context
+        .banks_client
+        .get_account(claim_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    assert!(claim.no_expiry);
+    assert_eq!(claim.amount, 180_000 - 50_000);
+
+    // Past the 60-day claim period the claim has not expired to the owner
+    let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    clock.unix_timestamp += 61 * 24 * 60 * 60;
+    context.set_sysvar(&clock);
+    recent_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+
+    let claim_expired_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::ClaimExpiredShares {
+            recipient: recipient.pubkey(),
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(claim_pda, false),
+            AccountMeta::new_readonly(get_beneficiary_pda(&recipient.pubkey()).0, false),
+        ],
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[claim_expired_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::ClaimPeriodNotExpired as u32)
+        )
+    );
+
+    // Claiming first settles the two further periods started since opting in
+    let claim_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::ClaimRecipientShare {
+            amount: None,
+            split: None,
+            close_to: None,
+        },
+        vec![
+            AccountMeta::new(recipient.pubkey(), true),
+            AccountMeta::new(claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(recipient_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[claim_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let recipient_account = context
+        .banks_client
+        .get_account(recipient_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    let recipient_token = TokenAccount::unpack(&recipient_account.data).unwrap();
+    assert_eq!(recipient_token.amount, 180_000 - 3 * 50_000);
+
+    let mailer_account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.owner_claimable, 20_000 + 3 * 50_000);
+
+    // An empty balance cannot prepay a new period
+    let mut transaction = Transaction::new_with_payer(
+        &[set_no_expiry(false), set_no_expiry(true)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(mailer::MailerError::NoExpirySurchargeUnpaid as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_extend_claim_window_restarts_expiry_for_a_fee_up_to_the_limit() {
+    use solana_sdk::{clock::Clock, instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+    let mut recent_blockhash = context.last_blockhash;
+
+    let usdc_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    // The recipient pays for their own later transactions
+    let recipient = Keypair::new();
+    let (claim_pda, _) = get_claim_pda(&recipient.pubkey());
+    let send_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Send {
+            to: recipient.pubkey(),
+            subject: "Priority".to_string(),
+            _body: "Body".to_string(),
+            revenue_share_to_receiver: true,
+            resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: true,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ],
+    );
+    let fund_recipient =
+        solana_sdk::system_instruction::transfer(&payer.pubkey(), &recipient.pubkey(), 10_000_000);
+    let mut transaction =
+        Transaction::new_with_payer(&[send_instruction, fund_recipient], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let extend = || {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::ExtendClaimWindow,
+            vec![
+                AccountMeta::new_readonly(recipient.pubkey(), true),
+                AccountMeta::new(claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+            ],
+        )
+    };
+    let days = 24 * 60 * 60;
+
+    // A day before expiry the first extension restarts the 60-day window
+    let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    clock.unix_timestamp += 59 * days;
+    context.set_sysvar(&clock);
+    recent_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(&[extend()], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // Past the original deadline the balance has not expired to the owner
+    clock.unix_timestamp += 59 * days;
+    context.set_sysvar(&clock);
+    recent_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+    let claim_expired_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::ClaimExpiredShares {
+            recipient: recipient.pubkey(),
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(claim_pda, false),
+            AccountMeta::new_readonly(get_beneficiary_pda(&recipient.pubkey()).0, false),
+        ],
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[claim_expired_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::ClaimPeriodNotExpired as u32)
+        )
+    );
+
+    // The second extension is the last one before another share arrives
+    let mut transaction = Transaction::new_with_payer(&[extend()], Some(&recipient.pubkey()));
+    transaction.sign(&[&recipient], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+    let mut transaction =
+        Transaction::new_with_payer(&[extend(), extend()], Some(&recipient.pubkey()));
+    transaction.sign(&[&recipient], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::ClaimExtensionLimitReached as u32)
+        )
+    );
+
+    let claim_account = context
+        .banks_client
+        .get_account(claim_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    assert_eq!(claim.amount, 90_000 - 2 * 20_000);
+    assert_eq!(claim.timestamp, clock.unix_timestamp);
+    assert_eq!(claim.extensions, 2);
+
+    let mailer_account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.owner_claimable, 10_000 + 2 * 20_000);
+    assert_eq!(mailer_state.recipient_liabilities, 90_000 - 2 * 20_000);
+}
+
+#[tokio::test]
+async fn test_claim_beneficiary_collects_expired_claim_before_owner_sweep() {
+    use mailer::ClaimBeneficiary;
+    use solana_sdk::{clock::Clock, instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+    let mut recent_blockhash = context.last_blockhash;
+
+    let usdc_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let recipient = Keypair::new();
+    let beneficiary = Keypair::new();
+    let (claim_pda, _) = get_claim_pda(&recipient.pubkey());
+    let (beneficiary_pda, beneficiary_bump) = get_beneficiary_pda(&recipient.pubkey());
+
+    let sender_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    let beneficiary_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &beneficiary.pubkey(),
+    )
+    .await;
+    mint_to(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let send = || {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient.pubkey(),
+                subject: "Priority".to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: true,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: true,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            ],
+        )
+    };
+
+    // The recipient registers a beneficiary, paying for the account themselves
+    let set_beneficiary_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetClaimBeneficiary {
+            beneficiary: Some(beneficiary.pubkey()),
+        },
+        vec![
+            AccountMeta::new(recipient.pubkey(), true),
+            AccountMeta::new(beneficiary_pda, false),
+            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let fund_recipient =
+        solana_sdk::system_instruction::transfer(&payer.pubkey(), &recipient.pubkey(), 10_000_000);
+    let mut transaction = Transaction::new_with_payer(
+        &[send(), fund_recipient, set_beneficiary_instruction],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let beneficiary_account = context
+        .banks_client
+        .get_account(beneficiary_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let record: ClaimBeneficiary =
+        BorshDeserialize::deserialize(&mut &beneficiary_account.data[8..]).unwrap();
+    assert_eq!(record.recipient, recipient.pubkey());
+    assert_eq!(record.beneficiary, beneficiary.pubkey());
+    assert_eq!(record.bump, beneficiary_bump);
+
+    let claim_expired = || {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::ClaimExpiredShares {
+                recipient: recipient.pubkey(),
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(claim_pda, false),
+                AccountMeta::new_readonly(beneficiary_pda, false),
+            ],
+        )
+    };
+    let claim_as_beneficiary = |signer: &Keypair| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::ClaimAsBeneficiary {
+                recipient: recipient.pubkey(),
+            },
+            vec![
+                AccountMeta::new_readonly(signer.pubkey(), true),
+                AccountMeta::new_readonly(beneficiary_pda, false),
+                AccountMeta::new(claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(beneficiary_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+            ],
+        )
+    };
+    let days = 24 * 60 * 60;
+
+    // While the recipient can still claim, the beneficiary cannot
+    let mut transaction =
+        Transaction::new_with_payer(&[claim_as_beneficiary(&beneficiary)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &beneficiary], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::ClaimPeriodNotExpired as u32)
+        )
+    );
+
+    // Once the claim expires the beneficiary's grace period holds off the owner
+    let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    clock.unix_timestamp += 60 * days + 1;
+    context.set_sysvar(&clock);
+    recent_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(&[claim_expired()], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::BeneficiaryGracePeriodActive as u32)
+        )
+    );
+
+    // Leaving the beneficiary account out does not let the owner skip the grace period
+    let mut sweep_without_beneficiary = claim_expired();
+    sweep_without_beneficiary.accounts.pop();
+    let mut transaction =
+        Transaction::new_with_payer(&[sweep_without_beneficiary], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::InvalidPDA as u32)
+        )
+    );
+
+    // Only the registered beneficiary may collect the claim
+    let stranger = Keypair::new();
+    let mut transaction =
+        Transaction::new_with_payer(&[claim_as_beneficiary(&stranger)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &stranger], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::NotClaimBeneficiary as u32)
+        )
+    );
+
+    let mut transaction =
+        Transaction::new_with_payer(&[claim_as_beneficiary(&beneficiary)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &beneficiary], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let beneficiary_token = context
+        .banks_client
+        .get_account(beneficiary_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&beneficiary_token.data)
+            .unwrap()
+            .amount,
+        90_000
+    );
+    let claim_account = context
+        .banks_client
+        .get_account(claim_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    assert_eq!(claim.amount, 0);
+
+    // A later share left unclaimed through the grace period goes to the owner
+    recent_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(&[send()], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    clock.unix_timestamp += 90 * days + 1;
+    context.set_sysvar(&clock);
+    recent_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+    let mut transaction =
+        Transaction::new_with_payer(&[claim_as_beneficiary(&beneficiary)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &beneficiary], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::BeneficiaryGracePeriodEnded as u32)
+        )
+    );
+    let mut transaction = Transaction::new_with_payer(&[claim_expired()], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let mailer_account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.owner_claimable, 2 * 10_000 + 90_000);
+    assert_eq!(mailer_state.recipient_liabilities, 0);
+
+    // Removing the beneficiary closes its account
+    let remove_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetClaimBeneficiary { beneficiary: None },
+        vec![
+            AccountMeta::new(recipient.pubkey(), true),
+            AccountMeta::new(beneficiary_pda, false),
+            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[remove_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+    assert!(context
+        .banks_client
+        .get_account(beneficiary_pda)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+// ============================================================================
+// Expired Claims Batch Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_claim_expired_shares_batch_skips_unexpired_and_invalid_accounts() {
+    use solana_sdk::clock::Clock;
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+    let mut recent_blockhash = context.last_blockhash;
+
+    let usdc_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
+
+    let send = |to: Pubkey| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to,
+                subject: "Subject".to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: true,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: true,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(get_claim_pda(&to).0, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(get_policy_pda(&to).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            ],
+        )
+    };
+
+    let (first, second, late) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[send(first), send(second)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // The first two claims expire; the late one is credited after the warp
+    let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    clock.unix_timestamp += 60 * 24 * 60 * 60 + 1;
+    context.set_sysvar(&clock);
+    recent_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+
+    let mut transaction = Transaction::new_with_payer(&[send(late)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let batch_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::ClaimExpiredSharesBatch,
+        vec![
+            AccountMeta::new_readonly(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(get_claim_pda(&first).0, false),
+            AccountMeta::new_readonly(get_beneficiary_pda(&first).0, false),
+            AccountMeta::new(get_claim_pda(&late).0, false),
+            AccountMeta::new_readonly(get_beneficiary_pda(&late).0, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new(get_claim_pda(&second).0, false),
+            AccountMeta::new_readonly(get_beneficiary_pda(&second).0, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[batch_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert!(result.result.is_ok(), "{:?}", result.result);
+    let activity = indexer::decode_logs(&result.metadata.unwrap().log_messages);
+    assert_eq!(activity.len(), 2);
+
+    for (recipient, amount) in [(first, 0), (second, 0), (late, 90_000)] {
+        let claim_account = context
+            .banks_client
+            .get_account(get_claim_pda(&recipient).0)
+            .await
+            .unwrap()
+            .unwrap();
+        let claim: RecipientClaim =
+            BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+        assert_eq!(claim.amount, amount);
+    }
+
+    // Owner shares of the three sends plus both expired recipient shares
+    let mailer_account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.owner_claimable, 3 * 10_000 + 2 * 90_000);
+}
+
+// ============================================================================
+// FFI Tests
+// ============================================================================
+
+#[test]
+fn test_ffi_header_matches_checked_in_file() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", dir)).unwrap();
+    let mut generated = Vec::new();
+    cbindgen::Builder::new()
+        .with_config(config)
+        .with_src(format!("{}/src/ffi.rs", dir))
+        .generate()
+        .expect("cbindgen failed")
+        .write(&mut generated);
+    let generated = String::from_utf8(generated).unwrap();
+
+    let path = format!("{}/include/mailer_ffi.h", dir);
+    if std::env::var("UPDATE_FFI_HEADER").is_ok() {
+        std::fs::create_dir_all(format!("{}/include", dir)).unwrap();
+        std::fs::write(&path, &generated).unwrap();
+    }
+    let header = std::fs::read_to_string(&path).expect("missing include/mailer_ffi.h");
+    assert_eq!(
+        header, generated,
+        "FFI header changed; regenerate with UPDATE_FFI_HEADER=1"
+    );
+
+    // One serializer per instruction variant
+    assert_eq!(
+        header.matches(" mailer_ix_").count(),
+        mailer::test_vectors::instruction_vectors().len()
+    );
+}
+
+#[test]
+fn test_ffi_serialization_matches_test_vectors() {
+    use mailer::ffi::{self, MailerBytes, MailerFfiStatus, MailerOptionU64};
+
+    let vector = |name: &str| {
+        mailer::test_vectors::instruction_vectors()
+            .into_iter()
+            .find(|v| v.name == name)
+            .unwrap()
+            .bytes
+    };
+    let text = |s: &'static str| MailerBytes {
+        ptr: s.as_ptr(),
+        len: s.len(),
+    };
+    let to = [2u8; 32];
+    let (thread_id, in_reply_to) = ([0x11u8; 32], [0x22u8; 32]);
+    let mut out = [0u8; 512];
+    let mut len = 0usize;
+
+    let status = unsafe {
+        ffi::mailer_ix_send(
+            to.as_ptr(),
+            text("Hello"),
+            text("World"),
+            true,
+            false,
+            true,
+            MailerOptionU64 {
+                present: true,
+                value: 7,
+            },
+            text("INV-001"),
+            true,
+            thread_id.as_ptr(),
+            in_reply_to.as_ptr(),
+            true,
+            25_000,
+            false,
+            MailerOptionU64 {
+                present: false,
+                value: 0,
+            },
+            out.as_mut_ptr(),
+            out.len(),
+            &mut len,
+        )
+    };
+    assert_eq!(status, MailerFfiStatus::Ok);
+    assert_eq!(out[..len], vector("Send")[..]);
+
+    let admins = [[7u8; 32], [8u8; 32]].concat();
+    let status = unsafe {
+        ffi::mailer_ix_set_admins(admins.as_ptr(), 2, 2, out.as_mut_ptr(), out.len(), &mut len)
+    };
+    assert_eq!(status, MailerFfiStatus::Ok);
+    assert_eq!(out[..len], vector("SetAdmins")[..]);
+
+    let split = [
+        ffi::MailerClaimSplit {
+            destination: [26u8; 32],
+            bps: 7_500,
+        },
+        ffi::MailerClaimSplit {
+            destination: [27u8; 32],
+            bps: 2_500,
+        },
+    ];
+    let status = unsafe {
+        ffi::mailer_ix_claim_recipient_share(
+            MailerOptionU64 {
+                present: true,
+                value: 50_000,
+            },
+            split.as_ptr(),
+            split.len(),
+            [28u8; 32].as_ptr(),
+            out.as_mut_ptr(),
+            out.len(),
+            &mut len,
+        )
+    };
+    assert_eq!(status, MailerFfiStatus::Ok);
+    assert_eq!(out[..len], vector("ClaimRecipientShare")[..]);
+
+    // A short buffer reports the required length without writing
+    let mut short = [0u8; 4];
+    let status = unsafe {
+        ffi::mailer_ix_set_admins(
+            admins.as_ptr(),
+            2,
+            2,
+            short.as_mut_ptr(),
+            short.len(),
+            &mut len,
+        )
+    };
+    assert_eq!(status, MailerFfiStatus::BufferTooSmall);
+    assert_eq!(len, vector("SetAdmins").len());
+    assert_eq!(short, [0u8; 4]);
+
+    // Required pointers, UTF-8 and enum indices are checked
+    let status = unsafe {
+        ffi::mailer_ix_initialize(std::ptr::null(), out.as_mut_ptr(), out.len(), &mut len)
+    };
+    assert_eq!(status, MailerFfiStatus::NullPointer);
+    let invalid = [0xffu8, 0xfe];
+    let status = unsafe {
+        ffi::mailer_ix_redeem_promo(
+            MailerBytes {
+                ptr: invalid.as_ptr(),
+                len: 2,
+            },
+            out.as_mut_ptr(),
+            out.len(),
+            &mut len,
+        )
+    };
+    assert_eq!(status, MailerFfiStatus::InvalidUtf8);
+    let status = unsafe {
+        ffi::mailer_ix_set_claim_creation_policy(3, out.as_mut_ptr(), out.len(), &mut len)
+    };
+    assert_eq!(status, MailerFfiStatus::InvalidEnum);
+}
+
+// ============================================================================
+// Pause Flag Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_pause_flags_pause_subsystems_independently() {
+    use solana_sdk::{clock::Clock, instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+    let mut recent_blockhash = context.last_blockhash;
+
+    let usdc_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
+
+    let recipient = Keypair::new();
+    let recipient_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &recipient.pubkey(),
+    )
+    .await;
+    let (claim_pda, _) = get_claim_pda(&recipient.pubkey());
+    let send = |subject: &str| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient.pubkey(),
+                subject: subject.to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: true,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: true,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            ],
+        )
+    };
+    let set_pause_flags = |flags: u8| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SetPauseFlags { flags },
+            vec![
+                AccountMeta::new_readonly(payer.pubkey(), true),
+                AccountMeta::new(mailer_pda, false),
+            ],
+        )
+    };
+    let claim_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::ClaimRecipientShare {
+            amount: None,
+            split: None,
+            close_to: None,
+        },
+        vec![
+            AccountMeta::new(recipient.pubkey(), true),
+            AccountMeta::new(claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(recipient_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+    );
+    let paused = TransactionError::InstructionError(
+        0,
+        InstructionError::Custom(mailer::MailerError::ContractPaused as u32),
+    );
+
+    // Claims paused: sends go through, claims do not
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            send("Before"),
+            set_pause_flags(mailer::PAUSE_CLAIM),
+            send("During"),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let mut transaction = Transaction::new_with_payer(
+        std::slice::from_ref(&claim_instruction),
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        paused
+    );
+
+    // Time spent with claims paused does not run down the claim window
+    let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    clock.unix_timestamp += 61 * 24 * 60 * 60;
+    context.set_sysvar(&clock);
+    recent_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+
+    // Sends paused instead: claims reopen, sends stop
+    let mut transaction = Transaction::new_with_payer(
+        &[set_pause_flags(mailer::PAUSE_SEND), claim_instruction],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let recipient_account = context
+        .banks_client
+        .get_account(recipient_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&recipient_account.data)
+            .unwrap()
+            .amount,
+        2 * 90_000
+    );
+
+    let mut transaction = Transaction::new_with_payer(&[send("After")], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        paused
+    );
+
+    // Unknown flags are rejected
+    let mut transaction =
+        Transaction::new_with_payer(&[set_pause_flags(1 << 4)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::InvalidPauseFlags as u32)
+        )
+    );
+
+    let mailer_account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.pause_flags, mailer::PAUSE_SEND);
+    assert!(!mailer_state.paused);
+}
+
+// ============================================================================
+// KYC Verifier Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_priority_sends_require_attestation_from_kyc_verifier() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let verifier = Pubkey::new_unique();
+    let verified = Keypair::new();
+    let unverified = Keypair::new();
+
+    let mut program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    // A current attestation for one sender and a lapsed one for the other
+    for (wallet, expires_at) in [(verified.pubkey(), 0), (unverified.pubkey(), 1)] {
+        program_test.add_account(
+            mailer::attestation::attestation_address(&verifier, &wallet).0,
+            solana_sdk::account::Account {
+                lamports: 1_000_000,
+                data: mailer::attestation::encode(&wallet, expires_at).to_vec(),
+                owner: verifier,
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+        program_test.add_account(
+            wallet,
+            solana_sdk::account::Account::new(1_000_000_000, 0, &system_program::id()),
+        );
+    }
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    let mut sender_usdc = Vec::new();
+    for sender in [&verified, &unverified] {
+        let token_account = create_token_account(
+            &mut banks_client,
+            &payer,
+            recent_blockhash,
+            &usdc_mint,
+            &sender.pubkey(),
+        )
+        .await;
+        mint_to(
+            &mut banks_client,
+            &payer,
+            recent_blockhash,
+            &usdc_mint,
+            &token_account,
+            10_000_000,
+        )
+        .await;
+        sender_usdc.push(token_account);
+    }
+
+    let set_kyc_verifier = |verifier: Option<Pubkey>| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SetKycVerifier { verifier },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(mailer_pda, false),
+            ],
+        )
+    };
+    let send = |sender: &Keypair, sender_usdc: Pubkey, priority: bool, with_attestation: bool| {
+        let to = Pubkey::new_unique();
+        let mut accounts = vec![
+            AccountMeta::new(sender.pubkey(), true),
+            AccountMeta::new(get_claim_pda(&to).0, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(sender.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&to).0, false),
+            AccountMeta::new(get_message_nonce_pda(&sender.pubkey()).0, false),
+        ];
+        if with_attestation {
+            let (attestation, _) =
+                mailer::attestation::attestation_address(&verifier, &sender.pubkey());
+            accounts.push(AccountMeta::new_readonly(attestation, false));
+        }
+        let instruction = Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to,
+                subject: "Subject".to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: priority,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: true,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            accounts,
+        );
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&sender.pubkey()));
+        transaction.sign(&[sender], recent_blockhash);
+        transaction
+    };
+    let verification_required = TransactionError::InstructionError(
+        0,
+        InstructionError::Custom(mailer::MailerError::VerificationRequired as u32),
+    );
+
+    let mut transaction =
+        Transaction::new_with_payer(&[set_kyc_verifier(Some(verifier))], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Standard sends stay open to everyone
+    banks_client
+        .process_transaction(send(&unverified, sender_usdc[1], false, false))
+        .await
+        .unwrap();
+
+    // Priority sends need a current attestation, which cannot be left out
+    for with_attestation in [false, true] {
+        assert_eq!(
+            banks_client
+                .process_transaction(send(&unverified, sender_usdc[1], true, with_attestation))
+                .await
+                .unwrap_err()
+                .unwrap(),
+            verification_required
+        );
+    }
+    assert_eq!(
+        banks_client
+            .process_transaction(send(&verified, sender_usdc[0], true, false))
+            .await
+            .unwrap_err()
+            .unwrap(),
+        verification_required
+    );
+    banks_client
+        .process_transaction(send(&verified, sender_usdc[0], true, true))
+        .await
+        .unwrap();
+
+    // Clearing the verifier reopens priority sends
+    let mut transaction =
+        Transaction::new_with_payer(&[set_kyc_verifier(None)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    banks_client
+        .process_transaction(send(&unverified, sender_usdc[1], true, false))
+        .await
+        .unwrap();
+
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.kyc_verifier, None);
+}
+
+// ============================================================================
+// Claim Split Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_claim_split_pays_each_destination_its_weight() {
+    use mailer::ClaimSplit;
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    // Three accounting buckets of the recipient, and one account of someone else
+    let recipient = Keypair::new();
+    let mut buckets = Vec::new();
+    for _ in 0..3 {
+        buckets.push(
+            create_token_account(
+                &mut banks_client,
+                &payer,
+                recent_blockhash,
+                &usdc_mint,
+                &recipient.pubkey(),
+            )
+            .await,
+        );
+    }
+    let foreign = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let (claim_pda, _) = get_claim_pda(&recipient.pubkey());
+
+    // A priority send leaves a 90,000 share to claim
+    let send_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Send {
+            to: recipient.pubkey(),
+            subject: "Invoice".to_string(),
+            _body: "Body".to_string(),
+            revenue_share_to_receiver: true,
+            resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: true,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let claim = |amount: Option<u64>, split: Vec<ClaimSplit>| {
+        let mut accounts = vec![
+            AccountMeta::new(recipient.pubkey(), true),
+            AccountMeta::new(claim_pda, false),
+            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(buckets[0], false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ];
+        accounts.extend(
+            split
+                .iter()
+                .map(|entry| AccountMeta::new(entry.destination, false)),
+        );
+        let instruction = Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::ClaimRecipientShare {
+                amount,
+                split: Some(split),
+                close_to: None,
+            },
+            accounts,
+        );
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer, &recipient], recent_blockhash);
+        transaction
+    };
+    let split = |weights: &[(Pubkey, u16)]| {
+        weights
+            .iter()
+            .map(|&(destination, bps)| ClaimSplit { destination, bps })
+            .collect::<Vec<_>>()
+    };
+    let fails_with = |error: mailer::MailerError| {
+        TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+    };
+
+    // Weights must cover the whole claim exactly, over distinct destinations
+    for invalid in [
+        split(&[(buckets[0], 5_000), (buckets[1], 4_999)]),
+        split(&[(buckets[0], 5_000), (buckets[0], 5_000)]),
+        split(&[(buckets[0], 10_000), (buckets[1], 0)]),
+        vec![],
+    ] {
+        assert_eq!(
+            banks_client
+                .process_transaction(claim(None, invalid))
+                .await
+                .unwrap_err()
+                .unwrap(),
+            fails_with(mailer::MailerError::InvalidClaimSplit)
+        );
+    }
+
+    // Every destination must belong to the recipient
+    assert_eq!(
+        banks_client
+            .process_transaction(claim(None, split(&[(buckets[1], 5_000), (foreign, 5_000)])))
+            .await
+            .unwrap_err()
+            .unwrap(),
+        fails_with(mailer::MailerError::InvalidAccountOwner)
+    );
+
+    // A partial claim split in half leaves its rounding dust with the first destination
+    let result = banks_client
+        .process_transaction_with_metadata(claim(
+            Some(10_001),
+            split(&[(buckets[1], 5_000), (buckets[2], 5_000)]),
+        ))
+        .await
+        .unwrap();
+    assert!(result.result.is_ok());
+    let splits: Vec<_> = indexer::decode_logs(&result.metadata.unwrap().log_messages)
+        .into_iter()
+        .filter_map(|activity| match activity {
+            MailerActivity::ClaimSplit {
+                recipient: who,
+                destination,
+                amount,
+                bps,
+            } => {
+                assert_eq!(who, recipient.pubkey());
+                Some((destination, amount, bps))
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        splits,
+        vec![(buckets[1], 5_001, 5_000), (buckets[2], 5_000, 5_000)]
+    );
+
+    // The rest is split three ways
+    let transaction = claim(
+        None,
+        split(&[
+            (buckets[0], 2_000),
+            (buckets[1], 3_000),
+            (buckets[2], 5_000),
+        ]),
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mut balances = Vec::new();
+    for bucket in &buckets {
+        let account = banks_client.get_account(*bucket).await.unwrap().unwrap();
+        balances.push(TokenAccount::unpack(&account.data).unwrap().amount);
+    }
+    assert_eq!(balances, vec![15_999 + 2, 5_001 + 23_999, 5_000 + 39_999]);
+    assert_eq!(balances.iter().sum::<u64>(), 90_000);
+}
+
+// ============================================================================
+// Delegation Fee Escrow Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_delegation_fee_escrow_is_refunded_on_timely_rejection() {
+    use solana_sdk::{clock::Clock, instruction::InstructionError, transaction::TransactionError};
+
+    let delegator = Keypair::new();
+    let first = Keypair::new();
+    let second = Keypair::new();
+    let third = Keypair::new();
+
+    let mut program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    program_test.add_account(
+        delegator.pubkey(),
+        solana_sdk::account::Account::new(1_000_000_000, 0, &system_program::id()),
+    );
+    let mut context = program_test.start_with_context().await;
+    let recent_blockhash = context.last_blockhash;
+
+    let usdc_mint =
+        create_usdc_mint(&mut context.banks_client, &context.payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+    let payer = context.payer.insecure_clone();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let mailer_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    let delegator_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &delegator.pubkey(),
+    )
+    .await;
+    mint_to(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &delegator_usdc,
+        100_000_000,
+    )
+    .await;
+
+    let (delegation_pda, _) = get_delegation_pda(&delegator.pubkey());
+    let set_window = |window: i64| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SetDelegationRefundWindow { window },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(mailer_pda, false),
+            ],
+        )
+    };
+    let delegate_to = |delegate: &Keypair, previous: Option<&Keypair>| {
+        let mut accounts = vec![
+            AccountMeta::new(delegator.pubkey(), true),
+            AccountMeta::new(delegation_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(delegator_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(get_delegate_index_pda(&delegate.pubkey()).0, false),
+        ];
+        if let Some(previous) = previous {
+            accounts.push(AccountMeta::new(
+                get_delegate_index_pda(&previous.pubkey()).0,
+                false,
+            ));
+        }
+        let instruction = Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::DelegateTo {
+                delegate: Some(delegate.pubkey()),
+            },
+            accounts,
+        );
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer, &delegator], recent_blockhash);
+        transaction
+    };
+    let respond = |delegate: &Keypair, accept: bool| {
+        let (instruction, mut accounts) = if accept {
+            (MailerInstruction::AcceptDelegation, vec![])
+        } else {
+            (
+                MailerInstruction::RejectDelegation,
+                vec![AccountMeta::new(
+                    get_delegate_index_pda(&delegate.pubkey()).0,
+                    false,
+                )],
+            )
+        };
+        accounts.splice(
+            0..0,
+            [
+                AccountMeta::new_readonly(delegate.pubkey(), true),
+                AccountMeta::new(delegation_pda, false),
+                AccountMeta::new(mailer_pda, false),
+            ],
+        );
+        let instruction = Instruction::new_with_borsh(program_id(), &instruction, accounts);
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer, delegate], recent_blockhash);
+        transaction
+    };
+    // The fee payer varies so repeated refunds are distinct transactions
+    let refund = |fee_payer: &Keypair| {
+        let instruction = Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::RefundDelegationFee,
+            vec![
+                AccountMeta::new_readonly(delegator.pubkey(), true),
+                AccountMeta::new(delegation_pda, false),
+                AccountMeta::new_readonly(mailer_pda, false),
+                AccountMeta::new(delegator_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+            ],
+        );
+        let mut transaction =
+            Transaction::new_with_payer(&[instruction], Some(&fee_payer.pubkey()));
+        transaction.sign(&[fee_payer, &delegator], recent_blockhash);
+        transaction
+    };
+    let mailer_error = |error: mailer::MailerError| {
+        TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+    };
+
+    // Windows beyond 30 days are rejected
+    let mut transaction =
+        Transaction::new_with_payer(&[set_window(31 * 24 * 60 * 60)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        mailer_error(mailer::MailerError::InvalidDelegationRefundWindow)
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[set_window(172_800)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // The fee is escrowed on the delegation instead of credited to the owner
+    context
+        .banks_client
+        .process_transaction(delegate_to(&first, None))
+        .await
+        .unwrap();
+    let delegation_account = context
+        .banks_client
+        .get_account(delegation_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let delegation: Delegation =
+        BorshDeserialize::deserialize(&mut &delegation_account.data[8..]).unwrap();
+    assert_eq!(delegation.escrowed_fee, 10_000_000);
+    assert!(!delegation.fee_refundable);
+    let mailer_account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.owner_claimable, 0);
+
+    // A timely rejection makes it refundable, and it must be reclaimed before delegating again
+    // (the extra index account keeps the failed attempt distinct from the later retry)
+    context
+        .banks_client
+        .process_transaction(respond(&first, false))
+        .await
+        .unwrap();
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(delegate_to(&second, Some(&first)))
+            .await
+            .unwrap_err()
+            .unwrap(),
+        mailer_error(mailer::MailerError::DelegationFeeRefundPending)
+    );
+    context
+        .banks_client
+        .process_transaction(refund(&payer))
+        .await
+        .unwrap();
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(refund(&delegator))
+            .await
+            .unwrap_err()
+            .unwrap(),
+        mailer_error(mailer::MailerError::DelegationFeeNotRefundable)
+    );
+    let delegator_account = context
+        .banks_client
+        .get_account(delegator_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    let delegator_token = TokenAccount::unpack(&delegator_account.data).unwrap();
+    assert_eq!(delegator_token.amount, 100_000_000);
+
+    // Only the delegate can accept, which credits the owner
+    context
+        .banks_client
+        .process_transaction(delegate_to(&second, None))
+        .await
+        .unwrap();
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(respond(&first, true))
+            .await
+            .unwrap_err()
+            .unwrap(),
+        mailer_error(mailer::MailerError::NoDelegationToAccept)
+    );
+    context
+        .banks_client
+        .process_transaction(respond(&second, true))
+        .await
+        .unwrap();
+    let mailer_account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.owner_claimable, 10_000_000);
+
+    // A rejection after the window leaves the fee with the owner
+    context
+        .banks_client
+        .process_transaction(delegate_to(&third, Some(&second)))
+        .await
+        .unwrap();
+    let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    clock.unix_timestamp += 172_800 + 1;
+    context.set_sysvar(&clock);
+    context
+        .banks_client
+        .process_transaction(respond(&third, false))
+        .await
+        .unwrap();
+
+    let delegation_account = context
+        .banks_client
+        .get_account(delegation_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let delegation: Delegation =
+        BorshDeserialize::deserialize(&mut &delegation_account.data[8..]).unwrap();
+    assert_eq!(delegation.escrowed_fee, 0);
+    assert!(!delegation.fee_refundable);
+    let mailer_account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.owner_claimable, 20_000_000);
+}
+
+// ============================================================================
+// Webhook SLA Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_webhook_sla_counters_track_routes_statuses_and_latency() {
+    use solana_sdk::{clock::Clock, instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let recent_blockhash = context.last_blockhash;
+    let payer = context.payer.insecure_clone();
+
+    let usdc_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+    let webhook_pda = register_webhook(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        "alerts",
+    )
+    .await;
+
+    let set_slot = |context: &mut ProgramTestContext, slot: u64| {
+        context.set_sysvar(&Clock {
+            slot,
+            ..Clock::default()
+        });
+    };
+    let webhook_send = |to: Pubkey| {
+        let instruction = Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SendThroughWebhook {
+                to,
+                webhook_id: "alerts".to_string(),
+                revenue_share_to_receiver: false,
+                resolve_sender_to_name: false,
+                strict: true,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(get_claim_pda(&to).0, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(webhook_pda, false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(get_policy_pda(&to).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            ],
+        );
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer], recent_blockhash);
+        transaction
+    };
+    let record_status = |to: Pubkey, sent_slot: u64, delivered: bool| {
+        let instruction = Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::RecordDeliveryStatus {
+                to,
+                nonce: 0,
+                sent_slot,
+                delivered,
+            },
+            vec![
+                AccountMeta::new_readonly(payer.pubkey(), true),
+                AccountMeta::new(webhook_pda, false),
+            ],
+        );
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer], recent_blockhash);
+        transaction
+    };
+
+    let first = Pubkey::new_unique();
+    let second = Pubkey::new_unique();
+    set_slot(&mut context, 100);
+    context
+        .banks_client
+        .process_transaction(webhook_send(first))
+        .await
+        .unwrap();
+    context
+        .banks_client
+        .process_transaction(webhook_send(second))
+        .await
+        .unwrap();
+
+    // Statuses cannot predate their send
+    set_slot(&mut context, 110);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(record_status(first, 111, true))
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::InvalidDeliverySlot as u32)
+        )
+    );
+    context
+        .banks_client
+        .process_transaction(record_status(first, 100, true))
+        .await
+        .unwrap();
+    set_slot(&mut context, 130);
+    context
+        .banks_client
+        .process_transaction(record_status(second, 100, false))
+        .await
+        .unwrap();
+
+    // Every routed send has a status now
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(record_status(second, 101, true))
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::NoPendingDelivery as u32)
+        )
+    );
+
+    // GetWebhookStats returns the counters with the average latency
+    let get_stats = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::GetWebhookStats,
+        vec![AccountMeta::new_readonly(webhook_pda, false)],
+    );
+    let mut transaction = Transaction::new_with_payer(&[get_stats], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let simulation = context
+        .banks_client
+        .simulate_transaction(transaction)
+        .await
+        .unwrap();
+    let return_data = simulation.simulation_details.unwrap().return_data.unwrap();
+
+    assert_eq!(return_data.program_id, program_id());
+    let stats: mailer::WebhookStats =
+        BorshDeserialize::deserialize(&mut &return_data.data[..]).unwrap();
+    assert_eq!(
+        stats,
+        mailer::WebhookStats {
+            sends_routed: 2,
+            statuses_recorded: 2,
+            deliveries_failed: 1,
+            average_ack_latency_slots: 20,
+        }
+    );
+}
+
+// ============================================================================
+// Send Credits Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_send_credits_pay_fees_before_falling_back_to_transfers() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let credits_pda = mailer::pda::send_credits(&program_id(), &payer.pubkey()).0;
+    let credit_accounts = |with_system_program: bool| {
+        let mut accounts = vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(credits_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ];
+        if with_system_program {
+            accounts.push(AccountMeta::new_readonly(system_program::id(), false));
+        }
+        accounts
+    };
+    let withdraw = |amount: Option<u64>| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::WithdrawSendCredits { amount },
+            credit_accounts(false),
+        )
+    };
+    let send = |priority: bool, with_credits: bool| {
+        let to = Pubkey::new_unique();
+        let mut accounts = vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(get_claim_pda(&to).0, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&to).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ];
+        if with_credits {
+            accounts.push(AccountMeta::new(credits_pda, false));
+        }
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to,
+                subject: "Subject".to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: priority,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: true,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            accounts,
+        )
+    };
+    let process = |instruction: Instruction| {
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer], recent_blockhash);
+        transaction
+    };
+
+    // Deposit half the sender's USDC as credits
+    let deposit = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::DepositSendCredits { amount: 500_000 },
+        credit_accounts(true),
+    );
+    banks_client
+        .process_transaction(process(deposit))
+        .await
+        .unwrap();
+
+    // Priority and standard fees come out of the credits, leaving the token balance alone
+    banks_client
+        .process_transaction(process(send(true, true)))
+        .await
+        .unwrap();
+    banks_client
+        .process_transaction(process(send(false, true)))
+        .await
+        .unwrap();
+
+    let credits_account = banks_client
+        .get_account(credits_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        &credits_account.data[..8],
+        &mailer::discriminator::account("SendCredits")
+    );
+    let credits: mailer::SendCredits =
+        BorshDeserialize::deserialize(&mut &credits_account.data[8..]).unwrap();
+    assert_eq!(credits.balance, 500_000 - 100_000 - 10_000);
+    let sender_account = banks_client
+        .get_account(sender_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&sender_account.data).unwrap().amount,
+        500_000
+    );
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.owner_claimable, 10_000 + 10_000);
+
+    // The vault owes the unspent credits until they are spent or withdrawn
+    assert_eq!(mailer_state.credit_liabilities, 500_000 - 100_000 - 10_000);
+    assert_eq!(
+        mailer_state.tracked_liabilities(),
+        mailer_state.owner_claimable
+            + mailer_state.recipient_liabilities
+            + mailer_state.credit_liabilities
+    );
+    let mailer_usdc_account = banks_client
+        .get_account(mailer_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    let vault_balance = TokenAccount::unpack(&mailer_usdc_account.data)
+        .unwrap()
+        .amount;
+    assert_eq!(vault_balance, mailer_state.tracked_liabilities());
+
+    // Once the credits cannot cover a fee, it is transferred as usual
+    banks_client
+        .process_transaction(process(withdraw(Some(389_999))))
+        .await
+        .unwrap();
+    banks_client
+        .process_transaction(process(send(false, true)))
+        .await
+        .unwrap();
+    let sender_account = banks_client
+        .get_account(sender_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&sender_account.data).unwrap().amount,
+        500_000 + 389_999 - 10_000
+    );
+
+    // Withdrawals are bounded by the balance, and `None` empties it
+    assert_eq!(
+        banks_client
+            .process_transaction(process(withdraw(Some(2))))
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::InvalidClaimAmount as u32)
+        )
+    );
+    banks_client
+        .process_transaction(process(withdraw(None)))
+        .await
+        .unwrap();
+    let credits_account = banks_client
+        .get_account(credits_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let credits: mailer::SendCredits =
+        BorshDeserialize::deserialize(&mut &credits_account.data[8..]).unwrap();
+    assert_eq!(credits.balance, 0);
+    let sender_account = banks_client
+        .get_account(sender_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&sender_account.data).unwrap().amount,
+        1_000_000 - 120_000
+    );
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.credit_liabilities, 0);
+}
+
+// ============================================================================
+// Priority Downgrade Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_priority_sends_downgrade_when_recipient_claim_is_capped_or_frozen() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let (capped, frozen) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let sanctioned = |address: &Pubkey| mailer::pda::sanctioned(&program_id(), address).0;
+    let send = |to: Pubkey, subject: &str, allow_downgrade: bool, sanctions: bool| {
+        let mut accounts = vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(get_claim_pda(&to).0, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&to).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ];
+        if sanctions {
+            accounts.push(AccountMeta::new_readonly(
+                sanctioned(&payer.pubkey()),
+                false,
+            ));
+            accounts.push(AccountMeta::new_readonly(sanctioned(&to), false));
+        }
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to,
+                subject: subject.to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: true,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: true,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            accounts,
+        )
+    };
+    let process = |instructions: &[Instruction]| {
+        let mut transaction = Transaction::new_with_payer(instructions, Some(&payer.pubkey()));
+        transaction.sign(&[&payer], recent_blockhash);
+        transaction
+    };
+    let claim_amount = |account: Option<solana_sdk::account::Account>| {
+        account.map(|account| {
+            let claim: RecipientClaim =
+                BorshDeserialize::deserialize(&mut &account.data[8..]).unwrap();
+            claim.amount
+        })
+    };
+
+    // Cap claims at 150_000: the first 90_000 share fits, a second one does not, so with
+    // consent the send goes out as standard, charging only the owner fee
+    let set_cap = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetClaimCap { cap: 150_000 },
+        vec![
+            AccountMeta::new_readonly(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+        ],
+    );
+    banks_client
+        .process_transaction(process(&[set_cap, send(capped, "First", false, false)]))
+        .await
+        .unwrap();
+    let transaction = process(&[send(capped, "Second", true, false)]);
+    let simulation = banks_client
+        .simulate_transaction(transaction.clone())
+        .await
+        .unwrap();
+    let activity = indexer::decode_logs(&simulation.simulation_details.unwrap().logs);
+    assert_eq!(
+        activity[0],
+        MailerActivity::DowngradedToStandard {
+            sender: payer.pubkey(),
+            to: capped,
+            reason: "claim cap".to_string(),
+        }
+    );
+    assert!(matches!(&activity[1], MailerActivity::Sent(mail) if !mail.revenue_share));
+    banks_client.process_transaction(transaction).await.unwrap();
+    assert_eq!(
+        claim_amount(
+            banks_client
+                .get_account(get_claim_pda(&capped).0)
+                .await
+                .unwrap()
+        ),
+        Some(90_000)
+    );
+    let sender_account = banks_client
+        .get_account(sender_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&sender_account.data).unwrap().amount,
+        1_000_000 - 100_000 - 10_000
+    );
+
+    // A sanctioned recipient cannot claim, so their claim is frozen
+    let sanction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SanctionAddress { address: frozen },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(sanctioned(&frozen), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let enable_sanctions = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetSanctionsEnabled { enabled: true },
+        vec![
+            AccountMeta::new_readonly(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+        ],
+    );
+    let transaction = process(&[
+        sanction,
+        enable_sanctions,
+        send(frozen, "Frozen", true, true),
+    ]);
+    let simulation = banks_client
+        .simulate_transaction(transaction.clone())
+        .await
+        .unwrap();
+    let activity = indexer::decode_logs(&simulation.simulation_details.unwrap().logs);
+    assert!(activity.contains(&MailerActivity::DowngradedToStandard {
+        sender: payer.pubkey(),
+        to: frozen,
+        reason: "claim frozen".to_string(),
+    }));
+    banks_client.process_transaction(transaction).await.unwrap();
+    assert_eq!(
+        claim_amount(
+            banks_client
+                .get_account(get_claim_pda(&frozen).0)
+                .await
+                .unwrap()
+        ),
+        None
+    );
+
+    // Without consent a send past the cap fails
+    assert_eq!(
+        banks_client
+            .process_transaction(process(&[send(capped, "Third", false, true)]))
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::ClaimCapExceeded as u32)
+        )
+    );
+
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.claim_cap, 150_000);
+    assert_eq!(mailer_state.owner_claimable, 10_000 * 3);
+}
+
+// ============================================================================
+// Solvency Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_priority_sends_fail_while_vault_under_covers_claim_liabilities() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+    // A donation leaves the vault holding 100_000 more than it owes
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_usdc,
+        100_000,
+    )
+    .await;
+
+    // The payer sends to (and later claims as) themselves
+    let (claim_pda, _) = get_claim_pda(&payer.pubkey());
+    let send = |subject: &str| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: payer.pubkey(),
+                subject: subject.to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: true,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(get_policy_pda(&payer.pubkey()).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            ],
+        )
+    };
+    let process = |instructions: &[Instruction]| {
+        let mut transaction = Transaction::new_with_payer(instructions, Some(&payer.pubkey()));
+        transaction.sign(&[&payer], recent_blockhash);
+        transaction
+    };
+    let mailer_state = |account: solana_sdk::account::Account| -> MailerState {
+        BorshDeserialize::deserialize(&mut &account.data[8..]).unwrap()
+    };
+
+    // Require 140% coverage: after two sends the vault holds 300_000 against 200_000 owed
+    let set_threshold = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetSolvencyThreshold {
+            min_coverage_bps: 14_000,
+        },
+        vec![
+            AccountMeta::new_readonly(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+        ],
+    );
+    banks_client
+        .process_transaction(process(&[set_threshold, send("First"), send("Second")]))
+        .await
+        .unwrap();
+    let state = mailer_state(banks_client.get_account(mailer_pda).await.unwrap().unwrap());
+    assert_eq!(state.min_coverage_bps, 14_000);
+    assert_eq!(state.recipient_liabilities, 180_000);
+    assert_eq!(state.tracked_liabilities(), 200_000);
+
+    // A third would leave 400_000 against 300_000, so it fails even though it is not strict
+    let transaction = process(&[send("Third")]);
+    let simulation = banks_client
+        .simulate_transaction(transaction.clone())
+        .await
+        .unwrap();
+    let logs = simulation.simulation_details.unwrap().logs;
+    assert!(logs.iter().any(|log| log.contains(
+        "SolvencyAlert { vault: 400000, liabilities: 300000, min_coverage_bps: 14000 }"
+    )));
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::TemporarilyInsolvent as u32)
+        )
+    );
+
+    // Claiming pays down the liabilities, after which sends go through again
+    let claim = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::ClaimRecipientShare {
+            amount: None,
+            split: None,
+            close_to: None,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+    );
+    banks_client
+        .process_transaction(process(&[claim, send("Fourth")]))
+        .await
+        .unwrap();
+    let state = mailer_state(banks_client.get_account(mailer_pda).await.unwrap().unwrap());
+    assert_eq!(state.recipient_liabilities, 90_000);
+    assert_eq!(state.owner_claimable, 30_000);
+    let vault_account = banks_client
+        .get_account(mailer_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&vault_account.data).unwrap().amount,
+        220_000
+    );
+}
+
+// ============================================================================
+// Subscription Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_subscriptions_cover_send_fees_until_exhausted_or_expired() {
+    use solana_sdk::{clock::Clock, instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let recent_blockhash = context.last_blockhash;
+    let payer = context.payer.insecure_clone();
+
+    let usdc_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        2_000_000,
+    )
+    .await;
+
+    let plan_pda = mailer::pda::plan(&program_id(), 1).0;
+    let subscription_pda = mailer::pda::subscription(&program_id(), &payer.pubkey()).0;
+    let set_plan = |active: bool| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SetPlan {
+                plan_id: 1,
+                price: 500_000,
+                duration: 3_600,
+                max_messages: 2,
+                active,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(mailer_pda, false),
+                AccountMeta::new(plan_pda, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    };
+    let subscription_accounts = |with_system_program: bool| {
+        let mut accounts = vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(plan_pda, false),
+            AccountMeta::new(subscription_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ];
+        if with_system_program {
+            accounts.push(AccountMeta::new_readonly(system_program::id(), false));
+        }
+        accounts
+    };
+    let subscribe = || {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Subscribe { plan_id: 1 },
+            subscription_accounts(true),
+        )
+    };
+    let renew = || {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::RenewSubscription,
+            subscription_accounts(false),
+        )
+    };
+    let send = || {
+        let to = Pubkey::new_unique();
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to,
+                subject: "Subject".to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: false,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: true,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(get_claim_pda(&to).0, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(subscription_pda, false),
+                AccountMeta::new_readonly(get_policy_pda(&to).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            ],
+        )
+    };
+    let process = |instructions: &[Instruction]| {
+        let mut transaction = Transaction::new_with_payer(instructions, Some(&payer.pubkey()));
+        transaction.sign(&[&payer], recent_blockhash);
+        transaction
+    };
+    let fails_with = |error: mailer::MailerError| {
+        TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+    };
+
+    // Subscribing pays the plan price to the owner; a second subscribe must renew instead
+    context
+        .banks_client
+        .process_transaction(process(&[set_plan(true), subscribe()]))
+        .await
+        .unwrap();
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(process(&[subscribe()]))
+            .await
+            .unwrap_err()
+            .unwrap(),
+        fails_with(mailer::MailerError::SubscriptionActive)
+    );
+    let subscription_account = context
+        .banks_client
+        .get_account(subscription_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        &subscription_account.data[..8],
+        &mailer::discriminator::account("Subscription")
+    );
+    let subscription: mailer::Subscription =
+        BorshDeserialize::deserialize(&mut &subscription_account.data[8..]).unwrap();
+    let clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    assert_eq!(subscription.expires_at, clock.unix_timestamp + 3_600);
+    assert_eq!(subscription.messages_remaining, 2);
+
+    // Two sends are covered; the third pays the standard fee
+    context
+        .banks_client
+        .process_transaction(process(&[send(), send(), send()]))
+        .await
+        .unwrap();
+    let sender_account = context
+        .banks_client
+        .get_account(sender_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&sender_account.data).unwrap().amount,
+        2_000_000 - 500_000 - 10_000
+    );
+
+    // Renewing the exhausted but unexpired subscription extends it and tops up its messages
+    context
+        .banks_client
+        .process_transaction(process(&[renew()]))
+        .await
+        .unwrap();
+    let subscription_account = context
+        .banks_client
+        .get_account(subscription_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let subscription: mailer::Subscription =
+        BorshDeserialize::deserialize(&mut &subscription_account.data[8..]).unwrap();
+    assert_eq!(subscription.expires_at, clock.unix_timestamp + 7_200);
+    assert_eq!(subscription.messages_remaining, 2);
+
+    // Once expired, sends pay again and the remaining messages are left unused
+    let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    clock.unix_timestamp = subscription.expires_at;
+    context.set_sysvar(&clock);
+    context
+        .banks_client
+        .process_transaction(process(&[send()]))
+        .await
+        .unwrap();
+    let subscription_account = context
+        .banks_client
+        .get_account(subscription_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let subscription: mailer::Subscription =
+        BorshDeserialize::deserialize(&mut &subscription_account.data[8..]).unwrap();
+    assert_eq!(subscription.messages_remaining, 2);
+
+    // Inactive plans accept no renewals
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(process(&[set_plan(false), renew()]))
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(mailer::MailerError::PlanInactive as u32)
+        )
+    );
+
+    let sender_account = context
+        .banks_client
+        .get_account(sender_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&sender_account.data).unwrap().amount,
+        2_000_000 - 1_000_000 - 20_000
+    );
+    let mailer_account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.owner_claimable, 1_000_000 + 20_000);
+}
+
+#[tokio::test]
+async fn test_payload_limits_reject_oversized_fields_and_tiers_scale_fees() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    // Subjects are limited to 16 bytes; every full 100 payload bytes add 50% of the fee
+    let set_limits = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetPayloadLimits {
+            limits: PayloadLimits {
+                max_subject_len: 16,
+                ..PayloadLimits::default()
+            },
+            fee_tier_len: 100,
+            fee_tier_percent: 50,
+        },
+        vec![
+            AccountMeta::new_readonly(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[set_limits], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let send = |subject: &str, body: &str| {
+        let to = Pubkey::new_unique();
+        let instruction = Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to,
+                subject: subject.to_string(),
+                _body: body.to_string(),
+                revenue_share_to_receiver: false,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: true,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(get_claim_pda(&to).0, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(get_policy_pda(&to).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            ],
+        );
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer], recent_blockhash);
+        transaction
+    };
+
+    // A short payload pays the flat fee; 257 payload bytes reach the second tier
+    banks_client
+        .process_transaction(send("Subject", "Body"))
+        .await
+        .unwrap();
+    banks_client
+        .process_transaction(send("Subject", &"x".repeat(250)))
+        .await
+        .unwrap();
+    let sender_account = banks_client
+        .get_account(sender_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&sender_account.data).unwrap().amount,
+        1_000_000 - 10_000 - 20_000
+    );
+
+    assert_eq!(
+        banks_client
+            .process_transaction(send(&"s".repeat(17), "Body"))
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::PayloadTooLarge as u32)
+        )
+    );
+
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.payload_limits.max_subject_len, 16);
+    assert_eq!(mailer_state.owner_claimable, 30_000);
+}
+
+#[cfg(feature = "devnet-faucet")]
+#[tokio::test]
+async fn test_dev_faucet_mints_to_caller_once_per_cooldown() {
+    use solana_sdk::{clock::Clock, instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+    let recent_blockhash = context.last_blockhash;
+
+    let usdc_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+    let caller_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+
+    // Devnet deployments hand the mint authority to the mailer PDA
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let set_authority = spl_instruction::set_authority(
+        &spl_token::id(),
+        &usdc_mint,
+        Some(&mailer_pda),
+        spl_instruction::AuthorityType::MintTokens,
+        &payer.pubkey(),
+        &[],
+    )
+    .unwrap();
+    let mut transaction =
+        Transaction::new_with_payer(&[init_instruction, set_authority], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let (drip_pda, _) = mailer::pda::faucet_drip(&program_id(), &payer.pubkey());
+    let drip = |amount: u64, blockhash| {
+        let instruction = Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::DevFaucet { amount },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(drip_pda, false),
+                AccountMeta::new_readonly(mailer_pda, false),
+                AccountMeta::new(usdc_mint, false),
+                AccountMeta::new(caller_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer], blockhash);
+        transaction
+    };
+    let custom = |error: mailer::MailerError| {
+        TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+    };
+
+    // Amounts over the per-call maximum are rejected
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(drip(100_000_001, recent_blockhash))
+            .await
+            .unwrap_err()
+            .unwrap(),
+        custom(mailer::MailerError::InvalidClaimAmount)
+    );
+
+    context
+        .banks_client
+        .process_transaction(drip(5_000_000, recent_blockhash))
+        .await
+        .unwrap();
+    let caller_account = context
+        .banks_client
+        .get_account(caller_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&caller_account.data).unwrap().amount,
+        5_000_000
+    );
+
+    // A second drip within the hour is rate limited
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(drip(1_000_000, recent_blockhash))
+            .await
+            .unwrap_err()
+            .unwrap(),
+        custom(mailer::MailerError::RateLimitExceeded)
+    );
+
+    let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    clock.unix_timestamp += 60 * 60;
+    context.set_sysvar(&clock);
+    let recent_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+
+    context
+        .banks_client
+        .process_transaction(drip(1_000_000, recent_blockhash))
+        .await
+        .unwrap();
+    let caller_account = context
+        .banks_client
+        .get_account(caller_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&caller_account.data).unwrap().amount,
+        6_000_000
+    );
+
+    let drip_account = context
+        .banks_client
+        .get_account(drip_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let record: mailer::FaucetDrip =
+        BorshDeserialize::deserialize(&mut &drip_account.data[8..]).unwrap();
+    assert_eq!(record.caller, payer.pubkey());
+    assert_eq!(record.last_drip_at, clock.unix_timestamp);
+}
+
+#[tokio::test]
+async fn test_send_to_email_hash_logs_hash_instead_of_address() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let send = |email_hash: Vec<u8>| {
+        let instruction = Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SendToEmailHash {
+                email_hash,
+                hint_id: 42,
+                subject: "Test".to_string(),
+                _body: "Body".to_string(),
+                strict: false,
+            },
+            vec![
+                AccountMeta::new_readonly(payer.pubkey(), true),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer], recent_blockhash);
+        transaction
+    };
+
+    // Only 32-byte hashes are accepted
+    assert_eq!(
+        banks_client
+            .process_transaction(send(vec![7; 20]))
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::InvalidEmailHash as u32)
+        )
+    );
+
+    let email_hash = mailer::hash_email(b"per-recipient salt", "user@example.com");
+    let result = banks_client
+        .process_transaction_with_metadata(send(email_hash.to_vec()))
+        .await
+        .unwrap();
+    assert!(result.result.is_ok(), "{:?}", result.result);
+    let logs = result.metadata.unwrap().log_messages;
+    assert!(logs.iter().all(|log| !log.contains("user@example.com")));
+    assert!(matches!(
+        indexer::decode_logs(&logs).as_slice(),
+        [MailerActivity::Sent(SentMail {
+            to: SendTarget::EmailHash { hash, hint_id: 42 },
+            effective_fee: 100_000,
+            ..
+        })] if *hash == email_hash
+    ));
+
+    // Standard fee: only the 10% owner share is charged
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.owner_claimable, 10_000);
+}
+
+// ============================================================================
+// State Migration Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_migrate_state_upgrades_unversioned_mailer_state_in_place() {
+    use mailer::state_versions::{
+        MailerStateV1, MailerStateV2, MailerStateV3, VersionedMailerState,
+    };
+    use solana_sdk::{
+        account::AccountSharedData, instruction::InstructionError, transaction::TransactionError,
+    };
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+    let recent_blockhash = context.last_blockhash;
+
+    let usdc_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // Rewrite the account as an earlier deployment left it: the unversioned layout, without
+    // the trailing version byte, treasury and credit liabilities, allocated and funded at its
+    // smaller size
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let mut account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let mut state: MailerState = BorshDeserialize::deserialize(&mut &account.data[8..]).unwrap();
+    state.owner_claimable = 123_456;
+    state.claim_cap = 5_000_000;
+    let encoded = borsh::to_vec(&state).unwrap();
+    account.data.truncate(8);
+    account
+        .data
+        .extend_from_slice(&encoded[..encoded.len() - 10]);
+    account.data.resize(8 + MailerStateV1::LEN, 0);
+    account.lamports = rent.minimum_balance(8 + MailerStateV1::LEN);
+    context.set_account(&mailer_pda, &AccountSharedData::from(account));
+
+    // Instructions refuse the old layout until it is migrated
+    let pause_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetFeePaused { fee_paused: true },
+        vec![
+            AccountMeta::new_readonly(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(
+        std::slice::from_ref(&pause_instruction),
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::StateMigrationRequired as u32)
+        )
+    );
+
+    let migrate = |owner: &Pubkey| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::MigrateState,
+            vec![
+                AccountMeta::new(*owner, true),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    };
+
+    // Only the owner can migrate
+    let stranger = Keypair::new();
+    let mut transaction =
+        Transaction::new_with_payer(&[migrate(&stranger.pubkey())], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &stranger], recent_blockhash);
+    assert!(context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_err());
+
+    let mut transaction =
+        Transaction::new_with_payer(&[migrate(&payer.pubkey())], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // Each migration moves one layout version, and instructions wait for the last one
+    let account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.data.len(), 8 + MailerStateV2::LEN);
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        std::slice::from_ref(&pause_instruction),
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::StateMigrationRequired as u32)
+        )
+    );
+
+    let mut transaction =
+        Transaction::new_with_payer(&[migrate(&payer.pubkey())], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+    let account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.data.len(), 8 + MailerStateV3::LEN);
+
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut transaction =
+        Transaction::new_with_payer(&[migrate(&payer.pubkey())], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // The account grows to the current layout, rent-exempt, with every field carried over
+    let account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.data.len(), 8 + MailerState::LEN);
+    assert_eq!(account.lamports, rent.minimum_balance(8 + MailerState::LEN));
+    let migrated = match VersionedMailerState::deserialize(&account.data[8..]).unwrap() {
+        VersionedMailerState::V4(migrated) => migrated,
+        other => panic!(
+            "expected the current layout, got version {}",
+            other.version()
+        ),
+    };
+    assert_eq!(migrated.version, mailer::MAILER_STATE_VERSION);
+    assert_eq!(migrated.owner, payer.pubkey());
+    assert_eq!(migrated.owner_claimable, 123_456);
+    assert_eq!(migrated.claim_cap, 5_000_000);
+    assert_eq!(migrated.treasury, None);
+    assert_eq!(migrated.credit_liabilities, 0);
+
+    // Nothing is left to migrate, and the state is usable again
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut transaction =
+        Transaction::new_with_payer(&[migrate(&payer.pubkey())], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::StateAlreadyCurrent as u32)
+        )
+    );
+
+    let mut transaction = Transaction::new_with_payer(&[pause_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+    let account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let state: MailerState = BorshDeserialize::deserialize(&mut &account.data[8..]).unwrap();
+    assert!(state.fee_paused);
+}
+
+#[tokio::test]
+async fn test_migrate_state_upgrades_launch_mailer_state_through_every_version() {
+    use mailer::state_versions::{MailerStateV0, VersionedMailerState};
+    use solana_sdk::account::AccountSharedData;
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+    let recent_blockhash = context.last_blockhash;
+
+    let usdc_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, bump) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // Rewrite the account as the launch deployment left it: 91 bytes of state, no configuration
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let mut account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let launch_state = MailerStateV0 {
+        owner: payer.pubkey(),
+        usdc_mint,
+        send_fee: 250_000,
+        delegation_fee: 5_000_000,
+        owner_claimable: 777_000,
+        paused: false,
+        fee_paused: true,
+        bump,
+    };
+    account.data.truncate(8);
+    account
+        .data
+        .extend_from_slice(&borsh::to_vec(&launch_state).unwrap());
+    assert_eq!(account.data.len(), 8 + MailerStateV0::LEN);
+    account.lamports = rent.minimum_balance(8 + MailerStateV0::LEN);
+    context.set_account(&mailer_pda, &AccountSharedData::from(account));
+
+    let account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        VersionedMailerState::deserialize(&account.data[8..])
+            .unwrap()
+            .version(),
+        0
+    );
+
+    // One MigrateState per layout version brings it to the current layout
+    for _ in 0..mailer::MAILER_STATE_VERSION {
+        let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+        let mut transaction = Transaction::new_with_payer(
+            &[Instruction::new_with_borsh(
+                program_id(),
+                &MailerInstruction::MigrateState,
+                vec![
+                    AccountMeta::new(payer.pubkey(), true),
+                    AccountMeta::new(mailer_pda, false),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                ],
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer], recent_blockhash);
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap();
+    }
+
+    // The launch fields carry over and every later field takes its Initialize default
+    let account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.data.len(), 8 + MailerState::LEN);
+    assert_eq!(account.lamports, rent.minimum_balance(8 + MailerState::LEN));
+    let migrated: MailerState = BorshDeserialize::deserialize(&mut &account.data[8..]).unwrap();
+    assert_eq!(migrated.version, mailer::MAILER_STATE_VERSION);
+    assert_eq!(migrated.owner, payer.pubkey());
+    assert_eq!(migrated.usdc_mint, usdc_mint);
+    assert_eq!(migrated.send_fee, 250_000);
+    assert_eq!(migrated.delegation_fee, 5_000_000);
+    assert_eq!(migrated.owner_claimable, 777_000);
+    assert!(migrated.fee_paused);
+    assert_eq!(migrated.bump, bump);
+    assert_eq!(
+        migrated.claim_creation_policy,
+        mailer::ClaimCreationPolicy::Eager
+    );
+    assert_eq!(migrated.usdc_decimals, 6);
+    assert_eq!(migrated.pending_owner, None);
+    assert_eq!(migrated.treasury, None);
+    assert_eq!(migrated.credit_liabilities, 0);
+
+    // The upgraded state is usable again
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SetFeePaused { fee_paused: false },
+            vec![
+                AccountMeta::new_readonly(payer.pubkey(), true),
+                AccountMeta::new(mailer_pda, false),
+            ],
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_priority_send_upgrades_v1_recipient_claim_and_keeps_counters() {
+    use mailer::state_versions::RecipientClaimV1;
+    use solana_sdk::account::AccountSharedData;
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+    let recent_blockhash = context.last_blockhash;
+
+    let usdc_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let recipient = Keypair::new();
+    let recipient_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &recipient.pubkey(),
+    )
+    .await;
+    let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
+
+    let send_ix = |subject: &str| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient.pubkey(),
+                subject: subject.to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: true,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(recipient_claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            ],
+        )
+    };
+    let claim_ix = |amount: Option<u64>| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::ClaimRecipientShare {
+                amount,
+                split: None,
+                close_to: None,
+            },
+            vec![
+                AccountMeta::new(recipient.pubkey(), true),
+                AccountMeta::new(recipient_claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(recipient_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+            ],
+        )
+    };
+
+    let mut transaction = Transaction::new_with_payer(&[send_ix("First")], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // Rewrite the claim as a deployment before the counters left it: the V1 layout,
+    // allocated and funded at its smaller size
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let mut account = context
+        .banks_client
+        .get_account(recipient_claim_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    account.data.truncate(8 + RecipientClaimV1::LEN);
+    account.lamports = rent.minimum_balance(8 + RecipientClaimV1::LEN);
+    context.set_account(&recipient_claim_pda, &AccountSharedData::from(account));
+
+    // V1 claims stay claimable without keeping the counters
+    let mut transaction =
+        Transaction::new_with_payer(&[claim_ix(Some(30_000))], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let claim_account = context
+        .banks_client
+        .get_account(recipient_claim_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(claim_account.data.len(), 8 + RecipientClaimV1::LEN);
+    let claim = RecipientClaim::from_account_data(&claim_account.data[8..]).unwrap();
+    assert_eq!(claim.amount, 60_000);
+    assert_eq!(claim.total_claimed, 0);
+
+    let claim_rent_reserve = |account: &solana_sdk::account::Account| {
+        MailerState::deserialize(&mut &account.data[8..])
+            .unwrap()
+            .claim_rent_reserve
+    };
+    let mailer_account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let reserve = claim_rent_reserve(&mailer_account);
+
+    // The next priority send reallocs the claim, the sender funding the extra rent
+    let mut transaction = Transaction::new_with_payer(&[send_ix("Second")], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let claim_account = context
+        .banks_client
+        .get_account(recipient_claim_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(claim_account.data.len(), 8 + RecipientClaim::LEN);
+    assert_eq!(
+        claim_account.lamports,
+        rent.minimum_balance(8 + RecipientClaim::LEN)
+    );
+    let claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    assert_eq!(claim.recipient, recipient.pubkey());
+    assert_eq!(claim.amount, 150_000);
+    assert_eq!(claim.messages_received, 1);
+
+    let mut transaction = Transaction::new_with_payer(&[claim_ix(None)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let claim_account = context
+        .banks_client
+        .get_account(recipient_claim_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    assert_eq!(claim.amount, 0);
+    assert_eq!(claim.messages_received, 1);
+    assert_eq!(claim.total_claimed, 150_000);
+
+    let mailer_account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        claim_rent_reserve(&mailer_account) - reserve,
+        rent.minimum_balance(8 + RecipientClaim::LEN)
+            - rent.minimum_balance(8 + RecipientClaimV1::LEN)
+    );
+}
+
+#[tokio::test]
+async fn test_priority_send_rent_payer_sponsors_recipient_claim() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    // A protocol treasury, separate from the sender, sponsors the claim's rent
+    let treasury = Keypair::new();
+    let fund_treasury = solana_sdk::system_instruction::transfer(
+        &payer.pubkey(),
+        &treasury.pubkey(),
+        1_000_000_000,
+    );
+    let mut transaction = Transaction::new_with_payer(&[fund_treasury], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let send_ix = |to: Pubkey, rent_payer: AccountMeta| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to,
+                subject: "Sponsored".to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: true,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(get_claim_pda(&to).0, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                rent_payer,
+                AccountMeta::new_readonly(get_policy_pda(&to).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            ],
+        )
+    };
+    let rent = banks_client.get_rent().await.unwrap();
+    let claim_rent = rent.minimum_balance(8 + RecipientClaim::LEN);
+
+    // The signing treasury pays for the new claim
+    let recipient = Pubkey::new_unique();
+    let mut transaction = Transaction::new_with_payer(
+        &[send_ix(
+            recipient,
+            AccountMeta::new(treasury.pubkey(), true),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &treasury], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let treasury_balance = banks_client.get_balance(treasury.pubkey()).await.unwrap();
+    assert_eq!(treasury_balance, 1_000_000_000 - claim_rent);
+    let claim_account = banks_client
+        .get_account(get_claim_pda(&recipient).0)
+        .await
+        .unwrap()
+        .unwrap();
+    let claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    assert_eq!(claim.recipient, recipient);
+    assert_eq!(claim.amount, 90_000);
+
+    // A rent payer that does not sign fails the send rather than falling back to the sender
+    let recipient = Pubkey::new_unique();
+    let mut transaction = Transaction::new_with_payer(
+        &[send_ix(
+            recipient,
+            AccountMeta::new(treasury.pubkey(), false),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(0, InstructionError::MissingRequiredSignature)
+    );
+
+    // The sender pays by passing itself as the rent payer
+    let sender_balance = banks_client.get_balance(payer.pubkey()).await.unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[send_ix(recipient, AccountMeta::new(payer.pubkey(), true))],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    assert_eq!(
+        banks_client.get_balance(treasury.pubkey()).await.unwrap(),
+        treasury_balance
+    );
+    assert_eq!(
+        sender_balance - banks_client.get_balance(payer.pubkey()).await.unwrap(),
+        claim_rent + 5_000
+    );
+}
+
+#[tokio::test]
+async fn test_full_claim_closes_claim_and_refunds_rent() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    let closure_log_pda = initialize_closure_log(&mut banks_client, &payer, recent_blockhash).await;
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let recipient = Keypair::new();
+    let recipient_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &recipient.pubkey(),
+    )
+    .await;
+    let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
+
+    let send_ix = |subject: &str| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient.pubkey(),
+                subject: subject.to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: true,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(recipient_claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            ],
+        )
+    };
+    let refund = Pubkey::new_unique();
+    let claim_ix = |amount: Option<u64>| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::ClaimRecipientShare {
+                amount,
+                split: None,
+                close_to: Some(refund),
+            },
+            vec![
+                AccountMeta::new(recipient.pubkey(), true),
+                AccountMeta::new(recipient_claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(recipient_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new(refund, false),
+                AccountMeta::new(closure_log_pda, false),
+            ],
+        )
+    };
+    let claim_rent_reserve = |account: &solana_sdk::account::Account| {
+        MailerState::deserialize(&mut &account.data[8..])
+            .unwrap()
+            .claim_rent_reserve
+    };
+
+    let mut transaction = Transaction::new_with_payer(&[send_ix("First")], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    let claim_rent = banks_client
+        .get_account(recipient_claim_pda)
+        .await
+        .unwrap()
+        .unwrap()
+        .lamports;
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let reserve = claim_rent_reserve(&mailer_account);
+
+    // Only a claim emptied by the withdrawal is closed
+    let mut transaction =
+        Transaction::new_with_payer(&[claim_ix(Some(30_000))], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::AccountNotEmpty as u32)
+        )
+    );
+
+    let mut transaction = Transaction::new_with_payer(&[claim_ix(None)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    assert!(banks_client
+        .get_account(recipient_claim_pda)
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(banks_client.get_balance(refund).await.unwrap(), claim_rent);
+    let recipient_token_account = banks_client
+        .get_account(recipient_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&recipient_token_account.data)
+            .unwrap()
+            .amount,
+        90_000
+    );
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    assert_eq!(claim_rent_reserve(&mailer_account), reserve - claim_rent);
+
+    // The next priority send recreates the claim
+    let mut transaction = Transaction::new_with_payer(&[send_ix("Second")], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let claim_account = banks_client
+        .get_account(recipient_claim_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    assert_eq!(claim.recipient, recipient.pubkey());
+    assert_eq!(claim.amount, 90_000);
+    assert_eq!(claim.messages_received, 1);
+    assert_eq!(claim.total_claimed, 0);
+}
+
+#[tokio::test]
+async fn test_treasury_receives_owner_share_and_pays_out_on_claim() {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+    let (treasury_pda, _) = mailer::pda::treasury(&program_id());
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    let owner_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let treasury_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &treasury_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let set_treasury = |token_account: Pubkey| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SetTreasury {
+                token_account: Some(token_account),
+            },
+            vec![
+                AccountMeta::new_readonly(payer.pubkey(), true),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new_readonly(token_account, false),
+            ],
+        )
+    };
+
+    // A token account the treasury PDA does not own is refused
+    let mut transaction =
+        Transaction::new_with_payer(&[set_treasury(owner_usdc)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::InvalidTreasury as u32)
+        )
+    );
+
+    let mut transaction =
+        Transaction::new_with_payer(&[set_treasury(treasury_usdc)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let recipient = Keypair::new();
+    let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
+    let send = |priority: bool, subject: &str, with_treasury: bool| {
+        let mut accounts = vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(recipient_claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ];
+        if with_treasury {
+            accounts.push(AccountMeta::new(treasury_usdc, false));
+        }
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient.pubkey(),
+                subject: subject.to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: priority,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            accounts,
+        )
+    };
+
+    // Sends must pass the registered treasury
+    let mut transaction =
+        Transaction::new_with_payer(&[send(false, "Missing", false)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::InvalidTreasury as u32)
+        )
+    );
+
+    // The standard 10% and the owner cut of a priority fee both land in the treasury
+    let mut transaction = Transaction::new_with_payer(
+        &[send(false, "Standard", true), send(true, "Priority", true)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let balance = |account: Option<solana_sdk::account::Account>| {
+        TokenAccount::unpack(&account.unwrap().data).unwrap().amount
+    };
+    assert_eq!(
+        balance(banks_client.get_account(treasury_usdc).await.unwrap()),
+        20_000
+    );
+    assert_eq!(
+        balance(banks_client.get_account(mailer_usdc).await.unwrap()),
+        90_000
+    );
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.treasury, Some(treasury_usdc));
+    assert_eq!(mailer_state.owner_claimable, 0);
+
+    // The owner claims the treasury balance, leaving the recipient's escrow in the vault
+    let claim_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::ClaimOwnerShare,
+        vec![
+            AccountMeta::new_readonly(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(owner_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(treasury_usdc, false),
+            AccountMeta::new_readonly(treasury_pda, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[claim_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    assert_eq!(
+        balance(banks_client.get_account(owner_usdc).await.unwrap()),
+        20_000
+    );
+    assert_eq!(
+        balance(banks_client.get_account(treasury_usdc).await.unwrap()),
+        0
+    );
+    assert_eq!(
+        balance(banks_client.get_account(mailer_usdc).await.unwrap()),
+        90_000
+    );
+}
+
+#[tokio::test]
+async fn test_verify_solvency_reports_vault_delta_over_claim_batch() {
+    use mailer::SolvencyReport;
+    use solana_sdk::{
+        account::AccountSharedData, instruction::InstructionError, transaction::TransactionError,
+    };
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+    let recent_blockhash = context.last_blockhash;
+
+    let usdc_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    // Two priority sends leave 90,000 in each recipient claim and 20,000 for the owner
+    let mut claims = Vec::new();
+    for _ in 0..2 {
+        let recipient = Keypair::new();
+        let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
+        let send_instruction = Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient.pubkey(),
+                subject: "Priority".to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: true,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: true,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(recipient_claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            ],
+        );
+        let mut transaction =
+            Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer], recent_blockhash);
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap();
+        claims.push(recipient_claim_pda);
+    }
+    claims.sort();
+
+    let verify = |claims: &[Pubkey]| {
+        let mut accounts = vec![
+            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new_readonly(mailer_usdc, false),
+        ];
+        accounts.extend(
+            claims
+                .iter()
+                .map(|claim| AccountMeta::new_readonly(*claim, false)),
+        );
+        Instruction::new_with_borsh(program_id(), &MailerInstruction::VerifySolvency, accounts)
+    };
+
+    // The vault exactly covers the owner share and both claims
+    let mut transaction = Transaction::new_with_payer(&[verify(&claims)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let simulation = context
+        .banks_client
+        .simulate_transaction(transaction)
+        .await
+        .unwrap();
+    let return_data = simulation.simulation_details.unwrap().return_data.unwrap();
+    let report: SolvencyReport = BorshDeserialize::deserialize(&mut &return_data.data[..]).unwrap();
+    assert_eq!(
+        report,
+        SolvencyReport {
+            vault_balance: 200_000,
+            owner_claimable: 20_000,
+            recipient_liabilities: 180_000,
+            credit_liabilities: 0,
+            claims_checked: 2,
+            claims_total: 180_000,
+            delta: 0,
+        }
+    );
+
+    // Claims out of order could be counted twice
+    let unsorted = [claims[1], claims[0]];
+    let mut transaction = Transaction::new_with_payer(&[verify(&unsorted)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::UnsortedClaimBatch as u32)
+        )
+    );
+
+    // A vault drained below its liabilities fails the check
+    let mut vault = context
+        .banks_client
+        .get_account(mailer_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    let mut vault_state = TokenAccount::unpack(&vault.data).unwrap();
+    vault_state.amount = 150_000;
+    TokenAccount::pack(vault_state, &mut vault.data).unwrap();
+    context.set_account(&mailer_usdc, &AccountSharedData::from(vault));
+
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(&[verify(&claims)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::TemporarilyInsolvent as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_claim_from_merkle_credits_recipient_claims_once() {
+    use mailer::{merkle, ClaimDistribution, RecipientClaim};
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+    let recent_blockhash = context.last_blockhash;
+
+    let usdc_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let owner_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &owner_usdc,
+        1_000_000,
+    )
+    .await;
+
+    // Three recipients share a 600,000 airdrop
+    let recipients = [Keypair::new(), Keypair::new(), Keypair::new()];
+    let amounts = [100_000u64, 200_000, 300_000];
+    let leaves: Vec<[u8; 32]> = recipients
+        .iter()
+        .zip(amounts)
+        .enumerate()
+        .map(|(index, (recipient, amount))| merkle::leaf(index as u64, &recipient.pubkey(), amount))
+        .collect();
+    let merkle_root = merkle::root(&leaves);
+
+    let (distribution_pda, _) = mailer::pda::claim_distribution(&program_id(), 1);
+    let fund_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetClaimMerkleRoot {
+            distribution_id: 1,
+            merkle_root,
+            leaf_count: 3,
+            total: 600_000,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(distribution_pda, false),
+            AccountMeta::new(owner_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[fund_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let vault = context
+        .banks_client
+        .get_account(mailer_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(TokenAccount::unpack(&vault.data).unwrap().amount, 600_000);
+
+    let claim_from_merkle = |recipient: &Pubkey, index: u64, amount: u64, proof: Vec<[u8; 32]>| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::ClaimFromMerkle {
+                distribution_id: 1,
+                index,
+                amount,
+                proof,
+            },
+            vec![
+                AccountMeta::new(*recipient, true),
+                AccountMeta::new(distribution_pda, false),
+                AccountMeta::new(get_claim_pda(recipient).0, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    };
+
+    let recipient = &recipients[1];
+    let fund_recipient =
+        solana_sdk::system_instruction::transfer(&payer.pubkey(), &recipient.pubkey(), 10_000_000);
+    let mut transaction = Transaction::new_with_payer(&[fund_recipient], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // A proof for a different amount does not match the root
+    let mut transaction = Transaction::new_with_payer(
+        &[claim_from_merkle(
+            &recipient.pubkey(),
+            1,
+            300_000,
+            merkle::proof(&leaves, 1),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, recipient], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::InvalidMerkleProof as u32)
+        )
+    );
+
+    // The recipient's leaf is credited to a new claim
+    let mut transaction = Transaction::new_with_payer(
+        &[claim_from_merkle(
+            &recipient.pubkey(),
+            1,
+            200_000,
+            merkle::proof(&leaves, 1),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, recipient], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let claim_account = context
+        .banks_client
+        .get_account(get_claim_pda(&recipient.pubkey()).0)
+        .await
+        .unwrap()
+        .unwrap();
+    let claim = RecipientClaim::from_account_data(&claim_account.data[8..]).unwrap();
+    assert_eq!(claim.recipient, recipient.pubkey());
+    assert_eq!(claim.amount, 200_000);
+
+    let distribution_account = context
+        .banks_client
+        .get_account(distribution_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let distribution: ClaimDistribution =
+        BorshDeserialize::deserialize(&mut &distribution_account.data[8..]).unwrap();
+    assert_eq!(distribution.remaining, 400_000);
+    assert!(distribution.is_claimed(1));
+    assert!(!distribution.is_claimed(0));
+
+    let mailer_account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.recipient_liabilities, 200_000);
+
+    // Each leaf pays out once
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[claim_from_merkle(
+            &recipient.pubkey(),
+            1,
+            200_000,
+            merkle::proof(&leaves, 1),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, recipient], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::DistributionLeafClaimed as u32)
+        )
+    );
+
+    // Another recipient's leaf cannot be claimed with their proof
+    let mut transaction = Transaction::new_with_payer(
+        &[claim_from_merkle(
+            &recipient.pubkey(),
+            2,
+            300_000,
+            merkle::proof(&leaves, 2),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, recipient], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::InvalidMerkleProof as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_priority_send_tip_is_credited_in_full_to_recipient() {
+    use mailer::RecipientClaim;
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+    let recent_blockhash = context.last_blockhash;
+
+    let usdc_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let recipient = Keypair::new();
+    let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
+    let send = |priority: bool, tip: u64| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient.pubkey(),
+                subject: "Urgent".to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: priority,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: true,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(recipient_claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            ],
+        )
+    };
+
+    // Standard sends have no claim to tip into
+    let mut transaction =
+        Transaction::new_with_payer(&[send(false, 50_000)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::TipRequiresPriority as u32)
+        )
+    );
+
+    let mut transaction = Transaction::new_with_payer(&[send(true, 50_000)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert!(result.result.is_ok(), "{:?}", result.result);
+    assert!(matches!(
+        indexer::decode_logs(&result.metadata.unwrap().log_messages).as_slice(),
+        [MailerActivity::Sent(SentMail {
+            revenue_share: true,
+            effective_fee: 100_000,
+            tip: Some(50_000),
+            ..
+        })]
+    ));
+
+    // The tip is charged on top of the fee and skips the owner's 10%
+    let sender_account = context
+        .banks_client
+        .get_account(sender_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&sender_account.data).unwrap().amount,
+        1_000_000 - 100_000 - 50_000
+    );
+    let claim_account = context
+        .banks_client
+        .get_account(recipient_claim_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let claim = RecipientClaim::from_account_data(&claim_account.data[8..]).unwrap();
+    assert_eq!(claim.amount, 90_000 + 50_000);
+
+    let mailer_account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.owner_claimable, 10_000);
+    assert_eq!(mailer_state.recipient_liabilities, 140_000);
+}
+
+#[tokio::test]
+async fn test_inbox_policy_min_fee_gates_priority_sends() {
+    use mailer::InboxPolicy;
+    use solana_sdk::{
+        account::{Account, AccountSharedData},
+        instruction::InstructionError,
+        transaction::TransactionError,
+    };
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+    let recent_blockhash = context.last_blockhash;
+
+    let usdc_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let recipient = Keypair::new();
+    let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
+    let (policy_pda, _) = get_policy_pda(&recipient.pubkey());
+    let send = |priority: bool, tip: u64, subject: &str| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient.pubkey(),
+                subject: subject.to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: priority,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: true,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(recipient_claim_pda, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(policy_pda, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            ],
+        )
+    };
+
+    // A full policy written before minimum fees existed sets no minimum
+    let mut data = mailer::discriminator::account("InboxPolicy").to_vec();
+    data.extend(
+        borsh::to_vec(&InboxPolicy {
+            recipient: recipient.pubkey(),
+            mode: mailer::InboxMode::Blocklist,
+            senders: (0..16).map(|_| Pubkey::new_unique()).collect(),
+            penalty_multiplier: 0,
+            bump: get_policy_pda(&recipient.pubkey()).1,
+            min_fee: 0,
+        })
+        .unwrap(),
+    );
+    data.truncate(8 + InboxPolicy::V1_LEN);
+    let rent = context.banks_client.get_rent().await.unwrap();
+    context.set_account(
+        &policy_pda,
+        &AccountSharedData::from(Account {
+            lamports: rent.minimum_balance(data.len()),
+            data,
+            owner: program_id(),
+            executable: false,
+            rent_epoch: 0,
+        }),
+    );
+
+    let mut transaction =
+        Transaction::new_with_payer(&[send(true, 0, "Before")], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // Setting a minimum resizes the old policy, the recipient paying the extra rent
+    let fund_recipient =
+        solana_sdk::system_instruction::transfer(&payer.pubkey(), &recipient.pubkey(), 10_000_000);
+    let set_policy = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetInboxPolicy {
+            mode: mailer::InboxMode::Open,
+            senders: vec![],
+            penalty_multiplier: 0,
+            min_fee: 150_000,
+        },
+        vec![
+            AccountMeta::new(recipient.pubkey(), true),
+            AccountMeta::new(policy_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction =
+        Transaction::new_with_payer(&[fund_recipient, set_policy], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let policy_account = context
+        .banks_client
+        .get_account(policy_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(policy_account.data.len(), 8 + InboxPolicy::LEN);
+    let policy = InboxPolicy::from_account_data(&policy_account.data[8..]).unwrap();
+    assert_eq!(policy.min_fee, 150_000);
+
+    // The 100,000 fee alone falls short of the minimum
+    let mut transaction =
+        Transaction::new_with_payer(&[send(true, 0, "Cheap")], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::RecipientFeeTooLow as u32)
+        )
+    );
+
+    // Leaving the policy out does not skip the minimum
+    let mut unchecked = send(true, 0, "Unchecked");
+    unchecked.accounts.retain(|meta| meta.pubkey != policy_pda);
+    let mut transaction = Transaction::new_with_payer(&[unchecked], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::MissingInboxPolicyAccount as u32)
+        )
+    );
+
+    // A tip makes up the difference
+    let mut transaction =
+        Transaction::new_with_payer(&[send(true, 50_000, "Tipped")], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // Standard sends pay the recipient nothing and are left to the policy's mode
+    let mut transaction =
+        Transaction::new_with_payer(&[send(false, 0, "Standard")], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let sender_account = context
+        .banks_client
+        .get_account(sender_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&sender_account.data).unwrap().amount,
+        1_000_000 - 100_000 - 150_000 - 10_000
+    );
+}
+
+#[tokio::test]
+async fn test_escrowed_send_pays_out_on_accept_or_refunds_after_expiry() {
+    use mailer::{Escrow, RecipientClaim};
+    use solana_sdk::{clock::Clock, instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+    let mut recent_blockhash = context.last_blockhash;
+
+    let usdc_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let recipient = Keypair::new();
+    let fund_recipient =
+        solana_sdk::system_instruction::transfer(&payer.pubkey(), &recipient.pubkey(), 10_000_000);
+    let mut transaction = Transaction::new_with_payer(&[fund_recipient], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let send_escrowed = |nonce: u64| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SendEscrowed {
+                to: recipient.pubkey(),
+                subject: "Intro".to_string(),
+                _body: "Worth your time".to_string(),
+                nonce,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(
+                    mailer::pda::escrow(&program_id(), &payer.pubkey(), nonce).0,
+                    false,
+                ),
+                AccountMeta::new_readonly(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            ],
+        )
+    };
+    let accept_message = |nonce: u64| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::AcceptMessage {
+                sender: payer.pubkey(),
+                nonce,
+            },
+            vec![
+                AccountMeta::new(recipient.pubkey(), true),
+                AccountMeta::new(
+                    mailer::pda::escrow(&program_id(), &payer.pubkey(), nonce).0,
+                    false,
+                ),
+                AccountMeta::new(get_claim_pda(&recipient.pubkey()).0, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), false),
+            ],
+        )
+    };
+    let reclaim_escrow = |nonce: u64| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::ReclaimEscrow { nonce },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(
+                    mailer::pda::escrow(&program_id(), &payer.pubkey(), nonce).0,
+                    false,
+                ),
+                AccountMeta::new_readonly(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+            ],
+        )
+    };
+
+    // Two escrowed sends each hold the full 100,000 priority fee
+    let mut transaction =
+        Transaction::new_with_payer(&[send_escrowed(1), send_escrowed(2)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let escrow_account = context
+        .banks_client
+        .get_account(mailer::pda::escrow(&program_id(), &payer.pubkey(), 1).0)
+        .await
+        .unwrap()
+        .unwrap();
+    let escrow: Escrow = BorshDeserialize::deserialize(&mut &escrow_account.data[8..]).unwrap();
+    assert_eq!(escrow.recipient, recipient.pubkey());
+    assert_eq!(escrow.amount, 100_000);
+    assert_eq!(escrow.expires_at - escrow.created_at, 7 * 24 * 60 * 60);
+
+    let sender_balance = |data: &[u8]| TokenAccount::unpack(data).unwrap().amount;
+    let sender_account = context
+        .banks_client
+        .get_account(sender_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(sender_balance(&sender_account.data), 800_000);
+
+    // Accepting the first message splits its fee 90/10 and closes the escrow
+    let mut transaction = Transaction::new_with_payer(&[accept_message(1)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let claim_account = context
+        .banks_client
+        .get_account(get_claim_pda(&recipient.pubkey()).0)
+        .await
+        .unwrap()
+        .unwrap();
+    let claim = RecipientClaim::from_account_data(&claim_account.data[8..]).unwrap();
+    assert_eq!(claim.amount, 90_000);
+    let mailer_account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.owner_claimable, 10_000);
+    assert!(context
+        .banks_client
+        .get_account(mailer::pda::escrow(&program_id(), &payer.pubkey(), 1).0)
+        .await
+        .unwrap()
+        .is_none());
+
+    // The sender cannot take back the second fee while it can still be accepted
+    let mut transaction = Transaction::new_with_payer(&[reclaim_escrow(2)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::EscrowNotExpired as u32)
+        )
+    );
+
+    let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    clock.unix_timestamp += 7 * 24 * 60 * 60;
+    context.set_sysvar(&clock);
+    recent_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+
+    // Once expired, the message can no longer be accepted and the fee is refunded in full
+    let mut transaction = Transaction::new_with_payer(&[accept_message(2)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &recipient], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::EscrowExpired as u32)
+        )
+    );
+
+    let mut transaction = Transaction::new_with_payer(&[reclaim_escrow(2)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let sender_account = context
+        .banks_client
+        .get_account(sender_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(sender_balance(&sender_account.data), 900_000);
+    let vault = context
+        .banks_client
+        .get_account(mailer_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(sender_balance(&vault.data), 100_000);
+    assert!(context
+        .banks_client
+        .get_account(mailer::pda::escrow(&program_id(), &payer.pubkey(), 2).0)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_send_to_list_charges_per_member_and_logs_each_chunk() {
+    use mailer::{ListChunk, MailingList};
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+    let recent_blockhash = context.last_blockhash;
+
+    let usdc_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let owner_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &owner_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let (list_pda, _) = mailer::pda::mailing_list(&program_id(), &payer.pubkey(), 1);
+    let create_list = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::CreateList { list_id: 1 },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(list_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let add_member = |chunk: u64, member: Pubkey| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::AddListMember {
+                list_id: 1,
+                chunk,
+                member,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(list_pda, false),
+                AccountMeta::new(
+                    mailer::pda::list_chunk(&program_id(), &list_pda, chunk).0,
+                    false,
+                ),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    };
+    let send_to_list = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SendToList {
+            list_id: 1,
+            subject: "Release notes".to_string(),
+            _body: "Version 2 is live".to_string(),
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(list_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(owner_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+
+    // An empty list cannot be sent to
+    let mut transaction =
+        Transaction::new_with_payer(&[create_list, send_to_list.clone()], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(mailer::MailerError::EmptyList as u32)
+        )
+    );
+    let create_list = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::CreateList { list_id: 1 },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(list_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[create_list], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // Three members across two chunks, created in order
+    let members = [
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    ];
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            add_member(0, members[0]),
+            add_member(0, members[1]),
+            add_member(1, members[2]),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let mut transaction = Transaction::new_with_payer(
+        &[add_member(3, Pubkey::new_unique())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::InvalidListChunk as u32)
+        )
+    );
+
+    let mut transaction =
+        Transaction::new_with_payer(&[add_member(0, members[1])], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::ListMemberExists as u32)
+        )
+    );
+
+    // Removing a member shrinks its chunk and the list
+    let remove_member = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::RemoveListMember {
+            list_id: 1,
+            chunk: 0,
+            member: members[0],
+        },
+        vec![
+            AccountMeta::new_readonly(payer.pubkey(), true),
+            AccountMeta::new(list_pda, false),
+            AccountMeta::new(
+                mailer::pda::list_chunk(&program_id(), &list_pda, 0).0,
+                false,
+            ),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[remove_member], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let list_account = context
+        .banks_client
+        .get_account(list_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let list: MailingList = BorshDeserialize::deserialize(&mut &list_account.data[8..]).unwrap();
+    assert_eq!((list.member_count, list.chunk_count), (2, 2));
+    let chunk_account = context
+        .banks_client
+        .get_account(mailer::pda::list_chunk(&program_id(), &list_pda, 0).0)
+        .await
+        .unwrap()
+        .unwrap();
+    let chunk: ListChunk = BorshDeserialize::deserialize(&mut &chunk_account.data[8..]).unwrap();
+    assert_eq!(chunk.members, vec![members[1]]);
+
+    // The broadcast pays 10% of the send fee per member and logs one event per chunk
+    let mut transaction = Transaction::new_with_payer(&[send_to_list], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert!(result.result.is_ok());
+    let logs = result.metadata.unwrap().log_messages;
+    let events: Vec<_> = logs
+        .iter()
+        .filter(|log| log.contains("ListMailSent"))
+        .collect();
+    assert_eq!(events.len(), 2);
+    assert!(events[1].contains("chunk: 1"));
+
+    let mailer_account = context
+        .banks_client
+        .get_account(mailer_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.owner_claimable, 20_000);
+    let owner_account = context
+        .banks_client
+        .get_account(owner_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&owner_account.data).unwrap().amount,
+        980_000
+    );
+}
+
+#[tokio::test]
+async fn test_send_to_domain_routes_to_the_domain_owner() {
+    use mailer::{sns, RecipientClaim};
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let owner = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    // "alice.sol" is registered to `owner`; "mallory.sol" is a look-alike not owned by the
+    // Name Service program
+    for (domain, name_program) in [
+        ("alice", sns::NAME_PROGRAM_ID),
+        ("mallory", system_program::id()),
+    ] {
+        program_test.add_account(
+            sns::domain_key(domain),
+            solana_sdk::account::Account {
+                lamports: 5_000_000,
+                data: sns::encode_name_record(&sns::SOL_TLD, &owner),
+                owner: name_program,
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+    }
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+    let recent_blockhash = context.last_blockhash;
+
+    let usdc_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let send_to_domain = |domain: &str, name_account: Pubkey| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SendToDomain {
+                domain: domain.to_string(),
+                subject: "Hello".to_string(),
+                _body: "World".to_string(),
+                revenue_share_to_receiver: true,
+                strict: true,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(name_account, false),
+                AccountMeta::new(get_claim_pda(&owner).0, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(get_policy_pda(&owner).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            ],
+        )
+    };
+
+    // Name accounts of another domain, or not owned by the Name Service, are rejected
+    for (domain, name_account) in [
+        ("bob.sol", sns::domain_key("alice")),
+        ("mallory", sns::domain_key("mallory")),
+    ] {
+        let mut transaction = Transaction::new_with_payer(
+            &[send_to_domain(domain, name_account)],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer], recent_blockhash);
+        assert_eq!(
+            context
+                .banks_client
+                .process_transaction(transaction)
+                .await
+                .unwrap_err()
+                .unwrap(),
+            TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(mailer::MailerError::InvalidDomain as u32)
+            )
+        );
+    }
+
+    // A priority send to alice.sol credits the domain owner's claim
+    let mut transaction = Transaction::new_with_payer(
+        &[send_to_domain("alice.sol", sns::domain_key("alice"))],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let claim_account = context
+        .banks_client
+        .get_account(get_claim_pda(&owner).0)
+        .await
+        .unwrap()
+        .unwrap();
+    let claim = RecipientClaim::from_account_data(&claim_account.data[8..]).unwrap();
+    assert_eq!(claim.recipient, owner);
+    assert_eq!(claim.amount, 90_000);
+}
+
+#[tokio::test]
+async fn test_send_to_evm_address_routes_to_the_bound_wallet() {
+    use mailer::{evm, EvmMapping, RecipientClaim};
+    use solana_sdk::{
+        instruction::InstructionError, secp256k1_instruction, transaction::TransactionError,
+    };
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+    let recent_blockhash = context.last_blockhash;
+
+    let usdc_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let wallet = Keypair::new();
+    let evm_key = libsecp256k1::SecretKey::parse(&[7; 32]).unwrap();
+    let evm_address = secp256k1_instruction::construct_eth_pubkey(
+        &libsecp256k1::PublicKey::from_secret_key(&evm_key),
+    );
+    let (mapping_pda, _) = mailer::pda::evm_mapping(&program_id(), &evm_address);
+    let fund_wallet =
+        solana_sdk::system_instruction::transfer(&payer.pubkey(), &wallet.pubkey(), 10_000_000);
+    let mut transaction = Transaction::new_with_payer(&[fund_wallet], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let bind = |message: &[u8]| {
+        let proof = secp256k1_instruction::new_secp256k1_instruction(&evm_key, message);
+        let bind = Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::BindEvmAddress { evm_address },
+            vec![
+                AccountMeta::new(wallet.pubkey(), true),
+                AccountMeta::new(mapping_pda, false),
+                AccountMeta::new_readonly(solana_sdk::sysvar::instructions::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        let mut transaction = Transaction::new_with_payer(&[proof, bind], Some(&payer.pubkey()));
+        transaction.sign(&[&payer, &wallet], recent_blockhash);
+        transaction
+    };
+    let send = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SendToEvmAddress {
+            evm_address,
+            subject: "Hello".to_string(),
+            _body: "World".to_string(),
+            revenue_share_to_receiver: true,
+            strict: true,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(mapping_pda, false),
+            AccountMeta::new(get_claim_pda(&wallet.pubkey()).0, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&wallet.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ],
+    );
+
+    // Nothing is routed before the address is bound
+    let mut transaction =
+        Transaction::new_with_payer(std::slice::from_ref(&send), Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::EvmAddressNotBound as u32)
+        )
+    );
+
+    // A valid signature over another wallet's binding message does not bind this wallet
+    let wrong_message = evm::binding_message(&program_id(), &payer.pubkey());
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(bind(&wrong_message))
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(mailer::MailerError::InvalidEvmSignature as u32)
+        )
+    );
+
+    let message = evm::binding_message(&program_id(), &wallet.pubkey());
+    context
+        .banks_client
+        .process_transaction(bind(&message))
+        .await
+        .unwrap();
+    let mapping_account = context
+        .banks_client
+        .get_account(mapping_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let mapping: EvmMapping =
+        BorshDeserialize::deserialize(&mut &mapping_account.data[8..]).unwrap();
+    assert_eq!(mapping.evm_address, evm_address);
+    assert_eq!(mapping.wallet, wallet.pubkey());
+
+    // A priority send to the EVM address credits the bound wallet's claim
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(&[send], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let claim_account = context
+        .banks_client
+        .get_account(get_claim_pda(&wallet.pubkey()).0)
+        .await
+        .unwrap()
+        .unwrap();
+    let claim = RecipientClaim::from_account_data(&claim_account.data[8..]).unwrap();
+    assert_eq!(claim.recipient, wallet.pubkey());
+    assert_eq!(claim.amount, 90_000);
+}
+
+/// Test Wormhole core bridge: checks the message fee was paid into the fee collector, like
+/// the real bridge, and stores each posted payload in its message account
+fn mock_core_bridge(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> solana_program::entrypoint::ProgramResult {
+    use solana_program::{program_error::ProgramError, sysvar::Sysvar};
+
+    let (_nonce, payload, _consistency) =
+        mailer::wormhole::decode_post_message(data).ok_or(ProgramError::InvalidInstructionData)?;
+    let (bridge, message, emitter, sequence, payer, fee_collector, system_program) = (
+        &accounts[0],
+        &accounts[1],
+        &accounts[2],
+        &accounts[3],
+        &accounts[4],
+        &accounts[5],
+        &accounts[7],
+    );
+    if !message.is_signer || !emitter.is_signer || !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let mut bridge_data = bridge.try_borrow_mut_data()?;
+    let last_lamports = u64::from_le_bytes(bridge_data[4..12].try_into().unwrap());
+    let fee = mailer::wormhole::message_fee(&bridge_data).unwrap();
+    if fee_collector.lamports() < last_lamports + fee {
+        return Err(ProgramError::InsufficientFunds);
+    }
+    bridge_data[4..12].copy_from_slice(&fee_collector.lamports().to_le_bytes());
+
+    let mut sequence_data = sequence.try_borrow_mut_data()?;
+    let next = u64::from_le_bytes(sequence_data[..8].try_into().unwrap()) + 1;
+    sequence_data[..8].copy_from_slice(&next.to_le_bytes());
+
+    solana_program::program::invoke(
+        &solana_program::system_instruction::create_account(
+            payer.key,
+            message.key,
+            solana_program::rent::Rent::get()?.minimum_balance(payload.len()),
+            payload.len() as u64,
+            program_id,
+        ),
+        &[payer.clone(), message.clone(), system_program.clone()],
+    )?;
+    message.try_borrow_mut_data()?.copy_from_slice(&payload);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bridged_send_posts_a_wormhole_message() {
+    use mailer::wormhole::{self, MirroredSend};
+    use mailer::WormholeConfig;
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let core_bridge = Pubkey::new_unique();
+    let (emitter, _) = mailer::pda::wormhole_emitter(&program_id());
+    let addresses = wormhole::BridgeAddresses::derive(&core_bridge, &emitter);
+    let mut program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    program_test.add_program("core_bridge", core_bridge, processor!(mock_core_bridge));
+    // Bridge config charging a 100 lamport message fee, collected so far up to 1_000_000
+    let mut bridge_data = vec![0u8; 24];
+    bridge_data[4..12].copy_from_slice(&1_000_000u64.to_le_bytes());
+    bridge_data[16..24].copy_from_slice(&100u64.to_le_bytes());
+    for (address, lamports, data) in [
+        (addresses.bridge, 5_000_000, bridge_data),
+        (addresses.fee_collector, 1_000_000, vec![]),
+        (addresses.sequence, 5_000_000, vec![0u8; 8]),
+    ] {
+        program_test.add_account(
+            address,
+            solana_sdk::account::Account {
+                lamports,
+                data,
+                owner: core_bridge,
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+    }
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+    let recent_blockhash = context.last_blockhash;
+
+    let usdc_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    let recipient = Pubkey::new_unique();
+    let (config_pda, _) = mailer::pda::wormhole_config(&program_id());
+    let (message_pda, _) = mailer::pda::wormhole_message(&program_id(), 0);
+    let bridged_send = |subject: &str| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient,
+                subject: subject.to_string(),
+                _body: "World".to_string(),
+                revenue_share_to_receiver: true,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: true,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: true,
+                idempotency_nonce: None,
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(get_claim_pda(&recipient).0, false),
+                AccountMeta::new(mailer_pda, false),
+                AccountMeta::new(sender_usdc, false),
+                AccountMeta::new(mailer_usdc, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(config_pda, false),
+                AccountMeta::new_readonly(core_bridge, false),
+                AccountMeta::new(addresses.bridge, false),
+                AccountMeta::new(message_pda, false),
+                AccountMeta::new_readonly(emitter, false),
+                AccountMeta::new(addresses.sequence, false),
+                AccountMeta::new(addresses.fee_collector, false),
+                AccountMeta::new_readonly(solana_sdk::sysvar::clock::id(), false),
+                AccountMeta::new_readonly(solana_sdk::sysvar::rent::id(), false),
+                AccountMeta::new_readonly(get_policy_pda(&recipient).0, false),
+                AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            ],
+        )
+    };
+
+    // Nothing is bridged before the owner sets the core bridge
+    let mut transaction =
+        Transaction::new_with_payer(&[bridged_send("Early")], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::WormholeNotConfigured as u32)
+        )
+    );
+
+    let set_bridge = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetWormholeBridge {
+            core_bridge: Some(core_bridge),
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new(config_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[set_bridge], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let mut transaction =
+        Transaction::new_with_payer(&[bridged_send("Hello")], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // The message account holds the mirrored send, and the sender paid the message fee
+    let message_account = context
+        .banks_client
+        .get_account(message_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(message_account.owner, core_bridge);
+    assert_eq!(
+        wormhole::decode_send_payload(&message_account.data),
+        Some(MirroredSend {
+            sender: payer.pubkey(),
+            recipient,
+            priority: true,
+            subject: "Hello".to_string(),
+        })
+    );
+    let fee_collector = context
+        .banks_client
+        .get_account(addresses.fee_collector)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(fee_collector.lamports, 1_000_100);
+
+    let config_account = context
+        .banks_client
+        .get_account(config_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let config: WormholeConfig =
+        BorshDeserialize::deserialize(&mut &config_account.data[8..]).unwrap();
+    assert_eq!(config.core_bridge, core_bridge);
+    assert_eq!(config.message_count, 1);
+
+    // The send itself went through as usual
+    let claim_account = context
+        .banks_client
+        .get_account(get_claim_pda(&recipient).0)
+        .await
+        .unwrap()
+        .unwrap();
+    let claim = RecipientClaim::from_account_data(&claim_account.data[8..]).unwrap();
+    assert_eq!(claim.amount, 90_000);
+}
+
+#[test]
+fn test_v2_instruction_data_zero_fills_missing_fields_and_skips_extensions() {
+    use mailer::instruction_versions::{self, InstructionFormat, FORMAT_PREFIX, FORMAT_V2};
+
+    let send = MailerInstruction::Send {
+        to: Pubkey::new_unique(),
+        subject: "Hello".to_string(),
+        _body: "World".to_string(),
+        revenue_share_to_receiver: true,
+        resolve_sender_to_name: false,
+        request_read_receipt: false,
+        receipt_nonce: None,
+        memo: None,
+        strict: true,
+        thread_id: None,
+        in_reply_to: None,
+        allow_downgrade: false,
+        tip: 25_000,
+        bridge: true,
+        idempotency_nonce: None,
+    };
+    let legacy = borsh::to_vec(&send).unwrap();
+    let v2 = instruction_versions::encode_v2(&send);
+    assert_eq!(v2[0], FORMAT_V2);
+    assert_eq!(v2[1..], legacy[..]);
+
+    // A client predating `tip`, `bridge` and `idempotency_nonce` leaves them out; they
+    // decode as zero
+    let decoded = instruction_versions::decode(&v2[..v2.len() - 10]).unwrap();
+    assert_eq!(decoded.format, InstructionFormat::V2);
+    assert_eq!(decoded.tag, legacy[0]);
+    match decoded.instruction {
+        MailerInstruction::Send {
+            subject,
+            strict,
+            tip,
+            bridge,
+            idempotency_nonce,
+            ..
+        } => {
+            assert_eq!(subject, "Hello");
+            assert!(strict);
+            assert_eq!(tip, 0);
+            assert!(!bridge);
+            assert_eq!(idempotency_nonce, None);
+        }
+        other => panic!("unexpected instruction {:?}", other),
+    }
+
+    // Extension bytes from a newer client are ignored in v2 but rejected in legacy data
+    let mut extended = v2.clone();
+    extended.extend_from_slice(&[7; 16]);
+    match instruction_versions::decode(&extended).unwrap().instruction {
+        MailerInstruction::Send { tip, bridge, .. } => assert_eq!((tip, bridge), (25_000, true)),
+        other => panic!("unexpected instruction {:?}", other),
+    }
+    assert!(instruction_versions::decode(&extended[1..]).is_err());
+    assert_eq!(
+        instruction_versions::decode(&legacy).unwrap().format,
+        InstructionFormat::Legacy
+    );
+
+    // Unknown formats are refused, and no variant index reaches the format bytes
+    let mut v3 = v2.clone();
+    v3[0] = FORMAT_PREFIX | 3;
+    assert_eq!(
+        instruction_versions::decode(&v3).unwrap_err(),
+        mailer::MailerError::UnsupportedInstructionFormat.into()
+    );
+    let last_variant = borsh::to_vec(&MailerInstruction::DevFaucet { amount: 1 }).unwrap();
+    assert!(last_variant[0] < FORMAT_PREFIX);
+}
+
+#[tokio::test]
+async fn test_program_accepts_v2_instruction_data() {
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: mailer::instruction_versions::encode_v2(&MailerInstruction::Initialize { usdc_mint }),
+    };
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    assert_eq!(mailer_state.usdc_mint, usdc_mint);
+}
+
+/// Program reading every Mailer view through the CPI helpers and returning them together;
+/// accounts are [mailer program, mailer state, claim PDA, delegation PDA], data is the
+/// claim's account followed by the delegator
+fn view_caller(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> solana_program::entrypoint::ProgramResult {
+    let account = Pubkey::try_from(&data[..32]).unwrap();
+    let delegator = Pubkey::try_from(&data[32..64]).unwrap();
+    let fees = cpi::get_fee(&accounts[0], &accounts[1])?;
+    let claimable = cpi::get_claimable(&accounts[0], &accounts[1], &accounts[2], account)?;
+    let delegation = cpi::get_delegation(&accounts[0], &accounts[3], delegator)?;
+    solana_program::program::set_return_data(
+        &borsh::to_vec(&(fees, claimable, delegation)).unwrap(),
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_view_instructions_through_cpi() {
+    use mailer::{ClaimableView, DelegationView, FeeView};
+
+    let caller_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    program_test.add_program("view_caller", caller_id, processor!(view_caller));
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        100_000_000,
+    )
+    .await;
+
+    let recipient = Keypair::new();
+    let (recipient_claim_pda, _) = get_claim_pda(&recipient.pubkey());
+    let (delegation_pda, _) = get_delegation_pda(&payer.pubkey());
+    let mut caller_data = recipient.pubkey().to_bytes().to_vec();
+    caller_data.extend_from_slice(payer.pubkey().as_ref());
+    let read_views = Instruction {
+        program_id: caller_id,
+        accounts: vec![
+            AccountMeta::new_readonly(program_id(), false),
+            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new_readonly(recipient_claim_pda, false),
+            AccountMeta::new_readonly(delegation_pda, false),
+        ],
+        data: caller_data,
+    };
+
+    // Before any claim or delegation exists the claim and delegation views are empty
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let mut transaction =
+        Transaction::new_with_payer(std::slice::from_ref(&read_views), Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let return_data = banks_client
+        .simulate_transaction(transaction)
+        .await
+        .unwrap()
+        .simulation_details
+        .unwrap()
+        .return_data
+        .unwrap();
+    assert_eq!(return_data.program_id, caller_id);
+    let (fees, claimable, delegation): (FeeView, ClaimableView, DelegationView) =
+        mailer::decode_view(&return_data.data).unwrap();
+    assert_eq!(
+        fees,
+        FeeView {
+            priority_fee: 100_000,
+            standard_fee: 10_000,
+            delegation_fee: 10_000_000,
+            fee_paused: false,
+        }
+    );
+    assert_eq!(claimable, ClaimableView::default());
+    assert_eq!(delegation, DelegationView::default());
+
+    let send_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Send {
+            to: recipient.pubkey(),
+            subject: "Priority".to_string(),
+            _body: "Test".to_string(),
+            revenue_share_to_receiver: true,
+            resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(recipient_claim_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient.pubkey()).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ],
+    );
+    let delegate = Pubkey::new_unique();
+    let delegate_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::DelegateTo {
+            delegate: Some(delegate),
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(delegation_pda, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(get_delegate_index_pda(&delegate).0, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(
+        &[send_instruction, delegate_instruction],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let claim_account = banks_client
+        .get_account(recipient_claim_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let recipient_claim: RecipientClaim =
+        BorshDeserialize::deserialize(&mut &claim_account.data[8..]).unwrap();
+    let mailer_account = banks_client.get_account(mailer_pda).await.unwrap().unwrap();
+    let mailer_state: MailerState =
+        BorshDeserialize::deserialize(&mut &mailer_account.data[8..]).unwrap();
+    let expires_at = mailer_state.claim_expiry(&recipient_claim, recipient_claim.timestamp);
+
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(&[read_views], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let return_data = banks_client
+        .simulate_transaction(transaction)
+        .await
+        .unwrap()
+        .simulation_details
+        .unwrap()
+        .return_data
+        .unwrap();
+    let (_, claimable, delegation): (FeeView, ClaimableView, DelegationView) =
+        mailer::decode_view(&return_data.data).unwrap();
+    assert_eq!(
+        claimable,
+        ClaimableView {
+            amount: 90_000,
+            expires_at,
+            expired: false,
+        }
+    );
+    assert_eq!(
+        delegation,
+        DelegationView {
+            delegate: Some(delegate),
+            escrowed_fee: 0,
+            refund_deadline: 0,
+        }
+    );
+
+    // A claim PDA of another account is rejected
+    let get_claimable = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::GetClaimable {
+            account: Pubkey::new_unique(),
+            reserved: Default::default(),
+        },
+        vec![
+            AccountMeta::new_readonly(mailer_pda, false),
+            AccountMeta::new_readonly(recipient_claim_pda, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[get_claimable], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = banks_client
+        .simulate_transaction(transaction)
+        .await
+        .unwrap()
+        .result
+        .unwrap();
+    assert_eq!(
+        result,
+        Err(solana_sdk::transaction::TransactionError::InstructionError(
+            0,
+            solana_sdk::instruction::InstructionError::Custom(
+                mailer::MailerError::InvalidPDA as u32
+            ),
+        ))
+    );
+}
+
+#[tokio::test]
+async fn test_largest_send_fee_does_not_overflow_standard_fee() {
+    use mailer::FeeView;
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let usdc_mint = create_usdc_mint(&mut banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let set_fee_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SetFee {
+            new_fee: u64::MAX,
+            expected_current_value: None,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(
+        &[init_instruction, set_fee_instruction],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let get_fee = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::GetFee {
+            reserved: Default::default(),
+        },
+        vec![AccountMeta::new_readonly(mailer_pda, false)],
+    );
+    let mut transaction = Transaction::new_with_payer(&[get_fee], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let return_data = banks_client
+        .simulate_transaction(transaction)
+        .await
+        .unwrap()
+        .simulation_details
+        .unwrap()
+        .return_data
+        .unwrap();
+    let fees: FeeView = mailer::decode_view(&return_data.data).unwrap();
+    assert_eq!(fees.priority_fee, u64::MAX);
+    assert_eq!(fees.standard_fee, u64::MAX / 10);
+
+    // A standard send at the largest fee is dropped for the unpaid fee instead of overflowing
+    let sender_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    let recipient = Pubkey::new_unique();
+    let send_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Send {
+            to: recipient,
+            subject: "Standard".to_string(),
+            _body: "Test".to_string(),
+            revenue_share_to_receiver: false,
+            resolve_sender_to_name: false,
+            request_read_receipt: false,
+            receipt_nonce: None,
+            memo: None,
+            strict: false,
+            thread_id: None,
+            in_reply_to: None,
+            allow_downgrade: false,
+            tip: 0,
+            bridge: false,
+            idempotency_nonce: None,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(get_claim_pda(&recipient).0, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[send_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert!(result.result.is_ok(), "{:?}", result.result);
+    assert!(result
+        .metadata
+        .unwrap()
+        .log_messages
+        .iter()
+        .any(|log| log.contains("fee paid: false")));
+}
+
+/// Program sending through the Mailer with the sender's MessageNonce PDA and returning the
+/// message id it read back; accounts are the Send accounts preceded by the mailer program and
+/// followed by the nonce and inbox policy PDAs, data is the recipient
+fn send_caller(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> solana_program::entrypoint::ProgramResult {
+    cpi::accounts::Send {
+        sender: accounts[1].clone(),
+        recipient_claim: accounts[2].clone(),
+        mailer_account: accounts[3].clone(),
+        sender_usdc: accounts[4].clone(),
+        mailer_usdc: accounts[5].clone(),
+        token_program: accounts[6].clone(),
+        system_program: accounts[7].clone(),
+    }
+    .invoke_with_remaining(
+        &accounts[0],
+        // The sender pays the claim's rent, ahead of the nonce and inbox policy PDAs
+        &[
+            accounts[1].clone(),
+            accounts[8].clone(),
+            accounts[9].clone(),
+        ],
+        &[],
+        Pubkey::try_from(&data[..32]).unwrap(),
+        "Hello".to_string(),
+        "Body".to_string(),
+        true,
+        false,
+        false,
+        None,
+        None,
+        false,
+        None,
+        None,
+        false,
+        0,
+        false,
+        None,
+    )?;
+    let message_id = cpi::sent_message_id(&accounts[0])?;
+    solana_program::program::set_return_data(&message_id);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sends_return_message_ids() {
+    use mailer::{hash_message_body, MessageNonce, SendResult};
+    use solana_sdk::clock::Clock;
+
+    let caller_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    program_test.add_program("send_caller", caller_id, processor!(send_caller));
+    let mut context = program_test.start_with_context().await;
+    let recent_blockhash = context.last_blockhash;
+    let payer = context.payer.insecure_clone();
+
+    let usdc_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        1_000_000,
+    )
+    .await;
+
+    // Pin the slot so message ids can be derived independently
+    let slot = 5_151;
+    let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    clock.slot = slot;
+    context.set_sysvar(&clock);
+    let recent_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+
+    // A program sending by CPI reads the id back and matches the logged one
+    let recipient = Pubkey::new_unique();
+    let (nonce_pda, _) = mailer::pda::message_nonce(&program_id(), &payer.pubkey());
+    let send_through_caller = Instruction {
+        program_id: caller_id,
+        accounts: vec![
+            AccountMeta::new_readonly(program_id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(get_claim_pda(&recipient).0, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(nonce_pda, false),
+            AccountMeta::new_readonly(get_policy_pda(&recipient).0, false),
+        ],
+        data: recipient.to_bytes().to_vec(),
+    };
+    let mut transaction =
+        Transaction::new_with_payer(&[send_through_caller], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert!(result.result.is_ok(), "{:?}", result.result);
+    let metadata = result.metadata.unwrap();
+    let return_data = metadata.return_data.unwrap();
+    assert_eq!(return_data.program_id, caller_id);
+    let message_id: [u8; 32] = mailer::decode_view(&return_data.data).unwrap();
+    assert_eq!(
+        message_id,
+        mailer::derive_message_id(
+            &payer.pubkey(),
+            recipient.as_ref(),
+            &hash_message_body("Hello", "Body"),
+            slot,
+            1
+        )
+    );
+    match indexer::decode_logs(&metadata.log_messages).as_slice() {
+        [MailerActivity::Sent(mail)] => assert_eq!(mail.message_id, Some(message_id)),
+        activity => panic!("unexpected activity: {:?}", activity),
+    }
+
+    let nonce_account = context
+        .banks_client
+        .get_account(nonce_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let message_nonce: MessageNonce =
+        BorshDeserialize::deserialize(&mut &nonce_account.data[8..]).unwrap();
+    assert_eq!(
+        (message_nonce.sender, message_nonce.nonce),
+        (payer.pubkey(), 1)
+    );
+
+    // Email sends return their id too, derived from the logged address and the sender's next
+    // nonce
+    let send_to_email = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::SendToEmail {
+            to_email: "User@Example.com".to_string(),
+            subject: "Hello".to_string(),
+            _body: "Body".to_string(),
+            strict: false,
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[send_to_email], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let return_data = context
+        .banks_client
+        .simulate_transaction(transaction)
+        .await
+        .unwrap()
+        .simulation_details
+        .unwrap()
+        .return_data
+        .unwrap();
+    assert_eq!(return_data.program_id, program_id());
+    let result: SendResult = mailer::decode_view(&return_data.data).unwrap();
+    assert_eq!(
+        result.message_id,
+        mailer::derive_message_id(
+            &payer.pubkey(),
+            b"user@example.com",
+            &hash_message_body("Hello", "Body"),
+            slot,
+            2
+        )
+    );
+    assert!(result.compute_report().remaining_units <= 1_400_000);
+}
+
+#[tokio::test]
+async fn test_idempotent_sends_skip_duplicates() {
+    use mailer::SendNonce;
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let recent_blockhash = context.last_blockhash;
+    let payer = context.payer.insecure_clone();
+
+    let usdc_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+
+    let recipient = Pubkey::new_unique();
+    let (send_nonce_pda, _) = mailer::pda::send_nonce(&program_id(), &payer.pubkey());
+    let send_instruction = |idempotency_nonce: u64, with_nonce_account: bool| {
+        let mut accounts = vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(get_claim_pda(&recipient).0, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ];
+        if with_nonce_account {
+            accounts.push(AccountMeta::new(send_nonce_pda, false));
+        }
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient,
+                subject: "Invoice".to_string(),
+                _body: "Due Friday".to_string(),
+                revenue_share_to_receiver: true,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: Some(idempotency_nonce),
+            },
+            accounts,
+        )
+    };
+
+    // A send dropped for an unpaid fee leaves its nonce free
+    let mut transaction =
+        Transaction::new_with_payer(&[send_instruction(9, true)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert!(result.result.is_ok(), "{:?}", result.result);
+    assert!(result
+        .metadata
+        .unwrap()
+        .log_messages
+        .iter()
+        .any(|log| log.contains("fee paid: false")));
+    assert!(context
+        .banks_client
+        .get_account(send_nonce_pda)
+        .await
+        .unwrap()
+        .is_none());
+
+    mint_to(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
+
+    // The retry is charged and records its nonce
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut transaction =
+        Transaction::new_with_payer(&[send_instruction(9, true)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+    let account = context
+        .banks_client
+        .get_account(sender_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&account.data).unwrap().amount,
+        10_000_000 - 100_000
+    );
+
+    // A retry under a new blockhash lands as a no-op
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut transaction =
+        Transaction::new_with_payer(&[send_instruction(9, true)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    let result = context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert!(result.result.is_ok(), "{:?}", result.result);
+    let logs = result.metadata.unwrap().log_messages;
+    assert!(logs.iter().any(|log| log.contains(&format!(
+        "Duplicate send nonce 9 from {}, skipped",
+        payer.pubkey()
+    ))));
+    assert!(indexer::decode_logs(&logs).is_empty());
+    let account = context
+        .banks_client
+        .get_account(sender_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&account.data).unwrap().amount,
+        10_000_000 - 100_000
+    );
+
+    // Idempotent sends need the ring buffer
+    let mut transaction =
+        Transaction::new_with_payer(&[send_instruction(10, false)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(mailer::MailerError::MissingSendNonceAccount as u32)
+        )
+    );
+
+    // Sixteen newer nonces push the first one out of the ring buffer, so it sends again
+    for nonce in 100..116 {
+        let mut transaction =
+            Transaction::new_with_payer(&[send_instruction(nonce, true)], Some(&payer.pubkey()));
+        transaction.sign(&[&payer], recent_blockhash);
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap();
+    }
+    let account = context
+        .banks_client
+        .get_account(send_nonce_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let send_nonce: SendNonce = BorshDeserialize::deserialize(&mut &account.data[8..]).unwrap();
+    assert_eq!(send_nonce.total, 17);
+    assert_eq!(send_nonce.nonces.len(), 16);
+    assert!(!send_nonce.nonces.contains(&9));
+
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut transaction =
+        Transaction::new_with_payer(&[send_instruction(9, true)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+    let account = context
+        .banks_client
+        .get_account(sender_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&account.data).unwrap().amount,
+        10_000_000 - 18 * 100_000
+    );
+}
+
+#[tokio::test]
+async fn test_volume_discount_tiers() {
+    use mailer::{SenderStats, VolumeTier};
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    let program_test = ProgramTest::new(
+        "mailer",
+        program_id(),
+        processor!(mailer::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let recent_blockhash = context.last_blockhash;
+    let payer = context.payer.insecure_clone();
+
+    let usdc_mint = create_usdc_mint(&mut context.banks_client, &payer, recent_blockhash).await;
+    let (mailer_pda, _) = get_mailer_pda();
+
+    let init_instruction = Instruction::new_with_borsh(
+        program_id(),
+        &MailerInstruction::Initialize { usdc_mint },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let mut transaction = Transaction::new_with_payer(&[init_instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let (schedule_pda, _) = mailer::pda::discount_schedule(&program_id());
+    let set_schedule = |tiers: Vec<(u64, u8)>| {
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::SetDiscountSchedule {
+                tiers: tiers
+                    .into_iter()
+                    .map(|(min_fees_paid, discount)| VolumeTier {
+                        min_fees_paid,
+                        discount,
+                    })
+                    .collect(),
+                reserved: Default::default(),
+            },
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(mailer_pda, false),
+                AccountMeta::new(schedule_pda, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    };
+
+    // Tiers must ascend in volume and not lower the discount
+    for tiers in [
+        vec![(300_000, 10), (200_000, 50)],
+        vec![(200_000, 50), (300_000, 10)],
+        vec![(200_000, 101)],
+    ] {
+        let mut transaction =
+            Transaction::new_with_payer(&[set_schedule(tiers)], Some(&payer.pubkey()));
+        transaction.sign(&[&payer], recent_blockhash);
+        assert_eq!(
+            context
+                .banks_client
+                .process_transaction(transaction)
+                .await
+                .unwrap_err()
+                .unwrap(),
+            TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(mailer::MailerError::InvalidDiscountSchedule as u32)
+            )
+        );
+    }
+
+    let mut transaction = Transaction::new_with_payer(
+        &[set_schedule(vec![(20_000, 10), (30_000, 50)])],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let sender_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &payer.pubkey(),
+    )
+    .await;
+    let mailer_usdc = create_token_account(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &mailer_pda,
+    )
+    .await;
+    mint_to(
+        &mut context.banks_client,
+        &payer,
+        recent_blockhash,
+        &usdc_mint,
+        &sender_usdc,
+        10_000_000,
+    )
+    .await;
+
+    let recipient = Pubkey::new_unique();
+    let (stats_pda, _) = mailer::pda::sender_stats(&program_id(), &payer.pubkey());
+    let send_instruction = |subject: &str, with_volume: bool| {
+        let mut accounts = vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(get_claim_pda(&recipient).0, false),
+            AccountMeta::new(mailer_pda, false),
+            AccountMeta::new(sender_usdc, false),
+            AccountMeta::new(mailer_usdc, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(get_policy_pda(&recipient).0, false),
+            AccountMeta::new(get_message_nonce_pda(&payer.pubkey()).0, false),
+        ];
+        if with_volume {
+            accounts.push(AccountMeta::new_readonly(schedule_pda, false));
+            accounts.push(AccountMeta::new(stats_pda, false));
+        }
+        Instruction::new_with_borsh(
+            program_id(),
+            &MailerInstruction::Send {
+                to: recipient,
+                subject: subject.to_string(),
+                _body: "Body".to_string(),
+                revenue_share_to_receiver: true,
+                resolve_sender_to_name: false,
+                request_read_receipt: false,
+                receipt_nonce: None,
+                memo: None,
+                strict: false,
+                thread_id: None,
+                in_reply_to: None,
+                allow_downgrade: false,
+                tip: 0,
+                bridge: false,
+                idempotency_nonce: None,
+            },
+            accounts,
+        )
+    };
+
+    // Each send pays the discount of the tier its earlier fees reached, counting only the
+    // owner's 10% of each priority fee
+    let mut balance = 10_000_000;
+    for (i, fee) in [100_000, 100_000, 90_000, 90_000, 50_000]
+        .into_iter()
+        .enumerate()
+    {
+        let mut transaction = Transaction::new_with_payer(
+            &[send_instruction(&format!("Volume {}", i), true)],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer], recent_blockhash);
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap();
+        balance -= fee;
+        let account = context
+            .banks_client
+            .get_account(sender_usdc)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            TokenAccount::unpack(&account.data).unwrap().amount,
+            balance,
+            "send {}",
+            i
+        );
+    }
+    let account = context
+        .banks_client
+        .get_account(stats_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let stats: SenderStats = BorshDeserialize::deserialize(&mut &account.data[8..]).unwrap();
+    assert_eq!(stats.sender, payer.pubkey());
+    assert_eq!(stats.fees_paid, 43_000);
+
+    // Sends without the accounts pay the regular fee and leave the volume alone
+    let mut transaction =
+        Transaction::new_with_payer(&[send_instruction("Plain", false)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+    let account = context
+        .banks_client
+        .get_account(sender_usdc)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&account.data).unwrap().amount,
+        balance - 100_000
+    );
+    let account = context
+        .banks_client
+        .get_account(stats_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let stats: SenderStats = BorshDeserialize::deserialize(&mut &account.data[8..]).unwrap();
+    assert_eq!(stats.fees_paid, 43_000);
+}
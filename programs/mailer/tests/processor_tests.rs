@@ -0,0 +1,977 @@
+//! Fast processor tests without a BanksClient
+//!
+//! Every processor reads its accounts through `AccountInfo`, which these tests back with
+//! in-memory [`FakeAccount`]s, and its sysvars through syscalls, which [`FakeSysvars`]
+//! answers with a fixed clock and the default rent. Each test drives `process_instruction`
+//! directly and asserts the exact error of one guard, so a failure names the check that
+//! broke. Paths that reach a CPI (token transfers, account creation) stay covered by the
+//! BanksClient suite in `integration_tests.rs`.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use mailer::{
+    discriminator, pda, ClaimCreationPolicy, ConversionVault, FeeMint, MailerError,
+    MailerInstruction, MailerState, PayloadLimits, RecipientClaim,
+};
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::{ProgramResult, SUCCESS},
+    instruction::Instruction,
+    program_error::ProgramError,
+    program_option::COption,
+    program_pack::Pack,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_program,
+};
+use spl_token::state::{Account as TokenAccount, AccountState};
+use std::str::FromStr;
+use std::sync::Once;
+
+const PROGRAM_ID_STR: &str = "9FLkBDGpZBcR8LMsQ7MwwV6X9P4TDFgN3DeRh5qYyHJF";
+
+/// Unix time reported by the fake clock
+const NOW: i64 = 1_700_000_000;
+
+/// Claim period of recipient shares (60 days)
+const CLAIM_PERIOD: i64 = 60 * 24 * 60 * 60;
+
+/// Error returned by every CPI attempted under the fake syscalls
+const CPI_UNAVAILABLE: u32 = u32::MAX;
+
+/// Longest memo accepted with a send
+const MAX_MEMO_LEN: usize = 64;
+
+fn program_id() -> Pubkey {
+    Pubkey::from_str(PROGRAM_ID_STR).unwrap()
+}
+
+/// Sysvar syscalls answered in memory; logs are discarded
+struct FakeSysvars;
+
+impl SyscallStubs for FakeSysvars {
+    fn sol_log(&self, _message: &str) {}
+
+    fn sol_log_data(&self, _fields: &[&[u8]]) {}
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock {
+            unix_timestamp: NOW,
+            ..Clock::default()
+        };
+        unsafe { std::ptr::write(var_addr as *mut Clock, clock) };
+        SUCCESS
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { std::ptr::write(var_addr as *mut Rent, Rent::default()) };
+        SUCCESS
+    }
+    /// CPIs are not simulated; failing them keeps a test from passing through a transfer
+    fn sol_invoke_signed(
+        &self,
+        _instruction: &Instruction,
+        _account_infos: &[AccountInfo],
+        _signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        Err(ProgramError::Custom(CPI_UNAVAILABLE))
+    }
+}
+
+/// An account held in memory and lent to the processor as an `AccountInfo`
+#[derive(Debug, Clone)]
+struct FakeAccount {
+    key: Pubkey,
+    owner: Pubkey,
+    lamports: u64,
+    data: Vec<u8>,
+    is_signer: bool,
+    is_writable: bool,
+}
+
+impl FakeAccount {
+    /// An empty, system-owned account
+    fn new(key: Pubkey) -> Self {
+        Self {
+            key,
+            owner: system_program::id(),
+            lamports: 0,
+            data: Vec::new(),
+            is_signer: false,
+            is_writable: true,
+        }
+    }
+
+    /// A funded wallet
+    fn wallet(key: Pubkey) -> Self {
+        Self {
+            lamports: 1_000_000_000,
+            ..Self::new(key)
+        }
+    }
+
+    /// A program account holding `state` behind the discriminator of `name`
+    fn state<T: BorshSerialize>(key: Pubkey, name: &str, state: &T) -> Self {
+        let mut data = discriminator::account(name).to_vec();
+        data.extend(borsh::to_vec(state).unwrap());
+        Self {
+            owner: program_id(),
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            ..Self::new(key)
+        }
+    }
+
+    /// An SPL token account of `mint` owned by `authority`
+    fn token(mint: Pubkey, authority: Pubkey, amount: u64) -> Self {
+        let mut data = vec![0u8; TokenAccount::LEN];
+        TokenAccount::pack(
+            TokenAccount {
+                mint,
+                owner: authority,
+                amount,
+                delegate: COption::None,
+                state: AccountState::Initialized,
+                is_native: COption::None,
+                delegated_amount: 0,
+                close_authority: COption::None,
+            },
+            &mut data,
+        )
+        .unwrap();
+        Self {
+            owner: spl_token::id(),
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            ..Self::new(Pubkey::new_unique())
+        }
+    }
+
+    /// A read-only program account such as the token or system program
+    fn program(key: Pubkey) -> Self {
+        Self {
+            is_writable: false,
+            ..Self::new(key)
+        }
+    }
+
+    fn signer(mut self) -> Self {
+        self.is_signer = true;
+        self
+    }
+}
+
+/// Run `instruction` against `accounts`, returning the processor's result
+fn process(instruction: &MailerInstruction, accounts: &mut [FakeAccount]) -> ProgramResult {
+    static STUBS: Once = Once::new();
+    STUBS.call_once(|| {
+        set_syscall_stubs(Box::new(FakeSysvars));
+    });
+
+    let instruction_data = borsh::to_vec(instruction).unwrap();
+    let infos: Vec<AccountInfo> = accounts
+        .iter_mut()
+        .map(|account| {
+            AccountInfo::new(
+                &account.key,
+                account.is_signer,
+                account.is_writable,
+                &mut account.lamports,
+                &mut account.data,
+                &account.owner,
+                false,
+                0,
+            )
+        })
+        .collect();
+    mailer::process_instruction(&program_id(), &infos, &instruction_data)
+}
+
+fn error(error: MailerError) -> ProgramResult {
+    Err(error.into())
+}
+
+/// A freshly initialized mailer owned by `owner`
+fn mailer_state(owner: Pubkey, usdc_mint: Pubkey) -> MailerState {
+    MailerState {
+        owner,
+        usdc_mint,
+        send_fee: 100_000,
+        delegation_fee: 10_000_000,
+        owner_claimable: 0,
+        paused: false,
+        fee_paused: false,
+        bump: pda::mailer_state(&program_id()).1,
+        protocol_sends_enabled: false,
+        pending_owner: None,
+        fee_timelock: 0,
+        pending_fee: None,
+        paused_at: 0,
+        total_paused_duration: 0,
+        claim_rent_reserve: 0,
+        delegation_rent_reserve: 0,
+        discount_rent_reserve: 0,
+        disabled_instructions: 0,
+        spam_oracle: None,
+        spam_threshold: None,
+        rate_limit_max_sends: 0,
+        rate_limit_window: 0,
+        lamport_send_fee: 0,
+        owner_claimable_lamports: 0,
+        fee_oracle: None,
+        fee_oracle_target: 0,
+        fee_oracle_max_age: 0,
+        claim_creation_policy: ClaimCreationPolicy::Eager,
+        usdc_decimals: 6,
+        sanctions_enabled: false,
+        backup_mint: None,
+        stats_enabled: false,
+        pause_flags: 0,
+        kyc_verifier: None,
+        delegation_refund_window: 0,
+        claim_cap: 0,
+        payload_limits: PayloadLimits::default(),
+        payload_fee_tier_len: 0,
+        payload_fee_tier_percent: 0,
+        recipient_liabilities: 0,
+        min_coverage_bps: 0,
+        version: mailer::MAILER_STATE_VERSION,
+        treasury: None,
+        credit_liabilities: 0,
+    }
+}
+
+/// The mailer state account, padded to its full allocated size
+fn mailer_account(state: &MailerState) -> FakeAccount {
+    let mut account = FakeAccount::state(pda::mailer_state(&program_id()).0, "MailerState", state);
+    account.data.resize(8 + MailerState::LEN, 0);
+    account
+}
+
+fn read_mailer_state(account: &FakeAccount) -> MailerState {
+    MailerState::deserialize(&mut &account.data[8..]).unwrap()
+}
+
+// ============================================================================
+// Send
+// ============================================================================
+
+fn send(memo: Option<String>) -> MailerInstruction {
+    MailerInstruction::Send {
+        to: Pubkey::new_unique(),
+        subject: "Subject".to_string(),
+        _body: "Body".to_string(),
+        revenue_share_to_receiver: false,
+        resolve_sender_to_name: false,
+        request_read_receipt: false,
+        receipt_nonce: None,
+        memo,
+        strict: true,
+        thread_id: None,
+        in_reply_to: None,
+        allow_downgrade: false,
+        tip: 0,
+        bridge: false,
+        idempotency_nonce: None,
+    }
+}
+
+/// Accounts of a `Send` by a fresh sender holding `sender_mint` tokens
+fn send_accounts(state: &MailerState, sender_mint: Pubkey) -> Vec<FakeAccount> {
+    let sender = Pubkey::new_unique();
+    let mailer = mailer_account(state);
+    let mailer_usdc = FakeAccount::token(state.usdc_mint, mailer.key, 0);
+    vec![
+        FakeAccount::wallet(sender).signer(),
+        FakeAccount::new(pda::recipient_claim(&program_id(), &Pubkey::new_unique()).0),
+        mailer,
+        FakeAccount::token(sender_mint, sender, 1_000_000),
+        mailer_usdc,
+        FakeAccount::program(spl_token::id()),
+        FakeAccount::program(system_program::id()),
+    ]
+}
+
+#[test]
+fn test_send_requires_sender_signature() {
+    let state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    let mut accounts = send_accounts(&state, state.usdc_mint);
+    accounts[0].is_signer = false;
+
+    assert_eq!(
+        process(&send(None), &mut accounts),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+}
+
+#[test]
+fn test_send_rejects_wrong_mailer_pda() {
+    let state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    let mut accounts = send_accounts(&state, state.usdc_mint);
+    accounts[2].key = Pubkey::new_unique();
+
+    assert_eq!(
+        process(&send(None), &mut accounts),
+        error(MailerError::InvalidPDA)
+    );
+}
+
+#[test]
+fn test_send_rejects_long_memo() {
+    let state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    let mut accounts = send_accounts(&state, state.usdc_mint);
+    let memo = "m".repeat(MAX_MEMO_LEN + 1);
+
+    assert_eq!(
+        process(&send(Some(memo)), &mut accounts),
+        error(MailerError::MemoTooLong)
+    );
+}
+
+#[test]
+fn test_send_rejects_unlisted_fee_mint() {
+    let state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    let mut accounts = send_accounts(&state, Pubkey::new_unique());
+
+    assert_eq!(
+        process(&send(None), &mut accounts),
+        error(MailerError::InvalidMint)
+    );
+}
+
+#[test]
+fn test_send_rejects_while_paused() {
+    let mut state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    state.paused = true;
+    let mut accounts = send_accounts(&state, state.usdc_mint);
+
+    assert_eq!(
+        process(&send(None), &mut accounts),
+        error(MailerError::ContractPaused)
+    );
+}
+
+#[test]
+fn test_send_rejects_disabled_instruction() {
+    let mut state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    let tag = borsh::to_vec(&send(None)).unwrap()[0];
+    state.set_instruction_disabled(tag, true);
+    let mut accounts = send_accounts(&state, state.usdc_mint);
+
+    assert_eq!(
+        process(&send(None), &mut accounts),
+        error(MailerError::InstructionDisabled)
+    );
+}
+
+#[test]
+fn test_send_rejects_disabled_instruction_without_mailer() {
+    let mut state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    let tag = borsh::to_vec(&send(None)).unwrap()[0];
+    state.set_instruction_disabled(tag, true);
+    let mut accounts = send_accounts(&state, state.usdc_mint);
+    accounts.remove(2);
+
+    assert_eq!(
+        process(&send(None), &mut accounts),
+        error(MailerError::MissingMailerStateAccount)
+    );
+}
+
+#[test]
+fn test_send_rejects_disabled_instruction_with_legacy_mailer() {
+    let mut state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    let tag = borsh::to_vec(&send(None)).unwrap()[0];
+    state.set_instruction_disabled(tag, true);
+    let mut accounts = send_accounts(&state, state.usdc_mint);
+    accounts[2].data.truncate(8 + MailerState::LEN - 1);
+
+    assert_eq!(
+        process(&send(None), &mut accounts),
+        error(MailerError::StateMigrationRequired)
+    );
+}
+
+#[test]
+fn test_set_instruction_disabled_rejects_instructions_without_mailer() {
+    let state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    let mut accounts = owner_accounts(&state, state.owner);
+    let prefs = MailerInstruction::SetRecipientPrefs {
+        read_receipts_opt_out: true,
+    };
+    let tag = borsh::to_vec(&prefs).unwrap()[0];
+
+    assert_eq!(
+        process(
+            &MailerInstruction::SetInstructionDisabled {
+                tag,
+                disabled: true
+            },
+            &mut accounts
+        ),
+        error(MailerError::InvalidInstructionTag)
+    );
+}
+
+// ============================================================================
+// ClaimRecipientShare
+// ============================================================================
+
+/// Accounts of a `ClaimRecipientShare` of `claim` by `recipient`
+fn claim_accounts(
+    state: &MailerState,
+    recipient: Pubkey,
+    claim: &RecipientClaim,
+) -> Vec<FakeAccount> {
+    let mailer = mailer_account(state);
+    let mailer_usdc = FakeAccount::token(state.usdc_mint, mailer.key, 1_000_000);
+    vec![
+        FakeAccount::wallet(recipient).signer(),
+        FakeAccount::state(
+            pda::recipient_claim(&program_id(), &recipient).0,
+            "RecipientClaim",
+            claim,
+        ),
+        mailer,
+        FakeAccount::token(state.usdc_mint, recipient, 0),
+        mailer_usdc,
+        FakeAccount::program(spl_token::id()),
+    ]
+}
+
+fn recipient_claim(recipient: Pubkey, amount: u64, timestamp: i64) -> RecipientClaim {
+    RecipientClaim {
+        recipient,
+        amount,
+        timestamp,
+        bump: pda::recipient_claim(&program_id(), &recipient).1,
+        paused_offset: 0,
+        no_expiry: false,
+        surcharge_paid_until: 0,
+        extensions: 0,
+        messages_received: 0,
+        total_claimed: 0,
+    }
+}
+
+#[test]
+fn test_claim_recipient_share_requires_recipient_signature() {
+    let state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    let recipient = Pubkey::new_unique();
+    let mut accounts = claim_accounts(&state, recipient, &recipient_claim(recipient, 90_000, NOW));
+    accounts[0].is_signer = false;
+
+    let result = process(
+        &MailerInstruction::ClaimRecipientShare {
+            amount: None,
+            split: None,
+            close_to: None,
+        },
+        &mut accounts,
+    );
+    assert_eq!(result, Err(ProgramError::MissingRequiredSignature));
+}
+
+#[test]
+fn test_claim_recipient_share_rejects_another_recipients_claim_pda() {
+    let state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    let recipient = Pubkey::new_unique();
+    let mut accounts = claim_accounts(&state, recipient, &recipient_claim(recipient, 90_000, NOW));
+    accounts[1].key = pda::recipient_claim(&program_id(), &Pubkey::new_unique()).0;
+
+    let result = process(
+        &MailerInstruction::ClaimRecipientShare {
+            amount: None,
+            split: None,
+            close_to: None,
+        },
+        &mut accounts,
+    );
+    assert_eq!(result, error(MailerError::InvalidPDA));
+}
+
+#[test]
+fn test_claim_recipient_share_rejects_mismatched_recipient() {
+    let state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    let recipient = Pubkey::new_unique();
+    let mut accounts = claim_accounts(
+        &state,
+        recipient,
+        &recipient_claim(Pubkey::new_unique(), 90_000, NOW),
+    );
+
+    let result = process(
+        &MailerInstruction::ClaimRecipientShare {
+            amount: None,
+            split: None,
+            close_to: None,
+        },
+        &mut accounts,
+    );
+    assert_eq!(result, error(MailerError::InvalidRecipient));
+}
+
+#[test]
+fn test_claim_recipient_share_rejects_empty_claim() {
+    let state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    let recipient = Pubkey::new_unique();
+    let mut accounts = claim_accounts(&state, recipient, &recipient_claim(recipient, 0, 0));
+
+    let result = process(
+        &MailerInstruction::ClaimRecipientShare {
+            amount: None,
+            split: None,
+            close_to: None,
+        },
+        &mut accounts,
+    );
+    assert_eq!(result, error(MailerError::NoClaimableAmount));
+}
+
+#[test]
+fn test_claim_recipient_share_rejects_expired_claim() {
+    let state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    let recipient = Pubkey::new_unique();
+    let claim = recipient_claim(recipient, 90_000, NOW - CLAIM_PERIOD - 1);
+    let mut accounts = claim_accounts(&state, recipient, &claim);
+
+    let result = process(
+        &MailerInstruction::ClaimRecipientShare {
+            amount: None,
+            split: None,
+            close_to: None,
+        },
+        &mut accounts,
+    );
+    assert_eq!(result, error(MailerError::ClaimPeriodExpired));
+}
+
+#[test]
+fn test_claim_recipient_share_rejects_partial_amount_above_balance() {
+    let state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    let recipient = Pubkey::new_unique();
+    let mut accounts = claim_accounts(&state, recipient, &recipient_claim(recipient, 90_000, NOW));
+
+    for amount in [0, 90_001] {
+        let result = process(
+            &MailerInstruction::ClaimRecipientShare {
+                amount: Some(amount),
+                split: None,
+                close_to: None,
+            },
+            &mut accounts,
+        );
+        assert_eq!(result, error(MailerError::InvalidClaimAmount));
+    }
+}
+
+// ============================================================================
+// ClaimRecipientShareAs
+// ============================================================================
+
+#[test]
+fn test_claim_recipient_share_as_rejects_owner_claimable_overflow() {
+    let mut state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    state.owner_claimable = u64::MAX;
+    let mailer = mailer_account(&state);
+    let recipient = Pubkey::new_unique();
+    let alt_mint = Pubkey::new_unique();
+    let vault_token = FakeAccount::token(alt_mint, mailer.key, 1_000_000);
+    let (conversion_pda, conversion_bump) = pda::conversion_vault(&program_id(), &alt_mint);
+    let vault = ConversionVault {
+        mint: alt_mint,
+        vault: vault_token.key,
+        enabled: true,
+        total_converted: 0,
+        bump: conversion_bump,
+    };
+
+    let mut accounts = vec![
+        FakeAccount::wallet(recipient).signer(),
+        FakeAccount::state(
+            pda::recipient_claim(&program_id(), &recipient).0,
+            "RecipientClaim",
+            &recipient_claim(recipient, 90_000, NOW),
+        ),
+        mailer.clone(),
+        FakeAccount::token(alt_mint, recipient, 0),
+        FakeAccount::state(conversion_pda, "ConversionVault", &vault),
+        vault_token,
+        FakeAccount::token(state.usdc_mint, recipient, 0),
+        FakeAccount::token(state.usdc_mint, mailer.key, 1_000_000),
+        FakeAccount::program(spl_token::id()),
+    ];
+
+    let result = process(
+        &MailerInstruction::ClaimRecipientShareAs { mint: alt_mint },
+        &mut accounts,
+    );
+    assert_eq!(result, error(MailerError::MathOverflow));
+}
+
+// ============================================================================
+// ClaimOwnerShare
+// ============================================================================
+
+fn owner_claim_accounts(
+    state: &MailerState,
+    signer: Pubkey,
+    owner_mint: Pubkey,
+) -> Vec<FakeAccount> {
+    let mailer = mailer_account(state);
+    vec![
+        FakeAccount::wallet(signer).signer(),
+        mailer.clone(),
+        FakeAccount::token(owner_mint, state.owner, 0),
+        FakeAccount::token(state.usdc_mint, mailer.key, 1_000_000),
+        FakeAccount::program(spl_token::id()),
+    ]
+}
+
+#[test]
+fn test_claim_owner_share_rejects_non_owner() {
+    let mut state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    state.owner_claimable = 10_000;
+    let mut accounts = owner_claim_accounts(&state, Pubkey::new_unique(), state.usdc_mint);
+
+    assert_eq!(
+        process(&MailerInstruction::ClaimOwnerShare, &mut accounts),
+        error(MailerError::OnlyOwner)
+    );
+}
+
+#[test]
+fn test_claim_owner_share_rejects_nothing_to_claim() {
+    let state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    let mut accounts = owner_claim_accounts(&state, state.owner, state.usdc_mint);
+
+    let result = process(&MailerInstruction::ClaimOwnerShare, &mut accounts);
+    assert_eq!(result, error(MailerError::NoClaimableAmount));
+}
+
+#[test]
+fn test_claim_owner_share_rejects_destination_in_wrong_mint() {
+    let mut state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    state.owner_claimable = 10_000;
+    let mut accounts = owner_claim_accounts(&state, state.owner, Pubkey::new_unique());
+
+    assert_eq!(
+        process(&MailerInstruction::ClaimOwnerShare, &mut accounts),
+        error(MailerError::InvalidMint)
+    );
+}
+
+// ============================================================================
+// SetFee, ProposeFee and the fee timelock
+// ============================================================================
+
+fn set_fee(expected_current_value: Option<u64>) -> MailerInstruction {
+    MailerInstruction::SetFee {
+        new_fee: 200_000,
+        expected_current_value,
+    }
+}
+
+fn owner_accounts(state: &MailerState, signer: Pubkey) -> Vec<FakeAccount> {
+    vec![FakeAccount::wallet(signer).signer(), mailer_account(state)]
+}
+
+#[test]
+fn test_set_fee_updates_state() {
+    let state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    let mut accounts = owner_accounts(&state, state.owner);
+
+    assert_eq!(process(&set_fee(Some(100_000)), &mut accounts), Ok(()));
+    assert_eq!(read_mailer_state(&accounts[1]).send_fee, 200_000);
+}
+
+#[test]
+fn test_set_fee_requires_owner_signature() {
+    let state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    let mut accounts = owner_accounts(&state, state.owner);
+    accounts[0].is_signer = false;
+
+    assert_eq!(
+        process(&set_fee(None), &mut accounts),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+}
+
+#[test]
+fn test_set_fee_rejects_non_owner() {
+    let state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    let mut accounts = owner_accounts(&state, Pubkey::new_unique());
+
+    assert_eq!(
+        process(&set_fee(None), &mut accounts),
+        error(MailerError::OnlyOwner)
+    );
+}
+
+#[test]
+fn test_set_fee_rejects_while_paused() {
+    let mut state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    state.paused = true;
+    let mut accounts = owner_accounts(&state, state.owner);
+
+    assert_eq!(
+        process(&set_fee(None), &mut accounts),
+        error(MailerError::ContractPaused)
+    );
+}
+
+#[test]
+fn test_set_fee_rejects_bypassing_timelock() {
+    let mut state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    state.fee_timelock = 86_400;
+    let mut accounts = owner_accounts(&state, state.owner);
+
+    assert_eq!(
+        process(&set_fee(None), &mut accounts),
+        error(MailerError::FeeTimelockActive)
+    );
+}
+
+#[test]
+fn test_set_fee_rejects_stale_current_value() {
+    let state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    let mut accounts = owner_accounts(&state, state.owner);
+
+    assert_eq!(
+        process(&set_fee(Some(1)), &mut accounts),
+        error(MailerError::StaleState)
+    );
+    assert_eq!(read_mailer_state(&accounts[1]).send_fee, 100_000);
+}
+
+#[test]
+fn test_propose_fee_rejects_effective_time_overflow() {
+    let mut state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    state.fee_timelock = i64::MAX;
+    let mut accounts = owner_accounts(&state, state.owner);
+
+    let result = process(
+        &MailerInstruction::ProposeFee { new_fee: 200_000 },
+        &mut accounts,
+    );
+    assert_eq!(result, error(MailerError::MathOverflow));
+}
+
+/// A mailer with a one-day fee timelock, and its owner's accounts followed by `extra`
+fn timelocked_owner_accounts(extra: Vec<FakeAccount>) -> (MailerState, Vec<FakeAccount>) {
+    let mut state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    state.fee_timelock = 86_400;
+    let mut accounts = owner_accounts(&state, state.owner);
+    accounts.extend(extra);
+    (state, accounts)
+}
+
+#[test]
+fn test_set_delegation_fee_rejects_bypassing_timelock() {
+    let (_, mut accounts) = timelocked_owner_accounts(vec![]);
+    let instruction = MailerInstruction::SetDelegationFee {
+        new_fee: 20_000_000,
+        expected_current_value: None,
+    };
+
+    assert_eq!(
+        process(&instruction, &mut accounts),
+        error(MailerError::FeeTimelockActive)
+    );
+}
+
+#[test]
+fn test_set_custom_fee_percentage_rejects_bypassing_timelock() {
+    let account = Pubkey::new_unique();
+    let (state, mut accounts) = timelocked_owner_accounts(vec![
+        FakeAccount::new(pda::fee_discount(&program_id(), &account).0),
+        FakeAccount::new(account),
+    ]);
+    accounts.push(FakeAccount::wallet(state.owner).signer());
+    accounts.push(FakeAccount::program(system_program::id()));
+    let instruction = MailerInstruction::SetCustomFeePercentage {
+        account,
+        percentage: 100,
+    };
+
+    assert_eq!(
+        process(&instruction, &mut accounts),
+        error(MailerError::FeeTimelockActive)
+    );
+}
+
+#[test]
+fn test_set_discount_schedule_rejects_bypassing_timelock() {
+    let (_, mut accounts) = timelocked_owner_accounts(vec![
+        FakeAccount::new(pda::discount_schedule(&program_id()).0),
+        FakeAccount::program(system_program::id()),
+    ]);
+    let instruction = MailerInstruction::SetDiscountSchedule {
+        tiers: vec![],
+        reserved: Default::default(),
+    };
+
+    assert_eq!(
+        process(&instruction, &mut accounts),
+        error(MailerError::FeeTimelockActive)
+    );
+}
+
+#[test]
+fn test_set_payload_limits_rejects_fee_tiers_bypassing_timelock() {
+    let (_, mut accounts) = timelocked_owner_accounts(vec![]);
+    let instruction = MailerInstruction::SetPayloadLimits {
+        limits: PayloadLimits::default(),
+        fee_tier_len: 256,
+        fee_tier_percent: 10,
+    };
+
+    assert_eq!(
+        process(&instruction, &mut accounts),
+        error(MailerError::FeeTimelockActive)
+    );
+}
+
+#[test]
+fn test_set_payload_limits_keeps_fee_tiers_under_timelock() {
+    let (_, mut accounts) = timelocked_owner_accounts(vec![]);
+    let limits = PayloadLimits {
+        max_subject_len: 100,
+        ..PayloadLimits::default()
+    };
+    let instruction = MailerInstruction::SetPayloadLimits {
+        limits,
+        fee_tier_len: 0,
+        fee_tier_percent: 0,
+    };
+
+    assert_eq!(process(&instruction, &mut accounts), Ok(()));
+    assert_eq!(read_mailer_state(&accounts[1]).payload_limits, limits);
+}
+
+#[test]
+fn test_set_lamport_fee_rejects_bypassing_timelock() {
+    let (_, mut accounts) = timelocked_owner_accounts(vec![
+        FakeAccount::new(pda::lamport_vault(&program_id()).0),
+        FakeAccount::program(system_program::id()),
+    ]);
+    let instruction = MailerInstruction::SetLamportFee { lamport_fee: 5_000 };
+
+    assert_eq!(
+        process(&instruction, &mut accounts),
+        error(MailerError::FeeTimelockActive)
+    );
+}
+
+#[test]
+fn test_set_fee_oracle_rejects_bypassing_timelock() {
+    let (_, mut accounts) = timelocked_owner_accounts(vec![]);
+    let instruction = MailerInstruction::SetFeeOracle {
+        oracle: Some(Pubkey::new_unique()),
+        target_fee: 10_000,
+        max_age: 60,
+    };
+
+    assert_eq!(
+        process(&instruction, &mut accounts),
+        error(MailerError::FeeTimelockActive)
+    );
+}
+
+#[test]
+fn test_add_fee_mint_rejects_fee_update_bypassing_timelock() {
+    let mint = Pubkey::new_unique();
+    let (fee_mint_pda, bump) = pda::fee_mint(&program_id(), &mint);
+    let fee_mint = FeeMint {
+        mint,
+        send_fee: 100_000,
+        delegation_fee: 10_000_000,
+        owner_claimable: 0,
+        bump,
+    };
+    let (_, mut accounts) = timelocked_owner_accounts(vec![
+        FakeAccount::state(fee_mint_pda, "FeeMint", &fee_mint),
+        FakeAccount::program(system_program::id()),
+    ]);
+    let instruction = MailerInstruction::AddFeeMint {
+        mint,
+        send_fee: 200_000,
+        delegation_fee: 10_000_000,
+    };
+
+    assert_eq!(
+        process(&instruction, &mut accounts),
+        error(MailerError::FeeTimelockActive)
+    );
+}
+
+// ============================================================================
+// Pause
+// ============================================================================
+
+#[test]
+fn test_pause_rejects_already_paused() {
+    let mut state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    state.paused = true;
+    let mailer = mailer_account(&state);
+    let mut accounts = vec![
+        FakeAccount::wallet(state.owner).signer(),
+        mailer.clone(),
+        FakeAccount::token(state.usdc_mint, state.owner, 0),
+        FakeAccount::token(state.usdc_mint, mailer.key, 0),
+        FakeAccount::program(spl_token::id()),
+    ];
+
+    assert_eq!(
+        process(&MailerInstruction::Pause, &mut accounts),
+        error(MailerError::ContractPaused)
+    );
+}
+
+#[test]
+fn test_pause_records_pause_time_without_owner_funds() {
+    let state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    let mailer = mailer_account(&state);
+    let mut accounts = vec![
+        FakeAccount::wallet(state.owner).signer(),
+        mailer.clone(),
+        FakeAccount::token(state.usdc_mint, state.owner, 0),
+        FakeAccount::token(state.usdc_mint, mailer.key, 0),
+        FakeAccount::program(spl_token::id()),
+    ];
+
+    assert_eq!(process(&MailerInstruction::Pause, &mut accounts), Ok(()));
+    let paused = read_mailer_state(&accounts[1]);
+    assert!(paused.paused);
+    assert_eq!(paused.paused_at, NOW);
+}
+
+// ============================================================================
+// In-place fields
+// ============================================================================
+
+#[test]
+fn test_recipient_liabilities_offset_follows_optional_fields() {
+    let mut state = mailer_state(Pubkey::new_unique(), Pubkey::new_unique());
+    state.recipient_liabilities = 7_000_000;
+    let unset = mailer_account(&state).data;
+    let offset = mailer::fields::recipient_liabilities_offset(&unset).unwrap();
+    assert_eq!(mailer::fields::read_u64(&unset, offset).unwrap(), 7_000_000);
+
+    state.pending_owner = Some(Pubkey::new_unique());
+    state.pending_fee = Some(mailer::PendingFeeChange {
+        new_fee: 1,
+        effective_at: NOW,
+    });
+    state.spam_oracle = Some(Pubkey::new_unique());
+    state.spam_threshold = Some(50);
+    state.fee_oracle = Some(Pubkey::new_unique());
+    state.backup_mint = Some(Pubkey::new_unique());
+    state.kyc_verifier = Some(Pubkey::new_unique());
+    state.treasury = Some(Pubkey::new_unique());
+    let set = mailer_account(&state).data;
+    let offset = mailer::fields::recipient_liabilities_offset(&set).unwrap();
+    assert_eq!(offset, 8 + MailerState::LEN - 2 - 1 - 33 - 8 - 8);
+    assert_eq!(mailer::fields::read_u64(&set, offset).unwrap(), 7_000_000);
+}
@@ -290,14 +290,16 @@ function encodeSendPreparedToEmail(toEmail: string, mailId: string): Buffer {
 }
 
 function encodeSetFee(sendFee: bigint): Buffer {
-  const data = Buffer.alloc(1 + 8);
+  // Trailing zero byte: no expected current value
+  const data = Buffer.alloc(1 + 8 + 1);
   data.writeUInt8(InstructionType.SetFee, 0);
   data.writeBigUInt64LE(sendFee, 1);
   return data;
 }
 
 function encodeSetDelegationFee(delegationFee: bigint): Buffer {
-  const data = Buffer.alloc(1 + 8);
+  // Trailing zero byte: no expected current value
+  const data = Buffer.alloc(1 + 8 + 1);
   data.writeUInt8(InstructionType.SetDelegationFee, 0);
   data.writeBigUInt64LE(delegationFee, 1);
   return data;